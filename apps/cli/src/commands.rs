@@ -1,17 +1,25 @@
 //! CLI command definitions, routing, and tracing setup.
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
 use color_eyre::eyre::{Result, eyre};
-use contextbuilder_core::pipeline::{
-    AddKbConfig, AddKbResult, ProgressReporter,
+use contextbuilder_core::pipeline::{AddKbConfig, CrawlResult};
+use contextbuilder_core::progress::{JsonProgress, ProgressEvent, ProgressReporter};
+use contextbuilder_core::scope::ScopedUrl;
+use contextbuilder_core::update::UpdateKbResult;
+use contextbuilder_markdown::ConvertOptions;
+use contextbuilder_shared::{
+    AppConfig, ContextBuilderError, CrawlConfig, CrawlMode, KbManifest, PolitenessProfile,
+    config_file_path, init_config, load_config, validate_api_key,
 };
-use contextbuilder_shared::{AppConfig, CrawlConfig, init_config, load_config, validate_api_key};
 use indicatif::{ProgressBar, ProgressStyle};
 use tracing::info;
 use url::Url;
 
+use crate::doctor;
+
 // ---------------------------------------------------------------------------
 // CLI structure
 // ---------------------------------------------------------------------------
@@ -33,10 +41,117 @@ pub(crate) struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
 
+    /// Forbid all network access; commands that need to fetch fail fast.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Limits on a crawl run, shared by `add` and `update`; each overrides the
+/// config file.
+#[derive(Debug, Default, clap::Args)]
+pub(crate) struct LimitOverrides {
+    /// Stop the crawl after this many pages.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..=1_000_000))]
+    pub max_pages: Option<u64>,
+
+    /// Maximum crawl depth from the start URL.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=50))]
+    pub depth: Option<u32>,
+
+    /// Seconds allowed for a whole request, body included.
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..=3600))]
+    pub timeout: Option<u64>,
+}
+
+impl LimitOverrides {
+    /// Apply the flags that were given to `crawl`.
+    fn apply(&self, crawl: &mut CrawlConfig) {
+        if let Some(max_pages) = self.max_pages {
+            crawl.max_pages = max_pages as usize;
+        }
+        if let Some(depth) = self.depth {
+            crawl.depth = depth;
+        }
+        if let Some(timeout) = self.timeout {
+            crawl.timeouts.timeout_secs = timeout;
+        }
+    }
+}
+
+/// Crawl settings given on the command line; each overrides the config file.
+#[derive(Debug, Default, clap::Args)]
+pub(crate) struct CrawlOverrides {
+    #[command(flatten)]
+    pub limits: LimitOverrides,
+
+    /// Politeness preset: gentle, balanced, or aggressive (concurrency and
+    /// rate limit; --concurrency and --rate-limit-ms still override it).
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<PolitenessProfile>,
+
+    /// Maximum concurrent requests.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub concurrency: Option<u32>,
+
+    /// Minimum delay in ms between requests to the same host.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(0..=60_000))]
+    pub rate_limit_ms: Option<u64>,
+
+    /// Only crawl URLs matching this glob (repeatable; replaces the configured patterns).
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip URLs matching this glob (repeatable; replaces the configured patterns).
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Skip pages whose markup matches this CSS selector, without following their links
+    /// (repeatable; replaces the configured selectors).
+    #[arg(long = "exclude-selector", value_name = "CSS")]
+    pub exclude_selector: Vec<String>,
+    /// Deepest TOC level to keep; deeper entries are flattened into it.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub flatten_depth: Option<u32>,
+
+    /// Drop TOC sections left without any page beneath them.
+    #[arg(long)]
+    pub prune_empty_sections: bool,
+}
+
+impl CrawlOverrides {
+    /// Apply the flags that were given to `crawl`.
+    fn apply(&self, crawl: &mut CrawlConfig) {
+        if let Some(profile) = self.profile {
+            profile.apply(crawl);
+        }
+        self.limits.apply(crawl);
+        if let Some(concurrency) = self.concurrency {
+            crawl.concurrency = concurrency;
+        }
+        if let Some(rate_limit_ms) = self.rate_limit_ms {
+            crawl.rate_limit_ms = rate_limit_ms;
+        }
+        if !self.include.is_empty() {
+            crawl.include_patterns = self.include.clone();
+        }
+        if !self.exclude.is_empty() {
+            crawl.exclude_patterns = self.exclude.clone();
+        }
+        if !self.exclude_selector.is_empty() {
+            crawl.skip_page_selectors = self.exclude_selector.clone();
+        }
+        if let Some(depth) = self.flatten_depth {
+            crawl.max_toc_depth = Some(depth as usize);
+        }
+        if self.prune_empty_sections {
+            crawl.prune_empty_sections = true;
+        }
+    }
+}
+
 /// Log output format.
 #[derive(Clone, Debug, clap::ValueEnum)]
 pub(crate) enum LogFormat {
@@ -62,7 +177,35 @@ pub(crate) enum Command {
 
         /// Discovery mode: auto, llms-txt, or crawl.
         #[arg(short, long, default_value = "auto")]
-        mode: String,
+        mode: CrawlMode,
+
+        #[command(flatten)]
+        crawl: CrawlOverrides,
+
+        /// Split pages into H2 sections for section-level summaries.
+        #[arg(long)]
+        section_chunks: bool,
+
+        /// Fetch only the page at the URL (crawl depth 0, no llms.txt discovery).
+        #[arg(long, conflicts_with = "depth")]
+        single_page: bool,
+
+        /// Reproducible output: fixed timestamps (SOURCE_DATE_EPOCH), ids, and page order.
+        #[arg(long)]
+        deterministic: bool,
+
+        /// Seed the enrichment cache from an existing KB (e.g. an older docs version).
+        #[arg(long, value_name = "KB_PATH")]
+        warm_cache_from: Option<PathBuf>,
+
+        /// If the output directory already has a KB for this URL, add only the pages
+        /// it lacks to that KB instead of refusing.
+        #[arg(long, conflicts_with = "warm_cache_from")]
+        merge: bool,
+
+        /// Write per-host crawl metrics (requests, bytes, errors, latency) as JSON.
+        #[arg(long, value_name = "FILE")]
+        metrics: Option<PathBuf>,
     },
 
     /// Build or rebuild artifacts for an existing KB.
@@ -89,6 +232,84 @@ pub(crate) enum Command {
         /// Force re-crawl even if content hashes match.
         #[arg(long)]
         force: bool,
+
+        /// Refresh H2 section chunks for changed pages.
+        #[arg(long)]
+        section_chunks: bool,
+
+        /// Reproducible output: fixed timestamps (SOURCE_DATE_EPOCH) and page order.
+        #[arg(long)]
+        deterministic: bool,
+
+        #[command(flatten)]
+        limits: LimitOverrides,
+
+        /// Exit with status 2 if any page was added, changed, or removed (for CI).
+        #[arg(long)]
+        fail_on_change: bool,
+
+        /// Only check that the manifest's source URL is reachable; crawl and
+        /// write nothing.
+        #[arg(long, conflicts_with_all = ["prune", "force", "fail_on_change", "metrics"])]
+        validate_only: bool,
+
+        /// Write per-host crawl metrics (requests, bytes, errors, latency) as JSON.
+        #[arg(long, value_name = "FILE")]
+        metrics: Option<PathBuf>,
+    },
+
+    /// Fetch specific pages and add them to an existing KB.
+    #[command(name = "add-pages")]
+    AddPages {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+
+        /// Page URLs to add (an existing page at the same path is replaced).
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Store H2 section chunks for the added pages.
+        #[arg(long)]
+        section_chunks: bool,
+
+        /// Reproducible output: fixed timestamps (SOURCE_DATE_EPOCH) and page order.
+        #[arg(long)]
+        deterministic: bool,
+    },
+
+    /// Re-run enrichment for one page, dropping its cached results.
+    Reenrich {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+
+        /// Page path within the KB (as in `toc.json`, e.g. `guide/setup`).
+        #[arg(long)]
+        page: String,
+
+        /// Reproducible output: fixed timestamps (SOURCE_DATE_EPOCH).
+        #[arg(long)]
+        deterministic: bool,
+    },
+
+    /// Combine several KBs into one, each under its own top-level section.
+    Merge {
+        /// Source KB path (repeat for each KB to merge).
+        #[arg(long = "kb", required = true, num_args = 1)]
+        kbs: Vec<String>,
+
+        /// Output directory for the merged KB (defaults to var/kb/<id>).
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Name for the merged KB (defaults to the source names joined by " + ").
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Reproducible output: fixed timestamps (SOURCE_DATE_EPOCH) and ids.
+        #[arg(long)]
+        deterministic: bool,
     },
 
     /// List all registered knowledge bases.
@@ -111,6 +332,79 @@ pub(crate) enum Command {
         #[command(subcommand)]
         action: ConfigAction,
     },
+
+    /// Enrichment cache maintenance.
+    Cache {
+        /// Cache subcommand.
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Convert a local HTML file to Markdown, e.g. to tune cleanup passes.
+    Convert {
+        /// HTML file to convert.
+        file: String,
+
+        /// URL the page came from, used to resolve relative links.
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Print each cleanup pass's changes to stderr.
+        #[arg(long)]
+        trace: bool,
+    },
+
+    /// Re-apply the current cleanup passes to a KB's stored pages.
+    Reclean {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+    },
+
+    /// Upgrade a KB's manifest and TOC to the current on-disk format.
+    Migrate {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+    },
+
+    /// Show a KB's crawl history, newest first.
+    History {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+    },
+
+    /// List the URLs a crawl would consider, with the scope rule deciding each,
+    /// without crawling.
+    Scope {
+        /// Start URL of the crawl.
+        #[arg(long)]
+        url: String,
+
+        /// Maximum link depth to discover (defaults to the configured depth).
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=50))]
+        depth: Option<u32>,
+
+        /// Only crawl URLs matching this glob (repeatable; replaces the configured patterns).
+        #[arg(long = "include", value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Skip URLs matching this glob (repeatable; replaces the configured patterns).
+        #[arg(long = "exclude", value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Classify the URLs of this sitemap file instead of discovering links.
+        #[arg(long, value_name = "PATH")]
+        sitemap: Option<PathBuf>,
+    },
+
+    /// Check the environment: config, API key, Bun, scripts, output directory.
+    Doctor {
+        /// Output directory to check (defaults to var/kb, like `add`).
+        #[arg(short, long)]
+        out: Option<String>,
+    },
 }
 
 /// MCP server subcommands.
@@ -133,6 +427,10 @@ pub(crate) enum McpAction {
         /// Port for HTTP transport.
         #[arg(long, default_value = "3100")]
         port: u16,
+
+        /// Seconds to wait for the server to start (defaults to config).
+        #[arg(long)]
+        startup_timeout: Option<u64>,
     },
     /// Print MCP client configuration snippets.
     Config {
@@ -159,6 +457,42 @@ pub(crate) enum ConfigAction {
     Show,
 }
 
+/// Cache subcommands.
+#[derive(Subcommand)]
+pub(crate) enum CacheAction {
+    /// Remove enrichment cache entries by model and/or age.
+    Prune {
+        /// KB path.
+        #[arg(long)]
+        kb: String,
+
+        /// Only remove entries produced by this model.
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Only remove entries older than this age (e.g. 30d, 12h).
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
+    },
+}
+
+/// Parse an age like `30d` or `12h` into a duration.
+fn parse_age(s: &str) -> std::result::Result<Duration, ContextBuilderError> {
+    let invalid = || {
+        ContextBuilderError::validation(format!("invalid age '{s}': expected e.g. 30d or 12h"))
+    };
+    let (num, unit_secs) = match s.char_indices().last() {
+        Some((i, 'd')) => (&s[..i], 24 * 60 * 60),
+        Some((i, 'h')) => (&s[..i], 60 * 60),
+        _ => return Err(invalid()),
+    };
+    let n: u64 = num.parse().map_err(|_| invalid())?;
+    let secs = n
+        .checked_mul(unit_secs)
+        .ok_or_else(|| ContextBuilderError::validation(format!("age '{s}' is too large")))?;
+    Ok(Duration::from_secs(secs))
+}
+
 // ---------------------------------------------------------------------------
 // Tracing setup
 // ---------------------------------------------------------------------------
@@ -198,15 +532,80 @@ pub(crate) fn init_tracing(cli: &Cli) {
 
 /// Run the CLI command.
 pub(crate) async fn run(cli: Cli) -> Result<()> {
+    let offline = cli.offline;
+    let json_progress = matches!(cli.log_format, LogFormat::Json);
     match cli.command {
         Command::Add {
             url,
             name,
             out,
             mode,
-        } => cmd_add(&url, name.as_deref(), out.as_deref(), &mode).await,
+            crawl,
+            section_chunks,
+            single_page,
+            deterministic,
+            warm_cache_from,
+            merge,
+            metrics,
+        } => {
+            let opts = AddOptions {
+                section_chunks,
+                single_page,
+                deterministic,
+                offline,
+                json_progress,
+                crawl,
+                warm_cache_from,
+                merge,
+                metrics,
+            };
+            cmd_add(&url, name.as_deref(), out.as_deref(), mode, opts).await
+        }
         Command::Build { kb, emit } => cmd_build(&kb, emit.as_deref()).await,
-        Command::Update { kb, prune, force } => cmd_update(&kb, prune, force).await,
+        Command::Update {
+            kb,
+            prune,
+            force,
+            section_chunks,
+            deterministic,
+            limits,
+            fail_on_change,
+            validate_only,
+            metrics,
+        } => {
+            let opts = UpdateOptions {
+                prune,
+                force,
+                section_chunks,
+                deterministic,
+                offline,
+                json_progress,
+                limits,
+                fail_on_change,
+                validate_only,
+                metrics,
+            };
+            cmd_update(&kb, opts).await
+        }
+        Command::AddPages {
+            kb,
+            urls,
+            section_chunks,
+            deterministic,
+        } => {
+            cmd_add_pages(&kb, &urls, section_chunks, deterministic, offline, json_progress).await
+        },
+        Command::Reenrich {
+            kb,
+            page,
+            deterministic,
+        } => cmd_reenrich(&kb, &page, deterministic, json_progress).await,
+        Command::Merge {
+            kbs,
+            out,
+            name,
+            deterministic,
+        } => cmd_merge(&kbs, out.as_deref(), name, deterministic, json_progress).await,
         Command::List => cmd_list().await,
         Command::Tui => cmd_tui().await,
         Command::Mcp { action } => match action {
@@ -215,7 +614,8 @@ pub(crate) async fn run(cli: Cli) -> Result<()> {
                 kb_root,
                 transport,
                 port,
-            } => cmd_mcp_serve(&kb, kb_root.as_deref(), &transport, port).await,
+                startup_timeout,
+            } => cmd_mcp_serve(&kb, kb_root.as_deref(), &transport, port, startup_timeout).await,
             McpAction::Config {
                 target,
                 kb,
@@ -226,6 +626,36 @@ pub(crate) async fn run(cli: Cli) -> Result<()> {
             ConfigAction::Init => cmd_config_init().await,
             ConfigAction::Show => cmd_config_show().await,
         },
+        Command::Cache { action } => match action {
+            CacheAction::Prune {
+                kb,
+                model,
+                older_than,
+            } => cmd_cache_prune(&kb, model.as_deref(), older_than).await,
+        },
+        Command::Convert { file, url, trace } => cmd_convert(&file, url.as_deref(), trace),
+        Command::Reclean { kb } => cmd_reclean(&kb).await,
+        Command::Migrate { kb } => cmd_migrate(&kb),
+        Command::History { kb } => cmd_history(&kb).await,
+        Command::Scope {
+            url,
+            depth,
+            include,
+            exclude,
+            sitemap,
+        } => {
+            let crawl = CrawlOverrides {
+                limits: LimitOverrides {
+                    depth,
+                    ..Default::default()
+                },
+                include,
+                exclude,
+                ..Default::default()
+            };
+            cmd_scope(&url, &crawl, sitemap.as_deref(), offline).await
+        }
+        Command::Doctor { out } => cmd_doctor(out.as_deref()).await,
     }
 }
 
@@ -233,11 +663,29 @@ pub(crate) async fn run(cli: Cli) -> Result<()> {
 // Placeholder command handlers
 // ---------------------------------------------------------------------------
 
-async fn cmd_add(url: &str, name: Option<&str>, out: Option<&str>, mode: &str) -> Result<()> {
-    // Validate API key before doing anything
-    let config = load_config()?;
-    validate_api_key(&config)?;
+/// Switches of the `add` command.
+struct AddOptions {
+    section_chunks: bool,
+    single_page: bool,
+    deterministic: bool,
+    offline: bool,
+    json_progress: bool,
+    crawl: CrawlOverrides,
+    warm_cache_from: Option<PathBuf>,
+    merge: bool,
+    metrics: Option<PathBuf>,
+}
 
+/// Resolve `add` arguments against the loaded config.
+fn add_kb_config(
+    url: &str,
+    name: Option<&str>,
+    out: Option<&str>,
+    mode: CrawlMode,
+    opts: &AddOptions,
+    config: &AppConfig,
+    cwd: &std::path::Path,
+) -> Result<AddKbConfig> {
     // Parse URL
     let parsed_url = Url::parse(url)
         .map_err(|e| eyre!("invalid URL '{url}': {e}"))?;
@@ -250,10 +698,6 @@ async fn cmd_add(url: &str, name: Option<&str>, out: Option<&str>, mode: &str) -
             .to_string()
     });
 
-    // Determine output root
-    let cwd = std::env::current_dir()
-        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
-
     let output_root = match out {
         Some(p) => PathBuf::from(p),
         None => {
@@ -262,37 +706,75 @@ async fn cmd_add(url: &str, name: Option<&str>, out: Option<&str>, mode: &str) -
         }
     };
 
-    // Build crawl config from loaded config
-    let crawl_config = CrawlConfig::from(&config);
+    // Build crawl config from loaded config, then apply CLI overrides
+    let mut crawl_config = CrawlConfig::from(config);
+    opts.crawl.apply(&mut crawl_config);
+    crawl_config.offline = opts.offline;
+    let mode = if opts.single_page {
+        crawl_config.depth = 0;
+        CrawlMode::Crawl
+    } else {
+        mode
+    };
 
-    let add_config = AddKbConfig {
+    Ok(AddKbConfig {
         url: parsed_url,
-        name: kb_name.clone(),
+        name: kb_name,
         output_root,
-        mode: mode.to_string(),
+        mode,
         crawl: crawl_config,
         tool_version: env!("CARGO_PKG_VERSION").to_string(),
         model_id: config.openrouter.default_model.clone(),
         bridge_cmd: "bun".to_string(),
-        bridge_script: "packages/ts/openrouter-provider/src/bridge.ts".to_string(),
+        bridge_script: doctor::BRIDGE_SCRIPT.to_string(),
         bridge_working_dir: cwd.to_string_lossy().to_string(),
-    };
+        bridge_startup_timeout_secs: config.defaults.startup_timeout_secs,
+        section_chunks: opts.section_chunks,
+        deterministic: opts.deterministic,
+        custom_tasks: config.custom_tasks.clone(),
+        summary_fallback: config.defaults.summary_fallback,
+        artifact_layout: config.defaults.artifact_layout,
+        llms_txt_links: config.defaults.llms_txt_links,
+        compress_artifacts: config.defaults.compress_artifacts,
+        warm_cache_from: opts.warm_cache_from.clone(),
+        merge: opts.merge,
+    })
+}
+
+async fn cmd_add(
+    url: &str,
+    name: Option<&str>,
+    out: Option<&str>,
+    mode: CrawlMode,
+    opts: AddOptions,
+) -> Result<()> {
+    // Validate API key before doing anything
+    let config = load_config()?;
+    validate_api_key(&config)?;
+
+    let cwd = std::env::current_dir()
+        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
+    let add_config = add_kb_config(url, name, out, mode, &opts, &config, &cwd)?;
+    let kb_name = add_config.name.clone();
+    let mode = add_config.mode;
 
     info!(
         url,
         name = %kb_name,
-        mode,
+        %mode,
         "adding documentation source"
     );
 
-    // Set up progress reporting
-    let reporter = CliProgress::new();
-
-    let result = contextbuilder_core::pipeline::add_kb(&add_config, &reporter).await?;
+    let reporter = progress_reporter(opts.json_progress);
+    let result = contextbuilder_core::pipeline::add_kb(&add_config, reporter.as_ref()).await?;
 
     // Print summary
     println!();
-    println!("  Knowledge base created successfully!");
+    if result.merged {
+        println!("  New pages merged into the existing knowledge base!");
+    } else {
+        println!("  Knowledge base created successfully!");
+    }
     println!("  ID:     {}", result.kb_id);
     println!("  Name:   {kb_name}");
     println!("  Pages:  {}", result.page_count);
@@ -303,7 +785,100 @@ async fn cmd_add(url: &str, name: Option<&str>, out: Option<&str>, mode: &str) -
         result.elapsed.as_secs_f64()
     );
     println!();
+    if let Some(crawl) = &result.crawl {
+        print_crawl_summary(crawl);
+    }
+    if let Some(path) = &opts.metrics {
+        write_crawl_metrics(path, result.crawl.as_ref())?;
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Crawl summary
+// ---------------------------------------------------------------------------
+
+/// Print the post-crawl health summary.
+fn print_crawl_summary(result: &CrawlResult) {
+    println!("  Crawl summary");
+    for (label, value) in crawl_summary_rows(result) {
+        println!("  {label:<13} {value}");
+    }
+    println!();
+}
+
+/// Label/value rows of the crawl summary table.
+fn crawl_summary_rows(result: &CrawlResult) -> Vec<(&'static str, String)> {
+    let breakdown = |counts: &std::collections::BTreeMap<String, usize>| {
+        counts
+            .iter()
+            .map(|(name, count)| format!("{name} {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut errors = result.errors.len().to_string();
+    if !result.error_kinds.is_empty() {
+        errors.push_str(&format!(" ({})", breakdown(&result.error_kinds)));
+    }
+    let adapters = if result.adapters.is_empty() {
+        "-".to_string()
+    } else {
+        breakdown(&result.adapters)
+    };
+    let depths = if result.pages_by_depth.is_empty() {
+        "-".to_string()
+    } else {
+        result
+            .pages_by_depth
+            .iter()
+            .map(|(depth, count)| format!("{depth}: {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    vec![
+        ("Fetched:", result.pages_fetched.to_string()),
+        ("Skipped:", result.pages_skipped.to_string()),
+        ("Errors:", errors),
+        ("Downloaded:", format!("{:.1} KiB", result.bytes_downloaded as f64 / 1024.0)),
+        ("Avg latency:", format!("{} ms", result.avg_latency.as_millis())),
+        ("Adapters:", adapters),
+        ("By depth:", depths),
+    ]
+}
+
+/// Per-host crawl metrics as one JSON object, as written by `--metrics`.
+fn crawl_metrics_json(result: &CrawlResult) -> serde_json::Value {
+    let hosts: serde_json::Map<String, serde_json::Value> = result
+        .hosts
+        .iter()
+        .map(|(host, metrics)| {
+            let value = serde_json::json!({
+                "requests": metrics.requests,
+                "bytes": metrics.bytes,
+                "errors": metrics.errors,
+                "p50_latency_ms": metrics.p50_latency.as_millis() as u64,
+                "p95_latency_ms": metrics.p95_latency.as_millis() as u64,
+            });
+            (host.clone(), value)
+        })
+        .collect();
+    serde_json::json!({
+        "duration_ms": result.duration.as_millis() as u64,
+        "hosts": hosts,
+    })
+}
 
+/// Write `--metrics` output to `path`. With no crawl (e.g. pages listed by
+/// `llms.txt`), the file lists no hosts.
+fn write_crawl_metrics(path: &std::path::Path, crawl: Option<&CrawlResult>) -> Result<()> {
+    let empty = CrawlResult::default();
+    let json = serde_json::to_string_pretty(&crawl_metrics_json(crawl.unwrap_or(&empty)))?;
+    std::fs::write(path, json + "\n")
+        .map_err(|e| eyre!("cannot write metrics to '{}': {e}", path.display()))?;
+    info!(path = %path.display(), "crawl metrics written");
     Ok(())
 }
 
@@ -330,57 +905,178 @@ impl CliProgress {
 }
 
 impl ProgressReporter for CliProgress {
-    fn phase(&self, name: &str) {
-        self.spinner.set_message(name.to_string());
-    }
-
-    fn page_fetched(&self, url: &str, current: usize, total_estimate: usize) {
-        self.spinner.set_message(format!(
-            "Fetching [{current}/{total_estimate}] {url}"
-        ));
-    }
-
-    fn page_converted(&self, path: &str, current: usize, total: usize) {
-        self.spinner.set_message(format!(
-            "Converting [{current}/{total}] {path}"
-        ));
+    fn event(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Phase { phase } => {
+                self.spinner.set_message(phase.label());
+            }
+            ProgressEvent::PageFetched { url, current, total } => {
+                self.spinner.set_message(format!("Fetching [{current}/{total}] {url}"));
+            }
+            ProgressEvent::PageConverted {
+                path,
+                current,
+                total,
+            } => {
+                self.spinner.set_message(format!("Converting [{current}/{total}] {path}"));
+            }
+            ProgressEvent::EnrichmentTask {
+                task,
+                target,
+                current,
+                total,
+            } => {
+                self.spinner.set_message(format!("[{current}/{total}] {task}: {target}"));
+            }
+            ProgressEvent::Warning { message } => {
+                self.spinner.println(format!("  warning: {message}"));
+            }
+            ProgressEvent::Done { .. } => {
+                self.spinner.finish_and_clear();
+            }
+        }
     }
+}
 
-    fn done(&self, _result: &AddKbResult) {
-        self.spinner.finish_and_clear();
+/// Spinner for text logs; JSON lines on stderr with `--log-format json`.
+fn progress_reporter(json: bool) -> Box<dyn ProgressReporter> {
+    if json {
+        Box::new(JsonProgress::stderr())
+    } else {
+        Box::new(CliProgress::new())
     }
 }
 
 async fn cmd_build(kb: &str, emit: Option<&str>) -> Result<()> {
+    let kb_path = PathBuf::from(kb);
+    let manifest_path = kb_path.join("manifest.json");
+    let manifest: KbManifest = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .map_err(|e| eyre!("cannot read {}: {e}", manifest_path.display()))?,
+    )?;
+    manifest.ensure_current_schema(&kb_path)?;
+
     info!(kb, emit = emit.unwrap_or("all"), "building artifacts");
     println!("build: not yet implemented (kb={kb})");
     Ok(())
 }
 
-async fn cmd_update(kb: &str, prune: bool, force: bool) -> Result<()> {
+/// Switches of the `update` command.
+struct UpdateOptions {
+    prune: bool,
+    force: bool,
+    section_chunks: bool,
+    deterministic: bool,
+    offline: bool,
+    json_progress: bool,
+    limits: LimitOverrides,
+    fail_on_change: bool,
+    validate_only: bool,
+    metrics: Option<PathBuf>,
+}
+
+/// Resolve `update` arguments against the loaded config.
+fn update_kb_config(
+    kb_path: PathBuf,
+    opts: &UpdateOptions,
+    config: &AppConfig,
+) -> contextbuilder_core::update::UpdateKbConfig {
+    let mut crawl_config = CrawlConfig::from(config);
+    opts.limits.apply(&mut crawl_config);
+    crawl_config.offline = opts.offline;
+
+    contextbuilder_core::update::UpdateKbConfig {
+        kb_path,
+        crawl: crawl_config,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        prune: opts.prune,
+        force: opts.force,
+        section_chunks: opts.section_chunks,
+        deterministic: opts.deterministic,
+    }
+}
+
+async fn cmd_update(kb: &str, opts: UpdateOptions) -> Result<()> {
     let config = load_config()?;
-    validate_api_key(&config)?;
 
     let kb_path = PathBuf::from(kb);
     if !kb_path.join("manifest.json").exists() {
         return Err(eyre!("no manifest.json found at '{kb}' — is this a valid KB directory?"));
     }
 
-    let crawl_config = CrawlConfig::from(&config);
+    let update_config = update_kb_config(kb_path, &opts, &config);
+    if opts.validate_only {
+        let check = contextbuilder_core::update::validate_source(&update_config).await?;
+        if opts.json_progress {
+            println!("{}", source_check_json(&check));
+        } else {
+            println!(
+                "  Source reachable: {} (HTTP {}, {} ms)",
+                check.source_url,
+                check.status,
+                check.elapsed.as_millis()
+            );
+        }
+        return Ok(());
+    }
+    validate_api_key(&config)?;
 
-    let update_config = contextbuilder_core::update::UpdateKbConfig {
-        kb_path,
-        crawl: crawl_config,
-        tool_version: env!("CARGO_PKG_VERSION").to_string(),
-        prune,
-        force,
-    };
+    info!(kb, prune = opts.prune, force = opts.force, "updating knowledge base");
+
+    let reporter = progress_reporter(opts.json_progress);
+    let result = contextbuilder_core::update::update_kb(&update_config, reporter.as_ref()).await?;
+
+    if opts.json_progress {
+        println!("{}", update_summary_json(&result));
+    } else {
+        print_update_summary(&result);
+    }
+    if let Some(path) = &opts.metrics {
+        write_crawl_metrics(path, Some(&result.crawl))?;
+    }
+
+    if let Some(code) = update_exit_code(&result, opts.fail_on_change) {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Exit status of `update --fail-on-change` when pages changed; errors
+/// keep exiting with 1.
+const CHANGED_EXIT_CODE: i32 = 2;
+
+/// The status `update` exits with instead of 0, if any.
+fn update_exit_code(result: &UpdateKbResult, fail_on_change: bool) -> Option<i32> {
+    (fail_on_change && result.has_changes()).then_some(CHANGED_EXIT_CODE)
+}
 
-    info!(kb, prune, force, "updating knowledge base");
+/// The `update --validate-only` outcome as one JSON object.
+fn source_check_json(check: &contextbuilder_core::update::SourceCheck) -> serde_json::Value {
+    serde_json::json!({
+        "source_url": check.source_url.as_str(),
+        "reachable": true,
+        "status": check.status,
+        "elapsed_ms": check.elapsed.as_millis() as u64,
+    })
+}
 
-    let reporter = CliProgress::new();
-    let result = contextbuilder_core::update::update_kb(&update_config, &reporter).await?;
+/// The `update` summary as one JSON object, printed to stdout with
+/// `--log-format json`.
+fn update_summary_json(result: &UpdateKbResult) -> serde_json::Value {
+    serde_json::json!({
+        "kb_id": result.kb_id.to_string(),
+        "changed": result.has_changes(),
+        "pages_added": result.pages_added,
+        "pages_changed": result.pages_changed,
+        "pages_unchanged": result.pages_unchanged,
+        "pages_removed": result.pages_removed,
+        "page_count": result.page_count,
+        "elapsed_ms": result.elapsed.as_millis() as u64,
+    })
+}
 
+/// Print the `update` summary table.
+fn print_update_summary(result: &UpdateKbResult) {
     println!();
     println!("  Knowledge base updated!");
     println!("  ID:        {}", result.kb_id);
@@ -394,6 +1090,190 @@ async fn cmd_update(kb: &str, prune: bool, force: bool) -> Result<()> {
         result.elapsed.as_secs_f64()
     );
     println!();
+    print_crawl_summary(&result.crawl);
+}
+
+async fn cmd_reenrich(
+    kb: &str,
+    page: &str,
+    deterministic: bool,
+    json_progress: bool,
+) -> Result<()> {
+    let config = load_config()?;
+    validate_api_key(&config)?;
+
+    let kb_path = PathBuf::from(kb);
+    if !kb_path.join("manifest.json").exists() {
+        return Err(eyre!("no manifest.json found at '{kb}' — is this a valid KB directory?"));
+    }
+    let cwd = std::env::current_dir()
+        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
+
+    let reenrich_config = contextbuilder_core::reenrich::ReenrichConfig {
+        kb_path,
+        page_path: page.trim_matches('/').to_string(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        model_id: config.openrouter.default_model.clone(),
+        bridge_cmd: "bun".to_string(),
+        bridge_script: doctor::BRIDGE_SCRIPT.to_string(),
+        bridge_working_dir: cwd.to_string_lossy().to_string(),
+        bridge_startup_timeout_secs: config.defaults.startup_timeout_secs,
+        deterministic,
+        custom_tasks: config.custom_tasks.clone(),
+        summary_fallback: config.defaults.summary_fallback,
+        artifact_layout: config.defaults.artifact_layout,
+        llms_txt_links: config.defaults.llms_txt_links,
+        compress_artifacts: config.defaults.compress_artifacts,
+    };
+
+    info!(kb, page, "re-enriching page");
+
+    let reporter = progress_reporter(json_progress);
+    let result =
+        contextbuilder_core::reenrich::reenrich_page(&reenrich_config, reporter.as_ref()).await?;
+
+    println!();
+    println!("  Page re-enriched!");
+    println!("  ID:          {}", result.kb_id);
+    println!("  Page:        {}", reenrich_config.page_path);
+    println!("  Invalidated: {} cache entries", result.entries_invalidated);
+    println!("  Time:        {:.1}s", result.elapsed.as_secs_f64());
+    println!();
+
+    Ok(())
+}
+
+async fn cmd_add_pages(
+    kb: &str,
+    urls: &[String],
+    section_chunks: bool,
+    deterministic: bool,
+    offline: bool,
+    json_progress: bool,
+) -> Result<()> {
+    let config = load_config()?;
+    validate_api_key(&config)?;
+
+    let kb_path = PathBuf::from(kb);
+    if !kb_path.join("manifest.json").exists() {
+        return Err(eyre!("no manifest.json found at '{kb}' — is this a valid KB directory?"));
+    }
+    let urls = urls
+        .iter()
+        .map(|u| Url::parse(u).map_err(|e| eyre!("invalid URL '{u}': {e}")))
+        .collect::<Result<Vec<_>>>()?;
+
+    let cwd = std::env::current_dir()
+        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
+    let mut crawl_config = CrawlConfig::from(&config);
+    crawl_config.offline = offline;
+
+    let add_config = contextbuilder_core::append::AddPagesConfig {
+        kb_path,
+        urls,
+        crawl: crawl_config,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        model_id: config.openrouter.default_model.clone(),
+        bridge_cmd: "bun".to_string(),
+        bridge_script: doctor::BRIDGE_SCRIPT.to_string(),
+        bridge_working_dir: cwd.to_string_lossy().to_string(),
+        bridge_startup_timeout_secs: config.defaults.startup_timeout_secs,
+        section_chunks,
+        deterministic,
+        custom_tasks: config.custom_tasks.clone(),
+        summary_fallback: config.defaults.summary_fallback,
+        artifact_layout: config.defaults.artifact_layout,
+        llms_txt_links: config.defaults.llms_txt_links,
+        compress_artifacts: config.defaults.compress_artifacts,
+    };
+
+    info!(kb, urls = add_config.urls.len(), "adding pages to knowledge base");
+
+    let reporter = progress_reporter(json_progress);
+    let result = contextbuilder_core::append::add_pages(&add_config, reporter.as_ref()).await?;
+
+    println!();
+    println!("  Pages added to knowledge base!");
+    println!("  ID:       {}", result.kb_id);
+    println!("  Added:    {}", result.pages_added);
+    println!("  Replaced: {}", result.pages_replaced);
+    println!("  Failed:   {}", result.pages_failed);
+    println!("  Total:    {}", result.page_count);
+    println!(
+        "  Time:     {:.1}s",
+        result.elapsed.as_secs_f64()
+    );
+    println!();
+
+    Ok(())
+}
+
+async fn cmd_merge(
+    kbs: &[String],
+    out: Option<&str>,
+    name: Option<String>,
+    deterministic: bool,
+    json_progress: bool,
+) -> Result<()> {
+    let config = load_config()?;
+    validate_api_key(&config)?;
+
+    if kbs.len() < 2 {
+        return Err(eyre!("merge needs at least two --kb paths"));
+    }
+    let kb_paths = kbs
+        .iter()
+        .map(|kb| {
+            let path = PathBuf::from(kb);
+            if path.join("manifest.json").exists() {
+                Ok(path)
+            } else {
+                Err(eyre!("no manifest.json found at '{kb}' — is this a valid KB directory?"))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let cwd = std::env::current_dir()
+        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
+    let output_root = match out {
+        Some(o) => PathBuf::from(o),
+        None => cwd.join("var").join("kb"),
+    };
+
+    let merge_config = contextbuilder_core::merge::MergeKbsConfig {
+        kb_paths,
+        output_root,
+        name,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        model_id: config.openrouter.default_model.clone(),
+        bridge_cmd: "bun".to_string(),
+        bridge_script: doctor::BRIDGE_SCRIPT.to_string(),
+        bridge_working_dir: cwd.to_string_lossy().to_string(),
+        bridge_startup_timeout_secs: config.defaults.startup_timeout_secs,
+        deterministic,
+        custom_tasks: config.custom_tasks.clone(),
+        summary_fallback: config.defaults.summary_fallback,
+        artifact_layout: config.defaults.artifact_layout,
+        llms_txt_links: config.defaults.llms_txt_links,
+        compress_artifacts: config.defaults.compress_artifacts,
+    };
+
+    info!(sources = merge_config.kb_paths.len(), "merging knowledge bases");
+
+    let reporter = progress_reporter(json_progress);
+    let result = contextbuilder_core::merge::merge_kbs(&merge_config, reporter.as_ref()).await?;
+
+    println!();
+    println!("  Knowledge bases merged!");
+    println!("  ID:       {}", result.kb_id);
+    println!("  Path:     {}", result.kb_path.display());
+    println!("  Sections: {}", result.namespaces.join(", "));
+    println!("  Pages:    {}", result.page_count);
+    println!(
+        "  Time:     {:.1}s",
+        result.elapsed.as_secs_f64()
+    );
+    println!();
 
     Ok(())
 }
@@ -415,6 +1295,7 @@ async fn cmd_mcp_serve(
     kb_root: Option<&str>,
     transport: &str,
     port: u16,
+    startup_timeout: Option<u64>,
 ) -> Result<()> {
     // Validate transport
     if transport != "stdio" && transport != "http" {
@@ -423,34 +1304,11 @@ async fn cmd_mcp_serve(
         ));
     }
 
-    // Check that bun is available
-    let bun_check = std::process::Command::new("bun")
-        .arg("--version")
-        .output();
-
-    match bun_check {
-        Ok(output) if output.status.success() => {
-            let version = String::from_utf8_lossy(&output.stdout);
-            info!(bun_version = %version.trim(), "bun runtime found");
-        }
-        _ => {
-            return Err(eyre!(
-                "bun runtime not found. Install Bun: https://bun.sh/docs/installation"
-            ));
-        }
-    }
-
-    // Resolve the MCP server script path relative to the CLI binary's location
-    // or the current working directory
+    // Check that bun and the server script are available
+    doctor::check_bun("bun").into_result()?;
     let cwd = std::env::current_dir()?;
-    let server_script = cwd.join("apps/mcp-server/src/index.ts");
-
-    if !server_script.exists() {
-        return Err(eyre!(
-            "MCP server script not found at '{}'. Run from the project root or install the package.",
-            server_script.display()
-        ));
-    }
+    doctor::check_script("mcp server", &cwd, doctor::MCP_SERVER_SCRIPT).into_result()?;
+    let server_script = cwd.join(doctor::MCP_SERVER_SCRIPT);
 
     // Build args for the subprocess
     let mut args: Vec<String> = vec![
@@ -506,15 +1364,51 @@ async fn cmd_mcp_serve(
         println!("Starting MCP server on http://localhost:{port}/mcp");
     }
 
-    // Spawn bun subprocess
+    let startup_timeout = match startup_timeout {
+        Some(secs) => secs,
+        None => load_config()?.defaults.startup_timeout_secs,
+    };
+
+    // Spawn bun subprocess; stderr is piped so we can watch for the
+    // server's `server_started` log line while forwarding everything.
     let mut child = std::process::Command::new("bun")
         .args(&args)
         .stdin(std::process::Stdio::inherit())
         .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::piped())
         .spawn()
         .map_err(|e| eyre!("failed to spawn bun: {e}"))?;
 
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| eyre!("failed to capture MCP server stderr"))?;
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        use std::io::BufRead;
+        for line in std::io::BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+            eprintln!("{line}");
+            if line.contains("\"server_started\"") {
+                let _ = started_tx.send(());
+            }
+        }
+    });
+
+    match started_rx.recv_timeout(std::time::Duration::from_secs(startup_timeout)) {
+        Ok(()) => info!("MCP server started"),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(eyre!(
+                "MCP server did not start within {startup_timeout}s and was stopped. \
+                 Check your bun install (`bun --version`) and run `bun install`."
+            ));
+        }
+        // stderr closed before the start signal: the server exited early,
+        // its exit status is reported below.
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {}
+    }
+
     // Wait for the child to finish (ctrl-C forwarded via signal inheritance)
     let status = child
         .wait()
@@ -536,7 +1430,7 @@ async fn cmd_mcp_config(
     kb_root: Option<&str>,
 ) -> Result<()> {
     let cwd = std::env::current_dir()?;
-    let binary_path = cwd.join("apps/mcp-server/src/index.ts");
+    let binary_path = cwd.join(doctor::MCP_SERVER_SCRIPT);
 
     // Build the args list
     let mut kb_args: Vec<serde_json::Value> = Vec::new();
@@ -632,3 +1526,671 @@ async fn cmd_config_show() -> Result<()> {
     println!("{toml_str}");
     Ok(())
 }
+
+async fn cmd_cache_prune(
+    kb: &str,
+    model: Option<&str>,
+    older_than: Option<Duration>,
+) -> Result<()> {
+    if model.is_none() && older_than.is_none() {
+        return Err(eyre!("specify --model and/or --older-than"));
+    }
+
+    let kb_path = PathBuf::from(kb);
+    let manifest_path = kb_path.join("manifest.json");
+    let manifest: KbManifest = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .map_err(|e| eyre!("cannot read {}: {e}", manifest_path.display()))?,
+    )?;
+
+    let db_path = kb_path.join("indexes").join("contextbuilder.db");
+    let storage = contextbuilder_storage::Storage::open(&db_path).await?;
+
+    info!(
+        kb,
+        model,
+        older_than_secs = older_than.map(|d| d.as_secs()),
+        "pruning enrichment cache"
+    );
+    let removed = storage
+        .prune_enrichment_cache(&manifest.id.to_string(), model, older_than)
+        .await?;
+
+    println!("Removed {removed} enrichment cache entries from '{}'.", manifest.name);
+    Ok(())
+}
+
+fn cmd_convert(file: &str, url: Option<&str>, trace: bool) -> Result<()> {
+    let config = load_config()?;
+    let html = std::fs::read_to_string(file).map_err(|e| eyre!("cannot read {file}: {e}"))?;
+    let opts = ConvertOptions {
+        source_url: url.unwrap_or_default().to_string(),
+        comment_metadata: config.crawl_policies.comment_metadata,
+        preserve_source_frontmatter: config.crawl_policies.preserve_source_frontmatter,
+        strip_selectors: config.crawl_policies.strip_selectors.clone(),
+        max_title_len: config.crawl_policies.max_title_len,
+        link_rewrites: config.crawl_policies.link_rewrites.clone(),
+        dedupe_adjacent: config.crawl_policies.dedupe_adjacent_items,
+        preserve_anchors: config.crawl_policies.preserve_anchors,
+        ..Default::default()
+    };
+
+    if !trace {
+        print!("{}", contextbuilder_markdown::convert(&html, &opts)?.markdown);
+        return Ok(());
+    }
+    let (result, passes) = contextbuilder_markdown::convert_with_trace(&html, &opts)?;
+    for pass in &passes {
+        let diff = pass.diff_lines();
+        if diff.is_empty() {
+            eprintln!("== {} (no change)", pass.pass);
+            continue;
+        }
+        eprintln!("== {} ({} lines)", pass.pass, diff.len());
+        for line in diff {
+            eprintln!("{line}");
+        }
+    }
+    eprintln!();
+    print!("{}", result.markdown);
+    Ok(())
+}
+
+async fn cmd_reclean(kb: &str) -> Result<()> {
+    let kb_path = PathBuf::from(kb);
+    let manifest_path = kb_path.join("manifest.json");
+    let manifest: KbManifest = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .map_err(|e| eyre!("cannot read {}: {e}", manifest_path.display()))?,
+    )?;
+
+    let report = contextbuilder_core::reclean::reclean_kb(&kb_path).await?;
+    println!(
+        "Recleaned {} of {} pages in '{}'.",
+        report.changed, report.pages, manifest.name
+    );
+    Ok(())
+}
+
+fn cmd_migrate(kb: &str) -> Result<()> {
+    let kb_path = PathBuf::from(kb);
+    let report = contextbuilder_core::migrate::migrate_kb(&kb_path)?;
+    if report.unchanged() {
+        println!(
+            "KB at '{}' is already at schema_version {}.",
+            kb_path.display(),
+            report.to
+        );
+    } else {
+        println!(
+            "Migrated KB at '{}' from schema_version {} to {}.",
+            kb_path.display(),
+            report.from,
+            report.to
+        );
+    }
+    Ok(())
+}
+
+async fn cmd_scope(
+    url: &str,
+    overrides: &CrawlOverrides,
+    sitemap: Option<&std::path::Path>,
+    offline: bool,
+) -> Result<()> {
+    let config = load_config()?;
+    let start_url = Url::parse(url).map_err(|e| eyre!("invalid URL '{url}': {e}"))?;
+    let mut crawl_config = CrawlConfig::from(&config);
+    overrides.apply(&mut crawl_config);
+    crawl_config.offline = offline;
+
+    info!(url, depth = crawl_config.depth, "previewing crawl scope");
+    let urls =
+        contextbuilder_core::scope::preview_scope(&start_url, &crawl_config, sitemap).await?;
+    for line in scope_report(&urls) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// The lines printed by `scope`: in-scope URLs, then out-of-scope ones, each
+/// with the rule that decided it.
+fn scope_report(urls: &[ScopedUrl]) -> Vec<String> {
+    let (inside, outside): (Vec<&ScopedUrl>, Vec<&ScopedUrl>) =
+        urls.iter().partition(|u| u.rule.in_scope());
+    let width = urls.iter().map(|u| u.url.len()).max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (heading, group) in [("In scope", inside), ("Out of scope", outside)] {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("{heading} ({}):", group.len()));
+        lines.extend(group.iter().map(|u| format!("  {:<width$}  {}", u.url, u.rule)));
+    }
+    lines
+}
+
+async fn cmd_history(kb: &str) -> Result<()> {
+    let kb_path = PathBuf::from(kb);
+    let manifest_path = kb_path.join("manifest.json");
+    let manifest: KbManifest = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .map_err(|e| eyre!("cannot read {}: {e}", manifest_path.display()))?,
+    )?;
+
+    let db_path = kb_path.join("indexes").join("contextbuilder.db");
+    let storage = contextbuilder_storage::Storage::open_readonly(&db_path).await?;
+    let jobs = storage.list_crawl_jobs(&manifest.id.to_string()).await?;
+
+    if jobs.is_empty() {
+        println!("No crawls recorded for '{}'.", manifest.name);
+        return Ok(());
+    }
+    println!(
+        "{:<19}  {:>8}  {:<21}  {:>7}  {:>7}  {:>6}",
+        "Started (UTC)", "Duration", "Status", "Fetched", "Skipped", "Errors"
+    );
+    for job in &jobs {
+        let [started, duration, status, fetched, skipped, errors] = history_row(job);
+        println!(
+            "{started:<19}  {duration:>8}  {status:<21}  {fetched:>7}  {skipped:>7}  {errors:>6}"
+        );
+    }
+    Ok(())
+}
+
+/// Columns of one `history` row: started, duration, status, fetched,
+/// skipped, errors.
+fn history_row(job: &contextbuilder_storage::CrawlJobRecord) -> [String; 6] {
+    let stat = |key: &str| match job.stats.as_ref().and_then(|s| s.get(key)) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => "-".to_string(),
+    };
+    let duration = job.finished_at.map_or_else(
+        || "-".to_string(),
+        |end| format!("{:.1}s", (end - job.started_at).num_milliseconds() as f64 / 1000.0),
+    );
+    let status = if job.finished_at.is_some() {
+        stat("status")
+    } else {
+        "unfinished".to_string()
+    };
+    [
+        job.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        duration,
+        status,
+        stat("pages_fetched"),
+        stat("pages_skipped"),
+        stat("errors"),
+    ]
+}
+
+async fn cmd_doctor(out: Option<&str>) -> Result<()> {
+    let cwd = std::env::current_dir()
+        .map_err(|e| eyre!("cannot determine working directory: {e}"))?;
+    let output_dir = match out {
+        Some(p) => PathBuf::from(p),
+        None => cwd.join("var").join("kb"),
+    };
+
+    let checks = doctor::run_checks(&config_file_path()?, &cwd, &output_dir);
+    println!();
+    for check in &checks {
+        println!("{}", check.render());
+    }
+    println!();
+
+    let failed = checks.iter().filter(|c| !c.ok).count();
+    if failed > 0 {
+        return Err(eyre!("{failed} of {} checks failed", checks.len()));
+    }
+    println!("  All checks passed.");
+    println!();
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `contextbuilder add <args>` and resolve it against `config`.
+    fn resolve_add(args: &[&str], config: &AppConfig) -> AddKbConfig {
+        let cli = Cli::try_parse_from(["contextbuilder", "add"].iter().chain(args)).unwrap();
+        let Command::Add {
+            url,
+            name,
+            out,
+            mode,
+            crawl,
+            section_chunks,
+            single_page,
+            deterministic,
+            warm_cache_from,
+            merge,
+            metrics,
+        } = cli.command
+        else {
+            panic!("expected the add command");
+        };
+        let opts = AddOptions {
+            section_chunks,
+            single_page,
+            deterministic,
+            offline: false,
+            json_progress: false,
+            crawl,
+            warm_cache_from,
+            merge,
+            metrics,
+        };
+        add_kb_config(
+            &url,
+            name.as_deref(),
+            out.as_deref(),
+            mode,
+            &opts,
+            config,
+            std::path::Path::new("/work"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn profile_flag_sets_a_preset_that_explicit_flags_override() {
+        let mut config = AppConfig::default();
+        config.defaults.profile = Some(PolitenessProfile::Aggressive);
+        let from_config = resolve_add(&["https://docs.example.com"], &config);
+        assert_eq!((from_config.crawl.concurrency, from_config.crawl.rate_limit_ms), (8, 0));
+
+        let gentle = resolve_add(&["https://docs.example.com", "--profile", "gentle"], &config);
+        assert_eq!((gentle.crawl.concurrency, gentle.crawl.rate_limit_ms), (1, 1000));
+
+        let args = [
+            "https://docs.example.com",
+            "--profile",
+            "gentle",
+            "--concurrency",
+            "2",
+        ];
+        let overridden = resolve_add(&args, &config);
+        assert_eq!((overridden.crawl.concurrency, overridden.crawl.rate_limit_ms), (2, 1000));
+        let args = ["https://docs.example.com", "--rate-limit-ms", "500", "--profile", "balanced"];
+        let overridden = resolve_add(&args, &config);
+        assert_eq!((overridden.crawl.concurrency, overridden.crawl.rate_limit_ms), (4, 500));
+    }
+
+    #[test]
+    fn add_flags_override_config() {
+        let mut config = AppConfig::default();
+        config.crawl_policies.include_patterns = vec!["/docs/*".into()];
+
+        let defaults = resolve_add(&["https://docs.example.com"], &config);
+        assert_eq!(defaults.crawl.depth, config.defaults.crawl_depth);
+        assert_eq!(defaults.crawl.include_patterns, vec!["/docs/*"]);
+        assert!(!defaults.crawl.prune_empty_sections);
+
+        let add = resolve_add(
+            &[
+                "https://docs.example.com",
+                "--depth",
+                "1",
+                "--concurrency",
+                "12",
+                "--rate-limit-ms",
+                "0",
+                "--include",
+                "/guides/*",
+                "--include",
+                "/api/*",
+                "--exclude",
+                "/api/internal/*",
+                "--exclude-selector",
+                "body.changelog",
+                "--flatten-depth",
+                "2",
+                "--prune-empty-sections",
+                "--max-pages",
+                "100",
+                "--timeout",
+                "45",
+            ],
+            &config,
+        );
+        assert_ne!(config.defaults.crawl_depth, 1);
+        assert_eq!(add.crawl.depth, 1);
+        assert_eq!(add.crawl.concurrency, 12);
+        assert_eq!(add.crawl.rate_limit_ms, 0);
+        assert_eq!(add.crawl.include_patterns, vec!["/guides/*", "/api/*"]);
+        assert_eq!(add.crawl.exclude_patterns, vec!["/api/internal/*"]);
+        assert_eq!(add.crawl.skip_page_selectors, vec!["body.changelog"]);
+        assert_eq!(add.crawl.max_toc_depth, Some(2));
+        assert!(add.crawl.prune_empty_sections);
+        assert_eq!(add.crawl.max_pages, 100);
+        assert_eq!(add.crawl.timeouts.timeout_secs, 45);
+        assert_eq!(add.output_root, PathBuf::from("/work/var/kb"));
+        assert!(!add.merge);
+
+        let merge = resolve_add(&["https://docs.example.com", "--merge"], &config);
+        assert!(merge.merge);
+        assert!(Cli::try_parse_from([
+            "contextbuilder",
+            "add",
+            "https://docs.example.com",
+            "--merge",
+            "--warm-cache-from",
+            "var/kb/old",
+        ])
+        .is_err());
+    }
+
+    /// Parse `contextbuilder update <args>` and resolve it against `config`.
+    fn resolve_update(
+        args: &[&str],
+        config: &AppConfig,
+    ) -> contextbuilder_core::update::UpdateKbConfig {
+        let cli = Cli::try_parse_from(["contextbuilder", "update"].iter().chain(args)).unwrap();
+        let Command::Update {
+            kb,
+            prune,
+            force,
+            section_chunks,
+            deterministic,
+            limits,
+            fail_on_change,
+            validate_only,
+            metrics,
+        } = cli.command
+        else {
+            panic!("expected the update command");
+        };
+        let opts = UpdateOptions {
+            prune,
+            force,
+            section_chunks,
+            deterministic,
+            offline: false,
+            json_progress: false,
+            limits,
+            fail_on_change,
+            validate_only,
+            metrics,
+        };
+        update_kb_config(PathBuf::from(kb), &opts, config)
+    }
+
+    #[test]
+    fn fail_on_change_exit_code_follows_the_diff() {
+        let unchanged = UpdateKbResult {
+            kb_id: contextbuilder_shared::KbId::new(),
+            pages_added: 0,
+            pages_removed: 0,
+            pages_changed: 0,
+            pages_unchanged: 4,
+            page_count: 4,
+            elapsed: Duration::from_millis(1200),
+            crawl: CrawlResult::default(),
+        };
+        assert_eq!(update_exit_code(&unchanged, true), None);
+        assert_eq!(update_summary_json(&unchanged)["changed"], false);
+
+        let changed = UpdateKbResult {
+            pages_changed: 1,
+            pages_unchanged: 3,
+            ..unchanged
+        };
+        assert_eq!(update_exit_code(&changed, true), Some(CHANGED_EXIT_CODE));
+        assert_eq!(update_exit_code(&changed, false), None);
+        let summary = update_summary_json(&changed);
+        assert_eq!((&summary["changed"], &summary["pages_changed"]), (&true.into(), &1.into()));
+        assert_eq!(summary["elapsed_ms"], 1200);
+
+        let args = ["contextbuilder", "update", "--kb", "kb", "--fail-on-change"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Command::Update { fail_on_change: true, .. }));
+    }
+
+    #[test]
+    fn validate_only_excludes_writing_flags() {
+        let args = ["contextbuilder", "update", "--kb", "kb", "--validate-only"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Command::Update { validate_only: true, .. }));
+
+        let args = ["contextbuilder", "update", "--kb", "kb", "--validate-only", "--prune"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn update_flags_override_config() {
+        let mut config = AppConfig::default();
+        config.crawl_policies.max_pages = 2000;
+
+        let defaults = resolve_update(&["--kb", "var/kb/docs"], &config);
+        assert_eq!(defaults.crawl.max_pages, 2000);
+        assert_eq!(defaults.crawl.depth, config.defaults.crawl_depth);
+        assert_eq!(defaults.crawl.timeouts, config.crawl_policies.timeouts);
+
+        let update = resolve_update(
+            &[
+                "--kb",
+                "var/kb/docs",
+                "--max-pages",
+                "50",
+                "--depth",
+                "1",
+                "--timeout",
+                "20",
+            ],
+            &config,
+        );
+        assert_eq!(update.kb_path, PathBuf::from("var/kb/docs"));
+        assert_eq!(update.crawl.max_pages, 50);
+        assert_eq!(update.crawl.depth, 1);
+        assert_eq!(update.crawl.timeouts.timeout_secs, 20);
+        assert_eq!(
+            update.crawl.timeouts.connect_timeout_secs,
+            config.crawl_policies.timeouts.connect_timeout_secs
+        );
+
+        for args in [["--max-pages", "0"], ["--timeout", "0"], ["--depth", "51"]] {
+            let parsed = Cli::try_parse_from(
+                ["contextbuilder", "update", "--kb", "var/kb/docs"].iter().chain(&args),
+            );
+            assert!(parsed.is_err(), "{args:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn add_flags_are_range_checked() {
+        for args in [
+            ["--depth", "51"],
+            ["--concurrency", "0"],
+            ["--rate-limit-ms", "60001"],
+            ["--depth", "-1"],
+            ["--flatten-depth", "0"],
+            ["--max-pages", "0"],
+            ["--timeout", "3601"],
+        ] {
+            let parsed = Cli::try_parse_from(
+                ["contextbuilder", "add", "https://docs.example.com"].iter().chain(&args),
+            );
+            assert!(parsed.is_err(), "{args:?} should be rejected");
+        }
+
+        // --depth and --single-page both set the depth
+        let parsed = Cli::try_parse_from([
+            "contextbuilder",
+            "add",
+            "https://docs.example.com",
+            "--depth",
+            "2",
+            "--single-page",
+        ]);
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn scope_report_groups_urls_with_their_rule() {
+        use contextbuilder_core::scope::ScopeRule;
+
+        let scoped = |url: &str, rule| ScopedUrl {
+            url: url.into(),
+            depth: 1,
+            rule,
+        };
+        let urls = [
+            scoped("https://docs.example.com/", ScopeRule::SameHost),
+            scoped(
+                "https://docs.example.com/private/keys",
+                ScopeRule::Exclude("/private/*".into()),
+            ),
+            scoped("https://docs.example.com/guide", ScopeRule::SameHost),
+            scoped("https://cdn.example.com/x", ScopeRule::Host),
+        ];
+        assert_eq!(
+            scope_report(&urls),
+            [
+                "In scope (2):",
+                "  https://docs.example.com/              same host",
+                "  https://docs.example.com/guide         same host",
+                "",
+                "Out of scope (2):",
+                "  https://docs.example.com/private/keys  exclude /private/*",
+                "  https://cdn.example.com/x              other host",
+            ]
+        );
+    }
+
+    #[test]
+    fn crawl_summary_lists_aggregates() {
+        let result = CrawlResult {
+            pages_fetched: 12,
+            pages_skipped: 3,
+            errors: vec![
+                ("https://docs.example.com/a".into(), "HTTP 404".into()),
+                ("https://docs.example.com/b".into(), "timed out".into()),
+            ],
+            error_kinds: [("http_404".to_string(), 1), ("network".to_string(), 1)].into(),
+            bytes_downloaded: 3 * 1024 + 512,
+            avg_latency: Duration::from_millis(85),
+            adapters: [("generic".to_string(), 2), ("vitepress".to_string(), 10)].into(),
+            pages_by_depth: [(0, 1), (1, 8), (2, 3)].into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            crawl_summary_rows(&result),
+            vec![
+                ("Fetched:", "12".to_string()),
+                ("Skipped:", "3".to_string()),
+                ("Errors:", "2 (http_404 1, network 1)".to_string()),
+                ("Downloaded:", "3.5 KiB".to_string()),
+                ("Avg latency:", "85 ms".to_string()),
+                ("Adapters:", "generic 2, vitepress 10".to_string()),
+                ("By depth:", "0: 1, 1: 8, 2: 3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn metrics_file_lists_requests_per_host() {
+        use contextbuilder_core::pipeline::HostMetrics;
+
+        let result = CrawlResult {
+            duration: Duration::from_millis(2500),
+            hosts: [
+                (
+                    "docs.example.com".to_string(),
+                    HostMetrics {
+                        requests: 40,
+                        bytes: 512_000,
+                        errors: 2,
+                        p50_latency: Duration::from_millis(80),
+                        p95_latency: Duration::from_millis(310),
+                    },
+                ),
+                (
+                    "cdn.example.com:8443".to_string(),
+                    HostMetrics { requests: 3, bytes: 9_000, ..Default::default() },
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let dir = std::env::temp_dir().join(format!("cb-metrics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metrics.json");
+
+        write_crawl_metrics(&path, Some(&result)).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["duration_ms"], 2500);
+        assert_eq!(
+            written["hosts"]["docs.example.com"],
+            serde_json::json!({
+                "requests": 40,
+                "bytes": 512_000,
+                "errors": 2,
+                "p50_latency_ms": 80,
+                "p95_latency_ms": 310,
+            })
+        );
+        assert_eq!(written["hosts"]["cdn.example.com:8443"]["requests"], 3);
+
+        // No crawl (llms.txt discovery): no hosts
+        write_crawl_metrics(&path, None).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["hosts"], serde_json::json!({}));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn history_rows_render_finished_and_unfinished_jobs() {
+        use chrono::TimeZone;
+        use contextbuilder_storage::CrawlJobRecord;
+
+        let started_at = chrono::Utc.with_ymd_and_hms(2025, 7, 14, 10, 0, 0).unwrap();
+        let finished = CrawlJobRecord {
+            id: "job-1".into(),
+            started_at,
+            finished_at: Some(started_at + chrono::Duration::milliseconds(72_400)),
+            stats: Some(serde_json::json!({
+                "status": "completed_with_errors",
+                "pages_fetched": 156,
+                "pages_skipped": 3,
+                "errors": 2,
+            })),
+        };
+        assert_eq!(
+            history_row(&finished),
+            ["2025-07-14 10:00:00", "72.4s", "completed_with_errors", "156", "3", "2"]
+                .map(String::from)
+        );
+
+        let unfinished = CrawlJobRecord {
+            finished_at: None,
+            stats: None,
+            ..finished
+        };
+        assert_eq!(
+            history_row(&unfinished),
+            ["2025-07-14 10:00:00", "-", "unfinished", "-", "-", "-"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn parse_age_rejects_overflowing_ages() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 24 * 60 * 60));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 60 * 60));
+        assert!(parse_age("12").is_err());
+        let err = parse_age(&format!("{}d", u64::MAX / 2)).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+    }
+}
@@ -0,0 +1,287 @@
+//! Environment checks behind `contextbuilder doctor`.
+//!
+//! Each check returns a [`Check`] instead of failing fast, so `doctor` can
+//! report every problem at once. Other commands reuse individual checks via
+//! [`Check::into_result`].
+
+use std::path::Path;
+
+use color_eyre::eyre::{Result, eyre};
+use contextbuilder_shared::{AppConfig, load_config_from, validate_api_key};
+
+/// OpenRouter bridge script, relative to the project root.
+pub(crate) const BRIDGE_SCRIPT: &str = "packages/ts/openrouter-provider/src/bridge.ts";
+
+/// MCP server entry point, relative to the project root.
+pub(crate) const MCP_SERVER_SCRIPT: &str = "apps/mcp-server/src/index.ts";
+
+/// Outcome of a single environment check.
+#[derive(Debug)]
+pub(crate) struct Check {
+    /// Short check name.
+    pub name: &'static str,
+    /// Whether the check passed.
+    pub ok: bool,
+    /// What was found (version, path, or error).
+    pub detail: String,
+    /// How to fix a failure.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    /// Turn a failed check into an error carrying its remediation hint.
+    pub(crate) fn into_result(self) -> Result<()> {
+        if self.ok {
+            return Ok(());
+        }
+        match self.hint {
+            Some(hint) => Err(eyre!("{}. {hint}", self.detail)),
+            None => Err(eyre!("{}", self.detail)),
+        }
+    }
+
+    /// Render as a pass/fail line, with the hint indented below failures.
+    pub(crate) fn render(&self) -> String {
+        let mark = if self.ok { "✓" } else { "✗" };
+        let mut line = format!("  {mark} {:<12} {}", self.name, self.detail);
+        if let Some(hint) = &self.hint {
+            line.push_str(&format!("\n      → {hint}"));
+        }
+        line
+    }
+}
+
+/// Load and validate the config file at `path` (defaults if it is missing).
+pub(crate) fn check_config(path: &Path) -> (Check, AppConfig) {
+    const NAME: &str = "config";
+    if !path.exists() {
+        return (
+            Check::pass(NAME, format!("{} not found, using defaults", path.display())),
+            AppConfig::default(),
+        );
+    }
+
+    let loaded = load_config_from(path).and_then(|config| {
+        contextbuilder_core::enrichment::validate_custom_tasks(&config.custom_tasks)?;
+        Ok(config)
+    });
+    match loaded {
+        Ok(config) => (Check::pass(NAME, path.display().to_string()), config),
+        Err(e) => (
+            Check::fail(
+                NAME,
+                e.to_string(),
+                format!(
+                    "Fix the file, or move it aside and run `contextbuilder config init` ({})",
+                    path.display()
+                ),
+            ),
+            AppConfig::default(),
+        ),
+    }
+}
+
+/// Check that the OpenRouter API key env var is set.
+pub(crate) fn check_api_key(config: &AppConfig) -> Check {
+    const NAME: &str = "api key";
+    let var = &config.openrouter.api_key_env;
+    match validate_api_key(config) {
+        Ok(()) => Check::pass(NAME, format!("{var} is set")),
+        Err(_) => Check::fail(
+            NAME,
+            format!("{var} is not set"),
+            format!("export {var}=<key> (get one at https://openrouter.ai/keys)"),
+        ),
+    }
+}
+
+/// Check that `<bun> --version` runs.
+pub(crate) fn check_bun(bun: &str) -> Check {
+    const NAME: &str = "bun";
+    match std::process::Command::new(bun).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            Check::pass(NAME, format!("bun {}", version.trim()))
+        }
+        Ok(output) => Check::fail(
+            NAME,
+            format!("`{bun} --version` exited with {}", output.status),
+            "Reinstall Bun: https://bun.sh/docs/installation",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("bun runtime not found ({e})"),
+            "Install Bun: https://bun.sh/docs/installation",
+        ),
+    }
+}
+
+/// Check that the script at `root/relative` exists.
+pub(crate) fn check_script(name: &'static str, root: &Path, relative: &str) -> Check {
+    let path = root.join(relative);
+    if path.is_file() {
+        Check::pass(name, path.display().to_string())
+    } else {
+        Check::fail(
+            name,
+            format!("script not found at '{}'", path.display()),
+            "Run from the project root or install the package, then `bun install`",
+        )
+    }
+}
+
+/// Check that `dir` can be created and written to.
+pub(crate) fn check_output_dir(dir: &Path) -> Check {
+    const NAME: &str = "output dir";
+    let hint = "Pass a writable directory with --out";
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return Check::fail(NAME, format!("cannot create '{}': {e}", dir.display()), hint);
+    }
+
+    let probe = dir.join(".contextbuilder-doctor");
+    match std::fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Check::pass(NAME, dir.display().to_string())
+        }
+        Err(e) => Check::fail(NAME, format!("'{}' is not writable: {e}", dir.display()), hint),
+    }
+}
+
+/// Run every check from the project root `cwd`.
+pub(crate) fn run_checks(config_path: &Path, cwd: &Path, output_dir: &Path) -> Vec<Check> {
+    let (config_check, config) = check_config(config_path);
+    vec![
+        config_check,
+        check_api_key(&config),
+        check_bun("bun"),
+        check_script("bridge", cwd, BRIDGE_SCRIPT),
+        check_script("mcp server", cwd, MCP_SERVER_SCRIPT),
+        check_output_dir(output_dir),
+    ]
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "cb-doctor-{label}-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn config_missing_uses_defaults() {
+        let dir = temp_dir("config-missing");
+        let (check, config) = check_config(&dir.join("contextbuilder.toml"));
+        assert!(check.ok);
+        assert_eq!(config.openrouter.api_key_env, "OPENROUTER_API_KEY");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn config_parse_and_validation_failures() {
+        let dir = temp_dir("config-invalid");
+        let path = dir.join("contextbuilder.toml");
+
+        std::fs::write(&path, "[defaults\ncrawl_depth = ").unwrap();
+        let (check, _) = check_config(&path);
+        assert!(!check.ok);
+        assert!(check.detail.contains("failed to parse"));
+        assert!(check.hint.is_some());
+
+        std::fs::write(&path, "[[custom_tasks]]\nname = \"x\"\noutput = \"llms.txt\"\ninstructions = \"i\"\n").unwrap();
+        let (check, _) = check_config(&path);
+        assert!(!check.ok, "custom task shadowing a built-in artifact must fail");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn api_key_check_reads_configured_var() {
+        let mut config = AppConfig::default();
+        config.openrouter.api_key_env = "CB_DOCTOR_TEST_UNSET_KEY".into();
+        let check = check_api_key(&config);
+        assert!(!check.ok);
+        assert!(check.hint.unwrap().contains("export CB_DOCTOR_TEST_UNSET_KEY"));
+
+        // PATH is always set in the test environment
+        config.openrouter.api_key_env = "PATH".into();
+        assert!(check_api_key(&config).ok);
+    }
+
+    #[test]
+    fn bun_check_reports_missing_runtime() {
+        let check = check_bun("cb-doctor-no-such-binary");
+        assert!(!check.ok);
+        assert!(check.hint.unwrap().contains("bun.sh"));
+
+        let err = check_bun("cb-doctor-no-such-binary").into_result().unwrap_err();
+        assert!(err.to_string().contains("Install Bun"));
+    }
+
+    #[test]
+    fn script_check_requires_a_file() {
+        let dir = temp_dir("script");
+        assert!(!check_script("bridge", &dir, BRIDGE_SCRIPT).ok);
+
+        let path = dir.join(BRIDGE_SCRIPT);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "// bridge").unwrap();
+        assert!(check_script("bridge", &dir, BRIDGE_SCRIPT).ok);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn output_dir_check_creates_and_probes() {
+        let dir = temp_dir("output");
+        let out = dir.join("var").join("kb");
+        assert!(check_output_dir(&out).ok);
+        assert!(out.is_dir());
+        assert!(!out.join(".contextbuilder-doctor").exists());
+
+        // A regular file in the way cannot become a directory
+        let blocker = dir.join("blocker");
+        std::fs::write(&blocker, "").unwrap();
+        let check = check_output_dir(&blocker.join("kb"));
+        assert!(!check.ok);
+        assert!(check.detail.contains("cannot create"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn render_shows_hint_only_on_failure() {
+        let pass = Check::pass("bun", "bun 1.1.0").render();
+        assert!(pass.contains('✓') && !pass.contains('→'));
+
+        let fail = Check::fail("bun", "missing", "install it").render();
+        assert!(fail.contains('✗') && fail.contains("→ install it"));
+    }
+}
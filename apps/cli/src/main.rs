@@ -4,6 +4,7 @@
 //! knowledge base with LLM enrichment.
 
 mod commands;
+mod doctor;
 
 use clap::Parser;
 use color_eyre::eyre::Result;
@@ -79,15 +79,11 @@ impl BrowseKbsScreen {
 
     pub(crate) fn handle_key(&mut self, code: KeyCode, _modifiers: KeyModifiers) {
         match code {
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.selected > 0 {
-                    self.selected -= 1;
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => {
+                self.selected -= 1;
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.selected + 1 < self.entries.len() {
-                    self.selected += 1;
-                }
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.entries.len() => {
+                self.selected += 1;
             }
             KeyCode::Char('r') => {
                 self.status = "Scanning for KBs...".to_string();
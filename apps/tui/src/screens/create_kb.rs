@@ -4,6 +4,8 @@ use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Paragraph};
 
+use contextbuilder_shared::CrawlMode;
+
 /// Which input field is focused.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Field {
@@ -15,7 +17,7 @@ enum Field {
 pub(crate) struct CreateKbScreen {
     url: String,
     name: String,
-    mode: String,
+    mode: CrawlMode,
     focused: Field,
     editing: bool,
     status: String,
@@ -26,7 +28,7 @@ impl CreateKbScreen {
         Self {
             url: String::new(),
             name: String::new(),
-            mode: "auto".to_string(),
+            mode: CrawlMode::default(),
             focused: Field::Url,
             editing: false,
             status: "Enter a documentation URL and press Enter to start.".to_string(),
@@ -127,11 +129,14 @@ impl CreateKbScreen {
                     self.next_field();
                 }
                 KeyCode::Backspace => {
-                    let field = self.current_field_mut();
-                    field.pop();
+                    if let Some(field) = self.current_field_mut() {
+                        field.pop();
+                    }
                 }
                 KeyCode::Char(c) => {
-                    self.current_field_mut().push(c);
+                    if let Some(field) = self.current_field_mut() {
+                        field.push(c);
+                    }
                 }
                 _ => {}
             }
@@ -155,11 +160,12 @@ impl CreateKbScreen {
         }
     }
 
-    fn current_field_mut(&mut self) -> &mut String {
+    /// The focused text field (`None` for the mode selector).
+    fn current_field_mut(&mut self) -> Option<&mut String> {
         match self.focused {
-            Field::Url => &mut self.url,
-            Field::Name => &mut self.name,
-            Field::Mode => &mut self.mode,
+            Field::Url => Some(&mut self.url),
+            Field::Name => Some(&mut self.name),
+            Field::Mode => None,
         }
     }
 
@@ -180,20 +186,18 @@ impl CreateKbScreen {
     }
 
     fn cycle_mode(&mut self) {
-        self.mode = match self.mode.as_str() {
-            "auto" => "llms-txt".to_string(),
-            "llms-txt" => "crawl".to_string(),
-            "crawl" => "auto".to_string(),
-            _ => "auto".to_string(),
+        self.mode = match self.mode {
+            CrawlMode::Auto => CrawlMode::LlmsTxt,
+            CrawlMode::LlmsTxt => CrawlMode::Crawl,
+            CrawlMode::Crawl => CrawlMode::Auto,
         };
     }
 
     fn cycle_mode_back(&mut self) {
-        self.mode = match self.mode.as_str() {
-            "auto" => "crawl".to_string(),
-            "llms-txt" => "auto".to_string(),
-            "crawl" => "llms-txt".to_string(),
-            _ => "auto".to_string(),
+        self.mode = match self.mode {
+            CrawlMode::Auto => CrawlMode::Crawl,
+            CrawlMode::LlmsTxt => CrawlMode::Auto,
+            CrawlMode::Crawl => CrawlMode::LlmsTxt,
         };
     }
 }
@@ -158,15 +158,11 @@ impl McpServerScreen {
                     Transport::Http => Transport::Stdio,
                 };
             }
-            KeyCode::Left => {
-                if self.config_target > 0 {
-                    self.config_target -= 1;
-                }
+            KeyCode::Left if self.config_target > 0 => {
+                self.config_target -= 1;
             }
-            KeyCode::Right => {
-                if self.config_target + 1 < CONFIG_TARGETS.len() {
-                    self.config_target += 1;
-                }
+            KeyCode::Right if self.config_target + 1 < CONFIG_TARGETS.len() => {
+                self.config_target += 1;
             }
             _ => {}
         }
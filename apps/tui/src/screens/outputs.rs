@@ -79,17 +79,13 @@ impl OutputsScreen {
 
     pub(crate) fn handle_key(&mut self, code: KeyCode, _modifiers: KeyModifiers) {
         match code {
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.selected > 0 {
-                    self.selected -= 1;
-                    self.load_artifact();
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => {
+                self.selected -= 1;
+                self.load_artifact();
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.selected + 1 < ARTIFACT_NAMES.len() {
-                    self.selected += 1;
-                    self.load_artifact();
-                }
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < ARTIFACT_NAMES.len() => {
+                self.selected += 1;
+                self.load_artifact();
             }
             KeyCode::Enter => {
                 self.load_artifact();
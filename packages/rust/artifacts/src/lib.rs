@@ -3,10 +3,10 @@
 //! Each generator accepts pre-computed data (enrichment results, TOC, page content)
 //! and produces deterministic output. Generators never call an LLM directly.
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
-use contextbuilder_shared::{Toc, TocEntry};
+use contextbuilder_shared::{LinkBase, Toc, TocEntry};
 
 /// Standard provenance comment inserted at the top of every generated artifact.
 fn provenance_comment(source_url: &str, tool_version: &str) -> String {
@@ -54,13 +54,17 @@ pub struct LlmsEntry {
 ///
 /// - [Title](url): description
 /// ```
+///
+/// `link_base` picks the link targets: source URLs, or the KB's own
+/// `docs/*.md` files for an llms.txt that works offline.
 pub fn generate_llms_txt(
     name: &str,
     summary: &str,
     toc: &Toc,
-    descriptions: &HashMap<String, String>,
+    descriptions: &BTreeMap<String, String>,
     source_url: &str,
     tool_version: &str,
+    link_base: LinkBase,
 ) -> String {
     let mut out = String::new();
     let _ = writeln!(out, "{}", provenance_comment(source_url, tool_version));
@@ -70,7 +74,7 @@ pub fn generate_llms_txt(
     let _ = writeln!(out, "> {summary}");
     let _ = writeln!(out);
 
-    write_llms_sections(&mut out, &toc.sections, descriptions, 2);
+    write_llms_sections(&mut out, &toc.sections, descriptions, link_base, 2);
 
     out
 }
@@ -79,7 +83,8 @@ pub fn generate_llms_txt(
 fn write_llms_sections(
     out: &mut String,
     entries: &[TocEntry],
-    descriptions: &HashMap<String, String>,
+    descriptions: &BTreeMap<String, String>,
+    link_base: LinkBase,
     heading_level: usize,
 ) {
     for entry in entries {
@@ -89,10 +94,7 @@ fn write_llms_sections(
                 .get(&entry.path)
                 .map(String::as_str)
                 .unwrap_or("Documentation page");
-            let url = entry
-                .source_url
-                .as_deref()
-                .unwrap_or(&entry.path);
+            let url = llms_link(entry, link_base);
             let _ = writeln!(out, "- [{}]({}): {}", entry.title, url, desc);
         } else {
             // Section heading
@@ -107,15 +109,24 @@ fn write_llms_sections(
                     .get(&entry.path)
                     .map(String::as_str)
                     .unwrap_or("Section overview");
-                let url = entry.source_url.as_deref().unwrap();
+                let url = llms_link(entry, link_base);
                 let _ = writeln!(out, "- [{}]({}): {}", entry.title, url, desc);
             }
 
-            write_llms_sections(out, &entry.children, descriptions, heading_level + 1);
+            write_llms_sections(out, &entry.children, descriptions, link_base, heading_level + 1);
         }
     }
 }
 
+/// Link target of a TOC entry in `llms.txt`.
+fn llms_link(entry: &TocEntry, link_base: LinkBase) -> String {
+    match link_base {
+        LinkBase::SourceUrls => entry.source_url.clone().unwrap_or_else(|| entry.path.clone()),
+        // llms.txt lives in artifacts/, next to docs/
+        LinkBase::LocalPaths => format!("../docs/{}.md", entry.path),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // llms-full.txt
 // ---------------------------------------------------------------------------
@@ -295,6 +306,28 @@ pub fn generate_do_dont(
     )
 }
 
+// ---------------------------------------------------------------------------
+// Custom task artifacts
+// ---------------------------------------------------------------------------
+
+/// Generate the artifact for a user-defined enrichment task.
+pub fn generate_custom(
+    title: &str,
+    name: &str,
+    source_url: &str,
+    enrichment_content: Option<&str>,
+    tool_version: &str,
+) -> String {
+    generate_passthrough_artifact(
+        title,
+        &format!("{title} — {name}"),
+        source_url,
+        enrichment_content,
+        "No content was generated for this task.",
+        tool_version,
+    )
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -385,8 +418,8 @@ mod tests {
         }
     }
 
-    fn sample_descriptions() -> HashMap<String, String> {
-        let mut m = HashMap::new();
+    fn sample_descriptions() -> BTreeMap<String, String> {
+        let mut m = BTreeMap::new();
         m.insert("getting-started".into(), "Quick start guide".into());
         m.insert("api".into(), "Full API reference".into());
         m.insert("api/auth".into(), "Authentication methods".into());
@@ -399,14 +432,14 @@ mod tests {
     #[test]
     fn llms_txt_contains_provenance() {
         let toc = Toc { sections: vec![] };
-        let out = generate_llms_txt("MyLib", "A library", &toc, &HashMap::new(), "https://example.com", "0.1.0");
+        let out = generate_llms_txt("MyLib", "A library", &toc, &BTreeMap::new(), "https://example.com", "0.1.0", LinkBase::SourceUrls);
         assert!(out.contains("<!-- Generated by ContextBuilder v0.1.0 from https://example.com -->"));
     }
 
     #[test]
     fn llms_txt_has_title_and_summary() {
         let toc = Toc { sections: vec![] };
-        let out = generate_llms_txt("MyLib", "A great library", &toc, &HashMap::new(), "https://example.com", "0.1.0");
+        let out = generate_llms_txt("MyLib", "A great library", &toc, &BTreeMap::new(), "https://example.com", "0.1.0", LinkBase::SourceUrls);
         assert!(out.contains("# MyLib"));
         assert!(out.contains("> A great library"));
     }
@@ -415,7 +448,7 @@ mod tests {
     fn llms_txt_generates_entries_from_toc() {
         let toc = sample_toc();
         let descs = sample_descriptions();
-        let out = generate_llms_txt("MyLib", "Summary", &toc, &descs, "https://example.com", "0.1.0");
+        let out = generate_llms_txt("MyLib", "Summary", &toc, &descs, "https://example.com", "0.1.0", LinkBase::SourceUrls);
 
         assert!(out.contains("- [Getting Started](https://example.com/getting-started): Quick start guide"));
         assert!(out.contains("## API Reference"));
@@ -434,10 +467,44 @@ mod tests {
                 children: vec![],
             }],
         };
-        let out = generate_llms_txt("Lib", "Sum", &toc, &HashMap::new(), "https://example.com", "0.1.0");
+        let out = generate_llms_txt("Lib", "Sum", &toc, &BTreeMap::new(), "https://example.com", "0.1.0", LinkBase::SourceUrls);
         assert!(out.contains("Documentation page"));
     }
 
+    #[test]
+    fn llms_txt_links_to_local_files() {
+        let toc = sample_toc();
+        let out = generate_llms_txt(
+            "MyLib",
+            "Summary",
+            &toc,
+            &sample_descriptions(),
+            "https://example.com",
+            "0.1.0",
+            LinkBase::LocalPaths,
+        );
+
+        assert!(out.contains("- [Getting Started](../docs/getting-started.md): Quick start guide"));
+        assert!(out.contains("- [Authentication](../docs/api/auth.md): Authentication methods"));
+        assert!(!out.contains("](https://"));
+        fn paths(entries: &[TocEntry], out: &mut Vec<String>) {
+            for entry in entries {
+                if entry.children.is_empty() || entry.source_url.is_some() {
+                    out.push(format!("../docs/{}.md", entry.path));
+                }
+                paths(&entry.children, out);
+            }
+        }
+        let mut expected = Vec::new();
+        paths(&toc.sections, &mut expected);
+        let linked: Vec<&str> = out
+            .lines()
+            .filter_map(|l| l.strip_prefix("- [")?.split_once("](")?.1.split_once(')'))
+            .map(|(target, _)| target)
+            .collect();
+        assert_eq!(linked, expected);
+    }
+
     // llms-full.txt tests -------------------------------------------------
 
     #[test]
@@ -559,6 +626,15 @@ mod tests {
         assert!(out.contains("No specific do's and don'ts"));
     }
 
+    // Custom task tests -----------------------------------------------------
+
+    #[test]
+    fn custom_with_enrichment() {
+        let out = generate_custom("FAQ", "Lib", "u", Some("Q: Why?\nA: Because."), "0.1.0");
+        assert!(out.contains("# FAQ — Lib"));
+        assert!(out.contains("Q: Why?"));
+    }
+
     // Helper tests ----------------------------------------------------------
 
     #[test]
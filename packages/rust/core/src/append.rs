@@ -0,0 +1,543 @@
+//! Append specific pages to an existing knowledge base.
+//!
+//! Fetches and converts only the given URLs, merges them into the KB's
+//! storage, docs, and TOC, then re-runs enrichment and re-emits artifacts.
+//! Pages already in the KB are read back from `docs/` and their enrichment is
+//! served from the cache, so only the new pages cost LLM calls.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tracing::{debug, info, instrument, warn};
+use url::Url;
+
+use contextbuilder_crawler::FetchedPage;
+use contextbuilder_shared::{
+    ArtifactLayout, ContextBuilderError, CrawlConfig, CustomTaskConfig, IdSource, KbId,
+    KbManifest, LinkBase, PageMeta, Result, SummaryFallback,
+};
+use contextbuilder_storage::{Storage, StorageBackend};
+
+use crate::assembler::{AssembleConfig, AssemblePage};
+use crate::enrichment::{self, EnrichmentConfig};
+use crate::pipeline::{ArtifactInputs, clock_for, language_allowed};
+use crate::progress::{Phase, ProgressEvent, ProgressExt, ProgressReporter};
+use crate::{split, toc};
+
+// ---------------------------------------------------------------------------
+// Config & result
+// ---------------------------------------------------------------------------
+
+/// Configuration for the `add_pages` pipeline.
+#[derive(Debug, Clone)]
+pub struct AddPagesConfig {
+    /// Path to the existing KB directory (contains manifest.json).
+    pub kb_path: PathBuf,
+    /// Page URLs to fetch and add.
+    pub urls: Vec<Url>,
+    /// Crawl configuration (timeouts, hash basis, drop selectors, ...).
+    pub crawl: CrawlConfig,
+    /// Tool version string.
+    pub tool_version: String,
+    /// OpenRouter model ID for enrichment.
+    pub model_id: String,
+    /// Bridge command (e.g., "bun").
+    pub bridge_cmd: String,
+    /// Bridge script path.
+    pub bridge_script: String,
+    /// Working directory for the bridge subprocess.
+    pub bridge_working_dir: String,
+    /// Seconds to wait for the bridge to signal readiness.
+    pub bridge_startup_timeout_secs: u64,
+    /// Store H2 section chunks for the added pages.
+    pub section_chunks: bool,
+    /// Produce reproducible output: fixed timestamps and path-ordered pages.
+    pub deterministic: bool,
+    /// User-defined enrichment tasks, each written as an extra artifact.
+    pub custom_tasks: Vec<CustomTaskConfig>,
+    /// Summaries to fall back on when the enrichment bridge can't start.
+    pub summary_fallback: SummaryFallback,
+    /// Where artifacts are written inside `artifacts/`.
+    pub artifact_layout: ArtifactLayout,
+    /// What the generated `llms.txt` links to.
+    pub llms_txt_links: LinkBase,
+    /// Also write a gzip copy of every artifact.
+    pub compress_artifacts: bool,
+}
+
+/// Result of the `add_pages` pipeline.
+#[derive(Debug)]
+pub struct AddPagesResult {
+    /// KB identifier.
+    pub kb_id: KbId,
+    /// Pages whose path was not in the KB before.
+    pub pages_added: usize,
+    /// Pages that replaced an existing page at the same path.
+    pub pages_replaced: usize,
+    /// URLs that could not be fetched or converted, or were filtered out.
+    pub pages_failed: usize,
+    /// Total page count after the append.
+    pub page_count: usize,
+    /// Total elapsed time.
+    pub elapsed: std::time::Duration,
+}
+
+// ---------------------------------------------------------------------------
+// Pipeline
+// ---------------------------------------------------------------------------
+
+/// Add the configured URLs to an existing KB.
+///
+/// 1. Load the manifest and open the KB's storage
+/// 2. Fetch each URL and upsert it (an existing path keeps its page ID)
+/// 3. Convert the fetched pages; read every other page from `docs/`
+/// 4. Rebuild the TOC and re-assemble the KB directory
+/// 5. Re-run enrichment (cached for existing pages) and rewrite artifacts
+#[instrument(skip_all, fields(kb_path = %config.kb_path.display(), urls = config.urls.len()))]
+pub async fn add_pages(
+    config: &AddPagesConfig,
+    progress: &dyn ProgressReporter,
+) -> Result<AddPagesResult> {
+    let start = Instant::now();
+
+    // --- Load manifest ---
+    progress.phase(Phase::LoadKb);
+    let manifest = crate::update::load_manifest(&config.kb_path)?;
+    manifest.ensure_current_schema(&config.kb_path)?;
+    let kb_id = manifest.id.clone();
+
+    if config.urls.is_empty() {
+        return Err(ContextBuilderError::validation("no page URLs given"));
+    }
+    if config.crawl.offline {
+        return Err(ContextBuilderError::offline(format!(
+            "fetch {} page(s)",
+            config.urls.len()
+        )));
+    }
+    enrichment::validate_custom_tasks(&config.custom_tasks)?;
+
+    let db_path = config.kb_path.join("indexes").join("contextbuilder.db");
+    let storage = Storage::open(&db_path).await?;
+    let existing_paths: Vec<String> = storage
+        .list_pages_by_kb(&kb_id.to_string())
+        .await?
+        .into_iter()
+        .map(|p| p.path)
+        .collect();
+
+    // --- Fetch ---
+    progress.phase(Phase::Fetch);
+    let client = crate::pipeline::build_page_client(&config.crawl)?;
+    let drop_selectors =
+        contextbuilder_crawler::DropSelectors::new(&config.crawl.drop_if_selector_matches)?;
+    let mut fetched_pages = Vec::new();
+    let mut pages_failed = 0;
+    let total = config.urls.len();
+
+    for (i, url) in config.urls.iter().enumerate() {
+        progress.event(ProgressEvent::PageFetched {
+            url: url.to_string(),
+            current: i + 1,
+            total,
+        });
+        match crate::pipeline::fetch_single_page(
+            &client,
+            url,
+            &kb_id.to_string(),
+            &config.crawl,
+        )
+        .await
+        {
+            Ok(page) => {
+                if let Some(selector) = drop_selectors.matching(&page.html) {
+                    debug!(%url, selector, "page matches drop selector, skipping");
+                    pages_failed += 1;
+                    continue;
+                }
+                fetched_pages.push(page);
+            }
+            Err(e) => {
+                warn!(%url, error = %e, "failed to fetch page");
+                progress.warning(format!("failed to fetch {url}: {e}"));
+                pages_failed += 1;
+            }
+        }
+    }
+
+    if fetched_pages.is_empty() {
+        return Err(ContextBuilderError::validation(
+            "none of the given pages could be fetched",
+        ));
+    }
+
+    let mut result = append_fetched(
+        config,
+        &manifest,
+        &storage,
+        fetched_pages,
+        &existing_paths,
+        progress,
+    )
+    .await?;
+    result.pages_failed += pages_failed;
+    result.elapsed = start.elapsed();
+
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: result.page_count,
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
+    info!(
+        pages_added = result.pages_added,
+        pages_replaced = result.pages_replaced,
+        pages_failed = result.pages_failed,
+        page_count = result.page_count,
+        elapsed_ms = result.elapsed.as_millis(),
+        "add-pages complete"
+    );
+
+    Ok(result)
+}
+
+/// Convert `fetched_pages` and merge them into the KB at `config.kb_path`:
+/// store them, rebuild the TOC, re-assemble, and rewrite the artifacts.
+/// `existing_paths` are the page paths stored before the fetch.
+pub(crate) async fn append_fetched(
+    config: &AddPagesConfig,
+    manifest: &KbManifest,
+    storage: &Storage,
+    mut fetched_pages: Vec<FetchedPage>,
+    existing_paths: &[String],
+    progress: &dyn ProgressReporter,
+) -> Result<AddPagesResult> {
+    let start = Instant::now();
+    let kb_id = manifest.id.clone();
+    let ids = IdSource::new(config.deterministic);
+    let mut pages_failed = 0;
+    let clock = clock_for(config.deterministic);
+    if clock.is_fixed() {
+        crate::pipeline::make_deterministic(&mut fetched_pages, clock);
+    }
+
+    // Conversion and enrichment draw from one budget
+    let budget = crate::pipeline::parallelism_budget(&config.crawl);
+
+    // --- Convert new pages ---
+    progress.phase(Phase::Convert);
+    let mut converted: HashMap<String, AssemblePage> = HashMap::new();
+    let total = fetched_pages.len();
+
+    let page_refs: Vec<_> = fetched_pages.iter().collect();
+    let conversions =
+        crate::pipeline::convert_pages(&page_refs, &config.crawl, budget.as_ref()).await;
+
+    for (i, (page, conversion)) in fetched_pages.iter().zip(conversions).enumerate() {
+        match conversion {
+            Ok(result) => {
+                if !language_allowed(result.language.as_deref(), &config.crawl.languages) {
+                    debug!(
+                        url = %page.meta.url,
+                        language = ?result.language,
+                        "page language not in target languages, skipping"
+                    );
+                    pages_failed += 1;
+                    continue;
+                }
+                progress.event(ProgressEvent::PageConverted {
+                    path: page.meta.path.clone(),
+                    current: i + 1,
+                    total,
+                });
+                converted.insert(
+                    page.meta.path.clone(),
+                    AssemblePage {
+                        path: page.meta.path.clone(),
+                        markdown: result.markdown,
+                        title: result.title,
+                    },
+                );
+            }
+            Err(e) => {
+                warn!(url = %page.meta.url, error = %e, "conversion failed, skipping page");
+                progress.warning(format!("conversion failed for {}: {e}", page.meta.url));
+                pages_failed += 1;
+            }
+        }
+    }
+
+    let pages_replaced = converted
+        .keys()
+        .filter(|path| existing_paths.contains(path))
+        .count();
+    let pages_added = converted.len() - pages_replaced;
+
+    // Split oversized pages, dropping the sub-pages a replaced page no
+    // longer has
+    let parents: HashSet<String> = converted.keys().cloned().collect();
+    if let Some(threshold) = config.crawl.split_large_pages {
+        let reserved: Vec<String> = existing_paths
+            .iter()
+            .filter(|path| !parents.contains(split::parent_path(path)))
+            .cloned()
+            .collect();
+        let pages = converted.drain().map(|(_, page)| page).collect();
+        let pages =
+            split::split_fetched_pages(pages, &mut fetched_pages, &reserved, threshold, ids);
+        converted.extend(pages.into_iter().map(|page| (page.path.clone(), page)));
+    }
+    for sub in storage.list_pages_by_kb(&kb_id.to_string()).await? {
+        if split::is_subpage(&sub)
+            && parents.contains(split::parent_path(&sub.path))
+            && !converted.contains_key(&sub.path)
+        {
+            storage.delete_page(&sub.id).await?;
+            storage.delete_chunks_for_page(&kb_id.to_string(), &sub.path).await?;
+            let md_path = config.kb_path.join("docs").join(format!("{}.md", sub.path));
+            let _ = std::fs::remove_file(&md_path);
+        }
+    }
+
+    if config.crawl.download_assets {
+        progress.phase(Phase::DownloadAssets);
+        let urls: HashMap<&str, &str> = fetched_pages
+            .iter()
+            .map(|p| (p.meta.path.as_str(), p.meta.url.as_str()))
+            .collect();
+        let pages = converted.values_mut().filter_map(|p| {
+            let url = urls.get(p.path.as_str()).copied()?;
+            Some((p, url))
+        });
+        let fetcher = crate::assets::AssetFetcher::new(&config.crawl)?;
+        crate::assets::localize_assets(pages, &config.kb_path, &fetcher).await?;
+    }
+
+    crate::pipeline::store_page_contents(storage, &fetched_pages, converted.values()).await?;
+
+    if config.section_chunks {
+        let pages: Vec<AssemblePage> = converted.values().cloned().collect();
+        crate::pipeline::store_chunks(storage, &kb_id.to_string(), &pages, ids).await?;
+    }
+
+    // --- Merge with existing pages ---
+    let (all_metas, assembled_pages) =
+        merge_pages(storage, &kb_id, &config.kb_path, &mut converted).await?;
+
+    // --- Rebuild TOC & re-assemble ---
+    progress.phase(Phase::BuildToc);
+    let toc = toc::build_toc(
+        &all_metas,
+        &[],
+        config.crawl.max_toc_depth,
+        config.crawl.prune_empty_sections,
+    );
+
+    progress.phase(Phase::Assemble);
+    let assemble_config = AssembleConfig {
+        kb_id: kb_id.clone(),
+        name: manifest.name.clone(),
+        source_url: manifest.source_url.clone(),
+        output_root: config
+            .kb_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+        tool_version: config.tool_version.clone(),
+        clock,
+        crawl_scope: manifest.crawl_scope.clone(),
+    };
+    crate::assembler::assemble(&assemble_config, &assembled_pages, &toc)?;
+    crate::pipeline::write_related(storage, &kb_id.to_string(), &all_metas, &config.kb_path)
+        .await?;
+
+    // --- Enrichment & artifacts ---
+    let enrich_config = EnrichmentConfig {
+        bridge_cmd: config.bridge_cmd.clone(),
+        bridge_script: config.bridge_script.clone(),
+        working_dir: config.bridge_working_dir.clone(),
+        model_id: config.model_id.clone(),
+        kb_name: manifest.name.clone(),
+        kb_source_url: manifest.source_url.clone(),
+        section_chunks: config.section_chunks,
+        startup_timeout_secs: config.bridge_startup_timeout_secs,
+        custom_tasks: config.custom_tasks.clone(),
+        fallback: config.summary_fallback,
+        budget,
+    };
+    crate::pipeline::enrich_and_write_artifacts(
+        &ArtifactInputs {
+            enrich: &enrich_config,
+            metas: &all_metas,
+            pages: &assembled_pages,
+            toc: &toc,
+            kb_path: &config.kb_path,
+            tool_version: &config.tool_version,
+            layout: config.artifact_layout,
+            llms_txt_links: config.llms_txt_links,
+            compress_artifacts: config.compress_artifacts,
+            clock,
+        },
+        storage,
+        progress,
+    )
+    .await?;
+
+    Ok(AddPagesResult {
+        kb_id,
+        pages_added,
+        pages_replaced,
+        pages_failed,
+        page_count: assembled_pages.len(),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Pair every stored page with its Markdown: freshly converted pages from
+/// `converted`, everything else from the KB's `docs/` directory.
+///
+/// Stored pages without a readable Markdown file are left out of the KB.
+pub(crate) async fn merge_pages(
+    storage: &dyn StorageBackend,
+    kb_id: &KbId,
+    kb_path: &Path,
+    converted: &mut HashMap<String, AssemblePage>,
+) -> Result<(Vec<PageMeta>, Vec<AssemblePage>)> {
+    let mut metas = Vec::new();
+    let mut pages = Vec::new();
+
+    for meta in storage.list_pages_by_kb(&kb_id.to_string()).await? {
+        let page = match converted.remove(&meta.path) {
+            Some(page) => page,
+            None => {
+                let md_path = kb_path.join("docs").join(format!("{}.md", meta.path));
+                match std::fs::read_to_string(&md_path) {
+                    Ok(markdown) => AssemblePage {
+                        path: meta.path.clone(),
+                        markdown,
+                        title: meta.title.clone().unwrap_or_else(|| meta.path.clone()),
+                    },
+                    Err(e) => {
+                        warn!(path = %meta.path, error = %e, "cannot read existing page, leaving it out");
+                        continue;
+                    }
+                }
+            }
+        };
+        metas.push(meta);
+        pages.push(page);
+    }
+
+    Ok((metas, pages))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+    use crate::test_support::{count_toc_entries, read_manifest, read_toc};
+
+    #[tokio::test]
+    async fn add_pages_requires_existing_kb() {
+        let config = AddPagesConfig {
+            kb_path: PathBuf::from("/nonexistent-kb"),
+            urls: vec![Url::parse("https://docs.example.com/new").unwrap()],
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: "false".into(),
+            bridge_script: String::new(),
+            bridge_working_dir: ".".into(),
+            bridge_startup_timeout_secs: 1,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+        };
+        let err = add_pages(&config, &SilentProgress).await.unwrap_err();
+        assert!(err.to_string().contains("manifest.json"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn add_pages_grows_seeded_kb_by_one() {
+        let dir = crate::test_support::temp_dir("add-pages");
+        let server = wiremock::MockServer::start().await;
+        let kb_path = crate::test_support::seed_kb(
+            &dir,
+            "Example",
+            &server.uri(),
+            &[("index", "Home", "# Home\n\nWelcome.\n")],
+        )
+        .await;
+
+        let before_manifest = read_manifest(&kb_path);
+        let before_toc = count_toc_entries(&read_toc(&kb_path).sections);
+
+        wiremock::Mock::given(wiremock::matchers::path("/setup"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Setup</h1><p>Install the tool.</p></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let config = AddPagesConfig {
+            kb_path: kb_path.clone(),
+            urls: vec![
+                Url::parse(&format!("{}/setup", server.uri())).unwrap(),
+                Url::parse(&format!("{}/missing", server.uri())).unwrap(),
+            ],
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+        };
+        let result = add_pages(&config, &SilentProgress).await.unwrap();
+        assert_eq!(result.pages_added, 1);
+        assert_eq!(result.pages_replaced, 0);
+        assert_eq!(result.pages_failed, 1);
+        assert_eq!(result.page_count, 2);
+
+        let manifest = read_manifest(&kb_path);
+        assert_eq!(manifest.page_count, before_manifest.page_count + 1);
+        assert!(manifest.artifacts.is_some(), "artifacts are re-emitted");
+        assert_eq!(count_toc_entries(&read_toc(&kb_path).sections), before_toc + 1);
+
+        // Existing page is carried over unchanged, the new one is converted
+        let home = std::fs::read_to_string(kb_path.join("docs/index.md")).unwrap();
+        assert_eq!(home, "# Home\n\nWelcome.\n");
+        let setup = std::fs::read_to_string(kb_path.join("docs/setup.md")).unwrap();
+        assert!(setup.contains("Install the tool."));
+        let llms_full = std::fs::read_to_string(kb_path.join("artifacts/llms-full.txt")).unwrap();
+        assert!(llms_full.contains("Install the tool.") && llms_full.contains("Welcome."));
+
+        // Re-adding the same URL replaces the page instead of duplicating it
+        let config = AddPagesConfig {
+            urls: config.urls[..1].to_vec(),
+            ..config
+        };
+        let again = add_pages(&config, &SilentProgress).await.unwrap();
+        assert_eq!((again.pages_added, again.pages_replaced), (0, 1));
+        assert_eq!(read_manifest(&kb_path).page_count, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
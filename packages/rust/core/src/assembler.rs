@@ -5,12 +5,12 @@
 
 use std::path::{Path, PathBuf};
 
-use chrono::Utc;
 use sha2::{Digest, Sha256};
 use tracing::{debug, info, instrument};
 
 use contextbuilder_shared::{
-    ContextBuilderError, KbId, KbManifest, Result, Toc, TocEntry, CURRENT_SCHEMA_VERSION,
+    Clock, ContextBuilderError, CrawlScope, KbId, KbManifest, Result, Toc, TocEntry,
+    CURRENT_SCHEMA_VERSION,
 };
 
 /// Output from a successful KB assembly.
@@ -48,6 +48,10 @@ pub struct AssembleConfig {
     pub output_root: PathBuf,
     /// Tool version string.
     pub tool_version: String,
+    /// Clock for manifest timestamps (fixed in deterministic mode).
+    pub clock: Clock,
+    /// Scope of the crawl that produced the pages, recorded in the manifest.
+    pub crawl_scope: Option<CrawlScope>,
 }
 
 /// Assemble a complete KB directory structure.
@@ -128,12 +132,7 @@ pub fn validate_kb(kb_path: &Path) -> Result<()> {
         ContextBuilderError::validation(format!("invalid manifest.json: {e}"))
     })?;
 
-    if manifest.schema_version != CURRENT_SCHEMA_VERSION {
-        return Err(ContextBuilderError::validation(format!(
-            "unsupported schema_version: {} (expected {})",
-            manifest.schema_version, CURRENT_SCHEMA_VERSION
-        )));
-    }
+    manifest.ensure_current_schema(kb_path)?;
 
     // Validate TOC
     let toc_content = std::fs::read_to_string(&toc_path)
@@ -158,6 +157,18 @@ pub struct ArtifactMeta {
     pub filename: String,
     pub sha256: String,
     pub size_bytes: usize,
+    /// The gzip copy written alongside, with `compress_artifacts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gzip: Option<CompressedArtifact>,
+}
+
+/// A compressed copy of an artifact. The checksum and size are of the
+/// compressed bytes, as stored.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CompressedArtifact {
+    pub filename: String,
+    pub sha256: String,
+    pub size_bytes: usize,
 }
 
 /// Metadata about the enrichment run.
@@ -168,59 +179,175 @@ pub struct EnrichmentMeta {
     pub total_tokens_out: u64,
     pub cache_hits: usize,
     pub cache_misses: usize,
+    /// Cache hits/misses per task type.
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub cache_by_task: std::collections::BTreeMap<String, crate::enrichment::CacheStats>,
+    /// Summaries and descriptions were extracted from the pages, not written
+    /// by an LLM (the bridge was unavailable).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub extractive: bool,
     pub completed_at: String,
 }
 
+/// Staging directory the next artifact set is written to before the swap.
+const ARTIFACTS_STAGING: &str = ".artifacts.staging";
+
+/// Where the previous artifact set is parked while the new one is swapped in.
+const ARTIFACTS_PREVIOUS: &str = ".artifacts.previous";
+
 /// Write artifact files to the KB artifacts directory and update the manifest.
 ///
-/// Each entry in `artifacts` is a `(filename, content)` pair.
-/// This function:
-/// 1. Writes each artifact file atomically (write to temp, then rename)
-/// 2. Updates `manifest.json` with artifact checksums and enrichment metadata
+/// Each entry in `artifacts` is a `(filename, content)` pair making up the
+/// complete artifact set. With `compress`, a gzip copy of each is written
+/// next to it as `<filename>.gz`. Assembly is all-or-nothing:
+/// 1. Every artifact is written to a staging directory
+/// 2. The staging directory replaces `artifacts/` with two renames
+/// 3. `manifest.json` is rewritten (temp + rename) last
+///
+/// If any write fails, the staging directory is discarded and the previous
+/// artifacts and manifest are left untouched. Leftovers from an interrupted
+/// run are cleaned up first, so a failed assembly can simply be retried.
 #[instrument(skip_all, fields(kb_path = %kb_path.display(), artifact_count = artifacts.len()))]
 pub fn assemble_artifacts(
     kb_path: &Path,
     artifacts: &[(&str, &str)],
+    compress: bool,
     enrichment_meta: &EnrichmentMeta,
+    clock: Clock,
 ) -> Result<Vec<ArtifactMeta>> {
-    let artifacts_dir = kb_path.join("artifacts");
-    std::fs::create_dir_all(&artifacts_dir)
-        .map_err(|e| ContextBuilderError::io(&artifacts_dir, e))?;
+    recover_artifacts(kb_path)?;
 
-    let mut metas = Vec::with_capacity(artifacts.len());
+    let staging = kb_path.join(ARTIFACTS_STAGING);
+    std::fs::create_dir_all(&staging).map_err(|e| ContextBuilderError::io(&staging, e))?;
 
-    for (filename, content) in artifacts {
-        let target = artifacts_dir.join(filename);
-        let temp = artifacts_dir.join(format!(".{filename}.tmp"));
+    let metas = match stage_artifacts(&staging, artifacts, compress) {
+        Ok(metas) => metas,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
 
-        // Write to temp file first
-        std::fs::write(&temp, content).map_err(|e| ContextBuilderError::io(&temp, e))?;
+    swap_artifacts(kb_path)?;
 
-        // Atomic rename
-        std::fs::rename(&temp, &target).map_err(|e| ContextBuilderError::io(&target, e))?;
+    // Update manifest
+    update_manifest(kb_path, &metas, enrichment_meta, clock)?;
+
+    info!(
+        count = metas.len(),
+        "artifact assembly complete"
+    );
+
+    Ok(metas)
+}
+
+/// Write every artifact into `staging`.
+fn stage_artifacts(
+    staging: &Path,
+    artifacts: &[(&str, &str)],
+    compress: bool,
+) -> Result<Vec<ArtifactMeta>> {
+    let mut metas = Vec::with_capacity(artifacts.len());
+
+    for (filename, content) in artifacts {
+        let target = staging.join(filename);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ContextBuilderError::io(parent, e))?;
+        }
+        std::fs::write(&target, content).map_err(|e| ContextBuilderError::io(&target, e))?;
 
         let mut hasher = Sha256::new();
         hasher.update(content.as_bytes());
         let hash = format!("{:x}", hasher.finalize());
 
-        debug!(file = %filename, size = content.len(), "wrote artifact");
+        debug!(file = %filename, size = content.len(), "staged artifact");
+
+        let gzip = if compress {
+            Some(stage_gzip(staging, filename, content)?)
+        } else {
+            None
+        };
 
         metas.push(ArtifactMeta {
             filename: (*filename).to_string(),
             sha256: hash,
             size_bytes: content.len(),
+            gzip,
         });
     }
 
-    // Update manifest
-    update_manifest(kb_path, &metas, enrichment_meta)?;
+    Ok(metas)
+}
 
-    info!(
-        count = metas.len(),
-        "artifact assembly complete"
-    );
+/// Write a gzip copy of `content` to `<staging>/<filename>.gz`. The header
+/// carries no timestamp, so identical content compresses to identical bytes.
+fn stage_gzip(staging: &Path, filename: &str, content: &str) -> Result<CompressedArtifact> {
+    use std::io::Write as _;
+
+    let gz_name = format!("{filename}.gz");
+    let target = staging.join(&gz_name);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    let bytes = encoder
+        .write_all(content.as_bytes())
+        .and_then(|()| encoder.finish())
+        .map_err(|e| ContextBuilderError::io(&target, e))?;
+    std::fs::write(&target, &bytes).map_err(|e| ContextBuilderError::io(&target, e))?;
+
+    debug!(file = %gz_name, size = bytes.len(), "staged compressed artifact");
+    Ok(CompressedArtifact {
+        filename: gz_name,
+        sha256: format!("{:x}", Sha256::digest(&bytes)),
+        size_bytes: bytes.len(),
+    })
+}
 
-    Ok(metas)
+/// Replace `artifacts/` with the staged set.
+fn swap_artifacts(kb_path: &Path) -> Result<()> {
+    let artifacts_dir = kb_path.join("artifacts");
+    let staging = kb_path.join(ARTIFACTS_STAGING);
+    let previous = kb_path.join(ARTIFACTS_PREVIOUS);
+
+    if artifacts_dir.exists() {
+        std::fs::rename(&artifacts_dir, &previous)
+            .map_err(|e| ContextBuilderError::io(&artifacts_dir, e))?;
+    }
+    if let Err(e) = std::fs::rename(&staging, &artifacts_dir) {
+        // Put the previous set back so the KB is never left without artifacts.
+        let _ = std::fs::rename(&previous, &artifacts_dir);
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(ContextBuilderError::io(&artifacts_dir, e));
+    }
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous).map_err(|e| ContextBuilderError::io(&previous, e))?;
+    }
+
+    debug!("swapped in staged artifacts");
+    Ok(())
+}
+
+/// Clean up after an assembly that was interrupted part-way.
+///
+/// A leftover staging directory is discarded. A parked previous set is
+/// restored when the swap never completed, and removed when it did.
+fn recover_artifacts(kb_path: &Path) -> Result<()> {
+    let artifacts_dir = kb_path.join("artifacts");
+    let staging = kb_path.join(ARTIFACTS_STAGING);
+    let previous = kb_path.join(ARTIFACTS_PREVIOUS);
+
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).map_err(|e| ContextBuilderError::io(&staging, e))?;
+    }
+    if previous.exists() {
+        if artifacts_dir.exists() {
+            std::fs::remove_dir_all(&previous)
+                .map_err(|e| ContextBuilderError::io(&previous, e))?;
+        } else {
+            std::fs::rename(&previous, &artifacts_dir)
+                .map_err(|e| ContextBuilderError::io(&artifacts_dir, e))?;
+        }
+        debug!("recovered from an interrupted artifact assembly");
+    }
+    Ok(())
 }
 
 /// Update `manifest.json` with artifact and enrichment metadata.
@@ -228,6 +355,7 @@ fn update_manifest(
     kb_path: &Path,
     artifacts: &[ArtifactMeta],
     enrichment_meta: &EnrichmentMeta,
+    clock: Clock,
 ) -> Result<()> {
     let manifest_path = kb_path.join("manifest.json");
 
@@ -240,9 +368,12 @@ fn update_manifest(
 
     manifest.artifacts = Some(serde_json::to_value(artifacts).unwrap_or_default());
     manifest.enrichment = Some(serde_json::to_value(enrichment_meta).unwrap_or_default());
-    manifest.updated_at = Utc::now();
+    manifest.updated_at = clock.now();
 
-    write_json(&manifest_path, &manifest)?;
+    let temp = kb_path.join(".manifest.json.tmp");
+    write_json(&temp, &manifest)?;
+    std::fs::rename(&temp, &manifest_path)
+        .map_err(|e| ContextBuilderError::io(&manifest_path, e))?;
     debug!("manifest updated with artifact metadata");
 
     Ok(())
@@ -271,7 +402,7 @@ fn create_dirs(kb_dir: &Path) -> Result<()> {
 
 /// Build the KB manifest.
 fn build_manifest(config: &AssembleConfig, page_count: usize) -> KbManifest {
-    let now = Utc::now();
+    let now = config.clock.now();
     KbManifest {
         schema_version: CURRENT_SCHEMA_VERSION,
         id: config.kb_id.clone(),
@@ -284,11 +415,12 @@ fn build_manifest(config: &AssembleConfig, page_count: usize) -> KbManifest {
         config: None,
         artifacts: None,
         enrichment: None,
+        crawl_scope: config.crawl_scope.clone(),
     }
 }
 
 /// Write a JSON file (pretty-printed).
-fn write_json<T: serde::Serialize>(path: &Path, data: &T) -> Result<()> {
+pub(crate) fn write_json<T: serde::Serialize>(path: &Path, data: &T) -> Result<()> {
     let json = serde_json::to_string_pretty(data).map_err(|e| {
         ContextBuilderError::validation(format!("JSON serialization failed: {e}"))
     })?;
@@ -339,6 +471,7 @@ fn validate_toc_paths(docs_dir: &Path, entries: &[TocEntry]) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use contextbuilder_shared::IdSource;
 
     fn temp_dir() -> PathBuf {
         let dir = std::env::temp_dir().join(format!(
@@ -356,6 +489,8 @@ mod tests {
             source_url: "https://docs.example.com".into(),
             output_root: output_root.into(),
             tool_version: "0.1.0-test".into(),
+            clock: Clock::System,
+            crawl_scope: None,
         }
     }
 
@@ -556,6 +691,8 @@ mod tests {
             cache_hits: 2,
             cache_misses: 3,
             completed_at: "2025-01-01T00:00:00Z".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
         };
 
         let artifacts = vec![
@@ -563,7 +700,14 @@ mod tests {
             ("rules.md", "# Rules\n\nBe nice.\n"),
         ];
 
-        let metas = assemble_artifacts(&result.kb_path, &artifacts, &enrichment_meta).unwrap();
+        let metas = assemble_artifacts(
+            &result.kb_path,
+            &artifacts,
+            false,
+            &enrichment_meta,
+            Clock::System,
+        )
+        .unwrap();
 
         assert_eq!(metas.len(), 2);
         assert_eq!(metas[0].filename, "llms.txt");
@@ -581,6 +725,57 @@ mod tests {
         let _ = std::fs::remove_dir_all(&tmp);
     }
 
+    #[test]
+    fn assemble_artifacts_writes_gzip_copies() {
+        use std::io::Read as _;
+
+        let tmp = temp_dir();
+        let result = assemble(&make_config(&tmp), &make_pages(), &make_toc()).unwrap();
+        let meta = EnrichmentMeta {
+            model: "m".into(),
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            completed_at: "2025-01-01T00:00:00Z".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
+        };
+        let full = "# Test\n\n".to_string() + &"Every page, in full. ".repeat(200);
+        let artifacts = [("llms.txt", "# Test\n"), ("llms-full.txt", full.as_str())];
+
+        let metas =
+            assemble_artifacts(&result.kb_path, &artifacts, true, &meta, Clock::System).unwrap();
+
+        let gzip = metas[1].gzip.as_ref().expect("gzip copy recorded");
+        assert_eq!(gzip.filename, "llms-full.txt.gz");
+        let bytes = std::fs::read(result.kb_path.join("artifacts/llms-full.txt.gz")).unwrap();
+        assert_eq!(gzip.size_bytes, bytes.len());
+        assert!(bytes.len() < full.len());
+        assert_eq!(gzip.sha256, format!("{:x}", Sha256::digest(&bytes)));
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, full);
+        assert_eq!(metas[1].sha256, format!("{:x}", Sha256::digest(decompressed.as_bytes())));
+
+        // The plaintext artifact stays, and the manifest records both
+        assert_eq!(
+            std::fs::read_to_string(result.kb_path.join("artifacts/llms-full.txt")).unwrap(),
+            full
+        );
+        let manifest_json = std::fs::read_to_string(result.kb_path.join("manifest.json")).unwrap();
+        let manifest: KbManifest = serde_json::from_str(&manifest_json).unwrap();
+        let recorded = &manifest.artifacts.unwrap()[1];
+        assert_eq!(recorded["filename"], "llms-full.txt");
+        assert_eq!(recorded["gzip"]["filename"], "llms-full.txt.gz");
+        assert_eq!(recorded["gzip"]["sha256"], gzip.sha256.as_str());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
     #[test]
     fn assemble_artifacts_updates_manifest() {
         let tmp = temp_dir();
@@ -597,10 +792,19 @@ mod tests {
             cache_hits: 2,
             cache_misses: 3,
             completed_at: "2025-01-01T00:00:00Z".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
         };
 
         let artifacts = vec![("llms.txt", "content")];
-        assemble_artifacts(&result.kb_path, &artifacts, &enrichment_meta).unwrap();
+        assemble_artifacts(
+            &result.kb_path,
+            &artifacts,
+            false,
+            &enrichment_meta,
+            Clock::System,
+        )
+        .unwrap();
 
         // Re-read manifest
         let manifest_json = std::fs::read_to_string(result.kb_path.join("manifest.json")).unwrap();
@@ -637,10 +841,19 @@ mod tests {
             cache_hits: 0,
             cache_misses: 0,
             completed_at: "now".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
         };
 
         let artifacts = vec![("test.md", "hello")];
-        assemble_artifacts(&result.kb_path, &artifacts, &enrichment_meta).unwrap();
+        assemble_artifacts(
+            &result.kb_path,
+            &artifacts,
+            false,
+            &enrichment_meta,
+            Clock::System,
+        )
+        .unwrap();
 
         // No temp files should remain
         let artifacts_dir = result.kb_path.join("artifacts");
@@ -651,4 +864,203 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&tmp);
     }
+
+    #[test]
+    fn assemble_artifacts_is_all_or_nothing() {
+        let tmp = temp_dir();
+        let result = assemble(&make_config(&tmp), &make_pages(), &make_toc()).unwrap();
+        let meta = EnrichmentMeta {
+            model: "m".into(),
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            completed_at: "now".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
+        };
+        assemble_artifacts(
+            &result.kb_path,
+            &[("llms.txt", "old")],
+            false,
+            &meta,
+            Clock::System,
+        )
+        .unwrap();
+        let manifest_before = std::fs::read_to_string(result.kb_path.join("manifest.json")).unwrap();
+
+        // The first artifact stages fine, the second cannot be written (its
+        // parent directory would have to be the file just staged).
+        let artifacts = vec![("llms.txt", "new"), ("llms.txt/rules.md", "rules")];
+        assert!(
+            assemble_artifacts(&result.kb_path, &artifacts, false, &meta, Clock::System).is_err()
+        );
+
+        let artifacts_dir = result.kb_path.join("artifacts");
+        assert_eq!(std::fs::read_to_string(artifacts_dir.join("llms.txt")).unwrap(), "old");
+        assert_eq!(std::fs::read_dir(&artifacts_dir).unwrap().count(), 1);
+        assert_eq!(
+            std::fs::read_to_string(result.kb_path.join("manifest.json")).unwrap(),
+            manifest_before
+        );
+        assert!(!result.kb_path.join(ARTIFACTS_STAGING).exists());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn assemble_artifacts_writes_cursor_layout() {
+        let tmp = temp_dir();
+        let result = assemble(&make_config(&tmp), &make_pages(), &make_toc()).unwrap();
+        let meta = EnrichmentMeta {
+            model: "m".into(),
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            completed_at: "now".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
+        };
+
+        let layout = contextbuilder_shared::ArtifactLayout::Cursor;
+        let names: Vec<String> = ["llms.txt", "rules.md"]
+            .iter()
+            .map(|file| layout.artifact_path(file, "Test KB"))
+            .collect();
+        let artifacts = vec![(names[0].as_str(), "llms"), (names[1].as_str(), "# Rules")];
+        let metas = assemble_artifacts(
+            &result.kb_path,
+            &artifacts,
+            false,
+            &meta,
+            Clock::System,
+        )
+        .unwrap();
+
+        let artifacts_dir = result.kb_path.join("artifacts");
+        assert_eq!(
+            std::fs::read_to_string(artifacts_dir.join(".cursor/rules/rules.md")).unwrap(),
+            "# Rules"
+        );
+        assert!(artifacts_dir.join("llms.txt").exists());
+        assert!(!artifacts_dir.join("rules.md").exists());
+        assert_eq!(metas[1].filename, ".cursor/rules/rules.md");
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn assemble_artifacts_recovers_from_interrupted_swap() {
+        let tmp = temp_dir();
+        let result = assemble(&make_config(&tmp), &make_pages(), &make_toc()).unwrap();
+        let meta = EnrichmentMeta {
+            model: "m".into(),
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            completed_at: "now".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
+        };
+        assemble_artifacts(
+            &result.kb_path,
+            &[("llms.txt", "old")],
+            false,
+            &meta,
+            Clock::System,
+        )
+        .unwrap();
+
+        // Simulate a crash between parking the old set and swapping in the new one.
+        let artifacts_dir = result.kb_path.join("artifacts");
+        std::fs::rename(&artifacts_dir, result.kb_path.join(ARTIFACTS_PREVIOUS)).unwrap();
+        std::fs::create_dir_all(result.kb_path.join(ARTIFACTS_STAGING)).unwrap();
+
+        recover_artifacts(&result.kb_path).unwrap();
+        assert_eq!(std::fs::read_to_string(artifacts_dir.join("llms.txt")).unwrap(), "old");
+        assert!(!result.kb_path.join(ARTIFACTS_STAGING).exists());
+        assert!(!result.kb_path.join(ARTIFACTS_PREVIOUS).exists());
+
+        // And a retry goes through.
+        assemble_artifacts(
+            &result.kb_path,
+            &[("llms.txt", "new")],
+            false,
+            &meta,
+            Clock::System,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(artifacts_dir.join("llms.txt")).unwrap(), "new");
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn deterministic_builds_are_byte_identical() {
+        let clock = Clock::Fixed(chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        let pages = make_pages();
+        let toc = make_toc();
+
+        let build = |tmp: &Path| {
+            let mut config = make_config(tmp);
+            config.clock = clock;
+            config.kb_id = KbId(IdSource::Seeded.uuid(&config.source_url));
+            let result = assemble(&config, &pages, &toc).unwrap();
+
+            let mut descriptions = std::collections::BTreeMap::new();
+            descriptions.insert("index".to_string(), "Landing page".to_string());
+            descriptions.insert("getting-started".to_string(), "First steps".to_string());
+            let llms_txt = contextbuilder_artifacts::generate_llms_txt(
+                &config.name,
+                "Summary",
+                &toc,
+                &descriptions,
+                &config.source_url,
+                &config.tool_version,
+                contextbuilder_shared::LinkBase::SourceUrls,
+            );
+            let meta = EnrichmentMeta {
+                model: "m".into(),
+                total_tokens_in: 0,
+                total_tokens_out: 0,
+                cache_hits: 0,
+                cache_misses: 0,
+                completed_at: clock.now().to_rfc3339(),
+                cache_by_task: Default::default(),
+                extractive: false,
+            };
+            assemble_artifacts(
+                &result.kb_path,
+                &[("llms.txt", llms_txt.as_str())],
+                false,
+                &meta,
+                clock,
+            )
+            .unwrap();
+            result.kb_path
+        };
+
+        let (tmp_a, tmp_b) = (temp_dir(), temp_dir());
+        let (kb_a, kb_b) = (build(&tmp_a), build(&tmp_b));
+        assert_eq!(kb_a.file_name(), kb_b.file_name());
+
+        for file in [
+            "manifest.json",
+            "toc.json",
+            "docs/index.md",
+            "docs/guide/installation.md",
+            "artifacts/llms.txt",
+        ] {
+            assert_eq!(
+                std::fs::read(kb_a.join(file)).unwrap(),
+                std::fs::read(kb_b.join(file)).unwrap(),
+                "{file} differs between builds"
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&tmp_a);
+        let _ = std::fs::remove_dir_all(&tmp_b);
+    }
 }
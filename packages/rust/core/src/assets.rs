@@ -0,0 +1,338 @@
+//! Local copies of page images, for a KB that renders offline.
+//!
+//! With `download_assets` on, every image a page references is fetched once,
+//! stored as `assets/<hash>.<ext>` (named by content, so identical images
+//! share a file), and the page's Markdown links to that file instead of the
+//! remote URL. Images that fail to download, exceed `max_asset_bytes`, or
+//! point at a private address keep their original link.
+//!
+//! Inline SVG diagrams converted with `inline_svg = "asset"` arrive as
+//! `data:image/svg+xml` URIs; they are decoded and saved the same way.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument, warn};
+use url::Url;
+
+use contextbuilder_shared::{ContextBuilderError, CrawlConfig, Result};
+
+use crate::assembler::AssemblePage;
+
+/// Directory inside the KB that holds downloaded assets.
+pub const ASSETS_DIR: &str = "assets";
+
+/// Outcome of [`localize_assets`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AssetReport {
+    /// Assets written to `assets/`.
+    pub downloaded: usize,
+    /// Image links now pointing at a local file.
+    pub rewritten: usize,
+    /// Images left remote (fetch failed, too large, or blocked).
+    pub skipped: usize,
+}
+
+/// Fetches assets with the crawl's HTTP settings.
+pub struct AssetFetcher {
+    client: reqwest::Client,
+    max_bytes: u64,
+    /// Allow localhost/private IPs (for integration tests with mock servers).
+    allow_localhost: bool,
+}
+
+impl AssetFetcher {
+    /// Build a fetcher that sends the crawl's auth and honors its timeouts.
+    pub fn new(crawl: &CrawlConfig) -> Result<Self> {
+        if crawl.offline {
+            return Err(ContextBuilderError::offline("download assets"));
+        }
+        Ok(Self {
+            client: crate::pipeline::build_page_client(crawl)?,
+            max_bytes: crawl.max_asset_bytes,
+            allow_localhost: false,
+        })
+    }
+
+    /// Allow fetching from localhost/private IPs (for integration tests).
+    #[cfg(test)]
+    pub(crate) fn allow_localhost(mut self) -> Self {
+        self.allow_localhost = true;
+        self
+    }
+
+    /// Download `url`, returning its body and a file extension for it.
+    async fn fetch(&self, url: &Url) -> Result<(Vec<u8>, &'static str)> {
+        if !self.allow_localhost && contextbuilder_crawler::is_ssrf_target(url) {
+            return Err(ContextBuilderError::validation(format!(
+                "{url}: blocked by SSRF protection"
+            )));
+        }
+
+        let mut response = self
+            .client
+            .get(url.as_str())
+            .send()
+            .await
+            .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ContextBuilderError::http_status(url, status.as_u16(), status));
+        }
+        let too_large = || {
+            ContextBuilderError::validation(format!(
+                "{url}: larger than {} bytes",
+                self.max_bytes
+            ))
+        };
+        if response.content_length().is_some_and(|len| len > self.max_bytes) {
+            return Err(too_large());
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+
+        // Read in chunks so a missing or lying Content-Length can't exceed the cap
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > self.max_bytes {
+                return Err(too_large());
+            }
+        }
+
+        Ok((body, extension(url, content_type.as_deref())))
+    }
+}
+
+/// Download the images referenced by `pages` into `<kb_path>/assets/` and
+/// rewrite their links to the local copies. Each page comes with the URL it
+/// was fetched from, used to resolve relative image paths.
+#[instrument(skip_all, fields(kb = %kb_path.display()))]
+pub async fn localize_assets<'a>(
+    pages: impl IntoIterator<Item = (&'a mut AssemblePage, &'a str)>,
+    kb_path: &Path,
+    fetcher: &AssetFetcher,
+) -> Result<AssetReport> {
+    let assets_dir = kb_path.join(ASSETS_DIR);
+    std::fs::create_dir_all(&assets_dir).map_err(|e| ContextBuilderError::io(&assets_dir, e))?;
+
+    let mut report = AssetReport::default();
+    // Absolute URL → file name under assets/ (None if it could not be fetched)
+    let mut fetched: HashMap<String, Option<String>> = HashMap::new();
+
+    for (page, source) in pages {
+        let Ok(base) = Url::parse(source) else {
+            continue;
+        };
+
+        // Resolve every image once per page, fetching URLs not seen before
+        let mut local: HashMap<String, String> = HashMap::new();
+        for src in contextbuilder_markdown::image_sources(&page.markdown) {
+            if let Some(svg) = inline_svg(&src) {
+                let file = store(&assets_dir, svg.as_bytes(), "svg", &mut report)?;
+                local.insert(src, format!("{}{ASSETS_DIR}/{file}", up_to_kb_root(&page.path)));
+                continue;
+            }
+            let Some(url) = base.join(&src).ok().filter(|u| u.scheme() != "data") else {
+                continue;
+            };
+            if !fetched.contains_key(url.as_str()) {
+                let file = match fetcher.fetch(&url).await {
+                    Ok((body, ext)) => {
+                        let file = store(&assets_dir, &body, ext, &mut report)?;
+                        debug!(%url, file, "downloaded asset");
+                        Some(file)
+                    }
+                    Err(e) => {
+                        warn!(%url, error = %e, "asset not downloaded, keeping remote link");
+                        None
+                    }
+                };
+                fetched.insert(url.to_string(), file);
+            }
+            match &fetched[url.as_str()] {
+                Some(file) => {
+                    local.insert(src, format!("{}{ASSETS_DIR}/{file}", up_to_kb_root(&page.path)));
+                }
+                None => report.skipped += 1,
+            }
+        }
+
+        if !local.is_empty() {
+            page.markdown = contextbuilder_markdown::rewrite_image_sources(&page.markdown, |src| {
+                let new_src = local.get(src).cloned();
+                report.rewritten += usize::from(new_src.is_some());
+                new_src
+            });
+        }
+    }
+
+    info!(
+        downloaded = report.downloaded,
+        rewritten = report.rewritten,
+        skipped = report.skipped,
+        "assets localized"
+    );
+    Ok(report)
+}
+
+/// Write `body` to `assets_dir` under its content name, unless an identical
+/// asset is already there, and return the file name.
+fn store(assets_dir: &Path, body: &[u8], ext: &str, report: &mut AssetReport) -> Result<String> {
+    let file = format!("{}.{ext}", hex_prefix(&Sha256::digest(body)));
+    let path = assets_dir.join(&file);
+    if !path.exists() {
+        std::fs::write(&path, body).map_err(|e| ContextBuilderError::io(&path, e))?;
+        report.downloaded += 1;
+    }
+    Ok(file)
+}
+
+/// The SVG markup in a `data:image/svg+xml` image source from an inline SVG.
+fn inline_svg(src: &str) -> Option<String> {
+    let encoded = src.strip_prefix(contextbuilder_markdown::SVG_DATA_URI_PREFIX)?;
+    percent_encoding::percent_decode_str(encoded)
+        .decode_utf8()
+        .ok()
+        .map(|svg| svg.into_owned())
+}
+
+/// The relative prefix from `docs/<page_path>.md` back to the KB root.
+fn up_to_kb_root(page_path: &str) -> String {
+    "../".repeat(page_path.split('/').count())
+}
+
+/// First 16 hex digits of a digest: plenty to tell a KB's assets apart.
+fn hex_prefix(digest: &[u8]) -> String {
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// File extension for an asset: the URL's own if it is a known image type,
+/// otherwise derived from the Content-Type.
+fn extension(url: &Url, content_type: Option<&str>) -> &'static str {
+    const KNOWN: [&str; 9] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "ico", "bmp"];
+    let from_url = url
+        .path()
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .and_then(|ext| KNOWN.iter().find(|known| **known == ext).copied());
+    if let Some(ext) = from_url {
+        return ext;
+    }
+
+    let mime = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|m| m.trim().to_ascii_lowercase());
+    match mime.as_deref() {
+        Some("image/png") => "png",
+        Some("image/jpeg") => "jpg",
+        Some("image/gif") => "gif",
+        Some("image/svg+xml") => "svg",
+        Some("image/webp") => "webp",
+        Some("image/avif") => "avif",
+        Some("image/x-icon" | "image/vnd.microsoft.icon") => "ico",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn page(path: &str, markdown: &str) -> AssemblePage {
+        AssemblePage {
+            path: path.into(),
+            markdown: markdown.into(),
+            title: path.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn images_are_downloaded_deduplicated_and_relinked() {
+        let server = MockServer::start().await;
+        let png = b"\x89PNG\r\n\x1a\nfake".to_vec();
+        for route in ["/img/logo.png", "/static/logo-copy.png"] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(png.clone()))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/img/huge.gif"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 64]))
+            .mount(&server)
+            .await;
+
+        let kb = temp_dir("assets");
+        let mut pages = [
+            page("guide/intro", "# Intro\n\n![Logo](../img/logo.png)\n\n![Big](/img/huge.gif)\n"),
+            page("index", "# Home\n\n![Same logo](/static/logo-copy.png)\n"),
+        ];
+        let base = server.uri();
+        let sources = [format!("{base}/guide/intro"), format!("{base}/")];
+
+        let crawl = CrawlConfig {
+            max_asset_bytes: 32,
+            ..CrawlConfig::default()
+        };
+        let fetcher = AssetFetcher::new(&crawl).unwrap().allow_localhost();
+        let pairs = pages.iter_mut().zip(sources.iter().map(String::as_str));
+        let report = localize_assets(pairs, &kb, &fetcher).await.unwrap();
+        assert_eq!(report, AssetReport { downloaded: 1, rewritten: 2, skipped: 1 });
+
+        let file = format!("{}.png", hex_prefix(&Sha256::digest(&png)));
+        assert_eq!(std::fs::read(kb.join("assets").join(&file)).unwrap(), png);
+        assert_eq!(
+            pages[0].markdown,
+            format!("# Intro\n\n![Logo](../../assets/{file})\n\n![Big](/img/huge.gif)\n")
+        );
+        assert_eq!(pages[1].markdown, format!("# Home\n\n![Same logo](../assets/{file})\n"));
+
+        let _ = std::fs::remove_dir_all(&kb);
+    }
+
+    #[tokio::test]
+    async fn inline_svg_data_uris_are_saved_as_files() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><title>Flow</title></svg>"#;
+        let src = format!(
+            "{}{}",
+            contextbuilder_markdown::SVG_DATA_URI_PREFIX,
+            percent_encoding::utf8_percent_encode(svg, percent_encoding::NON_ALPHANUMERIC)
+        );
+        let kb = temp_dir("assets-svg");
+        let mut pages = [page("arch", &format!("# Arch\n\n![Flow]({src})\n"))];
+
+        let fetcher = AssetFetcher::new(&CrawlConfig::default()).unwrap();
+        let pairs = pages.iter_mut().zip(["https://docs.example.com/arch"]);
+        let report = localize_assets(pairs, &kb, &fetcher).await.unwrap();
+        assert_eq!(report, AssetReport { downloaded: 1, rewritten: 1, skipped: 0 });
+
+        let file = format!("{}.svg", hex_prefix(&Sha256::digest(svg.as_bytes())));
+        assert_eq!(std::fs::read_to_string(kb.join("assets").join(&file)).unwrap(), svg);
+        assert_eq!(pages[0].markdown, format!("# Arch\n\n![Flow](../assets/{file})\n"));
+
+        let _ = std::fs::remove_dir_all(&kb);
+    }
+
+    #[test]
+    fn extension_prefers_url_then_content_type() {
+        let url = Url::parse("https://docs.example.com/a/logo.SVG").unwrap();
+        assert_eq!(extension(&url, Some("image/png")), "svg");
+        let url = Url::parse("https://docs.example.com/image?id=3").unwrap();
+        assert_eq!(extension(&url, Some("image/jpeg; charset=binary")), "jpg");
+        assert_eq!(extension(&url, None), "bin");
+    }
+}
@@ -0,0 +1,66 @@
+//! Section-level chunking of converted pages.
+//!
+//! Splits each page at its H2 headings so sections can be stored, enriched,
+//! and searched individually (`chunks` table).
+
+use contextbuilder_shared::{Chunk, IdSource};
+
+/// Heading level at which pages are split into chunks.
+pub const CHUNK_HEADING_LEVEL: u8 = 2;
+
+/// Split a page's Markdown into section chunks, with ids from `ids`.
+pub fn build_chunks(kb_id: &str, parent_path: &str, markdown: &str, ids: IdSource) -> Vec<Chunk> {
+    contextbuilder_markdown::split_sections(markdown, CHUNK_HEADING_LEVEL)
+        .into_iter()
+        .enumerate()
+        .map(|(i, section)| Chunk {
+            id: ids.uuid(&format!("{kb_id}/{parent_path}#{i}")).to_string(),
+            kb_id: kb_id.to_string(),
+            parent_path: parent_path.to_string(),
+            anchor: section.anchor,
+            title: section.title,
+            ordinal: i as u32,
+            content: section.content,
+            summary: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "---\nsource_url: \"https://example.com/guide\"\ntitle: \"Guide\"\n---\n\
+# Guide\n\nOverview.\n\n## Installation\n\nInstall it.\n\n### Linux\n\napt install.\n\n\
+## Configuration\n\nConfigure it.\n\n## Configuration\n\nAgain.\n";
+
+    #[test]
+    fn splits_multi_section_page_at_h2() {
+        let chunks = build_chunks("kb", "guide", PAGE, IdSource::Random);
+
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.parent_path == "guide" && c.kb_id == "kb"));
+
+        let ordinals: Vec<u32> = chunks.iter().map(|c| c.ordinal).collect();
+        assert_eq!(ordinals, vec![0, 1, 2, 3]);
+
+        assert_eq!(chunks[0].anchor, None);
+        assert_eq!(chunks[0].content, "# Guide\n\nOverview.");
+
+        assert_eq!(chunks[1].anchor.as_deref(), Some("installation"));
+        assert_eq!(
+            chunks[1].content,
+            "## Installation\n\nInstall it.\n\n### Linux\n\napt install."
+        );
+
+        assert_eq!(chunks[2].anchor.as_deref(), Some("configuration"));
+        assert_eq!(chunks[3].anchor.as_deref(), Some("configuration-1"));
+        assert_eq!(chunks[3].content, "## Configuration\n\nAgain.");
+    }
+
+    #[test]
+    fn frontmatter_is_not_chunked() {
+        let chunks = build_chunks("kb", "guide", PAGE, IdSource::Random);
+        assert!(chunks.iter().all(|c| !c.content.contains("source_url")));
+    }
+}
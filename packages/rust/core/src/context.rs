@@ -0,0 +1,251 @@
+//! Token-bounded page context for KB-level enrichment tasks.
+//!
+//! KB-level artifacts (`SKILL.md`, rules, style, do/don't) see the pages
+//! themselves, not just their summaries. Rather than truncating every page
+//! to the same length, [`pack_pages`] ranks pages by how likely they are to
+//! describe the library as a whole (shallow in the TOC, overview-style
+//! titles, substantive length) and fills a token budget in that order.
+
+use std::collections::HashMap;
+use serde::Serialize;
+
+use contextbuilder_shared::{PageMeta, Toc, TocEntry};
+
+/// Default token budget for the packed pages of a KB-level task.
+pub const KB_CONTEXT_TOKENS: usize = 24_000;
+
+/// Most tokens a single page may take, so one huge page cannot crowd out
+/// the rest.
+const MAX_PAGE_TOKENS: usize = 1_500;
+
+/// Pages that would get less than this much content are left out.
+const MIN_PAGE_TOKENS: usize = 100;
+
+/// Tokens charged per page for its path, title, and JSON framing.
+const PAGE_OVERHEAD_TOKENS: usize = 10;
+
+/// Appended to pages cut short.
+const TRUNCATION_MARKER: &str = "\n\n[... truncated ...]";
+
+/// Pages shorter than this (in characters) are mostly stubs or redirects.
+const MIN_USEFUL_CHARS: usize = 200;
+
+/// Title words of pages that explain the library as a whole.
+const OVERVIEW_TITLE_WORDS: [&str; 12] = [
+    "introduction",
+    "overview",
+    "getting started",
+    "quick start",
+    "quickstart",
+    "install",
+    "tutorial",
+    "guide",
+    "concepts",
+    "basics",
+    "configuration",
+    "usage",
+];
+
+/// Title words of pages that rarely help describe how to use the library.
+const LOW_VALUE_TITLE_WORDS: [&str; 7] = [
+    "changelog",
+    "release notes",
+    "releases",
+    "license",
+    "privacy",
+    "contributors",
+    "sponsors",
+];
+
+/// A page selected for the context, with its content cut to fit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackedPage {
+    /// Page path within the KB.
+    pub path: String,
+    /// Page title.
+    pub title: Option<String>,
+    /// Page Markdown, truncated to its share of the budget.
+    pub content: String,
+}
+
+/// Select and order `pages` for a KB-level prompt, within `budget_tokens`.
+///
+/// Pages are taken highest priority first; each gets at most
+/// [`MAX_PAGE_TOKENS`], and the last one that fits may be truncated further.
+/// The estimated token count of the result never exceeds the budget.
+pub fn pack_pages(
+    pages: &[(PageMeta, String)],
+    toc: &Toc,
+    budget_tokens: usize,
+) -> Vec<PackedPage> {
+    let depths = toc_depths(toc);
+    let mut ranked: Vec<(f64, &PageMeta, &str)> = pages
+        .iter()
+        .map(|(meta, content)| {
+            let depth = depths.get(meta.path.as_str()).copied();
+            (priority(meta, content, depth), meta, content.as_str())
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    let mut remaining = budget_tokens;
+    let mut packed = Vec::new();
+    for (_, meta, content) in ranked {
+        let overhead = PAGE_OVERHEAD_TOKENS
+            + estimate_tokens(&meta.path)
+            + meta.title.as_deref().map_or(0, estimate_tokens);
+        let Some(available) = remaining.checked_sub(overhead) else {
+            continue;
+        };
+        let wanted = estimate_tokens(content).min(MAX_PAGE_TOKENS);
+        let granted = wanted.min(available);
+        if granted < wanted && granted < MIN_PAGE_TOKENS {
+            continue;
+        }
+
+        remaining -= overhead + granted;
+        let content = if granted < estimate_tokens(content) {
+            let max_chars = granted.saturating_sub(estimate_tokens(TRUNCATION_MARKER)) * 4;
+            let end = content.char_indices().nth(max_chars).map_or(content.len(), |(i, _)| i);
+            format!("{}{TRUNCATION_MARKER}", &content[..end])
+        } else {
+            content.to_string()
+        };
+        packed.push(PackedPage {
+            path: meta.path.clone(),
+            title: meta.title.clone(),
+            content,
+        });
+    }
+    packed
+}
+
+/// Rough token count of `text` (four characters per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Relevance of a page for describing the whole KB (higher is better).
+fn priority(meta: &PageMeta, content: &str, depth: Option<usize>) -> f64 {
+    let depth_score = depth.map_or(0.5, |d| 3.0 / (1.0 + d as f64));
+
+    let title = meta.title.as_deref().unwrap_or(&meta.path).to_lowercase();
+    let title_score = if OVERVIEW_TITLE_WORDS.iter().any(|w| title.contains(w)) {
+        2.0
+    } else if LOW_VALUE_TITLE_WORDS.iter().any(|w| title.contains(w)) {
+        -2.0
+    } else {
+        0.0
+    };
+
+    let len = content.chars().count();
+    let length_score = if len < MIN_USEFUL_CHARS {
+        -1.0
+    } else {
+        (len as f64 / 1000.0).ln_1p().min(2.0)
+    };
+
+    depth_score + title_score + length_score
+}
+
+/// Nesting depth of every page in the TOC (top-level entries are depth 0).
+fn toc_depths(toc: &Toc) -> HashMap<&str, usize> {
+    fn walk<'a>(entries: &'a [TocEntry], depth: usize, out: &mut HashMap<&'a str, usize>) {
+        for entry in entries {
+            out.entry(entry.path.as_str()).or_insert(depth);
+            walk(&entry.children, depth + 1, out);
+        }
+    }
+    let mut depths = HashMap::new();
+    walk(&toc.sections, 0, &mut depths);
+    depths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(path: &str, title: &str, chars: usize) -> (PageMeta, String) {
+        let meta = PageMeta {
+            id: path.into(),
+            kb_id: "kb".into(),
+            url: format!("https://docs.example.com/{path}"),
+            path: path.into(),
+            title: Some(title.into()),
+            content_hash: String::new(),
+            fetched_at: chrono::Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        (meta, "word ".repeat(chars / 5))
+    }
+
+    fn entry(path: &str, children: Vec<TocEntry>) -> TocEntry {
+        TocEntry {
+            title: path.into(),
+            path: path.into(),
+            source_url: None,
+            summary: None,
+            children,
+        }
+    }
+
+    #[test]
+    fn packer_respects_budget_and_prefers_priority_pages() {
+        let pages = vec![
+            page("reference/internals/changelog", "Changelog", 20_000),
+            page("reference/internals/hooks", "Hooks", 3_000),
+            page("getting-started", "Getting Started", 3_000),
+            page("reference", "API Reference", 3_000),
+            page("stub", "Moved", 50),
+        ];
+        let toc = Toc {
+            sections: vec![
+                entry("getting-started", vec![]),
+                entry(
+                    "reference",
+                    vec![entry(
+                        "reference/internals",
+                        vec![
+                            entry("reference/internals/hooks", vec![]),
+                            entry("reference/internals/changelog", vec![]),
+                        ],
+                    )],
+                ),
+                entry("stub", vec![]),
+            ],
+        };
+
+        let budget = 1_700;
+        let packed = pack_pages(&pages, &toc, budget);
+
+        let used: usize = packed
+            .iter()
+            .map(|p| {
+                PAGE_OVERHEAD_TOKENS
+                    + estimate_tokens(&p.path)
+                    + p.title.as_deref().map_or(0, estimate_tokens)
+                    + estimate_tokens(&p.content)
+            })
+            .sum();
+        assert!(used <= budget, "used {used} of {budget}");
+
+        let paths: Vec<&str> = packed.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths[..2], ["getting-started", "reference"]);
+        assert!(!paths.contains(&"reference/internals/changelog"));
+        assert_eq!(packed[0].content, pages[2].1);
+    }
+
+    #[test]
+    fn packer_caps_single_pages() {
+        let pages = vec![page("guide", "Guide", 40_000)];
+        let toc = Toc { sections: vec![] };
+
+        let packed = pack_pages(&pages, &toc, KB_CONTEXT_TOKENS);
+        assert_eq!(packed.len(), 1);
+        assert!(packed[0].content.ends_with(TRUNCATION_MARKER));
+        assert!(estimate_tokens(&packed[0].content) <= MAX_PAGE_TOKENS);
+    }
+}
@@ -2,26 +2,45 @@
 //!
 //! Spawns the TypeScript bridge subprocess, sends enrichment tasks
 //! via JSON-lines stdin/stdout protocol, and caches results in storage.
+//! If the bridge can't start, [`SummaryFallback::Extractive`] fills in
+//! summaries from the pages themselves (see [`crate::extractive`]).
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
 use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 use sha2::{Digest, Sha256};
-use tracing::{error, info, instrument, warn};
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, instrument, warn};
 
-use contextbuilder_shared::{ContextBuilderError, PageMeta, Result, Toc};
-use contextbuilder_storage::Storage;
+use contextbuilder_shared::{
+    Chunk, ContextBuilderError, CustomTaskConfig, PageMeta, Result, SummaryFallback, TaskContext,
+    Toc,
+};
+use contextbuilder_storage::StorageBackend;
+
+use crate::context;
+use crate::extractive;
+use crate::progress::{Phase, ProgressExt, ProgressReporter};
 
 // ---------------------------------------------------------------------------
 // Protocol types (mirroring the TS schemas)
 // ---------------------------------------------------------------------------
 
+/// Bridge protocol versions this orchestrator can talk to. The bridge
+/// reports its version in the `ready` message (`PROTOCOL_VERSION` in
+/// `schemas.ts`); bump both together on incompatible changes.
+pub const SUPPORTED_PROTOCOL_VERSIONS: RangeInclusive<u32> = 2..=2;
+
 /// Task types matching the TS bridge protocol.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskType {
     SummarizePage,
+    SummarizeSection,
     GenerateDescription,
     GenerateSkillMd,
     GenerateRules,
@@ -29,6 +48,8 @@ pub enum TaskType {
     GenerateDoDont,
     GenerateLlmsTxt,
     GenerateLlmsFullTxt,
+    /// A user-defined task from `[[custom_tasks]]`.
+    Custom,
 }
 
 impl TaskType {
@@ -36,6 +57,7 @@ impl TaskType {
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::SummarizePage => "summarize_page",
+            Self::SummarizeSection => "summarize_section",
             Self::GenerateDescription => "generate_description",
             Self::GenerateSkillMd => "generate_skill_md",
             Self::GenerateRules => "generate_rules",
@@ -43,6 +65,7 @@ impl TaskType {
             Self::GenerateDoDont => "generate_do_dont",
             Self::GenerateLlmsTxt => "generate_llms_txt",
             Self::GenerateLlmsFullTxt => "generate_llms_full_txt",
+            Self::Custom => "custom",
         }
     }
 }
@@ -67,6 +90,16 @@ pub struct EnrichmentTask {
     pub kb_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kb_source_url: Option<String>,
+    /// Name of a custom task (`task_type = "custom"` only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_name: Option<String>,
+    /// Prompt for a custom task (`task_type = "custom"` only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// The page's own meta description, as a starting point for
+    /// `generate_description`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 /// Request message sent to the bridge.
@@ -74,7 +107,7 @@ pub struct EnrichmentTask {
 #[serde(tag = "type")]
 enum RequestMessage {
     #[serde(rename = "enrich")]
-    Enrich { id: String, task: EnrichmentTask },
+    Enrich { id: String, task: Box<EnrichmentTask> },
     #[serde(rename = "shutdown")]
     Shutdown,
 }
@@ -84,7 +117,11 @@ enum RequestMessage {
 #[serde(tag = "type")]
 enum ResponseMessage {
     #[serde(rename = "ready")]
-    Ready,
+    Ready {
+        /// Absent for bridges that predate version negotiation.
+        #[serde(default)]
+        protocol_version: Option<u32>,
+    },
     #[serde(rename = "result")]
     Result {
         id: String,
@@ -116,9 +153,11 @@ pub struct BridgeResult {
 #[derive(Debug, Clone, Default)]
 pub struct EnrichmentResults {
     /// Page summaries keyed by page path.
-    pub summaries: HashMap<String, String>,
+    pub summaries: BTreeMap<String, String>,
     /// Page descriptions keyed by page path.
-    pub descriptions: HashMap<String, String>,
+    pub descriptions: BTreeMap<String, String>,
+    /// Section summaries keyed by `path#anchor` (plain `path` for the preamble).
+    pub section_summaries: BTreeMap<String, String>,
     /// KB-level artifact content.
     pub skill_md: Option<String>,
     pub rules: Option<String>,
@@ -126,6 +165,8 @@ pub struct EnrichmentResults {
     pub do_dont: Option<String>,
     pub llms_txt: Option<String>,
     pub llms_full_txt: Option<String>,
+    /// Custom task output keyed by task name.
+    pub custom_artifacts: BTreeMap<String, String>,
     /// Total token usage.
     pub total_tokens_in: u64,
     pub total_tokens_out: u64,
@@ -135,6 +176,42 @@ pub struct EnrichmentResults {
     pub cache_hits: usize,
     /// Number of cache misses (LLM calls made).
     pub cache_misses: usize,
+    /// Cache hits and misses per task type (`summarize_page`,
+    /// `summarize_section`, `custom:<name>`, ...).
+    pub cache_by_task: BTreeMap<String, CacheStats>,
+    /// Summaries and descriptions come from the extractive fallback.
+    pub extractive: bool,
+}
+
+/// Enrichment cache hits and misses for one task type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache (0 when there were none).
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl EnrichmentResults {
+    fn record_hit(&mut self, task: &str) {
+        self.cache_hits += 1;
+        self.cache_by_task.entry(task.to_string()).or_default().hits += 1;
+    }
+
+    fn record_miss(&mut self, task: &str) {
+        self.cache_misses += 1;
+        self.cache_by_task.entry(task.to_string()).or_default().misses += 1;
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -156,6 +233,17 @@ pub struct EnrichmentConfig {
     pub kb_name: String,
     /// KB source URL for context.
     pub kb_source_url: String,
+    /// Also summarize each stored section chunk of every page.
+    pub section_chunks: bool,
+    /// Seconds to wait for the bridge's ready message before giving up.
+    pub startup_timeout_secs: u64,
+    /// User-defined KB-level tasks, run after the built-in artifacts.
+    pub custom_tasks: Vec<CustomTaskConfig>,
+    /// What to do when the bridge cannot start.
+    pub fallback: SummaryFallback,
+    /// Parallelism budget shared with the other pipeline phases; the bridge
+    /// holds one unit while it runs.
+    pub budget: Option<Arc<Semaphore>>,
 }
 
 // ---------------------------------------------------------------------------
@@ -200,41 +288,22 @@ impl BridgeHandle {
 
         let reader = BufReader::new(stdout);
 
-        let mut handle = Self {
+        // Wait for ready signal
+        let reader = match wait_for_ready(reader, Duration::from_secs(config.startup_timeout_secs)) {
+            Ok(reader) => reader,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
             child,
             stdin,
             reader,
             request_counter: 0,
-        };
-
-        // Wait for ready signal
-        handle.wait_for_ready()?;
-
-        Ok(handle)
-    }
-
-    /// Wait for the bridge to send its "ready" message.
-    fn wait_for_ready(&mut self) -> Result<()> {
-        let mut line = String::new();
-        self.reader
-            .read_line(&mut line)
-            .map_err(|e| ContextBuilderError::Enrichment(format!("bridge read error: {e}")))?;
-
-        let msg: ResponseMessage = serde_json::from_str(line.trim()).map_err(|e| {
-            ContextBuilderError::Enrichment(format!(
-                "invalid bridge ready message: {e} (got: {line})"
-            ))
-        })?;
-
-        match msg {
-            ResponseMessage::Ready => {
-                info!("bridge is ready");
-                Ok(())
-            }
-            _ => Err(ContextBuilderError::Enrichment(format!(
-                "expected ready message, got: {line}"
-            ))),
-        }
+        })
     }
 
     /// Send an enrichment task and wait for the response.
@@ -244,7 +313,7 @@ impl BridgeHandle {
 
         let request = RequestMessage::Enrich {
             id: id.clone(),
-            task,
+            task: Box::new(task),
         };
 
         let json = serde_json::to_string(&request).map_err(|e| {
@@ -290,7 +359,7 @@ impl BridgeHandle {
                 id: _,
                 error,
             } => Err(ContextBuilderError::Enrichment(error)),
-            ResponseMessage::Ready => Err(ContextBuilderError::Enrichment(
+            ResponseMessage::Ready { .. } => Err(ContextBuilderError::Enrichment(
                 "unexpected ready message during enrichment".into(),
             )),
         }
@@ -319,6 +388,82 @@ impl BridgeHandle {
 // Public orchestrator API
 // ---------------------------------------------------------------------------
 
+/// Wait up to `timeout` for the bridge to send its "ready" message.
+///
+/// The first line is read on a helper thread so a bridge that hangs on
+/// startup cannot block the caller forever; the reader is handed back once
+/// the bridge is ready. On error the caller must kill the child, which also
+/// unblocks the helper thread.
+fn wait_for_ready(
+    mut reader: BufReader<std::process::ChildStdout>,
+    timeout: Duration,
+) -> Result<BufReader<std::process::ChildStdout>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).map(|_| line);
+        let _ = tx.send((reader, read));
+    });
+
+    let (reader, read) = rx.recv_timeout(timeout).map_err(|_| {
+        ContextBuilderError::Enrichment(format!(
+            "bridge did not become ready within {}s. Check that bun is installed \
+             (`bun --version`) and dependencies are installed (`bun install`).",
+            timeout.as_secs()
+        ))
+    })?;
+
+    let line =
+        read.map_err(|e| ContextBuilderError::Enrichment(format!("bridge read error: {e}")))?;
+
+    let msg: ResponseMessage = serde_json::from_str(line.trim()).map_err(|e| {
+        ContextBuilderError::Enrichment(format!(
+            "invalid bridge ready message: {e} (got: {line})"
+        ))
+    })?;
+
+    match msg {
+        ResponseMessage::Ready { protocol_version } => {
+            check_protocol_version(protocol_version)?;
+            info!(?protocol_version, "bridge is ready");
+            Ok(reader)
+        }
+        _ => Err(ContextBuilderError::Enrichment(format!(
+            "expected ready message, got: {line}"
+        ))),
+    }
+}
+
+/// Reject a bridge whose protocol version is outside
+/// [`SUPPORTED_PROTOCOL_VERSIONS`], saying which side needs updating.
+fn check_protocol_version(version: Option<u32>) -> Result<()> {
+    let (min, max) = (
+        *SUPPORTED_PROTOCOL_VERSIONS.start(),
+        *SUPPORTED_PROTOCOL_VERSIONS.end(),
+    );
+    let supported = if min == max {
+        format!("v{min}")
+    } else {
+        format!("v{min}-v{max}")
+    };
+    match version {
+        Some(v) if SUPPORTED_PROTOCOL_VERSIONS.contains(&v) => Ok(()),
+        Some(v) if v > max => Err(ContextBuilderError::Enrichment(format!(
+            "enrichment bridge speaks protocol v{v}, but this ContextBuilder only supports \
+             {supported}. Upgrade ContextBuilder to match the bridge."
+        ))),
+        Some(v) => Err(ContextBuilderError::Enrichment(format!(
+            "enrichment bridge speaks protocol v{v}, but this ContextBuilder requires \
+             {supported}. Update the bridge (`git pull && bun install`)."
+        ))),
+        None => Err(ContextBuilderError::Enrichment(format!(
+            "enrichment bridge did not report a protocol version (it predates version \
+             negotiation); this ContextBuilder requires {supported}. Update the bridge \
+             (`git pull && bun install`)."
+        ))),
+    }
+}
+
 /// Compute a prompt hash for cache keying.
 fn prompt_hash(content: &str, task_type: &str) -> String {
     let mut hasher = Sha256::new();
@@ -327,6 +472,20 @@ fn prompt_hash(content: &str, task_type: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Cache keys (`artifact_type`, prompt hash) of the per-page tasks run for a
+/// page with Markdown `content` and section `chunks`.
+pub(crate) fn page_cache_keys(content: &str, chunks: &[Chunk]) -> Vec<(String, String)> {
+    let mut keys: Vec<(String, String)> = ["summarize_page", "generate_description"]
+        .into_iter()
+        .map(|task| (task.to_string(), prompt_hash(content, task)))
+        .collect();
+    keys.extend(chunks.iter().map(|chunk| {
+        let task = TaskType::SummarizeSection.as_str();
+        (task.to_string(), prompt_hash(&chunk.content, task))
+    }));
+    keys
+}
+
 /// Run the full enrichment pipeline.
 ///
 /// 1. Spawn bridge
@@ -339,8 +498,8 @@ pub async fn run_enrichment(
     config: &EnrichmentConfig,
     pages: &[(PageMeta, String)], // (meta, markdown_content)
     toc: &Toc,
-    storage: &Storage,
-    progress: &dyn EnrichmentProgress,
+    storage: &dyn StorageBackend,
+    progress: &dyn ProgressReporter,
 ) -> Result<EnrichmentResults> {
     let mut results = EnrichmentResults {
         model: config.model_id.clone(),
@@ -351,18 +510,39 @@ pub async fn run_enrichment(
         .first()
         .map(|(m, _)| m.kb_id.as_str())
         .unwrap_or("unknown");
-    let total_tasks = pages.len() * 2 + 4; // summaries + descriptions + 4 KB artifacts
+
+    // Load section chunks up front so the task total includes them
+    let mut chunks: Vec<Chunk> = Vec::new();
+    if config.section_chunks {
+        for (meta, _) in pages {
+            chunks.extend(storage.list_chunks_by_page(kb_id, &meta.path).await?);
+        }
+    }
+
+    // summaries + section summaries + descriptions + 4 KB artifacts + custom tasks
+    let total_tasks = pages.len() * 2 + chunks.len() + 4 + config.custom_tasks.len();
     let mut completed = 0;
 
     // --- Spawn bridge ---
-    progress.phase("Starting enrichment bridge");
-    let mut bridge = BridgeHandle::spawn(config)?;
+    let _budget = crate::pipeline::take_budget(config.budget.as_ref()).await;
+    progress.phase(Phase::StartBridge);
+    let mut bridge = match BridgeHandle::spawn(config) {
+        Ok(bridge) => bridge,
+        Err(e) if config.fallback == SummaryFallback::Extractive => {
+            warn!(error = %e, "enrichment bridge unavailable, using extractive summaries");
+            progress.warning(format!(
+                "enrichment bridge unavailable ({e}); using extractive summaries"
+            ));
+            return Ok(extractive_results(pages, &chunks));
+        }
+        Err(e) => return Err(e),
+    };
 
     // --- Phase 1: Summarize each page ---
-    progress.phase("Summarizing pages");
+    progress.phase(Phase::SummarizePages);
     for (meta, content) in pages {
         completed += 1;
-        progress.task_progress(completed, total_tasks, &format!("Summarizing: {}", meta.path));
+        progress.task(TaskType::SummarizePage.as_str(), &meta.path, completed, total_tasks);
 
         let hash = prompt_hash(content, "summarize_page");
 
@@ -372,7 +552,7 @@ pub async fn run_enrichment(
             .await?
         {
             results.summaries.insert(meta.path.clone(), cached);
-            results.cache_hits += 1;
+            results.record_hit("summarize_page");
             continue;
         }
 
@@ -386,13 +566,16 @@ pub async fn run_enrichment(
             pages_json: None,
             kb_name: Some(config.kb_name.clone()),
             kb_source_url: Some(config.kb_source_url.clone()),
+            task_name: None,
+            instructions: None,
+            description: None,
         };
 
         match bridge.send_task(task) {
             Ok(result) => {
                 results.total_tokens_in += result.tokens_in;
                 results.total_tokens_out += result.tokens_out;
-                results.cache_misses += 1;
+                results.record_miss("summarize_page");
 
                 // Cache result
                 let _ = storage
@@ -409,15 +592,91 @@ pub async fn run_enrichment(
             }
             Err(e) => {
                 warn!(path = %meta.path, error = %e, "page summarization failed");
+                progress.warning(format!("page summarization failed for {}: {e}", meta.path));
             }
         }
     }
 
+    // --- Phase 1b: Summarize sections ---
+    if !chunks.is_empty() {
+        progress.phase(Phase::SummarizeSections);
+        let page_titles: HashMap<&str, &str> = pages
+            .iter()
+            .filter_map(|(m, _)| m.title.as_deref().map(|t| (m.path.as_str(), t)))
+            .collect();
+
+        for chunk in &chunks {
+            completed += 1;
+            let key = chunk_key(chunk);
+            let task_type = TaskType::SummarizeSection.as_str();
+            progress.task(task_type, &key, completed, total_tasks);
+
+            let hash = prompt_hash(&chunk.content, task_type);
+
+            let summary = if let Some(cached) = storage
+                .get_enrichment_cache(kb_id, task_type, &hash, &config.model_id)
+                .await?
+            {
+                results.record_hit(task_type);
+                cached
+            } else {
+                let page_title = page_titles.get(chunk.parent_path.as_str()).copied();
+                let title = match (page_title, chunk.title.as_deref()) {
+                    (Some(page), Some(section)) => Some(format!("{page} — {section}")),
+                    (page, section) => section.or(page).map(str::to_string),
+                };
+
+                let task = EnrichmentTask {
+                    task_type: task_type.into(),
+                    content: Some(truncate_content(&chunk.content, 8_000)),
+                    title,
+                    source_url: None,
+                    toc_json: None,
+                    summaries_json: None,
+                    pages_json: None,
+                    kb_name: Some(config.kb_name.clone()),
+                    kb_source_url: Some(config.kb_source_url.clone()),
+                    task_name: None,
+                    instructions: None,
+                    description: None,
+                };
+
+                match bridge.send_task(task) {
+                    Ok(result) => {
+                        results.total_tokens_in += result.tokens_in;
+                        results.total_tokens_out += result.tokens_out;
+                        results.record_miss(task_type);
+
+                        let _ = storage
+                            .set_enrichment_cache(
+                                kb_id,
+                                task_type,
+                                &hash,
+                                &config.model_id,
+                                &result.text,
+                            )
+                            .await;
+
+                        result.text
+                    }
+                    Err(e) => {
+                        warn!(section = %key, error = %e, "section summarization failed");
+                        progress.warning(format!("section summarization failed for {key}: {e}"));
+                        continue;
+                    }
+                }
+            };
+
+            storage.set_chunk_summary(&chunk.id, &summary).await?;
+            results.section_summaries.insert(key, summary);
+        }
+    }
+
     // --- Phase 2: Generate descriptions ---
-    progress.phase("Generating descriptions");
+    progress.phase(Phase::GenerateDescriptions);
     for (meta, content) in pages {
         completed += 1;
-        progress.task_progress(completed, total_tasks, &format!("Describing: {}", meta.path));
+        progress.task(TaskType::GenerateDescription.as_str(), &meta.path, completed, total_tasks);
 
         let hash = prompt_hash(content, "generate_description");
 
@@ -426,7 +685,7 @@ pub async fn run_enrichment(
             .await?
         {
             results.descriptions.insert(meta.path.clone(), cached);
-            results.cache_hits += 1;
+            results.record_hit("generate_description");
             continue;
         }
 
@@ -440,13 +699,16 @@ pub async fn run_enrichment(
             pages_json: None,
             kb_name: Some(config.kb_name.clone()),
             kb_source_url: Some(config.kb_source_url.clone()),
+            task_name: None,
+            instructions: None,
+            description: meta.description.clone(),
         };
 
         match bridge.send_task(task) {
             Ok(result) => {
                 results.total_tokens_in += result.tokens_in;
                 results.total_tokens_out += result.tokens_out;
-                results.cache_misses += 1;
+                results.record_miss("generate_description");
 
                 let _ = storage
                     .set_enrichment_cache(
@@ -464,25 +726,26 @@ pub async fn run_enrichment(
             }
             Err(e) => {
                 warn!(path = %meta.path, error = %e, "description generation failed");
+                progress.warning(format!("description generation failed for {}: {e}", meta.path));
+                if let Some(description) = &meta.description {
+                    results.descriptions.insert(meta.path.clone(), description.clone());
+                }
             }
         }
     }
 
     // --- Phase 3: KB-level artifacts ---
+    progress.phase(Phase::GenerateKbArtifacts);
     let summaries_json = serde_json::to_string(&results.summaries).unwrap_or_default();
     let toc_json = serde_json::to_string(toc).unwrap_or_default();
 
-    // Build a truncated pages JSON for KB-level tasks
-    let pages_for_context: Vec<serde_json::Value> = pages
-        .iter()
-        .map(|(meta, content)| {
-            serde_json::json!({
-                "path": meta.path,
-                "title": meta.title,
-                "content": truncate_content(content, 4_000),
-            })
-        })
-        .collect();
+    // Pack the most relevant pages into a bounded context for KB-level tasks
+    let pages_for_context = context::pack_pages(pages, toc, context::KB_CONTEXT_TOKENS);
+    debug!(
+        packed = pages_for_context.len(),
+        total = pages.len(),
+        "packed pages for KB-level context"
+    );
     let pages_json = serde_json::to_string(&pages_for_context).unwrap_or_default();
 
     // Generate each KB-level artifact
@@ -495,11 +758,7 @@ pub async fn run_enrichment(
 
     for (task_type, task_type_str) in &kb_tasks {
         completed += 1;
-        progress.task_progress(
-            completed,
-            total_tasks,
-            &format!("Generating: {task_type_str}"),
-        );
+        progress.task(task_type_str, &config.kb_name, completed, total_tasks);
 
         let hash = prompt_hash(&summaries_json, task_type_str);
 
@@ -508,7 +767,7 @@ pub async fn run_enrichment(
             .await?
         {
             set_kb_artifact(&mut results, *task_type, cached);
-            results.cache_hits += 1;
+            results.record_hit(task_type_str);
             continue;
         }
 
@@ -522,13 +781,16 @@ pub async fn run_enrichment(
             pages_json: Some(pages_json.clone()),
             kb_name: Some(config.kb_name.clone()),
             kb_source_url: Some(config.kb_source_url.clone()),
+            task_name: None,
+            instructions: None,
+            description: None,
         };
 
         match bridge.send_task(task) {
             Ok(result) => {
                 results.total_tokens_in += result.tokens_in;
                 results.total_tokens_out += result.tokens_out;
-                results.cache_misses += 1;
+                results.record_miss(task_type_str);
 
                 let _ = storage
                     .set_enrichment_cache(
@@ -544,12 +806,63 @@ pub async fn run_enrichment(
             }
             Err(e) => {
                 error!(task = task_type_str, error = %e, "KB artifact generation failed");
+                progress.warning(format!("{task_type_str} failed: {e}"));
+            }
+        }
+    }
+
+    // --- Phase 4: Custom tasks ---
+    if !config.custom_tasks.is_empty() {
+        progress.phase(Phase::CustomTasks);
+    }
+    for custom in &config.custom_tasks {
+        completed += 1;
+        progress.task(&format!("custom:{}", custom.name), &config.kb_name, completed, total_tasks);
+
+        let task = custom_task(custom, config, &toc_json, &summaries_json, &pages_json);
+        // Key on the full request so edited instructions or context miss the cache
+        let artifact_type = format!("custom:{}", custom.name);
+        let hash = prompt_hash(
+            &serde_json::to_string(&task).unwrap_or_default(),
+            &artifact_type,
+        );
+
+        if let Some(cached) = storage
+            .get_enrichment_cache(kb_id, &artifact_type, &hash, &config.model_id)
+            .await?
+        {
+            results.custom_artifacts.insert(custom.name.clone(), cached);
+            results.record_hit(&artifact_type);
+            continue;
+        }
+
+        match bridge.send_task(task) {
+            Ok(result) => {
+                results.total_tokens_in += result.tokens_in;
+                results.total_tokens_out += result.tokens_out;
+                results.record_miss(&artifact_type);
+
+                let _ = storage
+                    .set_enrichment_cache(
+                        kb_id,
+                        &artifact_type,
+                        &hash,
+                        &config.model_id,
+                        &result.text,
+                    )
+                    .await;
+
+                results.custom_artifacts.insert(custom.name.clone(), result.text);
+            }
+            Err(e) => {
+                error!(task = %custom.name, error = %e, "custom task failed");
+                progress.warning(format!("custom task {} failed: {e}", custom.name));
             }
         }
     }
 
     // --- Shutdown bridge ---
-    progress.phase("Shutting down enrichment bridge");
+    progress.phase(Phase::StopBridge);
     bridge.shutdown()?;
 
     info!(
@@ -559,10 +872,117 @@ pub async fn run_enrichment(
         tokens_out = results.total_tokens_out,
         "enrichment complete"
     );
+    for (task, stats) in &results.cache_by_task {
+        info!(
+            task = %task,
+            hits = stats.hits,
+            misses = stats.misses,
+            hit_ratio = format!("{:.2}", stats.hit_ratio()),
+            "enrichment cache"
+        );
+    }
 
     Ok(results)
 }
 
+/// Summaries and descriptions taken from the pages' opening sentences; a
+/// page's meta description, when it has one, is its description instead.
+///
+/// Nothing is cached, so the next build with a working bridge enriches
+/// every page with the LLM.
+fn extractive_results(pages: &[(PageMeta, String)], chunks: &[Chunk]) -> EnrichmentResults {
+    let mut results = EnrichmentResults {
+        model: "extractive".into(),
+        extractive: true,
+        ..Default::default()
+    };
+    for (meta, content) in pages {
+        if let Some(summary) = extractive::summarize(content, extractive::SUMMARY_SENTENCES) {
+            results.summaries.insert(meta.path.clone(), summary);
+        }
+        // The author's own meta description beats the opening sentences
+        let description = meta.description.clone().or_else(|| {
+            extractive::summarize(content, extractive::DESCRIPTION_SENTENCES)
+        });
+        if let Some(description) = description {
+            results.descriptions.insert(meta.path.clone(), description);
+        }
+    }
+    for chunk in chunks {
+        if let Some(summary) =
+            extractive::summarize(&chunk.content, extractive::SUMMARY_SENTENCES)
+        {
+            results.section_summaries.insert(chunk_key(chunk), summary);
+        }
+    }
+    info!(pages = results.summaries.len(), "extractive summaries generated");
+    results
+}
+
+/// Key for a chunk in [`EnrichmentResults::section_summaries`].
+fn chunk_key(chunk: &Chunk) -> String {
+    match &chunk.anchor {
+        Some(anchor) => format!("{}#{anchor}", chunk.parent_path),
+        None => chunk.parent_path.clone(),
+    }
+}
+
+/// Build the bridge request for a custom task, sending only the context it asked for.
+fn custom_task(
+    custom: &CustomTaskConfig,
+    config: &EnrichmentConfig,
+    toc_json: &str,
+    summaries_json: &str,
+    pages_json: &str,
+) -> EnrichmentTask {
+    let wants = |ctx: TaskContext| custom.context.contains(&ctx);
+    EnrichmentTask {
+        task_type: TaskType::Custom.as_str().into(),
+        content: None,
+        title: None,
+        source_url: None,
+        toc_json: wants(TaskContext::Toc).then(|| toc_json.to_string()),
+        summaries_json: wants(TaskContext::Summaries).then(|| summaries_json.to_string()),
+        pages_json: wants(TaskContext::Pages).then(|| pages_json.to_string()),
+        kb_name: Some(config.kb_name.clone()),
+        kb_source_url: Some(config.kb_source_url.clone()),
+        task_name: Some(custom.name.clone()),
+        instructions: Some(custom.instructions.clone()),
+        description: None,
+    }
+}
+
+/// Check custom task definitions before any work is done.
+///
+/// Each task must be individually valid, and names and output files must be
+/// unique and not shadow a built-in artifact.
+pub fn validate_custom_tasks(tasks: &[CustomTaskConfig]) -> Result<()> {
+    let mut names = std::collections::HashSet::new();
+    let mut outputs = std::collections::HashSet::new();
+    for task in tasks {
+        task.validate()?;
+        if contextbuilder_artifacts::ARTIFACT_NAMES.contains(&task.output.as_str()) {
+            return Err(ContextBuilderError::config(format!(
+                "custom task '{}': output '{}' collides with a built-in artifact",
+                task.name, task.output
+            )));
+        }
+        if !names.insert(task.name.as_str()) {
+            return Err(ContextBuilderError::config(format!(
+                "duplicate custom task name '{}'",
+                task.name
+            )));
+        }
+        if !outputs.insert(task.output.as_str()) {
+            return Err(ContextBuilderError::config(format!(
+                "duplicate custom task output '{}'",
+                task.output
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Set a KB-level artifact in the results.
 fn set_kb_artifact(results: &mut EnrichmentResults, task_type: TaskType, text: String) {
     match task_type {
@@ -586,26 +1006,6 @@ fn truncate_content(content: &str, max_chars: usize) -> String {
     }
 }
 
-// ---------------------------------------------------------------------------
-// Progress trait
-// ---------------------------------------------------------------------------
-
-/// Progress callback for enrichment operations.
-pub trait EnrichmentProgress: Send + Sync {
-    /// Called when entering a new phase.
-    fn phase(&self, name: &str);
-    /// Task-level progress within the current phase.
-    fn task_progress(&self, current: usize, total: usize, detail: &str);
-}
-
-/// No-op enrichment progress.
-pub struct SilentEnrichmentProgress;
-
-impl EnrichmentProgress for SilentEnrichmentProgress {
-    fn phase(&self, _name: &str) {}
-    fn task_progress(&self, _current: usize, _total: usize, _detail: &str) {}
-}
-
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -645,6 +1045,7 @@ mod tests {
     #[test]
     fn task_type_as_str() {
         assert_eq!(TaskType::SummarizePage.as_str(), "summarize_page");
+        assert_eq!(TaskType::SummarizeSection.as_str(), "summarize_section");
         assert_eq!(TaskType::GenerateSkillMd.as_str(), "generate_skill_md");
         assert_eq!(TaskType::GenerateDoDont.as_str(), "generate_do_dont");
     }
@@ -653,7 +1054,7 @@ mod tests {
     fn request_message_serializes_correctly() {
         let msg = RequestMessage::Enrich {
             id: "req-1".into(),
-            task: EnrichmentTask {
+            task: Box::new(EnrichmentTask {
                 task_type: "summarize_page".into(),
                 content: Some("test".into()),
                 title: None,
@@ -663,7 +1064,10 @@ mod tests {
                 pages_json: None,
                 kb_name: None,
                 kb_source_url: None,
-            },
+                task_name: None,
+                instructions: None,
+                description: None,
+            }),
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains(r#""type":"enrich"#));
@@ -680,9 +1084,60 @@ mod tests {
 
     #[test]
     fn response_message_deserializes_ready() {
+        let json = r#"{"type":"ready","protocol_version":2}"#;
+        let msg: ResponseMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(msg, ResponseMessage::Ready { protocol_version: Some(2) }));
+
         let json = r#"{"type":"ready"}"#;
         let msg: ResponseMessage = serde_json::from_str(json).unwrap();
-        assert!(matches!(msg, ResponseMessage::Ready));
+        assert!(matches!(msg, ResponseMessage::Ready { protocol_version: None }));
+    }
+
+    #[test]
+    fn unsupported_protocol_versions_are_rejected() {
+        assert!(check_protocol_version(Some(*SUPPORTED_PROTOCOL_VERSIONS.end())).is_ok());
+
+        let newer = check_protocol_version(Some(99)).unwrap_err().to_string();
+        assert!(newer.contains("protocol v99"), "{newer}");
+        assert!(newer.contains("Upgrade ContextBuilder"), "{newer}");
+
+        let older = check_protocol_version(Some(1)).unwrap_err().to_string();
+        assert!(older.contains("protocol v1"), "{older}");
+        assert!(older.contains("Update the bridge"), "{older}");
+
+        let legacy = check_protocol_version(None).unwrap_err().to_string();
+        assert!(legacy.contains("did not report a protocol version"), "{legacy}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bridge_spawn_rejects_unsupported_protocol() {
+        let dir = crate::test_support::temp_dir("bridge");
+        let script = crate::test_support::bridge_script(
+            &dir,
+            "echo '{\"type\":\"ready\",\"protocol_version\":99}'\nexec sleep 30\n",
+        );
+
+        let config = EnrichmentConfig {
+            bridge_cmd: script,
+            bridge_script: "bridge.ts".into(),
+            working_dir: dir.to_string_lossy().into_owned(),
+            model_id: "test".into(),
+            kb_name: "test".into(),
+            kb_source_url: "https://example.com".into(),
+            section_chunks: false,
+            startup_timeout_secs: 5,
+            custom_tasks: Vec::new(),
+            fallback: SummaryFallback::None,
+            budget: None,
+        };
+
+        let err = BridgeHandle::spawn(&config).err().expect("spawn should fail");
+        let msg = err.to_string();
+        assert!(msg.contains("protocol v99"), "{msg}");
+        assert!(msg.contains("Upgrade ContextBuilder"), "{msg}");
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
@@ -723,6 +1178,54 @@ mod tests {
         assert_eq!(results.cache_misses, 0);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn bridge_spawn_times_out_without_ready() {
+        // A fake bridge command that starts but never prints "ready"
+        let dir = crate::test_support::temp_dir("bridge");
+        let script = crate::test_support::bridge_script(&dir, "exec sleep 30\n");
+
+        let config = EnrichmentConfig {
+            bridge_cmd: script,
+            bridge_script: "bridge.ts".into(),
+            working_dir: dir.to_string_lossy().into_owned(),
+            model_id: "test".into(),
+            kb_name: "test".into(),
+            kb_source_url: "https://example.com".into(),
+            section_chunks: false,
+            startup_timeout_secs: 1,
+            custom_tasks: Vec::new(),
+            fallback: SummaryFallback::None,
+            budget: None,
+        };
+
+        let start = std::time::Instant::now();
+        let err = BridgeHandle::spawn(&config).err().expect("spawn should time out");
+        assert!(start.elapsed() < Duration::from_secs(10));
+        let msg = err.to_string();
+        assert!(msg.contains("did not become ready"), "{msg}");
+        assert!(msg.contains("bun"), "{msg}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn chunk_key_includes_anchor() {
+        let mut chunk = Chunk {
+            id: "c1".into(),
+            kb_id: "kb".into(),
+            parent_path: "guide/setup".into(),
+            anchor: Some("install".into()),
+            title: Some("Install".into()),
+            ordinal: 1,
+            content: "## Install".into(),
+            summary: None,
+        };
+        assert_eq!(chunk_key(&chunk), "guide/setup#install");
+        chunk.anchor = None;
+        assert_eq!(chunk_key(&chunk), "guide/setup");
+    }
+
     #[test]
     fn set_kb_artifact_works() {
         let mut results = EnrichmentResults::default();
@@ -735,4 +1238,120 @@ mod tests {
         assert_eq!(results.style.as_deref(), Some("style content"));
         assert_eq!(results.do_dont.as_deref(), Some("dodont content"));
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn cache_counters_are_tracked_per_task_type() {
+        use contextbuilder_storage::MemoryStorage;
+
+        let dir = crate::test_support::temp_dir("cache-stats");
+        let config = EnrichmentConfig {
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            working_dir: dir.to_string_lossy().into_owned(),
+            model_id: "m".into(),
+            kb_name: "Example".into(),
+            kb_source_url: "https://docs.example.com".into(),
+            section_chunks: false,
+            startup_timeout_secs: 5,
+            custom_tasks: Vec::new(),
+            fallback: SummaryFallback::None,
+            budget: None,
+        };
+        let page = |path: &str| {
+            let meta = PageMeta {
+                id: path.into(),
+                kb_id: "kb1".into(),
+                url: format!("https://docs.example.com/{path}"),
+                path: path.into(),
+                title: Some(path.into()),
+                content_hash: "h".into(),
+                fetched_at: chrono::Utc::now(),
+                status_code: Some(200),
+                content_len: None,
+                depth: None,
+                description: None,
+            };
+            (meta, format!("# {path}\n\nAbout {path}."))
+        };
+        let storage = MemoryStorage::new();
+        let toc = Toc { sections: vec![] };
+        let progress = crate::progress::SilentProgress;
+
+        let mut pages = vec![page("intro"), page("setup")];
+        run_enrichment(&config, &pages, &toc, &storage, &progress).await.unwrap();
+
+        // One new page: its per-page tasks miss, the others hit, and the
+        // KB-level tasks miss because the summaries changed.
+        pages.push(page("deploy"));
+        let results = run_enrichment(&config, &pages, &toc, &storage, &progress).await.unwrap();
+
+        let stats = |task: &str| results.cache_by_task[task];
+        assert_eq!(stats("summarize_page"), CacheStats { hits: 2, misses: 1 });
+        assert_eq!(stats("generate_description"), CacheStats { hits: 2, misses: 1 });
+        assert_eq!(stats("generate_rules"), CacheStats { hits: 0, misses: 1 });
+        assert!((stats("summarize_page").hit_ratio() - 2.0 / 3.0).abs() < 1e-9);
+
+        let hits: usize = results.cache_by_task.values().map(|s| s.hits).sum();
+        let misses: usize = results.cache_by_task.values().map(|s| s.misses).sum();
+        assert_eq!(hits, results.cache_hits);
+        assert_eq!(misses, results.cache_misses);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn extractive_fallback_summarizes_without_the_bridge() {
+        use contextbuilder_storage::MemoryStorage;
+
+        let mut config = EnrichmentConfig {
+            bridge_cmd: "/nonexistent/contextbuilder-bridge".into(),
+            bridge_script: "bridge.ts".into(),
+            working_dir: ".".into(),
+            model_id: "m".into(),
+            kb_name: "Example".into(),
+            kb_source_url: "https://docs.example.com".into(),
+            section_chunks: false,
+            startup_timeout_secs: 5,
+            custom_tasks: Vec::new(),
+            fallback: SummaryFallback::None,
+            budget: None,
+        };
+        let meta = PageMeta {
+            id: "setup".into(),
+            kb_id: "kb1".into(),
+            url: "https://docs.example.com/setup".into(),
+            path: "setup".into(),
+            title: Some("Setup".into()),
+            content_hash: "h".into(),
+            fetched_at: chrono::Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        let content = "# Setup\n\nInstall the CLI first. Then create a config. Run it. Done.\n";
+        let pages = vec![(meta, content.to_string())];
+        let storage = MemoryStorage::new();
+        let toc = Toc { sections: vec![] };
+        let progress = crate::progress::SilentProgress;
+
+        let err = run_enrichment(&config, &pages, &toc, &storage, &progress).await.unwrap_err();
+        assert!(err.to_string().contains("failed to spawn bridge"), "{err}");
+
+        config.fallback = SummaryFallback::Extractive;
+        let results = run_enrichment(&config, &pages, &toc, &storage, &progress).await.unwrap();
+        assert!(results.extractive);
+        assert_eq!(
+            results.summaries["setup"],
+            "Install the CLI first. Then create a config. Run it."
+        );
+        assert_eq!(results.descriptions["setup"], "Install the CLI first.");
+        assert_eq!(results.cache_misses, 0);
+
+        // Nothing is cached, so a later run with the bridge uses the LLM
+        let hash = prompt_hash(content, "summarize_page");
+        let cached = storage.get_enrichment_cache("kb1", "summarize_page", &hash, "m").await;
+        assert!(cached.unwrap().is_none());
+    }
 }
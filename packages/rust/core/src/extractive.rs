@@ -0,0 +1,135 @@
+//! Extractive summaries: the LLM-free enrichment fallback.
+//!
+//! When the enrichment bridge can't start and `summary_fallback` is
+//! `extractive`, each page is summarized by its opening prose: the first
+//! sentences of the first paragraph after the title. The result is plain
+//! and literal, but keeps `llms.txt` and the TOC descriptive without an LLM.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Sentences kept for a page or section summary.
+pub const SUMMARY_SENTENCES: usize = 3;
+
+/// Sentences kept for a page description.
+pub const DESCRIPTION_SENTENCES: usize = 1;
+
+/// The first `max_sentences` sentences of the first prose paragraph of
+/// `markdown`, skipping frontmatter, headings, code, lists, tables, quotes,
+/// and images. `None` if the page has no prose.
+pub fn summarize(markdown: &str, max_sentences: usize) -> Option<String> {
+    let paragraph = first_paragraph(strip_frontmatter(markdown))?;
+    let text = plain_text(&paragraph);
+    let summary = sentences(&text)
+        .take(max_sentences)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!summary.is_empty()).then_some(summary)
+}
+
+/// `markdown` without a leading `---` frontmatter block.
+fn strip_frontmatter(markdown: &str) -> &str {
+    markdown
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n"))
+        .map_or(markdown, |(_, body)| body)
+}
+
+/// The lines of the first paragraph made only of prose, joined by spaces.
+fn first_paragraph(markdown: &str) -> Option<String> {
+    let mut in_fence = false;
+    let mut lines: Vec<&str> = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if trimmed.is_empty() {
+            if !lines.is_empty() {
+                break;
+            }
+            continue;
+        }
+        if is_prose(trimmed) {
+            lines.push(trimmed);
+        } else if !lines.is_empty() {
+            break;
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Whether a non-blank line is running text rather than structure.
+fn is_prose(line: &str) -> bool {
+    const STRUCTURE: [&str; 10] = ["#", "|", ">", "- ", "* ", "+ ", "![", "<", "[![", "---"];
+    let numbered = line
+        .split_once(". ")
+        .is_some_and(|(n, _)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    !numbered && !STRUCTURE.iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Inline Markdown reduced to its text: links keep their label and emphasis
+/// markers are dropped.
+fn plain_text(markdown: &str) -> String {
+    static LINK_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").expect("valid regex"));
+    static EMPHASIS_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\*\*|__|\*").expect("valid regex"));
+    let text = LINK_RE.replace_all(markdown, "$1");
+    EMPHASIS_RE.replace_all(&text, "").trim().to_string()
+}
+
+/// Split `text` after `.`, `!`, or `?` followed by whitespace.
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .char_indices()
+            .find(|&(i, c)| {
+                matches!(c, '.' | '!' | '?') && rest[i + 1..].starts_with(char::is_whitespace)
+            })
+            .map_or(rest.len(), |(i, _)| i + 1);
+        let (sentence, tail) = rest.split_at(end);
+        rest = tail.trim_start();
+        Some(sentence.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_takes_the_opening_sentences_after_the_title() {
+        let md = "---\ntitle: \"Install\"\n---\n\n# Install\n\n> Note: beta.\n\n\
+                  Install the **CLI** with [bun](https://bun.sh). It needs v1.1!\n\
+                  Then run it. Done?\n\n## Next\n\nMore.\n";
+        assert_eq!(
+            summarize(md, 2).as_deref(),
+            Some("Install the CLI with bun. It needs v1.1!")
+        );
+        assert_eq!(
+            summarize(md, 1).as_deref(),
+            Some("Install the CLI with bun.")
+        );
+        assert_eq!(
+            summarize(md, 10).as_deref(),
+            Some("Install the CLI with bun. It needs v1.1! Then run it. Done?")
+        );
+    }
+
+    #[test]
+    fn summarize_skips_code_and_lists() {
+        let md = "# API\n\n```\nSome code. More code.\n```\n\n- item one.\n1. step.\n\nReal text.";
+        assert_eq!(summarize(md, 3).as_deref(), Some("Real text."));
+        assert_eq!(summarize("# Only a title\n\n| a | b |\n", 3), None);
+    }
+}
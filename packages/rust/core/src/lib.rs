@@ -3,8 +3,24 @@
 //! This crate ties together discovery, crawling, markdown conversion, and
 //! KB assembly into end-to-end workflows (e.g., `add_kb`).
 
+pub mod append;
 pub mod assembler;
+pub mod assets;
+pub mod chunks;
+pub mod context;
 pub mod enrichment;
+pub mod extractive;
+pub mod merge;
+pub mod migrate;
 pub mod pipeline;
+pub mod progress;
+pub mod reclean;
+pub mod reenrich;
+pub mod related;
+pub mod scope;
+pub mod split;
 pub mod toc;
 pub mod update;
+
+#[cfg(test)]
+mod test_support;
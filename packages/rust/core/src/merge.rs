@@ -0,0 +1,507 @@
+//! Merge several knowledge bases into one.
+//!
+//! Each source KB's pages are namespaced under a directory named after the KB
+//! (`astro-docs/guides/install`), its TOC becomes one top-level section, and
+//! its pages and links are copied into a fresh database with new IDs. The
+//! combined KB then goes through enrichment and artifact generation like a
+//! newly added one.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tracing::{info, instrument};
+
+use contextbuilder_shared::{
+    ArtifactLayout, ContextBuilderError, CustomTaskConfig, IdSource, KbId, KbManifest,
+    LinkBase, PageMeta, Result, SummaryFallback, Toc, TocEntry,
+};
+use contextbuilder_storage::Storage;
+
+use crate::assembler::{AssembleConfig, AssemblePage};
+use crate::enrichment::{self, EnrichmentConfig};
+use crate::pipeline::{ArtifactInputs, clock_for};
+use crate::progress::{Phase, ProgressEvent, ProgressExt, ProgressReporter};
+
+// ---------------------------------------------------------------------------
+// Config & result
+// ---------------------------------------------------------------------------
+
+/// Configuration for the `merge_kbs` pipeline.
+#[derive(Debug, Clone)]
+pub struct MergeKbsConfig {
+    /// Source KB directories (each contains manifest.json).
+    pub kb_paths: Vec<PathBuf>,
+    /// Output root directory; the merged KB is written to `<output_root>/<kb_id>`.
+    pub output_root: PathBuf,
+    /// Name of the merged KB (defaults to the source names joined by ` + `).
+    pub name: Option<String>,
+    /// Tool version string.
+    pub tool_version: String,
+    /// OpenRouter model ID for enrichment.
+    pub model_id: String,
+    /// Bridge command (e.g., "bun").
+    pub bridge_cmd: String,
+    /// Bridge script path.
+    pub bridge_script: String,
+    /// Working directory for the bridge subprocess.
+    pub bridge_working_dir: String,
+    /// Seconds to wait for the bridge to signal readiness.
+    pub bridge_startup_timeout_secs: u64,
+    /// Produce reproducible output: fixed timestamps.
+    pub deterministic: bool,
+    /// User-defined enrichment tasks, each written as an extra artifact.
+    pub custom_tasks: Vec<CustomTaskConfig>,
+    /// Summaries to fall back on when the enrichment bridge can't start.
+    pub summary_fallback: SummaryFallback,
+    /// Where artifacts are written inside `artifacts/`.
+    pub artifact_layout: ArtifactLayout,
+    /// What the generated `llms.txt` links to.
+    pub llms_txt_links: LinkBase,
+    /// Also write a gzip copy of every artifact.
+    pub compress_artifacts: bool,
+}
+
+/// Result of the `merge_kbs` pipeline.
+#[derive(Debug)]
+pub struct MergeKbsResult {
+    /// Path to the merged KB directory.
+    pub kb_path: PathBuf,
+    /// Identifier of the merged KB.
+    pub kb_id: KbId,
+    /// Directory each source KB's pages were placed under, in source order.
+    pub namespaces: Vec<String>,
+    /// Total page count of the merged KB.
+    pub page_count: usize,
+    /// Total elapsed time.
+    pub elapsed: std::time::Duration,
+}
+
+// ---------------------------------------------------------------------------
+// Pipeline
+// ---------------------------------------------------------------------------
+
+/// Merge the configured KBs into a new KB.
+///
+/// 1. Load each source's manifest, TOC, stored pages, links, and docs
+/// 2. Namespace every page path under the source KB's name
+/// 3. Copy pages and links into a new database under a new KB ID
+/// 4. Assemble the merged KB with one top-level TOC section per source
+/// 5. Run enrichment and write the combined artifacts
+#[instrument(skip_all, fields(sources = config.kb_paths.len()))]
+pub async fn merge_kbs(
+    config: &MergeKbsConfig,
+    progress: &dyn ProgressReporter,
+) -> Result<MergeKbsResult> {
+    let start = Instant::now();
+    if config.kb_paths.len() < 2 {
+        return Err(ContextBuilderError::validation("merge needs at least two KBs"));
+    }
+    enrichment::validate_custom_tasks(&config.custom_tasks)?;
+
+    progress.phase(Phase::LoadKb);
+    let manifests = config
+        .kb_paths
+        .iter()
+        .map(|path| crate::update::load_manifest(path))
+        .collect::<Result<Vec<_>>>()?;
+    let namespaces = namespaces(&manifests);
+
+    // A merge of the same KBs gets the same id in deterministic builds
+    let ids = IdSource::new(config.deterministic);
+    let seed: Vec<String> = manifests.iter().map(|m| m.id.to_string()).collect();
+    let kb_id = KbId(ids.uuid(&seed.join("+")));
+    let clock = clock_for(config.deterministic);
+    let name = config.name.clone().unwrap_or_else(|| {
+        manifests
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    });
+    let source_url = manifests
+        .iter()
+        .map(|m| m.source_url.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // --- Storage ---
+    let kb_path = config.output_root.join(kb_id.to_string());
+    let storage = Storage::open(&kb_path.join("indexes").join("contextbuilder.db")).await?;
+    storage
+        .insert_kb(&kb_id.to_string(), &name, &source_url, None)
+        .await?;
+
+    // --- Copy sources ---
+    progress.phase(Phase::Merge);
+    let mut metas: Vec<PageMeta> = Vec::new();
+    let mut pages: Vec<AssemblePage> = Vec::new();
+    let mut toc = Toc { sections: Vec::new() };
+
+    for ((source_path, manifest), namespace) in
+        config.kb_paths.iter().zip(&manifests).zip(&namespaces)
+    {
+        let source = Storage::open_readonly(&source_path.join("indexes").join("contextbuilder.db"))
+            .await?;
+        let source_kb_id = manifest.id.to_string();
+
+        let mut ids_by_path: HashMap<String, String> = HashMap::new();
+        for meta in source.list_pages_by_kb(&source_kb_id).await? {
+            let md_path = source_path.join("docs").join(format!("{}.md", meta.path));
+            let markdown = std::fs::read_to_string(&md_path)
+                .map_err(|e| ContextBuilderError::io(&md_path, e))?;
+
+            let source_page_path = meta.path.clone();
+            let merged = PageMeta {
+                id: ids.uuid(&format!("{kb_id}/{namespace}/{}", meta.path)).to_string(),
+                kb_id: kb_id.to_string(),
+                path: format!("{namespace}/{}", meta.path),
+                ..meta
+            };
+            storage.upsert_page_with_content(&merged, &markdown).await?;
+            ids_by_path.insert(source_page_path, merged.id.clone());
+            pages.push(AssemblePage {
+                path: merged.path.clone(),
+                title: merged.title.clone().unwrap_or_else(|| merged.path.clone()),
+                markdown,
+            });
+            metas.push(merged);
+        }
+
+        for (from_path, to_url) in source.list_links_by_kb(&source_kb_id).await? {
+            if let Some(from_id) = ids_by_path.get(&from_path) {
+                storage.insert_link(from_id, &to_url, None).await?;
+            }
+        }
+
+        let source_toc = load_toc(source_path)?;
+        toc.sections.push(TocEntry {
+            title: manifest.name.clone(),
+            path: namespace.clone(),
+            source_url: Some(manifest.source_url.clone()),
+            summary: None,
+            children: prefix_entries(&source_toc.sections, namespace),
+        });
+    }
+
+    if metas.is_empty() {
+        return Err(ContextBuilderError::validation("the source KBs contain no pages"));
+    }
+
+    // --- Assemble ---
+    progress.phase(Phase::Assemble);
+    let assemble_config = AssembleConfig {
+        kb_id: kb_id.clone(),
+        name: name.clone(),
+        source_url: source_url.clone(),
+        output_root: config.output_root.clone(),
+        tool_version: config.tool_version.clone(),
+        clock,
+        crawl_scope: None,
+    };
+    let assemble_result = crate::assembler::assemble(&assemble_config, &pages, &toc)?;
+    crate::pipeline::write_related(&storage, &kb_id.to_string(), &metas, &assemble_result.kb_path)
+        .await?;
+
+    // --- Enrichment & artifacts ---
+    let enrich_config = EnrichmentConfig {
+        bridge_cmd: config.bridge_cmd.clone(),
+        bridge_script: config.bridge_script.clone(),
+        working_dir: config.bridge_working_dir.clone(),
+        model_id: config.model_id.clone(),
+        kb_name: name,
+        kb_source_url: source_url,
+        section_chunks: false,
+        startup_timeout_secs: config.bridge_startup_timeout_secs,
+        custom_tasks: config.custom_tasks.clone(),
+        fallback: config.summary_fallback,
+        budget: None,
+    };
+    crate::pipeline::enrich_and_write_artifacts(
+        &ArtifactInputs {
+            enrich: &enrich_config,
+            metas: &metas,
+            pages: &pages,
+            toc: &toc,
+            kb_path: &assemble_result.kb_path,
+            tool_version: &config.tool_version,
+            layout: config.artifact_layout,
+            llms_txt_links: config.llms_txt_links,
+            compress_artifacts: config.compress_artifacts,
+            clock,
+        },
+        &storage,
+        progress,
+    )
+    .await?;
+
+    let result = MergeKbsResult {
+        kb_path: assemble_result.kb_path,
+        kb_id,
+        namespaces,
+        page_count: pages.len(),
+        elapsed: start.elapsed(),
+    };
+
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: result.page_count,
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
+    info!(
+        kb_id = %result.kb_id,
+        page_count = result.page_count,
+        elapsed_ms = result.elapsed.as_millis(),
+        "merge complete"
+    );
+
+    Ok(result)
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Directory name for each source KB: its slugified name, with a numeric
+/// suffix when two sources share a name.
+fn namespaces(manifests: &[KbManifest]) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::new();
+    manifests
+        .iter()
+        .map(|m| {
+            let base = match contextbuilder_markdown::slugify_heading(&m.name) {
+                slug if slug.is_empty() => "kb".to_string(),
+                slug => slug,
+            };
+            let mut namespace = base.clone();
+            let mut n = 2;
+            while !seen.insert(namespace.clone()) {
+                namespace = format!("{base}-{n}");
+                n += 1;
+            }
+            namespace
+        })
+        .collect()
+}
+
+/// Copy TOC entries with every path moved under `namespace`.
+fn prefix_entries(entries: &[TocEntry], namespace: &str) -> Vec<TocEntry> {
+    entries
+        .iter()
+        .map(|entry| TocEntry {
+            path: format!("{namespace}/{}", entry.path),
+            children: prefix_entries(&entry.children, namespace),
+            ..entry.clone()
+        })
+        .collect()
+}
+
+/// Load and parse toc.json from a KB directory.
+fn load_toc(kb_path: &Path) -> Result<Toc> {
+    let toc_path = kb_path.join("toc.json");
+    let content = std::fs::read_to_string(&toc_path)
+        .map_err(|e| ContextBuilderError::io(&toc_path, e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| ContextBuilderError::validation(format!("invalid toc.json: {e}")))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+    use crate::test_support::{read_manifest, read_toc};
+
+    fn manifest(name: &str) -> KbManifest {
+        KbManifest {
+            schema_version: 1,
+            id: KbId::new(),
+            name: name.into(),
+            source_url: "https://example.com".into(),
+            tool_version: "test".into(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            page_count: 0,
+            config: None,
+            artifacts: None,
+            enrichment: None,
+            crawl_scope: None,
+        }
+    }
+
+    #[test]
+    fn namespaces_are_slugged_and_unique() {
+        let manifests = [manifest("Astro Docs"), manifest("astro docs"), manifest("!!!")];
+        assert_eq!(namespaces(&manifests), vec!["astro-docs", "astro-docs-2", "kb"]);
+    }
+
+    #[test]
+    fn toc_entries_are_prefixed_recursively() {
+        let entry = |path: &str, children| TocEntry {
+            title: path.into(),
+            path: path.into(),
+            source_url: None,
+            summary: None,
+            children,
+        };
+        let prefixed = prefix_entries(&[entry("guides", vec![entry("guides/setup", vec![])])], "a");
+        assert_eq!(prefixed[0].path, "a/guides");
+        assert_eq!(prefixed[0].children[0].path, "a/guides/setup");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn merge_two_kbs() {
+        let dir = crate::test_support::temp_dir("merge");
+        let sources = dir.join("sources");
+        // Both KBs have an `index` page: namespacing keeps them apart
+        let api = crate::test_support::seed_kb(
+            &sources,
+            "Widget API",
+            "https://api.example.com",
+            &[
+                ("index", "API", "# API\n\nEndpoints.\n"),
+                ("auth", "Auth", "# Auth\n\nTokens.\n"),
+            ],
+        )
+        .await;
+        let cli = crate::test_support::seed_kb(
+            &sources,
+            "Widget CLI",
+            "https://cli.example.com",
+            &[("index", "CLI", "# CLI\n\nCommands.\n")],
+        )
+        .await;
+        {
+            let storage = Storage::open(&api.join("indexes").join("contextbuilder.db"))
+                .await
+                .unwrap();
+            let kb_id = read_manifest(&api).id.to_string();
+            let index = storage.get_page(&kb_id, "index").await.unwrap().unwrap();
+            storage
+                .insert_link(&index.id, "https://api.example.com/auth", None)
+                .await
+                .unwrap();
+        }
+
+        let config = MergeKbsConfig {
+            kb_paths: vec![api.clone(), cli.clone()],
+            output_root: dir.join("out"),
+            name: None,
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+        };
+        let result = merge_kbs(&config, &SilentProgress).await.unwrap();
+        assert_eq!(result.namespaces, vec!["widget-api", "widget-cli"]);
+        assert_eq!(result.page_count, 3);
+
+        let manifest = read_manifest(&result.kb_path);
+        assert_eq!(manifest.name, "Widget API + Widget CLI");
+        assert_eq!(manifest.page_count, 3);
+        assert!(manifest.artifacts.is_some());
+
+        for (path, text) in [
+            ("widget-api/index", "Endpoints."),
+            ("widget-api/auth", "Tokens."),
+            ("widget-cli/index", "Commands."),
+        ] {
+            let md = std::fs::read_to_string(result.kb_path.join(format!("docs/{path}.md")))
+                .unwrap();
+            assert!(md.contains(text), "{path}: {md}");
+        }
+
+        let toc = read_toc(&result.kb_path);
+        let sections: Vec<(&str, &str, usize)> = toc
+            .sections
+            .iter()
+            .map(|s| (s.title.as_str(), s.path.as_str(), s.children.len()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![("Widget API", "widget-api", 2), ("Widget CLI", "widget-cli", 1)]
+        );
+        assert!(toc.sections[0].children.iter().any(|e| e.path == "widget-api/auth"));
+
+        // Pages and links were copied under the new KB ID
+        let storage = Storage::open_readonly(
+            &result.kb_path.join("indexes").join("contextbuilder.db"),
+        )
+        .await
+        .unwrap();
+        let kb_id = result.kb_id.to_string();
+        let stored = storage.list_pages_by_kb(&kb_id).await.unwrap();
+        assert_eq!(stored.len(), 3);
+        assert!(stored.iter().all(|p| p.kb_id == kb_id));
+        let links = storage.list_links_by_kb(&kb_id).await.unwrap();
+        assert_eq!(
+            links,
+            vec![("widget-api/index".to_string(), "https://api.example.com/auth".to_string())]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn merged_pages_are_searchable_by_content() {
+        let dir = crate::test_support::temp_dir("merge-search");
+        let sources = dir.join("sources");
+        let api = crate::test_support::seed_kb(
+            &sources,
+            "Widget API",
+            "https://api.example.com",
+            &[("auth", "Auth", "# Auth\n\nRotate the bearer tokens monthly.\n")],
+        )
+        .await;
+        let cli = crate::test_support::seed_kb(
+            &sources,
+            "Widget CLI",
+            "https://cli.example.com",
+            &[("index", "CLI", "# CLI\n\nCommands.\n")],
+        )
+        .await;
+
+        let config = MergeKbsConfig {
+            kb_paths: vec![api, cli],
+            output_root: dir.join("out"),
+            name: None,
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+        };
+        let result = merge_kbs(&config, &SilentProgress).await.unwrap();
+
+        let storage = Storage::open_readonly(
+            &result.kb_path.join("indexes").join("contextbuilder.db"),
+        )
+        .await
+        .unwrap();
+        let hits = storage.search(&result.kb_id.to_string(), "bearer", 10).await.unwrap();
+        let paths: Vec<&str> = hits.iter().map(|hit| hit.path.as_str()).collect();
+        assert_eq!(paths, vec!["widget-api/auth"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
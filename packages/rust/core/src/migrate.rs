@@ -0,0 +1,202 @@
+//! Upgrade a KB's on-disk format in place.
+//!
+//! Commands that change a KB refuse one whose `schema_version` is not
+//! [`CURRENT_SCHEMA_VERSION`] (see [`KbManifest::ensure_current_schema`]).
+//! [`migrate_kb`] brings an older KB up to date without rebuilding it: it
+//! applies each format step from the KB's version onwards to the raw
+//! `manifest.json` and `toc.json`, checks the result parses as the current
+//! format, and rewrites both files. Pages under `docs/` and `artifacts/` are
+//! left as they are.
+//!
+//! Version 1 is the first format, so there are no steps yet. When the format
+//! changes, bump [`CURRENT_SCHEMA_VERSION`] and append the step that upgrades
+//! the previous version to [`MIGRATIONS`].
+
+use std::path::Path;
+
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use contextbuilder_shared::{CURRENT_SCHEMA_VERSION, ContextBuilderError, KbManifest, Result, Toc};
+
+use crate::assembler::write_json;
+
+/// A format step: rewrites the manifest and TOC of one version into the
+/// next.
+type Migration = fn(&mut Value, &mut Value) -> Result<()>;
+
+/// The oldest format, the one `schema_version` was introduced with.
+const FIRST_SCHEMA_VERSION: u32 = 1;
+
+/// Format steps in order: the first upgrades [`FIRST_SCHEMA_VERSION`].
+const MIGRATIONS: &[Migration] = &[];
+
+const _: () = assert!(FIRST_SCHEMA_VERSION + MIGRATIONS.len() as u32 == CURRENT_SCHEMA_VERSION);
+
+/// Outcome of [`migrate_kb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrateReport {
+    /// Schema version the KB had.
+    pub from: u32,
+    /// Schema version the KB has now.
+    pub to: u32,
+}
+
+impl MigrateReport {
+    /// Whether the KB was already current and nothing was written.
+    pub fn unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Upgrade the manifest and TOC of the KB at `kb_path` to
+/// [`CURRENT_SCHEMA_VERSION`], rewriting them only if they were older.
+///
+/// A KB from a newer ContextBuilder is refused, and so is one whose
+/// upgraded files do not parse: nothing is written in either case.
+#[instrument(skip_all, fields(kb = %kb_path.display()))]
+pub fn migrate_kb(kb_path: &Path) -> Result<MigrateReport> {
+    migrate_with(kb_path, MIGRATIONS)
+}
+
+/// [`migrate_kb`] with the format steps `steps`, which upgrade
+/// [`FIRST_SCHEMA_VERSION`] to the version after the last step.
+fn migrate_with(kb_path: &Path, steps: &[Migration]) -> Result<MigrateReport> {
+    let current = FIRST_SCHEMA_VERSION + steps.len() as u32;
+    let manifest_path = kb_path.join("manifest.json");
+    let toc_path = kb_path.join("toc.json");
+    let mut manifest = read_json(&manifest_path)?;
+    let mut toc = read_json(&toc_path)?;
+
+    let from = manifest
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| {
+            ContextBuilderError::validation(format!(
+                "{}: schema_version is missing or not a version number",
+                manifest_path.display()
+            ))
+        })?;
+    if from < FIRST_SCHEMA_VERSION {
+        return Err(ContextBuilderError::validation(format!(
+            "KB at '{}' uses schema_version {from}, which cannot be migrated; rebuild it \
+             with `contextbuilder add`",
+            kb_path.display()
+        )));
+    }
+    if from > current {
+        // Reuse the standard "upgrade ContextBuilder" message
+        parse::<KbManifest>(&manifest_path, manifest.clone())?.ensure_current_schema(kb_path)?;
+    }
+    let report = MigrateReport { from, to: current };
+    if report.unchanged() {
+        return Ok(report);
+    }
+
+    for (version, step) in (FIRST_SCHEMA_VERSION..).zip(steps).skip_while(|(v, _)| *v < from) {
+        step(&mut manifest, &mut toc)?;
+        manifest["schema_version"] = Value::from(version + 1);
+    }
+    let manifest: KbManifest = parse(&manifest_path, manifest)?;
+    let toc: Toc = parse(&toc_path, toc)?;
+    replace_json(kb_path, "toc.json", &toc)?;
+    replace_json(kb_path, "manifest.json", &manifest)?;
+
+    info!(from, to = current, "KB migrated");
+    Ok(report)
+}
+
+/// Rewrite the KB file `name` through a temporary file, so an interrupted
+/// migration never leaves it half-written.
+fn replace_json<T: serde::Serialize>(kb_path: &Path, name: &str, data: &T) -> Result<()> {
+    let path = kb_path.join(name);
+    let temp = kb_path.join(format!(".{name}.tmp"));
+    write_json(&temp, data)?;
+    std::fs::rename(&temp, &path).map_err(|e| ContextBuilderError::io(&path, e))
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path).map_err(|e| ContextBuilderError::io(path, e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        ContextBuilderError::validation(format!("{}: invalid JSON: {e}", path.display()))
+    })
+}
+
+/// `value` (read from `path`) in the current format.
+fn parse<T: serde::de::DeserializeOwned>(path: &Path, value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| {
+        ContextBuilderError::validation(format!(
+            "{}: not a valid schema_version {CURRENT_SCHEMA_VERSION} file: {e}",
+            path.display()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{count_toc_entries, seed_kb, temp_dir};
+
+    /// A synthetic step for the tests only: its version records the page
+    /// count as the number of TOC entries.
+    fn recount_pages(manifest: &mut Value, toc: &mut Value) -> Result<()> {
+        let toc: Toc = serde_json::from_value(toc.clone()).unwrap();
+        manifest["page_count"] = Value::from(count_toc_entries(&toc.sections));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_applies_the_steps_after_the_kbs_version() {
+        let root = temp_dir("migrate");
+        let pages = [("guide", "Guide", "# Guide\n"), ("guide/setup", "Setup", "# Setup\n")];
+        let kb = seed_kb(&root, "Old", "https://docs.example.com", &pages).await;
+        let page = std::fs::read_to_string(kb.join("docs/guide.md")).unwrap();
+        let manifest_path = kb.join("manifest.json");
+        let stale = std::fs::read_to_string(&manifest_path)
+            .unwrap()
+            .replace("\"page_count\": 2", "\"page_count\": 0");
+        std::fs::write(&manifest_path, &stale).unwrap();
+
+        // The shipped format is current: nothing to do
+        assert!(migrate_kb(&kb).unwrap().unchanged());
+        assert_eq!(std::fs::read_to_string(&manifest_path).unwrap(), stale);
+
+        let steps: &[Migration] = &[recount_pages];
+        let report = migrate_with(&kb, steps).unwrap();
+        assert_eq!(report, MigrateReport { from: 1, to: 2 });
+
+        let manifest: KbManifest =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!((manifest.schema_version, manifest.name.as_str()), (2, "Old"));
+        assert_eq!(manifest.page_count, 2);
+        assert_eq!(std::fs::read_to_string(kb.join("docs/guide.md")).unwrap(), page);
+        assert!(!kb.join(".manifest.json.tmp").exists() && !kb.join(".toc.json.tmp").exists());
+
+        // Already at the last step's version: nothing to do
+        let before = std::fs::read_to_string(&manifest_path).unwrap();
+        assert!(migrate_with(&kb, steps).unwrap().unchanged());
+        assert_eq!(std::fs::read_to_string(&manifest_path).unwrap(), before);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn migrate_refuses_a_newer_kb() {
+        let root = temp_dir("migrate-newer");
+        let pages = [("guide", "Guide", "# Guide\n")];
+        let kb = seed_kb(&root, "New", "https://docs.example.com", &pages).await;
+        let manifest_path = kb.join("manifest.json");
+        let newer = std::fs::read_to_string(&manifest_path).unwrap().replace(
+            &format!("\"schema_version\": {CURRENT_SCHEMA_VERSION}"),
+            &format!("\"schema_version\": {}", CURRENT_SCHEMA_VERSION + 1),
+        );
+        std::fs::write(&manifest_path, &newer).unwrap();
+
+        let err = migrate_kb(&kb).unwrap_err();
+        assert!(err.to_string().contains("upgrade ContextBuilder"), "{err}");
+        assert_eq!(std::fs::read_to_string(&manifest_path).unwrap(), newer);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,22 +1,30 @@
 //! End-to-end `add` pipeline: URL → discovery → crawl → convert → assemble → KB.
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
-use tracing::{info, instrument, warn};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 
-use contextbuilder_crawler::{CrawlResult, Crawler, FetchedPage};
+use contextbuilder_crawler::{Crawler, FetchedPage};
+pub use contextbuilder_crawler::{CrawlResult, HostMetrics};
 use contextbuilder_discovery::{DiscoveryOptions, DiscoveryResult};
-use contextbuilder_markdown::ConvertOptions;
+use contextbuilder_markdown::{ConvertOptions, ConvertResult};
 use contextbuilder_shared::{
-    CrawlConfig, ContextBuilderError, KbId, Result,
+    ArtifactLayout, Clock, ContextBuilderError, CrawlConfig, CrawlMode, CrawlScope,
+    CustomTaskConfig, IdSource, InlineSvg, KbId, LinkBase, PageMeta, Result, SummaryFallback,
+    Toc,
 };
-use contextbuilder_storage::Storage;
+use contextbuilder_storage::{Storage, StorageBackend};
 
 use crate::assembler::{AssembleConfig, AssemblePage, EnrichmentMeta};
-use crate::enrichment::{self, EnrichmentConfig, EnrichmentProgress};
-use crate::toc;
+use crate::assets::{self, AssetFetcher};
+use crate::enrichment::{self, EnrichmentConfig};
+use crate::progress::{Phase, ProgressEvent, ProgressExt, ProgressReporter};
+use crate::{split, toc};
 
 /// Configuration for the `add_kb` pipeline.
 #[derive(Debug, Clone)]
@@ -27,8 +35,8 @@ pub struct AddKbConfig {
     pub name: String,
     /// Output root directory for KB storage.
     pub output_root: PathBuf,
-    /// Discovery mode: "auto", "llms-txt", or "crawl".
-    pub mode: String,
+    /// Discovery mode.
+    pub mode: CrawlMode,
     /// Crawl configuration.
     pub crawl: CrawlConfig,
     /// Tool version string.
@@ -41,6 +49,27 @@ pub struct AddKbConfig {
     pub bridge_script: String,
     /// Working directory for the bridge subprocess.
     pub bridge_working_dir: String,
+    /// Seconds to wait for the bridge to signal readiness.
+    pub bridge_startup_timeout_secs: u64,
+    /// Split pages into H2 section chunks for section-level enrichment and search.
+    pub section_chunks: bool,
+    /// Produce reproducible output: fixed timestamps and path-ordered pages.
+    pub deterministic: bool,
+    /// User-defined enrichment tasks, each written as an extra artifact.
+    pub custom_tasks: Vec<CustomTaskConfig>,
+    /// Summaries to fall back on when the enrichment bridge can't start.
+    pub summary_fallback: SummaryFallback,
+    /// Where artifacts are written inside `artifacts/`.
+    pub artifact_layout: ArtifactLayout,
+    /// What the generated `llms.txt` links to.
+    pub llms_txt_links: LinkBase,
+    /// Also write a gzip copy of every artifact.
+    pub compress_artifacts: bool,
+    /// Existing KB whose enrichment cache seeds the new one.
+    pub warm_cache_from: Option<PathBuf>,
+    /// When `output_root` already holds a KB for `url`, fetch only the pages
+    /// it lacks and add them to it, instead of refusing.
+    pub merge: bool,
 }
 
 /// Result of the `add_kb` pipeline.
@@ -56,49 +85,64 @@ pub struct AddKbResult {
     pub method: String,
     /// Total elapsed time.
     pub elapsed: std::time::Duration,
-}
-
-/// Progress callback for reporting pipeline status.
-pub trait ProgressReporter: Send + Sync {
-    /// Called when entering a new phase.
-    fn phase(&self, name: &str);
-    /// Called when a page is fetched during crawl.
-    fn page_fetched(&self, url: &str, current: usize, total_estimate: usize);
-    /// Called when a page is converted.
-    fn page_converted(&self, path: &str, current: usize, total: usize);
-    /// Called when the pipeline completes.
-    fn done(&self, result: &AddKbResult);
-}
-
-/// No-op progress reporter for headless/test usage.
-pub struct SilentProgress;
-
-impl ProgressReporter for SilentProgress {
-    fn phase(&self, _name: &str) {}
-    fn page_fetched(&self, _url: &str, _current: usize, _total_estimate: usize) {}
-    fn page_converted(&self, _path: &str, _current: usize, _total: usize) {}
-    fn done(&self, _result: &AddKbResult) {}
+    /// Crawl health summary (`None` when pages came from llms.txt).
+    pub crawl: Option<CrawlResult>,
+    /// The pages were merged into an existing KB rather than a new one.
+    pub merged: bool,
 }
 
 /// Run the full `add` pipeline.
 ///
 /// 1. Discovery: check for llms.txt
 /// 2. Crawl (if needed)
-/// 3. Convert HTML → Markdown
+/// 3. Convert HTML → Markdown (and download images, if enabled)
 /// 4. Build TOC
 /// 5. Assemble KB directory
+///
+/// If `output_root` already holds a KB for the same URL, this fails with a
+/// hint to run `update`, or with [`AddKbConfig::merge`] adds only the pages
+/// that KB lacks. Seeding from another KB (`warm_cache_from`) always
+/// creates a new one.
 #[instrument(skip_all, fields(url = %config.url, name = %config.name))]
 pub async fn add_kb(
     config: &AddKbConfig,
     progress: &dyn ProgressReporter,
 ) -> Result<AddKbResult> {
     let start = Instant::now();
-    let kb_id = KbId::new();
+    let ids = IdSource::new(config.deterministic);
+    let mut seed = format!("{}\n{}", config.url, config.name);
+    if let Some(from) = &config.warm_cache_from {
+        // A KB seeded from another one is a new KB, even in the same place
+        seed.push_str(&format!("\n{}", from.display()));
+    }
+    let kb_id = KbId(ids.uuid(&seed));
+    let clock = clock_for(config.deterministic);
 
     info!(%kb_id, url = %config.url, "starting add pipeline");
 
+    // Adding a KB always fetches; fail before anything is written to disk.
+    if config.crawl.offline {
+        return Err(ContextBuilderError::offline(format!("fetch {}", config.url)));
+    }
+    enrichment::validate_custom_tasks(&config.custom_tasks)?;
+
+    if config.warm_cache_from.is_none() {
+        if let Some(existing) = find_existing_kb(&config.output_root, &config.url) {
+            if !config.merge {
+                return Err(ContextBuilderError::validation(format!(
+                    "a knowledge base for {} already exists at {}; run `contextbuilder \
+                     update --kb {}` to refresh it, or `add --merge` to fetch only new pages",
+                    config.url,
+                    existing.display(),
+                    existing.display()
+                )));
+            }
+            return merge_into_existing(config, &existing, progress).await;
+        }
+    }
+
     // --- Phase 1: Storage ---
-    progress.phase("Initializing storage");
+    progress.phase(Phase::InitStorage);
     let db_path = config
         .output_root
         .join(kb_id.to_string())
@@ -120,94 +164,131 @@ pub async fn add_kb(
             None,
         )
         .await?;
+    if let Some(source) = &config.warm_cache_from {
+        warm_cache(&storage, &kb_id.to_string(), source, &config.model_id).await?;
+    }
+
+    // Crawl, conversion, and enrichment all draw from one budget
+    let budget = parallelism_budget(&config.crawl);
 
     // --- Phase 2: Discovery / Crawl ---
-    let (fetched_pages, method) = match config.mode.as_str() {
-        "llms-txt" => {
-            progress.phase("Discovering llms.txt");
-            discover_and_fetch(&config.url, &storage, &kb_id, progress).await?
-        }
-        "crawl" => {
-            progress.phase("Crawling documentation");
-            let (_result, pages) =
-                crawl_pages(&config.url, &config.crawl, &kb_id, &storage, progress).await?;
-            (pages, "crawl".to_string())
-        }
-        _ => {
-            // Auto mode: try discovery first, fall back to crawl
-            progress.phase("Discovering llms.txt");
-            match discover_and_fetch(&config.url, &storage, &kb_id, progress).await {
-                Ok((pages, method)) if !pages.is_empty() => (pages, method),
-                _ => {
-                    progress.phase("Crawling documentation");
-                    let (_result, pages) = crawl_pages(
-                        &config.url,
-                        &config.crawl,
-                        &kb_id,
-                        &storage,
-                        progress,
-                    )
-                    .await?;
-                    (pages, "crawl".to_string())
-                }
-            }
-        }
-    };
+    let mode = effective_mode(config);
+    let (mut fetched_pages, method, crawl_result) =
+        fetch_source(config, mode, &kb_id, &storage, budget.as_ref(), &HashMap::new(), progress)
+            .await?;
 
     if fetched_pages.is_empty() {
+        let message = "no pages were fetched from the documentation source";
         return Err(ContextBuilderError::validation(
-            "no pages were fetched from the documentation source",
+            match crawl_result.as_ref().and_then(CrawlResult::auth_hint) {
+                Some(hint) => format!("{message}; {hint}"),
+                None => message.to_string(),
+            },
         ));
     }
+    if clock.is_fixed() {
+        make_deterministic(&mut fetched_pages, clock);
+    }
 
     // --- Phase 3: Convert HTML → Markdown ---
-    progress.phase("Converting to Markdown");
+    progress.phase(Phase::Convert);
     let mut assembled_pages: Vec<AssemblePage> = Vec::new();
+    let mut kept_pages: Vec<FetchedPage> = Vec::new();
     let total = fetched_pages.len();
 
-    for (i, page) in fetched_pages.iter().enumerate() {
-        let opts = ConvertOptions {
-            source_url: page.meta.url.clone(),
-            title: page.meta.title.clone(),
-            fetched_at: Some(page.meta.fetched_at.to_rfc3339()),
-        };
+    let page_refs: Vec<&FetchedPage> = fetched_pages.iter().collect();
+    let conversions = convert_pages(&page_refs, &config.crawl, budget.as_ref()).await;
 
-        match contextbuilder_markdown::convert(&page.html, &opts) {
+    for (i, (page, conversion)) in fetched_pages.into_iter().zip(conversions).enumerate() {
+        match conversion {
             Ok(result) => {
-                progress.page_converted(&page.meta.path, i + 1, total);
+                if !language_allowed(result.language.as_deref(), &config.crawl.languages) {
+                    debug!(
+                        url = %page.meta.url,
+                        language = ?result.language,
+                        "page language not in target languages, dropping"
+                    );
+                    drop_stored_page(&storage, &kb_id.to_string(), &page.meta.path).await?;
+                    continue;
+                }
+                progress.event(ProgressEvent::PageConverted {
+                    path: page.meta.path.clone(),
+                    current: i + 1,
+                    total,
+                });
                 assembled_pages.push(AssemblePage {
                     path: page.meta.path.clone(),
                     markdown: result.markdown,
                     title: result.title,
                 });
+                kept_pages.push(page);
             }
             Err(e) => {
                 warn!(url = %page.meta.url, error = %e, "conversion failed, skipping page");
+                progress.warning(format!("conversion failed for {}: {e}", page.meta.url));
             }
         }
     }
+    let mut fetched_pages = kept_pages;
+    if fetched_pages.is_empty() {
+        return Err(ContextBuilderError::validation(
+            "no pages were left after conversion and language filtering",
+        ));
+    }
+    if let Some(threshold) = config.crawl.split_large_pages {
+        (assembled_pages, fetched_pages) =
+            split::split_large_pages(assembled_pages, fetched_pages, threshold, ids);
+    }
+
+    // --- Phase 3a: Assets ---
+    if config.crawl.download_assets {
+        progress.phase(Phase::DownloadAssets);
+        let sources = fetched_pages.iter().map(|p| p.meta.url.as_str());
+        assets::localize_assets(
+            assembled_pages.iter_mut().zip(sources),
+            &config.output_root.join(kb_id.to_string()),
+            &AssetFetcher::new(&config.crawl)?,
+        )
+        .await?;
+    }
+
+    // --- Phase 3b: Section chunks ---
+    if config.section_chunks {
+        progress.phase(Phase::SplitSections);
+        store_chunks(&storage, &kb_id.to_string(), &assembled_pages, ids).await?;
+    }
+    store_page_contents(&storage, &fetched_pages, &assembled_pages).await?;
 
     // --- Phase 4: Build TOC ---
-    progress.phase("Building table of contents");
+    progress.phase(Phase::BuildToc);
     let page_metas: Vec<_> = fetched_pages.iter().map(|p| p.meta.clone()).collect();
-    let toc = toc::build_toc(&page_metas, &[]);
+    let adapter_toc = crawl_result.as_ref().map_or(&[][..], |result| &result.toc[..]);
+    // Split pages' sections are missing from the site's own navigation
+    let adapter_toc = split::nest_subpages(adapter_toc, &page_metas);
+    let toc = toc::build_toc(
+        &page_metas,
+        &adapter_toc,
+        config.crawl.max_toc_depth,
+        config.crawl.prune_empty_sections,
+    );
 
     // --- Phase 5: Assemble KB ---
-    progress.phase("Assembling knowledge base");
+    progress.phase(Phase::Assemble);
     let assemble_config = AssembleConfig {
         kb_id: kb_id.clone(),
         name: config.name.clone(),
         source_url: config.url.to_string(),
         output_root: config.output_root.clone(),
         tool_version: config.tool_version.clone(),
+        clock,
+        crawl_scope: Some(CrawlScope::new(config.url.as_str(), mode, &config.crawl)),
     };
 
     let assemble_result =
         crate::assembler::assemble(&assemble_config, &assembled_pages, &toc)?;
+    write_related(&storage, &kb_id.to_string(), &page_metas, &assemble_result.kb_path).await?;
 
-    // --- Phase 6: Enrichment ---
-    progress.phase("Running LLM enrichment");
-
+    // --- Phase 6-7: Enrichment & artifacts ---
     let enrich_config = EnrichmentConfig {
         bridge_cmd: config.bridge_cmd.clone(),
         bridge_script: config.bridge_script.clone(),
@@ -215,91 +296,543 @@ pub async fn add_kb(
         model_id: config.model_id.clone(),
         kb_name: config.name.clone(),
         kb_source_url: config.url.to_string(),
+        section_chunks: config.section_chunks,
+        startup_timeout_secs: config.bridge_startup_timeout_secs,
+        custom_tasks: config.custom_tasks.clone(),
+        fallback: config.summary_fallback,
+        budget: budget.clone(),
+    };
+    enrich_and_write_artifacts(
+        &ArtifactInputs {
+            enrich: &enrich_config,
+            metas: &page_metas,
+            pages: &assembled_pages,
+            toc: &toc,
+            kb_path: &assemble_result.kb_path,
+            tool_version: &config.tool_version,
+            layout: config.artifact_layout,
+            llms_txt_links: config.llms_txt_links,
+            compress_artifacts: config.compress_artifacts,
+            clock,
+        },
+        &storage,
+        progress,
+    )
+    .await?;
+
+    let result = AddKbResult {
+        kb_path: assemble_result.kb_path,
+        kb_id,
+        page_count: assembled_pages.len(),
+        method,
+        elapsed: start.elapsed(),
+        crawl: crawl_result,
+        merged: false,
+    };
+
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: result.page_count,
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
+    info!(
+        kb_id = %result.kb_id,
+        page_count = result.page_count,
+        method = %result.method,
+        elapsed_ms = result.elapsed.as_millis(),
+        "add pipeline complete"
+    );
+
+    Ok(result)
+}
+
+/// The KB directory directly under `output_root` whose manifest names `url`
+/// as its source, if any.
+pub fn find_existing_kb(output_root: &Path, url: &Url) -> Option<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(output_root)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|dir| dir.join("manifest.json").is_file())
+        .collect();
+    dirs.sort();
+    dirs.into_iter().find(|dir| {
+        crate::update::load_manifest(dir).is_ok_and(|m| m.source_url == url.as_str())
+    })
+}
+
+/// `add` with [`AddKbConfig::merge`]: fetch the pages the KB at `kb_path`
+/// does not store yet and append them to it.
+async fn merge_into_existing(
+    config: &AddKbConfig,
+    kb_path: &Path,
+    progress: &dyn ProgressReporter,
+) -> Result<AddKbResult> {
+    let start = Instant::now();
+
+    progress.phase(Phase::LoadKb);
+    let manifest = crate::update::load_manifest(kb_path)?;
+    manifest.ensure_current_schema(kb_path)?;
+    let kb_id = manifest.id.clone();
+    let storage = Storage::open(&kb_path.join("indexes").join("contextbuilder.db")).await?;
+
+    // Stored pages are not fetched again; their stored links are followed
+    let stored = storage.list_pages_by_kb(&kb_id.to_string()).await?;
+    let mut links: HashMap<String, Vec<String>> = HashMap::new();
+    for (path, to_url) in storage.list_links_by_kb(&kb_id.to_string()).await? {
+        links.entry(path).or_default().push(to_url);
+    }
+    let known: HashMap<String, Vec<String>> = stored
+        .iter()
+        .map(|page| (page.url.clone(), links.remove(&page.path).unwrap_or_default()))
+        .collect();
+    let existing_paths: Vec<String> = stored.into_iter().map(|page| page.path).collect();
+    info!(%kb_id, kb_path = %kb_path.display(), stored = known.len(), "merging into existing KB");
+
+    let budget = parallelism_budget(&config.crawl);
+    let mode = effective_mode(config);
+    let (fetched_pages, method, crawl_result) =
+        fetch_source(config, mode, &kb_id, &storage, budget.as_ref(), &known, progress).await?;
+
+    let page_count = if fetched_pages.is_empty() {
+        info!(%kb_id, "no new pages to merge");
+        existing_paths.len()
+    } else {
+        let append_config = crate::append::AddPagesConfig {
+            kb_path: kb_path.to_path_buf(),
+            urls: Vec::new(),
+            crawl: config.crawl.clone(),
+            tool_version: config.tool_version.clone(),
+            model_id: config.model_id.clone(),
+            bridge_cmd: config.bridge_cmd.clone(),
+            bridge_script: config.bridge_script.clone(),
+            bridge_working_dir: config.bridge_working_dir.clone(),
+            bridge_startup_timeout_secs: config.bridge_startup_timeout_secs,
+            section_chunks: config.section_chunks,
+            deterministic: config.deterministic,
+            custom_tasks: config.custom_tasks.clone(),
+            summary_fallback: config.summary_fallback,
+            artifact_layout: config.artifact_layout,
+            llms_txt_links: config.llms_txt_links,
+            compress_artifacts: config.compress_artifacts,
+        };
+        crate::append::append_fetched(
+            &append_config,
+            &manifest,
+            &storage,
+            fetched_pages,
+            &existing_paths,
+            progress,
+        )
+        .await?
+        .page_count
     };
 
+    let result = AddKbResult {
+        kb_path: kb_path.to_path_buf(),
+        kb_id,
+        page_count,
+        method,
+        elapsed: start.elapsed(),
+        crawl: crawl_result,
+        merged: true,
+    };
+
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: result.page_count,
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
+    info!(
+        kb_id = %result.kb_id,
+        page_count = result.page_count,
+        method = %result.method,
+        elapsed_ms = result.elapsed.as_millis(),
+        "add pipeline merged into existing KB"
+    );
+
+    Ok(result)
+}
+
+/// The discovery mode `config` runs with. Depth 0 means "just the start
+/// page": llms.txt discovery would pull in every linked page, so it is
+/// skipped.
+fn effective_mode(config: &AddKbConfig) -> CrawlMode {
+    if config.crawl.depth == 0 {
+        CrawlMode::Crawl
+    } else {
+        config.mode
+    }
+}
+
+/// Fetch the source's pages in `mode`, storing them in `storage`.
+///
+/// Pages in `known` (stored URL to its stored outgoing links) are not
+/// fetched again. Returns the pages, the method used, and the crawl summary
+/// when the crawler ran.
+async fn fetch_source(
+    config: &AddKbConfig,
+    mode: CrawlMode,
+    kb_id: &KbId,
+    storage: &dyn StorageBackend,
+    budget: Option<&Arc<Semaphore>>,
+    known: &HashMap<String, Vec<String>>,
+    progress: &dyn ProgressReporter,
+) -> Result<(Vec<FetchedPage>, String, Option<CrawlResult>)> {
+    let crawl = |seeds: Vec<Url>| {
+        progress.phase(Phase::Crawl);
+        crawl_pages(config, seeds, budget, kb_id, storage, known, progress)
+    };
+    Ok(match mode {
+        CrawlMode::LlmsTxt => {
+            progress.phase(Phase::Discover);
+            discover_and_fetch(&config.url, storage, kb_id, &config.crawl, known, progress).await?
+        }
+        CrawlMode::Crawl => {
+            let (result, pages) = crawl(Vec::new()).await?;
+            (pages, "crawl".to_string(), Some(result))
+        }
+        CrawlMode::Auto => {
+            // Auto mode: try llms.txt first, then the sitemap, and fall back
+            // to a full crawl. An llms.txt whose pages are all known has
+            // nothing new, which is no reason to crawl instead.
+            progress.phase(Phase::Discover);
+            match discover_and_fetch(&config.url, storage, kb_id, &config.crawl, known, progress)
+                .await
+            {
+                Ok((pages, method, result))
+                    if !pages.is_empty() || (!known.is_empty() && method != "none") =>
+                {
+                    (pages, method, result)
+                }
+                _ => {
+                    let seeds = discover_sitemap_pages(&config.url, &config.crawl).await;
+                    let method = if seeds.is_empty() { "crawl" } else { "sitemap" };
+                    let (result, pages) = crawl(seeds).await?;
+                    (pages, method.to_string(), Some(result))
+                }
+            }
+        }
+    })
+}
+
+/// Discovery settings (timeouts, size cap, auth, ...) taken from the crawl
+/// config.
+fn discovery_options(crawl_config: &CrawlConfig) -> DiscoveryOptions {
+    DiscoveryOptions {
+        timeouts: crawl_config.timeouts,
+        offline: crawl_config.offline,
+        max_redirects: crawl_config.max_redirects,
+        max_full_size: crawl_config.max_full_size,
+        auth: crawl_config.auth.clone(),
+    }
+}
+
+/// The pages listed by the source's `sitemap.xml`, or none when it has no
+/// usable sitemap. A failure is logged rather than returned, since the full
+/// crawl remains.
+async fn discover_sitemap_pages(url: &Url, crawl_config: &CrawlConfig) -> Vec<Url> {
+    let opts = discovery_options(crawl_config);
+    contextbuilder_discovery::discover_sitemap(url, &opts)
+        .await
+        .unwrap_or_else(|e| {
+            debug!(%url, error = %e, "sitemap discovery failed");
+            Vec::new()
+        })
+}
+
+// ---------------------------------------------------------------------------
+// Conversion
+// ---------------------------------------------------------------------------
+
+/// Markdown conversion options for a fetched page.
+pub(crate) fn convert_options(page: &FetchedPage, crawl: &CrawlConfig) -> ConvertOptions {
+    ConvertOptions {
+        source_url: page.meta.url.clone(),
+        title: page.meta.title.clone(),
+        fetched_at: Some(page.meta.fetched_at.to_rfc3339()),
+        description: page.meta.description.clone(),
+        comment_metadata: crawl.comment_metadata,
+        preserve_source_frontmatter: crawl.preserve_source_frontmatter,
+        detect_language: crawl.detect_language || !crawl.languages.is_empty(),
+        strip_selectors: crawl.strip_selectors.clone(),
+        max_title_len: crawl.max_title_len,
+        link_rewrites: crawl.link_rewrites.clone(),
+        dedupe_adjacent: crawl.dedupe_adjacent_items,
+        preserve_anchors: crawl.preserve_anchors,
+        // Asset mode leaves data URIs for the asset step, which only runs
+        // with download_assets
+        inline_svg: match crawl.inline_svg {
+            InlineSvg::Asset if !crawl.download_assets => InlineSvg::Code,
+            mode => mode,
+        },
+        ..Default::default()
+    }
+}
+
+/// Convert `pages` to Markdown on blocking worker threads, at most
+/// `crawl.convert_concurrency` at a time (and within `budget`). Results are
+/// in page order.
+pub(crate) async fn convert_pages(
+    pages: &[&FetchedPage],
+    crawl: &CrawlConfig,
+    budget: Option<&Arc<Semaphore>>,
+) -> Vec<Result<ConvertResult>> {
+    let jobs: Vec<_> = pages
+        .iter()
+        .map(|page| (page.html.clone(), convert_options(page, crawl)))
+        .collect();
+    let results = map_blocking(jobs, crawl.convert_concurrency, budget, |(html, opts)| {
+        contextbuilder_markdown::convert(&html, &opts)
+    })
+    .await;
+    pages
+        .iter()
+        .zip(results)
+        .map(|(page, result)| {
+            result.unwrap_or_else(|e| {
+                Err(ContextBuilderError::parse(format!(
+                    "{}: conversion panicked: {e}",
+                    page.meta.url
+                )))
+            })
+        })
+        .collect()
+}
+
+/// Run `f` over `items` on the blocking thread pool with at most `limit`
+/// calls in flight, returning results in input order.
+///
+/// A task is only spawned once a permit is free (from `budget` too, when
+/// given), so a large batch never floods the blocking pool. A panicking call
+/// yields `Err` with the panic message.
+async fn map_blocking<T, R, F>(
+    items: Vec<T>,
+    limit: usize,
+    budget: Option<&Arc<Semaphore>>,
+    f: F,
+) -> Vec<std::result::Result<R, String>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+    let f = Arc::new(f);
+    let mut handles = Vec::with_capacity(items.len());
+    for item in items {
+        let permit = Arc::clone(&semaphore)
+            .acquire_owned()
+            .await
+            .expect("conversion semaphore is never closed");
+        let shared = take_budget(budget).await;
+        let f = Arc::clone(&f);
+        handles.push(tokio::task::spawn_blocking(move || {
+            let _permits = (permit, shared);
+            f(item)
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.map_err(|e| e.to_string()));
+    }
+    results
+}
+
+/// The run-wide parallelism budget (`[defaults] max_parallelism`), if set.
+pub(crate) fn parallelism_budget(crawl: &CrawlConfig) -> Option<Arc<Semaphore>> {
+    crawl.max_parallelism.map(|n| Arc::new(Semaphore::new(n.max(1))))
+}
+
+/// Take one unit of `budget`, waiting for it if needed.
+pub(crate) async fn take_budget(budget: Option<&Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+    match budget {
+        Some(budget) => Some(
+            Arc::clone(budget)
+                .acquire_owned()
+                .await
+                .expect("parallelism budget is never closed"),
+        ),
+        None => None,
+    }
+}
+
+/// Whether a page detected as `language` passes the `languages` filter.
+///
+/// An empty filter keeps everything, and so does an undetermined language:
+/// short or mixed-language pages are not dropped on a guess.
+pub(crate) fn language_allowed(language: Option<&str>, languages: &[String]) -> bool {
+    match language {
+        Some(lang) if !languages.is_empty() => {
+            languages.iter().any(|l| l.eq_ignore_ascii_case(lang))
+        }
+        _ => true,
+    }
+}
+
+/// Seed KB `kb_id`'s enrichment cache with `model_id` entries from the KB
+/// at `source`.
+async fn warm_cache(storage: &Storage, kb_id: &str, source: &Path, model_id: &str) -> Result<()> {
+    let db_path = source.join("indexes").join("contextbuilder.db");
+    if !db_path.exists() {
+        return Err(ContextBuilderError::validation(format!(
+            "cannot warm the cache from '{}': no KB database at {}",
+            source.display(),
+            db_path.display()
+        )));
+    }
+    let source_db = Storage::open_readonly(&db_path).await?;
+    let copied = storage.copy_enrichment_cache_from(&source_db, kb_id, model_id).await?;
+    info!(source = %source.display(), copied, "warmed enrichment cache");
+    Ok(())
+}
+
+/// Delete the stored row for a page that was fetched but left out of the KB.
+pub(crate) async fn drop_stored_page(
+    storage: &dyn StorageBackend,
+    kb_id: &str,
+    path: &str,
+) -> Result<()> {
+    if let Some(stored) = storage.get_page(kb_id, path).await? {
+        storage.delete_page(&stored.id).await?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Enrichment & artifacts
+// ---------------------------------------------------------------------------
+
+/// Inputs for the enrichment and artifact phases of a KB build.
+pub(crate) struct ArtifactInputs<'a> {
+    /// Bridge settings, KB name/source, and custom tasks.
+    pub enrich: &'a EnrichmentConfig,
+    /// Metadata for every page in the KB.
+    pub metas: &'a [contextbuilder_shared::PageMeta],
+    /// Converted pages, in the same order as `metas`.
+    pub pages: &'a [AssemblePage],
+    /// The KB's table of contents.
+    pub toc: &'a Toc,
+    /// Assembled KB directory.
+    pub kb_path: &'a Path,
+    /// Tool version string.
+    pub tool_version: &'a str,
+    /// Where artifacts are written inside `artifacts/`.
+    pub layout: ArtifactLayout,
+    /// What the generated `llms.txt` links to.
+    pub llms_txt_links: LinkBase,
+    /// Also write a gzip copy of every artifact.
+    pub compress_artifacts: bool,
+    /// Clock for manifest timestamps.
+    pub clock: Clock,
+}
+
+/// Run LLM enrichment over every page, then generate and write all artifacts.
+///
+/// Pages already enriched in an earlier build are served from the cache.
+pub(crate) async fn enrich_and_write_artifacts(
+    inputs: &ArtifactInputs<'_>,
+    storage: &dyn StorageBackend,
+    progress: &dyn ProgressReporter,
+) -> Result<()> {
+    progress.phase(Phase::Enrich);
+    let name = inputs.enrich.kb_name.as_str();
+    let source_url = inputs.enrich.kb_source_url.as_str();
+
     // Collect pages with their markdown content for enrichment
-    let pages_with_content: Vec<(contextbuilder_shared::PageMeta, String)> = fetched_pages
+    let pages_with_content: Vec<(contextbuilder_shared::PageMeta, String)> = inputs
+        .metas
         .iter()
-        .zip(assembled_pages.iter())
-        .map(|(fp, ap)| (fp.meta.clone(), ap.markdown.clone()))
+        .zip(inputs.pages.iter())
+        .map(|(meta, ap)| (meta.clone(), ap.markdown.clone()))
         .collect();
 
-    let enrich_progress = PipelineEnrichmentProgress { inner: progress };
     let enrich_results = enrichment::run_enrichment(
-        &enrich_config,
+        inputs.enrich,
         &pages_with_content,
-        &toc,
-        &storage,
-        &enrich_progress,
+        inputs.toc,
+        storage,
+        progress,
     )
     .await?;
 
-    // --- Phase 7: Generate & write artifacts ---
-    progress.phase("Generating artifacts");
+    progress.phase(Phase::GenerateArtifacts);
 
     let summary_text = enrich_results
         .summaries
         .values()
         .next()
         .cloned()
-        .unwrap_or_else(|| format!("Documentation for {}", config.name));
+        .unwrap_or_else(|| format!("Documentation for {name}"));
 
     let llms_txt = contextbuilder_artifacts::generate_llms_txt(
-        &config.name,
+        name,
         &summary_text,
-        &toc,
+        inputs.toc,
         &enrich_results.descriptions,
-        config.url.as_str(),
-        &config.tool_version,
+        source_url,
+        inputs.tool_version,
+        inputs.llms_txt_links,
     );
 
-    let full_pages: Vec<contextbuilder_artifacts::FullPage> = assembled_pages
+    let full_pages: Vec<contextbuilder_artifacts::FullPage> = inputs
+        .pages
         .iter()
-        .zip(fetched_pages.iter())
-        .map(|(ap, fp)| contextbuilder_artifacts::FullPage {
+        .zip(inputs.metas.iter())
+        .map(|(ap, meta)| contextbuilder_artifacts::FullPage {
             title: ap.title.clone(),
-            url: fp.meta.url.clone(),
+            url: meta.url.clone(),
             content: ap.markdown.clone(),
         })
         .collect();
 
     let llms_full_txt = contextbuilder_artifacts::generate_llms_full_txt(
-        &config.name,
+        name,
         &full_pages,
-        config.url.as_str(),
-        &config.tool_version,
+        source_url,
+        inputs.tool_version,
     );
 
     let skill_md = contextbuilder_artifacts::generate_skill_md(
-        &config.name,
-        config.url.as_str(),
+        name,
+        source_url,
         &summary_text,
         enrich_results.skill_md.as_deref(),
-        &config.tool_version,
+        inputs.tool_version,
     );
 
     let rules = contextbuilder_artifacts::generate_rules(
-        &config.name,
-        config.url.as_str(),
+        name,
+        source_url,
         enrich_results.rules.as_deref(),
-        &config.tool_version,
+        inputs.tool_version,
     );
 
     let style = contextbuilder_artifacts::generate_style(
-        &config.name,
-        config.url.as_str(),
+        name,
+        source_url,
         enrich_results.style.as_deref(),
-        &config.tool_version,
+        inputs.tool_version,
     );
 
     let do_dont = contextbuilder_artifacts::generate_do_dont(
-        &config.name,
-        config.url.as_str(),
+        name,
+        source_url,
         enrich_results.do_dont.as_deref(),
-        &config.tool_version,
+        inputs.tool_version,
     );
 
-    let artifacts: Vec<(&str, &str)> = vec![
+    let custom = render_custom_artifacts(
+        &inputs.enrich.custom_tasks,
+        &enrich_results,
+        name,
+        source_url,
+        inputs.tool_version,
+    );
+
+    let mut artifacts: Vec<(&str, &str)> = vec![
         ("llms.txt", &llms_txt),
         ("llms-full.txt", &llms_full_txt),
         ("SKILL.md", &skill_md),
@@ -307,72 +840,145 @@ pub async fn add_kb(
         ("style.md", &style),
         ("do_dont.md", &do_dont),
     ];
+    artifacts.extend(custom.iter().map(|(file, content)| (*file, content.as_str())));
+    let laid_out: Vec<(String, &str)> = artifacts
+        .iter()
+        .map(|(file, content)| (inputs.layout.artifact_path(file, name), *content))
+        .collect();
+    let artifacts: Vec<(&str, &str)> =
+        laid_out.iter().map(|(file, content)| (file.as_str(), *content)).collect();
 
-    let now = chrono::Utc::now();
+    let now = inputs.clock.now();
     let enrichment_meta = EnrichmentMeta {
         model: enrich_results.model.clone(),
         total_tokens_in: enrich_results.total_tokens_in,
         total_tokens_out: enrich_results.total_tokens_out,
         cache_hits: enrich_results.cache_hits,
         cache_misses: enrich_results.cache_misses,
+        cache_by_task: enrich_results.cache_by_task.clone(),
+        extractive: enrich_results.extractive,
         completed_at: now.to_rfc3339(),
     };
 
-    crate::assembler::assemble_artifacts(&assemble_result.kb_path, &artifacts, &enrichment_meta)?;
-
-    let result = AddKbResult {
-        kb_path: assemble_result.kb_path,
-        kb_id,
-        page_count: assembled_pages.len(),
-        method,
-        elapsed: start.elapsed(),
-    };
-
-    progress.done(&result);
-
-    info!(
-        kb_id = %result.kb_id,
-        page_count = result.page_count,
-        method = %result.method,
-        elapsed_ms = result.elapsed.as_millis(),
-        "add pipeline complete"
-    );
-
-    Ok(result)
+    crate::assembler::assemble_artifacts(
+        inputs.kb_path,
+        &artifacts,
+        inputs.compress_artifacts,
+        &enrichment_meta,
+        inputs.clock,
+    )?;
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
-// Enrichment progress adapter
+// Section chunks
 // ---------------------------------------------------------------------------
 
-/// Adapts a `ProgressReporter` to the `EnrichmentProgress` interface.
-struct PipelineEnrichmentProgress<'a> {
-    inner: &'a dyn ProgressReporter,
+/// Split each page into section chunks and replace its stored chunks.
+pub(crate) async fn store_chunks(
+    storage: &dyn StorageBackend,
+    kb_id: &str,
+    pages: &[AssemblePage],
+    ids: IdSource,
+) -> Result<usize> {
+    let mut count = 0;
+    for page in pages {
+        let chunks = crate::chunks::build_chunks(kb_id, &page.path, &page.markdown, ids);
+        count += chunks.len();
+        storage.replace_chunks(kb_id, &page.path, &chunks).await?;
+    }
+    info!(pages = pages.len(), chunks = count, "stored section chunks");
+    Ok(count)
+}
+
+/// Store the Markdown of each of `pages` with its fetched page's metadata,
+/// so storage can serve and search page bodies without the KB's files.
+pub(crate) async fn store_page_contents<'a>(
+    storage: &dyn StorageBackend,
+    fetched: &[FetchedPage],
+    pages: impl IntoIterator<Item = &'a AssemblePage>,
+) -> Result<()> {
+    let metas: HashMap<&str, &PageMeta> =
+        fetched.iter().map(|p| (p.meta.path.as_str(), &p.meta)).collect();
+    for page in pages {
+        if let Some(meta) = metas.get(page.path.as_str()) {
+            storage.upsert_page_with_content(meta, &page.markdown).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Render each custom task's output as `(file name, artifact content)`.
+pub(crate) fn render_custom_artifacts<'a>(
+    tasks: &'a [CustomTaskConfig],
+    results: &enrichment::EnrichmentResults,
+    kb_name: &str,
+    source_url: &str,
+    tool_version: &str,
+) -> Vec<(&'a str, String)> {
+    tasks
+        .iter()
+        .map(|task| {
+            let content = contextbuilder_artifacts::generate_custom(
+                task.title.as_deref().unwrap_or(&task.name),
+                kb_name,
+                source_url,
+                results.custom_artifacts.get(&task.name).map(String::as_str),
+                tool_version,
+            );
+            (task.output.as_str(), content)
+        })
+        .collect()
 }
 
-impl EnrichmentProgress for PipelineEnrichmentProgress<'_> {
-    fn phase(&self, name: &str) {
-        self.inner.phase(name);
+/// The clock for a build: fixed for deterministic builds, the system clock
+/// otherwise.
+pub(crate) fn clock_for(deterministic: bool) -> Clock {
+    if deterministic {
+        Clock::deterministic()
+    } else {
+        Clock::System
     }
+}
 
-    fn task_progress(&self, current: usize, total: usize, detail: &str) {
-        self.inner.phase(&format!("[{current}/{total}] {detail}"));
+/// Normalize fetched pages for reproducible output: order them by path and
+/// stamp them with the fixed clock instead of their fetch time.
+pub(crate) fn make_deterministic(pages: &mut [FetchedPage], clock: Clock) {
+    pages.sort_by(|a, b| a.meta.path.cmp(&b.meta.path));
+    for page in pages {
+        page.meta.fetched_at = clock.now();
     }
 }
 
+/// Compute related pages from the stored link graph and write `related.json`.
+pub(crate) async fn write_related(
+    storage: &dyn StorageBackend,
+    kb_id: &str,
+    pages: &[contextbuilder_shared::PageMeta],
+    kb_path: &Path,
+) -> Result<()> {
+    let links = storage.list_links_by_kb(kb_id).await?;
+    let related = crate::related::compute_related(pages, &links, crate::related::RELATED_TOP_K);
+    crate::assembler::write_json(&kb_path.join("related.json"), &related)
+}
+
 // ---------------------------------------------------------------------------
 // Discovery path
 // ---------------------------------------------------------------------------
 
-/// Try llms.txt discovery and fetch linked pages.
+/// Try llms.txt discovery and fetch linked pages. Returns the pages, the
+/// method used, and a summary of the fetches when llms.txt listed new pages.
 async fn discover_and_fetch(
     url: &Url,
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     kb_id: &KbId,
+    crawl_config: &CrawlConfig,
+    known: &HashMap<String, Vec<String>>,
     progress: &dyn ProgressReporter,
-) -> Result<(Vec<FetchedPage>, String)> {
-    let opts = DiscoveryOptions { timeout_secs: 10 };
-    let discovery = contextbuilder_discovery::discover(url, &opts).await?;
+) -> Result<(Vec<FetchedPage>, String, Option<CrawlResult>)> {
+    let start = Instant::now();
+    let opts = discovery_options(crawl_config);
+    let discovery = discover_with_retry(url, &opts, crawl_config).await?;
 
     match discovery {
         DiscoveryResult::Found {
@@ -386,88 +992,155 @@ async fn discover_and_fetch(
                 "llms.txt discovered"
             );
 
-            // Extract URLs from the parsed llms.txt
+            // Extract URLs from the parsed llms.txt, `## Optional` links last
             let urls: Vec<Url> = parsed
-                .entries
-                .iter()
+                .entries_to_fetch(crawl_config.include_optional_links)
+                .into_iter()
                 .filter_map(|e| Url::parse(&e.url).ok())
+                .filter(|url| !known.contains_key(url.as_str()))
                 .collect();
 
             if urls.is_empty() {
-                return Ok((vec![], "llms-txt".to_string()));
+                return Ok((vec![], "llms-txt".to_string(), None));
             }
 
             // Fetch each linked page
-            let client = reqwest::Client::builder()
-                .user_agent(concat!("ContextBuilder/", env!("CARGO_PKG_VERSION")))
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .map_err(|e| ContextBuilderError::Network(format!("client build: {e}")))?;
+            let client = build_page_client(crawl_config)?;
 
+            let drop_selectors =
+                contextbuilder_crawler::DropSelectors::new(&crawl_config.drop_if_selector_matches)?;
             let mut pages = Vec::new();
+            let mut result = CrawlResult::default();
+            let mut refused = 0;
             let total = urls.len();
 
             for (i, page_url) in urls.iter().enumerate() {
-                progress.page_fetched(page_url.as_str(), i + 1, total);
+                progress.event(ProgressEvent::PageFetched {
+                    url: page_url.to_string(),
+                    current: i + 1,
+                    total,
+                });
 
-                match fetch_single_page(&client, page_url, &kb_id.to_string()).await {
+                let fetched = fetch_single_page(
+                    &client,
+                    page_url,
+                    &kb_id.to_string(),
+                    crawl_config,
+                )
+                .await;
+                match fetched {
                     Ok(page) => {
+                        if let Some(selector) = drop_selectors.matching(&page.html) {
+                            debug!(
+                                url = %page_url,
+                                selector,
+                                "page matches drop selector, skipping"
+                            );
+                            result.pages_skipped += 1;
+                            continue;
+                        }
                         let _ = storage.upsert_page(&page.meta).await;
                         pages.push(page);
                     }
                     Err(e) => {
                         warn!(url = %page_url, error = %e, "failed to fetch llms.txt link");
+                        progress.warning(format!("failed to fetch {page_url}: {e}"));
+                        refused += usize::from(e.needs_auth());
+                        result.errors.push((page_url.to_string(), e.to_string()));
                     }
                 }
             }
+            if let Some(hint) = contextbuilder_crawler::auth_hint(refused, total) {
+                warn!("{hint}");
+                progress.warning(hint);
+            }
+
+            result.pages_fetched = pages.len();
+            result.duration = start.elapsed();
+            Ok((pages, "llms-txt".to_string(), Some(result)))
+        }
+        DiscoveryResult::NotFound => Ok((vec![], "none".to_string(), None)),
+    }
+}
 
-            Ok((pages, "llms-txt".to_string()))
+/// [`contextbuilder_discovery::discover`], retried after transient failures
+/// up to `discovery_retries` times, so a momentary blip does not send auto
+/// mode to the crawl fallback.
+async fn discover_with_retry(
+    url: &Url,
+    opts: &DiscoveryOptions,
+    crawl_config: &CrawlConfig,
+) -> Result<DiscoveryResult> {
+    let mut delay = std::time::Duration::from_millis(crawl_config.discovery_retry_delay_ms);
+    let mut attempt = 0;
+    loop {
+        match contextbuilder_discovery::discover(url, opts).await {
+            Err(e) if e.is_transient() && attempt < crawl_config.discovery_retries => {
+                attempt += 1;
+                warn!(%url, attempt, error = %e, "llms.txt discovery failed, retrying");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
         }
-        DiscoveryResult::NotFound => Ok((vec![], "none".to_string())),
     }
 }
 
+/// HTTP client for fetching individual pages outside the crawler.
+pub(crate) fn build_page_client(crawl_config: &CrawlConfig) -> Result<reqwest::Client> {
+    contextbuilder_crawler::with_auth(reqwest::Client::builder(), &crawl_config.auth)?
+        .redirect(reqwest::redirect::Policy::limited(crawl_config.max_redirects))
+        .pool_max_idle_per_host(crawl_config.pool_max_idle_per_host)
+        .tcp_keepalive(std::time::Duration::from_secs(crawl_config.tcp_keepalive_secs))
+        .connect_timeout(crawl_config.timeouts.connect())
+        .read_timeout(crawl_config.timeouts.read())
+        .timeout(crawl_config.timeouts.total())
+        .build()
+        .map_err(|e| ContextBuilderError::Network(format!("client build: {e}")))
+}
+
 /// Fetch a single page via HTTP.
-async fn fetch_single_page(
+pub(crate) async fn fetch_single_page(
     client: &reqwest::Client,
     url: &Url,
     kb_id: &str,
+    crawl_config: &CrawlConfig,
 ) -> Result<FetchedPage> {
-    let response = client
-        .get(url.as_str())
-        .send()
-        .await
-        .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
+    let response = client.get(url.as_str()).send().await.map_err(|e| {
+        if e.is_redirect() {
+            ContextBuilderError::redirect_limit(url, crawl_config.max_redirects)
+        } else {
+            ContextBuilderError::Network(format!("{url}: {e}"))
+        }
+    })?;
 
     let status = response.status();
     let status_code = status.as_u16();
 
     if !status.is_success() {
-        return Err(ContextBuilderError::Network(format!(
-            "{url}: HTTP {status}"
-        )));
+        return Err(ContextBuilderError::http_status(url, status_code, status));
     }
 
-    let body = response
-        .text()
+    let body = contextbuilder_crawler::charset::response_text(response)
         .await
         .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
 
-    let content_hash = {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(body.as_bytes());
-        format!("{:x}", hasher.finalize())
-    };
+    let content_hash = contextbuilder_crawler::content_hash(&body, url, crawl_config.hash_basis);
 
-    let page_path = contextbuilder_crawler::url_to_path(url);
+    let page_path = contextbuilder_crawler::page_path(url, &crawl_config.significant_query_params);
 
-    let title = {
+    let (full_title, description) = {
         let doc = scraper::Html::parse_document(&body);
         let h1_sel = scraper::Selector::parse("h1").unwrap();
-        doc.select(&h1_sel)
+        let full_title = doc
+            .select(&h1_sel)
             .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
+            .map(|el| el.text().collect::<String>().trim().to_string());
+        (full_title, contextbuilder_crawler::meta_description(&doc))
+    };
+    let title = match (&full_title, crawl_config.max_title_len) {
+        (Some(t), Some(max)) => Some(contextbuilder_shared::truncate_title(t, max)),
+        _ => full_title.clone(),
     };
 
     let meta = contextbuilder_shared::PageMeta {
@@ -480,13 +1153,13 @@ async fn fetch_single_page(
         fetched_at: chrono::Utc::now(),
         status_code: Some(status_code),
         content_len: Some(body.len()),
+        depth: None,
+        description,
     };
 
     let content = contextbuilder_crawler::ExtractedContent {
         html: body.clone(),
-        meta: contextbuilder_crawler::adapters::PageMeta {
-            title: meta.title.clone(),
-        },
+        meta: contextbuilder_crawler::adapters::PageMeta { title: full_title },
     };
 
     Ok(FetchedPage {
@@ -501,17 +1174,24 @@ async fn fetch_single_page(
 // Crawl path
 // ---------------------------------------------------------------------------
 
-/// Run the crawler to fetch pages.
+/// Run the crawler to fetch the source's pages, or only `seeds` when given.
 async fn crawl_pages(
-    url: &Url,
-    crawl_config: &CrawlConfig,
+    config: &AddKbConfig,
+    seeds: Vec<Url>,
+    budget: Option<&Arc<Semaphore>>,
     kb_id: &KbId,
-    storage: &Storage,
-    _progress: &dyn ProgressReporter,
+    storage: &dyn StorageBackend,
+    known: &HashMap<String, Vec<String>>,
+    progress: &dyn ProgressReporter,
 ) -> Result<(CrawlResult, Vec<FetchedPage>)> {
-    let crawler = Crawler::new(crawl_config.clone())?;
+    let mut crawler = Crawler::new(config.crawl.clone())?
+        .with_known_pages(known.iter().map(|(url, links)| (url.clone(), links.clone())))
+        .with_seeds(seeds);
+    if let Some(budget) = budget {
+        crawler = crawler.with_budget(Arc::clone(budget));
+    }
     let (result, pages) = crawler
-        .crawl(url, &kb_id.to_string(), storage)
+        .crawl(&config.url, &kb_id.to_string(), storage)
         .await?;
 
     info!(
@@ -520,6 +1200,833 @@ async fn crawl_pages(
         errors = result.errors.len(),
         "crawl complete"
     );
+    for (url, error) in &result.errors {
+        progress.warning(format!("failed to fetch {url}: {error}"));
+    }
+    if let Some(hint) = result.auth_hint() {
+        warn!("{hint}");
+        progress.warning(hint);
+    }
 
     Ok((result, pages))
 }
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+
+    #[tokio::test]
+    async fn map_blocking_caps_simultaneous_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let hook = {
+            let (active, peak) = (Arc::clone(&active), Arc::clone(&peak));
+            move |i: usize| {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                active.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        };
+
+        let results = map_blocking((0..24).collect(), 3, None, hook).await;
+
+        let doubled: Vec<usize> = results.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(doubled, (0..24).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3, "peak {}", peak.load(Ordering::SeqCst));
+        assert!(peak.load(Ordering::SeqCst) > 1, "conversions never overlapped");
+    }
+
+    #[tokio::test]
+    async fn map_blocking_stays_within_shared_budget() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let crawl = CrawlConfig {
+            max_parallelism: Some(2),
+            ..Default::default()
+        };
+        let budget = parallelism_budget(&crawl).unwrap();
+        let held = take_budget(Some(&budget)).await;
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let hook = {
+            let (active, peak) = (Arc::clone(&active), Arc::clone(&peak));
+            move |i: usize| {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                active.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        };
+
+        // One unit is held elsewhere, so conversions run one at a time
+        let results = map_blocking((0..8).collect(), 4, Some(&budget), hook).await;
+        assert_eq!(results.len(), 8);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        drop(held);
+        assert_eq!(budget.available_permits(), 2);
+        assert!(parallelism_budget(&CrawlConfig::default()).is_none());
+    }
+
+    #[tokio::test]
+    async fn offline_add_fails_fast() {
+        let output_root =
+            std::env::temp_dir().join(format!("cb-offline-test-{}", uuid::Uuid::now_v7()));
+        let config = AddKbConfig {
+            url: Url::parse("https://docs.example.com/").unwrap(),
+            name: "Example".into(),
+            output_root: output_root.clone(),
+            mode: CrawlMode::Auto,
+            crawl: CrawlConfig {
+                offline: true,
+                ..Default::default()
+            },
+            tool_version: "test".into(),
+            model_id: "test-model".into(),
+            bridge_cmd: "false".into(),
+            bridge_script: String::new(),
+            bridge_working_dir: ".".into(),
+            bridge_startup_timeout_secs: 1,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+
+        let err = add_kb(&config, &SilentProgress).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "network error: offline mode: network access is disabled \
+             (cannot fetch https://docs.example.com/)"
+        );
+        assert!(!output_root.exists(), "nothing should be written offline");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn add_kb_emits_progress_events_in_order() {
+        use crate::progress::{Phase, ProgressEvent};
+        use crate::test_support::RecordingProgress;
+
+        let dir = crate::test_support::temp_dir("progress");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Home]({}/home): Start here\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/home"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Home</h1><p>Welcome.</p></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let progress = RecordingProgress::default();
+        let result = add_kb(&config, &progress).await.unwrap();
+
+        assert_eq!(
+            progress.phases(),
+            vec![
+                Phase::InitStorage,
+                Phase::Discover,
+                Phase::Convert,
+                Phase::BuildToc,
+                Phase::Assemble,
+                Phase::Enrich,
+                Phase::StartBridge,
+                Phase::SummarizePages,
+                Phase::GenerateDescriptions,
+                Phase::GenerateKbArtifacts,
+                Phase::StopBridge,
+                Phase::GenerateArtifacts,
+            ]
+        );
+
+        let events = progress.events();
+        assert!(events.contains(&ProgressEvent::PageFetched {
+            url: format!("{}/home", server.uri()),
+            current: 1,
+            total: 1,
+        }));
+        assert!(events.contains(&ProgressEvent::PageConverted {
+            path: "home".into(),
+            current: 1,
+            total: 1,
+        }));
+        let tasks: Vec<&str> = events
+            .iter()
+            .filter_map(|e| match e {
+                ProgressEvent::EnrichmentTask { task, .. } => Some(task.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![
+                "summarize_page",
+                "generate_description",
+                "generate_skill_md",
+                "generate_rules",
+                "generate_style",
+                "generate_do_dont",
+            ]
+        );
+        assert_eq!(
+            events.last(),
+            Some(&ProgressEvent::Done {
+                kb_id: result.kb_id.to_string(),
+                page_count: 1,
+                elapsed_ms: result.elapsed.as_millis() as u64,
+            })
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn meta_description_reaches_frontmatter_and_fallback_descriptions() {
+        let dir = crate::test_support::temp_dir("meta-description");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Setup]({}/setup)\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/setup"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                r#"<html><head>
+                <meta name="description" content="Install and configure the CLI.">
+                </head><body><main><h1>Setup</h1>
+                <p>This page walks through every option in detail.</p>
+                </main></body></html>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: "/nonexistent/contextbuilder-bridge".into(),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::Extractive,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let result = add_kb(&config, &crate::progress::SilentProgress).await.unwrap();
+
+        let page = std::fs::read_to_string(result.kb_path.join("docs/setup.md")).unwrap();
+        assert!(page.contains("description: \"Install and configure the CLI.\""), "{page}");
+        // Without the LLM, the meta description is the page's llms.txt entry
+        let llms_txt = std::fs::read_to_string(result.kb_path.join("artifacts/llms.txt")).unwrap();
+        assert!(llms_txt.contains(": Install and configure the CLI."), "{llms_txt}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn llms_txt_pages_dropped_by_selector_are_counted_as_skipped() {
+        let dir = crate::test_support::temp_dir("llms-drop");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Old]({0}/old)\n- [New]({0}/new)\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        let pages = [
+            ("/old", r#"<div class="deprecated-banner">Deprecated.</div><h1>Old</h1>"#),
+            ("/new", "<h1>New</h1><p>The current way to do it.</p>"),
+        ];
+        for (path, body) in pages {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                    "<html><body><main>{body}</main></body></html>"
+                )))
+                .mount(&server)
+                .await;
+        }
+
+        let config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig {
+                drop_if_selector_matches: vec![".deprecated-banner".into()],
+                ..CrawlConfig::default()
+            },
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: "/nonexistent/contextbuilder-bridge".into(),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::Extractive,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let result = add_kb(&config, &crate::progress::SilentProgress).await.unwrap();
+
+        let crawl = result.crawl.expect("llms.txt fetch summary");
+        assert_eq!((crawl.pages_fetched, crawl.pages_skipped), (1, 1));
+        assert_eq!(result.page_count, 1);
+        assert!(!result.kb_path.join("docs/old.md").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn warm_cache_from_sibling_kb_serves_enrichment_from_cache() {
+        let dir = crate::test_support::temp_dir("warm-cache");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Home]({}/home): Start here\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/home"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Home</h1><p>Welcome.</p></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let mut config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: true,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let first = add_kb(&config, &SilentProgress).await.unwrap();
+
+        config.warm_cache_from = Some(first.kb_path.clone());
+        let second = add_kb(&config, &SilentProgress).await.unwrap();
+        assert_ne!(first.kb_id, second.kb_id);
+
+        let manifest: contextbuilder_shared::KbManifest = serde_json::from_str(
+            &std::fs::read_to_string(second.kb_path.join("manifest.json")).unwrap(),
+        )
+        .unwrap();
+        let scope = manifest.crawl_scope.unwrap();
+        assert_eq!((scope.mode, scope.start_url), (CrawlMode::LlmsTxt, config.url.to_string()));
+        let enrichment = manifest.enrichment.unwrap();
+        assert_eq!(enrichment["cache_misses"], 0);
+        assert!(enrichment["cache_hits"].as_u64().unwrap() > 0, "{enrichment}");
+
+        config.warm_cache_from = Some(dir.join("missing"));
+        let err = add_kb(&config, &SilentProgress).await.unwrap_err();
+        assert!(err.to_string().contains("cannot warm the cache"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn deterministic_adds_get_the_same_ids_and_output() {
+        let dir = crate::test_support::temp_dir("deterministic-ids");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Home]({}/home): Start here\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/home"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Home</h1><p>Welcome.</p>\
+                 <h2>Install</h2><p>Run it.</p></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let add = |out: &str| AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join(out),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: true,
+            deterministic: true,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let first = add_kb(&add("a"), &SilentProgress).await.unwrap();
+        let second = add_kb(&add("b"), &SilentProgress).await.unwrap();
+
+        assert_eq!(first.kb_id, second.kb_id);
+        for file in ["manifest.json", "toc.json"] {
+            assert_eq!(
+                std::fs::read(first.kb_path.join(file)).unwrap(),
+                std::fs::read(second.kb_path.join(file)).unwrap(),
+                "{file} differs between builds"
+            );
+        }
+        let chunk_ids = |kb_path: PathBuf| async move {
+            let storage = Storage::open_readonly(&kb_path.join("indexes/contextbuilder.db"))
+                .await
+                .unwrap();
+            let kb_id = crate::test_support::read_manifest(&kb_path).id.to_string();
+            let chunks = storage.list_chunks_by_page(&kb_id, "home").await.unwrap();
+            chunks.into_iter().map(|c| c.id).collect::<Vec<_>>()
+        };
+        let ids = chunk_ids(first.kb_path).await;
+        assert!(!ids.is_empty());
+        assert_eq!(ids, chunk_ids(second.kb_path).await);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn auto_mode_retries_transient_discovery_failure_before_crawling() {
+        let dir = crate::test_support::temp_dir("discovery-retry");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Home]({}/home): Start here\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/home"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Home</h1><p>Welcome.</p></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::Auto,
+            crawl: CrawlConfig {
+                discovery_retry_delay_ms: 0,
+                ..Default::default()
+            },
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let result = add_kb(&config, &SilentProgress).await.unwrap();
+
+        // No crawl fallback: the summary is the llms.txt fetch's
+        assert_eq!(result.method, "llms-txt");
+        let fetched = result.crawl.as_ref().map(|crawl| crawl.pages_fetched);
+        assert_eq!(fetched, Some(result.page_count));
+        let llms_requests = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.url.path() == "/llms.txt")
+            .count();
+        assert_eq!(llms_requests, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn second_add_to_same_output_refuses_or_merges_new_pages() {
+        let dir = crate::test_support::temp_dir("add-twice");
+        let server = wiremock::MockServer::start().await;
+        let mount = |links: &'static [&'static str]| {
+            let server = &server;
+            async move {
+                server.reset().await;
+                let entries: String = links
+                    .iter()
+                    .map(|link| format!("- [{link}]({}/{link})\n", server.uri()))
+                    .collect();
+                wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+                    .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                        format!("# Example\n\n## Docs\n\n{entries}"),
+                    ))
+                    .mount(server)
+                    .await;
+                for link in ["home", "new"] {
+                    wiremock::Mock::given(wiremock::matchers::path(format!("/{link}")))
+                        .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                            format!("<html><body><main><h1>{link}</h1></main></body></html>"),
+                        ))
+                        .mount(server)
+                        .await;
+                }
+            }
+        };
+        mount(&["home"]).await;
+
+        let mut config = AddKbConfig {
+            url: Url::parse(&server.uri()).unwrap(),
+            name: "Example".into(),
+            output_root: dir.join("kb"),
+            mode: CrawlMode::LlmsTxt,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            section_chunks: false,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+            warm_cache_from: None,
+            merge: false,
+        };
+        let first = add_kb(&config, &SilentProgress).await.unwrap();
+        assert!(!first.merged);
+
+        let err = add_kb(&config, &SilentProgress).await.unwrap_err().to_string();
+        assert!(err.contains("already exists"), "{err}");
+        assert!(err.contains("update --kb"), "{err}");
+        assert_eq!(std::fs::read_dir(dir.join("kb")).unwrap().count(), 1, "no second KB");
+
+        mount(&["home", "new"]).await;
+        config.merge = true;
+        let merged = add_kb(&config, &SilentProgress).await.unwrap();
+        assert!(merged.merged);
+        assert_eq!((&merged.kb_id, &merged.kb_path), (&first.kb_id, &first.kb_path));
+        assert_eq!(merged.page_count, 2);
+        assert!(first.kb_path.join("docs/new.md").exists());
+
+        let fetched: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.url.path().to_string())
+            .filter(|path| path != "/llms.txt" && path != "/llms-full.txt")
+            .collect();
+        assert_eq!(fetched, ["/new"], "stored pages are not fetched again");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn configured_max_full_size_rejects_larger_llms_full_txt() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Home]({}/home): Start here\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms-full.txt"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string(format!("# Example\n\n{}", "x".repeat(4096))),
+            )
+            .mount(&server)
+            .await;
+
+        let dir = crate::test_support::temp_dir("max-full-size");
+        let config_path = dir.join("config.toml");
+        std::fs::write(&config_path, "[crawl_policies]\nmax_full_size = 1024\n").unwrap();
+        let app = contextbuilder_shared::load_config_from(&config_path).unwrap();
+        let url = Url::parse(&server.uri()).unwrap();
+
+        let llms_full = |crawl: CrawlConfig| {
+            let url = url.clone();
+            async move {
+                match contextbuilder_discovery::discover(&url, &discovery_options(&crawl)).await {
+                    Ok(DiscoveryResult::Found { llms_full_txt, .. }) => llms_full_txt,
+                    other => panic!("expected Found, got {other:?}"),
+                }
+            }
+        };
+        assert!(llms_full(CrawlConfig::default()).await.is_some());
+        assert!(llms_full(CrawlConfig::from(&app)).await.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn custom_task_is_cached_and_written_as_artifact() {
+        use contextbuilder_shared::{CustomTaskConfig, PageMeta, TaskContext, Toc};
+
+        let dir = crate::test_support::temp_dir("custom-task");
+
+        let storage = Storage::open(&dir.join("test.db")).await.unwrap();
+        storage.insert_kb("kb1", "Example", "https://docs.example.com", None).await.unwrap();
+
+        let faq = CustomTaskConfig {
+            name: "faq".into(),
+            output: "FAQ.md".into(),
+            title: Some("FAQ".into()),
+            instructions: "Write an FAQ.".into(),
+            context: vec![TaskContext::Summaries],
+        };
+        let config = EnrichmentConfig {
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            working_dir: dir.to_string_lossy().into_owned(),
+            model_id: "m".into(),
+            kb_name: "Example".into(),
+            kb_source_url: "https://docs.example.com".into(),
+            section_chunks: false,
+            startup_timeout_secs: 5,
+            custom_tasks: vec![faq],
+            fallback: SummaryFallback::None,
+            budget: None,
+        };
+        let page = PageMeta {
+            id: "p1".into(),
+            kb_id: "kb1".into(),
+            url: "https://docs.example.com/".into(),
+            path: "index".into(),
+            title: Some("Home".into()),
+            content_hash: "h".into(),
+            fetched_at: chrono::Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        let pages = vec![(page, "# Home\n\nWelcome.".to_string())];
+        let toc = Toc { sections: vec![] };
+        let progress = SilentProgress;
+
+        let first = enrichment::run_enrichment(&config, &pages, &toc, &storage, &progress)
+            .await
+            .unwrap();
+        assert_eq!(first.custom_artifacts.get("faq").map(String::as_str), Some("Summary."));
+
+        // Second run is served entirely from the cache, custom task included
+        let second = enrichment::run_enrichment(&config, &pages, &toc, &storage, &progress)
+            .await
+            .unwrap();
+        assert_eq!(second.cache_misses, 0);
+        assert_eq!(second.cache_hits, first.cache_misses);
+        assert_eq!(second.custom_artifacts, first.custom_artifacts);
+
+        let custom = render_custom_artifacts(
+            &config.custom_tasks,
+            &second,
+            "Example",
+            "https://docs.example.com",
+            "test",
+        );
+        let artifacts: Vec<(&str, &str)> =
+            custom.iter().map(|(file, content)| (*file, content.as_str())).collect();
+        let meta = EnrichmentMeta {
+            model: "m".into(),
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            cache_hits: second.cache_hits,
+            cache_misses: 0,
+            completed_at: "now".into(),
+            cache_by_task: Default::default(),
+            extractive: false,
+        };
+        let assemble_config = AssembleConfig {
+            kb_id: KbId::new(),
+            name: "Example".into(),
+            source_url: "https://docs.example.com".into(),
+            output_root: dir.clone(),
+            tool_version: "test".into(),
+            clock: Clock::System,
+            crawl_scope: None,
+        };
+        let kb = crate::assembler::assemble(&assemble_config, &[], &toc).unwrap();
+        crate::assembler::assemble_artifacts(&kb.kb_path, &artifacts, false, &meta, Clock::System)
+            .unwrap();
+
+        let written = std::fs::read_to_string(kb.kb_path.join("artifacts/FAQ.md")).unwrap();
+        assert!(written.contains("# FAQ — Example"), "{written}");
+        assert!(written.contains("Summary."), "{written}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn language_filter_drops_mismatched_pages() {
+        let page = |path: &str, body: &str| FetchedPage {
+            meta: contextbuilder_shared::PageMeta {
+                id: uuid::Uuid::now_v7().to_string(),
+                kb_id: "kb1".into(),
+                url: format!("https://docs.example.com/{path}"),
+                path: path.into(),
+                title: None,
+                content_hash: "h".into(),
+                fetched_at: chrono::Utc::now(),
+                status_code: Some(200),
+                content_len: None,
+                depth: None,
+                description: None,
+            },
+            content: contextbuilder_crawler::ExtractedContent {
+                html: String::new(),
+                meta: contextbuilder_crawler::adapters::PageMeta { title: None },
+            },
+            html: format!("<html><body><main>{body}</main></body></html>"),
+            links: vec![],
+        };
+        let english = page(
+            "install",
+            "<h1>Installation</h1><p>This guide explains how to install the command line \
+             tool and configure it for your first project.</p>",
+        );
+        // Same path layout, untranslated content
+        let german = page(
+            "de/install",
+            "<h1>Installation</h1><p>Diese Anleitung erklärt, wie Sie das \
+             Kommandozeilenwerkzeug installieren und für Ihr erstes Projekt einrichten.</p>",
+        );
+
+        let crawl = CrawlConfig {
+            languages: vec!["ENG".into()],
+            ..Default::default()
+        };
+        let kept: Vec<&str> = [&english, &german]
+            .into_iter()
+            .filter_map(|p| {
+                let result =
+                    contextbuilder_markdown::convert(&p.html, &convert_options(p, &crawl)).unwrap();
+                let expected = if p.meta.path == "install" { "eng" } else { "deu" };
+                assert_eq!(result.language.as_deref(), Some(expected));
+                assert!(result.markdown.contains(&format!("language: \"{expected}\"")));
+                language_allowed(result.language.as_deref(), &crawl.languages)
+                    .then_some(p.meta.path.as_str())
+            })
+            .collect();
+        assert_eq!(kept, vec!["install"]);
+
+        // No filter, or no reliable detection: keep
+        assert!(language_allowed(Some("deu"), &[]));
+        assert!(language_allowed(None, &crawl.languages));
+    }
+
+    #[test]
+    fn custom_tasks_cannot_shadow_builtin_artifacts() {
+        let task = |name: &str, output: &str| contextbuilder_shared::CustomTaskConfig {
+            name: name.into(),
+            output: output.into(),
+            title: None,
+            instructions: "x".into(),
+            context: vec![],
+        };
+        assert!(enrichment::validate_custom_tasks(&[task("faq", "FAQ.md")]).is_ok());
+        assert!(enrichment::validate_custom_tasks(&[task("skill", "SKILL.md")]).is_err());
+        assert!(
+            enrichment::validate_custom_tasks(&[task("a", "A.md"), task("a", "B.md")]).is_err()
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Structured progress events emitted by the pipelines.
+//!
+//! Every pipeline (`add`, `update`, `add-pages`, `merge`) and the enrichment
+//! run report through a single [`ProgressReporter::event`] method, so a
+//! frontend receives the same typed stream whether it renders a spinner, a
+//! TUI, or JSON lines.
+
+use std::io::Write;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// A pipeline phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    InitStorage,
+    LoadKb,
+    Discover,
+    Crawl,
+    Fetch,
+    Compare,
+    Convert,
+    DownloadAssets,
+    SplitSections,
+    BuildToc,
+    Assemble,
+    Merge,
+    Enrich,
+    StartBridge,
+    SummarizePages,
+    SummarizeSections,
+    GenerateDescriptions,
+    GenerateKbArtifacts,
+    CustomTasks,
+    StopBridge,
+    GenerateArtifacts,
+}
+
+impl Phase {
+    /// Human-readable label for spinners and logs.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::InitStorage => "Initializing storage",
+            Self::LoadKb => "Loading existing KB",
+            Self::Discover => "Discovering llms.txt",
+            Self::Crawl => "Crawling documentation",
+            Self::Fetch => "Fetching pages",
+            Self::Compare => "Comparing content",
+            Self::Convert => "Converting to Markdown",
+            Self::DownloadAssets => "Downloading images",
+            Self::SplitSections => "Splitting pages into sections",
+            Self::BuildToc => "Building table of contents",
+            Self::Assemble => "Assembling knowledge base",
+            Self::Merge => "Merging knowledge bases",
+            Self::Enrich => "Running LLM enrichment",
+            Self::StartBridge => "Starting enrichment bridge",
+            Self::SummarizePages => "Summarizing pages",
+            Self::SummarizeSections => "Summarizing sections",
+            Self::GenerateDescriptions => "Generating descriptions",
+            Self::GenerateKbArtifacts => "Generating KB artifacts",
+            Self::CustomTasks => "Running custom tasks",
+            Self::StopBridge => "Shutting down enrichment bridge",
+            Self::GenerateArtifacts => "Generating artifacts",
+        }
+    }
+}
+
+/// A progress event. Serializes as `{"event": "<variant>", ...fields}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// A new phase started.
+    Phase { phase: Phase },
+    /// A page was fetched.
+    PageFetched {
+        url: String,
+        current: usize,
+        total: usize,
+    },
+    /// A page was converted to Markdown.
+    PageConverted {
+        path: String,
+        current: usize,
+        total: usize,
+    },
+    /// An enrichment task is about to run (or be served from the cache).
+    EnrichmentTask {
+        /// Task type, e.g. `summarize_page` or `custom:faq`.
+        task: String,
+        /// Page path, section key, or KB name the task is for.
+        target: String,
+        current: usize,
+        total: usize,
+    },
+    /// A recoverable problem; the pipeline continues.
+    Warning { message: String },
+    /// The pipeline finished.
+    Done {
+        kb_id: String,
+        page_count: usize,
+        elapsed_ms: u64,
+    },
+}
+
+/// Receives progress events from a running pipeline.
+pub trait ProgressReporter: Send + Sync {
+    /// Called for every event, in order.
+    fn event(&self, event: ProgressEvent);
+}
+
+/// No-op progress reporter for headless/test usage.
+pub struct SilentProgress;
+
+impl ProgressReporter for SilentProgress {
+    fn event(&self, _event: ProgressEvent) {}
+}
+
+/// Writes each event as one JSON line.
+pub struct JsonProgress<W> {
+    out: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonProgress<W> {
+    /// Emit events to `out`.
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl JsonProgress<std::io::Stderr> {
+    /// Emit events to stderr, next to the JSON logs.
+    pub fn stderr() -> Self {
+        Self::new(std::io::stderr())
+    }
+}
+
+impl<W: Write + Send> ProgressReporter for JsonProgress<W> {
+    fn event(&self, event: ProgressEvent) {
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        if let Ok(mut out) = self.out.lock() {
+            let _ = writeln!(out, "{line}");
+        }
+    }
+}
+
+/// Convenience helpers for emitting events.
+pub(crate) trait ProgressExt {
+    fn phase(&self, phase: Phase);
+    fn task(&self, task: &str, target: &str, current: usize, total: usize);
+    fn warning(&self, message: String);
+}
+
+impl<P: ProgressReporter + ?Sized> ProgressExt for P {
+    fn phase(&self, phase: Phase) {
+        self.event(ProgressEvent::Phase { phase });
+    }
+
+    fn task(&self, task: &str, target: &str, current: usize, total: usize) {
+        self.event(ProgressEvent::EnrichmentTask {
+            task: task.to_string(),
+            target: target.to_string(),
+            current,
+            total,
+        });
+    }
+
+    fn warning(&self, message: String) {
+        self.event(ProgressEvent::Warning { message });
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_progress_writes_one_tagged_line_per_event() {
+        let progress = JsonProgress::new(Vec::new());
+        progress.phase(Phase::Convert);
+        progress.event(ProgressEvent::PageConverted {
+            path: "guides/setup".into(),
+            current: 1,
+            total: 2,
+        });
+        progress.warning("fetch failed".into());
+
+        let out = String::from_utf8(progress.out.into_inner().unwrap()).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"event":"phase","phase":"convert"}"#,
+                r#"{"event":"page_converted","path":"guides/setup","current":1,"total":2}"#,
+                r#"{"event":"warning","message":"fetch failed"}"#,
+            ]
+        );
+    }
+}
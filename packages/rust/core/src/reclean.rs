@@ -0,0 +1,165 @@
+//! Re-run Markdown cleanup over an existing KB.
+//!
+//! Cleanup passes improve over time, but pages converted by an older build
+//! keep the old output until they are re-crawled. [`reclean_kb`] applies the
+//! current pipeline to every page under `docs/` in place, leaving each
+//! page's frontmatter as it was, and refreshes the pages' stored Markdown
+//! so search matches what the files say.
+
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, info, instrument};
+use url::Url;
+
+use contextbuilder_shared::{ContextBuilderError, Result};
+use contextbuilder_storage::{Storage, StorageBackend};
+
+/// Outcome of [`reclean_kb`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecleanReport {
+    /// Pages found under `docs/`.
+    pub pages: usize,
+    /// Pages whose content changed and were rewritten.
+    pub changed: usize,
+}
+
+/// Apply the current cleanup pipeline to every stored page of the KB at
+/// `kb_path`, rewriting only the pages it changes.
+#[instrument(skip_all, fields(kb = %kb_path.display()))]
+pub async fn reclean_kb(kb_path: &Path) -> Result<RecleanReport> {
+    let docs_dir = kb_path.join("docs");
+    if !docs_dir.is_dir() {
+        return Err(ContextBuilderError::validation(format!(
+            "{} has no docs/ directory",
+            kb_path.display()
+        )));
+    }
+
+    let db_path = kb_path.join("indexes").join("contextbuilder.db");
+    let index = if db_path.exists() {
+        let kb_id = crate::update::load_manifest(kb_path)?.id.to_string();
+        Some((Storage::open(&db_path).await?, kb_id))
+    } else {
+        None
+    };
+
+    let mut report = RecleanReport::default();
+    for path in markdown_files(&docs_dir)? {
+        let md = std::fs::read_to_string(&path).map_err(|e| ContextBuilderError::io(&path, e))?;
+        let base_url = source_url(&md);
+        let cleaned = contextbuilder_markdown::reclean_markdown(&md, base_url.as_ref());
+        report.pages += 1;
+        if cleaned != md {
+            debug!(path = %path.display(), "recleaned page");
+            std::fs::write(&path, &cleaned).map_err(|e| ContextBuilderError::io(&path, e))?;
+            if let Some((storage, kb_id)) = &index {
+                refresh_content(storage, kb_id, &docs_dir, &path, &cleaned).await?;
+            }
+            report.changed += 1;
+        }
+    }
+
+    info!(pages = report.pages, changed = report.changed, "reclean complete");
+    Ok(report)
+}
+
+/// Store `cleaned` as the Markdown of the page whose file is `path`.
+async fn refresh_content(
+    storage: &dyn StorageBackend,
+    kb_id: &str,
+    docs_dir: &Path,
+    path: &Path,
+    cleaned: &str,
+) -> Result<()> {
+    let Some(page_path) = path
+        .strip_prefix(docs_dir)
+        .ok()
+        .and_then(|p| p.with_extension("").to_str().map(|p| p.replace('\\', "/")))
+    else {
+        return Ok(());
+    };
+    if let Some(page) = storage.get_page(kb_id, &page_path).await? {
+        storage.upsert_page_with_content(&page, cleaned).await?;
+    }
+    Ok(())
+}
+
+/// Every `.md` file under `dir`, recursively, in a stable order.
+fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir).map_err(|e| ContextBuilderError::io(&dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| ContextBuilderError::io(&dir, e))?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The `source_url` recorded in a page's frontmatter.
+fn source_url(md: &str) -> Option<Url> {
+    let frontmatter = md.strip_prefix("---\n")?.split("\n---\n").next()?;
+    frontmatter
+        .lines()
+        .find_map(|line| line.strip_prefix("source_url: "))
+        .and_then(|value| Url::parse(value.trim_matches('"')).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[tokio::test]
+    async fn reclean_collapses_blank_lines_in_stored_pages() {
+        let kb = temp_dir("reclean");
+        let docs = kb.join("docs/guide");
+        std::fs::create_dir_all(&docs).unwrap();
+
+        let frontmatter = "---\nsource_url: \"https://docs.example.com/guide/a\"\n---\n";
+        let messy = format!("{frontmatter}\n# A\n\n\n\n\n\nBody.\n");
+        let tidy = format!("{frontmatter}\n# B\n\nBody.\n");
+        std::fs::write(docs.join("a.md"), &messy).unwrap();
+        std::fs::write(docs.join("b.md"), &tidy).unwrap();
+
+        let report = reclean_kb(&kb).await.unwrap();
+        assert_eq!(report, RecleanReport { pages: 2, changed: 1 });
+
+        let a = std::fs::read_to_string(docs.join("a.md")).unwrap();
+        assert_eq!(a, format!("{frontmatter}\n# A\n\n\nBody.\n"));
+        assert_eq!(std::fs::read_to_string(docs.join("b.md")).unwrap(), tidy);
+    }
+
+    #[tokio::test]
+    async fn reclean_refreshes_the_searchable_page_content() {
+        let dir = temp_dir("reclean-search");
+        let kb = crate::test_support::seed_kb(
+            &dir,
+            "Guide",
+            "https://docs.example.com",
+            &[("setup", "Setup", "# Setup\n\n\n\n\nInstall the kubernetes operator.\n")],
+        )
+        .await;
+        let db_path = kb.join("indexes").join("contextbuilder.db");
+        let kb_id = crate::test_support::read_manifest(&kb).id.to_string();
+        let storage = Storage::open(&db_path).await.unwrap();
+        assert!(storage.search(&kb_id, "kubernetes", 10).await.unwrap().is_empty());
+
+        assert_eq!(reclean_kb(&kb).await.unwrap().changed, 1);
+
+        let md = std::fs::read_to_string(kb.join("docs/setup.md")).unwrap();
+        let stored = storage.get_page_content(&kb_id, "setup").await.unwrap();
+        assert_eq!(stored.as_deref(), Some(md.as_str()));
+        let hits = storage.search(&kb_id, "kubernetes", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,218 @@
+//! Re-run enrichment for a single page of an existing knowledge base.
+//!
+//! Drops the cached summary, description, and section summaries of one page,
+//! then re-runs enrichment over the whole KB and re-emits artifacts. Every
+//! other page is served from the cache, so only the target page (and any
+//! KB-level artifact whose inputs changed) costs LLM calls.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use tracing::{info, instrument};
+
+use contextbuilder_shared::{
+    ArtifactLayout, ContextBuilderError, CustomTaskConfig, KbId, LinkBase, Result,
+    SummaryFallback, Toc,
+};
+use contextbuilder_storage::Storage;
+
+use crate::enrichment::{self, EnrichmentConfig};
+use crate::pipeline::{ArtifactInputs, clock_for};
+use crate::progress::{Phase, ProgressEvent, ProgressExt, ProgressReporter};
+
+/// Configuration for the `reenrich_page` pipeline.
+#[derive(Debug, Clone)]
+pub struct ReenrichConfig {
+    /// Path to the existing KB directory (contains manifest.json).
+    pub kb_path: PathBuf,
+    /// Path of the page to re-enrich, as listed in `toc.json`.
+    pub page_path: String,
+    /// Tool version string.
+    pub tool_version: String,
+    /// OpenRouter model ID for enrichment.
+    pub model_id: String,
+    /// Bridge command (e.g., "bun").
+    pub bridge_cmd: String,
+    /// Bridge script path.
+    pub bridge_script: String,
+    /// Working directory for the bridge subprocess.
+    pub bridge_working_dir: String,
+    /// Seconds to wait for the bridge to signal readiness.
+    pub bridge_startup_timeout_secs: u64,
+    /// Produce reproducible output: fixed timestamps.
+    pub deterministic: bool,
+    /// User-defined enrichment tasks, each written as an extra artifact.
+    pub custom_tasks: Vec<CustomTaskConfig>,
+    /// Summaries to fall back on when the enrichment bridge can't start.
+    pub summary_fallback: SummaryFallback,
+    /// Where artifacts are written inside `artifacts/`.
+    pub artifact_layout: ArtifactLayout,
+    /// What the generated `llms.txt` links to.
+    pub llms_txt_links: LinkBase,
+    /// Also write a gzip copy of every artifact.
+    pub compress_artifacts: bool,
+}
+
+/// Result of the `reenrich_page` pipeline.
+#[derive(Debug)]
+pub struct ReenrichResult {
+    /// KB identifier.
+    pub kb_id: KbId,
+    /// Cache entries dropped for the page.
+    pub entries_invalidated: u64,
+    /// Total elapsed time.
+    pub elapsed: std::time::Duration,
+}
+
+/// Invalidate one page's enrichment cache and re-enrich the KB.
+///
+/// 1. Load the manifest, TOC, and every page from `docs/`
+/// 2. Delete the page's cached per-page and section results (all models)
+/// 3. Re-run enrichment (cached for every other page) and rewrite artifacts
+#[instrument(skip_all, fields(kb_path = %config.kb_path.display(), page = %config.page_path))]
+pub async fn reenrich_page(
+    config: &ReenrichConfig,
+    progress: &dyn ProgressReporter,
+) -> Result<ReenrichResult> {
+    let start = Instant::now();
+
+    // --- Load KB ---
+    progress.phase(Phase::LoadKb);
+    let manifest = crate::update::load_manifest(&config.kb_path)?;
+    manifest.ensure_current_schema(&config.kb_path)?;
+    let kb_id = manifest.id.clone();
+    enrichment::validate_custom_tasks(&config.custom_tasks)?;
+
+    let toc_path = config.kb_path.join("toc.json");
+    let toc_json =
+        std::fs::read_to_string(&toc_path).map_err(|e| ContextBuilderError::io(&toc_path, e))?;
+    let toc: Toc = serde_json::from_str(&toc_json)
+        .map_err(|e| ContextBuilderError::validation(format!("invalid toc.json: {e}")))?;
+
+    let db_path = config.kb_path.join("indexes").join("contextbuilder.db");
+    let storage = Storage::open(&db_path).await?;
+    let (metas, pages) =
+        crate::append::merge_pages(&storage, &kb_id, &config.kb_path, &mut HashMap::new())
+            .await?;
+    let Some(page) = pages.iter().find(|p| p.path == config.page_path) else {
+        return Err(ContextBuilderError::validation(format!(
+            "no page '{}' in the KB",
+            config.page_path
+        )));
+    };
+
+    // --- Invalidate the page's cache entries ---
+    let chunks = storage.list_chunks_by_page(&kb_id.to_string(), &page.path).await?;
+    let keys = enrichment::page_cache_keys(&page.markdown, &chunks);
+    let entries_invalidated = storage
+        .delete_enrichment_cache_entries(&kb_id.to_string(), &keys)
+        .await?;
+    info!(entries_invalidated, "invalidated page enrichment cache");
+
+    // --- Enrichment & artifacts ---
+    let clock = clock_for(config.deterministic);
+    let enrich_config = EnrichmentConfig {
+        bridge_cmd: config.bridge_cmd.clone(),
+        bridge_script: config.bridge_script.clone(),
+        working_dir: config.bridge_working_dir.clone(),
+        model_id: config.model_id.clone(),
+        kb_name: manifest.name.clone(),
+        kb_source_url: manifest.source_url.clone(),
+        section_chunks: !chunks.is_empty(),
+        startup_timeout_secs: config.bridge_startup_timeout_secs,
+        custom_tasks: config.custom_tasks.clone(),
+        fallback: config.summary_fallback,
+        budget: None,
+    };
+    crate::pipeline::enrich_and_write_artifacts(
+        &ArtifactInputs {
+            enrich: &enrich_config,
+            metas: &metas,
+            pages: &pages,
+            toc: &toc,
+            kb_path: &config.kb_path,
+            tool_version: &config.tool_version,
+            layout: config.artifact_layout,
+            llms_txt_links: config.llms_txt_links,
+            compress_artifacts: config.compress_artifacts,
+            clock,
+        },
+        &storage,
+        progress,
+    )
+    .await?;
+
+    let result = ReenrichResult {
+        kb_id,
+        entries_invalidated,
+        elapsed: start.elapsed(),
+    };
+
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: pages.len(),
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::SilentProgress;
+    use crate::test_support::read_manifest;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reenrich_reruns_only_the_target_page() {
+        let dir = crate::test_support::temp_dir("reenrich");
+        let kb_path = crate::test_support::seed_kb(
+            &dir,
+            "Example",
+            "https://docs.example.com",
+            &[
+                ("index", "Home", "# Home\n\nWelcome.\n"),
+                ("setup", "Setup", "# Setup\n\nInstall the tool.\n"),
+            ],
+        )
+        .await;
+        let mut config = ReenrichConfig {
+            kb_path: kb_path.clone(),
+            page_path: "index".into(),
+            tool_version: "test".into(),
+            model_id: "m".into(),
+            bridge_cmd: crate::test_support::fake_bridge(&dir),
+            bridge_script: "bridge.ts".into(),
+            bridge_working_dir: dir.to_string_lossy().into_owned(),
+            bridge_startup_timeout_secs: 5,
+            deterministic: false,
+            custom_tasks: Vec::new(),
+            summary_fallback: SummaryFallback::None,
+            artifact_layout: ArtifactLayout::Flat,
+            llms_txt_links: LinkBase::SourceUrls,
+            compress_artifacts: false,
+        };
+
+        // First run fills the cache for every page
+        let first = reenrich_page(&config, &SilentProgress).await.unwrap();
+        assert_eq!(first.entries_invalidated, 0);
+
+        config.page_path = "setup".into();
+        let second = reenrich_page(&config, &SilentProgress).await.unwrap();
+        assert_eq!(second.entries_invalidated, 2);
+
+        let enrichment = read_manifest(&kb_path).enrichment.unwrap();
+        let by_task = &enrichment["cache_by_task"];
+        assert_eq!(by_task["summarize_page"]["hits"], 1, "{enrichment}");
+        assert_eq!(by_task["summarize_page"]["misses"], 1, "{enrichment}");
+        assert_eq!(by_task["generate_description"]["misses"], 1, "{enrichment}");
+
+        config.page_path = "missing".into();
+        let err = reenrich_page(&config, &SilentProgress).await.unwrap_err();
+        assert!(err.to_string().contains("no page 'missing'"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,210 @@
+//! Related-page computation over the stored link graph.
+//!
+//! Two pages are related when they link to the same pages (bibliographic
+//! coupling) or are linked to together from the same page (co-citation).
+//! The result is written to `related.json` next to `toc.json`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use contextbuilder_shared::PageMeta;
+
+/// Maximum related pages kept per page.
+pub const RELATED_TOP_K: usize = 5;
+
+/// Links shared by more pages than this (or pages linking to more pages than
+/// this) are treated as site navigation and ignored — they carry no signal
+/// and would make pair counting quadratic in the KB size.
+const MAX_FANOUT: usize = 50;
+
+/// A page related to another, with its relatedness score.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelatedPage {
+    /// Stable path of the related page.
+    pub path: String,
+    /// Shared outbound links plus co-citations.
+    pub score: u32,
+}
+
+/// Compute the top-`top_k` related pages for every page in `pages`.
+///
+/// `links` is the link graph as `(from_page_path, to_url)` pairs. Pages with
+/// no related pages map to an empty list. Ties are broken by path so the
+/// output is deterministic.
+pub fn compute_related(
+    pages: &[PageMeta],
+    links: &[(String, String)],
+    top_k: usize,
+) -> BTreeMap<String, Vec<RelatedPage>> {
+    let path_by_url: HashMap<String, &str> = pages
+        .iter()
+        .map(|p| (normalize_url(&p.url), p.path.as_str()))
+        .collect();
+    let known: HashSet<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+
+    // Outbound link targets per page (self-links dropped).
+    let mut outbound: BTreeMap<&str, HashSet<String>> = BTreeMap::new();
+    for (from, to_url) in links {
+        let Some(from) = known.get(from.as_str()).copied() else {
+            continue;
+        };
+        let target = normalize_url(to_url);
+        if path_by_url.get(&target) == Some(&from) {
+            continue;
+        }
+        outbound.entry(from).or_default().insert(target);
+    }
+
+    let mut scores: HashMap<(&str, &str), u32> = HashMap::new();
+
+    // Bibliographic coupling: pages sharing an outbound target.
+    let mut citers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (from, targets) in &outbound {
+        for target in targets {
+            citers.entry(target.as_str()).or_default().push(*from);
+        }
+    }
+    for group in citers.values_mut() {
+        count_pairs(&mut scores, group);
+    }
+
+    // Co-citation: pages linked to together from the same page.
+    for targets in outbound.values() {
+        let mut group: Vec<&str> = targets
+            .iter()
+            .filter_map(|t| path_by_url.get(t).copied())
+            .collect();
+        group.sort_unstable();
+        group.dedup();
+        count_pairs(&mut scores, &mut group);
+    }
+
+    let mut related: BTreeMap<String, Vec<RelatedPage>> = pages
+        .iter()
+        .map(|p| (p.path.clone(), Vec::new()))
+        .collect();
+    for ((a, b), score) in scores {
+        for (from, to) in [(a, b), (b, a)] {
+            if let Some(list) = related.get_mut(from) {
+                list.push(RelatedPage {
+                    path: to.to_string(),
+                    score,
+                });
+            }
+        }
+    }
+    for list in related.values_mut() {
+        list.sort_by(|x, y| y.score.cmp(&x.score).then_with(|| x.path.cmp(&y.path)));
+        list.truncate(top_k);
+    }
+
+    related
+}
+
+/// Add one to the score of every pair in `group`, skipping navigation-sized groups.
+fn count_pairs<'a>(scores: &mut HashMap<(&'a str, &'a str), u32>, group: &mut [&'a str]) {
+    if group.len() < 2 || group.len() > MAX_FANOUT {
+        return;
+    }
+    group.sort_unstable();
+    for (i, a) in group.iter().enumerate() {
+        for b in &group[i + 1..] {
+            *scores.entry((*a, *b)).or_default() += 1;
+        }
+    }
+}
+
+/// Normalize a URL for graph matching (drop fragment and trailing slash).
+fn normalize_url(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    url.trim_end_matches('/').to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn page(path: &str) -> PageMeta {
+        PageMeta {
+            id: path.into(),
+            kb_id: "kb".into(),
+            url: format!("https://docs.example.com/{path}"),
+            path: path.into(),
+            title: None,
+            content_hash: String::new(),
+            fetched_at: Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        }
+    }
+
+    fn link(from: &str, to: &str) -> (String, String) {
+        (from.into(), format!("https://docs.example.com/{to}"))
+    }
+
+    fn paths(list: &[RelatedPage]) -> Vec<&str> {
+        list.iter().map(|r| r.path.as_str()).collect()
+    }
+
+    #[test]
+    fn related_sets_are_symmetric_and_ranked() {
+        let pages: Vec<PageMeta> = ["a", "b", "c", "d", "e"].into_iter().map(page).collect();
+        let links = vec![
+            // a and b share two targets; a and c share one.
+            link("a", "x"),
+            link("a", "y"),
+            link("b", "x"),
+            link("b", "y"),
+            link("c", "y"),
+            // d cites a and b together (co-citation).
+            link("d", "a"),
+            link("d", "b"),
+            // Self-links and fragments are ignored.
+            link("e", "e#top"),
+        ];
+
+        let related = compute_related(&pages, &links, RELATED_TOP_K);
+
+        assert_eq!(paths(&related["a"]), vec!["b", "c"]);
+        assert_eq!(related["a"][0].score, 3);
+        assert_eq!(related["a"][1].score, 1);
+        assert_eq!(paths(&related["b"]), vec!["a", "c"]);
+        assert_eq!(paths(&related["c"]), vec!["a", "b"]);
+        assert!(related["d"].is_empty());
+        assert!(related["e"].is_empty());
+
+        for (path, list) in &related {
+            for r in list {
+                let back = related[&r.path].iter().find(|x| &x.path == path);
+                assert_eq!(back.map(|x| x.score), Some(r.score), "{path} <-> {}", r.path);
+            }
+        }
+    }
+
+    #[test]
+    fn related_is_bounded_to_top_k() {
+        let pages: Vec<PageMeta> = ["a", "b", "c", "d"].into_iter().map(page).collect();
+        let links = vec![link("a", "x"), link("b", "x"), link("c", "x"), link("d", "x")];
+
+        let related = compute_related(&pages, &links, 2);
+        assert_eq!(paths(&related["a"]), vec!["b", "c"]);
+        assert_eq!(paths(&related["d"]), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn navigation_links_are_ignored() {
+        let pages: Vec<PageMeta> = (0..=MAX_FANOUT).map(|i| page(&format!("p{i}"))).collect();
+        let links: Vec<_> = pages.iter().map(|p| link(&p.path, "nav")).collect();
+
+        let related = compute_related(&pages, &links, RELATED_TOP_K);
+        assert!(related.values().all(Vec::is_empty));
+    }
+}
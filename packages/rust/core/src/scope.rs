@@ -0,0 +1,44 @@
+//! Scope preview: which URLs a crawl would take, without running it.
+//!
+//! Include/exclude patterns are easy to get wrong, and a mistake only shows
+//! after a long crawl. [`preview_scope`] lists the URLs a crawl from a start
+//! URL would consider under the effective config, each with the rule that
+//! admits or rejects it. Links are discovered by fetching only the in-scope
+//! pages that a crawl would follow, or taken from a local sitemap file.
+
+use std::path::Path;
+
+use tracing::instrument;
+use url::Url;
+
+use contextbuilder_shared::{ContextBuilderError, CrawlConfig, Result};
+
+pub use contextbuilder_crawler::sitemap::sitemap_urls;
+pub use contextbuilder_crawler::{ScopeRule, ScopedUrl};
+
+/// Classify the URLs a crawl of `start_url` would consider under `crawl`.
+///
+/// With `sitemap`, the URLs listed in that sitemap file are classified and
+/// nothing is fetched.
+#[instrument(skip_all, fields(start_url = %start_url))]
+pub async fn preview_scope(
+    start_url: &Url,
+    crawl: &CrawlConfig,
+    sitemap: Option<&Path>,
+) -> Result<Vec<ScopedUrl>> {
+    let seeds = match sitemap {
+        Some(path) => {
+            let xml =
+                std::fs::read_to_string(path).map_err(|e| ContextBuilderError::io(path, e))?;
+            Some(sitemap_urls(&xml))
+        }
+        None => None,
+    };
+    // Classifying sitemap URLs needs no network, even offline
+    let crawl = CrawlConfig {
+        offline: crawl.offline && seeds.is_none(),
+        ..crawl.clone()
+    };
+    let crawler = contextbuilder_crawler::Crawler::new(crawl)?;
+    crawler.preview_scope(start_url, seeds.as_deref()).await
+}
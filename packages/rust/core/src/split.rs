@@ -0,0 +1,424 @@
+//! Splitting oversized pages into sub-pages.
+//!
+//! Some sites put a whole manual on one page, which makes a single KB page
+//! that is hard to navigate and too large to enrich in one request. With
+//! `split_large_pages`, [`split_large_pages`] cuts each page over the size
+//! threshold at its top-level headings: every section becomes a sub-page
+//! under the page's path (`manual/installation`), and the page itself keeps
+//! its introduction plus a list of links to the sections, so the TOC shows
+//! it as the parent of its sub-pages.
+
+use std::collections::{HashMap, HashSet};
+
+use tracing::debug;
+
+use contextbuilder_crawler::{ExtractedContent, FetchedPage};
+use contextbuilder_markdown::{escape_yaml_string, parse_headings, split_sections, Section};
+use contextbuilder_shared::{IdSource, PageMeta, TocEntry};
+
+use crate::assembler::AssemblePage;
+
+/// A page is only split into at least this many sections.
+const MIN_SECTIONS: usize = 2;
+
+/// Frontmatter fields a sub-page inherits from its page. The rest (the
+/// description, keywords, the heading list) describe the whole page.
+const INHERITED_FIELDS: [&str; 2] = ["fetched_at: ", "language: "];
+
+/// Split every page whose Markdown is over `threshold` bytes and has at
+/// least two top-level sections into an index page and one sub-page per
+/// section. `pages` and `fetched` are parallel, and so are the results.
+pub fn split_large_pages(
+    pages: Vec<AssemblePage>,
+    fetched: Vec<FetchedPage>,
+    threshold: usize,
+    ids: IdSource,
+) -> (Vec<AssemblePage>, Vec<FetchedPage>) {
+    split_avoiding(pages, fetched, threshold, &[], ids)
+}
+
+/// [`split_large_pages`] for a KB that already holds pages: `pages` are
+/// matched to their source in `fetched` by path, the sub-pages' sources are
+/// added to `fetched`, and sub-pages stay clear of the `reserved` paths.
+pub fn split_fetched_pages(
+    pages: Vec<AssemblePage>,
+    fetched: &mut Vec<FetchedPage>,
+    reserved: &[String],
+    threshold: usize,
+    ids: IdSource,
+) -> Vec<AssemblePage> {
+    let index: HashMap<String, usize> =
+        fetched.iter().enumerate().map(|(i, f)| (f.meta.path.clone(), i)).collect();
+    let (pages, unsourced): (Vec<_>, Vec<_>) =
+        pages.into_iter().partition(|page| index.contains_key(&page.path));
+    let sources = pages.iter().map(|page| fetched[index[&page.path]].clone()).collect();
+
+    let (mut pages, sources) = split_avoiding(pages, sources, threshold, reserved, ids);
+    for source in sources {
+        match index.get(&source.meta.path) {
+            Some(&i) => fetched[i] = source,
+            None => fetched.push(source),
+        }
+    }
+    pages.extend(unsourced);
+    pages
+}
+
+/// Whether `page` was split off another page, whose path is its parent
+/// directory. Sub-pages are the only pages whose URL has a `#fragment`.
+pub fn is_subpage(page: &PageMeta) -> bool {
+    page.url.contains('#') && page.path.contains('/')
+}
+
+/// The path of the page a sub-page at `path` was split off.
+pub fn parent_path(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(parent, _)| parent)
+}
+
+fn split_avoiding(
+    pages: Vec<AssemblePage>,
+    fetched: Vec<FetchedPage>,
+    threshold: usize,
+    reserved: &[String],
+    ids: IdSource,
+) -> (Vec<AssemblePage>, Vec<FetchedPage>) {
+    let mut taken: HashSet<String> =
+        pages.iter().map(|p| p.path.clone()).chain(reserved.iter().cloned()).collect();
+    let mut out_pages = Vec::with_capacity(pages.len());
+    let mut out_fetched = Vec::with_capacity(fetched.len());
+
+    for (page, source) in pages.into_iter().zip(fetched) {
+        let sections = if page.markdown.len() > threshold {
+            top_level_sections(&page.markdown)
+        } else {
+            Vec::new()
+        };
+        let headed = sections.iter().filter(|s| s.title.is_some()).count();
+        if headed < MIN_SECTIONS {
+            out_pages.push(page);
+            out_fetched.push(source);
+            continue;
+        }
+
+        debug!(path = %page.path, bytes = page.markdown.len(), sections = headed, "splitting page");
+        let frontmatter = &page.markdown[..frontmatter_len(&page.markdown)];
+        let mut preamble = String::new();
+        let mut subpages = Vec::new();
+        for section in sections {
+            let (Some(title), Some(anchor)) = (section.title, section.anchor) else {
+                preamble = section.content;
+                continue;
+            };
+            let path = unique_path(&format!("{}/{}", page.path, slug(&anchor)), &mut taken);
+            let url = format!("{}#{anchor}", source.meta.url.split('#').next().unwrap_or_default());
+            subpages.push((
+                AssemblePage {
+                    path: path.clone(),
+                    markdown: format!(
+                        "{}\n{}\n",
+                        sub_frontmatter(frontmatter, &url, &title),
+                        section.content
+                    ),
+                    title: title.clone(),
+                },
+                FetchedPage {
+                    meta: PageMeta {
+                        id: ids.uuid(&format!("{}/{path}", source.meta.kb_id)).to_string(),
+                        url,
+                        path,
+                        title: Some(title),
+                        description: None,
+                        ..source.meta.clone()
+                    },
+                    content: ExtractedContent {
+                        html: String::new(),
+                        meta: Default::default(),
+                    },
+                    html: String::new(),
+                    links: Vec::new(),
+                },
+            ));
+        }
+
+        out_pages.push(AssemblePage {
+            markdown: index_markdown(frontmatter, &page, &preamble, &subpages),
+            ..page
+        });
+        out_fetched.push(FetchedPage {
+            html: String::new(),
+            ..source
+        });
+        for (page, source) in subpages {
+            out_pages.push(page);
+            out_fetched.push(source);
+        }
+    }
+    (out_pages, out_fetched)
+}
+
+/// `entries` (an adapter's sidebar TOC) with the sub-pages among `pages`
+/// added as children of their split page's entry, which the site's own
+/// navigation knows nothing about.
+///
+/// Sub-pages are recognised by their URL: their page's URL plus the
+/// section's `#anchor`.
+pub fn nest_subpages(entries: &[TocEntry], pages: &[PageMeta]) -> Vec<TocEntry> {
+    let urls: HashMap<&str, &str> =
+        pages.iter().map(|p| (p.path.as_str(), p.url.as_str())).collect();
+    let mut subpages: HashMap<&str, Vec<TocEntry>> = HashMap::new();
+    for page in pages {
+        let Some((parent, _)) = page.path.rsplit_once('/') else {
+            continue;
+        };
+        let parent_url = urls.get(parent).copied().unwrap_or_default();
+        if page.url.split_once('#').is_some_and(|(url, _)| url == parent_url) {
+            subpages.entry(parent).or_default().push(TocEntry {
+                title: page.title.clone().unwrap_or_default(),
+                path: page.path.clone(),
+                source_url: Some(page.url.clone()),
+                summary: None,
+                children: Vec::new(),
+            });
+        }
+    }
+    if subpages.is_empty() {
+        return entries.to_vec();
+    }
+
+    fn nest(entries: &[TocEntry], subpages: &HashMap<&str, Vec<TocEntry>>) -> Vec<TocEntry> {
+        entries
+            .iter()
+            .map(|entry| {
+                let mut entry = TocEntry {
+                    children: nest(&entry.children, subpages),
+                    ..entry.clone()
+                };
+                for sub in subpages.get(entry.path.as_str()).into_iter().flatten() {
+                    if !entry.children.iter().any(|child| child.path == sub.path) {
+                        entry.children.push(sub.clone());
+                    }
+                }
+                entry
+            })
+            .collect()
+    }
+    nest(entries, &subpages)
+}
+
+/// The sections of `md` at its top level: the H1s when there are several
+/// (a manual of chapters), else the H2s under the page title.
+fn top_level_sections(md: &str) -> Vec<Section> {
+    let body_start = frontmatter_len(md);
+    let h1s = parse_headings(md)
+        .iter()
+        .filter(|h| h.level == 1 && h.offset >= body_start)
+        .count();
+    split_sections(md, if h1s >= MIN_SECTIONS { 1 } else { 2 })
+}
+
+/// The index page: the page's introduction, then a link to each sub-page.
+fn index_markdown(
+    frontmatter: &str,
+    page: &AssemblePage,
+    preamble: &str,
+    subpages: &[(AssemblePage, FetchedPage)],
+) -> String {
+    let mut md = format!("{frontmatter}\n");
+    if !preamble.trim_start().starts_with("# ") {
+        md.push_str(&format!("# {}\n\n", page.title));
+    }
+    if !preamble.is_empty() {
+        md.push_str(preamble);
+        md.push_str("\n\n");
+    }
+    md.push_str("## Contents\n\n");
+    // Sub-pages sit in the directory named after this page
+    let dir = page.path.rsplit('/').next().unwrap_or_default();
+    for (sub, _) in subpages {
+        let file = sub.path.rsplit('/').next().unwrap_or_default();
+        md.push_str(&format!("- [{}]({dir}/{file}.md)\n", sub.title));
+    }
+    md
+}
+
+/// Frontmatter for a sub-page titled `title` at `url`, keeping the page's
+/// [`INHERITED_FIELDS`].
+fn sub_frontmatter(frontmatter: &str, url: &str, title: &str) -> String {
+    let mut fm = String::from("---\n");
+    fm.push_str(&format!("source_url: \"{url}\"\n"));
+    fm.push_str(&format!("title: \"{}\"\n", escape_yaml_string(title)));
+    for line in frontmatter.lines() {
+        if INHERITED_FIELDS.iter().any(|field| line.starts_with(field)) {
+            fm.push_str(line);
+            fm.push('\n');
+        }
+    }
+    fm.push_str("---\n");
+    fm
+}
+
+/// A path segment for a heading anchor (which is empty for headings that
+/// are all punctuation).
+fn slug(anchor: &str) -> &str {
+    if anchor.is_empty() { "section" } else { anchor }
+}
+
+/// `path`, or `path-2`, `path-3`, ... if another page already has it.
+fn unique_path(path: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = path.to_string();
+    let mut n = 1;
+    while !taken.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{path}-{n}");
+    }
+    candidate
+}
+
+/// Byte length of a leading `---` frontmatter block (0 if absent).
+fn frontmatter_len(md: &str) -> usize {
+    if !md.starts_with("---\n") {
+        return 0;
+    }
+    md[4..].find("\n---\n").map_or(0, |end| 4 + end + 5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetched(path: &str) -> FetchedPage {
+        FetchedPage {
+            meta: PageMeta {
+                id: "p".into(),
+                kb_id: "kb".into(),
+                url: format!("https://docs.example.com/{path}"),
+                path: path.into(),
+                title: Some("Manual".into()),
+                content_hash: "h".into(),
+                fetched_at: chrono::Utc::now(),
+                status_code: Some(200),
+                content_len: None,
+                depth: Some(1),
+                description: Some("The whole manual.".into()),
+            },
+            content: ExtractedContent {
+                html: String::new(),
+                meta: Default::default(),
+            },
+            html: "<main>...</main>".into(),
+            links: Vec::new(),
+        }
+    }
+
+    fn manual() -> AssemblePage {
+        let filler = "Lorem ipsum dolor sit amet. ".repeat(40);
+        AssemblePage {
+            path: "docs/manual".into(),
+            markdown: format!(
+                "---\nsource_url: \"https://docs.example.com/docs/manual\"\ntitle: \"Manual\"\n\
+                 fetched_at: \"2024-01-01T00:00:00Z\"\ndescription: \"The whole manual.\"\n---\n\
+                 \n# Manual\n\nEverything in one place.\n\n## Installation\n\n{filler}\n\n\
+                 ### Linux\n\napt install tool\n\n## Configuration\n\n{filler}\n\n\
+                 ## FAQ\n\n{filler}\n"
+            ),
+            title: "Manual".into(),
+        }
+    }
+
+    #[test]
+    fn splits_a_large_page_at_its_sections() {
+        let other = AssemblePage {
+            path: "docs/manual/faq".into(),
+            markdown: "# FAQ page\n".into(),
+            title: "FAQ page".into(),
+        };
+        let pages = vec![manual(), other];
+        let sources = vec![fetched("docs/manual"), fetched("docs/manual/faq")];
+
+        let (pages, sources) = split_large_pages(pages, sources, 1024, IdSource::Random);
+
+        let paths: Vec<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "docs/manual",
+                "docs/manual/installation",
+                "docs/manual/configuration",
+                // The FAQ section yields to the existing page at its path
+                "docs/manual/faq-2",
+                "docs/manual/faq",
+            ]
+        );
+        let source_paths: Vec<&str> = sources.iter().map(|s| s.meta.path.as_str()).collect();
+        assert_eq!(source_paths, paths);
+
+        // The index page keeps the introduction and links every section
+        let index = &pages[0].markdown;
+        assert!(index.starts_with("---\nsource_url: \"https://docs.example.com/docs/manual\""));
+        assert!(index.contains("# Manual\n\nEverything in one place.\n\n## Contents\n\n"));
+        assert!(index.contains("- [Installation](manual/installation.md)\n"));
+        assert!(index.contains("- [FAQ](manual/faq-2.md)\n"));
+        assert!(!index.contains("Lorem ipsum"));
+
+        // Each section is a page with its own heading, URL, and title
+        let install = &pages[1];
+        assert_eq!(install.title, "Installation");
+        assert!(install.markdown.starts_with(
+            "---\nsource_url: \"https://docs.example.com/docs/manual#installation\"\n\
+             title: \"Installation\"\nfetched_at: \"2024-01-01T00:00:00Z\"\n---\n\n## Installation"
+        ));
+        assert!(install.markdown.contains("### Linux\n\napt install tool"));
+        assert!(!install.markdown.contains("description:"));
+        assert!(!install.markdown.contains("## Configuration"));
+        assert_eq!(sources[1].meta.url, "https://docs.example.com/docs/manual#installation");
+        assert_eq!(sources[1].meta.title.as_deref(), Some("Installation"));
+        assert_eq!(sources[1].meta.description, None);
+    }
+
+    #[test]
+    fn sub_pages_nest_under_their_page_in_an_adapter_toc() {
+        let (_, sources) =
+            split_large_pages(vec![manual()], vec![fetched("docs/manual")], 1024, IdSource::Random);
+        let metas: Vec<PageMeta> = sources.into_iter().map(|s| s.meta).collect();
+        let entry = |title: &str, path: &str, children| TocEntry {
+            title: title.into(),
+            path: path.into(),
+            source_url: None,
+            summary: None,
+            children,
+        };
+        let sidebar = vec![
+            entry("Home", "index", vec![]),
+            entry("Docs", "docs", vec![entry("Manual", "docs/manual", vec![])]),
+        ];
+
+        let toc = nest_subpages(&sidebar, &metas);
+        assert!(toc[0].children.is_empty());
+        let manual = &toc[1].children[0];
+        let children: Vec<&str> = manual.children.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            children,
+            ["docs/manual/installation", "docs/manual/configuration", "docs/manual/faq"]
+        );
+        assert_eq!(manual.children[2].title, "FAQ");
+    }
+
+    #[test]
+    fn leaves_small_or_single_section_pages_alone() {
+        let page = manual();
+        let md = page.markdown.clone();
+        let source = fetched("docs/manual");
+        let (pages, _) = split_large_pages(vec![page], vec![source], md.len(), IdSource::Random);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].markdown, md);
+
+        let single = AssemblePage {
+            path: "long".into(),
+            markdown: format!("# Long\n\n## Only\n\n{}\n", "word ".repeat(1000)),
+            title: "Long".into(),
+        };
+        let (pages, _) =
+            split_large_pages(vec![single], vec![fetched("long")], 100, IdSource::Random);
+        assert_eq!(pages.len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+//! Fixtures shared by the pipeline tests: a fake enrichment bridge and small
+//! seeded KBs on disk.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use contextbuilder_shared::{Clock, KbId, KbManifest, PageMeta, Toc, TocEntry};
+use contextbuilder_storage::Storage;
+
+use crate::assembler::{AssembleConfig, AssemblePage};
+use crate::progress::{Phase, ProgressEvent, ProgressReporter};
+
+/// A fresh, empty temp directory.
+pub(crate) fn temp_dir(label: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cb-{label}-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write a fake bridge into `dir` that answers every task with `Summary.`;
+/// returns the command to pass as `bridge_cmd`.
+#[cfg(unix)]
+pub(crate) fn fake_bridge(dir: &Path) -> String {
+    bridge_script(
+        dir,
+        r#"echo '{"type":"ready","protocol_version":2}'
+while IFS= read -r line; do
+  case "$line" in *'"type":"shutdown"'*) exit 0 ;; esac
+  id=$(printf '%s' "$line" | sed 's/.*"id":"\([^"]*\)".*/\1/')
+  printf '{"type":"result","id":"%s","result":{"text":"Summary.","tokens_in":1,"tokens_out":1,"model":"m","latency_ms":0}}\n' "$id"
+done
+"#,
+    )
+}
+
+/// Write an executable `sh` script with `body` into `dir` to stand in for
+/// the bridge; returns the command to pass as `bridge_cmd`.
+#[cfg(unix)]
+pub(crate) fn bridge_script(dir: &Path, body: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = dir.join("fake-bun");
+    std::fs::write(&script, format!("#!/bin/sh\n{body}")).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script.to_string_lossy().into_owned()
+}
+
+/// Assemble a KB under `output_root` from `(path, title, markdown)` pages and
+/// store its pages; returns the KB directory.
+pub(crate) async fn seed_kb(
+    output_root: &Path,
+    name: &str,
+    source_url: &str,
+    pages: &[(&str, &str, &str)],
+) -> PathBuf {
+    let kb_id = KbId::new();
+    let metas: Vec<PageMeta> = pages
+        .iter()
+        .map(|(path, title, _)| PageMeta {
+            id: uuid::Uuid::now_v7().to_string(),
+            kb_id: kb_id.to_string(),
+            url: format!("{}/{path}", source_url.trim_end_matches('/')),
+            path: (*path).into(),
+            title: Some((*title).into()),
+            content_hash: "h".into(),
+            fetched_at: chrono::Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        })
+        .collect();
+    let assemble_pages: Vec<AssemblePage> = pages
+        .iter()
+        .map(|(path, title, markdown)| AssemblePage {
+            path: (*path).into(),
+            markdown: (*markdown).into(),
+            title: (*title).into(),
+        })
+        .collect();
+
+    let result = crate::assembler::assemble(
+        &AssembleConfig {
+            kb_id: kb_id.clone(),
+            name: name.into(),
+            source_url: source_url.into(),
+            output_root: output_root.to_path_buf(),
+            tool_version: "test".into(),
+            clock: Clock::System,
+            crawl_scope: None,
+        },
+        &assemble_pages,
+        &crate::toc::build_toc(&metas, &[], None, false),
+    )
+    .unwrap();
+
+    let storage = Storage::open(&result.kb_path.join("indexes").join("contextbuilder.db"))
+        .await
+        .unwrap();
+    storage.insert_kb(&kb_id.to_string(), name, source_url, None).await.unwrap();
+    for meta in &metas {
+        storage.upsert_page(meta).await.unwrap();
+    }
+    result.kb_path
+}
+
+/// Parse a KB's `manifest.json`.
+pub(crate) fn read_manifest(kb_path: &Path) -> KbManifest {
+    let json = std::fs::read_to_string(kb_path.join("manifest.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// Parse a KB's `toc.json`.
+pub(crate) fn read_toc(kb_path: &Path) -> Toc {
+    let json = std::fs::read_to_string(kb_path.join("toc.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// Number of entries in a TOC tree, nested ones included.
+pub(crate) fn count_toc_entries(entries: &[TocEntry]) -> usize {
+    entries.iter().map(|e| 1 + count_toc_entries(&e.children)).sum()
+}
+
+/// Progress reporter that records every event.
+#[derive(Default)]
+pub(crate) struct RecordingProgress {
+    events: Mutex<Vec<ProgressEvent>>,
+}
+
+impl RecordingProgress {
+    pub(crate) fn events(&self) -> Vec<ProgressEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// The phases entered, in order.
+    pub(crate) fn phases(&self) -> Vec<Phase> {
+        self.events()
+            .into_iter()
+            .filter_map(|e| match e {
+                ProgressEvent::Phase { phase } => Some(phase),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl ProgressReporter for RecordingProgress {
+    fn event(&self, event: ProgressEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
@@ -3,25 +3,45 @@
 //! Merges crawler-extracted page metadata with adapter-detected navigation
 //! into a hierarchical `Toc` structure that maps to `toc.json`.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use tracing::{debug, instrument};
 
-use contextbuilder_shared::{PageMeta, Toc, TocEntry};
+use contextbuilder_shared::{PageMeta, Toc, TocEntry, sanitize_filename};
 
 /// Build a TOC from crawled pages and optional adapter-extracted navigation.
 ///
 /// The builder:
 /// 1. Creates entries from page metadata (path, title, source URL)
-/// 2. Merges adapter TOC info if available
+/// 2. Uses the adapter's sidebar TOC as is when it covers half the pages
 /// 3. Builds a hierarchical structure from path segments
-/// 4. Orders entries alphabetically (with index pages first)
+/// 4. Orders entries index first, then by crawl depth, then alphabetically
+/// 5. Drops sections without any page beneath them, if `prune_empty_sections`
+/// 6. Flattens entries nested deeper than `max_depth`, if set
 #[instrument(skip_all, fields(page_count = pages.len()))]
-pub fn build_toc(pages: &[PageMeta], adapter_toc: &[TocEntry]) -> Toc {
-    if !adapter_toc.is_empty() && adapter_toc.len() >= pages.len() / 2 {
+pub fn build_toc(
+    pages: &[PageMeta],
+    adapter_toc: &[TocEntry],
+    max_depth: Option<usize>,
+    prune_empty_sections: bool,
+) -> Toc {
+    let mut toc = build_nested_toc(pages, adapter_toc);
+    if prune_empty_sections {
+        toc.sections = prune_empty(std::mem::take(&mut toc.sections), pages);
+    }
+    if let Some(max_depth) = max_depth {
+        toc.sections = flatten_toc(std::mem::take(&mut toc.sections), max_depth);
+    }
+    toc
+}
+
+/// Build the TOC without any depth limit.
+fn build_nested_toc(pages: &[PageMeta], adapter_toc: &[TocEntry]) -> Toc {
+    let adapter_entries = entry_count(adapter_toc);
+    if adapter_entries > 0 && adapter_entries >= pages.len() / 2 {
         // Use adapter TOC as the primary structure when it covers most pages
         debug!(
-            adapter_entries = adapter_toc.len(),
+            adapter_entries,
             "using adapter-provided TOC structure"
         );
         return Toc {
@@ -30,6 +50,10 @@ pub fn build_toc(pages: &[PageMeta], adapter_toc: &[TocEntry]) -> Toc {
     }
 
     // Build from page paths
+    let depths: HashMap<&str, u32> = pages
+        .iter()
+        .filter_map(|p| p.depth.map(|d| (p.path.as_str(), d)))
+        .collect();
     let mut root_entries: Vec<TocEntry> = Vec::new();
     let mut section_map: HashMap<String, Vec<TocEntry>> = HashMap::new();
 
@@ -54,16 +78,77 @@ pub fn build_toc(pages: &[PageMeta], adapter_toc: &[TocEntry]) -> Toc {
     }
 
     // Merge section children into root entries or create section entries
-    let mut sections = build_hierarchy(root_entries, &mut section_map);
+    let mut sections = build_hierarchy(root_entries, &mut section_map, &depths);
 
-    // Sort sections: index first, then alphabetically
-    sort_entries(&mut sections);
+    // Sort sections: index first, then shallowest, then alphabetically
+    sort_entries(&mut sections, &depths);
 
     debug!(sections = sections.len(), "TOC built from page paths");
 
     Toc { sections }
 }
 
+/// Number of entries in `entries`, at every level.
+fn entry_count(entries: &[TocEntry]) -> usize {
+    entries.iter().map(|e| 1 + entry_count(&e.children)).sum()
+}
+
+/// Remove entries that are not one of `pages` and have no remaining
+/// children, bottom-up, so a section whose whole subtree was filtered out
+/// disappears with it. Sections with at least one page beneath them stay.
+pub fn prune_empty(entries: Vec<TocEntry>, pages: &[PageMeta]) -> Vec<TocEntry> {
+    let page_paths: HashSet<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+    prune_entries(entries, &page_paths)
+}
+
+fn prune_entries(entries: Vec<TocEntry>, page_paths: &HashSet<&str>) -> Vec<TocEntry> {
+    entries
+        .into_iter()
+        .filter_map(|mut entry| {
+            entry.children = prune_entries(std::mem::take(&mut entry.children), page_paths);
+            if entry.children.is_empty() && !page_paths.contains(entry.path.as_str()) {
+                debug!(path = %entry.path, "pruned empty TOC section");
+                None
+            } else {
+                Some(entry)
+            }
+        })
+        .collect()
+}
+
+/// Limit `entries` to `max_depth` levels (top level is 1; 0 is treated as 1).
+///
+/// An entry at the deepest allowed level keeps its place, and its
+/// descendants follow it as siblings, titled `Parent › Child` so the lost
+/// nesting stays readable. Paths are unchanged.
+pub fn flatten_toc(entries: Vec<TocEntry>, max_depth: usize) -> Vec<TocEntry> {
+    let mut out = Vec::with_capacity(entries.len());
+    for mut entry in entries {
+        let children = std::mem::take(&mut entry.children);
+        if max_depth > 1 {
+            entry.children = flatten_toc(children, max_depth - 1);
+            out.push(entry);
+        } else {
+            let prefix = entry.title.clone();
+            out.push(entry);
+            lift_descendants(children, &prefix, &mut out);
+        }
+    }
+    out
+}
+
+/// Push `entries` and their descendants onto `out`, depth-first, with their
+/// titles prefixed by their ancestors'.
+fn lift_descendants(entries: Vec<TocEntry>, prefix: &str, out: &mut Vec<TocEntry>) {
+    for mut entry in entries {
+        let children = std::mem::take(&mut entry.children);
+        entry.title = format!("{prefix} › {}", entry.title);
+        let prefix = entry.title.clone();
+        out.push(entry);
+        lift_descendants(children, &prefix, out);
+    }
+}
+
 /// Generate a slug-safe path from a URL path.
 pub fn slugify_path(url_path: &str) -> String {
     let cleaned = url_path
@@ -81,13 +166,13 @@ pub fn slugify_path(url_path: &str) -> String {
     cleaned
         .split('/')
         .map(|segment| {
-            segment
+            let slug = segment
                 .to_lowercase()
-                .replace(' ', "-")
-                .replace('_', "-")
+                .replace([' ', '_'], "-")
                 .chars()
                 .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '/')
-                .collect::<String>()
+                .collect::<String>();
+            sanitize_filename(&slug)
         })
         .collect::<Vec<_>>()
         .join("/")
@@ -106,8 +191,7 @@ fn title_from_path(path: &str) -> String {
     }
 
     segment
-        .replace('-', " ")
-        .replace('_', " ")
+        .replace(['-', '_'], " ")
         .split_whitespace()
         .map(|word| {
             let mut chars = word.chars();
@@ -136,11 +220,12 @@ fn parent_path(path: &str) -> Option<String> {
 fn build_hierarchy(
     mut root_entries: Vec<TocEntry>,
     section_map: &mut HashMap<String, Vec<TocEntry>>,
+    depths: &HashMap<&str, u32>,
 ) -> Vec<TocEntry> {
     // Check if any root entry matches a section key
     for entry in &mut root_entries {
         if let Some(mut children) = section_map.remove(&entry.path) {
-            sort_entries(&mut children);
+            sort_entries(&mut children, depths);
             entry.children = children;
         }
     }
@@ -150,7 +235,7 @@ fn build_hierarchy(
     remaining.sort_by(|a, b| a.0.cmp(&b.0));
 
     for (section_path, mut children) in remaining {
-        sort_entries(&mut children);
+        sort_entries(&mut children, depths);
         root_entries.push(TocEntry {
             title: title_from_path(&section_path),
             path: section_path,
@@ -163,8 +248,10 @@ fn build_hierarchy(
     root_entries
 }
 
-/// Sort entries: "index" first, then alphabetically by title.
-fn sort_entries(entries: &mut [TocEntry]) {
+/// Sort entries: "index" first, then pages the crawler reached in fewer
+/// hops, then alphabetically by title. Entries without a known depth sort
+/// after those with one.
+fn sort_entries(entries: &mut [TocEntry], depths: &HashMap<&str, u32>) {
     entries.sort_by(|a, b| {
         let a_is_index = a.path.ends_with("index") || a.path == "index";
         let b_is_index = b.path.ends_with("index") || b.path == "index";
@@ -172,18 +259,32 @@ fn sort_entries(entries: &mut [TocEntry]) {
         match (a_is_index, b_is_index) {
             (true, false) => std::cmp::Ordering::Less,
             (false, true) => std::cmp::Ordering::Greater,
-            _ => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            _ => min_depth(a, depths)
+                .cmp(&min_depth(b, depths))
+                .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase())),
         }
     });
 
     // Recursively sort children
     for entry in entries.iter_mut() {
         if !entry.children.is_empty() {
-            sort_entries(&mut entry.children);
+            sort_entries(&mut entry.children, depths);
         }
     }
 }
 
+/// Shallowest crawl depth of `entry` or any page beneath it; `u32::MAX` when
+/// none is known.
+fn min_depth(entry: &TocEntry, depths: &HashMap<&str, u32>) -> u32 {
+    entry
+        .children
+        .iter()
+        .map(|child| min_depth(child, depths))
+        .chain(depths.get(entry.path.as_str()).copied())
+        .min()
+        .unwrap_or(u32::MAX)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -204,6 +305,8 @@ mod tests {
             fetched_at: Utc::now(),
             status_code: Some(200),
             content_len: Some(1000),
+            depth: None,
+            description: None,
         }
     }
 
@@ -215,7 +318,7 @@ mod tests {
             make_page("api-reference", "API Reference", "https://docs.example.com/api-reference"),
         ];
 
-        let toc = build_toc(&pages, &[]);
+        let toc = build_toc(&pages, &[], None, false);
         assert_eq!(toc.sections.len(), 3);
         // Index should be first
         assert_eq!(toc.sections[0].path, "index");
@@ -230,13 +333,33 @@ mod tests {
             make_page("api", "API", "https://docs.example.com/api"),
         ];
 
-        let toc = build_toc(&pages, &[]);
+        let toc = build_toc(&pages, &[], None, false);
         assert_eq!(toc.sections.len(), 2); // guide (with children) + api
 
         let guide = toc.sections.iter().find(|s| s.path == "guide").unwrap();
         assert_eq!(guide.children.len(), 2);
     }
 
+    #[test]
+    fn build_toc_ranks_shallower_pages_first() {
+        let at_depth = |path: &str, title: &str, depth: u32| PageMeta {
+            depth: Some(depth),
+            ..make_page(path, title, &format!("https://docs.example.com/{path}"))
+        };
+        let pages = vec![
+            at_depth("appendix", "Appendix", 2),
+            at_depth("guide", "Guide", 1),
+            at_depth("reference/api", "API", 1),
+            at_depth("reference/cli", "CLI", 3),
+            make_page("about", "About", "https://docs.example.com/about"),
+        ];
+
+        let toc = build_toc(&pages, &[], None, false);
+        let order: Vec<&str> = toc.sections.iter().map(|s| s.path.as_str()).collect();
+        // "reference" has no page of its own but ranks by its shallowest child
+        assert_eq!(order, vec!["guide", "reference", "appendix", "about"]);
+    }
+
     #[test]
     fn build_toc_uses_adapter_when_sufficient() {
         let pages = vec![
@@ -261,16 +384,47 @@ mod tests {
             },
         ];
 
-        let toc = build_toc(&pages, &adapter_toc);
+        let toc = build_toc(&pages, &adapter_toc, None, false);
         assert_eq!(toc.sections[0].title, "Alpha");
     }
 
+    #[test]
+    fn nested_adapter_toc_counts_every_level() {
+        let pages: Vec<PageMeta> = ["guide/a", "guide/b", "guide/c", "api"]
+            .iter()
+            .map(|path| make_page(path, path, &format!("https://example.com/{path}")))
+            .collect();
+        let entry = |title: &str, path: &str, children| TocEntry {
+            title: title.into(),
+            path: path.into(),
+            source_url: None,
+            summary: None,
+            children,
+        };
+        // One top-level section, but it covers three of the four pages
+        let adapter_toc = vec![entry(
+            "Guide",
+            "guide",
+            vec![
+                entry("A", "guide/a", vec![]),
+                entry("B", "guide/b", vec![]),
+                entry("C", "guide/c", vec![]),
+            ],
+        )];
+
+        let toc = build_toc(&pages, &adapter_toc, None, false);
+        assert_eq!(toc.sections.len(), 1);
+        assert_eq!(toc.sections[0].title, "Guide");
+        assert_eq!(toc.sections[0].children.len(), 3);
+    }
+
     #[test]
     fn slugify_path_handles_common_patterns() {
         assert_eq!(slugify_path("/guide/getting-started.html"), "guide/getting-started");
         assert_eq!(slugify_path("/"), "index");
         assert_eq!(slugify_path("/docs/API_Reference.html"), "docs/api-reference");
         assert_eq!(slugify_path("/path/with spaces/page"), "path/with-spaces/page");
+        assert_eq!(slugify_path("/devices/AUX"), "devices/aux_");
     }
 
     #[test]
@@ -307,9 +461,91 @@ mod tests {
             },
         ];
 
-        sort_entries(&mut entries);
+        sort_entries(&mut entries, &HashMap::new());
         assert_eq!(entries[0].path, "index");
         assert_eq!(entries[1].path, "alpha");
         assert_eq!(entries[2].path, "zebra");
     }
+
+    #[test]
+    fn flatten_toc_lifts_entries_below_max_depth() {
+        fn entry(title: &str, path: &str, children: Vec<TocEntry>) -> TocEntry {
+            TocEntry {
+                title: title.into(),
+                path: path.into(),
+                source_url: None,
+                summary: None,
+                children,
+            }
+        }
+        let toc = vec![entry(
+            "Guide",
+            "guide",
+            vec![
+                entry(
+                    "Config",
+                    "guide/config",
+                    vec![
+                        entry("Advanced", "guide/config/advanced", vec![]),
+                        entry("Env", "guide/config/env", vec![]),
+                    ],
+                ),
+                entry("Install", "guide/install", vec![]),
+            ],
+        )];
+
+        let flat = flatten_toc(toc, 2);
+        assert_eq!(flat.len(), 1);
+        let level2: Vec<(&str, &str)> = flat[0]
+            .children
+            .iter()
+            .map(|e| (e.title.as_str(), e.path.as_str()))
+            .collect();
+        assert_eq!(
+            level2,
+            [
+                ("Config", "guide/config"),
+                ("Config › Advanced", "guide/config/advanced"),
+                ("Config › Env", "guide/config/env"),
+                ("Install", "guide/install"),
+            ]
+        );
+        assert!(flat[0].children.iter().all(|e| e.children.is_empty()));
+    }
+
+    #[test]
+    fn build_toc_prunes_sections_left_without_pages() {
+        fn section(title: &str, path: &str, children: Vec<TocEntry>) -> TocEntry {
+            TocEntry {
+                title: title.into(),
+                path: path.into(),
+                source_url: None,
+                summary: None,
+                children,
+            }
+        }
+        let pages = vec![
+            make_page("index", "Home", "https://example.com/"),
+            make_page("guide/intro", "Intro", "https://example.com/guide/intro"),
+        ];
+        // "api/internal" was filtered out, so "api" (and its nested
+        // "api/v1" section) has nothing left beneath it
+        let adapter_toc = vec![
+            section("Home", "index", vec![]),
+            section("Guide", "guide", vec![section("Intro", "guide/intro", vec![])]),
+            section(
+                "API",
+                "api",
+                vec![section("v1", "api/v1", vec![section("Internal", "api/internal", vec![])])],
+            ),
+        ];
+
+        let kept = build_toc(&pages, &adapter_toc, None, false);
+        assert_eq!(kept.sections.len(), 3);
+
+        let pruned = build_toc(&pages, &adapter_toc, None, true);
+        let paths: Vec<&str> = pruned.sections.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["index", "guide"]);
+        assert_eq!(pruned.sections[1].children[0].path, "guide/intro");
+    }
 }
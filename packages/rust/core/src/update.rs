@@ -8,19 +8,20 @@ use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use tracing::{info, instrument, warn};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 
-use contextbuilder_crawler::FetchedPage;
-use contextbuilder_markdown::ConvertOptions;
+use contextbuilder_crawler::{CrawlResult, ExtractedContent, FetchedPage};
 use contextbuilder_shared::{
-    ContextBuilderError, CrawlConfig, KbId, KbManifest, PageMeta, Result,
+    ContextBuilderError, CrawlConfig, CrawlMode, CrawlScope, IdSource, KbId, KbManifest,
+    PageMeta, Result,
 };
 use contextbuilder_storage::Storage;
 
 use crate::assembler::{AssembleConfig, AssemblePage};
-use crate::pipeline::ProgressReporter;
-use crate::toc;
+use crate::pipeline::{clock_for, language_allowed};
+use crate::progress::{Phase, ProgressEvent, ProgressExt, ProgressReporter};
+use crate::{split, toc};
 
 // ---------------------------------------------------------------------------
 // Update config & result
@@ -39,6 +40,10 @@ pub struct UpdateKbConfig {
     pub prune: bool,
     /// Whether to force re-crawl even if hashes match.
     pub force: bool,
+    /// Refresh H2 section chunks for re-converted pages.
+    pub section_chunks: bool,
+    /// Produce reproducible output: fixed timestamps and path-ordered pages.
+    pub deterministic: bool,
 }
 
 /// Result of the `update_kb` pipeline.
@@ -58,6 +63,15 @@ pub struct UpdateKbResult {
     pub page_count: usize,
     /// Total elapsed time.
     pub elapsed: std::time::Duration,
+    /// Crawl health summary of the re-crawl.
+    pub crawl: CrawlResult,
+}
+
+impl UpdateKbResult {
+    /// Whether any page was added, changed, or removed.
+    pub fn has_changes(&self) -> bool {
+        self.pages_added + self.pages_changed + self.pages_removed > 0
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -133,8 +147,9 @@ pub async fn update_kb(
     let start = Instant::now();
 
     // --- Load manifest ---
-    progress.phase("Loading existing KB");
+    progress.phase(Phase::LoadKb);
     let manifest = load_manifest(&config.kb_path)?;
+    manifest.ensure_current_schema(&config.kb_path)?;
     let kb_id = manifest.id.clone();
     let source_url = Url::parse(&manifest.source_url).map_err(|e| {
         ContextBuilderError::validation(format!("invalid source_url in manifest: {e}"))
@@ -142,6 +157,10 @@ pub async fn update_kb(
 
     info!(%kb_id, source = %source_url, "updating KB");
 
+    if config.crawl.offline {
+        return Err(ContextBuilderError::offline(format!("re-crawl {source_url}")));
+    }
+
     // --- Open storage ---
     let db_path = config
         .kb_path
@@ -151,23 +170,69 @@ pub async fn update_kb(
 
     // --- Get existing pages ---
     let existing_pages = storage.list_pages_by_kb(&kb_id.to_string()).await?;
-    let _existing_count = existing_pages.len();
 
     // --- Re-crawl ---
-    progress.phase("Re-crawling documentation");
-    let crawler = contextbuilder_crawler::Crawler::new(config.crawl.clone())?;
-    let (_crawl_result, fetched_pages) = crawler
+    progress.phase(Phase::Crawl);
+    let budget = crate::pipeline::parallelism_budget(&config.crawl);
+    let mut crawler = contextbuilder_crawler::Crawler::new(config.crawl.clone())?;
+    if let Some(budget) = &budget {
+        crawler = crawler.with_budget(budget.clone());
+    }
+    let (crawl_result, fetched_pages) = crawler
         .crawl(&source_url, &kb_id.to_string(), &storage)
         .await?;
 
+    let auth_hint = crawl_result.auth_hint();
+    if let Some(hint) = &auth_hint {
+        warn!("{hint}");
+        progress.warning(hint.clone());
+    }
+
     if fetched_pages.is_empty() {
-        return Err(ContextBuilderError::validation(
-            "re-crawl returned no pages",
-        ));
+        return Err(ContextBuilderError::validation(match auth_hint {
+            Some(hint) => format!("re-crawl returned no pages; {hint}"),
+            None => "re-crawl returned no pages".to_string(),
+        }));
+    }
+    update_fetched(
+        config,
+        &manifest,
+        &storage,
+        existing_pages,
+        (crawl_result, fetched_pages),
+        start,
+        progress,
+    )
+    .await
+}
+
+/// Diff the re-crawled `fetched` pages against the `existing` ones stored
+/// before the crawl, then prune, re-convert, and re-assemble the KB at
+/// `config.kb_path` accordingly.
+pub(crate) async fn update_fetched(
+    config: &UpdateKbConfig,
+    manifest: &KbManifest,
+    storage: &Storage,
+    existing: Vec<PageMeta>,
+    fetched: (CrawlResult, Vec<FetchedPage>),
+    start: Instant,
+    progress: &dyn ProgressReporter,
+) -> Result<UpdateKbResult> {
+    let kb_id = manifest.id.clone();
+    let (crawl_result, mut fetched_pages) = fetched;
+    let ids = IdSource::new(config.deterministic);
+    let budget = crate::pipeline::parallelism_budget(&config.crawl);
+    // Sub-pages are derived from their page, so only pages are diffed
+    let (existing_subpages, existing_pages): (Vec<_>, Vec<_>) =
+        existing.into_iter().partition(split::is_subpage);
+
+    let clock = clock_for(config.deterministic);
+    if clock.is_fixed() {
+        crate::pipeline::make_deterministic(&mut fetched_pages, clock);
     }
 
     // --- Diff ---
-    progress.phase("Comparing content");
+    progress.phase(Phase::Compare);
     let diff = diff_pages(&existing_pages, &fetched_pages, config.force);
 
     info!(
@@ -179,19 +244,29 @@ pub async fn update_kb(
     );
 
     // --- Handle removals ---
-    if config.prune {
-        for path in &diff.removed_pages {
-            if let Some(old) = existing_pages.iter().find(|p| &p.path == path) {
-                let _ = storage.delete_page(&old.id).await;
-                // Remove the markdown file
-                let md_path = config.kb_path.join("docs").join(format!("{path}.md"));
-                let _ = std::fs::remove_file(&md_path);
-            }
+    // Pages past a page limit were never reached, not removed upstream
+    let prune = config.prune && !crawl_result.truncated;
+    if config.prune && !prune {
+        let msg = "crawl stopped at max_pages; not pruning pages it did not reach";
+        warn!("{msg}");
+        progress.warning(msg.to_string());
+    }
+    if prune {
+        let removed: HashSet<&str> = diff.removed_pages.iter().map(String::as_str).collect();
+        let gone = existing_pages.iter().chain(&existing_subpages).filter(|p| {
+            removed.contains(p.path.as_str()) || removed.contains(split::parent_path(&p.path))
+        });
+        for old in gone {
+            let _ = storage.delete_page(&old.id).await;
+            let _ = storage.delete_chunks_for_page(&kb_id.to_string(), &old.path).await;
+            // Remove the markdown file
+            let md_path = config.kb_path.join("docs").join(format!("{}.md", old.path));
+            let _ = std::fs::remove_file(&md_path);
         }
     }
 
     // --- Convert changed/new pages ---
-    progress.phase("Converting updated pages");
+    progress.phase(Phase::Convert);
     let needs_convert: HashSet<&str> = diff
         .new_pages
         .iter()
@@ -200,20 +275,39 @@ pub async fn update_kb(
         .collect();
 
     let mut assembled_pages: Vec<AssemblePage> = Vec::new();
+    let mut dropped: HashSet<String> = HashSet::new();
     let total = fetched_pages.len();
 
+    let to_convert: Vec<&FetchedPage> = fetched_pages
+        .iter()
+        .filter(|p| needs_convert.contains(p.meta.path.as_str()) || config.force)
+        .collect();
+    let mut conversions =
+        crate::pipeline::convert_pages(&to_convert, &config.crawl, budget.as_ref())
+            .await
+            .into_iter();
+
     for (i, page) in fetched_pages.iter().enumerate() {
         if needs_convert.contains(page.meta.path.as_str()) || config.force {
-            // Convert HTML → Markdown
-            let opts = ConvertOptions {
-                source_url: page.meta.url.clone(),
-                title: page.meta.title.clone(),
-                fetched_at: Some(page.meta.fetched_at.to_rfc3339()),
-            };
-
-            match contextbuilder_markdown::convert(&page.html, &opts) {
+            let conversion = conversions
+                .next()
+                .expect("one conversion per page that needs converting");
+            match conversion {
                 Ok(result) => {
-                    progress.page_converted(&page.meta.path, i + 1, total);
+                    if !language_allowed(result.language.as_deref(), &config.crawl.languages) {
+                        debug!(
+                            path = %page.meta.path,
+                            language = ?result.language,
+                            "page language not in target languages, dropping"
+                        );
+                        dropped.insert(page.meta.path.clone());
+                        continue;
+                    }
+                    progress.event(ProgressEvent::PageConverted {
+                        path: page.meta.path.clone(),
+                        current: i + 1,
+                        total,
+                    });
                     assembled_pages.push(AssemblePage {
                         path: page.meta.path.clone(),
                         markdown: result.markdown,
@@ -222,6 +316,7 @@ pub async fn update_kb(
                 }
                 Err(e) => {
                     warn!(path = %page.meta.path, error = %e, "conversion failed, skipping");
+                    progress.warning(format!("conversion failed for {}: {e}", page.meta.path));
                 }
             }
         } else {
@@ -242,37 +337,133 @@ pub async fn update_kb(
                 }
                 Err(e) => {
                     warn!(path = %page.meta.path, error = %e, "cannot read existing page, re-converting");
-                    let opts = ConvertOptions {
-                        source_url: page.meta.url.clone(),
-                        title: page.meta.title.clone(),
-                        fetched_at: Some(page.meta.fetched_at.to_rfc3339()),
-                    };
-                    if let Ok(result) = contextbuilder_markdown::convert(&page.html, &opts) {
-                        assembled_pages.push(AssemblePage {
-                            path: page.meta.path.clone(),
-                            markdown: result.markdown,
-                            title: result.title,
-                        });
+                    let opts = crate::pipeline::convert_options(page, &config.crawl);
+                    match contextbuilder_markdown::convert(&page.html, &opts) {
+                        Ok(result)
+                            if language_allowed(
+                                result.language.as_deref(),
+                                &config.crawl.languages,
+                            ) =>
+                        {
+                            assembled_pages.push(AssemblePage {
+                                path: page.meta.path.clone(),
+                                markdown: result.markdown,
+                                title: result.title,
+                            });
+                        }
+                        Ok(_) => {
+                            dropped.insert(page.meta.path.clone());
+                        }
+                        Err(_) => {}
                     }
                 }
             }
         }
     }
 
-    // Update storage for changed/new pages
-    for page in &fetched_pages {
-        if needs_convert.contains(page.meta.path.as_str()) {
-            let _ = storage.upsert_page(&page.meta).await;
-        }
+    // Pages in the wrong language leave the KB entirely
+    for path in &dropped {
+        crate::pipeline::drop_stored_page(storage, &kb_id.to_string(), path).await?;
+        let _ = std::fs::remove_file(config.kb_path.join("docs").join(format!("{path}.md")));
+    }
+    fetched_pages.retain(|p| !dropped.contains(&p.meta.path));
+
+    // Sub-pages of unchanged pages are kept as they are; those of
+    // re-converted pages are split off again
+    let mut refreshed: HashSet<String> = assembled_pages
+        .iter()
+        .filter(|p| needs_convert.contains(p.path.as_str()) || config.force)
+        .map(|p| p.path.clone())
+        .collect();
+    let kept: HashSet<String> = assembled_pages
+        .iter()
+        .filter(|p| !refreshed.contains(&p.path))
+        .map(|p| p.path.clone())
+        .collect();
+    for sub in existing_subpages.iter().filter(|p| kept.contains(split::parent_path(&p.path))) {
+        let md_path = config.kb_path.join("docs").join(format!("{}.md", sub.path));
+        let Ok(markdown) = std::fs::read_to_string(&md_path) else {
+            continue;
+        };
+        assembled_pages.push(AssemblePage {
+            path: sub.path.clone(),
+            markdown,
+            title: sub.title.clone().unwrap_or_else(|| sub.path.clone()),
+        });
+        fetched_pages.push(FetchedPage {
+            meta: sub.clone(),
+            content: ExtractedContent {
+                html: String::new(),
+                meta: Default::default(),
+            },
+            html: String::new(),
+            links: Vec::new(),
+        });
+    }
+    if let Some(threshold) = config.crawl.split_large_pages {
+        let (fresh, rest): (Vec<_>, Vec<_>) =
+            assembled_pages.into_iter().partition(|p| refreshed.contains(&p.path));
+        let reserved: Vec<String> = rest.iter().map(|p| p.path.clone()).collect();
+        assembled_pages =
+            split::split_fetched_pages(fresh, &mut fetched_pages, &reserved, threshold, ids);
+        refreshed.extend(assembled_pages.iter().map(|p| p.path.clone()));
+        assembled_pages.extend(rest);
+    }
+    let stale = existing_subpages.iter().filter(|p| {
+        refreshed.contains(split::parent_path(&p.path)) && !refreshed.contains(&p.path)
+    });
+    for sub in stale {
+        let _ = storage.delete_page(&sub.id).await;
+        let _ = storage.delete_chunks_for_page(&kb_id.to_string(), &sub.path).await;
+        let md_path = config.kb_path.join("docs").join(format!("{}.md", sub.path));
+        let _ = std::fs::remove_file(&md_path);
+    }
+
+    // Download images of re-converted pages; unchanged pages already link
+    // to their local copies
+    if config.crawl.download_assets {
+        progress.phase(Phase::DownloadAssets);
+        let urls: HashMap<&str, &str> = fetched_pages
+            .iter()
+            .map(|p| (p.meta.path.as_str(), p.meta.url.as_str()))
+            .collect();
+        let converted = assembled_pages
+            .iter_mut()
+            .filter(|p| refreshed.contains(&p.path))
+            .filter_map(|p| {
+                let url = urls.get(p.path.as_str()).copied()?;
+                Some((p, url))
+            });
+        let fetcher = crate::assets::AssetFetcher::new(&config.crawl)?;
+        crate::assets::localize_assets(converted, &config.kb_path, &fetcher).await?;
+    }
+
+    // Update storage for changed/new pages, with their new Markdown
+    let converted = assembled_pages.iter().filter(|p| refreshed.contains(&p.path));
+    crate::pipeline::store_page_contents(storage, &fetched_pages, converted).await?;
+
+    // Refresh section chunks for re-converted pages
+    if config.section_chunks {
+        let converted: Vec<AssemblePage> = assembled_pages
+            .iter()
+            .filter(|p| refreshed.contains(&p.path))
+            .cloned()
+            .collect();
+        crate::pipeline::store_chunks(storage, &kb_id.to_string(), &converted, ids).await?;
     }
 
     // --- Rebuild TOC ---
-    progress.phase("Rebuilding table of contents");
+    progress.phase(Phase::BuildToc);
     let all_metas: Vec<_> = fetched_pages.iter().map(|p| p.meta.clone()).collect();
-    let toc = toc::build_toc(&all_metas, &[]);
+    let toc = toc::build_toc(
+        &all_metas,
+        &split::nest_subpages(&crawl_result.toc, &all_metas),
+        config.crawl.max_toc_depth,
+        config.crawl.prune_empty_sections,
+    );
 
     // --- Re-assemble ---
-    progress.phase("Re-assembling knowledge base");
+    progress.phase(Phase::Assemble);
     let output_root = config
         .kb_path
         .parent()
@@ -285,12 +476,20 @@ pub async fn update_kb(
         source_url: manifest.source_url.clone(),
         output_root,
         tool_version: config.tool_version.clone(),
+        clock,
+        crawl_scope: Some(CrawlScope::new(
+            manifest.source_url.as_str(),
+            CrawlMode::Crawl,
+            &config.crawl,
+        )),
     };
 
     let _assemble_result =
         crate::assembler::assemble(&assemble_config, &assembled_pages, &toc)?;
+    crate::pipeline::write_related(storage, &kb_id.to_string(), &all_metas, &config.kb_path)
+        .await?;
 
-    let removed_count = if config.prune {
+    let removed_count = if prune {
         diff.removed_pages.len()
     } else {
         0
@@ -304,8 +503,15 @@ pub async fn update_kb(
         pages_unchanged: diff.unchanged_pages.len(),
         page_count: assembled_pages.len(),
         elapsed: start.elapsed(),
+        crawl: crawl_result,
     };
 
+    progress.event(ProgressEvent::Done {
+        kb_id: result.kb_id.to_string(),
+        page_count: result.page_count,
+        elapsed_ms: result.elapsed.as_millis() as u64,
+    });
+
     info!(
         pages_added = result.pages_added,
         pages_changed = result.pages_changed,
@@ -318,12 +524,101 @@ pub async fn update_kb(
     Ok(result)
 }
 
+// ---------------------------------------------------------------------------
+// Source validation
+// ---------------------------------------------------------------------------
+
+/// Outcome of [`validate_source`] for a reachable source.
+#[derive(Debug)]
+pub struct SourceCheck {
+    /// The manifest's source URL.
+    pub source_url: Url,
+    /// HTTP status the source answered with.
+    pub status: u16,
+    /// Time until the response headers arrived.
+    pub elapsed: std::time::Duration,
+}
+
+/// Check that the KB's source is still reachable, without crawling or
+/// writing anything.
+///
+/// Parses the manifest, then sends a HEAD request to its `source_url`
+/// (retrying as GET when the server rejects HEAD). DNS, TLS certificate,
+/// connection, and HTTP status failures are reported as distinct errors.
+#[instrument(skip_all, fields(kb_path = %config.kb_path.display()))]
+pub async fn validate_source(config: &UpdateKbConfig) -> Result<SourceCheck> {
+    let manifest = load_manifest(&config.kb_path)?;
+    let source_url = Url::parse(&manifest.source_url).map_err(|e| {
+        ContextBuilderError::validation(format!("invalid source_url in manifest: {e}"))
+    })?;
+    if !matches!(source_url.scheme(), "http" | "https") {
+        return Err(ContextBuilderError::validation(format!(
+            "source_url in manifest is not an http(s) URL: {source_url}"
+        )));
+    }
+    if config.crawl.offline {
+        return Err(ContextBuilderError::offline(format!("check {source_url}")));
+    }
+
+    let client = crate::pipeline::build_page_client(&config.crawl)?;
+    let start = Instant::now();
+    let send = |method: reqwest::Method| client.request(method, source_url.as_str()).send();
+    let mut response = send(reqwest::Method::HEAD)
+        .await
+        .map_err(|e| reach_error(&source_url, &e, config.crawl.max_redirects))?;
+    if matches!(response.status().as_u16(), 405 | 501) {
+        debug!(%source_url, status = %response.status(), "HEAD rejected, retrying as GET");
+        response = send(reqwest::Method::GET)
+            .await
+            .map_err(|e| reach_error(&source_url, &e, config.crawl.max_redirects))?;
+    }
+    let elapsed = start.elapsed();
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ContextBuilderError::http_status(&source_url, status.as_u16(), status));
+    }
+    info!(%source_url, status = status.as_u16(), "source reachable");
+    Ok(SourceCheck {
+        source_url,
+        status: status.as_u16(),
+        elapsed,
+    })
+}
+
+/// A network error for a failed request to `url`, naming what went wrong:
+/// DNS lookup, TLS certificate, connection, or timeout.
+fn reach_error(url: &Url, e: &reqwest::Error, max_redirects: usize) -> ContextBuilderError {
+    if e.is_redirect() {
+        return ContextBuilderError::redirect_limit(url, max_redirects);
+    }
+    let mut detail = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(err) = source {
+        detail.push_str(&format!(": {err}"));
+        source = err.source();
+    }
+    let lower = detail.to_lowercase();
+    let problem = if e.is_timeout() {
+        "timed out"
+    } else if lower.contains("dns error") || lower.contains("failed to lookup address") {
+        "DNS lookup failed"
+    } else if lower.contains("certificate") || lower.contains("tls") || lower.contains("ssl") {
+        "TLS certificate problem"
+    } else if e.is_connect() {
+        "connection failed"
+    } else {
+        "request failed"
+    };
+    ContextBuilderError::Network(format!("{url}: {problem}: {detail}"))
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
 /// Load and parse manifest.json from a KB directory.
-fn load_manifest(kb_path: &Path) -> Result<KbManifest> {
+pub(crate) fn load_manifest(kb_path: &Path) -> Result<KbManifest> {
     let manifest_path = kb_path.join("manifest.json");
     let content = std::fs::read_to_string(&manifest_path)
         .map_err(|e| ContextBuilderError::io(&manifest_path, e))?;
@@ -355,6 +650,8 @@ mod tests {
             fetched_at: Utc::now(),
             status_code: Some(200),
             content_len: Some(100),
+            depth: None,
+            description: None,
         }
     }
 
@@ -462,6 +759,51 @@ mod tests {
         assert_eq!(diff.removed_pages, vec!["page-c"]);
     }
 
+    #[tokio::test]
+    async fn diff_against_memory_storage() {
+        use contextbuilder_storage::{MemoryStorage, StorageBackend};
+
+        let storage = MemoryStorage::new();
+        for (path, hash) in [("page-a", "ha"), ("page-b", "hb"), ("page-c", "hc")] {
+            storage.upsert_page(&make_page_meta(path, hash)).await.unwrap();
+        }
+        let existing = storage.list_pages_by_kb("test-kb").await.unwrap();
+
+        // A re-crawl upserts what it fetches before the diff runs
+        let fetched = vec![
+            make_fetched_page("page-a", "ha"),
+            make_fetched_page("page-b", "hb-changed"),
+            make_fetched_page("page-d", "hd"),
+        ];
+        for page in &fetched {
+            storage.upsert_page(&page.meta).await.unwrap();
+        }
+
+        let diff = diff_pages(&existing, &fetched, false);
+        assert_eq!(diff.changed_pages, vec!["page-b"]);
+        assert_eq!(diff.new_pages, vec!["page-d"]);
+        assert_eq!(diff.removed_pages, vec!["page-c"]);
+
+        // Changed pages keep their original ID
+        let b = storage.get_page("test-kb", "page-b").await.unwrap().unwrap();
+        let old_b = existing.iter().find(|p| p.path == "page-b").unwrap();
+        assert_eq!(b.id, old_b.id);
+        assert_eq!(b.content_hash, "hb-changed");
+
+        for path in &diff.removed_pages {
+            let old = existing.iter().find(|p| &p.path == path).unwrap();
+            storage.delete_page(&old.id).await.unwrap();
+        }
+        let paths: Vec<String> = storage
+            .list_pages_by_kb("test-kb")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(paths, vec!["page-a", "page-b", "page-d"]);
+    }
+
     #[test]
     fn update_result_fields() {
         let result = UpdateKbResult {
@@ -472,6 +814,7 @@ mod tests {
             pages_unchanged: 10,
             page_count: 15,
             elapsed: std::time::Duration::from_secs(5),
+            crawl: CrawlResult::default(),
         };
         assert_eq!(result.pages_added, 2);
         assert_eq!(result.pages_removed, 1);
@@ -479,4 +822,218 @@ mod tests {
         assert_eq!(result.pages_unchanged, 10);
         assert_eq!(result.page_count, 15);
     }
+
+    /// An update config for the KB at `kb_path`, as `update` would build it.
+    fn update_config(kb_path: PathBuf) -> UpdateKbConfig {
+        UpdateKbConfig {
+            kb_path,
+            crawl: CrawlConfig::default(),
+            tool_version: "test".into(),
+            prune: false,
+            force: false,
+            section_chunks: false,
+            deterministic: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_source_accepts_a_reachable_source() {
+        let dir = crate::test_support::temp_dir("validate-ok");
+        let server = wiremock::MockServer::start().await;
+        // The server rejects HEAD, so the check falls back to GET
+        wiremock::Mock::given(wiremock::matchers::method("HEAD"))
+            .respond_with(wiremock::ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let kb_path = crate::test_support::seed_kb(
+            &dir,
+            "Example",
+            &server.uri(),
+            &[("index", "Home", "# Home\n")],
+        )
+        .await;
+        let manifest_before = std::fs::read(kb_path.join("manifest.json")).unwrap();
+
+        let check = validate_source(&update_config(kb_path.clone())).await.unwrap();
+        assert_eq!(check.status, 200);
+        assert_eq!(check.source_url.as_str().trim_end_matches('/'), server.uri());
+        assert_eq!(std::fs::read(kb_path.join("manifest.json")).unwrap(), manifest_before);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn validate_source_reports_unreachable_sources_distinctly() {
+        let dir = crate::test_support::temp_dir("validate-down");
+
+        // Nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let kb_path = crate::test_support::seed_kb(&dir, "Down", &closed, &[]).await;
+        let err = validate_source(&update_config(kb_path)).await.unwrap_err();
+        assert!(err.to_string().contains("connection failed"), "{err}");
+
+        let server = wiremock::MockServer::start().await;
+        let kb_path = crate::test_support::seed_kb(&dir, "Gone", &server.uri(), &[]).await;
+        let err = validate_source(&update_config(kb_path)).await.unwrap_err();
+        assert!(err.to_string().contains("HTTP 404"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn update_rejects_an_outdated_schema_with_a_migrate_hint() {
+        use contextbuilder_shared::CURRENT_SCHEMA_VERSION;
+
+        let dir = crate::test_support::temp_dir("update-old-schema");
+        let kb_path = crate::test_support::seed_kb(
+            &dir,
+            "Old",
+            "http://127.0.0.1:9/docs",
+            &[("index", "Home", "# Home\n")],
+        )
+        .await;
+        let manifest_path = kb_path.join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        manifest["schema_version"] = (CURRENT_SCHEMA_VERSION - 1).into();
+        let old_manifest = serde_json::to_string_pretty(&manifest).unwrap();
+        std::fs::write(&manifest_path, &old_manifest).unwrap();
+
+        // Rejected before anything is fetched or written
+        let err = update_kb(&update_config(kb_path.clone()), &crate::progress::SilentProgress)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&format!("uses schema_version {}", CURRENT_SCHEMA_VERSION - 1)));
+        let hint = format!("run `contextbuilder migrate --kb {}`", kb_path.display());
+        assert!(err.contains(&hint), "{err}");
+        assert_eq!(std::fs::read_to_string(&manifest_path).unwrap(), old_manifest);
+        assert!(crate::assembler::validate_kb(&kb_path).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn update_and_prune_keep_split_sub_pages() {
+        use crate::pipeline::{add_kb, AddKbConfig};
+        use contextbuilder_shared::{ArtifactLayout, LinkBase, SummaryFallback};
+
+        let dir = crate::test_support::temp_dir("update-split");
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "# Example\n\n## Docs\n\n- [Manual]({}/manual): Everything\n",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        let section = "<p>A long paragraph that explains this step of the setup in detail.</p>";
+        let manual = format!(
+            "<html><body><main><h1>Manual</h1><p>Intro.</p>\
+             <h2>Install</h2>{section}<h2>Configure</h2>{section}<h2>Run</h2>{section}\
+             </main></body></html>"
+        );
+        wiremock::Mock::given(wiremock::matchers::path("/manual"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(manual.clone()))
+            .mount(&server)
+            .await;
+
+        let crawl = CrawlConfig {
+            split_large_pages: Some(200),
+            ..CrawlConfig::default()
+        };
+        let added = add_kb(
+            &AddKbConfig {
+                url: Url::parse(&server.uri()).unwrap(),
+                name: "Example".into(),
+                output_root: dir.join("kb"),
+                mode: CrawlMode::LlmsTxt,
+                crawl: crawl.clone(),
+                tool_version: "test".into(),
+                model_id: "m".into(),
+                bridge_cmd: crate::test_support::fake_bridge(&dir),
+                bridge_script: "bridge.ts".into(),
+                bridge_working_dir: dir.to_string_lossy().into_owned(),
+                bridge_startup_timeout_secs: 5,
+                section_chunks: false,
+                deterministic: false,
+                custom_tasks: Vec::new(),
+                summary_fallback: SummaryFallback::None,
+                artifact_layout: ArtifactLayout::Flat,
+                llms_txt_links: LinkBase::SourceUrls,
+                compress_artifacts: false,
+                warm_cache_from: None,
+                merge: false,
+            },
+            &crate::progress::SilentProgress,
+        )
+        .await
+        .unwrap();
+        let kb_path = added.kb_path;
+        let subpages = ["manual/install", "manual/configure", "manual/run"];
+        let assert_subpages_kept = || {
+            let toc = serde_json::to_string(&crate::test_support::read_toc(&kb_path)).unwrap();
+            for path in subpages {
+                assert!(kb_path.join("docs").join(format!("{path}.md")).exists(), "{path}");
+                assert!(toc.contains(&format!("\"{path}\"")), "{path} not in {toc}");
+            }
+            assert_eq!(crate::test_support::read_manifest(&kb_path).page_count, 4);
+        };
+        assert_subpages_kept();
+
+        // A re-crawl fetches the manual again, but not its sub-pages
+        let manifest = load_manifest(&kb_path).unwrap();
+        let storage = Storage::open(&kb_path.join("indexes").join("contextbuilder.db"))
+            .await
+            .unwrap();
+        let update = |config: UpdateKbConfig| {
+            let (manifest, storage, manual) = (&manifest, &storage, &manual);
+            async move {
+                let existing = storage.list_pages_by_kb(&manifest.id.to_string()).await.unwrap();
+                let meta = existing.iter().find(|p| p.path == "manual").unwrap().clone();
+                let refetched = FetchedPage {
+                    content: contextbuilder_crawler::ExtractedContent {
+                        html: manual.clone(),
+                        meta: Default::default(),
+                    },
+                    html: manual.clone(),
+                    links: Vec::new(),
+                    meta,
+                };
+                let fetched = (CrawlResult::default(), vec![refetched]);
+                let (start, progress) = (Instant::now(), crate::progress::SilentProgress);
+                update_fetched(&config, manifest, storage, existing, fetched, start, &progress)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // Unchanged pages keep their sub-pages, and pruning leaves them alone
+        let config = UpdateKbConfig {
+            crawl,
+            prune: true,
+            ..update_config(kb_path.clone())
+        };
+        let updated = update(config.clone()).await;
+        assert_eq!((updated.pages_removed, updated.page_count), (0, 4));
+        assert_subpages_kept();
+
+        // Re-converted pages are split again into the same sub-pages
+        let updated = update(UpdateKbConfig {
+            force: true,
+            ..config
+        })
+        .await;
+        assert_eq!((updated.pages_removed, updated.page_count), (0, 4));
+        assert_subpages_kept();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -1,6 +1,8 @@
 //! Docusaurus platform adapter.
 
-use super::{ExtractedContent, PageMeta, PlatformAdapter};
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
 use contextbuilder_shared::TocEntry;
 use scraper::{Html, Selector};
 use url::Url;
@@ -30,26 +32,13 @@ impl PlatformAdapter for DocusaurusAdapter {
     }
 
     fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
-
-        // Try sidebar with .menu__list structure
+        // Sidebar categories nest a .menu__list inside their list item
         let link_sel = Selector::parse(".menu__list .menu__link").unwrap();
-        for el in doc.select(&link_sel) {
-            let title = el.text().collect::<String>().trim().to_string();
-            let path = el.value().attr("href").unwrap_or("").to_string();
-
-            if !title.is_empty() && !path.is_empty() {
-                entries.push(TocEntry {
-                    title,
-                    path: normalize_doc_path(&path),
-                    source_url: Some(path),
-                    summary: None,
-                    children: Vec::new(),
-                });
-            }
-        }
-
-        entries
+        let items = doc
+            .select(&link_sel)
+            .filter_map(|el| SidebarItem::from_link(el, list_depth(el)))
+            .collect();
+        nest_sidebar(items, normalize_doc_path)
     }
 
     fn extract_content(&self, doc: &Html) -> ExtractedContent {
@@ -6,13 +6,39 @@
 
 use super::{ExtractedContent, PageMeta, PlatformAdapter};
 use super::docusaurus::extract_h1;
-use contextbuilder_shared::TocEntry;
+use contextbuilder_shared::{ContextBuilderError, Result, TocEntry};
 use scraper::{Html, Selector};
 use url::Url;
 
 /// Generic adapter that works on arbitrary HTML pages.
 /// Always matches as the lowest-priority fallback.
-pub struct GenericAdapter;
+#[derive(Default)]
+pub struct GenericAdapter {
+    /// Extra elements to remove on top of the built-in chrome list.
+    strip_selectors: Vec<Selector>,
+}
+
+impl GenericAdapter {
+    /// Also strip every element matching one of `selectors` (e.g. cookie
+    /// banners or feedback widgets) from the extracted content.
+    pub fn with_strip_selectors(selectors: &[String]) -> Result<Self> {
+        Ok(Self {
+            strip_selectors: parse_strip_selectors(selectors)?,
+        })
+    }
+}
+
+/// Parse user-supplied strip selectors, naming the first invalid one.
+pub(super) fn parse_strip_selectors(selectors: &[String]) -> Result<Vec<Selector>> {
+    selectors
+        .iter()
+        .map(|s| {
+            Selector::parse(s).map_err(|e| {
+                ContextBuilderError::config(format!("invalid strip selector '{s}': {e}"))
+            })
+        })
+        .collect()
+}
 
 impl PlatformAdapter for GenericAdapter {
     fn detect(&self, _doc: &Html, _url: &Url) -> bool {
@@ -62,7 +88,7 @@ impl PlatformAdapter for GenericAdapter {
             if let Some(el) = doc.select(&sel).next() {
                 let html = el.inner_html();
                 return ExtractedContent {
-                    html: strip_chrome(&html),
+                    html: strip_chrome(&html, &self.strip_selectors),
                     meta: PageMeta {
                         title: extract_h1(doc),
                     },
@@ -75,7 +101,7 @@ impl PlatformAdapter for GenericAdapter {
         if let Some(body) = doc.select(&body_sel).next() {
             let html = body.inner_html();
             return ExtractedContent {
-                html: strip_chrome(&html),
+                html: strip_chrome(&html, &self.strip_selectors),
                 meta: PageMeta {
                     title: extract_h1(doc),
                 },
@@ -93,16 +119,19 @@ impl PlatformAdapter for GenericAdapter {
     }
 }
 
-/// Strip common navigation/chrome elements from HTML content.
-fn strip_chrome(html: &str) -> String {
+/// Strip common navigation/chrome elements, plus anything matching `extra`,
+/// from HTML content.
+fn strip_chrome(html: &str, extra: &[Selector]) -> String {
     let doc = Html::parse_fragment(html);
     let chrome_sel =
         Selector::parse("nav, header, footer, aside, script, style, .sidebar, .nav").unwrap();
 
     let mut result = html.to_string();
-    for el in doc.select(&chrome_sel) {
-        let outer = el.html();
-        result = result.replace(&outer, "");
+    for sel in std::iter::once(&chrome_sel).chain(extra) {
+        for el in doc.select(sel) {
+            let outer = el.html();
+            result = result.replace(&outer, "");
+        }
     }
     result
 }
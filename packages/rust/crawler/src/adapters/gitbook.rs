@@ -1,6 +1,8 @@
 //! GitBook platform adapter.
 
-use super::{ExtractedContent, PageMeta, PlatformAdapter};
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
 use super::docusaurus::extract_h1;
 use contextbuilder_shared::TocEntry;
 use scraper::{Html, Selector};
@@ -27,26 +29,13 @@ impl PlatformAdapter for GitBookAdapter {
     }
 
     fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
-
-        // GitBook sidebar links
+        // GitBook sidebar links, nested by their lists
         let link_sel = Selector::parse("aside nav a, .sidebar nav a").unwrap();
-        for el in doc.select(&link_sel) {
-            let title = el.text().collect::<String>().trim().to_string();
-            let path = el.value().attr("href").unwrap_or("").to_string();
-
-            if !title.is_empty() && !path.is_empty() {
-                entries.push(TocEntry {
-                    title,
-                    path: path.trim_start_matches('/').to_string(),
-                    source_url: Some(path),
-                    summary: None,
-                    children: Vec::new(),
-                });
-            }
-        }
-
-        entries
+        let items = doc
+            .select(&link_sel)
+            .filter_map(|el| SidebarItem::from_link(el, list_depth(el)))
+            .collect();
+        nest_sidebar(items, |href| href.trim_start_matches('/').to_string())
     }
 
     fn extract_content(&self, doc: &Html) -> ExtractedContent {
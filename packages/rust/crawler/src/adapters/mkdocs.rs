@@ -0,0 +1,148 @@
+//! MkDocs (Material) platform adapter.
+
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
+use super::docusaurus::extract_h1;
+use contextbuilder_shared::TocEntry;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+/// Chrome inside the Material article: edit/view buttons, heading
+/// permalinks, the "last update" footer, and the feedback widget.
+const ARTICLE_CHROME: &str = ".md-content__button, a.headerlink, .md-source-file, .md-feedback";
+
+/// Detects and extracts content from MkDocs sites, Material theme first.
+pub struct MkDocsAdapter;
+
+impl PlatformAdapter for MkDocsAdapter {
+    fn detect(&self, doc: &Html, _url: &Url) -> bool {
+        // Material's content container
+        let md_content = Selector::parse(".md-content").unwrap();
+        if doc.select(&md_content).next().is_some() {
+            return true;
+        }
+
+        // Any MkDocs theme announces itself in the generator meta, but the
+        // readthedocs theme's markup is Read the Docs adapter territory
+        let generator = Selector::parse(r#"meta[name="generator"]"#).unwrap();
+        let wy_sel = Selector::parse(".wy-nav-side").unwrap();
+        doc.select(&generator).any(|meta| {
+            meta.value()
+                .attr("content")
+                .is_some_and(|content| content.to_ascii_lowercase().starts_with("mkdocs"))
+        }) && doc.select(&wy_sel).next().is_none()
+    }
+
+    fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
+        // Each .md-nav__item's own label is a direct child: a link, a
+        // <label> for a section, or a container around a section index link.
+        // The secondary nav (the page's own headings) is skipped.
+        let item_sel = Selector::parse(".md-nav__list > .md-nav__item").unwrap();
+        let secondary_sel = Selector::parse(".md-nav--secondary").unwrap();
+        let link_sel = Selector::parse("a").unwrap();
+        let secondary: Vec<_> = doc.select(&secondary_sel).map(|nav| nav.id()).collect();
+        let items: Vec<SidebarItem> = doc
+            .select(&item_sel)
+            .filter(|el| !el.ancestors().any(|a| secondary.contains(&a.id())))
+            .filter_map(|el| {
+                let depth = list_depth(el);
+                let label = el
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .find(|child| child.value().classes().any(|c| c == "md-nav__link"))?;
+                let link = if label.value().name() == "a" {
+                    Some(label)
+                } else {
+                    label.select(&link_sel).next()
+                };
+                match link {
+                    Some(link) => SidebarItem::from_link(link, depth)
+                        .filter(|item| !item.href.as_deref().is_some_and(|h| h.starts_with('#'))),
+                    None => {
+                        let title = label.text().collect::<String>().trim().to_string();
+                        (!title.is_empty()).then_some(SidebarItem {
+                            depth,
+                            title,
+                            href: None,
+                        })
+                    }
+                }
+            })
+            .collect();
+
+        // Hrefs are relative to the page; Material's canonical link says
+        // where the page is
+        let canonical_sel = Selector::parse(r#"link[rel="canonical"]"#).unwrap();
+        let base = doc
+            .select(&canonical_sel)
+            .find_map(|link| Url::parse(link.value().attr("href")?).ok());
+        nest_sidebar(items, |href| match base.as_ref().and_then(|base| base.join(href).ok()) {
+            Some(url) => doc_path(url.path()),
+            None => doc_path(href),
+        })
+    }
+
+    fn extract_content(&self, doc: &Html) -> ExtractedContent {
+        let selectors = [
+            ".md-content article",
+            ".md-content__inner",
+            ".md-content",
+            r#"[role="main"]"#,
+            "main",
+        ];
+
+        for sel_str in selectors {
+            let sel = Selector::parse(sel_str).unwrap();
+            if let Some(el) = doc.select(&sel).next() {
+                return ExtractedContent {
+                    html: strip_article_chrome(&el.inner_html()),
+                    meta: PageMeta {
+                        // Without the permalink's pilcrow
+                        title: extract_h1(doc)
+                            .map(|title| title.trim_end_matches('¶').trim_end().to_string()),
+                    },
+                };
+            }
+        }
+
+        ExtractedContent {
+            html: String::new(),
+            meta: PageMeta { title: None },
+        }
+    }
+
+    fn name(&self) -> &str {
+        "mkdocs"
+    }
+}
+
+/// The path of a nav href. MkDocs writes them relative to the current page
+/// (`../guide/install/`), with directory URLs by default; without a base to
+/// resolve against, the leading `../` and `./` are dropped.
+fn doc_path(href: &str) -> String {
+    let mut path = href.split(['#', '?']).next().unwrap_or_default();
+    while let Some(rest) = path.strip_prefix("../").or_else(|| path.strip_prefix("./")) {
+        path = rest;
+    }
+    let path = path.trim_start_matches('/');
+    let path = path.strip_suffix("index.html").unwrap_or(path);
+    let path = path.trim_end_matches(".html").trim_end_matches('/');
+    if path.is_empty() || path == "." || path == ".." {
+        "index".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Remove [`ARTICLE_CHROME`] elements from extracted HTML.
+fn strip_article_chrome(html: &str) -> String {
+    let doc = Html::parse_fragment(html);
+    let chrome_sel = Selector::parse(ARTICLE_CHROME).unwrap();
+
+    let mut result = html.to_string();
+    for el in doc.select(&chrome_sel) {
+        result = result.replacen(&el.html(), "", 1);
+    }
+    result
+}
@@ -1,22 +1,30 @@
 //! Platform adapter trait and built-in adapters for content extraction.
 //!
-//! Adapters detect specific documentation platforms (Docusaurus, VitePress, etc.)
-//! and extract content + TOC intelligently for each platform.
+//! Adapters detect specific documentation platforms (Docusaurus, VitePress, MkDocs, etc.)
+//! and extract content + TOC intelligently for each platform. Unrecognised pages
+//! go to the readability scorer, then to the generic container heuristics.
 
 mod docusaurus;
 mod generic;
 mod gitbook;
+mod mkdocs;
+mod readability;
 mod readthedocs;
+mod sphinx;
 mod vitepress;
 
-use contextbuilder_shared::TocEntry;
-use scraper::Html;
+use contextbuilder_shared::{Result, TocEntry};
+use scraper::{ElementRef, Html};
+use tracing::debug;
 use url::Url;
 
 pub use docusaurus::DocusaurusAdapter;
 pub use generic::GenericAdapter;
 pub use gitbook::GitBookAdapter;
+pub use mkdocs::MkDocsAdapter;
+pub use readability::ReadabilityAdapter;
 pub use readthedocs::ReadTheDocsAdapter;
+pub use sphinx::SphinxAdapter;
 pub use vitepress::VitePressAdapter;
 
 // ---------------------------------------------------------------------------
@@ -39,6 +47,28 @@ pub struct ExtractedContent {
     pub meta: PageMeta,
 }
 
+impl ExtractedContent {
+    /// Number of visible words in the extracted HTML.
+    pub fn word_count(&self) -> usize {
+        visible_word_count(Html::parse_fragment(&self.html).root_element())
+    }
+}
+
+/// An adapter's extraction, scored against the page it came from.
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    /// Name of the adapter whose output was kept.
+    pub adapter: String,
+    /// The kept content.
+    pub content: ExtractedContent,
+    /// Visible words in `content`.
+    pub word_count: usize,
+    /// `word_count` relative to the visible words of the whole page body.
+    pub density: f64,
+    /// Whether the detected adapter's output was replaced by the generic one.
+    pub fell_back: bool,
+}
+
 /// Trait for platform-specific content extraction.
 ///
 /// Adapters are tried in priority order; `GenericAdapter` is the always-last fallback.
@@ -61,21 +91,45 @@ pub trait PlatformAdapter: Send + Sync {
 // Registry
 // ---------------------------------------------------------------------------
 
+/// Adapter output below this share of the page's words is suspect, and the
+/// generic extraction is tried as well.
+const MIN_CONTENT_DENSITY: f64 = 0.1;
+
+/// Pages with fewer visible words than this are too small to judge.
+const MIN_SCORED_WORDS: usize = 50;
+
 /// Holds registered adapters in priority order.
 pub struct AdapterRegistry {
     adapters: Vec<Box<dyn PlatformAdapter>>,
 }
 
 impl AdapterRegistry {
-    /// Create a registry with all built-in adapters (platform-specific first, generic last).
+    /// Create a registry with all built-in adapters (platform-specific first,
+    /// then readability, generic last).
     pub fn new() -> Self {
+        Self::with_fallbacks(ReadabilityAdapter::default(), GenericAdapter::default())
+    }
+
+    /// Like [`AdapterRegistry::new`], with the readability and generic
+    /// fallbacks also stripping elements matching `strip_selectors`.
+    pub fn with_strip_selectors(strip_selectors: &[String]) -> Result<Self> {
+        Ok(Self::with_fallbacks(
+            ReadabilityAdapter::with_strip_selectors(strip_selectors)?,
+            GenericAdapter::with_strip_selectors(strip_selectors)?,
+        ))
+    }
+
+    fn with_fallbacks(readability: ReadabilityAdapter, generic: GenericAdapter) -> Self {
         Self {
             adapters: vec![
                 Box::new(DocusaurusAdapter),
                 Box::new(VitePressAdapter),
                 Box::new(GitBookAdapter),
+                Box::new(MkDocsAdapter),
+                Box::new(SphinxAdapter),
                 Box::new(ReadTheDocsAdapter),
-                Box::new(GenericAdapter),
+                Box::new(readability),
+                Box::new(generic),
             ],
         }
     }
@@ -91,6 +145,64 @@ impl AdapterRegistry {
         // Unreachable: GenericAdapter always matches
         unreachable!("GenericAdapter must always match");
     }
+
+    /// Register `adapter` ahead of the built-in ones.
+    pub fn with_adapter(mut self, adapter: Box<dyn PlatformAdapter>) -> Self {
+        self.adapters.insert(0, adapter);
+        self
+    }
+
+    /// Extract content with the detected adapter, scoring the result.
+    ///
+    /// When a platform adapter keeps less than [`MIN_CONTENT_DENSITY`] of a
+    /// non-trivial page's words it has likely over-stripped, so the generic extraction is
+    /// run too and whichever keeps more words wins.
+    pub fn extract(&self, doc: &Html, url: &Url) -> Extraction {
+        let adapter = self.detect(doc, url);
+        let content = adapter.extract_content(doc);
+        let word_count = content.word_count();
+        let page_words = visible_word_count(doc.root_element());
+        let density = |words: usize| {
+            if page_words == 0 {
+                1.0
+            } else {
+                words as f64 / page_words as f64
+            }
+        };
+
+        let generic = self.adapters.last().expect("registry has a generic adapter");
+        let suspect = page_words >= MIN_SCORED_WORDS
+            && density(word_count) < MIN_CONTENT_DENSITY
+            && !std::ptr::addr_eq(adapter, generic.as_ref());
+        if suspect {
+            let fallback = generic.extract_content(doc);
+            let fallback_words = fallback.word_count();
+            if fallback_words > word_count {
+                debug!(
+                    %url,
+                    adapter = adapter.name(),
+                    words = word_count,
+                    fallback_words,
+                    "adapter output suspiciously small, using generic extraction"
+                );
+                return Extraction {
+                    adapter: generic.name().to_string(),
+                    content: fallback,
+                    word_count: fallback_words,
+                    density: density(fallback_words),
+                    fell_back: true,
+                };
+            }
+        }
+
+        Extraction {
+            adapter: adapter.name().to_string(),
+            content,
+            word_count,
+            density: density(word_count),
+            fell_back: false,
+        }
+    }
 }
 
 impl Default for AdapterRegistry {
@@ -98,3 +210,102 @@ impl Default for AdapterRegistry {
         Self::new()
     }
 }
+
+// ---------------------------------------------------------------------------
+// Sidebar nesting
+// ---------------------------------------------------------------------------
+
+/// A sidebar item in document order: a page link, or a group label when
+/// `href` is `None`.
+pub(crate) struct SidebarItem {
+    /// Nesting level; only the order between items' depths matters.
+    pub depth: usize,
+    pub title: String,
+    pub href: Option<String>,
+}
+
+impl SidebarItem {
+    /// The item for link `el` at `depth`, or `None` if it has no text.
+    /// Links to `#` (collapsible category toggles) become group labels.
+    pub(crate) fn from_link(el: ElementRef<'_>, depth: usize) -> Option<Self> {
+        let title = el.text().collect::<String>().trim().to_string();
+        let href = el
+            .value()
+            .attr("href")
+            .filter(|href| !href.is_empty() && *href != "#")
+            .map(str::to_string);
+        (!title.is_empty()).then_some(Self { depth, title, href })
+    }
+}
+
+/// Number of `<ul>`/`<ol>` elements enclosing `el`.
+pub(crate) fn list_depth(el: ElementRef<'_>) -> usize {
+    el.ancestors()
+        .filter(|a| a.value().as_element().is_some_and(|e| matches!(e.name(), "ul" | "ol")))
+        .count()
+}
+
+/// Nest sidebar `items` into a TOC: each item becomes a child of the
+/// nearest preceding item with a smaller depth. `to_path` maps an href to
+/// the entry's path; a group label takes the parent path of its first page.
+pub(crate) fn nest_sidebar(
+    items: Vec<SidebarItem>,
+    to_path: impl Fn(&str) -> String,
+) -> Vec<TocEntry> {
+    fn attach(stack: &mut Vec<(usize, TocEntry)>, roots: &mut Vec<TocEntry>) {
+        let (_, mut entry) = stack.pop().expect("non-empty stack");
+        if entry.source_url.is_none() {
+            entry.path = group_path(&entry);
+        }
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(entry),
+            None => roots.push(entry),
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, TocEntry)> = Vec::new();
+    for item in items {
+        while stack.last().is_some_and(|(depth, _)| *depth >= item.depth) {
+            attach(&mut stack, &mut roots);
+        }
+        let entry = TocEntry {
+            title: item.title,
+            path: item.href.as_deref().map(&to_path).unwrap_or_default(),
+            source_url: item.href,
+            summary: None,
+            children: Vec::new(),
+        };
+        stack.push((item.depth, entry));
+    }
+    while !stack.is_empty() {
+        attach(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Path of a group label `entry`: the parent path of its first page, else
+/// its slugged title.
+pub(crate) fn group_path(entry: &TocEntry) -> String {
+    entry
+        .children
+        .first()
+        .and_then(|child| child.path.rsplit_once('/'))
+        .map(|(parent, _)| parent.to_string())
+        .unwrap_or_else(|| entry.title.to_lowercase().replace(' ', "-"))
+}
+
+/// Visible words under `root`, skipping scripts, styles, and templates.
+fn visible_word_count(root: ElementRef<'_>) -> usize {
+    root.descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node.ancestors().any(|a| {
+                a.value().as_element().is_some_and(|e| {
+                    matches!(e.name(), "script" | "style" | "noscript" | "template")
+                })
+            });
+            (!hidden).then(|| text.split_whitespace().count())
+        })
+        .sum()
+}
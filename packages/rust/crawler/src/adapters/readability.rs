@@ -0,0 +1,272 @@
+//! Readability-style platform adapter.
+//!
+//! Handles pages without a `<main>` landmark (blogs, hand-rolled sites),
+//! where the generic container selectors grab too much or too little.
+//! Blocks of prose are scored by length and commas; each score is credited
+//! to the block's ancestors, weighted by tag and by positive or negative
+//! class/id hints, and discounted by link density. The best-scoring element,
+//! plus any siblings that score well, is taken as the article, and
+//! link-heavy or chrome-classed leftovers inside it are dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use super::docusaurus::extract_h1;
+use super::generic::{parse_strip_selectors, GenericAdapter};
+use super::{ExtractedContent, PageMeta, PlatformAdapter};
+use contextbuilder_shared::{Result, TocEntry};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+/// Class/id hints for article content.
+static POSITIVE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|main|page|post|prose|story|text")
+        .expect("valid regex")
+});
+
+/// Class/id hints for chrome, comments, and other non-article blocks.
+static NEGATIVE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)advert|banner|breadcrumb|comment|cookie|disqus|footer|masthead|menu|meta|nav|newsletter|pagination|pager|popup|promo|related|share|sidebar|social|sponsor|subscribe|tags|toolbar|widget",
+    )
+    .expect("valid regex")
+});
+
+/// Tags that are never part of an article.
+const CHROME_TAGS: [&str; 9] = [
+    "nav", "aside", "footer", "form", "script", "style", "noscript", "iframe", "button",
+];
+
+/// Elements scored as paragraphs. `div`s only count when they hold text
+/// directly instead of wrapping other blocks.
+const PARAGRAPH_TAGS: [&str; 5] = ["p", "pre", "td", "blockquote", "div"];
+
+/// Tags that make a `div` a wrapper rather than a paragraph.
+const BLOCK_TAGS: [&str; 16] = [
+    "p", "div", "pre", "blockquote", "table", "ul", "ol", "dl", "section", "article", "h1",
+    "h2", "h3", "h4", "h5", "h6",
+];
+
+/// Paragraphs shorter than this (in characters) are not scored.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// The best candidate must score at least this much to be an article.
+const MIN_SCORE: f64 = 20.0;
+
+/// Weight of a positive or negative class/id hint.
+const HINT_WEIGHT: f64 = 25.0;
+
+/// Blocks inside the article with more than this share of link text (and
+/// few commas) are navigation lists, not content.
+const MAX_LINK_DENSITY: f64 = 0.5;
+
+/// Extracts the main article from pages no platform adapter recognises.
+///
+/// Ranked just above [`GenericAdapter`]: it only claims pages without a
+/// `<main>` landmark that contain a convincing block of prose.
+#[derive(Default)]
+pub struct ReadabilityAdapter {
+    /// Extra elements to remove from the article.
+    strip_selectors: Vec<Selector>,
+}
+
+impl ReadabilityAdapter {
+    /// Also strip every element matching one of `selectors` from the article.
+    pub fn with_strip_selectors(selectors: &[String]) -> Result<Self> {
+        Ok(Self {
+            strip_selectors: parse_strip_selectors(selectors)?,
+        })
+    }
+}
+
+impl PlatformAdapter for ReadabilityAdapter {
+    fn detect(&self, doc: &Html, _url: &Url) -> bool {
+        let landmark = Selector::parse(r#"main, [role="main"]"#).unwrap();
+        doc.select(&landmark).next().is_none() && top_candidate(doc).is_some()
+    }
+
+    fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
+        GenericAdapter::default().extract_toc(doc)
+    }
+
+    fn extract_content(&self, doc: &Html) -> ExtractedContent {
+        let html = match top_candidate(doc) {
+            Some((top, score)) => article_html(top, score),
+            None => String::new(),
+        };
+        ExtractedContent {
+            html: clean(&html, &self.strip_selectors),
+            meta: PageMeta {
+                title: extract_h1(doc),
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        "readability"
+    }
+}
+
+/// The best-scoring article candidate and its score, if one is convincing.
+fn top_candidate(doc: &Html) -> Option<(ElementRef<'_>, f64)> {
+    let mut scores = HashMap::new();
+    for el in doc.root_element().descendants().filter_map(ElementRef::wrap) {
+        if !is_paragraph(el) || unlikely(el) {
+            continue;
+        }
+        let text: String = el.text().collect();
+        let len = text.trim().chars().count();
+        if len < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+
+        let ancestors = el.ancestors().filter_map(ElementRef::wrap).take(3);
+        for (level, ancestor) in ancestors.enumerate() {
+            if matches!(ancestor.value().name(), "html" | "body") {
+                break;
+            }
+            let divider = [1.0, 2.0, 6.0][level];
+            *scores.entry(ancestor.id()).or_insert_with(|| initial_score(ancestor)) +=
+                score / divider;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let el = ElementRef::wrap(doc.tree.get(id)?)?;
+            Some((el, score * (1.0 - link_density(el))))
+        })
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// The article: `top` plus the siblings that read as part of it, in order.
+fn article_html(top: ElementRef<'_>, top_score: f64) -> String {
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return top.html();
+    };
+    let threshold = (top_score * 0.2).max(10.0);
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top.id() {
+                return true;
+            }
+            if unlikely(*sibling) {
+                return false;
+            }
+            let name = sibling.value().name();
+            if matches!(name, "h1" | "h2" | "h3") {
+                return true;
+            }
+            let text: String = sibling.text().collect();
+            let len = text.trim().chars().count();
+            let density = link_density(*sibling);
+            let score = (class_weight(*sibling) + len as f64 / 100.0) * (1.0 - density);
+            score >= threshold
+                || (name == "p" && len > 80 && density < 0.25)
+                || (name == "p" && len > 0 && density == 0.0 && text.trim_end().ends_with('.'))
+        })
+        .map(|el| el.html())
+        .collect()
+}
+
+/// Remove chrome, negatively hinted blocks, link lists, and anything
+/// matching `extra` from the article HTML.
+fn clean(html: &str, extra: &[Selector]) -> String {
+    let doc = Html::parse_fragment(html);
+    let mut result = html.to_string();
+    let mut removed = HashSet::new();
+    for el in doc.root_element().descendants().filter_map(ElementRef::wrap) {
+        if el.ancestors().any(|a| removed.contains(&a.id())) {
+            continue;
+        }
+        let name = el.value().name();
+        let link_list = matches!(name, "div" | "section" | "ul" | "ol" | "table")
+            && el.text().collect::<String>().matches(',').count() < 10
+            && link_density(el) > MAX_LINK_DENSITY;
+        let remove = CHROME_TAGS.contains(&name)
+            || unlikely(el)
+            || link_list
+            || extra.iter().any(|sel| sel.matches(&el));
+        if remove {
+            removed.insert(el.id());
+            result = result.replacen(&el.html(), "", 1);
+        }
+    }
+    result
+}
+
+/// Whether `el` holds prose directly.
+fn is_paragraph(el: ElementRef<'_>) -> bool {
+    let name = el.value().name();
+    PARAGRAPH_TAGS.contains(&name)
+        && (name != "div"
+            || !el.children().filter_map(ElementRef::wrap).any(|child| {
+                BLOCK_TAGS.contains(&child.value().name())
+            }))
+}
+
+/// Whether `el` or an ancestor is chrome by tag or by a negative hint.
+/// `body`, `article` and `main` are exempt from hints, since themes put
+/// layout classes like `has-sidebar` on them.
+fn unlikely(el: ElementRef<'_>) -> bool {
+    std::iter::once(el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .any(|e| {
+            let v = e.value();
+            CHROME_TAGS.contains(&v.name())
+                || v.attr("role").is_some_and(|r| r == "navigation" || r == "complementary")
+                || (!matches!(v.name(), "html" | "body" | "article" | "main")
+                    && hints(e).any(|hint| NEGATIVE_RE.is_match(hint)))
+        })
+}
+
+/// Score an ancestor starts from: its tag's weight plus its class hints.
+fn initial_score(el: ElementRef<'_>) -> f64 {
+    let tag = match el.value().name() {
+        "div" | "article" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag + class_weight(el)
+}
+
+/// `+HINT_WEIGHT` per positive and `-HINT_WEIGHT` per negative class or id.
+/// A hint matching both (`post-comments`) counts as negative.
+fn class_weight(el: ElementRef<'_>) -> f64 {
+    hints(el)
+        .map(|hint| {
+            if NEGATIVE_RE.is_match(hint) {
+                -HINT_WEIGHT
+            } else if POSITIVE_RE.is_match(hint) {
+                HINT_WEIGHT
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// The element's id and class names.
+fn hints<'a>(el: ElementRef<'a>) -> impl Iterator<Item = &'a str> {
+    let v = el.value();
+    v.id().into_iter().chain(v.classes())
+}
+
+/// Share of `el`'s text that sits inside links.
+fn link_density(el: ElementRef<'_>) -> f64 {
+    let total: usize = el.text().map(str::len).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let links = Selector::parse("a").expect("valid selector");
+    let linked: usize = el.select(&links).flat_map(|a| a.text()).map(str::len).sum();
+    linked as f64 / total as f64
+}
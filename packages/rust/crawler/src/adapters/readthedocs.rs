@@ -1,6 +1,8 @@
 //! Read the Docs platform adapter.
 
-use super::{ExtractedContent, PageMeta, PlatformAdapter};
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
 use super::docusaurus::extract_h1;
 use contextbuilder_shared::TocEntry;
 use scraper::{Html, Selector};
@@ -34,9 +36,8 @@ impl PlatformAdapter for ReadTheDocsAdapter {
     }
 
     fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
-
-        // Try .wy-menu links, or .toctree links
+        // Try .wy-menu links, or .toctree links; toctree-l2 and deeper
+        // items sit in lists nested inside their parent's item
         let selectors = [
             ".wy-menu a",
             ".toctree-wrapper a",
@@ -45,29 +46,21 @@ impl PlatformAdapter for ReadTheDocsAdapter {
 
         for sel_str in selectors {
             let sel = Selector::parse(sel_str).unwrap();
-            for el in doc.select(&sel) {
-                let title = el.text().collect::<String>().trim().to_string();
-                let path = el.value().attr("href").unwrap_or("").to_string();
-
-                if !title.is_empty() && !path.is_empty() && !path.starts_with('#') {
-                    entries.push(TocEntry {
-                        title,
-                        path: path
-                            .trim_start_matches('/')
-                            .trim_end_matches(".html")
-                            .to_string(),
-                        source_url: Some(path),
-                        summary: None,
-                        children: Vec::new(),
-                    });
-                }
-            }
-            if !entries.is_empty() {
-                break; // Use the first selector that yields results
+            let items: Vec<_> = doc
+                .select(&sel)
+                .filter(|el| !el.value().attr("href").unwrap_or("").starts_with('#'))
+                .filter_map(|el| SidebarItem::from_link(el, list_depth(el)))
+                .filter(|item| item.href.is_some())
+                .collect();
+            if !items.is_empty() {
+                // Use the first selector that yields results
+                return nest_sidebar(items, |href| {
+                    href.trim_start_matches('/').trim_end_matches(".html").to_string()
+                });
             }
         }
 
-        entries
+        Vec::new()
     }
 
     fn extract_content(&self, doc: &Html) -> ExtractedContent {
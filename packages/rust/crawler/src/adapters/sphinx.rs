@@ -0,0 +1,108 @@
+//! Sphinx platform adapter (alabaster, furo, and other non-RTD themes).
+
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
+use super::docusaurus::extract_h1;
+use contextbuilder_shared::TocEntry;
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Sphinx chrome that can end up inside the content: heading permalinks
+/// (the `¶` anchors) and the theme's sidebars and footers.
+const CONTENT_CHROME: &str = "a.headerlink, .sphinxsidebar, .related, .footer, footer";
+
+/// Detects and extracts content from Sphinx sites that don't use the Read
+/// the Docs theme.
+pub struct SphinxAdapter;
+
+impl PlatformAdapter for SphinxAdapter {
+    fn detect(&self, doc: &Html, _url: &Url) -> bool {
+        // The sphinx_rtd_theme also announces Sphinx; its markup is left to
+        // the Read the Docs adapter
+        let generator = Selector::parse(r#"meta[name="generator"]"#).unwrap();
+        let body_sel = Selector::parse(r#"div.document, [role="main"]"#).unwrap();
+        let wy_sel = Selector::parse(".wy-nav-side, .wy-body-for-nav").unwrap();
+        doc.select(&generator).any(|meta| {
+            meta.value()
+                .attr("content")
+                .is_some_and(|content| content.to_ascii_lowercase().contains("sphinx"))
+        }) && doc.select(&body_sel).next().is_some()
+            && doc.select(&wy_sel).next().is_none()
+    }
+
+    fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
+        // The toctree on index pages, else the sidebar's global toctree
+        // (alabaster's .sphinxsidebar, furo's .sidebar-tree); deeper levels
+        // sit in lists nested inside their parent's item
+        let selectors = [
+            ".toctree-wrapper a",
+            r#".sphinxsidebar li[class*="toctree-l"] > a"#,
+            r#".sidebar-tree li[class*="toctree-l"] > a"#,
+        ];
+
+        for sel_str in selectors {
+            let sel = Selector::parse(sel_str).unwrap();
+            let items: Vec<_> = doc
+                .select(&sel)
+                .filter(|el| !el.value().attr("href").unwrap_or("").starts_with('#'))
+                .filter_map(|el| SidebarItem::from_link(el, list_depth(el)))
+                .filter(|item| item.href.is_some())
+                .collect();
+            if !items.is_empty() {
+                return nest_sidebar(items, doc_path);
+            }
+        }
+
+        Vec::new()
+    }
+
+    fn extract_content(&self, doc: &Html) -> ExtractedContent {
+        let selectors = ["div.body", r#"[role="main"]"#, "div.document", "main"];
+
+        for sel_str in selectors {
+            let sel = Selector::parse(sel_str).unwrap();
+            if let Some(el) = doc.select(&sel).next() {
+                return ExtractedContent {
+                    html: strip_chrome(&el.inner_html()),
+                    meta: PageMeta {
+                        // Without the permalink's pilcrow
+                        title: extract_h1(doc)
+                            .map(|title| title.trim_end_matches('¶').trim_end().to_string()),
+                    },
+                };
+            }
+        }
+
+        ExtractedContent {
+            html: String::new(),
+            meta: PageMeta { title: None },
+        }
+    }
+
+    fn name(&self) -> &str {
+        "sphinx"
+    }
+}
+
+/// The path of a toctree href. Sphinx writes them relative to the current
+/// page (`../api/client.html`); the leading `../` and `./` are dropped.
+fn doc_path(href: &str) -> String {
+    let mut path = href.split(['#', '?']).next().unwrap_or_default();
+    while let Some(rest) = path.strip_prefix("../").or_else(|| path.strip_prefix("./")) {
+        path = rest;
+    }
+    path.trim_start_matches('/').trim_end_matches(".html").trim_end_matches('/').to_string()
+}
+
+/// Remove [`CONTENT_CHROME`] elements from extracted HTML.
+fn strip_chrome(html: &str) -> String {
+    let doc = Html::parse_fragment(html);
+    let chrome_sel = Selector::parse(CONTENT_CHROME).unwrap();
+
+    let mut result = html.to_string();
+    for el in doc.select(&chrome_sel) {
+        result = result.replacen(&el.html(), "", 1);
+    }
+    result
+}
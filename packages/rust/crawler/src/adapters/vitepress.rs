@@ -1,6 +1,8 @@
 //! VitePress platform adapter.
 
-use super::{ExtractedContent, PageMeta, PlatformAdapter};
+use super::{
+    list_depth, nest_sidebar, ExtractedContent, PageMeta, PlatformAdapter, SidebarItem,
+};
 use super::docusaurus::extract_h1;
 use contextbuilder_shared::TocEntry;
 use scraper::{Html, Selector};
@@ -26,29 +28,46 @@ impl PlatformAdapter for VitePressAdapter {
     }
 
     fn extract_toc(&self, doc: &Html) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
+        // Each .VPSidebarItem carries its nesting as a `level-N` class, and
+        // its own label (a link, or a group heading) in its first .item
+        let item_sel = Selector::parse(".VPSidebar .VPSidebarItem").unwrap();
+        let label_sel = Selector::parse(".item").unwrap();
+        let link_sel = Selector::parse("a").unwrap();
+        let mut items: Vec<SidebarItem> = doc
+            .select(&item_sel)
+            .filter_map(|el| {
+                let label = el.select(&label_sel).next()?;
+                let depth = el
+                    .value()
+                    .classes()
+                    .find_map(|class| class.strip_prefix("level-")?.parse().ok())
+                    .unwrap_or(0);
+                match label.select(&link_sel).next() {
+                    Some(link) => SidebarItem::from_link(link, depth),
+                    None => {
+                        let title = label.text().collect::<String>().trim().to_string();
+                        (!title.is_empty()).then_some(SidebarItem {
+                            depth,
+                            title,
+                            href: None,
+                        })
+                    }
+                }
+            })
+            .collect();
 
-        // Try .VPSidebar links
-        let link_sel = Selector::parse(".VPSidebar a").unwrap();
-        for el in doc.select(&link_sel) {
-            let title = el.text().collect::<String>().trim().to_string();
-            let path = el.value().attr("href").unwrap_or("").to_string();
-
-            if !title.is_empty() && !path.is_empty() {
-                entries.push(TocEntry {
-                    title,
-                    path: path
-                        .trim_start_matches('/')
-                        .trim_end_matches(".html")
-                        .to_string(),
-                    source_url: Some(path),
-                    summary: None,
-                    children: Vec::new(),
-                });
-            }
+        // Older or custom themes: plain links, nested by their lists
+        if items.is_empty() {
+            let link_sel = Selector::parse(".VPSidebar a").unwrap();
+            items = doc
+                .select(&link_sel)
+                .filter_map(|el| SidebarItem::from_link(el, list_depth(el)))
+                .collect();
         }
 
-        entries
+        nest_sidebar(items, |href| {
+            href.trim_start_matches('/').trim_end_matches(".html").to_string()
+        })
     }
 
     fn extract_content(&self, doc: &Html) -> ExtractedContent {
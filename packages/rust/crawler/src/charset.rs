@@ -0,0 +1,99 @@
+//! Character-set detection and transcoding of fetched bodies.
+//!
+//! Older documentation sites still serve latin-1 / windows-1252 pages.
+//! Bodies are decoded using, in order: a byte-order mark, the `charset` of
+//! the `Content-Type` header, and a `<meta charset>` (or `http-equiv`)
+//! declaration near the top of the document, defaulting to UTF-8. Bytes that
+//! do not decode are replaced with U+FFFD rather than failing the page.
+
+use std::sync::LazyLock;
+
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+
+/// How far into the document to look for a `<meta>` charset declaration.
+const META_PRESCAN_BYTES: usize = 1024;
+
+/// `<meta charset="...">` or `<meta http-equiv=... content="...; charset=...">`.
+static META_CHARSET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<meta\s[^>]*?charset\s*=\s*["']?\s*([a-z0-9_:.-]+)"#).expect("valid regex")
+});
+
+/// Read a response body as text, transcoding it to UTF-8.
+pub async fn response_text(response: reqwest::Response) -> reqwest::Result<String> {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let bytes = response.bytes().await?;
+    Ok(decode_body(&bytes, content_type.as_deref()))
+}
+
+/// Decode `bytes` to UTF-8 using the detected character set.
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let (text, _, _) = detect_encoding(bytes, content_type).decode(bytes);
+    text.into_owned()
+}
+
+/// The encoding `bytes` should be decoded with.
+pub fn detect_encoding(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    content_type
+        .and_then(header_charset)
+        .or_else(|| meta_charset(bytes))
+        .unwrap_or(UTF_8)
+}
+
+/// The `charset` parameter of a `Content-Type` header value.
+fn header_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+/// The charset declared by a `<meta>` tag in the document's first bytes.
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(META_PRESCAN_BYTES)];
+    let label = META_CHARSET_RE.captures(head)?.get(1)?;
+    let encoding = Encoding::for_label(label.as_bytes())?;
+    // A UTF-16 declaration in an ASCII-readable document is wrong by definition
+    Some(if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        UTF_8
+    } else {
+        encoding
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_charset_wins_over_meta() {
+        let body = b"<html><head><meta charset=\"utf-8\"></head><body>caf\xe9</body></html>";
+        let content_type = Some("text/html; charset=ISO-8859-1");
+        assert_eq!(detect_encoding(body, content_type).name(), "windows-1252");
+        assert!(decode_body(body, content_type).contains("café"));
+    }
+
+    #[test]
+    fn meta_declarations_are_honoured() {
+        let body =
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">\xe9";
+        assert_eq!(detect_encoding(body, Some("text/html")).name(), "windows-1252");
+        let body = b"<meta charset='utf-16'>plain";
+        assert_eq!(detect_encoding(body, None), UTF_8);
+    }
+
+    #[test]
+    fn malformed_utf8_is_decoded_lossily() {
+        assert_eq!(decode_body(b"ok \xff\xfe done", None), "ok \u{fffd}\u{fffd} done");
+    }
+}
@@ -3,24 +3,35 @@
 //! The crawler starts from a given URL, performs BFS traversal within scope,
 //! respects depth/concurrency/rate limits, and stores results via the storage layer.
 
-use std::collections::HashSet;
-use std::net::IpAddr;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::Utc;
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, ClientBuilder, Proxy};
 use scraper::{Html, Selector};
 use sha2::{Digest, Sha256};
 use tokio::sync::{Mutex, Semaphore};
 use tracing::{debug, info, instrument, warn};
-use url::Url;
+use url::{Origin, Url};
 use uuid::Uuid;
 
-use contextbuilder_shared::{ContextBuilderError, CrawlConfig, PageMeta, Result};
-use contextbuilder_storage::Storage;
-
-use crate::adapters::{AdapterRegistry, ExtractedContent};
+pub use contextbuilder_shared::is_ssrf_target;
+use contextbuilder_shared::{
+    sanitize_filename, truncate_title, ContextBuilderError, CrawlConfig, HashBasis, HttpAuth,
+    PageMeta, Result, TocEntry,
+};
+use contextbuilder_storage::StorageBackend;
+
+use crate::adapters::{group_path, AdapterRegistry, ExtractedContent};
+use crate::charset;
+use crate::filter::DropSelectors;
+use crate::pdf;
+use crate::robots::{self, RobotsRules};
+use crate::sitemap;
+use crate::soft404::Soft404Detector;
+use crate::throttle::{HostThrottle, ThrottlePermit};
 
 /// User-Agent string for crawl requests.
 const USER_AGENT: &str = concat!("ContextBuilder/", env!("CARGO_PKG_VERSION"));
@@ -30,7 +41,7 @@ const USER_AGENT: &str = concat!("ContextBuilder/", env!("CARGO_PKG_VERSION"));
 // ---------------------------------------------------------------------------
 
 /// Summary of a completed crawl operation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CrawlResult {
     /// Number of pages successfully fetched.
     pub pages_fetched: usize,
@@ -42,6 +53,93 @@ pub struct CrawlResult {
     pub duration: Duration,
     /// Adapter name used for the majority of pages.
     pub primary_adapter: String,
+    /// Errors by kind (`http_404`, `network`, `redirect_limit`, ...).
+    pub error_kinds: BTreeMap<String, usize>,
+    /// Body bytes downloaded for fetched pages.
+    pub bytes_downloaded: u64,
+    /// Mean time from request to body read, over completed fetches.
+    pub avg_latency: Duration,
+    /// Fetched pages per detected adapter.
+    pub adapters: BTreeMap<String, usize>,
+    /// Most URLs waiting in the crawl queue at once.
+    pub peak_queue_len: usize,
+    /// Links (and `robots.txt` sitemap seeds) not enqueued because the queue
+    /// was full.
+    pub links_dropped: usize,
+    /// The crawl stopped at `max_pages` with URLs still queued.
+    pub truncated: bool,
+    /// The largest sidebar TOC an adapter found, with entry paths matching
+    /// the fetched pages' paths. Empty when no page had a sidebar.
+    pub toc: Vec<TocEntry>,
+    /// Fetched pages per link depth from the start URL.
+    pub pages_by_depth: BTreeMap<u32, usize>,
+    /// Request counts, bytes, errors, and latency per `host[:port]`.
+    pub hosts: BTreeMap<String, HostMetrics>,
+}
+
+/// Requests made to one host during a crawl.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostMetrics {
+    /// Completed fetches, successful or not.
+    pub requests: usize,
+    /// Body bytes downloaded for fetched pages.
+    pub bytes: u64,
+    /// Fetches that failed.
+    pub errors: usize,
+    /// Median time from request to body read.
+    pub p50_latency: Duration,
+    /// 95th-percentile time from request to body read.
+    pub p95_latency: Duration,
+}
+
+impl HostMetrics {
+    /// Metrics for `latencies` (one per request), with percentiles by
+    /// nearest rank.
+    fn from_latencies(mut latencies: Vec<Duration>) -> Self {
+        latencies.sort_unstable();
+        let percentile = |p: usize| {
+            let rank = (latencies.len() * p).div_ceil(100).max(1);
+            latencies.get(rank - 1).copied().unwrap_or_default()
+        };
+        Self {
+            requests: latencies.len(),
+            p50_latency: percentile(50),
+            p95_latency: percentile(95),
+            ..Self::default()
+        }
+    }
+}
+
+impl CrawlResult {
+    /// Fetches refused with HTTP 401 or 403.
+    pub fn auth_failures(&self) -> usize {
+        ["http_401", "http_403"]
+            .iter()
+            .filter_map(|kind| self.error_kinds.get(*kind))
+            .sum()
+    }
+
+    /// A hint to configure credentials, when most fetches were refused with
+    /// HTTP 401/403.
+    pub fn auth_hint(&self) -> Option<String> {
+        auth_hint(self.auth_failures(), self.pages_fetched + self.errors.len())
+    }
+}
+
+/// Share of refused fetches at or above which a site is reported as needing
+/// authentication.
+const AUTH_HINT_SHARE: f64 = 0.5;
+
+/// A "this site requires authentication" hint, when `refused` of `attempted`
+/// fetches answered HTTP 401/403 and that is at least [`AUTH_HINT_SHARE`].
+pub fn auth_hint(refused: usize, attempted: usize) -> Option<String> {
+    (refused > 0 && refused as f64 >= attempted as f64 * AUTH_HINT_SHARE).then(|| {
+        format!(
+            "{refused} of {attempted} pages answered HTTP 401/403: this site requires \
+             authentication. Set `headers` (e.g. an `Authorization` header) or `cookies` \
+             under [crawl_policies] and retry."
+        )
+    })
 }
 
 /// A fetched page with its extracted content.
@@ -66,30 +164,138 @@ pub struct Crawler {
     config: CrawlConfig,
     client: Client,
     registry: AdapterRegistry,
+    drop_selectors: DropSelectors,
+    skip_selectors: DropSelectors,
     /// Allow localhost/private IPs (for integration tests with mock servers).
     allow_localhost: bool,
+    /// Throttle shared across crawls (for tests); otherwise each crawl
+    /// starts its own.
+    throttle: Option<Arc<HostThrottle>>,
+    /// Parallelism budget shared with the other pipeline phases.
+    budget: Option<Arc<Semaphore>>,
+    /// Already-stored pages, by visit key, with their outgoing links.
+    known_pages: HashMap<String, Vec<String>>,
+    /// Pages to fetch instead of crawling from the start URL.
+    seeds: Vec<Url>,
 }
 
 impl Crawler {
     /// Create a new crawler with the given configuration.
     pub fn new(config: CrawlConfig) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent(USER_AGENT)
-            .redirect(reqwest::redirect::Policy::limited(5))
-            .timeout(Duration::from_secs(30))
+        if config.offline {
+            return Err(ContextBuilderError::offline("start the crawler"));
+        }
+
+        // One client for the whole crawl: per-task clones share its
+        // connection pool, so same-host fetches reuse resolved, negotiated
+        // keep-alive connections instead of reconnecting.
+        let client = with_auth(Client::builder(), &config.auth)?
+            .redirect(reqwest::redirect::Policy::limited(config.max_redirects))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .tcp_keepalive(Duration::from_secs(config.tcp_keepalive_secs))
+            .connect_timeout(config.timeouts.connect())
+            .read_timeout(config.timeouts.read())
+            .timeout(config.timeouts.total())
             .build()
             .map_err(|e| {
                 ContextBuilderError::Network(format!("failed to build HTTP client: {e}"))
             })?;
 
+        let drop_selectors = DropSelectors::new(&config.drop_if_selector_matches)?;
+        let skip_selectors = DropSelectors::with_label(&config.skip_page_selectors, "skip page")?;
+        let registry = AdapterRegistry::with_strip_selectors(&config.strip_selectors)?;
+
         Ok(Self {
             config,
             client,
-            registry: AdapterRegistry::new(),
+            registry,
+            drop_selectors,
+            skip_selectors,
             allow_localhost: false,
+            throttle: None,
+            budget: None,
+            known_pages: HashMap::new(),
+            seeds: Vec::new(),
         })
     }
 
+    /// Also take a permit from `budget` for every fetch, so the crawl shares
+    /// one overall parallelism cap with the rest of the pipeline.
+    pub fn with_budget(mut self, budget: Arc<Semaphore>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Treat `pages` (URL and stored outgoing links) as already fetched: they
+    /// are not requested again, and their stored links are followed instead,
+    /// so only pages new to the KB are fetched.
+    pub fn with_known_pages(
+        mut self,
+        pages: impl IntoIterator<Item = (String, Vec<String>)>,
+    ) -> Self {
+        self.known_pages = pages
+            .into_iter()
+            .filter_map(|(url, links)| {
+                let url = Url::parse(&url).ok()?;
+                Some((visit_key(&url, &self.config), links))
+            })
+            .collect();
+        self
+    }
+
+    /// Fetch `seeds` (e.g. a sitemap's pages) instead of crawling from the
+    /// start URL. Their links are not followed, since the list is taken to
+    /// be complete; scope, `robots.txt`, and `max_pages` still apply, with
+    /// the start URL anchoring the scope.
+    pub fn with_seeds(mut self, seeds: Vec<Url>) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    /// The `User-Agent` sent with requests, matched against `robots.txt`.
+    fn user_agent(&self) -> &str {
+        self.config.auth.user_agent.as_deref().unwrap_or(USER_AGENT)
+    }
+
+    /// Queue the `links` of the page at `url` (found at `depth`), capped at
+    /// `max_links_per_page` and by `max_queue_size`. Returns how many links
+    /// were dropped because the queue was full.
+    fn enqueue_links(
+        &self,
+        queue: &mut Vec<(Url, u32)>,
+        url: &str,
+        links: &[String],
+        depth: u32,
+    ) -> usize {
+        let max_links = self.config.max_links_per_page;
+        if max_links > 0 && links.len() > max_links {
+            warn!(
+                url,
+                links = links.len(),
+                max_links,
+                "link fan-out cap hit, enqueueing first links only"
+            );
+        }
+        let limit = if max_links > 0 { max_links } else { usize::MAX };
+        let max_queue = self.config.max_queue_size;
+        let capacity = if max_queue > 0 { max_queue } else { usize::MAX };
+        let mut dropped = 0;
+        for link in links.iter().take(limit) {
+            let Ok(link_url) = Url::parse(link) else {
+                continue;
+            };
+            if queue.len() >= capacity {
+                dropped += 1;
+                continue;
+            }
+            queue.push((link_url, depth + 1));
+        }
+        if dropped > 0 {
+            debug!(url, dropped, max_queue, "crawl queue full, not enqueueing links");
+        }
+        dropped
+    }
+
     /// Allow crawling localhost/private IPs (for integration tests).
     #[cfg(test)]
     pub fn allow_localhost(mut self) -> Self {
@@ -97,6 +303,13 @@ impl Crawler {
         self
     }
 
+    /// Throttle requests through `throttle`, so tests can inspect it.
+    #[cfg(test)]
+    fn with_throttle(mut self, throttle: Arc<HostThrottle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
     /// Crawl starting from `start_url`, storing results in `storage`.
     ///
     /// Returns a summary of the crawl and the list of fetched pages.
@@ -105,22 +318,92 @@ impl Crawler {
         &self,
         start_url: &Url,
         kb_id: &str,
-        storage: &Storage,
+        storage: &dyn StorageBackend,
     ) -> Result<(CrawlResult, Vec<FetchedPage>)> {
         let start_time = std::time::Instant::now();
 
         // Create crawl job
         let crawl_job_id = storage.insert_crawl_job(kb_id).await?;
 
-        let scope = CrawlScope::new(start_url, &self.config);
+        let mut queue: Vec<(Url, u32)> = if self.seeds.is_empty() {
+            vec![(start_url.clone(), 0)]
+        } else {
+            self.seeds.iter().map(|url| (url.clone(), 0)).collect()
+        };
+        // A seeded crawl fetches its list without following links
+        let max_depth = if self.seeds.is_empty() { self.config.depth } else { 0 };
+        let mut robots = RobotsRules::default();
+        let mut links_dropped: usize = 0;
+        if self.config.respect_robots_txt || self.config.robots_sitemaps {
+            let _budget = match &self.budget {
+                Some(budget) => Some(budget.acquire().await.expect("budget closed")),
+                None => None,
+            };
+            if let Some((robots_url, robots_txt)) =
+                robots::fetch_robots_txt(&self.client, start_url).await
+            {
+                if self.config.respect_robots_txt {
+                    robots = RobotsRules::parse(&robots_txt, self.user_agent());
+                }
+                if self.config.robots_sitemaps {
+                    let seeds = sitemap::robots_sitemap_seeds(
+                        &self.client,
+                        &robots_txt,
+                        &robots_url,
+                        self.allow_localhost,
+                    )
+                    .await;
+                    let max_queue = self.config.max_queue_size;
+                    let room = if max_queue > 0 {
+                        max_queue.saturating_sub(queue.len())
+                    } else {
+                        usize::MAX
+                    };
+                    if seeds.len() > room {
+                        warn!(
+                            seeds = seeds.len(),
+                            max_queue,
+                            "crawl queue full, not seeding every sitemap page"
+                        );
+                        links_dropped += seeds.len() - room;
+                    }
+                    queue.extend(seeds.into_iter().take(room).map(|url| (url, 0)));
+                }
+            }
+        }
+
+        let mut politeness = self.config.clone();
+        if let Some(delay) = robots.crawl_delay() {
+            let delay_ms = delay.as_millis() as u64;
+            if delay_ms > politeness.rate_limit_ms {
+                info!(delay_ms, "using the Crawl-delay from robots.txt");
+                politeness.rate_limit_ms = delay_ms;
+            }
+        }
+        let scope = CrawlScope::new(start_url, &self.config).with_robots(start_url, robots);
         let visited = Arc::new(Mutex::new(HashSet::<String>::new()));
         let semaphore = Arc::new(Semaphore::new(self.config.concurrency as usize));
-
-        let mut queue: Vec<(Url, u32)> = vec![(start_url.clone(), 0)];
+        let throttle = match &self.throttle {
+            Some(throttle) => Arc::clone(throttle),
+            None => Arc::new(HostThrottle::new(&politeness)),
+        };
         let mut fetched_pages: Vec<FetchedPage> = Vec::new();
         let mut errors: Vec<(String, String)> = Vec::new();
+        let mut error_kinds: BTreeMap<String, usize> = BTreeMap::new();
         let mut pages_skipped: usize = 0;
-        let mut primary_adapter = String::from("generic");
+        let mut adapters: BTreeMap<String, usize> = BTreeMap::new();
+        let mut pages_by_depth: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut bytes_downloaded: u64 = 0;
+        let mut latency_total = Duration::ZERO;
+        let mut fetches: u32 = 0;
+        // Per host: latencies, bytes, and errors
+        let mut host_stats: BTreeMap<String, (Vec<Duration>, u64, usize)> = BTreeMap::new();
+        let mut peak_queue_len: usize = queue.len();
+        let soft_404 = if self.config.detect_soft_404 {
+            Soft404Detector::probe(&self.client, start_url).await
+        } else {
+            None
+        };
 
         info!(
             depth = self.config.depth,
@@ -129,17 +412,31 @@ impl Crawler {
             "starting crawl"
         );
 
+        let max_pages = self.config.max_pages;
+        let mut truncated = false;
+        let mut toc: Vec<TocEntry> = Vec::new();
         while !queue.is_empty() {
-            // Take a batch from the queue (up to concurrency limit)
+            // Take a batch from the queue (up to concurrency limit and the
+            // pages still allowed)
+            let remaining = if max_pages > 0 {
+                max_pages.saturating_sub(fetched_pages.len())
+            } else {
+                usize::MAX
+            };
+            if remaining == 0 {
+                warn!(max_pages, queued = queue.len(), "page limit reached, stopping crawl");
+                truncated = true;
+                break;
+            }
             let batch: Vec<(Url, u32)> = {
-                let drain_count = queue.len().min(self.config.concurrency as usize);
+                let drain_count = queue.len().min(self.config.concurrency as usize).min(remaining);
                 queue.drain(..drain_count).collect()
             };
 
             let mut handles = Vec::new();
 
             for (url, depth) in batch {
-                let normalized = normalize_url(&url);
+                let normalized = visit_key(&url, &self.config);
 
                 // Check if already visited
                 {
@@ -151,9 +448,10 @@ impl Crawler {
                     vis.insert(normalized.clone());
                 }
 
-                // Check scope
-                if !scope.in_scope(&url) {
-                    debug!(%url, "out of scope, skipping");
+                // Check scope (including robots.txt)
+                let rule = scope.check(&url);
+                if !rule.in_scope() {
+                    debug!(%url, %rule, "out of scope, skipping");
                     pages_skipped += 1;
                     continue;
                 }
@@ -165,64 +463,141 @@ impl Crawler {
                     continue;
                 }
 
+                // Already stored: follow its stored links without fetching
+                if let Some(links) = self.known_pages.get(&normalized) {
+                    debug!(%url, "page already stored, not fetching");
+                    pages_skipped += 1;
+                    if depth < max_depth {
+                        links_dropped += self.enqueue_links(&mut queue, url.as_str(), links, depth);
+                        peak_queue_len = peak_queue_len.max(queue.len());
+                    }
+                    continue;
+                }
+
                 let client = self.client.clone();
                 let sem = semaphore.clone();
-                let rate_limit = self.config.rate_limit_ms;
+                let budget = self.budget.clone();
+                let throttle = throttle.clone();
+                let opts = FetchOptions::from(&self.config);
                 let kb_id_owned = kb_id.to_string();
 
                 handles.push(tokio::spawn(async move {
-                    let _permit = sem.acquire().await.expect("semaphore closed");
-
-                    // Rate limiting
-                    if rate_limit > 0 {
-                        tokio::time::sleep(Duration::from_millis(rate_limit)).await;
-                    }
+                    // Per-host rate limiting, backing off on 429/5xx. The
+                    // host's delay is waited out before taking a crawl-wide
+                    // permit, so a slow host does not hold up the others.
+                    let host = url.host_str().unwrap_or_default().to_string();
+                    let mut slot = throttle.acquire(&host).await;
 
-                    fetch_page(&client, &url, depth, &kb_id_owned).await
+                    let _permit = sem.acquire().await.expect("semaphore closed");
+                    let _budget = match &budget {
+                        Some(budget) => Some(budget.acquire().await.expect("budget closed")),
+                        None => None,
+                    };
+
+                    let started = std::time::Instant::now();
+                    let result =
+                        fetch_page(&client, &url, depth, &kb_id_owned, opts, &mut slot).await;
+                    (url, started.elapsed(), result)
                 }));
             }
 
             // Collect results
             for handle in handles {
-                match handle.await {
-                    Ok(Ok((page, depth))) => {
-                        // Detect adapter for the first page
-                        if fetched_pages.is_empty() {
-                            let doc = Html::parse_document(&page.html);
-                            let adapter = self.registry.detect(&doc, &Url::parse(&page.meta.url).unwrap_or_else(|_| Url::parse("https://example.com").unwrap()));
-                            primary_adapter = adapter.name().to_string();
+                let (url, latency, result) = match handle.await {
+                    Ok(fetched) => fetched,
+                    Err(e) => {
+                        errors.push(("task".into(), e.to_string()));
+                        *error_kinds.entry("task".into()).or_default() += 1;
+                        pages_skipped += 1;
+                        continue;
+                    }
+                };
+                latency_total += latency;
+                fetches += 1;
+                let host = host_stats.entry(host_key(&url)).or_default();
+                host.0.push(latency);
+
+                match result {
+                    Ok(Some((page, depth))) => {
+                        let bytes = page.meta.content_len.unwrap_or(0) as u64;
+                        bytes_downloaded += bytes;
+                        host.1 += bytes;
+
+                        if soft_404.as_ref().is_some_and(|d| d.is_soft_404(&page.html)) {
+                            debug!(url = %page.meta.url, "soft-404 page, skipping");
+                            pages_skipped += 1;
+                            continue;
+                        }
+
+                        // Skip pages matching a skip selector, links and all
+                        if let Some(selector) = self.skip_selectors.matching(&page.html) {
+                            debug!(
+                                url = %page.meta.url,
+                                selector,
+                                "page matches skip selector, not storing or following links"
+                            );
+                            pages_skipped += 1;
+                            continue;
                         }
 
-                        // Enqueue child links if within depth
-                        if depth < self.config.depth {
-                            for link in &page.links {
-                                if let Ok(link_url) = Url::parse(link) {
-                                    queue.push((link_url, depth + 1));
-                                }
+                        let adapter = {
+                            let doc = Html::parse_document(&page.html);
+                            // Collapsed sidebars hide part of the tree, so
+                            // keep the most complete one seen
+                            let page_toc = self.registry.detect(&doc, &url).extract_toc(&doc);
+                            if toc_len(&page_toc) > toc_len(&toc) {
+                                toc = resolve_toc(
+                                    page_toc,
+                                    &url,
+                                    &self.config.significant_query_params,
+                                );
                             }
+                            self.registry.extract(&doc, &url).adapter
+                        };
+
+                        // Enqueue child links if within depth, capped per page and
+                        // by the queue size
+                        if depth < max_depth {
+                            links_dropped +=
+                                self.enqueue_links(&mut queue, &page.meta.url, &page.links, depth);
+                            peak_queue_len = peak_queue_len.max(queue.len());
+                        }
+
+                        // Drop pages matching a configured selector (links are
+                        // still followed above so reachable pages aren't lost)
+                        if let Some(selector) = self.drop_selectors.matching(&page.html) {
+                            debug!(
+                                url = %page.meta.url,
+                                selector,
+                                "page matches drop selector, skipping"
+                            );
+                            pages_skipped += 1;
+                            continue;
                         }
 
                         // Store in database
                         if let Err(e) = storage.upsert_page(&page.meta).await {
                             warn!(url = %page.meta.url, error = %e, "failed to store page");
                             errors.push((page.meta.url.clone(), e.to_string()));
+                            *error_kinds.entry(error_kind(&e)).or_default() += 1;
                         }
 
                         // Store links
-                        for link in &page.links {
-                            let _ = storage
-                                .insert_link(&page.meta.id, link, None)
-                                .await;
+                        let links: Vec<(String, Option<String>)> =
+                            page.links.iter().map(|l| (l.clone(), None)).collect();
+                        if let Err(e) = storage.insert_links_batch(&page.meta.id, &links).await {
+                            warn!(url = %page.meta.url, error = %e, "failed to store links");
                         }
 
+                        *adapters.entry(adapter).or_default() += 1;
+                        *pages_by_depth.entry(depth).or_default() += 1;
                         fetched_pages.push(page);
                     }
-                    Ok(Err(e)) => {
-                        errors.push(("unknown".into(), e.to_string()));
-                        pages_skipped += 1;
-                    }
+                    Ok(None) => pages_skipped += 1,
                     Err(e) => {
-                        errors.push(("task".into(), e.to_string()));
+                        host.2 += 1;
+                        errors.push((url.to_string(), e.to_string()));
+                        *error_kinds.entry(error_kind(&e)).or_default() += 1;
                         pages_skipped += 1;
                     }
                 }
@@ -230,6 +605,13 @@ impl Crawler {
         }
 
         let duration = start_time.elapsed();
+        if links_dropped > 0 {
+            warn!(
+                links_dropped,
+                max_queue_size = self.config.max_queue_size,
+                "crawl queue was full, some links were not followed"
+            );
+        }
 
         // Update crawl job with stats
         let stats = serde_json::json!({
@@ -237,17 +619,43 @@ impl Crawler {
             "pages_fetched": fetched_pages.len(),
             "pages_skipped": pages_skipped,
             "errors": errors.len(),
+            "pages_by_depth": pages_by_depth,
         });
         let _ = storage
             .update_crawl_job(&crawl_job_id, &stats.to_string())
             .await;
 
+        let primary_adapter = adapters
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| "generic".to_string());
         let result = CrawlResult {
             pages_fetched: fetched_pages.len(),
             pages_skipped,
             errors,
             duration,
             primary_adapter,
+            error_kinds,
+            bytes_downloaded,
+            avg_latency: latency_total.checked_div(fetches).unwrap_or_default(),
+            adapters,
+            peak_queue_len,
+            links_dropped,
+            truncated,
+            toc,
+            pages_by_depth,
+            hosts: host_stats
+                .into_iter()
+                .map(|(host, (latencies, bytes, errors))| {
+                    let metrics = HostMetrics {
+                        bytes,
+                        errors,
+                        ..HostMetrics::from_latencies(latencies)
+                    };
+                    (host, metrics)
+                })
+                .collect(),
         };
 
         info!(
@@ -256,11 +664,113 @@ impl Crawler {
             errors = result.errors.len(),
             duration_ms = result.duration.as_millis(),
             adapter = %result.primary_adapter,
+            bytes = result.bytes_downloaded,
+            avg_latency_ms = result.avg_latency.as_millis(),
             "crawl completed"
         );
 
         Ok((result, fetched_pages))
     }
+
+    /// List the URLs a crawl from `start_url` would consider, each with the
+    /// scope rule that admits or rejects it, without storing anything.
+    ///
+    /// With `seeds` (e.g. a sitemap's URLs) only those are classified and
+    /// nothing is fetched. Otherwise `robots.txt` is read (unless
+    /// `respect_robots_txt` is off) and links are discovered by fetching the
+    /// in-scope pages above the depth limit one at a time; out-of-scope URLs
+    /// are listed but never fetched.
+    #[instrument(skip_all, fields(start_url = %start_url))]
+    pub async fn preview_scope(
+        &self,
+        start_url: &Url,
+        seeds: Option<&[Url]>,
+    ) -> Result<Vec<ScopedUrl>> {
+        let mut scope = CrawlScope::new(start_url, &self.config);
+        if self.config.respect_robots_txt && seeds.is_none() {
+            if let Some((_, robots_txt)) = robots::fetch_robots_txt(&self.client, start_url).await {
+                let robots = RobotsRules::parse(&robots_txt, self.user_agent());
+                scope = scope.with_robots(start_url, robots);
+            }
+        }
+        let throttle = Arc::new(HostThrottle::new(&self.config));
+        let mut visited = HashSet::new();
+        let mut urls = Vec::new();
+        let mut queue: VecDeque<(Url, u32)> = match seeds {
+            Some(seeds) => seeds.iter().map(|url| (url.clone(), 0)).collect(),
+            None => VecDeque::from([(start_url.clone(), 0)]),
+        };
+
+        while let Some((url, depth)) = queue.pop_front() {
+            if !visited.insert(visit_key(&url, &self.config)) {
+                continue;
+            }
+            let rule = scope.check(&url);
+            let follow = seeds.is_none()
+                && rule.in_scope()
+                && depth < self.config.depth
+                && (self.allow_localhost || !is_ssrf_target(&url));
+            urls.push(ScopedUrl {
+                url: url.to_string(),
+                depth,
+                rule,
+            });
+            if !follow {
+                continue;
+            }
+
+            let host = url.host_str().unwrap_or_default().to_string();
+            let mut slot = throttle.acquire(&host).await;
+            let opts = FetchOptions::from(&self.config);
+            match fetch_page(&self.client, &url, depth, "", opts, &mut slot).await {
+                Ok(Some((page, _))) => {
+                    for link in &page.links {
+                        if let Ok(link_url) = Url::parse(link) {
+                            queue.push_back((link_url, depth + 1));
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(%url, error = %e, "could not fetch page for links"),
+            }
+        }
+
+        info!(
+            urls = urls.len(),
+            in_scope = urls.iter().filter(|u| u.rule.in_scope()).count(),
+            "scope preview complete"
+        );
+        Ok(urls)
+    }
+}
+
+/// Short name for the kind of a crawl error, for [`CrawlResult::error_kinds`].
+fn error_kind(e: &ContextBuilderError) -> String {
+    match e {
+        ContextBuilderError::RedirectLimit { .. } => "redirect_limit".into(),
+        ContextBuilderError::Unauthorized { .. } => "http_401".into(),
+        ContextBuilderError::Forbidden { .. } => "http_403".into(),
+        // `fetch_page` reports bad statuses as "<url>: HTTP <status>"
+        ContextBuilderError::Network(message) => match message.rsplit_once(": HTTP ") {
+            Some((_, status)) => {
+                format!("http_{}", status.split_whitespace().next().unwrap_or_default())
+            }
+            None => "network".into(),
+        },
+        ContextBuilderError::Parse { .. } => "parse".into(),
+        ContextBuilderError::Storage(_) => "storage".into(),
+        _ => "other".into(),
+    }
+}
+
+/// Key of `url`'s host in [`CrawlResult::hosts`]: the host, with the port
+/// when it is not the scheme's default.
+fn host_key(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -271,68 +781,159 @@ impl Crawler {
 struct CrawlScope {
     /// Base path prefix that URLs must match.
     base_path: String,
-    /// Base host that URLs must match.
+    /// Base host that URLs must match (without `www.` under `strip_www`).
     base_host: String,
-    /// Include patterns (if non-empty, URL must match at least one).
-    include_patterns: Vec<regex::Regex>,
-    /// Exclude patterns (if URL matches any, it's excluded).
-    exclude_patterns: Vec<regex::Regex>,
+    /// Ignore a leading `www.` when comparing hosts.
+    strip_www: bool,
+    /// Include patterns (if non-empty, URL must match at least one), with
+    /// the globs they came from.
+    include_patterns: Vec<(String, regex::Regex)>,
+    /// Exclude patterns (if URL matches any, it's excluded), with the globs
+    /// they came from.
+    exclude_patterns: Vec<(String, regex::Regex)>,
+    /// `robots.txt` rules of the start URL's origin, and that origin.
+    robots: Option<(Origin, RobotsRules)>,
+}
+
+/// The scope rule that admits or rejects a URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeRule {
+    /// Not an `http(s)` URL.
+    Scheme,
+    /// On another host than the start URL.
+    Host,
+    /// Matched this exclude pattern.
+    Exclude(String),
+    /// Matched this include pattern.
+    Include(String),
+    /// Include patterns are configured and none matched.
+    NoInclude,
+    /// On the start URL's host, with no include patterns configured.
+    SameHost,
+    /// Outside the start URL's path.
+    OutsidePath,
+    /// Disallowed by the start URL's `robots.txt`.
+    Robots,
+}
+
+impl ScopeRule {
+    /// Whether the rule admits the URL into the crawl.
+    pub fn in_scope(&self) -> bool {
+        matches!(self, Self::Include(_) | Self::SameHost)
+    }
+}
+
+impl std::fmt::Display for ScopeRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Scheme => f.write_str("not http(s)"),
+            Self::Host => f.write_str("other host"),
+            Self::Exclude(pattern) => write!(f, "exclude {pattern}"),
+            Self::Include(pattern) => write!(f, "include {pattern}"),
+            Self::NoInclude => f.write_str("no include pattern matched"),
+            Self::SameHost => f.write_str("same host"),
+            Self::OutsidePath => f.write_str("outside start path"),
+            Self::Robots => f.write_str("disallowed by robots.txt"),
+        }
+    }
+}
+
+/// A URL found by [`Crawler::preview_scope`], with the rule deciding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopedUrl {
+    /// The URL as discovered.
+    pub url: String,
+    /// Link depth from the start URL (0 for the start URL and sitemap URLs).
+    pub depth: u32,
+    /// Why the URL is in or out of scope.
+    pub rule: ScopeRule,
 }
 
 impl CrawlScope {
     fn new(start_url: &Url, config: &CrawlConfig) -> Self {
         let base_path = start_url.path().to_string();
-        let base_host = start_url.host_str().unwrap_or("").to_string();
-
-        let include_patterns = config
-            .include_patterns
-            .iter()
-            .filter_map(|p| glob_to_regex(p))
-            .collect();
-
-        let exclude_patterns = config
-            .exclude_patterns
-            .iter()
-            .filter_map(|p| glob_to_regex(p))
-            .collect();
+        let base_host =
+            canonical_host(start_url.host_str().unwrap_or(""), config.strip_www).to_string();
+
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| Some((p.clone(), glob_to_regex(p)?)))
+                .collect()
+        };
+        let include_patterns = compile(&config.include_patterns);
+        let exclude_patterns = compile(&config.exclude_patterns);
 
         Self {
             base_path,
             base_host,
+            strip_www: config.strip_www,
             include_patterns,
             exclude_patterns,
+            robots: None,
         }
     }
 
+    /// Also reject URLs on `start_url`'s origin that `robots` disallows.
+    fn with_robots(mut self, start_url: &Url, robots: RobotsRules) -> Self {
+        self.robots = Some((start_url.origin(), robots));
+        self
+    }
+
+    #[cfg(test)]
     fn in_scope(&self, url: &Url) -> bool {
+        self.check(url).in_scope()
+    }
+
+    /// The rule deciding whether `url` is in scope.
+    fn check(&self, url: &Url) -> ScopeRule {
+        let rule = self.check_config(url);
+        match &self.robots {
+            Some((origin, robots))
+                if rule.in_scope() && url.origin() == *origin && !robots.is_allowed(url) =>
+            {
+                ScopeRule::Robots
+            }
+            _ => rule,
+        }
+    }
+
+    /// The rule from the host, path, and include/exclude settings alone.
+    fn check_config(&self, url: &Url) -> ScopeRule {
         // Must be http/https
         if url.scheme() != "http" && url.scheme() != "https" {
-            return false;
+            return ScopeRule::Scheme;
         }
 
         // Must match base host
-        if url.host_str().unwrap_or("") != self.base_host {
-            return false;
+        if canonical_host(url.host_str().unwrap_or(""), self.strip_www) != self.base_host {
+            return ScopeRule::Host;
         }
 
         let path = url.path();
 
         // Check exclude patterns
-        for pattern in &self.exclude_patterns {
-            if pattern.is_match(path) {
-                return false;
-            }
+        if let Some((glob, _)) = self.exclude_patterns.iter().find(|(_, p)| p.is_match(path)) {
+            return ScopeRule::Exclude(glob.clone());
         }
 
         // Check include patterns (if any configured, must match at least one)
         if !self.include_patterns.is_empty() {
-            return self.include_patterns.iter().any(|p| p.is_match(path));
+            return match self.include_patterns.iter().find(|(_, p)| p.is_match(path)) {
+                Some((glob, _)) => ScopeRule::Include(glob.clone()),
+                None => ScopeRule::NoInclude,
+            };
         }
 
         // Default: must share path prefix with start URL
-        path.starts_with(&self.base_path)
+        if path.starts_with(&self.base_path)
             || self.base_path.starts_with(path)
             || path.starts_with("/")
+        {
+            ScopeRule::SameHost
+        } else {
+            ScopeRule::OutsidePath
+        }
     }
 }
 
@@ -346,106 +947,118 @@ fn glob_to_regex(pattern: &str) -> Option<regex::Regex> {
 }
 
 // ---------------------------------------------------------------------------
-// SSRF protection
+// Page fetching
 // ---------------------------------------------------------------------------
 
-/// Check if a URL targets a potentially dangerous resource.
-fn is_ssrf_target(url: &Url) -> bool {
-    // Block non-HTTP schemes
-    match url.scheme() {
-        "http" | "https" => {}
-        _ => return true,
-    }
-
-    // Block private/loopback IPs
-    if let Some(host) = url.host_str() {
-        if let Ok(ip) = host.parse::<IpAddr>() {
-            return is_private_ip(&ip);
-        }
-        // Block known local hostnames
-        if host == "localhost"
-            || host == "127.0.0.1"
-            || host == "[::1]"
-            || host.ends_with(".local")
-            || host.ends_with(".internal")
-        {
-            return true;
-        }
-    }
-
-    false
+/// Per-page fetch settings copied out of the crawl config.
+#[derive(Debug, Clone)]
+struct FetchOptions {
+    hash_basis: HashBasis,
+    ingest_pdfs: bool,
+    max_redirects: usize,
+    max_title_len: Option<usize>,
+    significant_query_params: Vec<String>,
+    query_links_as_anchors: bool,
 }
 
-/// Check if an IP is in a private/reserved range.
-fn is_private_ip(ip: &IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(v4) => {
-            v4.is_loopback()
-                || v4.is_private()
-                || v4.is_link_local()
-                || v4.is_broadcast()
-                || v4.is_unspecified()
-                // 100.64.0.0/10 (Carrier-grade NAT)
-                || (v4.octets()[0] == 100 && (v4.octets()[1] & 0xC0) == 64)
-                // 192.0.0.0/24
-                || (v4.octets()[0] == 192 && v4.octets()[1] == 0 && v4.octets()[2] == 0)
+impl From<&CrawlConfig> for FetchOptions {
+    fn from(config: &CrawlConfig) -> Self {
+        Self {
+            hash_basis: config.hash_basis,
+            ingest_pdfs: config.ingest_pdfs,
+            max_redirects: config.max_redirects,
+            max_title_len: config.max_title_len,
+            significant_query_params: config.significant_query_params.clone(),
+            query_links_as_anchors: config.query_links_as_anchors,
         }
-        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified(),
     }
 }
 
-// ---------------------------------------------------------------------------
-// Page fetching
-// ---------------------------------------------------------------------------
-
 /// Fetch a single page and extract its content.
+///
+/// PDFs are converted to an HTML page when `ingest_pdfs` is set and skipped
+/// (`Ok(None)`) otherwise.
 async fn fetch_page(
     client: &Client,
     url: &Url,
     depth: u32,
     kb_id: &str,
-) -> Result<(FetchedPage, u32)> {
+    opts: FetchOptions,
+    slot: &mut ThrottlePermit,
+) -> Result<Option<(FetchedPage, u32)>> {
     debug!(%url, depth, "fetching page");
 
-    let response = client
-        .get(url.as_str())
-        .send()
-        .await
-        .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
+    let response = client.get(url.as_str()).send().await.map_err(|e| {
+        if e.is_redirect() {
+            // The host answered; a long chain or loop is not a reason to back off
+            return ContextBuilderError::redirect_limit(url, opts.max_redirects);
+        }
+        slot.record(None);
+        ContextBuilderError::Network(format!("{url}: {e}"))
+    })?;
 
     let status = response.status();
     let status_code = status.as_u16();
+    slot.record(Some(status_code));
 
     if !status.is_success() {
-        return Err(ContextBuilderError::Network(format!(
-            "{url}: HTTP {status}"
-        )));
+        return Err(ContextBuilderError::http_status(url, status_code, status));
     }
 
-    let body = response
-        .text()
-        .await
-        .map_err(|e| ContextBuilderError::Network(format!("{url}: body read failed: {e}")))?;
+    let is_pdf = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(pdf::is_pdf_content_type);
+
+    let body = if is_pdf {
+        if !opts.ingest_pdfs {
+            debug!(%url, "skipping PDF (ingest_pdfs is off)");
+            return Ok(None);
+        }
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ContextBuilderError::Network(format!("{url}: body read failed: {e}")))?;
+        let text = tokio::task::spawn_blocking(move || pdf::extract_text(&bytes))
+            .await
+            .map_err(|e| ContextBuilderError::parse(format!("{url}: {e}")))?
+            .map_err(|e| ContextBuilderError::parse(format!("{url}: {e}")))?;
+        let file_name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or("document.pdf");
+        pdf::text_to_html(&text, file_name)
+    } else {
+        charset::response_text(response)
+            .await
+            .map_err(|e| ContextBuilderError::Network(format!("{url}: body read failed: {e}")))?
+    };
 
     // Parse HTML
     let doc = Html::parse_document(&body);
 
     // Extract links
-    let links = extract_links(&doc, url);
+    let links = extract_links(&doc, url, &opts);
 
     // Compute content hash
-    let content_hash = compute_hash(&body);
+    let content_hash = content_hash(&body, url, opts.hash_basis);
 
     // Generate a slug-based path from the URL
-    let page_path = url_to_path(url);
+    let page_path = page_path(url, &opts.significant_query_params);
 
     // Extract title from H1
-    let title = {
+    let full_title = {
         let h1_sel = Selector::parse("h1").unwrap();
         doc.select(&h1_sel)
             .next()
             .map(|el| el.text().collect::<String>().trim().to_string())
     };
+    let title = match (&full_title, opts.max_title_len) {
+        (Some(t), Some(max)) => Some(truncate_title(t, max)),
+        _ => full_title.clone(),
+    };
+    let description = meta_description(&doc);
 
     let meta = PageMeta {
         id: Uuid::now_v7().to_string(),
@@ -457,18 +1070,18 @@ async fn fetch_page(
         fetched_at: Utc::now(),
         status_code: Some(status_code),
         content_len: Some(body.len()),
+        depth: Some(depth),
+        description,
     };
 
     // Create an ExtractedContent placeholder (the actual adapter extraction
-    // happens during markdown conversion)
+    // happens during markdown conversion). It keeps the untruncated title.
     let content = ExtractedContent {
         html: body.clone(),
-        meta: crate::adapters::PageMeta {
-            title: meta.title.clone(),
-        },
+        meta: crate::adapters::PageMeta { title: full_title },
     };
 
-    Ok((
+    Ok(Some((
         FetchedPage {
             meta,
             content,
@@ -476,17 +1089,19 @@ async fn fetch_page(
             links,
         },
         depth,
-    ))
+    )))
 }
 
 /// Extract all links from a document, resolved against the base URL.
-fn extract_links(doc: &Html, base_url: &Url) -> Vec<String> {
+fn extract_links(doc: &Html, base_url: &Url, opts: &FetchOptions) -> Vec<String> {
     let link_sel = Selector::parse("a[href]").unwrap();
     let mut links = Vec::new();
+    let mut page = base_url.clone();
+    page.set_fragment(None);
 
     for el in doc.select(&link_sel) {
         if let Some(href) = el.value().attr("href") {
-            // Skip anchors, javascript:, mailto:
+            // Skip anchors (including `#/route` hash routes), javascript:, mailto:
             if href.starts_with('#')
                 || href.starts_with("javascript:")
                 || href.starts_with("mailto:")
@@ -496,9 +1111,19 @@ fn extract_links(doc: &Html, base_url: &Url) -> Vec<String> {
 
             // Resolve relative URLs
             if let Ok(resolved) = base_url.join(href) {
-                // Strip fragment
+                // Strip fragment; what is left of a hash route into this page
+                // is the page itself
                 let mut resolved = resolved;
+                let had_fragment = resolved.fragment().is_some();
                 resolved.set_fragment(None);
+                if had_fragment && resolved == page {
+                    continue;
+                }
+                if opts.query_links_as_anchors
+                    && is_query_delta(&resolved, &page, &opts.significant_query_params)
+                {
+                    continue;
+                }
                 links.push(resolved.to_string());
             }
         }
@@ -507,10 +1132,69 @@ fn extract_links(doc: &Html, base_url: &Url) -> Vec<String> {
     links
 }
 
+/// Whether `link` is `page` with only its query string changed, and no
+/// `significant` parameter among the changes: the way client-side routers
+/// encode in-page state (`?tab=x`, `?id=section`).
+fn is_query_delta(link: &Url, page: &Url, significant: &[String]) -> bool {
+    link.scheme() == page.scheme()
+        && link.host_str() == page.host_str()
+        && link.port_or_known_default() == page.port_or_known_default()
+        && link.path() == page.path()
+        && link.query() != page.query()
+        && significant_query(link, significant) == significant_query(page, significant)
+}
+
+/// The page's author-written summary: `<meta name="description">`, else
+/// `<meta property="og:description">`, whitespace-collapsed. Empty values are
+/// ignored.
+pub fn meta_description(doc: &Html) -> Option<String> {
+    ["meta[name=description]", "meta[property=\"og:description\"]"]
+        .iter()
+        .filter_map(|selector| {
+            let selector = Selector::parse(selector).unwrap();
+            let content = doc.select(&selector).next()?.value().attr("content")?;
+            Some(content.split_whitespace().collect::<Vec<_>>().join(" "))
+        })
+        .find(|description| !description.is_empty())
+}
+
+/// Apply the user agent, default headers, and proxy of `auth` to `builder`.
+pub fn with_auth(builder: ClientBuilder, auth: &HttpAuth) -> Result<ClientBuilder> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in auth.header_pairs() {
+        let invalid = |e: &dyn std::fmt::Display| {
+            ContextBuilderError::config(format!("invalid header '{name}': {e}"))
+        };
+        let value = HeaderValue::from_str(&value).map_err(|e| invalid(&e))?;
+        headers.insert(HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?, value);
+    }
+    let mut builder = builder
+        .user_agent(auth.user_agent.as_deref().unwrap_or(USER_AGENT))
+        .default_headers(headers);
+    if let Some(proxy) = &auth.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| ContextBuilderError::config(format!("invalid proxy '{proxy}': {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder)
+}
+
 /// Normalize a URL for deduplication (strip fragment, trailing slash, lowercase host).
-fn normalize_url(url: &Url) -> String {
+///
+/// Query parameters are dropped unless listed in `significant`; those that
+/// are kept are sorted, so `?b=2&a=1` and `?a=1&b=2` are the same page.
+fn normalize_url(url: &Url, significant: &[String]) -> String {
     let mut normalized = url.clone();
     normalized.set_fragment(None);
+    let query = significant_query(url, significant);
+    if query.is_empty() {
+        normalized.set_query(None);
+    } else {
+        normalized
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(query.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    }
     let mut s = normalized.to_string();
     // Remove trailing slash for consistency (except root path)
     if s.ends_with('/') && s.matches('/').count() > 3 {
@@ -519,7 +1203,83 @@ fn normalize_url(url: &Url) -> String {
     s
 }
 
+/// The `visited` key for `url`: [`normalize_url`] after folding `http://`
+/// into `https://` (`prefer_https`) and dropping a leading `www.`
+/// (`strip_www`), so every variant of a page is crawled once. Only the key
+/// is rewritten; the page is still fetched at the URL it was linked as.
+fn visit_key(url: &Url, config: &CrawlConfig) -> String {
+    let mut canonical = url.clone();
+    if config.prefer_https && canonical.scheme() == "http" {
+        let _ = canonical.set_scheme("https");
+    }
+    if let Some(host) = url.host_str() {
+        let host = canonical_host(host, config.strip_www);
+        if host != url.host_str().unwrap_or_default() {
+            let _ = canonical.set_host(Some(host));
+        }
+    }
+    normalize_url(&canonical, &config.significant_query_params)
+}
+
+/// `host` without a leading `www.` when `strip_www` is set.
+fn canonical_host(host: &str, strip_www: bool) -> &str {
+    if strip_www {
+        host.strip_prefix("www.").unwrap_or(host)
+    } else {
+        host
+    }
+}
+
+/// The query parameters of `url` named in `significant`, sorted.
+fn significant_query(url: &Url, significant: &[String]) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| significant.iter().any(|s| s == name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// [`url_to_path`], disambiguated by the `significant` query parameters the
+/// URL carries: `/guide?version=v2` becomes `guide--version-v2`.
+pub fn page_path(url: &Url, significant: &[String]) -> String {
+    let mut path = url_to_path(url);
+    for (name, value) in significant_query(url, significant) {
+        path.push_str(&sanitize_filename(&format!("--{name}-{value}").replace('/', "-")));
+    }
+    path
+}
+
+/// Number of entries in `toc`, at every level.
+fn toc_len(toc: &[TocEntry]) -> usize {
+    toc.iter().map(|entry| 1 + toc_len(&entry.children)).sum()
+}
+
+/// Rewrite an adapter TOC found on `page_url` so each link's path is the
+/// [`page_path`] of its absolute URL, as for fetched pages.
+fn resolve_toc(toc: Vec<TocEntry>, page_url: &Url, significant: &[String]) -> Vec<TocEntry> {
+    toc.into_iter()
+        .map(|mut entry| {
+            entry.children = resolve_toc(entry.children, page_url, significant);
+            match entry.source_url.as_deref().map(|href| page_url.join(href)) {
+                Some(Ok(mut url)) => {
+                    url.set_fragment(None);
+                    entry.path = page_path(&url, significant);
+                    entry.source_url = Some(url.to_string());
+                }
+                Some(Err(_)) => {}
+                None => entry.path = group_path(&entry),
+            }
+            entry
+        })
+        .collect()
+}
+
 /// Convert a URL path to a filesystem-safe path.
+///
+/// Each segment goes through [`sanitize_filename`], so the path can be
+/// written on Windows as well.
 pub fn url_to_path(url: &Url) -> String {
     let path = url.path();
     let cleaned = path
@@ -527,12 +1287,42 @@ pub fn url_to_path(url: &Url) -> String {
         .trim_end_matches('/')
         .trim_end_matches(".html")
         .trim_end_matches(".htm")
-        .trim_end_matches(".md");
+        .trim_end_matches(".md")
+        .trim_end_matches(".pdf");
 
     if cleaned.is_empty() {
         "index".to_string()
     } else {
-        cleaned.to_string()
+        cleaned.split('/').map(sanitize_filename).collect::<Vec<_>>().join("/")
+    }
+}
+
+/// Compute the change-detection hash of a fetched page.
+///
+/// Non-raw bases hash only what ends up in the KB, so markup churn such as
+/// rotating CSRF tokens or script nonces does not mark a page as changed.
+/// Falls back to the raw HTML if Markdown conversion fails.
+pub fn content_hash(html: &str, url: &Url, basis: HashBasis) -> String {
+    match basis {
+        HashBasis::RawHtml => compute_hash(html),
+        HashBasis::ExtractedHtml => {
+            let doc = Html::parse_document(html);
+            let extraction = AdapterRegistry::new().extract(&doc, url);
+            compute_hash(&extraction.content.html)
+        }
+        HashBasis::Markdown => {
+            let opts = contextbuilder_markdown::ConvertOptions {
+                source_url: url.to_string(),
+                ..Default::default()
+            };
+            match contextbuilder_markdown::convert(html, &opts) {
+                Ok(result) => compute_hash(&result.markdown),
+                Err(e) => {
+                    debug!(%url, error = %e, "markdown hash failed, hashing raw HTML");
+                    compute_hash(html)
+                }
+            }
+        }
     }
 }
 
@@ -546,15 +1336,45 @@ fn compute_hash(content: &str) -> String {
 #[cfg(test)]
 mod crawler_tests {
     use super::*;
+    use contextbuilder_shared::CrawlMode;
+    use contextbuilder_storage::MemoryStorage;
+
+    /// An in-memory store holding one KB sourced from `url`, and its id.
+    async fn test_storage(url: &str) -> (MemoryStorage, String) {
+        let storage = MemoryStorage::new();
+        let kb_id = Uuid::now_v7().to_string();
+        storage.insert_kb(&kb_id, "test-kb", url, None).await.unwrap();
+        (storage, kb_id)
+    }
 
     #[test]
     fn test_normalize_url() {
         let url = Url::parse("https://docs.example.com/guide/intro#section-1").unwrap();
-        let normalized = normalize_url(&url);
+        let normalized = normalize_url(&url, &[]);
         assert!(!normalized.contains('#'));
         assert!(normalized.starts_with("https://docs.example.com/guide/intro"));
     }
 
+    #[test]
+    fn test_significant_query_params_disambiguate_pages() {
+        let significant = vec!["version".to_string(), "page".to_string()];
+        let v1 = Url::parse("https://docs.example.com/guide?version=v1&utm_source=x").unwrap();
+        let v2 = Url::parse("https://docs.example.com/guide?utm_source=y&version=v2").unwrap();
+        let tracked = Url::parse("https://docs.example.com/guide?utm_source=z").unwrap();
+        let plain = Url::parse("https://docs.example.com/guide").unwrap();
+
+        assert_eq!(normalize_url(&v1, &significant), "https://docs.example.com/guide?version=v1");
+        assert_ne!(normalize_url(&v1, &significant), normalize_url(&v2, &significant));
+        assert_eq!(normalize_url(&tracked, &significant), normalize_url(&plain, &significant));
+        assert_eq!(normalize_url(&v1, &[]), normalize_url(&v2, &[]));
+
+        assert_eq!(page_path(&v1, &significant), "guide--version-v1");
+        assert_eq!(page_path(&v2, &significant), "guide--version-v2");
+        assert_eq!(page_path(&tracked, &significant), "guide");
+        let both = Url::parse("https://docs.example.com/?version=v2&page=2").unwrap();
+        assert_eq!(page_path(&both, &significant), "index--page-2--version-v2");
+    }
+
     #[test]
     fn test_url_to_path() {
         let url = Url::parse("https://docs.example.com/guide/getting-started.html").unwrap();
@@ -562,6 +1382,9 @@ mod crawler_tests {
 
         let root = Url::parse("https://docs.example.com/").unwrap();
         assert_eq!(url_to_path(&root), "index");
+
+        let windows = Url::parse("https://docs.example.com/api/std::io|Read/con").unwrap();
+        assert_eq!(url_to_path(&windows), "api/std%3A%3Aio%7CRead/con_");
     }
 
     #[test]
@@ -575,33 +1398,42 @@ mod crawler_tests {
     }
 
     #[test]
-    fn test_ssrf_protection_blocks_file() {
-        let url = Url::parse("file:///etc/passwd").unwrap();
-        assert!(is_ssrf_target(&url));
-    }
-
-    #[test]
-    fn test_ssrf_protection_blocks_private_ip() {
-        let url = Url::parse("http://192.168.1.1/admin").unwrap();
-        assert!(is_ssrf_target(&url));
-
-        let url = Url::parse("http://10.0.0.1/").unwrap();
-        assert!(is_ssrf_target(&url));
-
-        let url = Url::parse("http://127.0.0.1:8080/").unwrap();
-        assert!(is_ssrf_target(&url));
-    }
+    fn test_content_hash_markdown_basis_ignores_nonce() {
+        let page = |nonce: &str| {
+            format!(
+                "<html><head><meta name=\"csrf-token\" content=\"{nonce}\">\
+                 <script nonce=\"{nonce}\">track();</script></head>\
+                 <body><main><h1>Guide</h1><p>Install the tool.</p></main>\
+                 <!-- rendered {nonce} --></body></html>"
+            )
+        };
+        let url = Url::parse("https://docs.example.com/guide").unwrap();
+        let (a, b) = (page("a1b2c3"), page("z9y8x7"));
 
-    #[test]
-    fn test_ssrf_protection_allows_public() {
-        let url = Url::parse("https://docs.example.com/page").unwrap();
-        assert!(!is_ssrf_target(&url));
+        assert_ne!(
+            content_hash(&a, &url, HashBasis::RawHtml),
+            content_hash(&b, &url, HashBasis::RawHtml)
+        );
+        assert_eq!(
+            content_hash(&a, &url, HashBasis::Markdown),
+            content_hash(&b, &url, HashBasis::Markdown)
+        );
+        assert_eq!(
+            content_hash(&a, &url, HashBasis::ExtractedHtml),
+            content_hash(&b, &url, HashBasis::ExtractedHtml)
+        );
     }
 
     #[test]
-    fn test_ssrf_blocks_localhost() {
-        let url = Url::parse("http://localhost:3000/api").unwrap();
-        assert!(is_ssrf_target(&url));
+    fn test_content_hash_markdown_basis_detects_content_change() {
+        let url = Url::parse("https://docs.example.com/guide").unwrap();
+        let a = "<html><body><main><h1>Guide</h1><p>Version 1.</p></main></body></html>";
+        let b = "<html><body><main><h1>Guide</h1><p>Version 2.</p></main></body></html>";
+
+        assert_ne!(
+            content_hash(a, &url, HashBasis::Markdown),
+            content_hash(b, &url, HashBasis::Markdown)
+        );
     }
 
     #[test]
@@ -613,8 +1445,9 @@ mod crawler_tests {
             include_patterns: vec![],
             exclude_patterns: vec![],
             rate_limit_ms: 0,
-            mode: "crawl".into(),
+            mode: CrawlMode::Crawl,
             respect_robots_txt: false,
+            ..Default::default()
         };
         let scope = CrawlScope::new(&start, &config);
 
@@ -628,16 +1461,69 @@ mod crawler_tests {
     }
 
     #[test]
-    fn test_scope_excludes() {
-        let start = Url::parse("https://docs.example.com/").unwrap();
-        let config = CrawlConfig {
-            depth: 3,
-            concurrency: 4,
-            include_patterns: vec![],
-            exclude_patterns: vec!["/blog/**".into()],
-            rate_limit_ms: 0,
-            mode: "crawl".into(),
-            respect_robots_txt: false,
+    fn test_visit_key_collapses_scheme_and_www_variants() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let config = CrawlConfig::default();
+        assert!(config.prefer_https && !config.strip_www);
+        assert_eq!(
+            visit_key(&url("http://docs.example.com/a"), &config),
+            visit_key(&url("https://docs.example.com/a"), &config)
+        );
+        assert_ne!(
+            visit_key(&url("https://www.docs.example.com/a"), &config),
+            visit_key(&url("https://docs.example.com/a"), &config)
+        );
+
+        let strip = CrawlConfig {
+            strip_www: true,
+            ..CrawlConfig::default()
+        };
+        assert_eq!(
+            visit_key(&url("http://www.docs.example.com/a"), &strip),
+            visit_key(&url("https://docs.example.com/a"), &strip)
+        );
+
+        let exact = CrawlConfig {
+            prefer_https: false,
+            ..CrawlConfig::default()
+        };
+        assert_ne!(
+            visit_key(&url("http://docs.example.com/a"), &exact),
+            visit_key(&url("https://docs.example.com/a"), &exact)
+        );
+    }
+
+    #[test]
+    fn test_scope_strip_www_matches_both_hosts() {
+        let start = Url::parse("https://www.example.com/docs/").unwrap();
+        let apex = Url::parse("https://example.com/docs/intro").unwrap();
+        let www = Url::parse("https://www.example.com/docs/intro").unwrap();
+
+        let scope = CrawlScope::new(&start, &CrawlConfig::default());
+        assert!(scope.in_scope(&www));
+        assert!(!scope.in_scope(&apex));
+
+        let config = CrawlConfig {
+            strip_www: true,
+            ..CrawlConfig::default()
+        };
+        let scope = CrawlScope::new(&start, &config);
+        assert!(scope.in_scope(&www));
+        assert!(scope.in_scope(&apex));
+    }
+
+    #[test]
+    fn test_scope_excludes() {
+        let start = Url::parse("https://docs.example.com/").unwrap();
+        let config = CrawlConfig {
+            depth: 3,
+            concurrency: 4,
+            include_patterns: vec![],
+            exclude_patterns: vec!["/blog/**".into()],
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
         };
         let scope = CrawlScope::new(&start, &config);
 
@@ -648,13 +1534,96 @@ mod crawler_tests {
         assert!(scope.in_scope(&docs));
     }
 
+    #[test]
+    fn test_scope_check_names_the_deciding_rule() {
+        let start = Url::parse("https://docs.example.com/").unwrap();
+        let config = CrawlConfig {
+            include_patterns: vec!["/guide/**".into(), "/api/*".into()],
+            exclude_patterns: vec!["/api/internal".into()],
+            ..Default::default()
+        };
+        let scope = CrawlScope::new(&start, &config);
+        let check = |url: &str| scope.check(&Url::parse(url).unwrap());
+
+        assert_eq!(
+            check("https://docs.example.com/guide/a/b"),
+            ScopeRule::Include("/guide/**".into())
+        );
+        assert_eq!(
+            check("https://docs.example.com/api/internal"),
+            ScopeRule::Exclude("/api/internal".into())
+        );
+        assert_eq!(check("https://docs.example.com/blog/post"), ScopeRule::NoInclude);
+        assert_eq!(check("https://other.example.com/guide/a"), ScopeRule::Host);
+        assert_eq!(check("ftp://docs.example.com/guide/a"), ScopeRule::Scheme);
+        assert_eq!(ScopeRule::Exclude("/api/internal".into()).to_string(), "exclude /api/internal");
+    }
+
+    #[tokio::test]
+    async fn test_preview_scope_reports_rules_without_following_excluded_links() {
+        let server = wiremock::MockServer::start().await;
+        let page = |links: &[&str]| {
+            let links: String = links
+                .iter()
+                .map(|href| format!(r#"<a href="{href}">{href}</a>"#))
+                .collect();
+            format!("<html><body><main><h1>Page</h1>{links}</main></body></html>")
+        };
+        let routes = [
+            ("/", page(&["/guide", "/private/keys", "https://elsewhere.example/x"])),
+            ("/guide", page(&["/guide/deep"])),
+            ("/private/keys", page(&["/private/hidden"])),
+        ];
+        for (route, body) in routes {
+            wiremock::Mock::given(wiremock::matchers::path(route))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            exclude_patterns: vec!["/private/*".into()],
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start = Url::parse(&server.uri()).unwrap();
+        let urls = crawler.preview_scope(&start, None).await.unwrap();
+
+        let base = server.uri();
+        let found: Vec<(String, u32, ScopeRule)> =
+            urls.into_iter().map(|u| (u.url, u.depth, u.rule)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (format!("{base}/"), 0, ScopeRule::SameHost),
+                (format!("{base}/guide"), 1, ScopeRule::SameHost),
+                (format!("{base}/private/keys"), 1, ScopeRule::Exclude("/private/*".into())),
+                ("https://elsewhere.example/x".to_string(), 1, ScopeRule::Host),
+            ]
+        );
+
+        // Only robots.txt and the start page are fetched: /guide is at the
+        // depth limit and the excluded page is never requested
+        let requests = server.received_requests().await.unwrap();
+        let paths: Vec<&str> = requests.iter().map(|r| r.url.path()).collect();
+        assert_eq!(paths, ["/robots.txt", "/"]);
+
+        // Seeds are classified without any fetch
+        let seeds = [Url::parse(&format!("{base}/private/notes")).unwrap()];
+        let urls = crawler.preview_scope(&start, Some(&seeds)).await.unwrap();
+        assert_eq!(urls[0].rule, ScopeRule::Exclude("/private/*".into()));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
     #[test]
     fn test_extract_links() {
         let html = r##"<html><body><a href="/page2">Page 2</a><a href="https://external.com">External</a><a href="#section">Anchor</a><a href="relative/path">Relative</a></body></html>"##;
 
         let doc = Html::parse_document(html);
         let base = Url::parse("https://docs.example.com/page1").unwrap();
-        let links = extract_links(&doc, &base);
+        let links = extract_links(&doc, &base, &FetchOptions::from(&CrawlConfig::default()));
 
         assert!(links.contains(&"https://docs.example.com/page2".to_string()));
         assert!(links.contains(&"https://external.com/".to_string()));
@@ -663,6 +1632,64 @@ mod crawler_tests {
         assert!(!links.iter().any(|l| l.contains('#')));
     }
 
+    #[test]
+    fn test_meta_description_prefers_name_then_og() {
+        let description = |head: &str| {
+            meta_description(&Html::parse_document(&format!("<html><head>{head}</head></html>")))
+        };
+        let both = r#"<meta property="og:description" content="From OG">
+            <meta name="description" content="  Install the
+            CLI.  ">"#;
+        assert_eq!(description(both).as_deref(), Some("Install the CLI."));
+        let og_only = r#"<meta name="description" content=" ">
+            <meta property="og:description" content="From OG">"#;
+        assert_eq!(description(og_only).as_deref(), Some("From OG"));
+        assert_eq!(description("<title>No meta</title>"), None);
+    }
+
+    #[test]
+    fn test_extract_links_treats_client_side_routes_as_in_page() {
+        let html = r##"<html><body>
+            <a href="#/guide/intro">Hash route</a>
+            <a href="/docs/app?tab=w#!/guide/setup">Hash-bang route</a>
+            <a href="?tab=x">Tab</a>
+            <a href="app?id=section&amp;tab=y">Section</a>
+            <a href="?version=v2">Version</a>
+            <a href="/docs/other?tab=x">Other page</a>
+        </body></html>"##;
+        let doc = Html::parse_document(html);
+        let base = Url::parse("https://docs.example.com/docs/app?tab=w").unwrap();
+        let config = CrawlConfig {
+            significant_query_params: vec!["version".into()],
+            ..Default::default()
+        };
+
+        let links = extract_links(&doc, &base, &FetchOptions::from(&config));
+        assert_eq!(
+            links,
+            [
+                "https://docs.example.com/docs/app?version=v2",
+                "https://docs.example.com/docs/other?tab=x",
+            ]
+        );
+
+        // Turned off, query-only links are pages again; hash routes are not
+        let config = CrawlConfig {
+            query_links_as_anchors: false,
+            ..config
+        };
+        let links = extract_links(&doc, &base, &FetchOptions::from(&config));
+        assert_eq!(
+            links,
+            [
+                "https://docs.example.com/docs/app?tab=x",
+                "https://docs.example.com/docs/app?id=section&tab=y",
+                "https://docs.example.com/docs/app?version=v2",
+                "https://docs.example.com/docs/other?tab=x",
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_crawl_with_mock_server() {
         let server = wiremock::MockServer::start().await;
@@ -711,16 +1738,7 @@ mod crawler_tests {
             .mount(&server)
             .await;
 
-        // Set up storage
-        let tmp_dir = std::env::temp_dir().join(format!("cb-crawl-test-{}", Uuid::now_v7()));
-        let db_path = tmp_dir.join("test.db");
-        let storage = Storage::open(&db_path).await.unwrap();
-
-        let kb_id = Uuid::now_v7().to_string();
-        storage
-            .insert_kb(&kb_id, "test-kb", &server.uri(), None)
-            .await
-            .unwrap();
+        let (storage, kb_id) = test_storage(&server.uri()).await;
 
         let config = CrawlConfig {
             depth: 3,
@@ -728,8 +1746,9 @@ mod crawler_tests {
             include_patterns: vec![],
             exclude_patterns: vec![],
             rate_limit_ms: 0,
-            mode: "crawl".into(),
+            mode: CrawlMode::Crawl,
             respect_robots_txt: false,
+            ..Default::default()
         };
 
         let crawler = Crawler::new(config).unwrap().allow_localhost();
@@ -742,70 +1761,1513 @@ mod crawler_tests {
         // Verify pages stored in DB
         let db_pages = storage.list_pages_by_kb(&kb_id).await.unwrap();
         assert_eq!(db_pages.len(), 3);
-
-        // Cleanup
-        let _ = std::fs::remove_dir_all(&tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_crawl_respects_depth() {
+    async fn test_crawl_with_memory_storage() {
         let server = wiremock::MockServer::start().await;
 
-        let page1 = r#"<html><body><main>
-            <h1>Root</h1><a href="/page2">Page 2</a>
+        let root = r#"<html><body><main>
+            <h1>Root</h1><a href="/a">A</a><a href="/b">B</a><a href="/a">A again</a>
         </main></body></html>"#;
+        let leaf = "<html><body><main><h1>Leaf</h1><a href=\"/\">Home</a></main></body></html>";
 
-        let page2 = r#"<html><body><main>
-            <h1>Page 2</h1><a href="/page3">Page 3</a>
+        for (path, body) in [("/", root), ("/a", leaf), ("/b", leaf)] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 2,
+            concurrency: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+        assert_eq!(result.pages_fetched, 3);
+
+        let paths: Vec<String> = storage
+            .list_pages_by_kb(&kb_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(paths, vec!["a", "b", "index"]);
+
+        // The duplicate link from the root collapses to one edge
+        let links = storage.list_links_by_kb(&kb_id).await.unwrap();
+        let from_root: Vec<&str> = links
+            .iter()
+            .filter(|(path, _)| path == "index")
+            .map(|(_, to)| to.as_str())
+            .collect();
+        assert_eq!(from_root.len(), 2);
+        assert!(from_root.iter().all(|to| to.ends_with("/a") || to.ends_with("/b")));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_ingests_linked_pdfs() {
+        let server = wiremock::MockServer::start().await;
+
+        let root = r#"<html><body><main>
+            <h1>Docs</h1><a href="/spec.pdf">Protocol spec (PDF)</a>
         </main></body></html>"#;
+        let pdf = std::fs::read("../../../fixtures/pdf/spec.pdf").expect("read pdf fixture");
 
-        let page3 = r#"<html><body><main>
-            <h1>Page 3</h1><p>Deep page</p>
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/spec.pdf"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200).set_body_raw(pdf, "application/pdf"),
+            )
+            .mount(&server)
+            .await;
+
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let crawl = |ingest_pdfs| {
+            let config = CrawlConfig {
+                depth: 1,
+                rate_limit_ms: 0,
+                mode: CrawlMode::Crawl,
+                respect_robots_txt: false,
+                ingest_pdfs,
+                ..Default::default()
+            };
+            let crawler = Crawler::new(config).unwrap().allow_localhost();
+            let start_url = start_url.clone();
+            async move {
+                let storage = MemoryStorage::new();
+                crawler.crawl(&start_url, "kb", &storage).await.unwrap()
+            }
+        };
+
+        // Off by default: the PDF is skipped
+        let (result, pages) = crawl(false).await;
+        assert_eq!(result.pages_fetched, 1);
+        assert!(result.errors.is_empty());
+        assert!(pages.iter().all(|p| p.meta.path != "spec"));
+
+        let (_, pages) = crawl(true).await;
+        let spec = pages.iter().find(|p| p.meta.path == "spec").expect("pdf page");
+        assert_eq!(spec.meta.title.as_deref(), Some("Widget Protocol Specification"));
+
+        let opts = contextbuilder_markdown::ConvertOptions {
+            source_url: spec.meta.url.clone(),
+            ..Default::default()
+        };
+        let markdown = contextbuilder_markdown::convert(&spec.html, &opts).unwrap().markdown;
+        assert!(markdown.contains("# Widget Protocol Specification"));
+        assert!(markdown.contains("Clients send a HELLO frame before any request."));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_drops_soft_404s() {
+        let server = wiremock::MockServer::start().await;
+        let root = r#"<html><body><main><h1>Docs</h1><p>Welcome to the docs.</p>
+            <a href="/install">Install</a>
+            <a href="/old-page">Old</a>
+            <a href="/removed/guide">Removed</a>
         </main></body></html>"#;
+        let install = r#"<html><body><main><h1>Install</h1>
+            <p>Download the installer and run it.</p></main></body></html>"#;
+        // Every unknown path (including the probe) gets the same 200 body
+        let not_found = r#"<html><body><div id="app"><h1>Page not found</h1>
+            <p>Sorry, we could not find the page you were looking for.</p>
+            <a href="/">Back home</a></div></body></html>"#;
+
+        for (path, body) in [("/", root), ("/install", install)] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .with_priority(1)
+                .mount(&server)
+                .await;
+        }
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(not_found))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let crawl = |detect_soft_404| {
+            let config = CrawlConfig {
+                depth: 1,
+                rate_limit_ms: 0,
+                mode: CrawlMode::Crawl,
+                respect_robots_txt: false,
+                detect_soft_404,
+                ..Default::default()
+            };
+            let crawler = Crawler::new(config).unwrap().allow_localhost();
+            let start_url = start_url.clone();
+            async move {
+                let storage = MemoryStorage::new();
+                crawler.crawl(&start_url, "kb", &storage).await.unwrap()
+            }
+        };
+        let paths = |pages: &[FetchedPage]| {
+            let mut paths: Vec<String> = pages.iter().map(|p| p.meta.path.clone()).collect();
+            paths.sort();
+            paths
+        };
+
+        // Off by default: the "not found" bodies become pages
+        let (_, pages) = crawl(false).await;
+        assert_eq!(pages.len(), 4);
+
+        let (result, pages) = crawl(true).await;
+        assert_eq!(paths(&pages), vec!["index", "install"]);
+        assert_eq!(result.pages_fetched, 2);
+        assert!(result.pages_skipped >= 2);
+    }
 
+    #[tokio::test]
+    async fn test_crawl_reports_auth_failures_distinctly() {
+        let server = wiremock::MockServer::start().await;
+        let root = r#"<html><body><main><h1>Home</h1>
+            <a href="/a">A</a><a href="/b">B</a><a href="/c">C</a><a href="/d">D</a>
+        </main></body></html>"#;
         wiremock::Mock::given(wiremock::matchers::path("/"))
-            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page1))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/[ab]$"))
+            .respond_with(wiremock::ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/[cd]$"))
+            .respond_with(wiremock::ResponseTemplate::new(403))
             .mount(&server)
             .await;
 
-        wiremock::Mock::given(wiremock::matchers::path("/page2"))
-            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page2))
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            adaptive_throttle: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _) = crawler.crawl(&start_url, "kb", &MemoryStorage::new()).await.unwrap();
+
+        let kinds: Vec<(&str, usize)> =
+            result.error_kinds.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(kinds, vec![("http_401", 2), ("http_403", 2)]);
+        assert_eq!(result.auth_failures(), 4);
+        let hint = result.auth_hint().expect("auth hint");
+        assert!(hint.starts_with("4 of 5 pages answered HTTP 401/403"), "{hint}");
+        assert!(hint.contains("requires authentication"), "{hint}");
+    }
+
+    #[test]
+    fn auth_hint_needs_a_majority_of_refusals() {
+        assert_eq!(auth_hint(0, 10), None);
+        assert_eq!(auth_hint(4, 10), None);
+        assert!(auth_hint(5, 10).is_some());
+        assert!(auth_hint(1, 1).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_result_aggregates_mixed_outcomes() {
+        let server = wiremock::MockServer::start().await;
+        let root = r#"<html><body><main><h1>Home</h1>
+            <a href="/vp/a">A</a><a href="/vp/b">B</a><a href="/gone">Gone</a>
+            <a href="/missing">Missing</a><a href="/loop">Loop</a><a href="/">Home</a>
+        </main></body></html>"#;
+        let vitepress = r#"<html><body><div id="VPContent"><h1>VitePress</h1></div></body></html>"#;
+
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/vp/[ab]$"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(vitepress))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/(gone|missing)$"))
+            .respond_with(wiremock::ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/loop"))
+            .respond_with(wiremock::ResponseTemplate::new(302).insert_header("location", "/loop"))
             .mount(&server)
             .await;
 
-        wiremock::Mock::given(wiremock::matchers::path("/page3"))
-            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page3))
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            max_redirects: 2,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, "kb", &MemoryStorage::new()).await.unwrap();
+
+        assert_eq!(result.pages_fetched, 3);
+        assert_eq!(pages.len(), 3);
+        // Three failed fetches plus the revisited start page
+        assert_eq!(result.pages_skipped, 4);
+
+        let kinds: Vec<(&str, usize)> =
+            result.error_kinds.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(kinds, vec![("http_404", 2), ("redirect_limit", 1)]);
+        assert_eq!(result.error_kinds.values().sum::<usize>(), result.errors.len());
+        assert_eq!(result.auth_hint(), None);
+        assert!(result.errors.iter().any(|(url, _)| url.ends_with("/gone")));
+
+        let adapters: Vec<(&str, usize)> =
+            result.adapters.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(adapters, vec![("generic", 1), ("vitepress", 2)]);
+        assert_eq!(result.primary_adapter, "vitepress");
+
+        assert_eq!(result.bytes_downloaded, (root.len() + 2 * vitepress.len()) as u64);
+        assert!(result.avg_latency > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_redirect_limit() {
+        let server = wiremock::MockServer::start().await;
+        // `/start -> /moved -> /docs`: two redirects
+        for (from, to) in [("/start", "/moved"), ("/moved", "/docs")] {
+            wiremock::Mock::given(wiremock::matchers::path(from))
+                .respond_with(
+                    wiremock::ResponseTemplate::new(301).insert_header("location", to),
+                )
+                .mount(&server)
+                .await;
+        }
+        wiremock::Mock::given(wiremock::matchers::path("/docs"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Docs</h1><p>Hello.</p></main></body></html>",
+            ))
             .mount(&server)
             .await;
 
-        let tmp_dir = std::env::temp_dir().join(format!("cb-depth-test-{}", Uuid::now_v7()));
-        let db_path = tmp_dir.join("test.db");
-        let storage = Storage::open(&db_path).await.unwrap();
+        let start_url = Url::parse(&format!("{}/start", server.uri())).unwrap();
+        let crawl = |max_redirects| {
+            let config = CrawlConfig {
+                depth: 0,
+                rate_limit_ms: 0,
+                mode: CrawlMode::Crawl,
+                respect_robots_txt: false,
+                max_redirects,
+                ..Default::default()
+            };
+            let crawler = Crawler::new(config).unwrap().allow_localhost();
+            let start_url = start_url.clone();
+            async move {
+                let storage = MemoryStorage::new();
+                crawler.crawl(&start_url, "kb", &storage).await.unwrap()
+            }
+        };
 
-        let kb_id = Uuid::now_v7().to_string();
-        storage
-            .insert_kb(&kb_id, "test-kb", &server.uri(), None)
-            .await
-            .unwrap();
+        let (result, pages) = crawl(2).await;
+        assert_eq!(result.pages_fetched, 1);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(pages[0].html.contains("Hello."));
 
-        // Depth 1 = root + 1 level deep
+        let (result, pages) = crawl(1).await;
+        assert!(pages.is_empty());
+        let errors: Vec<&str> = result.errors.iter().map(|(_, e)| e.as_str()).collect();
+        assert_eq!(
+            errors,
+            vec![format!("redirect limit exceeded: {start_url} (more than 1 redirects)")]
+        );
+    }
+
+    /// Minimal HTTP/1.1 keep-alive server: `/` links to `/p0`..`/p{pages}`,
+    /// every other path is a leaf. Returns the base URL and a counter of
+    /// accepted TCP connections.
+    async fn keepalive_server(
+        pages: usize,
+    ) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    loop {
+                        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                            match sock.read(&mut chunk).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                            }
+                            continue;
+                        };
+                        let request = String::from_utf8_lossy(&buf[..end]).into_owned();
+                        buf.drain(..end + 4);
+                        let path = request.split_whitespace().nth(1).unwrap_or("/");
+                        let body = if path == "/" {
+                            let links: String = (0..pages)
+                                .map(|i| format!("<a href=\"/p{i}\">p{i}</a>"))
+                                .collect();
+                            format!("<html><body><main><h1>Root</h1>{links}</main></body></html>")
+                        } else {
+                            format!("<html><body><main><h1>{path}</h1></main></body></html>")
+                        };
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\n\r\n{body}",
+                            body.len()
+                        );
+                        if sock.write_all(response.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        (format!("http://{addr}"), connections)
+    }
+
+    #[tokio::test]
+    async fn test_crawl_reuses_connections_per_host() {
+        use std::sync::atomic::Ordering;
+
+        const PAGES: usize = 30;
+        let crawl = |pool_max_idle_per_host| async move {
+            let (base, connections) = keepalive_server(PAGES).await;
+            let config = CrawlConfig {
+                depth: 1,
+                concurrency: 4,
+                rate_limit_ms: 0,
+                mode: CrawlMode::Crawl,
+                respect_robots_txt: false,
+                pool_max_idle_per_host,
+                ..Default::default()
+            };
+            let crawler = Crawler::new(config).unwrap().allow_localhost();
+            let storage = MemoryStorage::new();
+            let (result, _) = crawler
+                .crawl(&Url::parse(&base).unwrap(), "kb", &storage)
+                .await
+                .unwrap();
+            assert_eq!(result.pages_fetched, PAGES + 1);
+            connections.load(Ordering::SeqCst)
+        };
+
+        // Pooled: connections are bounded by concurrency, not page count
+        let pooled = crawl(CrawlConfig::default().pool_max_idle_per_host).await;
+        assert!(pooled <= 4 + 1, "expected reuse, got {pooled} connections");
+
+        // Pooling disabled: every fetch sets up a new connection
+        let unpooled = crawl(0).await;
+        assert!(unpooled > PAGES, "expected a connection per fetch, got {unpooled}");
+    }
+
+    /// Tracks how many units of work run at once, and the most seen.
+    #[derive(Default)]
+    struct InFlight {
+        now: std::sync::atomic::AtomicUsize,
+        max: std::sync::atomic::AtomicUsize,
+    }
+
+    impl InFlight {
+        async fn run_for(&self, duration: Duration) {
+            use std::sync::atomic::Ordering;
+            let now = self.now.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(duration).await;
+            self.now.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crawl_shares_parallelism_budget_with_other_work() {
+        use std::sync::atomic::Ordering;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        const LEAVES: usize = 8;
+        let in_flight = Arc::new(InFlight::default());
+
+        // Every request counts as one unit of work while it is being served
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server_in_flight = in_flight.clone();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let in_flight = server_in_flight.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match sock.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf).into_owned();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    in_flight.run_for(Duration::from_millis(30)).await;
+                    let body = if path == "/" {
+                        (0..LEAVES).map(|i| format!("<a href=\"/p{i}\">p{i}</a>")).collect()
+                    } else {
+                        format!("<h1>{path}</h1>")
+                    };
+                    let body = format!("<html><body><main>{body}</main></body></html>");
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = sock.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        let budget = Arc::new(Semaphore::new(2));
         let config = CrawlConfig {
             depth: 1,
-            concurrency: 2,
-            include_patterns: vec![],
-            exclude_patterns: vec![],
+            concurrency: 4,
             rate_limit_ms: 0,
-            mode: "crawl".into(),
+            mode: CrawlMode::Crawl,
             respect_robots_txt: false,
+            ..Default::default()
         };
+        let crawler = Crawler::new(config).unwrap().allow_localhost().with_budget(budget.clone());
+        let storage = MemoryStorage::new();
+        let start_url = Url::parse(&base).unwrap();
+
+        // Another phase drawing from the same budget at the same time
+        let other_phase: Vec<_> = (0..LEAVES)
+            .map(|_| {
+                let budget = budget.clone();
+                let in_flight = in_flight.clone();
+                tokio::spawn(async move {
+                    let _permit = budget.acquire().await.unwrap();
+                    in_flight.run_for(Duration::from_millis(30)).await;
+                })
+            })
+            .collect();
+
+        let (result, _) = crawler.crawl(&start_url, "kb", &storage).await.unwrap();
+        for task in other_phase {
+            task.await.unwrap();
+        }
+        assert_eq!(result.pages_fetched, LEAVES + 1);
+        let max = in_flight.max.load(Ordering::SeqCst);
+        assert!(max <= 2, "{max} units of work ran at once");
+        assert_eq!(max, 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_backs_off_on_429_burst() {
+        const LEAVES: usize = 12;
+        let server = wiremock::MockServer::start().await;
+
+        let links: String = (0..LEAVES).map(|i| format!(r#"<a href="/p{i}">P{i}</a>"#)).collect();
+        let root = format!("<html><body><main><h1>Root</h1>{links}</main></body></html>");
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/p\d+$"))
+            .respond_with(wiremock::ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
 
-        let crawler = Crawler::new(config).unwrap().allow_localhost();
         let start_url = Url::parse(&server.uri()).unwrap();
-        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+        let host = start_url.host_str().unwrap().to_string();
+        let crawl = |adaptive_throttle| {
+            let config = CrawlConfig {
+                depth: 1,
+                concurrency: 4,
+                rate_limit_ms: 0,
+                mode: CrawlMode::Crawl,
+                respect_robots_txt: false,
+                adaptive_throttle,
+                max_backoff_ms: 100,
+                ..Default::default()
+            };
+            let throttle = Arc::new(HostThrottle::new(&config));
+            let crawler = Crawler::new(config)
+                .unwrap()
+                .allow_localhost()
+                .with_throttle(Arc::clone(&throttle));
+            let (start_url, host) = (start_url.clone(), host.clone());
+            async move {
+                let storage = MemoryStorage::new();
+                let (result, _) = crawler.crawl(&start_url, "kb", &storage).await.unwrap();
+                assert_eq!(result.errors.len(), LEAVES);
+                let state = throttle.state(&host).unwrap();
+                (state.limit, state.delay)
+            }
+        };
 
-        // Should fetch root (depth=0) and page2 (depth=1), but not page3 (depth=2)
-        assert_eq!(result.pages_fetched, 2);
+        // The 429s bring the host down to one request at a time, with the
+        // maximum delay
+        assert_eq!(crawl(true).await, (1, Duration::from_millis(100)));
+        assert_eq!(crawl(false).await, (4, Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_respects_depth() {
+        let server = wiremock::MockServer::start().await;
+
+        let page1 = r#"<html><body><main>
+            <h1>Root</h1><a href="/page2">Page 2</a>
+        </main></body></html>"#;
+
+        let page2 = r#"<html><body><main>
+            <h1>Page 2</h1><a href="/page3">Page 3</a>
+        </main></body></html>"#;
+
+        let page3 = r#"<html><body><main>
+            <h1>Page 3</h1><p>Deep page</p>
+        </main></body></html>"#;
+
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page1))
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::path("/page2"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page2))
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::path("/page3"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page3))
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        // Depth 1 = root + 1 level deep
+        let config = CrawlConfig {
+            depth: 1,
+            concurrency: 2,
+            include_patterns: vec![],
+            exclude_patterns: vec![],
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // Should fetch root (depth=0) and page2 (depth=1), but not page3 (depth=2)
+        assert_eq!(result.pages_fetched, 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_seeds_from_robots_sitemap() {
+        let server = wiremock::MockServer::start().await;
+        let page = |title: &str| {
+            wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "<html><body><main><h1>{title}</h1><p>No links here.</p></main></body></html>"
+            ))
+        };
+
+        let robots = format!(
+            "User-agent: *\nAllow: /\nSitemap: {}/maps/docs-index.xml\n",
+            server.uri()
+        );
+        wiremock::Mock::given(wiremock::matchers::path("/robots.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(robots))
+            .mount(&server)
+            .await;
+        let index = format!(
+            "<sitemapindex><sitemap><loc>{}/maps/docs.xml</loc></sitemap></sitemapindex>",
+            server.uri()
+        );
+        wiremock::Mock::given(wiremock::matchers::path("/maps/docs-index.xml"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(index))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let urlset = format!(
+            "<urlset><url><loc>{0}/guide</loc></url><url><loc>{0}/api</loc></url></urlset>",
+            server.uri()
+        );
+        wiremock::Mock::given(wiremock::matchers::path("/maps/docs.xml"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(urlset))
+            .expect(1)
+            .mount(&server)
+            .await;
+        for (path, title) in [("/", "Home"), ("/guide", "Guide"), ("/api", "API")] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(page(title))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // The home page links nowhere: the other pages come from the sitemap
+        let mut paths: Vec<&str> = pages.iter().map(|p| p.meta.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, vec!["api", "guide", "index"]);
+        assert_eq!(result.pages_by_depth, BTreeMap::from([(0, 3)]));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_robots_sitemap_seeds_stop_at_queue_cap() {
+        let server = wiremock::MockServer::start().await;
+        let base = server.uri();
+        let robots = format!("User-agent: *\nSitemap: {base}/sitemap.xml\n");
+        wiremock::Mock::given(wiremock::matchers::path("/robots.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(robots))
+            .mount(&server)
+            .await;
+        let urlset: String = ["a", "b", "c"]
+            .map(|path| format!("<url><loc>{base}/{path}</loc></url>"))
+            .concat();
+        wiremock::Mock::given(wiremock::matchers::path("/sitemap.xml"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string(format!("<urlset>{urlset}</urlset>")),
+            )
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/[abc]?$"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Page</h1></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&base).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            max_queue_size: 2,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&base).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // The start URL and one seed fill the queue; the other two are dropped
+        let paths: Vec<&str> = pages.iter().map(|p| p.meta.path.as_str()).collect();
+        assert_eq!(paths, ["index", "a"]);
+        assert_eq!(result.links_dropped, 2);
+        assert_eq!(result.peak_queue_len, 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_with_seeds_fetches_only_the_seeds() {
+        let server = wiremock::MockServer::start().await;
+        for path in ["/", "/docs/guide", "/docs/api", "/docs/linked", "/blog/post"] {
+            let html = format!(
+                r#"<html><body><main><h1>{path}</h1><a href="/docs/linked">More</a></main>"#
+            );
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(html))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            robots_sitemaps: false,
+            ..Default::default()
+        };
+        let seeds = ["/docs/guide", "/docs/api", "/blog/post"]
+            .map(|path| Url::parse(&format!("{}{path}", server.uri())).unwrap());
+        let crawler = Crawler::new(config).unwrap().allow_localhost().with_seeds(seeds.to_vec());
+        let start_url = Url::parse(&format!("{}/docs/", server.uri())).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // Neither the start URL nor the seeds' links are fetched
+        let mut paths: Vec<&str> = pages.iter().map(|p| p.meta.url.as_str()).collect();
+        paths.sort_unstable();
+        let expected = ["/blog/post", "/docs/api", "/docs/guide"]
+            .map(|path| format!("{}{path}", server.uri()));
+        assert_eq!(paths, expected);
+        assert_eq!(result.pages_by_depth, BTreeMap::from([(0, 3)]));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_stores_page_depth() {
+        let server = wiremock::MockServer::start().await;
+
+        // "/" links to a and b; a links to c; c links back to "/" and b
+        let pages = [
+            ("/", r#"<a href="/a">A</a><a href="/b">B</a>"#),
+            ("/a", r#"<a href="/c">C</a>"#),
+            ("/b", "<p>Leaf</p>"),
+            ("/c", r#"<a href="/">Home</a><a href="/b">B</a>"#),
+        ];
+        for (path, body) in pages {
+            let html = format!("<html><body><main><h1>{path}</h1>{body}</main></body></html>");
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(html))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 3,
+            concurrency: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        let stored: BTreeMap<String, Option<u32>> = storage
+            .list_pages_by_kb(&kb_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| (Url::parse(&p.url).unwrap().path().to_string(), p.depth))
+            .collect();
+        let expected: BTreeMap<String, Option<u32>> = [
+            ("/".to_string(), Some(0)),
+            ("/a".to_string(), Some(1)),
+            ("/b".to_string(), Some(1)),
+            ("/c".to_string(), Some(2)),
+        ]
+        .into();
+        assert_eq!(stored, expected);
+        assert_eq!(result.pages_by_depth, BTreeMap::from([(0, 1), (1, 2), (2, 1)]));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_respects_robots_txt() {
+        let server = wiremock::MockServer::start().await;
+        let robots = "User-agent: *\nDisallow: /internal/\nDisallow: /*.zip$\nCrawl-delay: 0.05\n";
+        wiremock::Mock::given(wiremock::matchers::path("/robots.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(robots))
+            .mount(&server)
+            .await;
+        let pages = [
+            (
+                "/",
+                r#"<a href="/guide">G</a><a href="/internal/admin">I</a><a href="/dl.zip">Z</a>"#,
+            ),
+            ("/guide", "<p>Guide</p>"),
+            ("/internal/admin", "<p>Secret</p>"),
+            ("/dl.zip", "<p>Archive</p>"),
+        ];
+        for (path, body) in pages {
+            let html = format!("<html><body><main><h1>{path}</h1>{body}</main></body></html>");
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(html))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            robots_sitemaps: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config.clone()).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        let mut paths: Vec<String> =
+            pages.iter().map(|p| Url::parse(&p.meta.url).unwrap().path().to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, ["/", "/guide"]);
+        assert_eq!(result.pages_skipped, 2);
+        let requested: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.url.path().to_string())
+            .collect();
+        assert!(!requested.iter().any(|p| p == "/internal/admin" || p == "/dl.zip"));
+        assert_eq!(requested.iter().filter(|p| *p == "/robots.txt").count(), 1);
+
+        // With respect_robots_txt off, the rules are ignored
+        let config = CrawlConfig {
+            respect_robots_txt: false,
+            ..config
+        };
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb-2", &server.uri(), None)
+            .await
+            .unwrap();
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let (result, _) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+        assert_eq!(result.pages_fetched, 4);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_collects_metrics_per_host() {
+        let docs = wiremock::MockServer::start().await;
+        let assets = wiremock::MockServer::start().await;
+
+        // Both servers are on 127.0.0.1, so both are in scope; only the port differs
+        let home = format!(
+            r#"<html><body><main><h1>Home</h1><a href="/a">A</a>
+            <a href="{0}/x">X</a><a href="{0}/y">Y</a></main></body></html>"#,
+            assets.uri()
+        );
+        let page = "<html><body><main><h1>Page</h1><p>Text</p></main></body></html>";
+        for (path, body) in [("/", home.as_str()), ("/a", page)] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .mount(&docs)
+                .await;
+        }
+        wiremock::Mock::given(wiremock::matchers::path("/x"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page))
+            .mount(&assets)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/y"))
+            .respond_with(wiremock::ResponseTemplate::new(404))
+            .mount(&assets)
+            .await;
+
+        let (storage, kb_id) = test_storage(&docs.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            concurrency: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            robots_sitemaps: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&docs.uri()).unwrap();
+        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        let host = |server: &wiremock::MockServer| {
+            let url = Url::parse(&server.uri()).unwrap();
+            result.hosts[&host_key(&url)].clone()
+        };
+        let (docs_metrics, assets_metrics) = (host(&docs), host(&assets));
+        assert_eq!(result.hosts.len(), 2);
+        assert_eq!((docs_metrics.requests, docs_metrics.errors), (2, 0));
+        assert_eq!((assets_metrics.requests, assets_metrics.errors), (2, 1));
+        assert_eq!(docs_metrics.bytes + assets_metrics.bytes, result.bytes_downloaded);
+        assert_eq!(assets_metrics.bytes, page.len() as u64);
+        assert!(docs_metrics.p50_latency <= docs_metrics.p95_latency);
+    }
+
+    #[test]
+    fn host_metrics_percentiles_use_nearest_rank() {
+        let latencies = (1..=20).map(Duration::from_millis).collect();
+        let metrics = HostMetrics::from_latencies(latencies);
+        assert_eq!(metrics.requests, 20);
+        assert_eq!(metrics.p50_latency, Duration::from_millis(10));
+        assert_eq!(metrics.p95_latency, Duration::from_millis(19));
+
+        let single = HostMetrics::from_latencies(vec![Duration::from_millis(7)]);
+        assert_eq!(single.p50_latency, Duration::from_millis(7));
+        assert_eq!(single.p95_latency, Duration::from_millis(7));
+        assert_eq!(HostMetrics::from_latencies(Vec::new()), HostMetrics::default());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_caps_links_per_page() {
+        let server = wiremock::MockServer::start().await;
+
+        let links: String = (0..50)
+            .map(|i| format!(r#"<a href="/p{i}">Page {i}</a>"#))
+            .collect();
+        let index = format!("<html><body><main><h1>Index</h1>{links}</main></body></html>");
+
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(index))
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/p\d+$"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string("<html><body><main><h1>Leaf</h1></main></body></html>"),
+            )
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            concurrency: 4,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            max_links_per_page: 5,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // Root + first 5 links in document order
+        assert_eq!(result.pages_fetched, 6);
+        let mut paths: Vec<String> = pages.iter().map(|p| p.meta.path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["index", "p0", "p1", "p2", "p3", "p4"]);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_queue_stays_within_cap() {
+        let server = wiremock::MockServer::start().await;
+
+        // Every page links to 50 others, so the frontier grows fast
+        let page = |title: &str| {
+            let links: String = (0..50)
+                .map(|i| format!(r#"<a href="/p{i}">Page {i}</a>"#))
+                .collect();
+            format!("<html><body><main><h1>{title}</h1>{links}</main></body></html>")
+        };
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page("Index")))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/p\d+$"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page("Leaf")))
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 3,
+            concurrency: 4,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            max_links_per_page: 0,
+            max_queue_size: 20,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        assert!(result.peak_queue_len <= 20, "peak queue {}", result.peak_queue_len);
+        assert!(result.links_dropped > 0);
+        assert!(result.pages_fetched > 1);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_stops_at_max_pages() {
+        let server = wiremock::MockServer::start().await;
+        let links: String = (0..30)
+            .map(|i| format!(r#"<a href="/p{i}">Page {i}</a>"#))
+            .collect();
+        let index = format!("<html><body><main><h1>Index</h1>{links}</main></body></html>");
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(index))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/p\d+$"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string("<html><body><main><h1>Leaf</h1></main></body></html>"),
+            )
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            concurrency: 4,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            robots_sitemaps: false,
+            max_pages: 7,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        assert_eq!(result.pages_fetched, 7);
+        assert_eq!(pages.len(), 7);
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 7, "no fetches beyond the limit");
+        assert!(result.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_keeps_the_fullest_sidebar_with_page_paths() {
+        let server = wiremock::MockServer::start().await;
+        let page = |sidebar: &str| {
+            format!(
+                r#"<html><head><meta name="generator" content="Docusaurus v3"></head><body>
+                <nav><ul class="menu__list">{sidebar}</ul></nav>
+                <article><h1>Doc</h1></article></body></html>"#
+            )
+        };
+        let collapsed = r#"<li><a class="menu__link" href="/docs/guide">Guide</a></li>"#;
+        let expanded = r#"<li><a class="menu__link" href="/docs/guide">Guide</a>
+            <ul class="menu__list">
+              <li><a class="menu__link" href="setup#install">Setup</a></li>
+            </ul></li>"#;
+        for (route, sidebar) in [("/", collapsed), ("/docs/guide", expanded)] {
+            wiremock::Mock::given(wiremock::matchers::path(route))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(page(sidebar)))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, _) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        assert_eq!(result.toc.len(), 1);
+        let guide = &result.toc[0];
+        assert_eq!(guide.path, "docs/guide");
+        assert_eq!(guide.children.len(), 1);
+        let setup = &guide.children[0];
+        assert_eq!(setup.path, "docs/setup");
+        assert_eq!(setup.source_url, Some(format!("{}/docs/setup", server.uri())));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_follows_stored_links_of_known_pages_without_fetching() {
+        let server = wiremock::MockServer::start().await;
+        for route in ["/", "/a", "/b"] {
+            wiremock::Mock::given(wiremock::matchers::path(route))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                    "<html><body><main><h1>Page</h1></main></body></html>",
+                ))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            robots_sitemaps: false,
+            ..Default::default()
+        };
+        let base = server.uri();
+        let crawler = Crawler::new(config)
+            .unwrap()
+            .allow_localhost()
+            .with_known_pages([
+                (format!("{base}/"), vec![format!("{base}/a"), format!("{base}/b")]),
+                (format!("{base}/a"), vec![]),
+            ]);
+        let start_url = Url::parse(&base).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        let fetched: Vec<&str> = pages.iter().map(|p| p.meta.path.as_str()).collect();
+        assert_eq!(fetched, ["b"]);
+        assert_eq!(result.pages_skipped, 2);
+        let requests = server.received_requests().await.unwrap();
+        let paths: Vec<&str> = requests.iter().map(|r| r.url.path()).collect();
+        assert_eq!(paths, ["/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_caps_stored_links_of_known_pages() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/[abc]$"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>Page</h1></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            robots_sitemaps: false,
+            max_links_per_page: 2,
+            max_queue_size: 1,
+            ..Default::default()
+        };
+        let base = server.uri();
+        let links = ["a", "b", "c"].map(|path| format!("{base}/{path}")).to_vec();
+        let crawler = Crawler::new(config)
+            .unwrap()
+            .allow_localhost()
+            .with_known_pages([(format!("{base}/"), links)]);
+        let start_url = Url::parse(&base).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        // "/a" fills the queue, "/b" is dropped, "/c" is past the fan-out cap
+        let fetched: Vec<&str> = pages.iter().map(|p| p.meta.path.as_str()).collect();
+        assert_eq!(fetched, ["a"]);
+        assert_eq!(result.links_dropped, 1);
+        assert!(result.peak_queue_len <= 1, "peak queue {}", result.peak_queue_len);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_fetches_http_and_https_variants_once() {
+        let server = wiremock::MockServer::start().await;
+        let http = server.uri();
+        let https = http.replacen("http://", "https://", 1);
+
+        let root = format!(
+            r#"<html><body><main><h1>Root</h1>
+            <a href="{http}/a">A</a><a href="{https}/a">A again</a>
+            </main></body></html>"#
+        );
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::path("/a"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string("<html><body><main><h1>A</h1></main></body></html>"),
+            )
+            .mount(&server)
+            .await;
+
+        let storage = MemoryStorage::new();
+        let config = CrawlConfig {
+            depth: 2,
+            rate_limit_ms: 0,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&http).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, "kb", &storage).await.unwrap();
+
+        let paths: Vec<&str> = pages.iter().map(|p| p.meta.path.as_str()).collect();
+        assert_eq!(paths.len(), 2, "{paths:?}");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_drops_pages_matching_selector() {
+        let server = wiremock::MockServer::start().await;
+
+        let root = r#"<html><body><main>
+            <h1>Root</h1><a href="/old">Old</a><a href="/new">New</a>
+        </main></body></html>"#;
+        let old = r#"<html><body>
+            <div class="deprecated-banner">This page is deprecated.</div>
+            <main><h1>Old</h1><a href="/older">Older</a></main>
+        </body></html>"#;
+        let leaf = "<html><body><main><h1>Leaf</h1></main></body></html>";
+
+        for (path, body) in [("/", root), ("/old", old), ("/new", leaf), ("/older", leaf)] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 2,
+            concurrency: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            drop_if_selector_matches: vec![".deprecated-banner".into()],
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
+
+        let mut paths: Vec<String> = pages.iter().map(|p| p.meta.path.clone()).collect();
+        paths.sort();
+        // The deprecated page is dropped, but pages only it links to are kept
+        assert_eq!(paths, vec!["index", "new", "older"]);
+        assert_eq!(result.pages_fetched, 3);
+        assert!(result.pages_skipped >= 1);
+        assert!(storage.get_page(&kb_id, "old").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_skips_pages_matching_skip_selector() {
+        let server = wiremock::MockServer::start().await;
+
+        let root = r#"<html><body><main>
+            <h1>Root</h1><a href="/stub">Stub</a><a href="/guide">Guide</a>
+        </main></body></html>"#;
+        let stub = r#"<html><body><main class="api-stub">
+            <h1>Stub</h1><a href="/generated">Generated</a>
+        </main></body></html>"#;
+        let leaf = "<html><body><main><h1>Leaf</h1></main></body></html>";
+        for (path, body) in [("/", root), ("/stub", stub), ("/guide", leaf)] {
+            wiremock::Mock::given(wiremock::matchers::path(path))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+        // Only the skipped page links here, so it must never be requested
+        wiremock::Mock::given(wiremock::matchers::path("/generated"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(leaf))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let config = CrawlConfig {
+            depth: 3,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            skip_page_selectors: vec!["main.api-stub".into()],
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let storage = MemoryStorage::new();
+        let (result, pages) = crawler.crawl(&start_url, "kb", &storage).await.unwrap();
+
+        let mut paths: Vec<String> = pages.iter().map(|p| p.meta.path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["guide", "index"]);
+        assert_eq!(result.pages_skipped, 1);
+        assert!(storage.get_page("kb", "stub").await.unwrap().is_none());
+
+        let err = Crawler::new(CrawlConfig {
+            skip_page_selectors: vec!["main[".into()],
+            ..Default::default()
+        })
+        .err()
+        .expect("invalid selector must fail");
+        assert!(err.to_string().contains("invalid skip page selector 'main['"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_crawl_keeps_significant_query_variants_apart() {
+        let server = wiremock::MockServer::start().await;
+        let root = r#"<html><body><main><h1>Root</h1>
+            <a href="/guide?version=v1">v1</a>
+            <a href="/guide?version=v2">v2</a>
+            <a href="/guide?version=v2&utm_source=nav">v2 again</a>
+        </main></body></html>"#;
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .mount(&server)
+            .await;
+        for version in ["v1", "v2"] {
+            wiremock::Mock::given(wiremock::matchers::path("/guide"))
+                .and(wiremock::matchers::query_param("version", version))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                    "<html><body><main><h1>Guide {version}</h1></main></body></html>"
+                )))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let config = CrawlConfig {
+            depth: 1,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            significant_query_params: vec!["version".into()],
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let storage = MemoryStorage::new();
+        let (_, pages) = crawler.crawl(&start_url, "kb", &storage).await.unwrap();
+
+        let mut paths: Vec<(String, Option<String>)> = pages
+            .iter()
+            .map(|p| (p.meta.path.clone(), p.meta.title.clone()))
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                ("guide--version-v1".to_string(), Some("Guide v1".to_string())),
+                ("guide--version-v2".to_string(), Some("Guide v2".to_string())),
+                ("index".to_string(), Some("Root".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crawler_refuses_offline() {
+        let config = CrawlConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let err = Crawler::new(config).err().expect("offline crawler must fail");
+        assert!(err.to_string().contains("offline mode"), "{err}");
+    }
+
+    #[test]
+    fn test_crawler_rejects_invalid_drop_selector() {
+        let config = CrawlConfig {
+            drop_if_selector_matches: vec!["div[".into()],
+            ..Default::default()
+        };
+        assert!(Crawler::new(config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_truncates_long_titles() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(
+                "<html><body><main><h1>How to configure the widget server for high \
+                 availability deployments</h1></main></body></html>",
+            ))
+            .mount(&server)
+            .await;
+
+        let config = CrawlConfig {
+            depth: 0,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            max_title_len: Some(30),
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (_, pages) = crawler.crawl(&start_url, "kb", &MemoryStorage::new()).await.unwrap();
+
+        assert_eq!(pages[0].meta.title.as_deref(), Some("How to configure the widget…"));
+        // The original stays available on the extracted content
+        assert_eq!(
+            pages[0].content.meta.title.as_deref(),
+            Some("How to configure the widget server for high availability deployments")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_crawl_transcodes_windows_1252_bodies() {
+        let server = wiremock::MockServer::start().await;
+        // "Café résumé – naïve" in windows-1252, declared only by the header
+        let body = b"<html><body><main><h1>Caf\xe9 r\xe9sum\xe9</h1>\
+            <p>A na\xefve \x96 guide</p></main></body></html>";
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_raw(body.to_vec(), "text/html; charset=windows-1252"),
+            )
+            .mount(&server)
+            .await;
+
+        let config = CrawlConfig {
+            depth: 0,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (_, pages) = crawler.crawl(&start_url, "kb", &MemoryStorage::new()).await.unwrap();
+
+        assert_eq!(pages[0].meta.title.as_deref(), Some("Café résumé"));
+        assert!(pages[0].html.contains("A naïve – guide"));
+        assert!(!pages[0].html.contains('\u{fffd}'));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_depth_zero_fetches_single_page() {
+        let server = wiremock::MockServer::start().await;
+
+        let root = r#"<html><body><main>
+            <h1>Root</h1><a href="/a">A</a><a href="/b">B</a><a href="/c">C</a>
+        </main></body></html>"#;
+
+        wiremock::Mock::given(wiremock::matchers::path("/"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(root))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::path_regex(r"^/[abc]$"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string("<h1>Child</h1>"))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let (storage, kb_id) = test_storage(&server.uri()).await;
+
+        let config = CrawlConfig {
+            depth: 0,
+            concurrency: 2,
+            rate_limit_ms: 0,
+            mode: CrawlMode::Crawl,
+            respect_robots_txt: false,
+            ..Default::default()
+        };
+
+        let crawler = Crawler::new(config).unwrap().allow_localhost();
+        let start_url = Url::parse(&server.uri()).unwrap();
+        let (result, pages) = crawler.crawl(&start_url, &kb_id, &storage).await.unwrap();
 
-        let _ = std::fs::remove_dir_all(&tmp_dir);
+        assert_eq!(result.pages_fetched, 1);
+        assert_eq!(pages[0].meta.path, "index");
+        assert_eq!(pages[0].links.len(), 3);
     }
 }
@@ -0,0 +1,82 @@
+//! Content-based page filters.
+//!
+//! Complements URL include/exclude patterns with checks on the fetched HTML,
+//! e.g. dropping pages that carry a "deprecated" banner or a `noindex` class.
+
+use scraper::{Html, Selector};
+
+use contextbuilder_shared::{ContextBuilderError, Result};
+
+/// Drops pages whose HTML contains an element matching any of a set of CSS selectors.
+#[derive(Debug, Clone, Default)]
+pub struct DropSelectors {
+    selectors: Vec<(String, Selector)>,
+}
+
+impl DropSelectors {
+    /// Parse the configured selectors, rejecting any that are invalid.
+    pub fn new(selectors: &[String]) -> Result<Self> {
+        Self::with_label(selectors, "drop")
+    }
+
+    /// Like [`DropSelectors::new`], naming the setting as `label` in errors
+    /// (`invalid <label> selector '...'`).
+    pub fn with_label(selectors: &[String], label: &str) -> Result<Self> {
+        let selectors = selectors
+            .iter()
+            .map(|s| {
+                Selector::parse(s)
+                    .map(|sel| (s.clone(), sel))
+                    .map_err(|e| {
+                        ContextBuilderError::config(format!("invalid {label} selector '{s}': {e}"))
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { selectors })
+    }
+
+    /// Whether no selectors are configured.
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty()
+    }
+
+    /// Return the first configured selector that matches an element in `html`.
+    pub fn matching(&self, html: &str) -> Option<&str> {
+        if self.selectors.is_empty() {
+            return None;
+        }
+        let doc = Html::parse_document(html);
+        self.selectors
+            .iter()
+            .find(|(_, sel)| doc.select(sel).next().is_some())
+            .map(|(raw, _)| raw.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_configured_selectors() {
+        let drop = DropSelectors::new(&[
+            ".deprecated-banner".into(),
+            "meta[name=robots][content*=noindex]".into(),
+        ])
+        .unwrap();
+
+        let deprecated = r#"<html><body><div class="deprecated-banner">Deprecated</div></body></html>"#;
+        assert_eq!(drop.matching(deprecated), Some(".deprecated-banner"));
+
+        let noindex = r#"<html><head><meta name="robots" content="noindex, follow"></head><body></body></html>"#;
+        assert_eq!(drop.matching(noindex), Some("meta[name=robots][content*=noindex]"));
+
+        assert_eq!(drop.matching("<html><body><p>Fine</p></body></html>"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_selector() {
+        let err = DropSelectors::new(&["div[".into()]).unwrap_err();
+        assert!(err.to_string().contains("invalid drop selector 'div['"));
+    }
+}
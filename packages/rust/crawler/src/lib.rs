@@ -3,16 +3,36 @@
 //! This crate provides:
 //! - [`adapters`] — Platform-specific content extractors (Docusaurus, VitePress, etc.)
 //! - [`AdapterRegistry`] — Detects the best adapter for a given HTML document
+//! - [`charset`] — Transcodes fetched bodies to UTF-8 (Content-Type / `<meta charset>`)
 //! - [`engine`] — Concurrent, scope-aware web crawler
+//! - [`filter`] — Content-based page filters (drop by CSS selector)
+//! - [`pdf`] — Text extraction for linked PDF documents
+//! - [`robots`] — `robots.txt` allow/disallow rules and crawl delay
+//! - [`sitemap`] — Sitemap parsing and crawl seeding from `robots.txt`
+//! - [`soft404`] — Drops "not found" pages served with HTTP 200
+//! - [`throttle`] — Adaptive per-host politeness (AIMD back-off on 429/5xx)
 
 pub mod adapters;
+pub mod charset;
 pub mod engine;
+pub mod filter;
+pub mod pdf;
+pub mod robots;
+pub mod sitemap;
+pub mod soft404;
+pub mod throttle;
 
 pub use adapters::{
-    AdapterRegistry, DocusaurusAdapter, ExtractedContent, GenericAdapter, GitBookAdapter,
-    PlatformAdapter, ReadTheDocsAdapter, VitePressAdapter,
+    AdapterRegistry, DocusaurusAdapter, ExtractedContent, Extraction, GenericAdapter,
+    GitBookAdapter, MkDocsAdapter, PlatformAdapter, ReadTheDocsAdapter, ReadabilityAdapter,
+    SphinxAdapter, VitePressAdapter,
 };
-pub use engine::{CrawlResult, Crawler, FetchedPage, url_to_path};
+pub use engine::{
+    CrawlResult, Crawler, FetchedPage, HostMetrics, ScopeRule, ScopedUrl, auth_hint, content_hash,
+    is_ssrf_target, meta_description, page_path, url_to_path, with_auth,
+};
+pub use filter::DropSelectors;
+pub use robots::RobotsRules;
 
 #[cfg(test)]
 mod tests {
@@ -59,6 +79,29 @@ mod tests {
         assert_eq!(adapter.name(), "gitbook");
     }
 
+    #[test]
+    fn detect_mkdocs() {
+        let doc = load_fixture("mkdocs.html");
+        let registry = AdapterRegistry::new();
+        let adapter = registry.detect(&doc, &dummy_url());
+        assert_eq!(adapter.name(), "mkdocs");
+    }
+
+    #[test]
+    fn detect_mkdocs_by_generator_unless_readthedocs_themed() {
+        let registry = AdapterRegistry::new();
+        let generator = r#"<meta name="generator" content="mkdocs-1.6.0">"#;
+        let doc = Html::parse_document(&format!(
+            "<html><head>{generator}</head><body><div role=\"main\"><h1>Hi</h1></div></body></html>"
+        ));
+        assert_eq!(registry.detect(&doc, &dummy_url()).name(), "mkdocs");
+
+        let doc = Html::parse_document(&format!(
+            r#"<html><head>{generator}</head><body><nav class="wy-nav-side"></nav></body></html>"#
+        ));
+        assert_eq!(registry.detect(&doc, &dummy_url()).name(), "readthedocs");
+    }
+
     #[test]
     fn detect_readthedocs() {
         let doc = load_fixture("readthedocs.html");
@@ -67,6 +110,40 @@ mod tests {
         assert_eq!(adapter.name(), "readthedocs");
     }
 
+    #[test]
+    fn detect_sphinx() {
+        let doc = load_fixture("sphinx.html");
+        let registry = AdapterRegistry::new();
+        let adapter = registry.detect(&doc, &dummy_url());
+        assert_eq!(adapter.name(), "sphinx");
+    }
+
+    #[test]
+    fn sphinx_rtd_theme_stays_with_readthedocs() {
+        let doc = Html::parse_document(
+            r#"<html><head><meta name="generator" content="Sphinx 7.2.6"></head>
+            <body class="wy-body-for-nav"><nav class="wy-nav-side"></nav>
+            <div role="main" class="document"><h1>API</h1></div></body></html>"#,
+        );
+        let registry = AdapterRegistry::new();
+        assert_eq!(registry.detect(&doc, &dummy_url()).name(), "readthedocs");
+    }
+
+    #[test]
+    fn detect_readability_for_pages_without_landmarks() {
+        let registry = AdapterRegistry::new();
+        for fixture in ["blog.html", "legacy.html"] {
+            let doc = load_fixture(fixture);
+            assert_eq!(registry.detect(&doc, &dummy_url()).name(), "readability", "{fixture}");
+        }
+
+        // Too little prose to score: left to the generic adapter
+        let doc = Html::parse_document(
+            r#"<html><body><div><a href="/a">Link</a><p>Short.</p></div></body></html>"#,
+        );
+        assert_eq!(registry.detect(&doc, &dummy_url()).name(), "generic");
+    }
+
     #[test]
     fn detect_generic_fallback() {
         let doc = load_fixture("generic.html");
@@ -114,6 +191,22 @@ mod tests {
         assert!(content.html.contains("Create Your First Space"));
     }
 
+    #[test]
+    fn mkdocs_extracts_content() {
+        let doc = load_fixture("mkdocs.html");
+        let content = MkDocsAdapter.extract_content(&doc);
+
+        assert_eq!(content.meta.title, Some("Configuration".into()));
+        assert!(content.html.contains("port = 8080"));
+        assert!(content.html.contains("Environment variables override"));
+        // Page chrome, permalinks, and the sidebar are left out
+        assert!(!content.html.contains("Edit this page"));
+        assert!(!content.html.contains("headerlink"));
+        assert!(!content.html.contains("Last update"));
+        assert!(!content.html.contains("md-nav"));
+        assert!(!content.html.contains("Copyright"));
+    }
+
     #[test]
     fn readthedocs_extracts_content() {
         let doc = load_fixture("readthedocs.html");
@@ -125,10 +218,26 @@ mod tests {
         assert!(content.html.contains("from project import Client"));
     }
 
+    #[test]
+    fn sphinx_extracts_content_without_headerlinks() {
+        let doc = load_fixture("sphinx.html");
+        let content = SphinxAdapter.extract_content(&doc);
+
+        assert_eq!(content.meta.title, Some("Client usage".into()));
+        assert!(content.html.contains("client = Client("));
+        assert!(content.html.contains("exponential back-off"));
+        // Heading permalinks and the theme's chrome are left out
+        assert!(!content.html.contains('¶'));
+        assert!(!content.html.contains("headerlink"));
+        assert!(content.html.contains("<h2>Connecting</h2>"));
+        assert!(!content.html.contains("Navigation"));
+        assert!(!content.html.contains("Powered by"));
+    }
+
     #[test]
     fn generic_extracts_content() {
         let doc = load_fixture("generic.html");
-        let adapter = GenericAdapter;
+        let adapter = GenericAdapter::default();
         let content = adapter.extract_content(&doc);
 
         assert_eq!(content.meta.title, Some("About Our Company".into()));
@@ -137,6 +246,115 @@ mod tests {
         assert!(!content.html.contains("analytics"));
     }
 
+    #[test]
+    fn readability_extracts_the_article_from_a_blog_layout() {
+        let doc = load_fixture("blog.html");
+        let content = ReadabilityAdapter::default().extract_content(&doc);
+
+        assert_eq!(content.meta.title, Some("Tuning Connection Pools".into()));
+        assert!(content.html.contains("Tuning Connection Pools"));
+        assert!(content.html.contains("quietly decides how the service behaves"));
+        assert!(content.html.contains("pool.max_connections"));
+        assert!(content.html.contains("what the database actually sees"));
+        // Sidebar widgets, comments, related posts, share buttons and chrome are dropped
+        for chrome in [
+            "About the author",
+            "halving the pool size",
+            "Reading query plans",
+            "Tweet",
+            "Posted on",
+            "Archive",
+            "Powered by",
+            "dataLayer",
+        ] {
+            assert!(!content.html.contains(chrome), "kept {chrome:?}");
+        }
+    }
+
+    #[test]
+    fn readability_extracts_the_body_cell_from_a_table_layout() {
+        let doc = load_fixture("legacy.html");
+        let content = ReadabilityAdapter::default().extract_content(&doc);
+
+        assert_eq!(content.meta.title, Some("Configuration".into()));
+        assert!(content.html.contains("named widget.conf"));
+        assert!(content.html.contains("How many times a failed request is repeated"));
+        assert!(!content.html.contains("Frequently asked questions"));
+        assert!(!content.html.contains("Sign up for release announcements"));
+        assert!(!content.html.contains("all wrongs reversed"));
+    }
+
+    #[test]
+    fn readability_strips_custom_selectors() {
+        let doc = load_fixture("blog.html");
+        let adapter = ReadabilityAdapter::with_strip_selectors(&["pre".into()]).unwrap();
+        let content = adapter.extract_content(&doc);
+
+        assert!(!content.html.contains("pool.max_connections"));
+        assert!(content.html.contains("Start from the database"));
+    }
+
+    #[test]
+    fn generic_strips_custom_selectors() {
+        let doc = Html::parse_document(
+            r#"<html><body><main><div class="cookie-banner">We use cookies.</div>
+            <h1>Guide</h1><p>Real content.</p></main></body></html>"#,
+        );
+        let adapter = GenericAdapter::with_strip_selectors(&[".cookie-banner".into()]).unwrap();
+        let content = adapter.extract_content(&doc);
+
+        assert!(!content.html.contains("We use cookies"));
+        assert!(content.html.contains("Real content."));
+
+        assert!(GenericAdapter::with_strip_selectors(&["div[".into()]).is_err());
+    }
+
+    /// Claims every page, then keeps only its first paragraph.
+    struct OverStrippingAdapter;
+
+    impl PlatformAdapter for OverStrippingAdapter {
+        fn detect(&self, _doc: &Html, _url: &Url) -> bool {
+            true
+        }
+
+        fn extract_toc(&self, _doc: &Html) -> Vec<contextbuilder_shared::TocEntry> {
+            Vec::new()
+        }
+
+        fn extract_content(&self, _doc: &Html) -> ExtractedContent {
+            ExtractedContent {
+                html: "<p>Overview</p>".into(),
+                meta: adapters::PageMeta::default(),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "over-stripping"
+        }
+    }
+
+    #[test]
+    fn thin_adapter_output_falls_back_to_generic() {
+        let doc = load_fixture("generic.html");
+        let registry = AdapterRegistry::new().with_adapter(Box::new(OverStrippingAdapter));
+
+        let extraction = registry.extract(&doc, &dummy_url());
+        assert!(extraction.fell_back);
+        assert_eq!(extraction.adapter, "generic");
+        assert!(extraction.word_count > 20, "kept {} words", extraction.word_count);
+        assert!(extraction.density > 0.1);
+        assert!(extraction.content.html.contains("Our Mission"));
+    }
+
+    #[test]
+    fn dense_adapter_output_is_kept() {
+        let doc = load_fixture("docusaurus.html");
+        let extraction = AdapterRegistry::new().extract(&doc, &dummy_url());
+        assert!(!extraction.fell_back);
+        assert_eq!(extraction.adapter, "docusaurus");
+        assert_eq!(extraction.word_count, extraction.content.word_count());
+    }
+
     // -----------------------------------------------------------------------
     // TOC extraction tests
     // -----------------------------------------------------------------------
@@ -147,11 +365,45 @@ mod tests {
         let adapter = DocusaurusAdapter;
         let toc = adapter.extract_toc(&doc);
 
-        assert!(!toc.is_empty());
-        // Should find sidebar links
+        // Category pages nest under their category, as in the sidebar
         let titles: Vec<&str> = toc.iter().map(|e| e.title.as_str()).collect();
-        assert!(titles.contains(&"Getting Started"));
-        assert!(titles.contains(&"Installation"));
+        assert_eq!(titles, ["Getting Started", "API Reference"]);
+        let children: Vec<(&str, &str)> = toc[0]
+            .children
+            .iter()
+            .map(|e| (e.title.as_str(), e.path.as_str()))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("Installation", "getting-started/installation"),
+                ("Quick Start", "getting-started/quick-start"),
+            ]
+        );
+        assert!(toc[1].children.is_empty());
+    }
+
+    #[test]
+    fn docusaurus_category_without_page_groups_its_items() {
+        let doc = Html::parse_document(
+            r##"<ul class="menu__list">
+              <li class="menu__list-item">
+                <div class="menu__list-item-collapsible">
+                  <a class="menu__link menu__link--sublist" href="#">Guides</a>
+                </div>
+                <ul class="menu__list">
+                  <li><a class="menu__link" href="/docs/guides/deploy">Deploy</a></li>
+                </ul>
+              </li>
+              <li><a class="menu__link" href="/docs/faq">FAQ</a></li>
+            </ul>"##,
+        );
+        let toc = DocusaurusAdapter.extract_toc(&doc);
+
+        assert_eq!(toc.len(), 2);
+        assert_eq!((toc[0].path.as_str(), toc[0].source_url.as_deref()), ("guides", None));
+        assert_eq!(toc[0].children[0].path, "guides/deploy");
+        assert_eq!(toc[1].path, "faq");
     }
 
     #[test]
@@ -165,10 +417,99 @@ mod tests {
         assert!(titles.contains(&"Getting Started"));
     }
 
+    #[test]
+    fn vitepress_nests_sidebar_items_by_level() {
+        let doc = Html::parse_document(
+            r#"<aside class="VPSidebar"><nav>
+              <div class="VPSidebarItem level-0 collapsible">
+                <div class="item"><h2 class="text">Guide</h2></div>
+                <div class="items">
+                  <div class="VPSidebarItem level-1 is-link">
+                    <div class="item"><a class="VPLink" href="/guide/intro.html">
+                      <p class="text">Introduction</p></a></div>
+                  </div>
+                  <div class="VPSidebarItem level-1 is-link">
+                    <div class="item"><a class="VPLink" href="/guide/setup.html">
+                      <p class="text">Setup</p></a></div>
+                  </div>
+                </div>
+              </div>
+              <div class="VPSidebarItem level-0 is-link">
+                <div class="item"><a class="VPLink" href="/reference.html">
+                  <p class="text">Reference</p></a></div>
+              </div>
+            </nav></aside>"#,
+        );
+        let toc = VitePressAdapter.extract_toc(&doc);
+
+        let top: Vec<(&str, &str)> =
+            toc.iter().map(|e| (e.title.as_str(), e.path.as_str())).collect();
+        assert_eq!(top, [("Guide", "guide"), ("Reference", "reference")]);
+        let guide: Vec<&str> = toc[0].children.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(guide, ["guide/intro", "guide/setup"]);
+        assert!(toc[0].children.iter().all(|e| e.children.is_empty()));
+    }
+
+    #[test]
+    fn mkdocs_extracts_toc_from_the_primary_nav() {
+        let doc = load_fixture("mkdocs.html");
+        let toc = MkDocsAdapter.extract_toc(&doc);
+
+        let top: Vec<(&str, &str)> =
+            toc.iter().map(|e| (e.title.as_str(), e.path.as_str())).collect();
+        assert_eq!(
+            top,
+            [("Home", "index"), ("User Guide", "guide"), ("Changelog", "changelog")]
+        );
+        // Relative hrefs resolve against the canonical URL; the page's own
+        // headings (the secondary nav) are not TOC entries
+        let guide: Vec<(&str, &str)> =
+            toc[1].children.iter().map(|e| (e.title.as_str(), e.path.as_str())).collect();
+        assert_eq!(
+            guide,
+            [("Installation", "guide/installation"), ("Configuration", "guide/configuration")]
+        );
+        assert!(toc[1].children.iter().all(|e| e.children.is_empty()));
+    }
+
+    #[test]
+    fn list_sidebars_nest_by_list_depth() {
+        let doc = Html::parse_document(
+            r#"<nav class="wy-nav-side"><div class="wy-menu"><ul>
+              <li class="toctree-l1"><a href="/usage.html">Usage</a>
+                <ul><li class="toctree-l2"><a href="/usage.html#cli">CLI</a></li></ul>
+              </li>
+              <li class="toctree-l1"><a href="/api.html">API</a></li>
+            </ul></div></nav>"#,
+        );
+        let toc = ReadTheDocsAdapter.extract_toc(&doc);
+
+        let top: Vec<&str> = toc.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(top, ["Usage", "API"]);
+        assert_eq!(toc[0].children.len(), 1);
+        assert_eq!(toc[0].children[0].title, "CLI");
+    }
+
+    #[test]
+    fn sphinx_extracts_toc_from_the_sidebar_toctree() {
+        let doc = load_fixture("sphinx.html");
+        let toc = SphinxAdapter.extract_toc(&doc);
+
+        let top: Vec<(&str, &str)> =
+            toc.iter().map(|e| (e.title.as_str(), e.path.as_str())).collect();
+        assert_eq!(
+            top,
+            [("Installation", "install"), ("User guide", "guide/index"), ("Changelog", "changelog")]
+        );
+        // The current page's `#` link is not an entry
+        let guide: Vec<&str> = toc[1].children.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(guide, ["Configuration"]);
+    }
+
     #[test]
     fn generic_extracts_toc_from_headings() {
         let doc = load_fixture("generic.html");
-        let adapter = GenericAdapter;
+        let adapter = GenericAdapter::default();
         let toc = adapter.extract_toc(&doc);
 
         assert!(!toc.is_empty());
@@ -0,0 +1,116 @@
+//! Text extraction for linked PDF documents.
+//!
+//! With `ingest_pdfs` enabled, PDFs reached during a crawl are rendered as a
+//! minimal HTML page (heading + paragraphs) so they go through the regular
+//! Markdown conversion like any other page.
+
+use contextbuilder_shared::{ContextBuilderError, Result};
+
+/// Whether a `Content-Type` header value denotes a PDF.
+pub fn is_pdf_content_type(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/pdf"))
+}
+
+/// Extract the plain text of a PDF document.
+pub fn extract_text(bytes: &[u8]) -> Result<String> {
+    // pdf-extract panics on some malformed documents
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(bytes))
+        .map_err(|_| ContextBuilderError::parse("PDF text extraction panicked"))?
+        .map_err(|e| ContextBuilderError::parse(format!("PDF text extraction failed: {e}")))
+}
+
+/// Render extracted PDF text as an HTML page.
+///
+/// The first non-blank line becomes the `<h1>` (or `fallback_title` if the
+/// text is empty); blank-line separated blocks become paragraphs, with hard
+/// line wraps joined by spaces.
+pub fn text_to_html(text: &str, fallback_title: &str) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join(" "));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join(" "));
+    }
+
+    // Title is the first line of the first block, not the whole block
+    let title = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or(fallback_title);
+    if let Some(first) = blocks.first_mut() {
+        *first = first[title.len()..].trim_start().to_string();
+        if first.is_empty() {
+            blocks.remove(0);
+        }
+    }
+
+    let title = escape(title);
+    let mut html = format!("<html><head><title>{title}</title></head><body><main>\n<h1>{title}</h1>\n");
+    for block in &blocks {
+        html.push_str(&format!("<p>{}</p>\n", escape(block)));
+    }
+    html.push_str("</main></body></html>\n");
+    html
+}
+
+/// Escape text for inclusion in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_pdf_content_type() {
+        assert!(is_pdf_content_type("application/pdf"));
+        assert!(is_pdf_content_type("Application/PDF; charset=binary"));
+        assert!(!is_pdf_content_type("text/html; charset=utf-8"));
+        assert!(!is_pdf_content_type("application/pdf-x"));
+    }
+
+    #[test]
+    fn extracts_fixture_text() {
+        let bytes = std::fs::read("../../../fixtures/pdf/spec.pdf").expect("read pdf fixture");
+        let text = extract_text(&bytes).unwrap();
+        assert!(text.contains("Widget Protocol Specification"));
+        assert!(text.contains("HELLO frame"));
+
+        assert!(extract_text(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn text_becomes_heading_and_paragraphs() {
+        let html = text_to_html(
+            "\n\nGuide <v2>\nIntro line one\nline two\n\nA & B\n",
+            "fallback",
+        );
+        assert!(html.contains("<h1>Guide &lt;v2&gt;</h1>"));
+        assert!(html.contains("<p>Intro line one line two</p>"));
+        assert!(html.contains("<p>A &amp; B</p>"));
+
+        let empty = text_to_html("  \n", "spec.pdf");
+        assert!(empty.contains("<h1>spec.pdf</h1>"));
+        assert!(!empty.contains("<p>"));
+    }
+}
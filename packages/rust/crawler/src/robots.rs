@@ -0,0 +1,231 @@
+//! `robots.txt` rules.
+//!
+//! [`RobotsRules`] holds the `Allow`/`Disallow` rules and `Crawl-delay` of the
+//! groups that apply to our user agent, as RFC 9309 describes: the groups
+//! naming our product token, else the `*` groups. The longest matching rule
+//! decides, `Allow` winning ties; `*` matches any run of characters and a
+//! trailing `$` anchors the rule at the end of the path.
+
+use std::time::Duration;
+
+use reqwest::Client;
+use url::Url;
+
+use crate::sitemap::fetch_text;
+
+/// Longest `Crawl-delay` honoured, so one `robots.txt` cannot stall a crawl.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// The `robots.txt` rules that apply to one user agent.
+///
+/// The default (and the rules of an empty or missing `robots.txt`) allows
+/// everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// A `User-agent` group while parsing.
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// The rules of `robots_txt` for `user_agent`, matched by its product
+    /// token (the part before `/`, case-insensitively).
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        let token = user_agent
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let mut groups: Vec<Group> = Vec::new();
+        // A `User-agent` line after a rule starts a new group
+        let mut after_rule = true;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if after_rule || groups.is_empty() {
+                        groups.push(Group::default());
+                        after_rule = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    after_rule = true;
+                    // An empty value (`Disallow:`) restricts nothing
+                    if let Some(group) = groups.last_mut().filter(|_| !value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    after_rule = true;
+                    let seconds = value.parse::<f64>().ok().filter(|s| s.is_finite() && *s >= 0.0);
+                    if let (Some(group), Some(seconds)) = (groups.last_mut(), seconds) {
+                        let seconds = seconds.min(MAX_CRAWL_DELAY.as_secs_f64());
+                        group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let naming = |agent: &str| -> Vec<&Group> {
+            groups.iter().filter(|g| g.agents.iter().any(|a| a == agent)).collect()
+        };
+        let mut applicable = naming(&token);
+        if applicable.is_empty() {
+            applicable = naming("*");
+        }
+        Self {
+            rules: applicable.iter().flat_map(|g| g.rules.iter().cloned()).collect(),
+            crawl_delay: applicable.iter().filter_map(|g| g.crawl_delay).max(),
+        }
+    }
+
+    /// Whether the rules allow fetching `url` (its path and query).
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        if target == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &target))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// The requested delay between requests, capped at [`MAX_CRAWL_DELAY`].
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// The URL of `start_url`'s `robots.txt` and its body, or `None` when it is
+/// missing or fails to load.
+pub async fn fetch_robots_txt(client: &Client, start_url: &Url) -> Option<(Url, String)> {
+    let robots_url = start_url.join("/robots.txt").ok()?;
+    let body = fetch_text(client, &robots_url).await?;
+    Some((robots_url, body))
+}
+
+/// Whether `path` matches the `robots.txt` rule `pattern`, which is anchored
+/// at the start of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i + 1 == parts.len() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UA: &str = "ContextBuilder/0.1.0";
+
+    fn allowed(rules: &RobotsRules, path: &str) -> bool {
+        rules.is_allowed(&Url::parse("https://docs.example.com").unwrap().join(path).unwrap())
+    }
+
+    #[test]
+    fn empty_robots_txt_allows_everything() {
+        for robots in ["", "# nothing here\n", "User-agent: *\nDisallow:\n"] {
+            let rules = RobotsRules::parse(robots, UA);
+            assert!(allowed(&rules, "/"), "{robots:?}");
+            assert!(allowed(&rules, "/private/keys"), "{robots:?}");
+            assert_eq!(rules.crawl_delay(), None);
+        }
+        assert!(allowed(&RobotsRules::default(), "/anything?at=all"));
+    }
+
+    #[test]
+    fn wildcard_rules_and_longest_match() {
+        let robots = "User-agent: *\n\
+                      Disallow: /private/\n\
+                      Allow: /private/public-*\n\
+                      Disallow: /*.pdf$\n\
+                      Disallow: /*?print=\n\
+                      Disallow: /*/drafts/*/edit\n";
+        let rules = RobotsRules::parse(robots, UA);
+
+        assert!(allowed(&rules, "/guide/setup"));
+        assert!(!allowed(&rules, "/private/keys"));
+        assert!(allowed(&rules, "/private/public-faq"));
+        assert!(!allowed(&rules, "/files/spec.pdf"));
+        assert!(allowed(&rules, "/files/spec.pdf.html"));
+        assert!(!allowed(&rules, "/guide?print=1"));
+        assert!(allowed(&rules, "/guide?lang=en"));
+        assert!(!allowed(&rules, "/docs/drafts/intro/edit"));
+        assert!(allowed(&rules, "/docs/drafts/intro/view"));
+        assert!(allowed(&rules, "/robots.txt"));
+    }
+
+    #[test]
+    fn our_group_wins_over_the_wildcard_group() {
+        let robots = "User-agent: *\n\
+                      Disallow: /\n\
+                      \n\
+                      User-agent: OtherBot\n\
+                      User-agent: contextbuilder\n\
+                      Disallow: /api/\n\
+                      Crawl-delay: 2.5\n";
+        let rules = RobotsRules::parse(robots, UA);
+        assert!(allowed(&rules, "/guide"));
+        assert!(!allowed(&rules, "/api/v1"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+
+        // Any other agent falls back to `*`
+        let other = RobotsRules::parse(robots, "DocsBot/1.0");
+        assert!(!allowed(&other, "/guide"));
+        assert_eq!(other.crawl_delay(), None);
+    }
+
+    #[test]
+    fn crawl_delay_is_capped() {
+        let rules = RobotsRules::parse("User-agent: *\nCrawl-delay: 86400\n", UA);
+        assert_eq!(rules.crawl_delay(), Some(MAX_CRAWL_DELAY));
+    }
+}
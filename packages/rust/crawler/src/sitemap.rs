@@ -0,0 +1,108 @@
+//! Sitemap seeding from `robots.txt`.
+//!
+//! Sites often declare their sitemaps in `robots.txt` with `Sitemap:` lines,
+//! and not always at the conventional `/sitemap.xml`. Before crawling,
+//! [`robots_sitemap_seeds`] reads those directives, fetches each declared
+//! sitemap (following sitemap indexes as `sitemap.xml` discovery does), and
+//! returns the page URLs they list so the crawl can queue them next to the
+//! start URL.
+
+use reqwest::Client;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use contextbuilder_shared::sitemap::collect_sitemap_pages;
+pub use contextbuilder_shared::sitemap::{is_sitemap_index, sitemap_urls};
+
+/// Largest sitemap or `robots.txt` body read, in bytes.
+const MAX_SITEMAP_BYTES: usize = 10 * 1024 * 1024;
+
+/// Sitemap URLs declared by `Sitemap:` lines in a `robots.txt` body, resolved
+/// against `robots_url`. The directive is case-insensitive and may appear
+/// anywhere in the file, outside any user-agent group.
+pub fn declared_sitemaps(robots_txt: &str, robots_url: &Url) -> Vec<Url> {
+    robots_txt
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = line.split_once(':')?;
+            if !key.trim().eq_ignore_ascii_case("sitemap") {
+                return None;
+            }
+            robots_url.join(value.trim()).ok()
+        })
+        .collect()
+}
+
+/// Page URLs from the sitemaps that `robots_txt` (fetched from
+/// `robots_url`) declares.
+///
+/// A `robots.txt` without `Sitemap:` lines, or a sitemap that fails to load,
+/// yields fewer (or no) seeds, never an error.
+pub async fn robots_sitemap_seeds(
+    client: &Client,
+    robots_txt: &str,
+    robots_url: &Url,
+    allow_localhost: bool,
+) -> Vec<Url> {
+    let declared = declared_sitemaps(robots_txt, robots_url);
+    let sitemaps = declared.into_iter().map(|url| (url, None)).collect();
+    let seeds = collect_sitemap_pages(sitemaps, allow_localhost, |url| async move {
+        fetch_text(client, &url).await
+    })
+    .await;
+    if !seeds.is_empty() {
+        info!(pages = seeds.len(), "seeding crawl from robots.txt sitemaps");
+    }
+    seeds
+}
+
+/// GET `url` and return its body, or `None` on any failure, a non-success
+/// status, or a body over [`MAX_SITEMAP_BYTES`].
+pub(crate) async fn fetch_text(client: &Client, url: &Url) -> Option<String> {
+    let response = match client.get(url.as_str()).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(%url, error = %e, "sitemap fetch failed");
+            return None;
+        }
+    };
+    if !response.status().is_success() {
+        debug!(%url, status = %response.status(), "no sitemap data");
+        return None;
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_SITEMAP_BYTES as u64)
+    {
+        warn!(%url, "sitemap too large, skipping");
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    if body.len() > MAX_SITEMAP_BYTES {
+        warn!(%url, "sitemap too large, skipping");
+        return None;
+    }
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_sitemaps_reads_directives_anywhere() {
+        let robots = "User-agent: *\n\
+                      Disallow: /private\n\
+                      SITEMAP: https://cdn.example.com/maps/docs.xml # main\n\
+                      sitemap:/nested/other.xml\n\
+                      # Sitemap: https://example.com/commented.xml\n";
+        let robots_url = Url::parse("https://docs.example.com/robots.txt").unwrap();
+        let urls: Vec<String> =
+            declared_sitemaps(robots, &robots_url).iter().map(Url::to_string).collect();
+        assert_eq!(
+            urls,
+            ["https://cdn.example.com/maps/docs.xml", "https://docs.example.com/nested/other.xml"]
+        );
+    }
+}
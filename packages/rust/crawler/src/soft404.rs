@@ -0,0 +1,147 @@
+//! Soft-404 detection.
+//!
+//! Single-page apps often answer unknown paths with HTTP 200 and a "Page not
+//! found" body. Before crawling, [`Soft404Detector::probe`] requests a path
+//! that cannot exist; if the site answers it with a success status, the body
+//! becomes the site's 404 fingerprint, and fetched pages whose text is nearly
+//! the same are dropped.
+
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use reqwest::Client;
+use scraper::Html;
+use tracing::{debug, info};
+use url::Url;
+use uuid::Uuid;
+
+/// Jaccard similarity at or above which a page counts as the 404 page.
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// Words per shingle.
+const SHINGLE_WORDS: usize = 3;
+
+/// Fingerprint of a site's soft-404 page.
+#[derive(Debug, Clone)]
+pub struct Soft404Detector {
+    shingles: HashSet<u64>,
+}
+
+impl Soft404Detector {
+    /// Fingerprint the 404 page served as `html`.
+    pub fn from_html(html: &str) -> Self {
+        Self {
+            shingles: shingles(&visible_text(html)),
+        }
+    }
+
+    /// Request a random, non-existent path on `start_url`'s host.
+    ///
+    /// Returns a detector when the site answers it with a 2xx body (a
+    /// soft-404), and `None` when it returns a real error status or the probe
+    /// fails.
+    pub async fn probe(client: &Client, start_url: &Url) -> Option<Self> {
+        let probe_url = start_url
+            .join(&format!("/contextbuilder-404-probe-{}", Uuid::now_v7()))
+            .ok()?;
+        let response = match client.get(probe_url.as_str()).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                debug!(%probe_url, error = %e, "soft-404 probe failed");
+                return None;
+            }
+        };
+        if !response.status().is_success() {
+            debug!(%probe_url, status = %response.status(), "site returns real 404s");
+            return None;
+        }
+        let body = crate::charset::response_text(response).await.ok()?;
+        let detector = Self::from_html(&body);
+        if detector.shingles.is_empty() {
+            return None;
+        }
+        info!(%probe_url, "site serves soft-404s, fingerprinted its 404 page");
+        Some(detector)
+    }
+
+    /// Whether `html` is (nearly) the fingerprinted 404 page.
+    pub fn is_soft_404(&self, html: &str) -> bool {
+        jaccard(&self.shingles, &shingles(&visible_text(html))) >= SIMILARITY_THRESHOLD
+    }
+}
+
+/// Text content of a document, without scripts and styles.
+fn visible_text(html: &str) -> String {
+    let doc = Html::parse_document(html);
+    let mut text = String::new();
+    for node in doc.tree.root().descendants() {
+        let Some(t) = node.value().as_text() else {
+            continue;
+        };
+        let hidden = node
+            .parent()
+            .and_then(|p| p.value().as_element().map(|e| e.name()))
+            .is_some_and(|name| matches!(name, "script" | "style" | "noscript" | "template"));
+        if !hidden {
+            text.push_str(t);
+            text.push(' ');
+        }
+    }
+    text
+}
+
+/// Hashed, lowercased word n-grams of `text`.
+fn shingles(text: &str) -> HashSet<u64> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    words
+        .windows(SHINGLE_WORDS.min(words.len()).max(1))
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOT_FOUND: &str = "<html><body><nav>Home Guides API Blog</nav>\
+        <main><h1>Page not found</h1><p>Sorry, we couldn't find the page you were \
+        looking for. Check the URL or head back to the home page.</p></main>\
+        <script>window.__APP__ = {route: 'x'}</script></body></html>";
+
+    #[test]
+    fn matches_the_404_page_but_not_real_pages() {
+        let detector = Soft404Detector::from_html(NOT_FOUND);
+        assert!(detector.is_soft_404(NOT_FOUND));
+
+        // Same text, different markup and scripts
+        let restyled = NOT_FOUND
+            .replace("<main>", "<main class=\"content\">")
+            .replace("route: 'x'", "route: 'y', build: 42");
+        assert!(detector.is_soft_404(&restyled));
+
+        // A real page sharing the navigation
+        let real = "<html><body><nav>Home Guides API Blog</nav><main><h1>Install</h1>\
+            <p>Run the installer, then add the binary to your PATH and verify the \
+            version it prints.</p></main></body></html>";
+        assert!(!detector.is_soft_404(real));
+    }
+
+    #[test]
+    fn empty_documents_never_match() {
+        let detector = Soft404Detector::from_html(NOT_FOUND);
+        assert!(!detector.is_soft_404(""));
+        assert!(!Soft404Detector::from_html("").is_soft_404(""));
+    }
+}
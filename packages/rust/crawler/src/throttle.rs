@@ -0,0 +1,259 @@
+//! Adaptive per-host politeness.
+//!
+//! Every request waits for a slot in its host's concurrency window and then
+//! sleeps the host's current delay (starting at `rate_limit_ms`). When a host
+//! answers 429/5xx or fails at the network level, its window is halved and its
+//! delay doubled; each successful response widens the window by one and eases
+//! the delay back toward the configured rate limit (AIMD).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use contextbuilder_shared::CrawlConfig;
+
+/// Smallest delay applied after a back-off signal when no rate limit is set.
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Current politeness settings for one host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostState {
+    /// Maximum concurrent requests to the host.
+    pub limit: u32,
+    /// Delay before each request to the host.
+    pub delay: Duration,
+    in_flight: u32,
+}
+
+/// Per-host concurrency and delay, adjusted from response statuses.
+#[derive(Debug)]
+pub struct HostThrottle {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_concurrency: u32,
+    adaptive: bool,
+    hosts: Mutex<HashMap<String, HostState>>,
+    released: Notify,
+}
+
+impl HostThrottle {
+    /// Build a throttle from the crawl's rate limit, concurrency, and back-off settings.
+    pub fn new(config: &CrawlConfig) -> Self {
+        let base_delay = Duration::from_millis(config.rate_limit_ms);
+        Self {
+            base_delay,
+            max_delay: Duration::from_millis(config.max_backoff_ms).max(base_delay),
+            max_concurrency: config.concurrency.max(1),
+            adaptive: config.adaptive_throttle,
+            hosts: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+        }
+    }
+
+    /// Current settings for `host`, if it has been seen.
+    pub fn state(&self, host: &str) -> Option<HostState> {
+        self.lock().get(host).copied()
+    }
+
+    /// Wait for a free slot for `host`, then sleep its current delay.
+    pub async fn acquire(self: &Arc<Self>, host: &str) -> ThrottlePermit {
+        let delay = loop {
+            let released = self.released.notified();
+            if let Some(delay) = self.try_enter(host) {
+                break delay;
+            }
+            released.await;
+        };
+        let permit = ThrottlePermit {
+            throttle: Arc::clone(self),
+            host: host.to_string(),
+            recorded: false,
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        permit
+    }
+
+    fn try_enter(&self, host: &str) -> Option<Duration> {
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert(HostState {
+            limit: self.max_concurrency,
+            delay: self.base_delay,
+            in_flight: 0,
+        });
+        if state.in_flight >= state.limit {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(state.delay)
+    }
+
+    /// Adjust `host` after a response (`None` = the request failed outright).
+    fn record(&self, host: &str, status: Option<u16>) {
+        if !self.adaptive {
+            return;
+        }
+        let mut hosts = self.lock();
+        let Some(state) = hosts.get_mut(host) else {
+            return;
+        };
+        if is_backoff_signal(status) {
+            state.limit = (state.limit / 2).max(1);
+            state.delay = (state.delay * 2).max(MIN_BACKOFF).min(self.max_delay);
+            tracing::debug!(
+                host,
+                ?status,
+                limit = state.limit,
+                delay_ms = state.delay.as_millis() as u64,
+                "backing off"
+            );
+        } else {
+            state.limit = (state.limit + 1).min(self.max_concurrency);
+            let eased = state.delay.saturating_sub(state.delay.saturating_sub(self.base_delay) / 4);
+            state.delay = if eased < self.base_delay + MIN_BACKOFF {
+                self.base_delay
+            } else {
+                eased
+            };
+        }
+    }
+
+    fn release(&self, host: &str) {
+        if let Some(state) = self.lock().get_mut(host) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+        self.released.notify_waiters();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HostState>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether a response should slow the host down: 429, any 5xx, or no response.
+fn is_backoff_signal(status: Option<u16>) -> bool {
+    match status {
+        None => true,
+        Some(code) => code == 429 || (500..600).contains(&code),
+    }
+}
+
+/// A held request slot; releases it on drop.
+#[derive(Debug)]
+pub struct ThrottlePermit {
+    throttle: Arc<HostThrottle>,
+    host: String,
+    recorded: bool,
+}
+
+impl ThrottlePermit {
+    /// Report the response status (`None` for a network failure). Only the
+    /// first call counts.
+    pub fn record(&mut self, status: Option<u16>) {
+        if !self.recorded {
+            self.recorded = true;
+            self.throttle.record(&self.host, status);
+        }
+    }
+}
+
+impl Drop for ThrottlePermit {
+    fn drop(&mut self) {
+        self.throttle.release(&self.host);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle(rate_limit_ms: u64, concurrency: u32) -> Arc<HostThrottle> {
+        Arc::new(HostThrottle::new(&CrawlConfig {
+            rate_limit_ms,
+            concurrency,
+            max_backoff_ms: 1_000,
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn backs_off_on_429_and_recovers() {
+        let t = throttle(0, 8);
+        for _ in 0..3 {
+            t.acquire("a.test").await.record(Some(429));
+        }
+        let state = t.state("a.test").unwrap();
+        assert_eq!(state.limit, 1);
+        assert_eq!(state.delay, Duration::from_millis(400));
+
+        // Other hosts are unaffected
+        t.acquire("b.test").await.record(Some(200));
+        assert_eq!(t.state("b.test").unwrap().limit, 8);
+
+        for _ in 0..20 {
+            let mut permit = t.acquire("a.test").await;
+            permit.record(Some(200));
+        }
+        let state = t.state("a.test").unwrap();
+        assert_eq!(state.limit, 8);
+        assert_eq!(state.delay, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn delay_is_capped_and_errors_count() {
+        let t = throttle(50, 2);
+        for _ in 0..10 {
+            t.acquire("a.test").await.record(None);
+        }
+        assert_eq!(t.state("a.test").unwrap().delay, Duration::from_millis(1_000));
+
+        // Client errors mean the host is responsive
+        let t = throttle(50, 2);
+        t.acquire("a.test").await.record(Some(404));
+        assert_eq!(t.state("a.test").unwrap().delay, Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn limit_bounds_in_flight_requests() {
+        let t = throttle(0, 4);
+        for _ in 0..2 {
+            t.acquire("a.test").await.record(Some(503));
+        }
+        assert_eq!(t.state("a.test").unwrap().limit, 1);
+
+        let first = t.acquire("a.test").await;
+        let waiting = {
+            let t = Arc::clone(&t);
+            tokio::spawn(async move { t.acquire("a.test").await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished(), "second request must wait for the only slot");
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(2), waiting)
+            .await
+            .expect("slot released")
+            .unwrap();
+        drop(second);
+    }
+
+    #[tokio::test]
+    async fn disabled_throttle_keeps_limits() {
+        let t = Arc::new(HostThrottle::new(&CrawlConfig {
+            concurrency: 4,
+            rate_limit_ms: 0,
+            adaptive_throttle: false,
+            ..Default::default()
+        }));
+        t.acquire("a.test").await.record(Some(429));
+        let state = t.state("a.test").unwrap();
+        assert_eq!((state.limit, state.delay), (4, Duration::ZERO));
+    }
+}
@@ -3,25 +3,31 @@
 //! Before crawling a site, ContextBuilder first checks whether the site publishes
 //! an `llms.txt` file (per <https://llmstxt.org/>). If found, we parse it to
 //! extract page URLs instead of crawling, which is faster and more respectful.
+//! Failing that, [`sitemap::discover_sitemap`] lists the pages of the site's
+//! `sitemap.xml`.
 
 mod parser;
+pub mod sitemap;
 
-use contextbuilder_shared::{ContextBuilderError, Result};
-use reqwest::Client;
+use contextbuilder_shared::{ContextBuilderError, HttpAuth, HttpTimeouts, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, ClientBuilder, Proxy};
 use tracing::{debug, info, instrument};
 use url::Url;
 
 pub use parser::{LlmsEntry, LlmsParsed, LlmsSection};
+pub use sitemap::discover_sitemap;
 
-/// Maximum number of redirects to follow when fetching llms.txt.
-const MAX_REDIRECTS: usize = 3;
+/// Default maximum number of redirects to follow when fetching llms.txt.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
 
-/// Default timeout in seconds for fetching llms.txt.
-const DEFAULT_TIMEOUT_SECS: u64 = 10;
-
-/// Maximum response size we consider valid (10 MB).
+/// Maximum `llms.txt` (and `sitemap.xml`) response size we consider valid
+/// (10 MB).
 const MAX_RESPONSE_SIZE: u64 = 10 * 1024 * 1024;
 
+/// Default maximum `llms-full.txt` response size (100 MB).
+const DEFAULT_MAX_FULL_RESPONSE_SIZE: u64 = 100 * 1024 * 1024;
+
 /// User-Agent string for discovery requests.
 const USER_AGENT: &str = concat!("ContextBuilder/", env!("CARGO_PKG_VERSION"));
 
@@ -52,14 +58,27 @@ pub enum DiscoveryResult {
 /// Configuration for the discovery process.
 #[derive(Debug, Clone)]
 pub struct DiscoveryOptions {
-    /// Timeout for HTTP requests in seconds.
-    pub timeout_secs: u64,
+    /// HTTP connect / read / total timeouts.
+    pub timeouts: HttpTimeouts,
+    /// Maximum accepted size of `llms-full.txt` in bytes (after decompression).
+    pub max_full_size: u64,
+    /// Refuse to make any request (offline mode).
+    pub offline: bool,
+    /// Redirects followed per request before failing with a redirect-limit error.
+    pub max_redirects: usize,
+    /// User agent, headers, cookies, and proxy, shared with the crawler so
+    /// private sites can be discovered as well as crawled.
+    pub auth: HttpAuth,
 }
 
 impl Default for DiscoveryOptions {
     fn default() -> Self {
         Self {
-            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            timeouts: HttpTimeouts::default(),
+            max_full_size: DEFAULT_MAX_FULL_RESPONSE_SIZE,
+            offline: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            auth: HttpAuth::default(),
         }
     }
 }
@@ -73,24 +92,35 @@ impl Default for DiscoveryOptions {
 /// Checks `<origin>/llms.txt` and `<origin>/llms-full.txt` (in parallel),
 /// validates the content is well-formed Markdown starting with an H1,
 /// and parses it into structured sections with linked URLs.
+///
+/// A transient failure fetching llms.txt (see
+/// [`ContextBuilderError::is_transient`]) is returned as an error rather than
+/// [`DiscoveryResult::NotFound`].
 #[instrument(skip_all, fields(url = %url))]
 pub async fn discover(url: &Url, opts: &DiscoveryOptions) -> Result<DiscoveryResult> {
     let origin = origin_url(url)?;
     let llms_url = format!("{origin}/llms.txt");
     let llms_full_url = format!("{origin}/llms-full.txt");
 
+    if opts.offline {
+        return Err(ContextBuilderError::offline(format!("fetch {llms_url}")));
+    }
+
     info!(%llms_url, "checking for llms.txt");
 
     let client = build_client(opts)?;
 
     // Fetch llms.txt and llms-full.txt concurrently
     let (llms_result, llms_full_result) = tokio::join!(
-        fetch_and_validate(&client, &llms_url),
-        fetch_and_validate(&client, &llms_full_url),
+        fetch_and_validate(&client, &llms_url, MAX_RESPONSE_SIZE, opts.max_redirects),
+        fetch_and_validate(&client, &llms_full_url, opts.max_full_size, opts.max_redirects),
     );
 
     let llms_txt = match llms_result {
         Ok(content) => content,
+        // A blip says nothing about whether llms.txt exists; let the caller
+        // decide whether to retry
+        Err(e) if e.is_transient() => return Err(e),
         Err(e) => {
             debug!(error = %e, "llms.txt not found or invalid");
             return Ok(DiscoveryResult::NotFound);
@@ -144,52 +174,118 @@ fn origin_url(url: &Url) -> Result<String> {
 
 /// Build a reqwest client with appropriate settings.
 fn build_client(opts: &DiscoveryOptions) -> Result<Client> {
-    Client::builder()
-        .user_agent(USER_AGENT)
-        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
-        .timeout(std::time::Duration::from_secs(opts.timeout_secs))
+    with_auth(Client::builder(), &opts.auth)?
+        .redirect(reqwest::redirect::Policy::limited(opts.max_redirects))
+        .connect_timeout(opts.timeouts.connect())
+        .read_timeout(opts.timeouts.read())
+        .timeout(opts.timeouts.total())
         .build()
         .map_err(|e| ContextBuilderError::Network(format!("failed to build HTTP client: {e}")))
 }
 
+/// Apply the user agent, default headers, and proxy of `auth` to `builder`.
+fn with_auth(builder: ClientBuilder, auth: &HttpAuth) -> Result<ClientBuilder> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in auth.header_pairs() {
+        let invalid = |e: &dyn std::fmt::Display| {
+            ContextBuilderError::config(format!("invalid header '{name}': {e}"))
+        };
+        let value = HeaderValue::from_str(&value).map_err(|e| invalid(&e))?;
+        headers.insert(HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?, value);
+    }
+    let mut builder = builder
+        .user_agent(auth.user_agent.as_deref().unwrap_or(USER_AGENT))
+        .default_headers(headers);
+    if let Some(proxy) = &auth.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| ContextBuilderError::config(format!("invalid proxy '{proxy}': {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder)
+}
+
 /// Fetch a URL and validate the response is valid Markdown content.
-async fn fetch_and_validate(client: &Client, url: &str) -> Result<String> {
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
+///
+/// The body is streamed: the H1 check runs as soon as the first non-blank
+/// bytes arrive, and reading stops with an error once `max_size` is exceeded.
+/// Gzip `Content-Encoding` is decoded transparently by the client.
+async fn fetch_and_validate(
+    client: &Client,
+    url: &str,
+    max_size: u64,
+    max_redirects: usize,
+) -> Result<String> {
+    let mut response = client.get(url).send().await.map_err(|e| {
+        if e.is_redirect() {
+            ContextBuilderError::redirect_limit(url, max_redirects)
+        } else {
+            ContextBuilderError::Network(format!("{url}: {e}"))
+        }
+    })?;
 
     let status = response.status();
     if !status.is_success() {
-        return Err(ContextBuilderError::Network(format!(
-            "{url}: HTTP {status}"
-        )));
+        return Err(ContextBuilderError::http_status(url, status.as_u16(), status));
     }
 
-    // Check content-length if available
+    // Check content-length if available (absent for compressed responses)
     if let Some(len) = response.content_length() {
-        if len > MAX_RESPONSE_SIZE {
+        if len > max_size {
             return Err(ContextBuilderError::validation(format!(
-                "{url}: response too large ({len} bytes, max {MAX_RESPONSE_SIZE})"
+                "{url}: response too large ({len} bytes, max {max_size})"
             )));
         }
     }
 
-    let body = response
-        .text()
+    let mut body: Vec<u8> = Vec::new();
+    let mut validated = false;
+
+    while let Some(chunk) = response
+        .chunk()
         .await
-        .map_err(|e| ContextBuilderError::Network(format!("{url}: failed to read body: {e}")))?;
+        .map_err(|e| ContextBuilderError::Network(format!("{url}: failed to read body: {e}")))?
+    {
+        if (body.len() + chunk.len()) as u64 > max_size {
+            return Err(ContextBuilderError::validation(format!(
+                "{url}: response too large (over {max_size} bytes)"
+            )));
+        }
+        body.extend_from_slice(&chunk);
+
+        if !validated {
+            match starts_with_h1(&body) {
+                Some(true) => validated = true,
+                Some(false) => return Err(not_h1(url)),
+                None => {}
+            }
+        }
+    }
 
     // Validate that the content starts with an H1 (Markdown heading)
-    let trimmed = body.trim_start();
-    if !trimmed.starts_with("# ") {
-        return Err(ContextBuilderError::validation(format!(
-            "{url}: content does not start with an H1 heading"
-        )));
+    if !validated && starts_with_h1(&body) != Some(true) {
+        return Err(not_h1(url));
+    }
+
+    debug!(url, bytes = body.len(), "fetched");
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Whether `bytes` (after leading whitespace) begin with `"# "`.
+///
+/// Returns `None` while there isn't enough content to decide.
+fn starts_with_h1(bytes: &[u8]) -> Option<bool> {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace())?;
+    match &bytes[start..] {
+        [b'#', b' ', ..] => Some(true),
+        [b'#'] => None,
+        _ => Some(false),
     }
+}
 
-    Ok(body)
+fn not_h1(url: &str) -> ContextBuilderError {
+    ContextBuilderError::validation(format!(
+        "{url}: content does not start with an H1 heading"
+    ))
 }
 
 #[cfg(test)]
@@ -208,6 +304,60 @@ mod tests {
         assert_eq!(origin_url(&url).unwrap(), "http://localhost:3000");
     }
 
+    /// Mount `/hop0 -> /hop1 -> ... -> /hop{hops}`, the last serving llms.txt.
+    async fn redirect_chain(server: &wiremock::MockServer, hops: usize) {
+        for i in 0..hops {
+            wiremock::Mock::given(wiremock::matchers::path(format!("/hop{i}")))
+                .respond_with(
+                    wiremock::ResponseTemplate::new(301)
+                        .insert_header("location", format!("/hop{}", i + 1)),
+                )
+                .mount(server)
+                .await;
+        }
+        wiremock::Mock::given(wiremock::matchers::path(format!("/hop{hops}")))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string("# Docs\n"))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_redirect_limit() {
+        let server = wiremock::MockServer::start().await;
+        redirect_chain(&server, 3).await;
+        wiremock::Mock::given(wiremock::matchers::path("/loop"))
+            .respond_with(wiremock::ResponseTemplate::new(302).insert_header("location", "/loop"))
+            .mount(&server)
+            .await;
+        let url = format!("{}/hop0", server.uri());
+        let fetch = |max_redirects, url: String| async move {
+            let opts = DiscoveryOptions {
+                max_redirects,
+                ..Default::default()
+            };
+            let client = build_client(&opts).unwrap();
+            fetch_and_validate(&client, &url, MAX_RESPONSE_SIZE, max_redirects).await
+        };
+
+        // Exactly at the limit: followed to the end
+        assert_eq!(fetch(3, url.clone()).await.unwrap(), "# Docs\n");
+
+        // One hop over the limit
+        let err = fetch(2, url.clone()).await.unwrap_err();
+        assert!(
+            matches!(&err, ContextBuilderError::RedirectLimit { url: u, limit: 2 } if *u == url),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("redirect limit exceeded: {url} (more than 2 redirects)")
+        );
+
+        // A loop never resolves, so it ends at the limit too
+        let err = fetch(5, format!("{}/loop", server.uri())).await.unwrap_err();
+        assert!(matches!(err, ContextBuilderError::RedirectLimit { limit: 5, .. }), "{err:?}");
+    }
+
     #[tokio::test]
     async fn test_discover_with_mock_server() {
         let server = wiremock::MockServer::start().await;
@@ -244,6 +394,73 @@ mod tests {
         }
     }
 
+    /// Serve `body` at `/llms.txt` in `chunks` pieces with `pause` between
+    /// them (404 for anything else). Returns the server's base URL.
+    async fn dribble_server(body: &'static str, chunks: usize, pause: std::time::Duration) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let n = sock.read(&mut buf).await.unwrap_or(0);
+                    if !buf[..n].starts_with(b"GET /llms.txt ") {
+                        let _ = sock
+                            .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                            .await;
+                        return;
+                    }
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = sock.write_all(head.as_bytes()).await;
+                    for piece in body.as_bytes().chunks(body.len().div_ceil(chunks)) {
+                        let _ = sock.write_all(piece).await;
+                        let _ = sock.flush().await;
+                        tokio::time::sleep(pause).await;
+                    }
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_slow_body_within_read_timeout_succeeds() {
+        const BODY: &str = "# Slow Docs\n\n> Streams slowly.\n\n## Docs\n\n- [Intro](https://example.com/intro): Start here\n";
+        // Just over 1s in total, but never more than 550ms between chunks
+        let base = dribble_server(BODY, 3, std::time::Duration::from_millis(550)).await;
+        let url = Url::parse(&base).unwrap();
+
+        let opts = DiscoveryOptions {
+            timeouts: HttpTimeouts {
+                connect_timeout_secs: 1,
+                read_timeout_secs: 1,
+                timeout_secs: 10,
+            },
+            ..Default::default()
+        };
+        // The same response under a flat 1s timeout is abandoned, as a
+        // transient failure the caller may retry. Both run at once.
+        let flat = DiscoveryOptions {
+            timeouts: HttpTimeouts {
+                connect_timeout_secs: 1,
+                read_timeout_secs: 1,
+                timeout_secs: 1,
+            },
+            ..Default::default()
+        };
+        let (steady, cut_off) = tokio::join!(discover(&url, &opts), discover(&url, &flat));
+        match steady.unwrap() {
+            DiscoveryResult::Found { parsed, .. } => assert_eq!(parsed.title, "Slow Docs"),
+            DiscoveryResult::NotFound => panic!("slow but steady body should be accepted"),
+        }
+        assert!(cut_off.unwrap_err().is_transient());
+    }
+
     #[tokio::test]
     async fn test_discover_with_full_txt() {
         let server = wiremock::MockServer::start().await;
@@ -277,6 +494,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_starts_with_h1() {
+        assert_eq!(starts_with_h1(b"# Title"), Some(true));
+        assert_eq!(starts_with_h1(b"\n  # Title"), Some(true));
+        assert_eq!(starts_with_h1(b"Title"), Some(false));
+        assert_eq!(starts_with_h1(b"## Sub"), Some(false));
+        assert_eq!(starts_with_h1(b"  \n"), None);
+        assert_eq!(starts_with_h1(b"#"), None);
+    }
+
+    /// Mount `/llms.txt` from the fixture and `/llms-full.txt` with `full`.
+    async fn mount_llms(server: &wiremock::MockServer, full: wiremock::ResponseTemplate) {
+        let llms_content = std::fs::read_to_string("../../../fixtures/llms/valid-llms.txt")
+            .expect("read llms fixture");
+
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(&llms_content))
+            .mount(server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/llms-full.txt"))
+            .respond_with(full)
+            .mount(server)
+            .await;
+    }
+
+    /// A valid `llms-full.txt` body just over `min_len` bytes.
+    fn large_full_txt(min_len: usize) -> String {
+        let mut body = String::from("# Example Docs\n\n");
+        let line = "Lorem ipsum dolor sit amet, consectetur adipiscing elit.\n";
+        while body.len() <= min_len {
+            body.push_str(line);
+        }
+        body
+    }
+
+    #[tokio::test]
+    async fn test_discover_accepts_full_txt_over_old_cap() {
+        let server = wiremock::MockServer::start().await;
+        let full = large_full_txt(MAX_RESPONSE_SIZE as usize);
+        mount_llms(
+            &server,
+            wiremock::ResponseTemplate::new(200).set_body_string(full.clone()),
+        )
+        .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let result = discover(&url, &DiscoveryOptions::default()).await.unwrap();
+
+        match result {
+            DiscoveryResult::Found { llms_full_txt, .. } => {
+                let text = llms_full_txt.expect("llms-full.txt accepted");
+                assert_eq!(text.len(), full.len());
+                assert!(text.len() as u64 > MAX_RESPONSE_SIZE);
+            }
+            DiscoveryResult::NotFound => panic!("expected Found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_rejects_full_txt_over_configured_cap() {
+        let server = wiremock::MockServer::start().await;
+        mount_llms(
+            &server,
+            wiremock::ResponseTemplate::new(200).set_body_string(large_full_txt(4096)),
+        )
+        .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let opts = DiscoveryOptions {
+            max_full_size: 1024,
+            ..Default::default()
+        };
+        let result = discover(&url, &opts).await.unwrap();
+
+        match result {
+            DiscoveryResult::Found { llms_full_txt, .. } => assert!(llms_full_txt.is_none()),
+            DiscoveryResult::NotFound => panic!("expected Found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_decodes_gzip_full_txt() {
+        use std::io::Write;
+
+        let full = large_full_txt(64 * 1024);
+        let mut encoder =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(full.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let server = wiremock::MockServer::start().await;
+        mount_llms(
+            &server,
+            wiremock::ResponseTemplate::new(200)
+                .insert_header("content-encoding", "gzip")
+                .set_body_raw(gzipped, "text/markdown"),
+        )
+        .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let result = discover(&url, &DiscoveryOptions::default()).await.unwrap();
+
+        match result {
+            DiscoveryResult::Found { llms_full_txt, .. } => {
+                assert_eq!(llms_full_txt.as_deref(), Some(full.as_str()));
+            }
+            DiscoveryResult::NotFound => panic!("expected Found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_offline_makes_no_requests() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let opts = DiscoveryOptions {
+            offline: true,
+            ..Default::default()
+        };
+        let err = discover(&url, &opts).await.unwrap_err();
+        assert!(err.to_string().contains("offline mode"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_discover_sends_auth_headers() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .and(wiremock::matchers::header("authorization", "Bearer secret"))
+            .and(wiremock::matchers::header("cookie", "session=abc"))
+            .and(wiremock::matchers::header("user-agent", "DocsBot/1.0"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_string("# Private\n\n- [Guide](https://example.com/guide)\n"),
+            )
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(wiremock::ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let mut opts = DiscoveryOptions::default();
+        assert!(matches!(discover(&url, &opts).await.unwrap(), DiscoveryResult::NotFound));
+
+        opts.auth = HttpAuth {
+            user_agent: Some("DocsBot/1.0".into()),
+            headers: [("Authorization".into(), "Bearer secret".into())].into(),
+            cookies: [("session".into(), "abc".into())].into(),
+            proxy: None,
+        };
+        match discover(&url, &opts).await.unwrap() {
+            DiscoveryResult::Found { parsed, .. } => assert_eq!(parsed.title, "Private"),
+            DiscoveryResult::NotFound => panic!("expected Found"),
+        }
+    }
+
     #[tokio::test]
     async fn test_discover_not_found() {
         let server = wiremock::MockServer::start().await;
@@ -314,4 +696,17 @@ mod tests {
         // Invalid content → NotFound (graceful fallback)
         assert!(matches!(result, DiscoveryResult::NotFound));
     }
+
+    #[tokio::test]
+    async fn test_discover_reports_server_errors_as_transient() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/llms.txt"))
+            .respond_with(wiremock::ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let err = discover(&url, &DiscoveryOptions::default()).await.unwrap_err();
+        assert!(err.is_transient(), "{err}");
+    }
 }
@@ -8,6 +8,7 @@
 
 use contextbuilder_shared::{ContextBuilderError, Result};
 use regex::Regex;
+use std::collections::HashSet;
 use std::sync::LazyLock;
 
 // ---------------------------------------------------------------------------
@@ -32,10 +33,41 @@ pub struct LlmsParsed {
 pub struct LlmsSection {
     /// Section title (from ## heading).
     pub title: String,
+    /// Whether this is the spec's `## Optional` section: secondary links
+    /// that can be skipped when context is short.
+    pub optional: bool,
     /// Entries within this section.
     pub entries: Vec<LlmsEntry>,
 }
 
+impl LlmsParsed {
+    /// Entries to fetch, in order: every entry outside the `## Optional`
+    /// section, then (if `include_optional`) the optional ones.
+    ///
+    /// A link listed both in a regular section and under `## Optional` counts
+    /// as regular.
+    pub fn entries_to_fetch(&self, include_optional: bool) -> Vec<&LlmsEntry> {
+        let urls = |optional: bool| -> HashSet<&str> {
+            self.sections
+                .iter()
+                .filter(|s| s.optional == optional)
+                .flat_map(|s| s.entries.iter().map(|e| e.url.as_str()))
+                .collect()
+        };
+        let (optional_urls, required_urls) = (urls(true), urls(false));
+        let is_optional = |e: &LlmsEntry| {
+            optional_urls.contains(e.url.as_str()) && !required_urls.contains(e.url.as_str())
+        };
+
+        let mut entries: Vec<&LlmsEntry> =
+            self.entries.iter().filter(|e| !is_optional(e)).collect();
+        if include_optional {
+            entries.extend(self.entries.iter().filter(|e| is_optional(e)));
+        }
+        entries
+    }
+}
+
 /// A single linked entry in the llms.txt.
 #[derive(Debug, Clone)]
 pub struct LlmsEntry {
@@ -143,8 +175,10 @@ pub(crate) fn parse_llms_txt(content: &str) -> Result<LlmsParsed> {
             if let Some(section) = current_section.take() {
                 sections.push(section);
             }
+            let title = caps[1].trim().to_string();
             current_section = Some(LlmsSection {
-                title: caps[1].trim().to_string(),
+                optional: title.eq_ignore_ascii_case("optional"),
+                title,
                 entries: Vec::new(),
             });
             continue;
@@ -258,4 +292,29 @@ mod tests {
         let parsed = parse_llms_txt(content).unwrap();
         assert_eq!(parsed.summary, Some("Line one Line two".into()));
     }
+
+    #[test]
+    fn optional_section_is_flagged_and_skippable() {
+        let content = "# Test\n\n## Optional\n\n- [Changelog](https://a.com/changelog)\n\
+                       - [Install](https://a.com/install)\n\n\
+                       ## Docs\n\n- [Install](https://a.com/install)\n- [API](https://a.com/api)\n";
+        let parsed = parse_llms_txt(content).unwrap();
+        assert!(parsed.sections[0].optional);
+        assert!(!parsed.sections[1].optional);
+
+        let urls = |include_optional| -> Vec<&str> {
+            parsed
+                .entries_to_fetch(include_optional)
+                .iter()
+                .map(|e| e.url.as_str())
+                .collect()
+        };
+        // Install is also a regular link, so only the changelog is optional
+        assert_eq!(
+            urls(false),
+            ["https://a.com/install", "https://a.com/install", "https://a.com/api"]
+        );
+        assert_eq!(urls(true).last(), Some(&"https://a.com/changelog"));
+        assert_eq!(urls(true).len(), parsed.entries.len());
+    }
 }
@@ -0,0 +1,241 @@
+//! `sitemap.xml` discovery.
+//!
+//! Sites without an `llms.txt` usually still publish a sitemap listing every
+//! page. [`discover_sitemap`] checks `<origin>/sitemap.xml` (then
+//! `sitemap.xml.gz`), follows nested `<sitemapindex>` files, and returns the
+//! page URLs they list, so auto mode can fetch exactly those pages instead of
+//! discovering them link by link.
+
+use std::io::Read;
+
+use contextbuilder_shared::sitemap::{collect_sitemap_pages, is_sitemap_index};
+use contextbuilder_shared::{is_ssrf_target, ContextBuilderError, Result};
+use reqwest::Client;
+use tracing::{debug, info, instrument, warn};
+use url::Url;
+
+use crate::{build_client, origin_url, DiscoveryOptions, MAX_RESPONSE_SIZE};
+
+/// Conventional sitemap locations, tried in order.
+const SITEMAP_PATHS: [&str; 2] = ["sitemap.xml", "sitemap.xml.gz"];
+
+/// The page URLs listed by the sitemap at `url`'s origin, in sitemap order
+/// and without duplicates.
+///
+/// An empty list means the site has no usable sitemap. Child sitemaps that
+/// fail to load are skipped; every file, compressed or not, is held to the
+/// same 10 MB cap as `llms.txt`.
+#[instrument(skip_all, fields(url = %url))]
+pub async fn discover_sitemap(url: &Url, opts: &DiscoveryOptions) -> Result<Vec<Url>> {
+    let origin = origin_url(url)?;
+    if opts.offline {
+        return Err(ContextBuilderError::offline(format!("fetch {origin}/sitemap.xml")));
+    }
+
+    let client = build_client(opts)?;
+    for path in SITEMAP_PATHS {
+        let root = Url::parse(&format!("{origin}/{path}"))
+            .map_err(|e| ContextBuilderError::validation(format!("{origin}/{path}: {e}")))?;
+        match fetch_sitemap(&client, &root).await {
+            Ok(xml) => {
+                let pages = collect_pages(&client, &root, &xml).await;
+                info!(sitemap = %root, pages = pages.len(), "sitemap discovered");
+                return Ok(pages);
+            }
+            Err(e) => debug!(sitemap = %root, error = %e, "sitemap not found or invalid"),
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Page URLs from the sitemap `xml` fetched from `root`, following indexes
+/// with the limits shared with crawl seeding.
+///
+/// Child sitemaps on a private or local host are only fetched when `root`
+/// itself is on one, i.e. when the user pointed us there.
+async fn collect_pages(client: &Client, root: &Url, xml: &str) -> Vec<Url> {
+    let sitemaps = vec![(root.clone(), Some(xml.to_string()))];
+    collect_sitemap_pages(sitemaps, is_ssrf_target(root), |url| async move {
+        match fetch_sitemap(client, &url).await {
+            Ok(xml) => Some(xml),
+            Err(e) => {
+                warn!(sitemap_url = %url, error = %e, "skipping sitemap");
+                None
+            }
+        }
+    })
+    .await
+}
+
+/// Fetch the sitemap at `url`, gunzipping a gzip file body, and check it is
+/// a `<urlset>` or `<sitemapindex>`.
+///
+/// Reading stops with an error once the body, before or after
+/// decompression, exceeds [`MAX_RESPONSE_SIZE`]. (Gzip `Content-Encoding` is
+/// decoded by the client; this handles `.xml.gz` files served as-is.)
+async fn fetch_sitemap(client: &Client, url: &Url) -> Result<String> {
+    let mut response = client
+        .get(url.as_str())
+        .send()
+        .await
+        .map_err(|e| ContextBuilderError::Network(format!("{url}: {e}")))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ContextBuilderError::http_status(url, status.as_u16(), status));
+    }
+    if let Some(len) = response.content_length().filter(|len| *len > MAX_RESPONSE_SIZE) {
+        return Err(too_large(url, &format!("{len} bytes")));
+    }
+
+    let mut body: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ContextBuilderError::Network(format!("{url}: failed to read body: {e}")))?
+    {
+        if (body.len() + chunk.len()) as u64 > MAX_RESPONSE_SIZE {
+            return Err(too_large(url, "over the limit"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    if body.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(body.as_slice())
+            .take(MAX_RESPONSE_SIZE + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| ContextBuilderError::validation(format!("{url}: bad gzip: {e}")))?;
+        if decoded.len() as u64 > MAX_RESPONSE_SIZE {
+            return Err(too_large(url, "over the limit once decompressed"));
+        }
+        body = decoded;
+    }
+
+    let xml = String::from_utf8_lossy(&body).into_owned();
+    if !xml.contains("<urlset") && !is_sitemap_index(&xml) {
+        return Err(ContextBuilderError::validation(format!(
+            "{url}: not a <urlset> or <sitemapindex> document"
+        )));
+    }
+    debug!(%url, bytes = xml.len(), "fetched sitemap");
+    Ok(xml)
+}
+
+fn too_large(url: &Url, size: &str) -> ContextBuilderError {
+    ContextBuilderError::validation(format!(
+        "{url}: sitemap too large ({size}, max {MAX_RESPONSE_SIZE} bytes)"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn urlset(base: &str, pages: &[&str]) -> String {
+        let urls: String = pages
+            .iter()
+            .map(|page| format!("<url><loc>{base}{page}</loc></url>"))
+            .collect();
+        format!(r#"<?xml version="1.0"?><urlset xmlns="x">{urls}</urlset>"#)
+    }
+
+    fn index(base: &str, sitemaps: &[&str]) -> String {
+        let entries: String = sitemaps
+            .iter()
+            .map(|s| format!("<sitemap><loc>{base}{s}</loc></sitemap>"))
+            .collect();
+        format!(r#"<?xml version="1.0"?><sitemapindex xmlns="x">{entries}</sitemapindex>"#)
+    }
+
+    async fn mount(server: &MockServer, at: &str, body: impl Into<Vec<u8>>) {
+        Mock::given(path(at))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body.into(), "application/xml"))
+            .mount(server)
+            .await;
+    }
+
+    fn paths(urls: &[Url]) -> Vec<&str> {
+        urls.iter().map(Url::path).collect()
+    }
+
+    #[tokio::test]
+    async fn discovers_urlset_pages() {
+        let server = MockServer::start().await;
+        let base = server.uri();
+        mount(&server, "/sitemap.xml", urlset(&base, &["/a", "/b", "/a"])).await;
+
+        let url = Url::parse(&format!("{base}/docs/intro")).unwrap();
+        let pages = discover_sitemap(&url, &DiscoveryOptions::default()).await.unwrap();
+        assert_eq!(paths(&pages), ["/a", "/b"]);
+    }
+
+    #[tokio::test]
+    async fn follows_nested_sitemap_indexes_in_order() {
+        let server = MockServer::start().await;
+        let base = server.uri();
+        mount(&server, "/sitemap.xml", index(&base, &["/docs.xml", "/blog-index.xml"])).await;
+        mount(&server, "/docs.xml", urlset(&base, &["/docs/1", "/docs/2"])).await;
+        mount(&server, "/blog-index.xml", index(&base, &["/blog.xml", "/missing.xml"])).await;
+        mount(&server, "/blog.xml", urlset(&base, &["/blog/1"])).await;
+
+        let url = Url::parse(&base).unwrap();
+        let pages = discover_sitemap(&url, &DiscoveryOptions::default()).await.unwrap();
+        assert_eq!(paths(&pages), ["/docs/1", "/docs/2", "/blog/1"]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_gzipped_sitemap() {
+        use std::io::Write;
+
+        let server = MockServer::start().await;
+        let base = server.uri();
+        let mut encoder =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(urlset(&base, &["/guide"]).as_bytes()).unwrap();
+        mount(&server, "/sitemap.xml.gz", encoder.finish().unwrap()).await;
+
+        let url = Url::parse(&base).unwrap();
+        let pages = discover_sitemap(&url, &DiscoveryOptions::default()).await.unwrap();
+        assert_eq!(paths(&pages), ["/guide"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_sitemaps_over_the_size_cap() {
+        use std::io::Write;
+
+        let server = MockServer::start().await;
+        let base = server.uri();
+        // A small gzip body that inflates past the cap
+        let padding = " ".repeat(MAX_RESPONSE_SIZE as usize);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        write!(encoder, "{}{padding}", urlset(&base, &["/bomb"])).unwrap();
+        mount(&server, "/sitemap.xml", encoder.finish().unwrap()).await;
+        mount(&server, "/sitemap.xml.gz", format!("{}{padding}", urlset(&base, &["/big"]))).await;
+
+        let url = Url::parse(&base).unwrap();
+        let pages = discover_sitemap(&url, &DiscoveryOptions::default()).await.unwrap();
+        assert!(pages.is_empty(), "{pages:?}");
+    }
+
+    #[tokio::test]
+    async fn missing_or_invalid_sitemap_yields_nothing() {
+        let server = MockServer::start().await;
+        Mock::given(path("/sitemap.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>Home</html>"))
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let pages = discover_sitemap(&url, &DiscoveryOptions::default()).await.unwrap();
+        assert!(pages.is_empty());
+
+        let opts = DiscoveryOptions {
+            offline: true,
+            ..Default::default()
+        };
+        let err = discover_sitemap(&url, &opts).await.unwrap_err();
+        assert!(err.to_string().contains("offline mode"), "{err}");
+    }
+}
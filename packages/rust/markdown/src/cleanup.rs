@@ -6,20 +6,95 @@
 use std::sync::LazyLock;
 
 use regex::Regex;
+use tracing::warn;
 use url::Url;
 
+use crate::fence::{map_prose, FenceTracker};
+use crate::{ConvertOptions, H1Policy, PassDiff};
+
+/// Options controlling individual cleanup passes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CleanupOptions<'a> {
+    /// How to handle pages with more than one H1.
+    pub h1_policy: H1Policy,
+    /// Known page title (used by [`H1Policy::PromoteTitle`]).
+    pub title: Option<&'a str>,
+    /// `(pattern, replacement)` regex rewrites applied to resolved link targets.
+    pub link_rewrites: &'a [(String, String)],
+    /// Collapse adjacent identical list items and links.
+    pub dedupe_adjacent: bool,
+    /// Headings may carry preserved `{#id}` anchors.
+    pub preserve_anchors: bool,
+}
+
+impl<'a> From<&'a ConvertOptions> for CleanupOptions<'a> {
+    fn from(opts: &'a ConvertOptions) -> Self {
+        Self {
+            h1_policy: opts.h1_policy,
+            title: opts.title.as_deref(),
+            link_rewrites: &opts.link_rewrites,
+            dedupe_adjacent: opts.dedupe_adjacent,
+            preserve_anchors: opts.preserve_anchors,
+        }
+    }
+}
+
 /// Run the full cleanup pipeline on raw Markdown text.
-pub(crate) fn run_pipeline(md: &str, base_url: Option<&Url>) -> String {
-    let mut result = md.to_string();
+pub(crate) fn run_pipeline(md: &str, base_url: Option<&Url>, opts: &CleanupOptions<'_>) -> String {
+    run_passes(md, base_url, opts, |_, _, _| {})
+}
+
+/// Run the cleanup pipeline, recording each pass's input and output.
+pub(crate) fn run_pipeline_traced(
+    md: &str,
+    base_url: Option<&Url>,
+    opts: &CleanupOptions<'_>,
+    trace: &mut Vec<PassDiff>,
+) -> String {
+    run_passes(md, base_url, opts, |pass, before, after| {
+        trace.push(PassDiff {
+            pass,
+            before: before.to_string(),
+            after: after.to_string(),
+        });
+    })
+}
 
-    result = normalize_headings(&result);
-    result = clean_blank_lines(&result);
-    result = fix_code_block_languages(&result);
-    result = strip_leftover_html(&result);
-    result = resolve_links(&result, base_url);
-    result = normalize_whitespace(&result);
-    result = ensure_trailing_newline(&result);
+/// A cleanup pass with its options bound.
+type Pass<'a> = dyn Fn(&str) -> String + 'a;
+
+/// Apply every pass in order, calling `observe(name, before, after)` after each.
+fn run_passes(
+    md: &str,
+    base_url: Option<&Url>,
+    opts: &CleanupOptions<'_>,
+    mut observe: impl FnMut(&'static str, &str, &str),
+) -> String {
+    let rewrites = compile_rewrites(opts.link_rewrites);
+    let headings = |md: &str| normalize_headings(md, opts.h1_policy, opts.title);
+    let links = |md: &str| resolve_links(md, base_url, &rewrites);
+    let mut passes: Vec<(&'static str, &Pass<'_>)> = vec![
+        ("normalize_headings", &headings),
+        ("clean_blank_lines", &clean_blank_lines),
+        ("fix_code_block_languages", &fix_code_block_languages),
+        ("strip_leftover_html", &strip_leftover_html),
+        ("resolve_links", &links),
+    ];
+    if opts.preserve_anchors {
+        passes.push(("unescape_heading_ids", &unescape_heading_ids));
+    }
+    if opts.dedupe_adjacent {
+        passes.push(("dedupe_adjacent", &dedupe_adjacent));
+    }
+    passes.push(("normalize_whitespace", &normalize_whitespace));
+    passes.push(("ensure_trailing_newline", &ensure_trailing_newline));
 
+    let mut result = md.to_string();
+    for (name, pass) in passes {
+        let next = pass(&result);
+        observe(name, &result, &next);
+        result = next;
+    }
     result
 }
 
@@ -27,16 +102,77 @@ pub(crate) fn run_pipeline(md: &str, base_url: Option<&Url>) -> String {
 // Pass 1: Normalize heading levels
 // ---------------------------------------------------------------------------
 
-/// Ensure there's at most one H1, and heading hierarchy is proper.
-fn normalize_headings(md: &str) -> String {
-    static H_RE: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(r"(?m)^(#{1,6})\s+(.+)$").expect("valid regex")
-    });
+/// Rewrite setext headings as ATX, then apply the configured [`H1Policy`].
+fn normalize_headings(md: &str, policy: H1Policy, title: Option<&str>) -> String {
+    let md = setext_to_atx(md);
+    match policy {
+        H1Policy::DemoteExtra => demote_extra_h1s(&md),
+        H1Policy::KeepAll => md,
+        H1Policy::PromoteTitle => promote_title(&md, title),
+    }
+}
+
+/// Matches an ATX heading line, capturing the hashes and the heading text.
+static H_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^(#{1,6})\s+(.+)$").expect("valid regex")
+});
+
+/// Matches a setext underline (`===` for H1, `---` for H2).
+static SETEXT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").expect("valid regex")
+});
 
+/// Matches lines that start a block other than a paragraph.
+static BLOCK_START_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?: {4}|\t| {0,3}(?:[#>|<]|[-*+](?:\s|$)|\d{1,9}[.)](?:\s|$)))").expect("valid regex")
+});
+
+/// Convert setext headings (`Title` underlined with `===` / `---`) to ATX.
+///
+/// The underlined paragraph becomes the heading text, its lines joined by
+/// spaces. A `---` line not preceded by a paragraph stays a thematic break.
+fn setext_to_atx(md: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    // Index in `lines` where the current paragraph started
+    let mut paragraph: Option<usize> = None;
+    let mut fences = FenceTracker::default();
+
+    for line in md.lines() {
+        if fences.is_code(line) {
+            paragraph = None;
+            lines.push(line.to_string());
+            continue;
+        }
+        if let (Some(start), Some(caps)) = (paragraph, SETEXT_RE.captures(line)) {
+            let text: Vec<String> = lines.drain(start..).map(|l| l.trim().to_string()).collect();
+            let hashes = if caps[1].starts_with('=') { "#" } else { "##" };
+            lines.push(format!("{hashes} {}", text.join(" ")));
+            paragraph = None;
+            continue;
+        }
+
+        if line.trim().is_empty() || BLOCK_START_RE.is_match(line) || SETEXT_RE.is_match(line) {
+            paragraph = None;
+        } else if paragraph.is_none() {
+            paragraph = Some(lines.len());
+        }
+        lines.push(line.to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Ensure there's at most one H1 by demoting every H1 after the first to H2.
+fn demote_extra_h1s(md: &str) -> String {
     let mut h1_count = 0;
     let mut lines: Vec<String> = Vec::new();
+    let mut fences = FenceTracker::default();
 
     for line in md.lines() {
+        if fences.is_code(line) {
+            lines.push(line.to_string());
+            continue;
+        }
         if let Some(caps) = H_RE.captures(line) {
             let hashes = &caps[1];
             let text = &caps[2];
@@ -58,17 +194,61 @@ fn normalize_headings(md: &str) -> String {
     lines.join("\n")
 }
 
+/// Make `title` the page's only H1 and shift every other heading down a level.
+///
+/// When no title is given, the first H1 is kept as the title. A leading H1
+/// whose text equals the title is consumed rather than duplicated.
+fn promote_title(md: &str, title: Option<&str>) -> String {
+    let mut fences = FenceTracker::default();
+    let first_h1 = md.lines().filter(|line| !fences.is_code(line)).find_map(|line| {
+        H_RE.captures(line)
+            .filter(|caps| &caps[1] == "#")
+            .map(|caps| caps[2].trim().to_string())
+    });
+    let Some(title) = title.map(str::to_string).or(first_h1) else {
+        return md.to_string();
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut title_consumed = false;
+    let mut fences = FenceTracker::default();
+
+    for line in md.lines() {
+        if fences.is_code(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+        if let Some(caps) = H_RE.captures(line) {
+            let level = caps[1].len();
+            let text = caps[2].trim();
+
+            if !title_consumed && level == 1 && text == title {
+                title_consumed = true;
+                continue;
+            }
+
+            let hashes = "#".repeat((level + 1).min(6));
+            lines.push(format!("{hashes} {text}"));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    let body = lines.join("\n");
+    format!("# {title}\n\n{}", body.trim_start_matches('\n'))
+}
+
 // ---------------------------------------------------------------------------
 // Pass 2: Clean up excessive blank lines
 // ---------------------------------------------------------------------------
 
-/// Collapse runs of 3+ blank lines into exactly 2.
+/// Collapse runs of 3+ blank lines into exactly 2 (code blocks are left alone).
 fn clean_blank_lines(md: &str) -> String {
     static MULTI_BLANK_RE: LazyLock<Regex> = LazyLock::new(|| {
         Regex::new(r"\n{4,}").expect("valid regex")
     });
 
-    MULTI_BLANK_RE.replace_all(md, "\n\n\n").to_string()
+    map_prose(md, |prose| MULTI_BLANK_RE.replace_all(prose, "\n\n\n").to_string())
 }
 
 // ---------------------------------------------------------------------------
@@ -81,10 +261,11 @@ fn clean_blank_lines(md: &str) -> String {
 fn fix_code_block_languages(md: &str) -> String {
     static LANG_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
         // Matches code fence with a class-like language prefix
-        Regex::new(r"(?m)^```(?:language-|lang-|highlight-)(\w+)").expect("valid regex")
+        Regex::new(r"(?m)^(\s*(?:`{3,}|~{3,}))(?:language-|lang-|highlight-)(\w+)")
+            .expect("valid regex")
     });
 
-    LANG_PREFIX_RE.replace_all(md, "```$1").to_string()
+    LANG_PREFIX_RE.replace_all(md, "$1$2").to_string()
 }
 
 // ---------------------------------------------------------------------------
@@ -98,17 +279,10 @@ fn fix_code_block_languages(md: &str) -> String {
 fn strip_leftover_html(md: &str) -> String {
     // Don't strip inside code blocks
     let mut result = String::new();
-    let mut in_code_block = false;
+    let mut fences = FenceTracker::default();
 
     for line in md.lines() {
-        if line.trim_start().starts_with("```") {
-            in_code_block = !in_code_block;
-            result.push_str(line);
-            result.push('\n');
-            continue;
-        }
-
-        if in_code_block {
+        if fences.is_code(line) {
             result.push_str(line);
             result.push('\n');
             continue;
@@ -144,21 +318,67 @@ fn strip_html_tags(line: &str) -> String {
 // ---------------------------------------------------------------------------
 
 /// Resolve relative URLs in Markdown links against a base URL.
-fn resolve_links(md: &str, base_url: Option<&Url>) -> String {
-    let Some(base) = base_url else {
+///
+/// Plain images are left as-is; linked images (`[![alt](src)](href)`) have
+/// both the image source and the link target resolved. Code blocks are
+/// never rewritten.
+fn resolve_links(md: &str, base_url: Option<&Url>, rewrites: &[(Regex, String)]) -> String {
+    if base_url.is_none() && rewrites.is_empty() {
         return md.to_string();
+    }
+
+    map_prose(md, |prose| resolve_prose_links(prose, base_url, rewrites))
+}
+
+/// Compile `(pattern, replacement)` link rewrites, skipping invalid patterns.
+fn compile_rewrites(rewrites: &[(String, String)]) -> Vec<(Regex, String)> {
+    rewrites
+        .iter()
+        .filter_map(|(pattern, replacement)| match Regex::new(pattern) {
+            Ok(re) => Some((re, replacement.clone())),
+            Err(e) => {
+                warn!(pattern, error = %e, "invalid link rewrite pattern, ignoring");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Resolve a link target against `base`, then apply every rewrite in order.
+fn rewrite_href(base: Option<&Url>, href: &str, rewrites: &[(Regex, String)]) -> String {
+    let mut href = match base {
+        Some(base) => resolve_href(base, href),
+        None => href.to_string(),
     };
+    for (re, replacement) in rewrites {
+        href = re.replace(&href, replacement.as_str()).into_owned();
+    }
+    href
+}
 
+/// Resolve the links in a prose-only slice of Markdown.
+fn resolve_prose_links(md: &str, base: Option<&Url>, rewrites: &[(Regex, String)]) -> String {
     static LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
-        // Match [text](url) — we'll filter out image links (![...]) in the replacement
-        Regex::new(r"\[([^\]]*)\]\(([^)]+)\)").expect("valid regex")
+        // Linked image first so it wins over the plain link at the same position,
+        // then [text](url) — image links (![...]) are filtered in the replacement
+        Regex::new(r"\[!\[([^\]]*)\]\(([^)\s]+)\)\]\(([^)]+)\)|\[([^\]]*)\]\(([^)]+)\)")
+            .expect("valid regex")
     });
 
     LINK_RE
         .replace_all(md, |caps: &regex::Captures| {
+            if let (Some(alt), Some(src), Some(href)) = (caps.get(1), caps.get(2), caps.get(3)) {
+                return format!(
+                    "[![{}]({})]({})",
+                    alt.as_str(),
+                    base.map_or_else(|| src.as_str().to_string(), |b| resolve_href(b, src.as_str())),
+                    rewrite_href(base, href.as_str(), rewrites)
+                );
+            }
+
             let full_match = caps.get(0).unwrap();
-            let text = &caps[1];
-            let href = &caps[2];
+            let text = &caps[4];
+            let href = &caps[5];
 
             // Check if this is an image link by looking at the char before the match
             let start = full_match.start();
@@ -167,26 +387,109 @@ fn resolve_links(md: &str, base_url: Option<&Url>) -> String {
                 return caps[0].to_string();
             }
 
-            // Skip absolute URLs and anchors
-            if href.starts_with("http://")
-                || href.starts_with("https://")
-                || href.starts_with('#')
-                || href.starts_with("mailto:")
-            {
-                return format!("[{text}]({href})");
-            }
-
-            // Resolve relative URL
-            match base.join(href) {
-                Ok(resolved) => format!("[{text}]({})", resolved),
-                Err(_) => format!("[{text}]({href})"),
-            }
+            format!("[{text}]({})", rewrite_href(base, href, rewrites))
         })
         .to_string()
 }
 
+/// Resolve a single link target, leaving absolute URLs, anchors, and
+/// `mailto:` links untouched.
+fn resolve_href(base: &Url, href: &str) -> String {
+    if href.starts_with("http://")
+        || href.starts_with("https://")
+        || href.starts_with('#')
+        || href.starts_with("mailto:")
+    {
+        return href.to_string();
+    }
+
+    match base.join(href) {
+        Ok(resolved) => resolved.to_string(),
+        Err(_) => href.to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Pass 6 (optional): Unescape preserved heading ids
+// ---------------------------------------------------------------------------
+
+/// Remove the backslashes htmd adds inside `{#id}` heading markers
+/// (`{#setup\_1}` becomes `{#setup_1}`).
+fn unescape_heading_ids(md: &str) -> String {
+    static ID_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?m)^(#{1,6}[ \t].*\{#)([^\s{}]+)\}[ \t]*$").expect("valid regex")
+    });
+
+    map_prose(md, |prose| {
+        ID_RE
+            .replace_all(prose, |caps: &regex::Captures| {
+                format!("{}{}}}", &caps[1], caps[2].replace('\\', ""))
+            })
+            .into_owned()
+    })
+}
+
 // ---------------------------------------------------------------------------
-// Pass 6: Normalize whitespace
+// Pass 7 (optional): Collapse adjacent duplicates
+// ---------------------------------------------------------------------------
+
+/// Matches a bullet or ordered list item line.
+static LIST_ITEM_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:[-*+]|\d{1,9}[.)])\s+\S").expect("valid regex")
+});
+
+/// Drop list items identical to the item right above them, and links
+/// repeated back to back (`[A](/a) [A](/a)`).
+///
+/// Only exact, immediately adjacent duplicates are removed: items separated
+/// by anything else, and links separated by more than whitespace, are kept.
+fn dedupe_adjacent(md: &str) -> String {
+    map_prose(md, |prose| {
+        let mut out = String::with_capacity(prose.len());
+        let mut prev_item: Option<&str> = None;
+        for line in prose.split_inclusive('\n') {
+            let content = line.trim_end();
+            if LIST_ITEM_RE.is_match(content) {
+                if prev_item == Some(content) {
+                    continue;
+                }
+                prev_item = Some(content);
+            } else {
+                prev_item = None;
+            }
+            out.push_str(&dedupe_adjacent_links(line));
+        }
+        out
+    })
+}
+
+/// Remove links that repeat the link just before them on `line`.
+fn dedupe_adjacent_links(line: &str) -> String {
+    static LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"!?\[[^\]]*\]\([^)\s]*\)").expect("valid regex")
+    });
+
+    let mut out = String::with_capacity(line.len());
+    let mut copied = 0;
+    let mut prev: Option<(&str, usize)> = None;
+    for m in LINK_RE.find_iter(line) {
+        let link = m.as_str();
+        let repeated = prev.is_some_and(|(text, end)| {
+            text == link && line[end..m.start()].trim().is_empty()
+        });
+        if repeated {
+            // Keep the text up to the previous link, skip the gap and the copy
+            out.push_str(&line[copied..prev.map_or(0, |(_, end)| end)]);
+            copied = m.end();
+        }
+        prev = (!link.starts_with('!')).then_some((link, m.end()));
+    }
+    out.push_str(&line[copied..]);
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Pass 8: Normalize whitespace
 // ---------------------------------------------------------------------------
 
 /// Clean up trailing whitespace on lines and normalize line endings.
@@ -198,7 +501,7 @@ fn normalize_whitespace(md: &str) -> String {
 }
 
 // ---------------------------------------------------------------------------
-// Pass 7: Ensure trailing newline
+// Pass 9: Ensure trailing newline
 // ---------------------------------------------------------------------------
 
 /// Ensure the file ends with exactly one newline.
@@ -215,10 +518,12 @@ fn ensure_trailing_newline(md: &str) -> String {
 mod tests {
     use super::*;
 
+    const TWO_H1_DOC: &str = "# First\n\nIntro\n\n## Detail\n\n# Second\n\nMore";
+
     #[test]
     fn normalize_headings_demotes_duplicate_h1() {
         let input = "# Title\n\nSome text\n\n# Another Title\n\nMore text";
-        let result = normalize_headings(input);
+        let result = normalize_headings(input, H1Policy::DemoteExtra, None);
         assert_eq!(
             result,
             "# Title\n\nSome text\n\n## Another Title\n\nMore text"
@@ -228,10 +533,69 @@ mod tests {
     #[test]
     fn normalize_headings_keeps_single_h1() {
         let input = "# Only One\n\n## Sub\n\n### Deep";
-        let result = normalize_headings(input);
+        let result = normalize_headings(input, H1Policy::DemoteExtra, None);
         assert_eq!(result, input);
     }
 
+    #[test]
+    fn h1_policy_demote_extra_two_h1s() {
+        let result = normalize_headings(TWO_H1_DOC, H1Policy::DemoteExtra, None);
+        assert_eq!(result, "# First\n\nIntro\n\n## Detail\n\n## Second\n\nMore");
+    }
+
+    #[test]
+    fn h1_policy_keep_all_two_h1s() {
+        let result = normalize_headings(TWO_H1_DOC, H1Policy::KeepAll, None);
+        assert_eq!(result, TWO_H1_DOC);
+    }
+
+    #[test]
+    fn h1_policy_promote_title_two_h1s() {
+        let result = normalize_headings(TWO_H1_DOC, H1Policy::PromoteTitle, Some("Page"));
+        assert_eq!(
+            result,
+            "# Page\n\n## First\n\nIntro\n\n### Detail\n\n## Second\n\nMore"
+        );
+    }
+
+    #[test]
+    fn h1_policy_promote_title_uses_first_h1_without_title() {
+        let result = normalize_headings(TWO_H1_DOC, H1Policy::PromoteTitle, None);
+        assert_eq!(
+            result,
+            "# First\n\nIntro\n\n### Detail\n\n## Second\n\nMore"
+        );
+    }
+
+    #[test]
+    fn setext_headings_become_atx() {
+        let input = "Title\n=====\n\nIntro text\n\nSection\n-------\n\nBody";
+        let result = normalize_headings(input, H1Policy::KeepAll, None);
+        assert_eq!(result, "# Title\n\nIntro text\n\n## Section\n\nBody");
+    }
+
+    #[test]
+    fn setext_h1_participates_in_demotion() {
+        let input = "# First\n\nIntro\n\nSecond\n======\n\nMore";
+        let result = normalize_headings(input, H1Policy::DemoteExtra, None);
+        assert_eq!(result, "# First\n\nIntro\n\n## Second\n\nMore");
+
+        let result = normalize_headings("Only\n===\n\nText", H1Policy::PromoteTitle, Some("Page"));
+        assert_eq!(result, "# Page\n\n## Only\n\nText");
+    }
+
+    #[test]
+    fn setext_leaves_breaks_lists_and_code_alone() {
+        // Thematic break after a blank line, list items, and fenced code
+        let input = "Text\n\n---\n\n- item\n---\n\n```\ncode\n===\n```";
+        let result = normalize_headings(input, H1Policy::KeepAll, None);
+        assert_eq!(result, input);
+
+        // A multi-line paragraph becomes one heading
+        let result = normalize_headings("Long\ntitle\n---", H1Policy::KeepAll, None);
+        assert_eq!(result, "## Long title");
+    }
+
     #[test]
     fn clean_blank_lines_collapses_excess() {
         let input = "Line 1\n\n\n\n\nLine 2";
@@ -276,11 +640,51 @@ mod tests {
         assert!(result.contains("<div>Preserved</div>"));
     }
 
+    #[test]
+    fn strip_leftover_html_handles_tilde_and_indented_fences() {
+        let input = "~~~html\n<div>tilde</div>\n```\n<div>still code</div>\n~~~\n\n\
+                     1. Step\n\n   ```html\n   <span>indented</span>\n   ```\n\n<div>prose</div>";
+        let result = strip_leftover_html(input);
+        assert!(result.contains("<div>tilde</div>"));
+        assert!(result.contains("<div>still code</div>"));
+        assert!(result.contains("   <span>indented</span>"));
+        assert!(result.ends_with("\nprose"), "{result}");
+    }
+
+    #[test]
+    fn strip_leftover_html_backtick_info_string_is_not_a_fence() {
+        // "```js `x`" is inline code, so it must not open a block
+        let input = "```js `x`\n<div>prose</div>";
+        assert_eq!(strip_leftover_html(input), "```js `x`\nprose");
+    }
+
+    #[test]
+    fn prose_passes_leave_code_untouched() {
+        let base = Url::parse("https://docs.example.com/guide/").unwrap();
+        let input = "# Title\n\n````md\n# Second\n```\n[rel](page)\n```\n````\n\n[rel](page)";
+
+        let demoted = normalize_headings(input, H1Policy::DemoteExtra, None);
+        assert!(demoted.contains("\n# Second\n"));
+
+        let resolved = resolve_links(input, Some(&base), &[]);
+        assert!(resolved.contains("```\n[rel](page)\n```"));
+        assert!(resolved.ends_with("[rel](https://docs.example.com/guide/page)"));
+    }
+
+    #[test]
+    fn fix_code_block_languages_tilde_and_indented() {
+        let input = "~~~lang-python\nx\n~~~\n  ```language-js\ny\n  ```";
+        assert_eq!(
+            fix_code_block_languages(input),
+            "~~~python\nx\n~~~\n  ```js\ny\n  ```"
+        );
+    }
+
     #[test]
     fn resolve_links_absolute_untouched() {
         let base = Url::parse("https://docs.example.com/guide/").unwrap();
         let input = "[Link](https://other.com/page)";
-        let result = resolve_links(input, Some(&base));
+        let result = resolve_links(input, Some(&base), &[]);
         assert_eq!(result, "[Link](https://other.com/page)");
     }
 
@@ -288,7 +692,7 @@ mod tests {
     fn resolve_links_relative_resolved() {
         let base = Url::parse("https://docs.example.com/guide/intro").unwrap();
         let input = "[Next](/api/reference)";
-        let result = resolve_links(input, Some(&base));
+        let result = resolve_links(input, Some(&base), &[]);
         assert_eq!(result, "[Next](https://docs.example.com/api/reference)");
     }
 
@@ -296,10 +700,50 @@ mod tests {
     fn resolve_links_anchor_untouched() {
         let base = Url::parse("https://docs.example.com/page").unwrap();
         let input = "[Section](#section-1)";
-        let result = resolve_links(input, Some(&base));
+        let result = resolve_links(input, Some(&base), &[]);
         assert_eq!(result, "[Section](#section-1)");
     }
 
+    #[test]
+    fn resolve_links_linked_image_resolves_src_and_href() {
+        let base = Url::parse("https://docs.example.com/guide/intro").unwrap();
+        let input = "See [![Logo](img/logo.png)](/home) here";
+        let result = resolve_links(input, Some(&base), &[]);
+        assert_eq!(
+            result,
+            "See [![Logo](https://docs.example.com/guide/img/logo.png)](https://docs.example.com/home) here"
+        );
+    }
+
+    #[test]
+    fn resolve_links_applies_rewrites() {
+        let base = Url::parse("https://docs.example.com/docs/intro").unwrap();
+        let rewrites = compile_rewrites(&[
+            (r"^(https://docs\.example\.com/docs)/old/".into(), "$1/".into()),
+            ("[".into(), "broken".into()),
+        ]);
+        assert_eq!(rewrites.len(), 1, "invalid patterns are skipped");
+
+        let input = "[X](/docs/old/x) and [Y](/docs/y) and ![Old](/docs/old/x.png)";
+        assert_eq!(
+            resolve_links(input, Some(&base), &rewrites),
+            "[X](https://docs.example.com/docs/x) and [Y](https://docs.example.com/docs/y) \
+             and ![Old](/docs/old/x.png)"
+        );
+
+        // Rewrites apply even without a base URL
+        let rewrites = compile_rewrites(&[("^/docs/old/".into(), "/docs/".into())]);
+        assert_eq!(resolve_links("[X](/docs/old/x)", None, &rewrites), "[X](/docs/x)");
+    }
+
+    #[test]
+    fn resolve_links_plain_image_untouched() {
+        let base = Url::parse("https://docs.example.com/guide/intro").unwrap();
+        let input = "![Diagram](img/diagram.png)";
+        let result = resolve_links(input, Some(&base), &[]);
+        assert_eq!(result, "![Diagram](img/diagram.png)");
+    }
+
     #[test]
     fn normalize_whitespace_trims_trailing() {
         let input = "Line 1   \nLine 2\t\nLine 3";
@@ -325,7 +769,7 @@ mod tests {
     fn full_pipeline_cleans_markdown() {
         let input = "# Title\n\n\n\n\n\n## Section\n\n<div>Some content</div>\n\n```language-python\nprint('hi')\n```\n\nEnd";
         let base = Url::parse("https://example.com/page").unwrap();
-        let result = run_pipeline(input, Some(&base));
+        let result = run_pipeline(input, Some(&base), &CleanupOptions::default());
 
         // Excessive blank lines collapsed
         assert!(!result.contains("\n\n\n\n"));
@@ -338,4 +782,42 @@ mod tests {
         // Ends with newline
         assert!(result.ends_with('\n'));
     }
+
+    #[test]
+    fn dedupe_adjacent_collapses_repeated_list_items() {
+        let input = "- [Install](/install)\n- [Install](/install)\n- [Usage](/usage)\n";
+        assert_eq!(dedupe_adjacent(input), "- [Install](/install)\n- [Usage](/usage)\n");
+    }
+
+    #[test]
+    fn dedupe_adjacent_keeps_non_adjacent_repeats() {
+        let input = "- Install\n- Usage\n- Install\n\n- Usage\n\n- Usage\n";
+        assert_eq!(dedupe_adjacent(input), input);
+
+        let code = "```\n- same\n- same\n```\n";
+        assert_eq!(dedupe_adjacent(code), code);
+    }
+
+    #[test]
+    fn dedupe_adjacent_collapses_back_to_back_links() {
+        assert_eq!(
+            dedupe_adjacent("See [API](/api) [API](/api)[API](/api), then [API](/api)."),
+            "See [API](/api), then [API](/api)."
+        );
+        assert_eq!(
+            dedupe_adjacent("[A](/a) [B](/b) ![A](/a) ![A](/a)"),
+            "[A](/a) [B](/b) ![A](/a) ![A](/a)"
+        );
+    }
+
+    #[test]
+    fn dedupe_adjacent_runs_only_when_enabled() {
+        let input = "- Item\n- Item\n";
+        let opts = CleanupOptions {
+            dedupe_adjacent: true,
+            ..Default::default()
+        };
+        assert_eq!(run_pipeline(input, None, &CleanupOptions::default()), input);
+        assert_eq!(run_pipeline(input, None, &opts), "- Item\n");
+    }
 }
@@ -0,0 +1,127 @@
+//! Readability-style main-content detection.
+//!
+//! Used when a page has none of the known content containers: paragraphs are
+//! scored by length and punctuation, each score is credited to the
+//! paragraph's parent (and half to its grandparent), and the best-scoring
+//! block, discounted by how much of its text is link text, is taken as the
+//! main content. Blocks inside obvious chrome (`nav`, `footer`, elements
+//! classed `menu`, `sidebar`, ...) never score.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+/// Class/id fragments that mark an element as page chrome.
+static CHROME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)nav|menu|header|footer|sidebar|breadcrumb|banner|cookie|share|social|comment|related|promo|sponsor|widget",
+    )
+    .expect("valid regex")
+});
+
+/// Tags whose content is never main content.
+const CHROME_TAGS: [&str; 8] = [
+    "nav", "header", "footer", "aside", "script", "style", "form", "noscript",
+];
+
+/// Paragraphs shorter than this (in characters) are not scored.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// A winning block must score at least this much to be trusted.
+const MIN_SCORE: f64 = 5.0;
+
+/// Find the densest block of prose in `doc`, if there is a convincing one.
+pub(crate) fn main_content(doc: &Html) -> Option<ElementRef<'_>> {
+    let paragraphs = Selector::parse("p, pre, td, blockquote").expect("valid selector");
+
+    let mut scores = HashMap::new();
+    for p in doc.select(&paragraphs) {
+        if in_chrome(p) {
+            continue;
+        }
+        let text: String = p.text().collect();
+        let len = text.trim().chars().count();
+        if len < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+
+        let parent = p.parent().and_then(ElementRef::wrap);
+        if let Some(parent) = parent {
+            *scores.entry(parent.id()).or_insert(0.0) += score;
+            if let Some(grandparent) = parent.parent().and_then(ElementRef::wrap) {
+                *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+            }
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let el = ElementRef::wrap(doc.tree.get(id)?)?;
+            Some((el, score * (1.0 - link_density(el))))
+        })
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(el, _)| el)
+}
+
+/// Whether `el` or one of its ancestors is navigation or other chrome.
+fn in_chrome(el: ElementRef<'_>) -> bool {
+    std::iter::once(el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .any(|e| {
+            let v = e.value();
+            CHROME_TAGS.contains(&v.name())
+                || v.attr("role").is_some_and(|r| r == "navigation")
+                || v.id().is_some_and(|id| CHROME_RE.is_match(id))
+                || v.classes().any(|c| CHROME_RE.is_match(c))
+        })
+}
+
+/// Share of `el`'s text that sits inside links.
+fn link_density(el: ElementRef<'_>) -> f64 {
+    let total: usize = el.text().map(str::len).sum();
+    if total == 0 {
+        return 1.0;
+    }
+    let links = Selector::parse("a").expect("valid selector");
+    let linked: usize = el.select(&links).flat_map(|a| a.text()).map(str::len).sum();
+    linked as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_dense_block_over_link_chrome() {
+        let html = r#"<html><body>
+            <div class="topbar"><a href="/">Home</a> <a href="/docs">Docs</a></div>
+            <div class="menu"><ul>
+                <li><p><a href="/a">A very long menu entry that links somewhere else</a></p></li>
+                <li><p><a href="/b">Another very long menu entry, linking elsewhere</a></p></li>
+            </ul></div>
+            <div id="stuff">
+                <h1>Widget Guide</h1>
+                <p>Widgets are configured through a single file, which lives next to your project root.</p>
+                <p>Each widget has a name, a size, and a colour, all of which are optional.</p>
+            </div>
+            <div class="legal"><p>Copyright 2024, Example Corp. All rights reserved worldwide.</p></div>
+        </body></html>"#;
+        let doc = Html::parse_document(html);
+
+        let block = main_content(&doc).expect("content block");
+        assert_eq!(block.value().id(), Some("stuff"));
+    }
+
+    #[test]
+    fn no_block_without_enough_prose() {
+        let doc = Html::parse_document(
+            r#"<html><body><div><a href="/a">Link</a><p>Short.</p></div></body></html>"#,
+        );
+        assert!(main_content(&doc).is_none());
+    }
+}
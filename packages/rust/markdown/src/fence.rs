@@ -0,0 +1,187 @@
+//! Fenced code block detection shared by the cleanup passes.
+//!
+//! Follows the CommonMark fence rules: a fence is a run of 3+ backticks or
+//! tildes, a backtick fence's info string may not itself contain backticks,
+//! and a block only closes on a line of the same character that is at least
+//! as long as the opener (or at the end of the document). Leading indentation
+//! is ignored so fences nested in list items are recognised too.
+
+/// An open code fence: its character and run length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fence {
+    marker: u8,
+    len: usize,
+}
+
+/// Parse `line` as an opening fence.
+fn opening_fence(line: &str) -> Option<Fence> {
+    let trimmed = line.trim_start();
+    let marker = *trimmed.as_bytes().first()?;
+    if marker != b'`' && marker != b'~' {
+        return None;
+    }
+
+    let len = trimmed.bytes().take_while(|&b| b == marker).count();
+    if len < 3 {
+        return None;
+    }
+    // ```js `x` is inline code, not a fence
+    if marker == b'`' && trimmed[len..].contains('`') {
+        return None;
+    }
+
+    Some(Fence { marker, len })
+}
+
+/// Whether `line` closes `fence`: same character, at least as long, nothing after.
+fn closes(fence: Fence, line: &str) -> bool {
+    let trimmed = line.trim();
+    let len = trimmed.bytes().take_while(|&b| b == fence.marker).count();
+    len >= fence.len && len == trimmed.len()
+}
+
+/// Line-by-line code fence state.
+#[derive(Debug, Default)]
+pub(crate) struct FenceTracker {
+    open: Option<Fence>,
+}
+
+impl FenceTracker {
+    /// Feed the next line (without its newline) and report whether it belongs
+    /// to a code block — fence lines included.
+    pub(crate) fn is_code(&mut self, line: &str) -> bool {
+        match self.open {
+            Some(fence) => {
+                if closes(fence, line) {
+                    self.open = None;
+                }
+                true
+            }
+            None => {
+                self.open = opening_fence(line);
+                self.open.is_some()
+            }
+        }
+    }
+}
+
+/// A contiguous run of prose or code in a Markdown document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Segment<'a> {
+    /// Byte offset of the segment in the source.
+    pub offset: usize,
+    /// Segment text, including line endings.
+    pub text: &'a str,
+    /// Whether this is a fenced code block (fence lines included).
+    pub code: bool,
+}
+
+/// Split `md` into alternating prose and code segments covering every byte.
+pub(crate) fn segments(md: &str) -> Vec<Segment<'_>> {
+    let mut out: Vec<Segment<'_>> = Vec::new();
+    let mut tracker = FenceTracker::default();
+    let mut offset = 0;
+
+    for line in md.split_inclusive('\n') {
+        let code = tracker.is_code(line.trim_end_matches(['\n', '\r']));
+        match out.last_mut() {
+            Some(last) if last.code == code => {
+                last.text = &md[last.offset..offset + line.len()];
+            }
+            _ => out.push(Segment {
+                offset,
+                text: line,
+                code,
+            }),
+        }
+        offset += line.len();
+    }
+
+    out
+}
+
+/// Apply `f` to every prose segment of `md`, copying code blocks verbatim.
+pub(crate) fn map_prose(md: &str, mut f: impl FnMut(&str) -> String) -> String {
+    segments(md)
+        .into_iter()
+        .map(|seg| if seg.code { seg.text.to_string() } else { f(seg.text) })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_lines(md: &str) -> Vec<&str> {
+        let mut tracker = FenceTracker::default();
+        md.lines().filter(|line| tracker.is_code(line)).collect()
+    }
+
+    #[test]
+    fn tilde_fences() {
+        let md = "text\n~~~python\n# comment\n```\nstill code\n~~~\nafter";
+        assert_eq!(
+            code_lines(md),
+            vec!["~~~python", "# comment", "```", "still code", "~~~"]
+        );
+    }
+
+    #[test]
+    fn indented_fences() {
+        let md = "- item\n\n    ```sh\n    <div>x</div>\n    ```\n\n<div>prose</div>";
+        assert_eq!(
+            code_lines(md),
+            vec!["    ```sh", "    <div>x</div>", "    ```"]
+        );
+    }
+
+    #[test]
+    fn longer_fence_contains_shorter_one() {
+        let md = "````md\n```js\nlet a;\n```\n````\nprose";
+        assert_eq!(code_lines(md).len(), 5);
+        assert_eq!(segments(md).last().unwrap().text, "prose");
+    }
+
+    #[test]
+    fn backticks_in_info_string_are_not_a_fence() {
+        // "```x`y`" is inline code, so the following text stays prose
+        let md = "```x `y`\n# heading\n``` text `z`";
+        assert!(code_lines(md).is_empty());
+
+        // A closing fence may not carry an info string
+        let md = "```\ncode\n``` not closed\nstill code\n```\nprose";
+        assert_eq!(code_lines(md).len(), 5);
+    }
+
+    #[test]
+    fn unclosed_fence_runs_to_end() {
+        let md = "intro\n```\ncode\nmore";
+        let segs = segments(md);
+        assert_eq!(segs.len(), 2);
+        assert!(segs[1].code);
+        assert_eq!(segs[1].text, "```\ncode\nmore");
+    }
+
+    #[test]
+    fn segments_are_byte_accurate() {
+        let md = "a\n\n```rs\nfn x() {}\n```\nb\n~~~\nc\n~~~\n";
+        let segs = segments(md);
+        assert_eq!(segs.len(), 4);
+        for seg in &segs {
+            assert_eq!(&md[seg.offset..seg.offset + seg.text.len()], seg.text);
+        }
+        assert_eq!(segs[1].text, "```rs\nfn x() {}\n```\n");
+        assert_eq!(segs.iter().map(|s| s.text).collect::<String>(), md);
+    }
+
+    #[test]
+    fn map_prose_skips_code() {
+        let md = "<div>a</div>\n```\n<div>b</div>\n```\n";
+        let out = map_prose(md, |s| s.replace("<div>", "").replace("</div>", ""));
+        assert_eq!(out, "a\n```\n<div>b</div>\n```\n");
+    }
+}
@@ -0,0 +1,300 @@
+//! Heading extraction for converted Markdown pages.
+//!
+//! Provides a per-page heading outline (level, text, anchor slug, byte offset)
+//! that other features build on: in-page TOCs, section extraction, and
+//! section-level navigation.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::fence::FenceTracker;
+
+/// A single ATX heading found in a Markdown document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Heading level (1–6).
+    pub level: u8,
+    /// Heading text with inline Markdown (links, emphasis, code) removed.
+    pub text: String,
+    /// GitHub-style anchor slug, de-duplicated within the page (`intro`, `intro-1`, ...).
+    pub anchor: String,
+    /// Byte offset of the start of the heading line in the source.
+    pub offset: usize,
+}
+
+/// A slice of a page between two headings of the split level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Heading text that opens the section (`None` for content before the first heading).
+    pub title: Option<String>,
+    /// Anchor slug of the opening heading (`None` for the preamble).
+    pub anchor: Option<String>,
+    /// Byte offset of the section start in the source.
+    pub offset: usize,
+    /// Section Markdown, including its opening heading line.
+    pub content: String,
+}
+
+/// Matches an ATX heading line (up to 3 spaces of indentation).
+static ATX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^ {0,3}(#{1,6})[ \t]+(.*?)(?:[ \t]+#+)?[ \t]*$").expect("valid regex")
+});
+
+/// Matches an explicit `{#id}` anchor at the end of a heading's text.
+static ID_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[ \t]*\{#([^\s{}]+)\}$").expect("valid regex")
+});
+
+/// Split a heading's text into the text proper and its explicit `{#id}`
+/// anchor, if it ends with one.
+pub(crate) fn split_heading_id(text: &str) -> (&str, Option<&str>) {
+    match ID_ATTR_RE.captures(text) {
+        Some(caps) => {
+            let whole = caps.get(0).expect("group 0");
+            (&text[..whole.start()], caps.get(1).map(|id| id.as_str()))
+        }
+        None => (text, None),
+    }
+}
+
+/// Parse all ATX headings in `md`, skipping anything inside fenced code blocks.
+///
+/// A heading ending in `{#id}` (a preserved source anchor) uses `id` as its
+/// anchor instead of a generated slug.
+pub fn parse_headings(md: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut fences = FenceTracker::default();
+    let mut offset = 0;
+
+    for line in md.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if fences.is_code(line) {
+            continue;
+        }
+
+        let Some(caps) = ATX_RE.captures(line) else {
+            continue;
+        };
+
+        let (text, explicit_id) = split_heading_id(caps[2].trim());
+        let text = strip_inline_markdown(text);
+        if text.is_empty() {
+            continue;
+        }
+        let anchor = match explicit_id {
+            Some(id) => id.to_string(),
+            None => {
+                let base = slugify_heading(&text);
+                match seen.get(&base).copied() {
+                    // Skip suffixes already taken, e.g. by an explicit id
+                    Some(mut count) => loop {
+                        count += 1;
+                        let candidate = format!("{base}-{count}");
+                        if !seen.contains_key(&candidate) {
+                            seen.insert(base, count);
+                            break candidate;
+                        }
+                    },
+                    None => base,
+                }
+            }
+        };
+        // Explicit ids count as taken too, so no generated slug repeats one
+        seen.entry(anchor.clone()).or_insert(0);
+
+        headings.push(Heading {
+            level: caps[1].len() as u8,
+            text,
+            anchor,
+            offset: line_start,
+        });
+    }
+
+    headings
+}
+
+/// Split `md` into sections at every heading of exactly `level`.
+///
+/// Deeper headings stay inside their parent section; shallower headings (e.g.
+/// the page H1) are kept with the preceding text. Leading YAML frontmatter is
+/// skipped, and a blank preamble is dropped.
+pub fn split_sections(md: &str, level: u8) -> Vec<Section> {
+    let body_start = frontmatter_len(md);
+    let boundaries: Vec<Heading> = parse_headings(md)
+        .into_iter()
+        .filter(|h| h.level == level && h.offset >= body_start)
+        .collect();
+
+    let mut sections = Vec::new();
+    let first = boundaries.first().map_or(md.len(), |h| h.offset);
+    let preamble = &md[body_start..first];
+    if !preamble.trim().is_empty() {
+        sections.push(Section {
+            title: None,
+            anchor: None,
+            offset: body_start,
+            content: preamble.trim().to_string(),
+        });
+    }
+
+    for (i, heading) in boundaries.iter().enumerate() {
+        let end = boundaries.get(i + 1).map_or(md.len(), |next| next.offset);
+        sections.push(Section {
+            title: Some(heading.text.clone()),
+            anchor: Some(heading.anchor.clone()),
+            offset: heading.offset,
+            content: md[heading.offset..end].trim().to_string(),
+        });
+    }
+
+    sections
+}
+
+/// Byte length of a leading `---` YAML frontmatter block (0 if absent).
+pub(crate) fn frontmatter_len(md: &str) -> usize {
+    if !md.starts_with("---\n") {
+        return 0;
+    }
+    match md[4..].find("\n---\n") {
+        Some(end) => 4 + end + 5,
+        None => 0,
+    }
+}
+
+/// Generate a GitHub-style anchor slug for a heading.
+///
+/// Lowercases, keeps alphanumerics, `-` and `_`, turns spaces into `-`, and
+/// drops all other punctuation.
+pub fn slugify_heading(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Reduce inline Markdown in heading text to its visible text.
+fn strip_inline_markdown(text: &str) -> String {
+    static LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").expect("valid regex")
+    });
+
+    LINK_RE
+        .replace_all(text, "$1")
+        .chars()
+        .filter(|c| !matches!(c, '`' | '*'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_atx_headings_with_levels_and_offsets() {
+        let md = "# Title\n\nIntro text.\n\n## Getting Started\n\n### Install ###\n";
+        let headings = parse_headings(md);
+
+        assert_eq!(headings.len(), 3);
+        assert_eq!(headings[0].level, 1);
+        assert_eq!(headings[0].text, "Title");
+        assert_eq!(headings[0].offset, 0);
+        assert_eq!(headings[1].level, 2);
+        assert_eq!(headings[1].text, "Getting Started");
+        assert_eq!(&md[headings[1].offset..], "## Getting Started\n\n### Install ###\n");
+        assert_eq!(headings[2].level, 3);
+        assert_eq!(headings[2].text, "Install");
+    }
+
+    #[test]
+    fn ignores_hashes_inside_code_fences() {
+        let md = "# Real\n\n```bash\n# not a heading\n```\n\n~~~\n## also not\n~~~\n\n## Also Real\n";
+        let headings = parse_headings(md);
+
+        let texts: Vec<&str> = headings.iter().map(|h| h.text.as_str()).collect();
+        assert_eq!(texts, vec!["Real", "Also Real"]);
+    }
+
+    #[test]
+    fn requires_space_after_hashes() {
+        let headings = parse_headings("#hashtag\n\n####### seven\n\n## Ok\n");
+        assert_eq!(headings.len(), 1);
+        assert_eq!(headings[0].text, "Ok");
+    }
+
+    #[test]
+    fn generates_github_style_slugs() {
+        assert_eq!(slugify_heading("Getting Started"), "getting-started");
+        assert_eq!(slugify_heading("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(slugify_heading("snake_case and-dash"), "snake_case-and-dash");
+    }
+
+    #[test]
+    fn slugs_strip_inline_markdown_and_dedupe() {
+        let md = "## The `Client` [API](/api)\n\n## Example\n\n## Example\n";
+        let headings = parse_headings(md);
+
+        assert_eq!(headings[0].text, "The Client API");
+        assert_eq!(headings[0].anchor, "the-client-api");
+        assert_eq!(headings[1].anchor, "example");
+        assert_eq!(headings[2].anchor, "example-1");
+    }
+
+    #[test]
+    fn generated_slugs_do_not_collide_with_explicit_ids() {
+        let md = "## Setup {#setup}\n\n## Install {#example-1}\n\n## Setup\n\n\
+                  ## Example\n\n## Example\n\n## Example\n";
+        let anchors: Vec<String> = parse_headings(md).into_iter().map(|h| h.anchor).collect();
+
+        assert_eq!(
+            anchors,
+            ["setup", "example-1", "setup-1", "example", "example-2", "example-3"]
+        );
+    }
+
+    #[test]
+    fn split_sections_by_h2() {
+        let md = "---\ntitle: \"Guide\"\n---\n# Guide\n\nIntro.\n\n## Install\n\nRun it.\n\n### From source\n\nBuild it.\n\n## Usage\n\n```sh\n## not a section\n```\n";
+        let sections = split_sections(md, 2);
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].anchor, None);
+        assert_eq!(sections[0].content, "# Guide\n\nIntro.");
+        assert_eq!(sections[1].anchor.as_deref(), Some("install"));
+        assert_eq!(
+            sections[1].content,
+            "## Install\n\nRun it.\n\n### From source\n\nBuild it."
+        );
+        assert_eq!(&md[sections[1].offset..sections[1].offset + 10], "## Install");
+        assert_eq!(sections[2].title.as_deref(), Some("Usage"));
+        assert!(sections[2].content.ends_with("```"));
+    }
+
+    #[test]
+    fn split_sections_without_headings_is_single_preamble() {
+        let sections = split_sections("Just text.\n", 2);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title, None);
+        assert_eq!(sections[0].content, "Just text.");
+    }
+}
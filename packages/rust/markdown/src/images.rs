@@ -0,0 +1,76 @@
+//! Image references in converted Markdown.
+//!
+//! Finds the sources of `![alt](src)` images (including linked images) and
+//! rewrites them, e.g. to point at locally downloaded copies. Code blocks
+//! are never touched.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::fence::{map_prose, segments};
+
+/// `![alt](src "optional title")`; group 1 is the source.
+static IMAGE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!\[[^\]]*\]\(([^)\s]+)(?:\s+"[^"]*")?\)"#).expect("valid regex")
+});
+
+/// Every image source in `md`, in order of first appearance, without
+/// duplicates.
+pub fn image_sources(md: &str) -> Vec<String> {
+    let mut sources: Vec<String> = Vec::new();
+    for seg in segments(md).into_iter().filter(|seg| !seg.code) {
+        for caps in IMAGE_RE.captures_iter(seg.text) {
+            let src = &caps[1];
+            if !sources.iter().any(|s| s == src) {
+                sources.push(src.to_string());
+            }
+        }
+    }
+    sources
+}
+
+/// Replace each image source for which `replace` returns a new value,
+/// keeping the alt text and title.
+pub fn rewrite_image_sources(md: &str, mut replace: impl FnMut(&str) -> Option<String>) -> String {
+    map_prose(md, |prose| {
+        IMAGE_RE
+            .replace_all(prose, |caps: &regex::Captures| {
+                let whole = &caps[0];
+                let src = caps.get(1).expect("source group");
+                match replace(src.as_str()) {
+                    Some(new_src) => {
+                        let offset = src.start() - caps.get(0).expect("match").start();
+                        format!(
+                            "{}{new_src}{}",
+                            &whole[..offset],
+                            &whole[offset + src.len()..]
+                        )
+                    }
+                    None => whole.to_string(),
+                }
+            })
+            .into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_rewrites_images_outside_code() {
+        let md = "![Logo](img/logo.png \"Logo\")\n\n[![Badge](/b.svg)](/x) ![Logo](img/logo.png)\n\
+                  ```\n![Code](img/code.png)\n```\n";
+        assert_eq!(image_sources(md), ["img/logo.png", "/b.svg"]);
+
+        let out = rewrite_image_sources(md, |src| {
+            (src == "img/logo.png").then(|| "../assets/abc.png".to_string())
+        });
+        assert_eq!(
+            out,
+            "![Logo](../assets/abc.png \"Logo\")\n\n[![Badge](/b.svg)](/x) \
+             ![Logo](../assets/abc.png)\n```\n![Code](img/code.png)\n```\n"
+        );
+    }
+}
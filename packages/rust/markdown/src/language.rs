@@ -0,0 +1,66 @@
+//! Content-language detection for converted pages.
+//!
+//! Runs `whatlang` over the prose of a Markdown page (fenced code, inline
+//! code, and link targets removed) so pages can be tagged with their actual
+//! language and untranslated pages filtered out.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::fence;
+
+/// Inline code spans and the `(target)` part of links and images.
+static NON_PROSE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"`[^`\n]*`|\]\([^)\n]*\)|https?://\S+").expect("valid regex")
+});
+
+/// Detect the language of a Markdown page's prose.
+///
+/// Returns the ISO 639-3 code (`eng`, `deu`, `jpn`, ...), or `None` when the
+/// detection is not reliable (too little text, or mixed languages).
+pub fn detect_language(md: &str) -> Option<String> {
+    let prose: String = fence::segments(md)
+        .iter()
+        .filter(|seg| !seg.code)
+        .map(|seg| NON_PROSE_RE.replace_all(seg.text, " "))
+        .collect();
+
+    let info = whatlang::detect(&prose)?;
+    info.is_reliable().then(|| info.lang().code().to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_english_and_german() {
+        let en = "# Installation\n\nThis guide explains how to install the command line tool \
+                  and configure it for your first project. Follow each step carefully.\n";
+        assert_eq!(detect_language(en).as_deref(), Some("eng"));
+
+        let de = "# Installation\n\nDiese Anleitung erklärt, wie Sie das Kommandozeilenwerkzeug \
+                  installieren und für Ihr erstes Projekt einrichten. Folgen Sie jedem Schritt sorgfältig.\n";
+        assert_eq!(detect_language(de).as_deref(), Some("deu"));
+    }
+
+    #[test]
+    fn ignores_code_and_links() {
+        // Mostly English code around a short German sentence
+        let md = "Diese Anleitung erklärt, wie Sie das Werkzeug installieren und einrichten.\n\n\
+                  ```sh\nnpm install --save-dev the-tool && the-tool init --with-defaults\n```\n\n\
+                  Siehe [die Dokumentation](https://example.com/getting-started/install-the-tool).\n";
+        assert_eq!(detect_language(md).as_deref(), Some("deu"));
+    }
+
+    #[test]
+    fn short_text_is_undetermined() {
+        assert_eq!(detect_language(""), None);
+        assert_eq!(detect_language("```\ncode only\n```\n"), None);
+    }
+}
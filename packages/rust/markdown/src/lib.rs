@@ -4,6 +4,20 @@
 //! a series of cleanup passes to normalize headings, whitespace, code blocks, and links.
 
 mod cleanup;
+mod density;
+mod fence;
+mod headings;
+mod images;
+mod language;
+mod source;
+mod svg;
+mod tabs;
+
+pub use headings::{parse_headings, slugify_heading, split_sections, Heading, Section};
+pub use images::{image_sources, rewrite_image_sources};
+pub use language::detect_language;
+pub use source::is_markdown_source;
+pub use svg::SVG_DATA_URI_PREFIX;
 
 use std::sync::LazyLock;
 
@@ -12,7 +26,7 @@ use scraper::Html;
 use tracing::{debug, instrument};
 use url::Url;
 
-use contextbuilder_shared::{ContextBuilderError, Result};
+use contextbuilder_shared::{ContextBuilderError, InlineSvg, Result};
 
 // ---------------------------------------------------------------------------
 // Public types
@@ -27,10 +41,14 @@ pub struct ConvertResult {
     pub title: String,
     /// Approximate word count of the Markdown body (excluding frontmatter).
     pub word_count: usize,
+    /// Detected ISO 639-3 language of the body (with `detect_language` set).
+    pub language: Option<String>,
+    /// The untruncated title, when `max_title_len` shortened it.
+    pub full_title: Option<String>,
 }
 
 /// Options for the HTML-to-Markdown conversion.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ConvertOptions {
     /// Source URL used for resolving relative links and frontmatter.
     pub source_url: String,
@@ -38,6 +56,114 @@ pub struct ConvertOptions {
     pub title: Option<String>,
     /// ISO 8601 timestamp for the `fetched_at` frontmatter field.
     pub fetched_at: Option<String>,
+    /// How to treat multiple top-level (H1) headings in a page.
+    pub h1_policy: H1Policy,
+    /// Fold `<!-- description: ... -->` / `<!-- keywords: ... -->` comments
+    /// into the frontmatter and strip every other HTML comment.
+    pub comment_metadata: bool,
+    /// Add the page's heading outline (level, text, anchor) to the
+    /// frontmatter as `headings:`, leaving the body untouched.
+    pub emit_headings_frontmatter: bool,
+    /// Detect the body's language and record it as `language:` in the frontmatter.
+    pub detect_language: bool,
+    /// CSS selectors whose elements are removed from the content before
+    /// conversion. Invalid selectors are skipped.
+    pub strip_selectors: Vec<String>,
+    /// Truncate titles longer than this many characters at a word boundary.
+    pub max_title_len: Option<usize>,
+    /// `(pattern, replacement)` regex rewrites applied in order to every
+    /// resolved link target, e.g. to canonicalize vanity URLs.
+    pub link_rewrites: Vec<(String, String)>,
+    /// Collapse list items identical to the item above them and links
+    /// repeated back to back.
+    pub dedupe_adjacent: bool,
+    /// Keep source heading ids that differ from the generated slug as a
+    /// trailing `{#id}`, so in-page links to them keep working.
+    pub preserve_anchors: bool,
+    /// What happens to inline `<svg>` diagrams.
+    pub inline_svg: InlineSvg,
+    /// The page's `<meta name="description">`, written to the frontmatter as
+    /// `description:` unless a description comment provides one.
+    pub description: Option<String>,
+    /// For Markdown sources, merge the fields of the source's own
+    /// frontmatter into the generated one instead of dropping them. The
+    /// generated fields win on conflicts.
+    pub preserve_source_frontmatter: bool,
+}
+
+/// One cleanup pass's effect, as recorded by [`convert_with_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassDiff {
+    /// Name of the cleanup pass (e.g. `normalize_headings`).
+    pub pass: &'static str,
+    /// Markdown going into the pass.
+    pub before: String,
+    /// Markdown after the pass.
+    pub after: String,
+}
+
+impl PassDiff {
+    /// Whether the pass changed anything.
+    pub fn changed(&self) -> bool {
+        self.before != self.after
+    }
+
+    /// Line-level diff of the pass: removed lines prefixed `-`, added lines
+    /// prefixed `+`, unchanged lines omitted.
+    pub fn diff_lines(&self) -> Vec<String> {
+        let before: Vec<&str> = self.before.lines().collect();
+        let after: Vec<&str> = self.after.lines().collect();
+
+        // Longest common subsequence table, filled from the end
+        let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+        for i in (0..before.len()).rev() {
+            for j in (0..after.len()).rev() {
+                lcs[i][j] = if before[i] == after[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let mut lines = Vec::new();
+        while i < before.len() || j < after.len() {
+            if i < before.len() && j < after.len() && before[i] == after[j] {
+                i += 1;
+                j += 1;
+            } else if i < before.len() && (j == after.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(format!("-{}", before[i]));
+                i += 1;
+            } else {
+                lines.push(format!("+{}", after[j]));
+                j += 1;
+            }
+        }
+        lines
+    }
+}
+
+/// Page metadata recovered from HTML comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommentMetadata {
+    /// From the first `<!-- description: ... -->` comment.
+    pub description: Option<String>,
+    /// Comma-separated values of every `<!-- keywords: ... -->` comment.
+    pub keywords: Vec<String>,
+}
+
+/// Policy for pages containing more than one H1 heading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum H1Policy {
+    /// Keep the first H1 and demote every later H1 to H2 (default).
+    #[default]
+    DemoteExtra,
+    /// Leave all H1 headings untouched (pages with several top-level sections).
+    KeepAll,
+    /// Make the page title the sole H1 and shift every other heading down one
+    /// level, preserving the relative hierarchy below it.
+    PromoteTitle,
 }
 
 // ---------------------------------------------------------------------------
@@ -48,17 +174,57 @@ pub struct ConvertOptions {
 ///
 /// This is the main entry point. It:
 /// 1. Extracts the content HTML (via adapter or from raw `<main>`/`<body>`)
-/// 2. Pre-processes HTML tables into markdown tables
+/// 2. Pre-processes code-tab groups and HTML tables
 /// 3. Converts HTML → Markdown via `htmd`
 /// 4. Runs the cleanup pipeline
 /// 5. Prepends YAML frontmatter
 #[instrument(skip(html), fields(url = %opts.source_url))]
 pub fn convert(html: &str, opts: &ConvertOptions) -> Result<ConvertResult> {
+    convert_page(html, opts, None)
+}
+
+/// [`convert`], also returning the Markdown before and after each cleanup
+/// pass, in pipeline order. For previewing what a pass does to real pages.
+pub fn convert_with_trace(
+    html: &str,
+    opts: &ConvertOptions,
+) -> Result<(ConvertResult, Vec<PassDiff>)> {
+    let mut trace = Vec::new();
+    let result = convert_page(html, opts, Some(&mut trace))?;
+    Ok((result, trace))
+}
+
+fn convert_page(
+    html: &str,
+    opts: &ConvertOptions,
+    trace: Option<&mut Vec<PassDiff>>,
+) -> Result<ConvertResult> {
+    if is_markdown_source(&opts.source_url, html) {
+        return Ok(convert_markdown_source(html, opts, trace));
+    }
+
+    // Step 0: Pull metadata out of comments (they may sit outside the content)
+    let (html, comment_meta) = if opts.comment_metadata {
+        extract_comment_metadata(html)
+    } else {
+        (html.to_string(), CommentMetadata::default())
+    };
+
     // Step 1: Extract content HTML (strip nav/header/footer/aside/script/style)
-    let content_html = extract_content_html(html);
+    let content_html = extract_content_html(&html);
+    let content_html = strip_selected(&content_html, &opts.strip_selectors);
+    let content_html = svg::preprocess_inline_svg(&content_html, opts.inline_svg);
 
-    // Step 2: Pre-process tables into markdown
+    // Step 2: Pre-process code tabs into labeled blocks, tables into markdown,
+    // keystrokes/output into code
+    let content_html = tabs::preprocess_code_tabs(&content_html);
     let content_html = preprocess_tables(&content_html);
+    let content_html = preprocess_inline_code_tags(&content_html);
+    let content_html = if opts.preserve_anchors {
+        preprocess_heading_anchors(&content_html)
+    } else {
+        content_html
+    };
 
     // Step 3: Convert HTML → Markdown using htmd
     let converter = htmd::HtmlToMarkdown::builder()
@@ -73,20 +239,89 @@ pub fn convert(html: &str, opts: &ConvertOptions) -> Result<ConvertResult> {
 
     // Step 3: Run cleanup pipeline
     let base_url = Url::parse(&opts.source_url).ok();
-    let cleaned = cleanup::run_pipeline(&raw_markdown, base_url.as_ref());
+    let cleanup_opts = cleanup::CleanupOptions::from(opts);
+    let cleaned = match trace {
+        Some(trace) => {
+            cleanup::run_pipeline_traced(&raw_markdown, base_url.as_ref(), &cleanup_opts, trace)
+        }
+        None => cleanup::run_pipeline(&raw_markdown, base_url.as_ref(), &cleanup_opts),
+    };
+
+    Ok(finish_page(cleaned, comment_meta, None, opts))
+}
+
+/// Convert a Markdown source (see [`is_markdown_source`]): its frontmatter
+/// is split off, and the body only goes through the cleanup pipeline.
+fn convert_markdown_source(
+    md: &str,
+    opts: &ConvertOptions,
+    trace: Option<&mut Vec<PassDiff>>,
+) -> ConvertResult {
+    let md = md.replace("\r\n", "\n");
+    let (source_frontmatter, body) = source::split_frontmatter(&md);
+    let (body, comment_meta) = if opts.comment_metadata {
+        extract_comment_metadata(body)
+    } else {
+        (body.to_string(), CommentMetadata::default())
+    };
+
+    let base_url = Url::parse(&opts.source_url).ok();
+    let cleanup_opts = cleanup::CleanupOptions::from(opts);
+    let cleaned = match trace {
+        Some(trace) => cleanup::run_pipeline_traced(&body, base_url.as_ref(), &cleanup_opts, trace),
+        None => cleanup::run_pipeline(&body, base_url.as_ref(), &cleanup_opts),
+    };
 
+    finish_page(cleaned, comment_meta, source_frontmatter, opts)
+}
+
+/// Title, word count, and frontmatter for a cleaned page body, merging in
+/// `source_frontmatter` (the inside of a Markdown source's own block) when
+/// `preserve_source_frontmatter` is set.
+fn finish_page(
+    cleaned: String,
+    comment_meta: CommentMetadata,
+    source_frontmatter: Option<&str>,
+    opts: &ConvertOptions,
+) -> ConvertResult {
     // Step 4: Extract title
     let title = opts
         .title
         .clone()
         .or_else(|| extract_title_from_markdown(&cleaned))
+        .or_else(|| source_frontmatter.and_then(source::frontmatter_title))
         .unwrap_or_else(|| "Untitled".to_string());
+    let (title, full_title) = shorten_title(title, opts.max_title_len);
 
     // Step 5: Count words (body only)
     let word_count = count_words(&cleaned);
 
     // Step 6: Build frontmatter
-    let frontmatter = build_frontmatter(&opts.source_url, &title, opts.fetched_at.as_deref());
+    let headings = if opts.emit_headings_frontmatter {
+        parse_headings(&cleaned)
+    } else {
+        Vec::new()
+    };
+    let language = if opts.detect_language {
+        detect_language(&cleaned)
+    } else {
+        None
+    };
+    let comment_meta = with_page_description(comment_meta, opts);
+    let frontmatter = build_frontmatter(
+        &opts.source_url,
+        &title,
+        opts.fetched_at.as_deref(),
+        &comment_meta,
+        &headings,
+        language.as_deref(),
+    );
+    let frontmatter = match source_frontmatter {
+        Some(source) if opts.preserve_source_frontmatter => {
+            source::merge_frontmatter(&frontmatter, source)
+        }
+        _ => frontmatter,
+    };
     let markdown = format!("{frontmatter}\n{cleaned}");
 
     debug!(
@@ -96,11 +331,13 @@ pub fn convert(html: &str, opts: &ConvertOptions) -> Result<ConvertResult> {
         "conversion complete"
     );
 
-    Ok(ConvertResult {
+    ConvertResult {
         markdown,
         title,
         word_count,
-    })
+        language,
+        full_title,
+    }
 }
 
 /// Convert pre-extracted content HTML (from a platform adapter) to Markdown.
@@ -109,7 +346,21 @@ pub fn convert(html: &str, opts: &ConvertOptions) -> Result<ConvertResult> {
 /// and just need the HTML → Markdown + cleanup step.
 #[instrument(skip(content_html), fields(url = %opts.source_url))]
 pub fn convert_extracted(content_html: &str, opts: &ConvertOptions) -> Result<ConvertResult> {
-    let content_html = preprocess_tables(content_html);
+    let (content_html, comment_meta) = if opts.comment_metadata {
+        extract_comment_metadata(content_html)
+    } else {
+        (content_html.to_string(), CommentMetadata::default())
+    };
+    let content_html = strip_selected(&content_html, &opts.strip_selectors);
+    let content_html = svg::preprocess_inline_svg(&content_html, opts.inline_svg);
+    let content_html = tabs::preprocess_code_tabs(&content_html);
+    let content_html = preprocess_tables(&content_html);
+    let content_html = preprocess_inline_code_tags(&content_html);
+    let content_html = if opts.preserve_anchors {
+        preprocess_heading_anchors(&content_html)
+    } else {
+        content_html
+    };
 
     let converter = htmd::HtmlToMarkdown::builder()
         .skip_tags(vec!["script", "style", "nav", "iframe", "noscript", "svg"])
@@ -120,25 +371,105 @@ pub fn convert_extracted(content_html: &str, opts: &ConvertOptions) -> Result<Co
     })?;
 
     let base_url = Url::parse(&opts.source_url).ok();
-    let cleaned = cleanup::run_pipeline(&raw_markdown, base_url.as_ref());
+    let cleaned = cleanup::run_pipeline(
+        &raw_markdown,
+        base_url.as_ref(),
+        &cleanup::CleanupOptions::from(opts),
+    );
 
     let title = opts
         .title
         .clone()
         .or_else(|| extract_title_from_markdown(&cleaned))
         .unwrap_or_else(|| "Untitled".to_string());
+    let (title, full_title) = shorten_title(title, opts.max_title_len);
 
     let word_count = count_words(&cleaned);
-    let frontmatter = build_frontmatter(&opts.source_url, &title, opts.fetched_at.as_deref());
+    let headings = if opts.emit_headings_frontmatter {
+        parse_headings(&cleaned)
+    } else {
+        Vec::new()
+    };
+    let language = if opts.detect_language {
+        detect_language(&cleaned)
+    } else {
+        None
+    };
+    let comment_meta = with_page_description(comment_meta, opts);
+    let frontmatter = build_frontmatter(
+        &opts.source_url,
+        &title,
+        opts.fetched_at.as_deref(),
+        &comment_meta,
+        &headings,
+        language.as_deref(),
+    );
     let markdown = format!("{frontmatter}\n{cleaned}");
 
     Ok(ConvertResult {
         markdown,
         title,
         word_count,
+        language,
+        full_title,
     })
 }
 
+/// Re-run the cleanup pipeline on already-converted Markdown.
+///
+/// A leading YAML frontmatter block is kept byte for byte; only the body is
+/// cleaned, with default options. Lets stored pages pick up cleanup fixes
+/// without re-crawling.
+pub fn reclean_markdown(md: &str, base_url: Option<&Url>) -> String {
+    let split = headings::frontmatter_len(md);
+    let (frontmatter, body) = md.split_at(split);
+    let body = body.trim_start_matches('\n');
+    let cleaned = cleanup::run_pipeline(body, base_url, &cleanup::CleanupOptions::default());
+    if frontmatter.is_empty() {
+        cleaned
+    } else {
+        format!("{frontmatter}\n{cleaned}")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Comment metadata pre-processing
+// ---------------------------------------------------------------------------
+
+/// Strip every HTML comment from `html`, collecting recognised
+/// `description` / `keywords` comments as metadata.
+fn extract_comment_metadata(html: &str) -> (String, CommentMetadata) {
+    static COMMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?s)<!--(.*?)-->").expect("valid regex")
+    });
+    static META_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)^\s*(description|keywords)\s*:(.*)$").expect("valid regex")
+    });
+
+    let mut meta = CommentMetadata::default();
+    let stripped = COMMENT_RE.replace_all(html, |caps: &regex::Captures| {
+        if let Some(m) = META_RE.captures(&caps[1]) {
+            let value = m[2].split_whitespace().collect::<Vec<_>>().join(" ");
+            if m[1].eq_ignore_ascii_case("description") {
+                if meta.description.is_none() && !value.is_empty() {
+                    meta.description = Some(value);
+                }
+            } else {
+                meta.keywords.extend(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|k| !k.is_empty())
+                        .map(str::to_string),
+                );
+            }
+        }
+        ""
+    });
+
+    (stripped.into_owned(), meta)
+}
+
 // ---------------------------------------------------------------------------
 // Table pre-processing
 // ---------------------------------------------------------------------------
@@ -170,6 +501,8 @@ fn preprocess_tables(html: &str) -> String {
 }
 
 /// Convert a single HTML table element to a markdown table string.
+///
+/// A `<caption>` becomes an italic line right above the table.
 fn html_table_to_markdown(table: &scraper::ElementRef) -> String {
     let tr_sel = scraper::Selector::parse("tr").unwrap();
     let th_sel = scraper::Selector::parse("th").unwrap();
@@ -219,6 +552,12 @@ fn html_table_to_markdown(table: &scraper::ElementRef) -> String {
 
     let mut md = String::from("\n\n");
 
+    // Emitted as HTML (the table text itself passes through htmd) so it
+    // converts to emphasis instead of escaped asterisks
+    if let Some(caption) = table_caption(table) {
+        md.push_str(&format!("<p><em>{}</em></p>\n\n", escape_html_text(&caption)));
+    }
+
     // Header row
     let header = &rows[0];
     md.push_str("| ");
@@ -247,11 +586,110 @@ fn html_table_to_markdown(table: &scraper::ElementRef) -> String {
     md
 }
 
+/// The text of the table's own `<caption>`, whitespace-collapsed.
+fn table_caption(table: &scraper::ElementRef) -> Option<String> {
+    let caption = table
+        .children()
+        .filter_map(scraper::ElementRef::wrap)
+        .find(|el| el.value().name() == "caption")?;
+    let text = caption.text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
 /// Reconstruct the outer HTML of an element (approximate, for matching).
 fn element_outer_html(el: &scraper::ElementRef) -> String {
     el.html()
 }
 
+// ---------------------------------------------------------------------------
+// Keyboard / sample output pre-processing
+// ---------------------------------------------------------------------------
+
+/// Convert `<kbd>` and `<samp>` elements to `<code>` before htmd conversion.
+///
+/// htmd drops these tags and keeps only their text, which loses the
+/// distinction between prose and keystrokes/program output. Nested keys such
+/// as `<kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>` collapse into one code span.
+fn preprocess_inline_code_tags(html: &str) -> String {
+    let doc = Html::parse_fragment(html);
+
+    let sel = scraper::Selector::parse("kbd, samp").unwrap();
+
+    if doc.select(&sel).next().is_none() {
+        return html.to_string();
+    }
+
+    let mut result = html.to_string();
+
+    for el in doc.select(&sel) {
+        // Outer element already covers nested ones.
+        let nested = el.ancestors().filter_map(scraper::ElementRef::wrap).any(|a| {
+            matches!(a.value().name(), "kbd" | "samp")
+        });
+        if nested {
+            continue;
+        }
+
+        let text = el.text().collect::<String>();
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let code = format!("<code>{}</code>", escape_html_text(text));
+        result = result.replacen(&element_outer_html(&el), &code, 1);
+    }
+
+    result
+}
+
+// ---------------------------------------------------------------------------
+// Heading anchor pre-processing
+// ---------------------------------------------------------------------------
+
+/// Append `{#id}` to headings whose source anchor differs from the slug we
+/// would generate for them.
+///
+/// The anchor is the heading's `id`, or else the `id`/`name` of an `<a>`
+/// inside it (`<h2><a name="setup"></a>Install</h2>`). The cleanup pipeline
+/// undoes htmd's escaping inside the marker.
+fn preprocess_heading_anchors(html: &str) -> String {
+    let doc = Html::parse_fragment(html);
+    let sel = scraper::Selector::parse("h1, h2, h3, h4, h5, h6").unwrap();
+    let anchor_sel = scraper::Selector::parse("a[id], a[name]").unwrap();
+
+    let mut result = html.to_string();
+    for el in doc.select(&sel) {
+        let id = el.value().id().or_else(|| {
+            el.select(&anchor_sel)
+                .next()
+                .and_then(|a| a.value().id().or_else(|| a.value().attr("name")))
+        });
+        let Some(id) = id.filter(|id| !id.is_empty() && !id.contains(char::is_whitespace)) else {
+            continue;
+        };
+        if id.contains(['{', '}']) || slugify_heading(&el.text().collect::<String>()) == id {
+            continue;
+        }
+
+        let outer = element_outer_html(&el);
+        let close = format!("</{}>", el.value().name());
+        let Some(end) = outer.rfind(&close) else {
+            continue;
+        };
+        let marked = format!("{} {{#{}}}{}", &outer[..end], escape_html_text(id), &outer[end..]);
+        result = result.replacen(&outer, &marked, 1);
+    }
+
+    result
+}
+
+/// Escape text for re-insertion into HTML.
+fn escape_html_text(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -279,6 +717,12 @@ fn extract_content_html(html: &str) -> String {
         }
     }
 
+    // No known container: look for the densest block of prose
+    if let Some(el) = density::main_content(&doc) {
+        debug!("no content container matched, using densest text block");
+        return el.inner_html();
+    }
+
     // Fallback: use <body> content
     if let Ok(body_sel) = scraper::Selector::parse("body") {
         if let Some(body) = doc.select(&body_sel).next() {
@@ -290,6 +734,35 @@ fn extract_content_html(html: &str) -> String {
     html.to_string()
 }
 
+/// Remove every element matching one of `selectors` from a content fragment.
+fn strip_selected(html: &str, selectors: &[String]) -> String {
+    if selectors.is_empty() {
+        return html.to_string();
+    }
+    let doc = Html::parse_fragment(html);
+    let mut result = html.to_string();
+    for raw in selectors {
+        let Ok(selector) = scraper::Selector::parse(raw) else {
+            debug!(selector = %raw, "skipping invalid strip selector");
+            continue;
+        };
+        for el in doc.select(&selector) {
+            result = result.replace(&el.html(), "");
+        }
+    }
+    result
+}
+
+/// Apply `max_title_len`, returning the title and the original if it was cut.
+fn shorten_title(title: String, max_len: Option<usize>) -> (String, Option<String>) {
+    match max_len {
+        Some(max) if title.chars().count() > max => {
+            (contextbuilder_shared::truncate_title(&title, max), Some(title))
+        }
+        _ => (title, None),
+    }
+}
+
 /// Extract title from the first H1 in the Markdown text.
 fn extract_title_from_markdown(md: &str) -> Option<String> {
     static H1_RE: LazyLock<Regex> = LazyLock::new(|| {
@@ -298,36 +771,74 @@ fn extract_title_from_markdown(md: &str) -> Option<String> {
 
     H1_RE
         .captures(md)
-        .map(|c| c[1].trim().to_string())
+        .map(|c| headings::split_heading_id(c[1].trim()).0.trim().to_string())
 }
 
 /// Count words in Markdown body (excluding code blocks and frontmatter).
 fn count_words(md: &str) -> usize {
-    static CODE_BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(r"(?s)```.*?```").expect("valid regex")
-    });
-
-    let without_code = CODE_BLOCK_RE.replace_all(md, "");
-    without_code
-        .split_whitespace()
+    fence::segments(md)
+        .iter()
+        .filter(|seg| !seg.code)
+        .flat_map(|seg| seg.text.split_whitespace())
         .filter(|w| !w.starts_with('#') || w.len() > 2)
         .count()
 }
 
+/// `meta` with the page's meta description as its description, unless a
+/// description comment already set one.
+fn with_page_description(mut meta: CommentMetadata, opts: &ConvertOptions) -> CommentMetadata {
+    if meta.description.is_none() {
+        meta.description = opts.description.clone();
+    }
+    meta
+}
+
 /// Build a YAML frontmatter block.
-fn build_frontmatter(source_url: &str, title: &str, fetched_at: Option<&str>) -> String {
+fn build_frontmatter(
+    source_url: &str,
+    title: &str,
+    fetched_at: Option<&str>,
+    meta: &CommentMetadata,
+    headings: &[Heading],
+    language: Option<&str>,
+) -> String {
     let mut fm = String::from("---\n");
     fm.push_str(&format!("source_url: \"{source_url}\"\n"));
     fm.push_str(&format!("title: \"{}\"\n", escape_yaml_string(title)));
     if let Some(ts) = fetched_at {
         fm.push_str(&format!("fetched_at: \"{ts}\"\n"));
     }
+    if let Some(language) = language {
+        fm.push_str(&format!("language: \"{language}\"\n"));
+    }
+    if let Some(description) = &meta.description {
+        fm.push_str(&format!("description: \"{}\"\n", escape_yaml_string(description)));
+    }
+    if !meta.keywords.is_empty() {
+        let keywords: Vec<String> = meta
+            .keywords
+            .iter()
+            .map(|k| format!("\"{}\"", escape_yaml_string(k)))
+            .collect();
+        fm.push_str(&format!("keywords: [{}]\n", keywords.join(", ")));
+    }
+    if !headings.is_empty() {
+        fm.push_str("headings:\n");
+        for h in headings {
+            fm.push_str(&format!(
+                "  - level: {}\n    text: \"{}\"\n    anchor: \"{}\"\n",
+                h.level,
+                escape_yaml_string(&h.text),
+                escape_yaml_string(&h.anchor)
+            ));
+        }
+    }
     fm.push_str("---\n");
     fm
 }
 
-/// Escape special characters in a YAML string value.
-fn escape_yaml_string(s: &str) -> String {
+/// Escape special characters in a double-quoted YAML string value.
+pub fn escape_yaml_string(s: &str) -> String {
     s.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
@@ -356,6 +867,7 @@ mod tests {
             source_url: url.to_string(),
             title: None,
             fetched_at: None,
+            ..Default::default()
         }
     }
 
@@ -381,6 +893,7 @@ mod tests {
                 source_url: "https://example.com/test".into(),
                 title: None,
                 fetched_at: Some("2024-01-15T10:30:00Z".into()),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -391,6 +904,85 @@ mod tests {
         assert!(result.markdown.contains("fetched_at: \"2024-01-15T10:30:00Z\""));
     }
 
+    #[test]
+    fn convert_folds_comment_metadata_into_frontmatter() {
+        let html = r#"<html><head><!-- description: How to "install" the CLI --></head>
+            <body><main>
+            <!-- keywords: install, cli ,setup -->
+            <h1>Install</h1>
+            <!-- TODO: rewrite this section -->
+            <p>Run the installer.</p>
+            </main></body></html>"#;
+        let opts = ConvertOptions {
+            source_url: "https://example.com/install".into(),
+            comment_metadata: true,
+            ..Default::default()
+        };
+        let result = convert(html, &opts).unwrap();
+
+        let (frontmatter, body) = result.markdown[4..].split_once("---\n").unwrap();
+        assert!(frontmatter.contains(r#"description: "How to \"install\" the CLI""#));
+        assert!(frontmatter.contains(r#"keywords: ["install", "cli", "setup"]"#));
+        assert!(!result.markdown.contains("TODO"));
+        assert!(!result.markdown.contains("<!--"));
+        assert!(body.contains("Run the installer."));
+
+        // Off by default: no metadata is added
+        let plain = convert(html, &make_opts("https://example.com/install")).unwrap();
+        assert!(!plain.markdown.contains("description:"));
+    }
+
+    #[test]
+    fn convert_writes_meta_description_unless_a_comment_sets_one() {
+        let html = "<html><body><main><h1>Install</h1><p>Run it.</p></main></body></html>";
+        let opts = ConvertOptions {
+            description: Some("Install the \"cb\" CLI.".into()),
+            ..make_opts("https://example.com/install")
+        };
+        let result = convert_extracted(html, &opts).unwrap();
+        assert!(result.markdown.contains(r#"description: "Install the \"cb\" CLI.""#));
+
+        let commented = "<!-- description: From the comment --><h1>Install</h1><p>Run it.</p>";
+        let opts = ConvertOptions {
+            comment_metadata: true,
+            ..opts
+        };
+        let result = convert_extracted(commented, &opts).unwrap();
+        assert!(result.markdown.contains(r#"description: "From the comment""#));
+        assert_eq!(result.markdown.matches("description:").count(), 1);
+    }
+
+    #[test]
+    fn convert_emits_headings_frontmatter() {
+        let html = r#"<html><body><main>
+            <h1>Guide</h1><p>Intro.</p>
+            <h2>Install "CLI"</h2><p>Step.</p>
+            <h3>From <code>npm</code></h3><p>Run it.</p>
+            <h2>Usage</h2><p>Use it.</p>
+            </main></body></html>"#;
+        let opts = ConvertOptions {
+            source_url: "https://example.com/guide".into(),
+            emit_headings_frontmatter: true,
+            ..Default::default()
+        };
+        let result = convert(html, &opts).unwrap();
+
+        let (frontmatter, body) = result.markdown[4..].split_once("---\n").unwrap();
+        let expected = concat!(
+            "headings:\n",
+            "  - level: 1\n    text: \"Guide\"\n    anchor: \"guide\"\n",
+            "  - level: 2\n    text: \"Install \\\"CLI\\\"\"\n    anchor: \"install-cli\"\n",
+            "  - level: 3\n    text: \"From npm\"\n    anchor: \"from-npm\"\n",
+            "  - level: 2\n    text: \"Usage\"\n    anchor: \"usage\"\n",
+        );
+        assert!(frontmatter.ends_with(expected), "frontmatter:\n{frontmatter}");
+
+        // The body is the same as without the option
+        let plain = convert(html, &make_opts("https://example.com/guide")).unwrap();
+        assert!(!plain.markdown.contains("headings:"));
+        assert!(plain.markdown.ends_with(body));
+    }
+
     #[test]
     fn convert_strips_nav_and_footer() {
         let html = r#"<html><body>
@@ -436,6 +1028,83 @@ mod tests {
         assert!(result.markdown.contains("| foo | bar |"));
     }
 
+    #[test]
+    fn convert_puts_table_caption_above_the_table() {
+        let html = r#"<html><body><main>
+            <h1>Limits</h1>
+            <table>
+                <caption>Rate limits
+                    per plan</caption>
+                <tr><th>Plan</th><th>Requests</th></tr>
+                <tr><td>Free</td><td>60</td></tr>
+            </table>
+        </main></body></html>"#;
+
+        let result = convert(html, &make_opts("https://example.com/limits")).unwrap();
+        let md = &result.markdown;
+        let caption = md.find("_Rate limits per plan_").expect(md);
+        let table = md.find("| Plan | Requests |").expect(md);
+        assert!(caption < table, "{md}");
+        assert!(!md.contains("\\*"), "{md}");
+        assert!(!md.contains("| Rate limits"), "{md}");
+    }
+
+    #[test]
+    fn convert_keeps_inline_svg_per_mode() {
+        let html = r#"<html><body><main>
+            <h1>Architecture</h1>
+            <p>How requests flow:</p>
+            <svg viewBox="0 0 10 10"><title>Request flow</title><path d="M0 0L10 10"/></svg>
+        </main></body></html>"#;
+        let opts = |inline_svg| ConvertOptions {
+            inline_svg,
+            ..make_opts("https://example.com/arch")
+        };
+
+        let skipped = convert(html, &opts(InlineSvg::Skip)).unwrap().markdown;
+        assert!(!skipped.contains("path"), "{skipped}");
+
+        let code = convert(html, &opts(InlineSvg::Code)).unwrap().markdown;
+        assert!(
+            code.contains(
+                "```svg\n<svg viewBox=\"0 0 10 10\"><title>Request flow</title>\
+                 <path d=\"M0 0L10 10\"></path></svg>\n```"
+            ),
+            "{code}"
+        );
+
+        let asset = convert(html, &opts(InlineSvg::Asset)).unwrap().markdown;
+        let sources = image_sources(&asset);
+        assert_eq!(sources.len(), 1, "{asset}");
+        assert!(sources[0].starts_with(SVG_DATA_URI_PREFIX), "{asset}");
+        assert!(asset.contains("![Request flow](data:image/svg+xml,"), "{asset}");
+    }
+
+    #[test]
+    fn convert_labels_every_code_tab() {
+        let html = r#"<html><body><main>
+            <h1>Install</h1>
+            <div class="tabs-container">
+                <ul role="tablist">
+                    <li role="tab" aria-selected="true">JavaScript</li>
+                    <li role="tab">Python</li>
+                </ul>
+                <div role="tabpanel">
+                    <pre><code class="language-js">client.connect();</code></pre>
+                </div>
+                <div role="tabpanel" hidden>
+                    <pre><code class="language-python">client.connect()</code></pre>
+                </div>
+            </div>
+        </main></body></html>"#;
+
+        let md = convert(html, &make_opts("https://example.com/install")).unwrap().markdown;
+        let js = md.find("**JavaScript**\n\n```js\nclient.connect();\n```").expect(&md);
+        let py = md.find("**Python**\n\n```python\nclient.connect()\n```").expect(&md);
+        assert!(js < py, "{md}");
+        assert!(!md.contains("- JavaScript"), "{md}");
+    }
+
     #[test]
     fn convert_handles_lists() {
         let html = r#"<html><body><main>
@@ -480,6 +1149,7 @@ mod tests {
                 source_url: "https://example.com/".into(),
                 title: Some("Custom Title".into()),
                 fetched_at: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -488,6 +1158,44 @@ mod tests {
         assert!(result.markdown.contains("title: \"Custom Title\""));
     }
 
+    #[test]
+    fn convert_kbd_to_inline_code() {
+        let html = "<html><body><main><h1>Keys</h1><p>Press <kbd>Ctrl+C</kbd> to stop.</p></main></body></html>";
+        let result = convert(html, &make_opts("https://example.com/keys")).unwrap();
+        assert!(result.markdown.contains("Press `Ctrl+C` to stop."), "{}", result.markdown);
+    }
+
+    #[test]
+    fn convert_nested_kbd_to_single_code_span() {
+        let html = "<html><body><main><p>Press <kbd><kbd>Ctrl</kbd>+<kbd>Shift</kbd>+<kbd>P</kbd></kbd>.</p></main></body></html>";
+        let result = convert(html, &make_opts("https://example.com/keys")).unwrap();
+        assert!(result.markdown.contains("Press `Ctrl+Shift+P`."), "{}", result.markdown);
+    }
+
+    #[test]
+    fn convert_samp_to_inline_code() {
+        let html = "<html><body><main><h1>Output</h1><p>The tool prints <samp>Build succeeded &lt;ok&gt;</samp> when done.</p></main></body></html>";
+        let result = convert(html, &make_opts("https://example.com/out")).unwrap();
+        assert!(
+            result.markdown.contains("prints `Build succeeded <ok>` when done."),
+            "{}",
+            result.markdown
+        );
+    }
+
+    #[test]
+    fn convert_linked_image_keeps_nested_structure() {
+        let html = r#"<html><body><main><p><a href="../start"><img src="/assets/badge.svg" alt="Badge"></a></p></main></body></html>"#;
+        let result = convert(html, &make_opts("https://example.com/docs/guide/page")).unwrap();
+        assert!(
+            result.markdown.contains(
+                "[![Badge](https://example.com/assets/badge.svg)](https://example.com/docs/start)"
+            ),
+            "{}",
+            result.markdown
+        );
+    }
+
     // --- Fixture-based tests ---
 
     #[test]
@@ -590,4 +1298,183 @@ mod tests {
         // Word count should be small (just "One two three." + "Title")
         assert!(result.word_count < 10, "word_count={} should exclude code", result.word_count);
     }
+
+    #[test]
+    fn convert_strips_configured_selectors() {
+        let html = r#"<html><body><main>
+            <div class="cookie-banner"><p>We use cookies.</p><button>Accept</button></div>
+            <h1>Guide</h1>
+            <p>Real content.</p>
+        </main></body></html>"#;
+        let opts = ConvertOptions {
+            strip_selectors: vec![".cookie-banner".into(), "div[".into()],
+            ..make_opts("https://example.com/guide")
+        };
+
+        let result = convert(html, &opts).unwrap();
+        assert!(!result.markdown.contains("cookies"));
+        assert!(!result.markdown.contains("Accept"));
+        assert!(result.markdown.contains("Real content."));
+
+        let extracted = convert_extracted(
+            r#"<div class="cookie-banner">We use cookies.</div><p>Body.</p>"#,
+            &opts,
+        )
+        .unwrap();
+        assert!(!extracted.markdown.contains("cookies"));
+        assert!(extracted.markdown.contains("Body."));
+    }
+
+    #[test]
+    fn convert_with_trace_records_each_pass() {
+        let html = r#"<html><body><main>
+            <h1>Guide</h1>
+            <p>See the <a href="/install">install page</a>.</p>
+        </main></body></html>"#;
+        let opts = make_opts("https://docs.example.com/guide");
+
+        let (result, trace) = convert_with_trace(html, &opts).unwrap();
+        assert_eq!(result.markdown, convert(html, &opts).unwrap().markdown);
+        assert_eq!(trace.len(), 7);
+        assert!(result.markdown.ends_with(&trace.last().unwrap().after));
+
+        let resolve = trace.iter().find(|d| d.pass == "resolve_links").unwrap();
+        assert!(resolve.changed());
+        assert_eq!(
+            resolve.diff_lines(),
+            [
+                "-See the [install page](/install).",
+                "+See the [install page](https://docs.example.com/install).",
+            ]
+        );
+
+        let fences = trace.iter().find(|d| d.pass == "fix_code_block_languages").unwrap();
+        assert!(!fences.changed());
+        assert!(fences.diff_lines().is_empty());
+    }
+
+    #[test]
+    fn convert_preserves_source_heading_anchors() {
+        let html = r##"<html><body><main>
+            <h1 id="top">Guide</h1>
+            <p>Jump to <a href="#setup_1">setup</a>.</p>
+            <h2 id="setup_1">Install it</h2>
+            <p>Run the installer.</p>
+            <h2><a name="conf"></a>Configuration</h2>
+            <h2 id="usage">Usage</h2>
+        </main></body></html>"##;
+        let opts = ConvertOptions {
+            preserve_anchors: true,
+            ..make_opts("https://docs.example.com/guide")
+        };
+
+        let result = convert(html, &opts).unwrap();
+        let md = &result.markdown;
+        assert_eq!(result.title, "Guide");
+        assert!(md.contains("[setup](#setup_1)"), "{md}");
+        assert!(md.contains("\n## Install it {#setup_1}\n"), "{md}");
+        assert!(md.contains("\n## Configuration {#conf}\n"), "{md}");
+        assert!(md.contains("\n## Usage\n"), "{md}");
+
+        let anchors: Vec<String> = parse_headings(md).into_iter().map(|h| h.anchor).collect();
+        assert_eq!(anchors, ["top", "setup_1", "conf", "usage"]);
+
+        let plain = convert(html, &make_opts("https://docs.example.com/guide")).unwrap();
+        assert!(!plain.markdown.contains("{#"));
+    }
+
+    #[test]
+    fn convert_truncates_long_titles() {
+        let html = "<html><body><main><h1>How to configure the widget server for high \
+            availability deployments</h1><p>Body.</p></main></body></html>";
+        let opts = ConvertOptions {
+            max_title_len: Some(30),
+            ..make_opts("https://example.com/ha")
+        };
+
+        let result = convert(html, &opts).unwrap();
+        assert_eq!(result.title, "How to configure the widget…");
+        assert!(result.markdown.contains("title: \"How to configure the widget…\""));
+        assert_eq!(
+            result.full_title.as_deref(),
+            Some("How to configure the widget server for high availability deployments")
+        );
+        // The heading in the body is left alone
+        assert!(result.markdown.contains("# How to configure the widget server"));
+
+        let short = "<html><body><main><h1>Install</h1><p>Body.</p></main></body></html>";
+        let result = convert(short, &opts).unwrap();
+        assert_eq!(result.title, "Install");
+        assert_eq!(result.full_title, None);
+    }
+
+    #[test]
+    fn convert_falls_back_to_densest_block() {
+        let html = r#"<html><body>
+            <div class="site-menu">
+                <p><a href="/pricing">Pricing plans for every team size</a></p>
+                <p><a href="/blog">Read the latest posts from our blog</a></p>
+            </div>
+            <div id="page">
+                <h1>Widget Guide</h1>
+                <p>Widgets are configured through a single file, which lives next to your project root.</p>
+                <p>Each widget has a name, a size, and a colour, all of which are optional.</p>
+            </div>
+            <div class="site-footer"><p>Copyright 2024, Example Corp. All rights reserved.</p></div>
+        </body></html>"#;
+
+        let result = convert(html, &make_opts("https://example.com/widgets")).unwrap();
+        assert_eq!(result.title, "Widget Guide");
+        assert!(result.markdown.contains("a name, a size, and a colour"));
+        assert!(!result.markdown.contains("Pricing plans"));
+        assert!(!result.markdown.contains("Copyright"));
+    }
+
+    #[test]
+    fn reclean_collapses_blank_lines_and_keeps_frontmatter() {
+        let frontmatter = "---\nsource_url: \"https://example.com/a\"\ntitle: \"A\"\n---\n";
+        let md = format!("{frontmatter}\n# A\n\n\n\n\n\nBody.   \n");
+
+        let recleaned = reclean_markdown(&md, None);
+        assert_eq!(recleaned, format!("{frontmatter}\n# A\n\n\nBody.\n"));
+        assert_eq!(reclean_markdown(&recleaned, None), recleaned);
+    }
+
+    #[test]
+    fn markdown_source_keeps_its_frontmatter_when_asked() {
+        let source = "---\ntitle: \"Their title\"\nsource_url: \"https://mirror.example.com\"\n\
+                      tags:\n  - cli\n  - setup\n---\n\n# Setup\n\nRun `tool init`.\n";
+        let opts = ConvertOptions {
+            preserve_source_frontmatter: true,
+            ..make_opts("https://example.com/docs/setup.md")
+        };
+
+        let result = convert(source, &opts).unwrap();
+        assert_eq!(result.title, "Setup");
+        let md = &result.markdown;
+        assert!(md.starts_with(
+            "---\nsource_url: \"https://example.com/docs/setup.md\"\ntitle: \"Setup\"\n\
+             tags:\n  - cli\n  - setup\n---\n\n# Setup\n\nRun `tool init`."
+        ), "{md}");
+        assert_eq!(md.matches("---\n").count(), 2, "{md}");
+
+        // Without the option the source block is dropped, not turned into text
+        let result = convert(source, &make_opts("https://example.com/docs/setup.md")).unwrap();
+        assert!(!result.markdown.contains("tags:"));
+        assert!(!result.markdown.contains("Their title"));
+        assert!(result.markdown.contains("---\n\n# Setup\n"));
+    }
+
+    #[test]
+    fn markdown_source_with_crlf_line_endings_is_split_from_its_frontmatter() {
+        let source = "---\r\ntitle: Setup\r\ntags: [cli]\r\n---\r\n\r\n# Setup\r\n\r\nRun it.\r\n";
+        let opts = ConvertOptions {
+            preserve_source_frontmatter: true,
+            ..make_opts("https://example.com/docs/setup.md")
+        };
+
+        let md = convert(source, &opts).unwrap().markdown;
+        assert!(md.contains("\ntags: [cli]\n---\n\n# Setup\n\nRun it."), "{md}");
+        assert!(!md.contains("## title") && !md.contains('\r'), "{md}");
+    }
 }
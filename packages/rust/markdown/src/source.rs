@@ -0,0 +1,143 @@
+//! Markdown sources: `.md` endpoints served as-is instead of HTML pages.
+//!
+//! Many sites (and most `llms.txt` links) serve their pages' Markdown
+//! directly. Such a body skips the HTML steps of the conversion, and a YAML
+//! frontmatter block at its top is split off rather than being read as text.
+//! With `preserve_source_frontmatter`, its fields are merged into the page's
+//! generated frontmatter: generated fields win, the rest are kept verbatim.
+
+use url::Url;
+
+use crate::headings;
+
+/// Whether `body`, fetched from `source_url`, is a Markdown file: the URL
+/// ends in `.md` or `.markdown` and the body is not markup.
+pub fn is_markdown_source(source_url: &str, body: &str) -> bool {
+    let Ok(url) = Url::parse(source_url) else {
+        return false;
+    };
+    let path = url.path().to_ascii_lowercase();
+    (path.ends_with(".md") || path.ends_with(".markdown")) && !body.trim_start().starts_with('<')
+}
+
+/// Split a Markdown source (with `\n` line endings) into the inside of its
+/// frontmatter block (if it has one) and its body. The closing `---` may
+/// end the file.
+pub(crate) fn split_frontmatter(md: &str) -> (Option<&str>, &str) {
+    let md = md.strip_prefix('\u{feff}').unwrap_or(md);
+    match headings::frontmatter_len(md) {
+        0 => match md.strip_prefix("---\n").and_then(|rest| rest.strip_suffix("\n---")) {
+            Some(inside) => (Some(&md[4..4 + inside.len() + 1]), ""),
+            None => (None, md),
+        },
+        len => (Some(&md[4..len - 4]), md[len..].trim_start_matches('\n')),
+    }
+}
+
+/// The `title:` of a source frontmatter block, unquoted.
+pub(crate) fn frontmatter_title(source: &str) -> Option<String> {
+    fields(source).into_iter().find(|(key, _)| *key == "title").and_then(|(_, field)| {
+        let value = field.split_once(':')?.1.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// `generated` (a complete `---` frontmatter block) with every field of
+/// `source` whose key it does not already have appended, in source order.
+pub(crate) fn merge_frontmatter(generated: &str, source: &str) -> String {
+    let taken: Vec<&str> = fields(generated.trim_start_matches("---\n"))
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    let Some(body) = generated.strip_suffix("---\n") else {
+        return generated.to_string();
+    };
+
+    let mut merged = body.to_string();
+    for (key, field) in fields(source) {
+        if !taken.contains(&key) {
+            merged.push_str(field.trim_end_matches('\n'));
+            merged.push('\n');
+        }
+    }
+    merged.push_str("---\n");
+    merged
+}
+
+/// The top-level fields of a YAML block as `(key, text)`, where the text
+/// runs from the key's line up to the next top-level key (so it includes
+/// nested mappings and list items). Comments and stray lines before the
+/// first key are dropped.
+fn fields(yaml: &str) -> Vec<(&str, &str)> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in yaml.split_inclusive('\n') {
+        if let Some(key) = top_level_key(line) {
+            starts.push((key, offset));
+        }
+        offset += line.len();
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &(key, start))| {
+            let end = starts.get(i + 1).map_or(yaml.len(), |&(_, next)| next);
+            (key, &yaml[start..end])
+        })
+        .collect()
+}
+
+/// The key of a line that starts a top-level `key: value` field.
+fn top_level_key(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t', '-', '#']) {
+        return None;
+    }
+    let (key, _) = line.split_once(':')?;
+    let key = key.trim();
+    (!key.is_empty() && !key.contains(char::is_whitespace)).then_some(key)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_markdown_sources_by_extension_and_body() {
+        assert!(is_markdown_source("https://x.dev/docs/setup.md", "# Setup\n"));
+        assert!(is_markdown_source("https://x.dev/README.markdown?raw=1", "---\na: b\n---\n"));
+        assert!(!is_markdown_source("https://x.dev/docs/setup.md", "<!doctype html><html>"));
+        assert!(!is_markdown_source("https://x.dev/docs/setup", "# Setup\n"));
+    }
+
+    #[test]
+    fn merges_source_fields_the_generated_block_lacks() {
+        let source = "title: Theirs\ntags:\n  - cli\n  - setup\n# owner\nsidebar_position: 2\n";
+        let md = format!("---\n{source}---\n\n# Setup\n");
+        let (fm, body) = split_frontmatter(&md);
+        assert_eq!((fm, body), (Some(source), "# Setup\n"));
+        assert_eq!(frontmatter_title(source).as_deref(), Some("Theirs"));
+
+        let generated = "---\nsource_url: \"https://x.dev/setup.md\"\ntitle: \"Ours\"\n---\n";
+        assert_eq!(
+            merge_frontmatter(generated, source),
+            "---\nsource_url: \"https://x.dev/setup.md\"\ntitle: \"Ours\"\n\
+             tags:\n  - cli\n  - setup\n# owner\nsidebar_position: 2\n---\n"
+        );
+    }
+
+    #[test]
+    fn splits_a_frontmatter_block_closed_at_the_end_of_the_file() {
+        assert_eq!(split_frontmatter("---\ntitle: Empty\n---"), (Some("title: Empty\n"), ""));
+        assert_eq!(split_frontmatter("---\ntitle: Open\n"), (None, "---\ntitle: Open\n"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Inline `<svg>` diagrams.
+//!
+//! By default `svg` is a skipped tag, so diagrams drawn inline vanish from
+//! the Markdown. With [`InlineSvg::Code`] each one is kept as a fenced `svg`
+//! block; with [`InlineSvg::Asset`] it becomes an image whose source is a
+//! `data:image/svg+xml` URI, which the asset step writes out to a file.
+
+use std::sync::LazyLock;
+
+use contextbuilder_shared::InlineSvg;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use regex::{Captures, Regex};
+
+use crate::escape_html_text;
+
+/// Prefix of the data URIs emitted for [`InlineSvg::Asset`].
+pub const SVG_DATA_URI_PREFIX: &str = "data:image/svg+xml,";
+
+/// An outermost `<svg>...</svg>` element (nested SVGs are rare in docs).
+static SVG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<svg\b.*?</svg\s*>").expect("valid regex"));
+
+/// `aria-label` on the opening tag; group 1 is the value.
+static ARIA_LABEL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^<svg\b[^>]*\baria-label\s*=\s*"([^"]*)""#).expect("valid regex")
+});
+
+/// The SVG's `<title>`; group 1 is its text.
+static TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").expect("valid regex"));
+
+/// Rewrite every inline `<svg>` in `html` for `mode`. [`InlineSvg::Skip`]
+/// leaves the HTML as is, for the converter to drop.
+pub(crate) fn preprocess_inline_svg(html: &str, mode: InlineSvg) -> String {
+    if mode == InlineSvg::Skip || !SVG_RE.is_match(html) {
+        return html.to_string();
+    }
+    SVG_RE
+        .replace_all(html, |caps: &Captures| {
+            let svg = &caps[0];
+            match mode {
+                InlineSvg::Asset => {
+                    let uri = format!(
+                        "{SVG_DATA_URI_PREFIX}{}",
+                        utf8_percent_encode(&standalone(svg), NON_ALPHANUMERIC)
+                    );
+                    let alt = escape_html_text(&label(svg)).replace('"', "&quot;");
+                    format!(r#"<img src="{uri}" alt="{alt}">"#)
+                }
+                InlineSvg::Code | InlineSvg::Skip => format!(
+                    r#"<pre><code class="language-svg">{}</code></pre>"#,
+                    escape_html_text(svg.trim())
+                ),
+            }
+        })
+        .into_owned()
+}
+
+/// The SVG's accessible name: its `aria-label`, else its `<title>`, else
+/// "Diagram".
+fn label(svg: &str) -> String {
+    let label = ARIA_LABEL_RE
+        .captures(svg)
+        .or_else(|| TITLE_RE.captures(svg))
+        .map(|caps| caps[1].split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    if label.is_empty() {
+        "Diagram".to_string()
+    } else {
+        label
+    }
+}
+
+/// `svg` as a standalone document: inline SVG may omit the namespace, which a
+/// `.svg` file needs to render.
+fn standalone(svg: &str) -> String {
+    let svg = svg.trim();
+    let open_tag = &svg[..svg.find('>').unwrap_or(svg.len())];
+    if open_tag.contains("xmlns=") {
+        svg.to_string()
+    } else {
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg"{}"#, &svg[4..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = "<svg viewBox=\"0 0 10 10\"><title>Request flow</title>\
+                       <path d=\"M0 0L10 10\"/></svg>";
+
+    #[test]
+    fn skip_leaves_svg_for_the_converter() {
+        let html = format!("<p>Before</p>{SVG}");
+        assert_eq!(preprocess_inline_svg(&html, InlineSvg::Skip), html);
+    }
+
+    #[test]
+    fn code_escapes_the_markup_into_a_block() {
+        let out = preprocess_inline_svg(&format!("<p>Flow</p>{SVG}"), InlineSvg::Code);
+        assert_eq!(
+            out,
+            "<p>Flow</p><pre><code class=\"language-svg\">&lt;svg viewBox=\"0 0 10 10\"&gt;\
+             &lt;title&gt;Request flow&lt;/title&gt;&lt;path d=\"M0 0L10 10\"/&gt;&lt;/svg&gt;\
+             </code></pre>"
+        );
+    }
+
+    /// The decoded SVG and alt text of an `<img>` emitted for `Asset`.
+    fn image_parts(img: &str) -> (String, String) {
+        let caps = Regex::new(r#"^<img src="([^"]*)" alt="([^"]*)">$"#)
+            .unwrap()
+            .captures(img)
+            .expect(img);
+        let encoded = caps[1].strip_prefix(SVG_DATA_URI_PREFIX).expect(img);
+        let svg = percent_encoding::percent_decode_str(encoded).decode_utf8().unwrap();
+        (svg.into_owned(), caps[2].to_string())
+    }
+
+    #[test]
+    fn asset_becomes_a_labeled_data_uri_image() {
+        let (svg, alt) = image_parts(&preprocess_inline_svg(SVG, InlineSvg::Asset));
+        assert_eq!(alt, "Request flow");
+        assert_eq!(svg, SVG.replacen("<svg", "<svg xmlns=\"http://www.w3.org/2000/svg\"", 1));
+
+        let labeled = "<svg aria-label=\"Arch\" xmlns=\"http://www.w3.org/2000/svg\"></svg>";
+        let (svg, alt) = image_parts(&preprocess_inline_svg(labeled, InlineSvg::Asset));
+        assert_eq!((svg.as_str(), alt.as_str()), (labeled, "Arch"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Code-tab group detection.
+//!
+//! Docs often show one example in several languages as tabs: a tab list
+//! (`[role=tablist]`, or VitePress's `.vp-code-group` labels) plus one panel
+//! per language. Converted as-is, the tab labels become a stray list and the
+//! panels run together (or the hidden ones are lost). [`preprocess_code_tabs`]
+//! rewrites each group as its panels in order, each under a bold label.
+
+use scraper::{ElementRef, Html, Selector};
+
+use crate::escape_html_text;
+
+/// A detected group: the element to replace and its `(label, panel)` pairs.
+struct TabGroup<'a> {
+    element: ElementRef<'a>,
+    tabs: Vec<(String, ElementRef<'a>)>,
+}
+
+/// Replace every code-tab group in `html` with its labeled panels.
+///
+/// Only groups whose every panel holds a code block are rewritten, so prose
+/// tabs keep their markup.
+pub(crate) fn preprocess_code_tabs(html: &str) -> String {
+    let doc = Html::parse_fragment(html);
+    let pre = Selector::parse("pre").expect("valid selector");
+    let groups: Vec<TabGroup<'_>> = aria_groups(&doc)
+        .into_iter()
+        .chain(vitepress_groups(&doc))
+        .filter(|group| {
+            group.tabs.len() >= 2
+                && group.tabs.iter().all(|(_, panel)| panel.select(&pre).next().is_some())
+        })
+        .collect();
+    if groups.is_empty() {
+        return html.to_string();
+    }
+
+    // Match against the serialized fragment, which is what `html()` returns
+    let mut result = doc.root_element().inner_html();
+    for group in groups {
+        let rendered: String = group
+            .tabs
+            .iter()
+            .map(|(label, panel)| {
+                format!(
+                    "<p><strong>{}</strong></p>\n{}\n",
+                    escape_html_text(label),
+                    panel.inner_html()
+                )
+            })
+            .collect();
+        result = result.replacen(&group.element.html(), &rendered, 1);
+    }
+    result
+}
+
+/// Groups marked up with ARIA roles: a `tablist` whose parent holds the
+/// `tabpanel`s. Panels are matched to tabs by `aria-controls` when every tab
+/// has one, and by document order otherwise.
+fn aria_groups(doc: &Html) -> Vec<TabGroup<'_>> {
+    let tablist_sel = Selector::parse("[role=tablist]").expect("valid selector");
+    let tab_sel = Selector::parse("[role=tab]").expect("valid selector");
+    let panel_sel = Selector::parse("[role=tabpanel]").expect("valid selector");
+
+    doc.select(&tablist_sel)
+        .filter_map(|tablist| {
+            let element = tablist.parent().and_then(ElementRef::wrap)?;
+            let tabs: Vec<ElementRef<'_>> = tablist.select(&tab_sel).collect();
+            let panels: Vec<ElementRef<'_>> = element.select(&panel_sel).collect();
+
+            let by_id: Option<Vec<ElementRef<'_>>> = tabs
+                .iter()
+                .map(|tab| {
+                    let target = tab.value().attr("aria-controls")?;
+                    panels.iter().copied().find(|p| p.value().id() == Some(target))
+                })
+                .collect();
+            let panels = by_id.unwrap_or(panels);
+            if panels.len() != tabs.len() {
+                return None;
+            }
+
+            let tabs = tabs.iter().map(|tab| label(*tab)).zip(panels).collect();
+            Some(TabGroup { element, tabs })
+        })
+        .collect()
+}
+
+/// VitePress `::: code-group` blocks: `<label>`s in `.tabs`, one block per
+/// label in `.blocks`.
+fn vitepress_groups(doc: &Html) -> Vec<TabGroup<'_>> {
+    let group_sel = Selector::parse(".vp-code-group").expect("valid selector");
+    let label_sel = Selector::parse(".tabs label").expect("valid selector");
+    let block_sel = Selector::parse(".blocks > *").expect("valid selector");
+
+    doc.select(&group_sel)
+        .filter_map(|element| {
+            let labels: Vec<String> = element.select(&label_sel).map(label).collect();
+            let blocks: Vec<ElementRef<'_>> = element.select(&block_sel).collect();
+            (labels.len() == blocks.len()).then(|| TabGroup {
+                element,
+                tabs: labels.into_iter().zip(blocks).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Whitespace-collapsed text of a tab.
+fn label(tab: ElementRef<'_>) -> String {
+    tab.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aria_tabs_are_matched_by_aria_controls() {
+        let html = r#"<div class="tabs-container">
+            <ul role="tablist">
+                <li role="tab" aria-controls="p-py">Python</li>
+                <li role="tab" aria-controls="p-js">JS</li>
+            </ul>
+            <div role="tabpanel" id="p-js"><pre><code>js()</code></pre></div>
+            <div role="tabpanel" id="p-py" hidden><pre><code>py()</code></pre></div>
+        </div>"#;
+        let out = preprocess_code_tabs(html);
+
+        let python = out.find("<strong>Python</strong>").expect("python label");
+        let py = out.find("py()").expect("python code");
+        let js_label = out.find("<strong>JS</strong>").expect("js label");
+        let js = out.find("js()").expect("js code");
+        assert!(python < py && py < js_label && js_label < js, "{out}");
+        assert!(!out.contains("tablist"), "{out}");
+    }
+
+    #[test]
+    fn vitepress_code_groups_are_labeled() {
+        let html = r#"<div class="vp-code-group"><div class="tabs">
+            <input type="radio" id="t1"><label for="t1">npm</label>
+            <input type="radio" id="t2"><label for="t2">pnpm</label></div>
+            <div class="blocks">
+            <div class="language-sh active"><pre><code>npm i x</code></pre></div>
+            <div class="language-sh"><pre><code>pnpm add x</code></pre></div></div></div>"#;
+        let out = preprocess_code_tabs(html);
+
+        assert!(out.contains("<strong>npm</strong>"), "{out}");
+        assert!(out.contains("<strong>pnpm</strong>"), "{out}");
+        assert!(out.find("npm i x") < out.find("pnpm add x"));
+    }
+
+    #[test]
+    fn prose_tabs_are_left_alone() {
+        let html = r#"<div>
+            <div role="tablist">
+                <button role="tab">Mac</button><button role="tab">Linux</button>
+            </div>
+            <div role="tabpanel"><p>Use brew.</p></div>
+            <div role="tabpanel"><p>Use apt.</p></div></div>"#;
+        assert_eq!(preprocess_code_tabs(html), html);
+    }
+}
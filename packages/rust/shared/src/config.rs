@@ -3,7 +3,9 @@
 //! User config lives at `~/.contextbuilder/contextbuilder.toml`.
 //! CLI flags override config file values, which override defaults.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
@@ -37,6 +39,10 @@ pub struct AppConfig {
     /// Registered knowledge bases.
     #[serde(default)]
     pub kbs: Vec<KbRegistryEntry>,
+
+    /// User-defined enrichment tasks, each producing one extra artifact.
+    #[serde(default)]
+    pub custom_tasks: Vec<CustomTaskConfig>,
 }
 
 /// `[defaults]` section.
@@ -50,13 +56,44 @@ pub struct DefaultsConfig {
     #[serde(default = "default_crawl_depth")]
     pub crawl_depth: u32,
 
-    /// Default concurrent requests.
-    #[serde(default = "default_crawl_concurrency")]
-    pub crawl_concurrency: u32,
+    /// Default concurrent requests (unset = the profile's, else 4).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawl_concurrency: Option<u32>,
 
     /// Discovery/crawl mode.
-    #[serde(default = "default_mode")]
-    pub mode: String,
+    #[serde(default)]
+    pub mode: CrawlMode,
+
+    /// Seconds to wait for bun subprocesses (enrichment bridge, MCP server) to start.
+    #[serde(default = "default_startup_timeout_secs")]
+    pub startup_timeout_secs: u64,
+
+    /// Where artifacts are written inside `artifacts/`.
+    #[serde(default)]
+    pub artifact_layout: ArtifactLayout,
+
+    /// What the generated `llms.txt` links to.
+    #[serde(default)]
+    pub llms_txt_links: LinkBase,
+
+    /// Also write a gzip copy (`<name>.gz`) of every artifact.
+    #[serde(default)]
+    pub compress_artifacts: bool,
+
+    /// What to fill summaries and descriptions with when the enrichment
+    /// bridge cannot start.
+    #[serde(default)]
+    pub summary_fallback: SummaryFallback,
+
+    /// Cap on simultaneous work across crawl fetches, conversions, and
+    /// enrichment (unset = each phase uses only its own limit).
+    #[serde(default)]
+    pub max_parallelism: Option<usize>,
+
+    /// Politeness preset for crawl concurrency and per-host delay; an
+    /// explicit `crawl_concurrency` or `rate_limit_ms` wins over it.
+    #[serde(default)]
+    pub profile: Option<PolitenessProfile>,
 }
 
 impl Default for DefaultsConfig {
@@ -64,8 +101,15 @@ impl Default for DefaultsConfig {
         Self {
             output_dir: default_output_dir(),
             crawl_depth: default_crawl_depth(),
-            crawl_concurrency: default_crawl_concurrency(),
-            mode: default_mode(),
+            crawl_concurrency: None,
+            mode: CrawlMode::default(),
+            startup_timeout_secs: default_startup_timeout_secs(),
+            artifact_layout: ArtifactLayout::default(),
+            llms_txt_links: LinkBase::default(),
+            compress_artifacts: false,
+            summary_fallback: SummaryFallback::default(),
+            max_parallelism: None,
+            profile: None,
         }
     }
 }
@@ -79,8 +123,241 @@ fn default_crawl_depth() -> u32 {
 fn default_crawl_concurrency() -> u32 {
     4
 }
-fn default_mode() -> String {
-    "auto".into()
+fn default_startup_timeout_secs() -> u64 {
+    30
+}
+
+/// How documentation pages are discovered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CrawlMode {
+    /// Try `llms.txt` discovery first, fall back to crawling.
+    #[default]
+    Auto,
+    /// Only use `llms.txt` discovery.
+    LlmsTxt,
+    /// Only crawl, skipping `llms.txt` discovery.
+    Crawl,
+}
+
+impl CrawlMode {
+    /// All modes, in display order.
+    pub const ALL: [CrawlMode; 3] = [Self::Auto, Self::LlmsTxt, Self::Crawl];
+
+    /// Canonical string form (as accepted by the CLI and config file).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::LlmsTxt => "llms-txt",
+            Self::Crawl => "crawl",
+        }
+    }
+}
+
+impl std::fmt::Display for CrawlMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CrawlMode {
+    type Err = ContextBuilderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "llms-txt" => Ok(Self::LlmsTxt),
+            "crawl" => Ok(Self::Crawl),
+            other => Err(ContextBuilderError::config(format!(
+                "unknown mode '{other}': expected one of {}",
+                Self::ALL.map(|m| m.as_str()).join(", ")
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for CrawlMode {
+    type Error = ContextBuilderError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<CrawlMode> for String {
+    fn from(mode: CrawlMode) -> Self {
+        mode.as_str().to_string()
+    }
+}
+
+/// Directory layout of the files in a KB's `artifacts/` directory.
+///
+/// The non-flat layouts place the rule and skill artifacts where an AI tool
+/// picks them up, so `artifacts/` can be copied into a project as-is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ArtifactLayout {
+    /// Every artifact directly in `artifacts/`.
+    #[default]
+    Flat,
+    /// Rules, style, and do/don't under `.cursor/rules/`.
+    Cursor,
+    /// A Claude skill directory: `.claude/skills/<kb>/` with `SKILL.md`
+    /// and the rule files next to it.
+    Claude,
+    /// Copilot instructions: `.github/copilot-instructions.md` plus
+    /// `.github/instructions/*.instructions.md`.
+    Vscode,
+}
+
+impl ArtifactLayout {
+    /// All layouts, in display order.
+    pub const ALL: [ArtifactLayout; 4] = [Self::Flat, Self::Cursor, Self::Claude, Self::Vscode];
+
+    /// Canonical string form (as accepted by the CLI and config file).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Cursor => "cursor",
+            Self::Claude => "claude",
+            Self::Vscode => "vscode",
+        }
+    }
+
+    /// Path of artifact `filename` relative to `artifacts/`, for the KB
+    /// named `kb_name`. Files a layout has no place for stay flat.
+    pub fn artifact_path(&self, filename: &str, kb_name: &str) -> String {
+        const RULE_FILES: [&str; 3] = ["rules.md", "style.md", "do_dont.md"];
+        match self {
+            Self::Cursor if RULE_FILES.contains(&filename) => format!(".cursor/rules/{filename}"),
+            Self::Claude if filename == "SKILL.md" || RULE_FILES.contains(&filename) => {
+                format!(".claude/skills/{}/{filename}", skill_dir_name(kb_name))
+            }
+            Self::Vscode if filename == "SKILL.md" => ".github/copilot-instructions.md".into(),
+            Self::Vscode if RULE_FILES.contains(&filename) => format!(
+                ".github/instructions/{}.instructions.md",
+                filename.trim_end_matches(".md")
+            ),
+            _ => filename.to_string(),
+        }
+    }
+}
+
+/// Lowercase, dash-separated form of a KB name for a skill directory.
+fn skill_dir_name(kb_name: &str) -> String {
+    let slug = kb_name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "docs".to_string() } else { slug }
+}
+
+impl std::fmt::Display for ArtifactLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ArtifactLayout {
+    type Err = ContextBuilderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|l| l.as_str() == s).ok_or_else(|| {
+            ContextBuilderError::config(format!(
+                "unknown artifact layout '{s}': expected one of {}",
+                Self::ALL.map(|l| l.as_str()).join(", ")
+            ))
+        })
+    }
+}
+
+impl TryFrom<String> for ArtifactLayout {
+    type Error = ContextBuilderError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<ArtifactLayout> for String {
+    fn from(layout: ArtifactLayout) -> Self {
+        layout.as_str().to_string()
+    }
+}
+
+/// A bundle of crawl politeness settings, so users need not tune concurrency
+/// and per-host delay one by one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PolitenessProfile {
+    /// One request at a time, a second apart: for small or fragile hosts.
+    Gentle,
+    /// The default concurrency and delay.
+    Balanced,
+    /// Many simultaneous requests with no delay: for large CDN-backed sites.
+    Aggressive,
+}
+
+impl PolitenessProfile {
+    /// All profiles, in display order.
+    pub const ALL: [PolitenessProfile; 3] = [Self::Gentle, Self::Balanced, Self::Aggressive];
+
+    /// Canonical string form (as accepted by the CLI and config file).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gentle => "gentle",
+            Self::Balanced => "balanced",
+            Self::Aggressive => "aggressive",
+        }
+    }
+
+    /// Set the profile's concurrency and per-host delay on `crawl`.
+    pub fn apply(&self, crawl: &mut CrawlConfig) {
+        let (concurrency, rate_limit_ms) = match self {
+            Self::Gentle => (1, 1000),
+            Self::Balanced => (default_crawl_concurrency(), default_rate_limit()),
+            Self::Aggressive => (8, 0),
+        };
+        crawl.concurrency = concurrency;
+        crawl.rate_limit_ms = rate_limit_ms;
+    }
+}
+
+impl std::fmt::Display for PolitenessProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for PolitenessProfile {
+    type Err = ContextBuilderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|p| p.as_str() == s).ok_or_else(|| {
+            ContextBuilderError::config(format!(
+                "unknown profile '{s}': expected one of {}",
+                Self::ALL.map(|p| p.as_str()).join(", ")
+            ))
+        })
+    }
+}
+
+impl TryFrom<String> for PolitenessProfile {
+    type Error = ContextBuilderError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<PolitenessProfile> for String {
+    fn from(profile: PolitenessProfile) -> Self {
+        profile.as_str().to_string()
+    }
 }
 
 /// `[openrouter]` section.
@@ -126,9 +403,199 @@ pub struct CrawlPoliciesConfig {
     #[serde(default = "default_true")]
     pub respect_robots_txt: bool,
 
-    /// Minimum ms between requests to the same host.
-    #[serde(default = "default_rate_limit")]
-    pub rate_limit_ms: u64,
+    /// Minimum ms between requests to the same host (unset = the profile's,
+    /// else 200).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_ms: Option<u64>,
+
+    /// Maximum outbound links enqueued from a single page (0 = unlimited).
+    #[serde(default)]
+    pub max_links_per_page: usize,
+
+    /// Most URLs waiting in the crawl queue; links found while it is full are
+    /// not enqueued (0 = unlimited).
+    #[serde(default = "default_max_queue_size")]
+    pub max_queue_size: usize,
+
+    /// Most pages fetched by a crawl; the crawl stops once it is reached
+    /// (0 = unlimited).
+    #[serde(default)]
+    pub max_pages: usize,
+
+    /// Extra attempts at llms.txt discovery after a transient failure
+    /// (network error, HTTP 429 or 5xx) before falling back to a crawl.
+    #[serde(default = "default_discovery_retries")]
+    pub discovery_retries: u32,
+
+    /// Delay in ms before each discovery retry, doubled per attempt.
+    #[serde(default = "default_discovery_retry_delay_ms")]
+    pub discovery_retry_delay_ms: u64,
+
+    /// What the page content hash is computed over.
+    #[serde(default)]
+    pub hash_basis: HashBasis,
+
+    /// CSS selectors that drop a page when any element matches.
+    #[serde(default)]
+    pub drop_if_selector_matches: Vec<String>,
+
+    /// Fold `description` / `keywords` HTML comments into page frontmatter.
+    #[serde(default)]
+    pub comment_metadata: bool,
+
+    /// Keep the fields of a Markdown source's own frontmatter (`tags:`,
+    /// `sidebar_position:`, ...) alongside the generated ones.
+    #[serde(default)]
+    pub preserve_source_frontmatter: bool,
+
+    /// Convert linked PDFs (`application/pdf`) to pages instead of skipping them.
+    #[serde(default)]
+    pub ingest_pdfs: bool,
+
+    /// Download images referenced by pages into `assets/` and link them
+    /// locally, for a KB that renders offline.
+    #[serde(default)]
+    pub download_assets: bool,
+
+    /// Largest asset downloaded, in bytes; bigger ones keep their remote link.
+    #[serde(default = "default_max_asset_bytes")]
+    pub max_asset_bytes: u64,
+
+    /// What happens to inline `<svg>` diagrams.
+    #[serde(default)]
+    pub inline_svg: InlineSvg,
+
+    /// Detect each page's language and record it in the frontmatter.
+    #[serde(default)]
+    pub detect_language: bool,
+
+    /// Keep only pages detected as one of these ISO 639-3 codes (e.g. `eng`);
+    /// empty keeps every language. Implies `detect_language`.
+    #[serde(default)]
+    pub languages: Vec<String>,
+
+    /// Idle keep-alive connections kept per host for reuse (0 disables pooling).
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+
+    /// TCP keep-alive interval in seconds for pooled connections.
+    #[serde(default = "default_tcp_keepalive_secs")]
+    pub tcp_keepalive_secs: u64,
+
+    /// Slow down a host (fewer concurrent requests, longer delay) after
+    /// 429/5xx responses, recovering as requests succeed.
+    #[serde(default = "default_true")]
+    pub adaptive_throttle: bool,
+
+    /// Upper bound in ms for a host's back-off delay.
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    /// Redirects followed per request before it fails as a redirect-limit error.
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+
+    /// Largest `llms-full.txt` accepted by discovery, in bytes (after
+    /// decompression).
+    #[serde(default = "default_max_full_size")]
+    pub max_full_size: u64,
+
+    /// Fingerprint the site's 404 page and drop pages served with HTTP 200
+    /// that match it.
+    #[serde(default)]
+    pub detect_soft_404: bool,
+
+    /// Seed the crawl with the pages listed in sitemaps that `robots.txt`
+    /// declares with `Sitemap:` lines.
+    #[serde(default = "default_true")]
+    pub robots_sitemaps: bool,
+
+    /// CSS selectors whose elements are removed from page content before
+    /// conversion (e.g. `.cookie-banner`), on top of the built-in chrome list.
+    #[serde(default)]
+    pub strip_selectors: Vec<String>,
+
+    /// Truncate page titles longer than this many characters at a word
+    /// boundary, ending with `…` (unset = no limit).
+    #[serde(default)]
+    pub max_title_len: Option<usize>,
+
+    /// Split pages whose Markdown is larger than this many bytes at their
+    /// top-level headings into an index page and one sub-page per section
+    /// (unset = never split).
+    #[serde(default)]
+    pub split_large_pages: Option<usize>,
+
+    /// `[pattern, replacement]` regex rewrites applied in order to resolved
+    /// link targets in converted pages, to canonicalize vanity or
+    /// inconsistent URLs.
+    #[serde(default)]
+    pub link_rewrites: Vec<(String, String)>,
+
+    /// Pages converted to Markdown in parallel (unset = available
+    /// parallelism).
+    #[serde(default)]
+    pub convert_concurrency: Option<usize>,
+
+    /// CSS selectors that skip a page entirely when any element matches: it
+    /// is neither stored nor crawled for links.
+    #[serde(default)]
+    pub skip_page_selectors: Vec<String>,
+
+    /// Query parameters that select distinct content (`page`, `version`).
+    /// They are kept in the dedup key and the page path; all other query
+    /// parameters are ignored when deduplicating.
+    #[serde(default)]
+    pub significant_query_params: Vec<String>,
+
+    /// Treat links that only change the current page's query string (e.g.
+    /// `?tab=x`, as client-side routers emit) as in-page navigation rather
+    /// than new pages, unless a significant query parameter changes.
+    #[serde(default = "default_true")]
+    pub query_links_as_anchors: bool,
+
+    /// Treat `http://` and `https://` URLs of a page as the same page when
+    /// deduplicating.
+    #[serde(default = "default_true")]
+    pub prefer_https: bool,
+
+    /// Treat `www.<host>` and `<host>` as the same host, both for
+    /// deduplication and for the crawl scope.
+    #[serde(default)]
+    pub strip_www: bool,
+
+    /// Collapse adjacent identical list items and back-to-back duplicate
+    /// links in converted pages.
+    #[serde(default)]
+    pub dedupe_adjacent_items: bool,
+
+    /// Keep source heading ids that differ from generated slugs as `{#id}`,
+    /// so in-page `#id` links keep resolving.
+    #[serde(default)]
+    pub preserve_anchors: bool,
+
+    /// Deepest TOC level kept; deeper entries are lifted to this level with
+    /// their ancestors' titles as a prefix (unset = no limit).
+    #[serde(default)]
+    pub max_toc_depth: Option<usize>,
+
+    /// Drop TOC section entries left without a page or any descendant page,
+    /// e.g. after every child was filtered out.
+    #[serde(default)]
+    pub prune_empty_sections: bool,
+
+    /// Fetch the links of an llms.txt `## Optional` section (after all
+    /// other links).
+    #[serde(default = "default_true")]
+    pub include_optional_links: bool,
+
+    /// HTTP connect / read / total timeouts.
+    #[serde(flatten)]
+    pub timeouts: HttpTimeouts,
+
+    /// User agent, headers, cookies, and proxy for private sites.
+    #[serde(flatten)]
+    pub auth: HttpAuth,
 }
 
 impl Default for CrawlPoliciesConfig {
@@ -137,17 +604,246 @@ impl Default for CrawlPoliciesConfig {
             include_patterns: Vec::new(),
             exclude_patterns: Vec::new(),
             respect_robots_txt: true,
-            rate_limit_ms: default_rate_limit(),
+            rate_limit_ms: None,
+            max_links_per_page: 0,
+            max_queue_size: default_max_queue_size(),
+            max_pages: 0,
+            discovery_retries: default_discovery_retries(),
+            discovery_retry_delay_ms: default_discovery_retry_delay_ms(),
+            hash_basis: HashBasis::default(),
+            drop_if_selector_matches: Vec::new(),
+            comment_metadata: false,
+            preserve_source_frontmatter: false,
+            ingest_pdfs: false,
+            download_assets: false,
+            max_asset_bytes: default_max_asset_bytes(),
+            inline_svg: InlineSvg::default(),
+            detect_language: false,
+            languages: Vec::new(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            tcp_keepalive_secs: default_tcp_keepalive_secs(),
+            adaptive_throttle: true,
+            max_backoff_ms: default_max_backoff_ms(),
+            max_redirects: default_max_redirects(),
+            max_full_size: default_max_full_size(),
+            detect_soft_404: false,
+            robots_sitemaps: true,
+            strip_selectors: Vec::new(),
+            max_title_len: None,
+            split_large_pages: None,
+            link_rewrites: Vec::new(),
+            convert_concurrency: None,
+            skip_page_selectors: Vec::new(),
+            significant_query_params: Vec::new(),
+            query_links_as_anchors: true,
+            prefer_https: true,
+            strip_www: false,
+            dedupe_adjacent_items: false,
+            preserve_anchors: false,
+            max_toc_depth: None,
+            prune_empty_sections: false,
+            include_optional_links: true,
+            timeouts: HttpTimeouts::default(),
+            auth: HttpAuth::default(),
+        }
+    }
+}
+
+/// HTTP client timeouts, applied to every discovery and crawl client.
+///
+/// `read_timeout_secs` resets on every received chunk, so a large body that
+/// keeps streaming is bounded only by the overall `timeout_secs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpTimeouts {
+    /// Seconds to wait for the TCP/TLS connection.
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+
+    /// Seconds to wait between reads before a stalled response is abandoned.
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+
+    /// Seconds allowed for a whole request, including the body.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl HttpTimeouts {
+    /// Connect timeout as a [`Duration`].
+    pub fn connect(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    /// Per-read timeout as a [`Duration`].
+    pub fn read(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs)
+    }
+
+    /// Overall request timeout as a [`Duration`].
+    pub fn total(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Reject zero timeouts, which would fail every request at once.
+    pub fn validate(&self) -> Result<()> {
+        let fields = [
+            ("connect_timeout_secs", self.connect_timeout_secs),
+            ("read_timeout_secs", self.read_timeout_secs),
+            ("timeout_secs", self.timeout_secs),
+        ];
+        match fields.iter().find(|(_, secs)| *secs == 0) {
+            Some((name, _)) => Err(ContextBuilderError::config(format!(
+                "crawl_policies.{name} must be at least 1 second"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+fn default_read_timeout_secs() -> u64 {
+    30
+}
+fn default_timeout_secs() -> u64 {
+    300
+}
+
+/// Identity and credentials sent with every discovery and crawl request, so
+/// docs behind a login or a corporate proxy can be reached.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpAuth {
+    /// `User-Agent` to send instead of `ContextBuilder/<version>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+
+    /// Extra request headers, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Cookies sent as a single `Cookie` header.
+    #[serde(default)]
+    pub cookies: BTreeMap<String, String>,
+
+    /// Proxy URL for all requests (`http://`, `https://`, or `socks5://`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+impl HttpAuth {
+    /// Headers to send with every request, with `cookies` folded into a
+    /// `Cookie` header.
+    pub fn header_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> =
+            self.headers.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        if !self.cookies.is_empty() {
+            let cookie = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join("; ");
+            pairs.push(("Cookie".to_string(), cookie));
         }
+        pairs
     }
 }
 
+/// Link targets used in a generated `llms.txt`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkBase {
+    /// Each page's source URL.
+    #[default]
+    SourceUrls,
+    /// The page's Markdown file in the KB (`../docs/<path>.md`, relative to
+    /// `artifacts/llms.txt`), for offline use.
+    LocalPaths,
+}
+
+/// Summaries used when the enrichment bridge is unavailable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SummaryFallback {
+    /// Fail the enrichment phase.
+    #[default]
+    None,
+    /// Take each page's opening sentences as its summary and description
+    /// (no LLM involved).
+    Extractive,
+}
+
+/// Input to the page content hash used for change detection on update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashBasis {
+    /// The raw HTML response body.
+    #[default]
+    RawHtml,
+    /// The main content HTML extracted by the platform adapter.
+    ExtractedHtml,
+    /// The converted Markdown body (ignores markup-only churn).
+    Markdown,
+}
+
+/// What the converter does with inline `<svg>` elements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InlineSvg {
+    /// Drop them (default).
+    #[default]
+    Skip,
+    /// Keep the markup as a fenced `svg` code block.
+    Code,
+    /// Save each one as an `.svg` file under `assets/` and link it as an
+    /// image. Needs `download_assets`; without it, behaves like `code`.
+    Asset,
+}
+
 fn default_true() -> bool {
     true
 }
 fn default_rate_limit() -> u64 {
     200
 }
+fn default_max_queue_size() -> usize {
+    10_000
+}
+fn default_discovery_retries() -> u32 {
+    2
+}
+fn default_discovery_retry_delay_ms() -> u64 {
+    1000
+}
+fn default_max_asset_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+fn default_pool_max_idle_per_host() -> usize {
+    16
+}
+fn default_tcp_keepalive_secs() -> u64 {
+    60
+}
+fn default_max_backoff_ms() -> u64 {
+    30_000
+}
+fn default_max_redirects() -> usize {
+    10
+}
+fn default_max_full_size() -> u64 {
+    100 * 1024 * 1024
+}
 
 /// `[[kbs]]` entry — a registered KB in the config's KB registry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -160,6 +856,59 @@ pub struct KbRegistryEntry {
     pub source_url: String,
 }
 
+/// `[[custom_tasks]]` entry — an extra KB-level enrichment task.
+///
+/// The bridge receives `instructions` plus the selected `context` fields and
+/// the result is written to `artifacts/<output>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomTaskConfig {
+    /// Task name, used in the enrichment cache key (`custom:<name>`).
+    pub name: String,
+    /// Artifact file name (e.g. `FAQ.md`).
+    pub output: String,
+    /// Heading for the artifact (defaults to the task name).
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Prompt describing what to generate.
+    pub instructions: String,
+    /// KB context sent along with the instructions.
+    #[serde(default = "default_task_context")]
+    pub context: Vec<TaskContext>,
+}
+
+impl CustomTaskConfig {
+    /// Reject empty names and output file names that would escape `artifacts/`.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(ContextBuilderError::config("custom task name must not be empty"));
+        }
+        let output = self.output.as_str();
+        if output.is_empty() || output.starts_with('.') || output.contains(['/', '\\']) {
+            return Err(ContextBuilderError::config(format!(
+                "custom task '{}': invalid output file name '{output}'",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// KB context a custom enrichment task can receive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskContext {
+    /// The table of contents as JSON.
+    Toc,
+    /// Page summaries keyed by path.
+    Summaries,
+    /// Truncated page contents.
+    Pages,
+}
+
+fn default_task_context() -> Vec<TaskContext> {
+    vec![TaskContext::Summaries]
+}
+
 // ---------------------------------------------------------------------------
 // Crawl config (runtime, merged from config + CLI flags)
 // ---------------------------------------------------------------------------
@@ -177,23 +926,169 @@ pub struct CrawlConfig {
     pub exclude_patterns: Vec<String>,
     /// Rate limit in ms between requests to the same host.
     pub rate_limit_ms: u64,
-    /// Discovery/crawl mode: "auto", "prefer-llms", "crawl-only".
-    pub mode: String,
+    /// Discovery/crawl mode.
+    pub mode: CrawlMode,
     /// Whether to respect robots.txt.
     pub respect_robots_txt: bool,
+    /// Maximum outbound links enqueued from a single page (0 = unlimited).
+    pub max_links_per_page: usize,
+    /// Most URLs waiting in the crawl queue (0 = unlimited).
+    pub max_queue_size: usize,
+    /// Most pages fetched by a crawl (0 = unlimited).
+    pub max_pages: usize,
+    /// Extra llms.txt discovery attempts after a transient failure.
+    pub discovery_retries: u32,
+    /// Delay in ms before the first discovery retry, doubled per attempt.
+    pub discovery_retry_delay_ms: u64,
+    /// What the page content hash is computed over.
+    pub hash_basis: HashBasis,
+    /// CSS selectors that drop a page when any element matches.
+    pub drop_if_selector_matches: Vec<String>,
+    /// Fold `description` / `keywords` HTML comments into page frontmatter.
+    pub comment_metadata: bool,
+    /// Merge a Markdown source's own frontmatter into the generated one.
+    pub preserve_source_frontmatter: bool,
+    /// Convert linked PDFs to pages instead of skipping them.
+    pub ingest_pdfs: bool,
+    /// Download referenced images into `assets/` and rewrite their links.
+    pub download_assets: bool,
+    /// Largest asset downloaded, in bytes.
+    pub max_asset_bytes: u64,
+    /// What happens to inline `<svg>` diagrams.
+    pub inline_svg: InlineSvg,
+    /// Detect each page's language and record it in the frontmatter.
+    pub detect_language: bool,
+    /// Keep only pages detected as one of these ISO 639-3 codes (empty = all).
+    pub languages: Vec<String>,
+    /// Idle keep-alive connections kept per host (0 disables pooling).
+    pub pool_max_idle_per_host: usize,
+    /// TCP keep-alive interval in seconds.
+    pub tcp_keepalive_secs: u64,
+    /// Back off per host on 429/5xx responses.
+    pub adaptive_throttle: bool,
+    /// Upper bound in ms for a host's back-off delay.
+    pub max_backoff_ms: u64,
+    /// Redirects followed per request before failing.
+    pub max_redirects: usize,
+    /// Largest `llms-full.txt` accepted by discovery, in bytes.
+    pub max_full_size: u64,
+    /// Drop pages that match the site's soft-404 page.
+    pub detect_soft_404: bool,
+    /// Seed the crawl from sitemaps declared in `robots.txt`.
+    pub robots_sitemaps: bool,
+    /// CSS selectors whose elements are removed from page content.
+    pub strip_selectors: Vec<String>,
+    /// Truncate page titles longer than this many characters.
+    pub max_title_len: Option<usize>,
+    /// Split pages over this many bytes of Markdown into sub-pages.
+    pub split_large_pages: Option<usize>,
+    /// `(pattern, replacement)` regex rewrites for link targets.
+    pub link_rewrites: Vec<(String, String)>,
+    /// Pages converted to Markdown in parallel (at least 1).
+    pub convert_concurrency: usize,
+    /// CSS selectors that skip a page and the links on it.
+    pub skip_page_selectors: Vec<String>,
+    /// Query parameters kept in the dedup key and page path.
+    pub significant_query_params: Vec<String>,
+    /// Don't follow links that only change the current page's query string.
+    pub query_links_as_anchors: bool,
+    /// Dedup `http://` and `https://` variants of a URL as one page.
+    pub prefer_https: bool,
+    /// Treat `www.<host>` and `<host>` as one host.
+    pub strip_www: bool,
+    /// Collapse adjacent duplicate list items and links in converted pages.
+    pub dedupe_adjacent_items: bool,
+    /// Keep source heading ids as `{#id}` heading anchors.
+    pub preserve_anchors: bool,
+    /// Deepest TOC level kept (top level is 1).
+    pub max_toc_depth: Option<usize>,
+    /// Drop TOC sections with no page and no descendant page.
+    pub prune_empty_sections: bool,
+    /// Fetch llms.txt `## Optional` links, after all other links.
+    pub include_optional_links: bool,
+    /// HTTP connect / read / total timeouts.
+    pub timeouts: HttpTimeouts,
+    /// User agent, headers, cookies, and proxy sent with every request.
+    pub auth: HttpAuth,
+    /// Cap on simultaneous work shared by all pipeline phases.
+    pub max_parallelism: Option<usize>,
+    /// Forbid all network access (set by `--offline`).
+    pub offline: bool,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        Self::from(&AppConfig::default())
+    }
 }
 
 impl From<&AppConfig> for CrawlConfig {
     fn from(config: &AppConfig) -> Self {
-        Self {
+        let mut crawl = Self {
             depth: config.defaults.crawl_depth,
-            concurrency: config.defaults.crawl_concurrency,
+            concurrency: default_crawl_concurrency(),
             include_patterns: config.crawl_policies.include_patterns.clone(),
             exclude_patterns: config.crawl_policies.exclude_patterns.clone(),
-            rate_limit_ms: config.crawl_policies.rate_limit_ms,
-            mode: config.defaults.mode.clone(),
+            rate_limit_ms: default_rate_limit(),
+            mode: config.defaults.mode,
             respect_robots_txt: config.crawl_policies.respect_robots_txt,
+            max_links_per_page: config.crawl_policies.max_links_per_page,
+            max_queue_size: config.crawl_policies.max_queue_size,
+            max_pages: config.crawl_policies.max_pages,
+            discovery_retries: config.crawl_policies.discovery_retries,
+            discovery_retry_delay_ms: config.crawl_policies.discovery_retry_delay_ms,
+            hash_basis: config.crawl_policies.hash_basis,
+            drop_if_selector_matches: config.crawl_policies.drop_if_selector_matches.clone(),
+            comment_metadata: config.crawl_policies.comment_metadata,
+            preserve_source_frontmatter: config.crawl_policies.preserve_source_frontmatter,
+            ingest_pdfs: config.crawl_policies.ingest_pdfs,
+            download_assets: config.crawl_policies.download_assets,
+            max_asset_bytes: config.crawl_policies.max_asset_bytes,
+            inline_svg: config.crawl_policies.inline_svg,
+            detect_language: config.crawl_policies.detect_language,
+            languages: config.crawl_policies.languages.clone(),
+            pool_max_idle_per_host: config.crawl_policies.pool_max_idle_per_host,
+            tcp_keepalive_secs: config.crawl_policies.tcp_keepalive_secs,
+            adaptive_throttle: config.crawl_policies.adaptive_throttle,
+            max_backoff_ms: config.crawl_policies.max_backoff_ms,
+            max_redirects: config.crawl_policies.max_redirects,
+            max_full_size: config.crawl_policies.max_full_size,
+            detect_soft_404: config.crawl_policies.detect_soft_404,
+            robots_sitemaps: config.crawl_policies.robots_sitemaps,
+            strip_selectors: config.crawl_policies.strip_selectors.clone(),
+            max_title_len: config.crawl_policies.max_title_len,
+            split_large_pages: config.crawl_policies.split_large_pages,
+            link_rewrites: config.crawl_policies.link_rewrites.clone(),
+            convert_concurrency: config
+                .crawl_policies
+                .convert_concurrency
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+                .max(1),
+            skip_page_selectors: config.crawl_policies.skip_page_selectors.clone(),
+            significant_query_params: config.crawl_policies.significant_query_params.clone(),
+            query_links_as_anchors: config.crawl_policies.query_links_as_anchors,
+            prefer_https: config.crawl_policies.prefer_https,
+            strip_www: config.crawl_policies.strip_www,
+            dedupe_adjacent_items: config.crawl_policies.dedupe_adjacent_items,
+            preserve_anchors: config.crawl_policies.preserve_anchors,
+            max_toc_depth: config.crawl_policies.max_toc_depth,
+            prune_empty_sections: config.crawl_policies.prune_empty_sections,
+            include_optional_links: config.crawl_policies.include_optional_links,
+            timeouts: config.crawl_policies.timeouts,
+            auth: config.crawl_policies.auth.clone(),
+            max_parallelism: config.defaults.max_parallelism,
+            offline: false,
+        };
+        if let Some(profile) = config.defaults.profile {
+            profile.apply(&mut crawl);
+        }
+        if let Some(concurrency) = config.defaults.crawl_concurrency {
+            crawl.concurrency = concurrency;
         }
+        if let Some(rate_limit_ms) = config.crawl_policies.rate_limit_ms {
+            crawl.rate_limit_ms = rate_limit_ms;
+        }
+        crawl
     }
 }
 
@@ -229,9 +1124,11 @@ pub fn load_config() -> Result<AppConfig> {
 pub fn load_config_from(path: &Path) -> Result<AppConfig> {
     let content = std::fs::read_to_string(path).map_err(|e| ContextBuilderError::io(path, e))?;
 
-    toml::from_str(&content).map_err(|e| {
+    let config: AppConfig = toml::from_str(&content).map_err(|e| {
         ContextBuilderError::config(format!("failed to parse {}: {e}", path.display()))
-    })
+    })?;
+    config.crawl_policies.timeouts.validate()?;
+    Ok(config)
 }
 
 /// Create the config directory and write a default config file.
@@ -307,6 +1204,200 @@ source_url = "https://example.com/docs"
         assert_eq!(crawl.depth, 3);
         assert_eq!(crawl.concurrency, 4);
         assert_eq!(crawl.rate_limit_ms, 200);
+        assert_eq!(crawl.max_links_per_page, 0);
+        assert_eq!(crawl.max_queue_size, 10_000);
+    }
+
+    #[test]
+    fn politeness_profiles_set_concurrency_and_delay() {
+        let expected = [
+            (PolitenessProfile::Gentle, 1, 1000),
+            (PolitenessProfile::Balanced, 4, 200),
+            (PolitenessProfile::Aggressive, 8, 0),
+        ];
+        for (profile, concurrency, rate_limit_ms) in expected {
+            assert_eq!(profile.as_str().parse::<PolitenessProfile>().unwrap(), profile);
+
+            let mut app = AppConfig::default();
+            app.defaults.profile = Some(profile);
+            let crawl = CrawlConfig::from(&app);
+            assert_eq!((crawl.concurrency, crawl.rate_limit_ms), (concurrency, rate_limit_ms));
+        }
+        assert!("reckless".parse::<PolitenessProfile>().is_err());
+
+        let app: AppConfig = toml::from_str("[defaults]\nprofile = \"gentle\"\n").unwrap();
+        assert_eq!(app.defaults.profile, Some(PolitenessProfile::Gentle));
+    }
+
+    #[test]
+    fn explicit_concurrency_and_rate_limit_win_over_the_profile() {
+        let app: AppConfig = toml::from_str(
+            "[defaults]\nprofile = \"aggressive\"\ncrawl_concurrency = 2\n\n\
+             [crawl_policies]\nrate_limit_ms = 50\n",
+        )
+        .unwrap();
+        let crawl = CrawlConfig::from(&app);
+        assert_eq!((crawl.concurrency, crawl.rate_limit_ms), (2, 50));
+
+        // Only the key that is set wins; the other comes from the profile
+        let app: AppConfig =
+            toml::from_str("[defaults]\nprofile = \"gentle\"\ncrawl_concurrency = 2\n").unwrap();
+        let crawl = CrawlConfig::from(&app);
+        assert_eq!((crawl.concurrency, crawl.rate_limit_ms), (2, 1000));
+    }
+
+    #[test]
+    fn crawl_mode_parses_each_variant() {
+        assert_eq!("auto".parse::<CrawlMode>().unwrap(), CrawlMode::Auto);
+        assert_eq!("llms-txt".parse::<CrawlMode>().unwrap(), CrawlMode::LlmsTxt);
+        assert_eq!("crawl".parse::<CrawlMode>().unwrap(), CrawlMode::Crawl);
+        assert_eq!(" Crawl ".parse::<CrawlMode>().unwrap(), CrawlMode::Crawl);
+
+        for mode in CrawlMode::ALL {
+            assert_eq!(mode.to_string().parse::<CrawlMode>().unwrap(), mode);
+        }
+    }
+
+    #[test]
+    fn crawl_mode_rejects_unknown_value() {
+        let err = "crawl-only".parse::<CrawlMode>().unwrap_err().to_string();
+        assert!(err.contains("unknown mode 'crawl-only'"), "{err}");
+        assert!(err.contains("auto, llms-txt, crawl"), "{err}");
+    }
+
+    #[test]
+    fn artifact_layouts_map_rule_files() {
+        let cursor = ArtifactLayout::Cursor;
+        assert_eq!(cursor.artifact_path("rules.md", "Astro Docs"), ".cursor/rules/rules.md");
+        assert_eq!(cursor.artifact_path("llms.txt", "Astro Docs"), "llms.txt");
+        assert_eq!(
+            ArtifactLayout::Claude.artifact_path("SKILL.md", "Astro Docs"),
+            ".claude/skills/astro-docs/SKILL.md"
+        );
+        assert_eq!(
+            ArtifactLayout::Vscode.artifact_path("do_dont.md", "x"),
+            ".github/instructions/do_dont.instructions.md"
+        );
+        for file in ["SKILL.md", "rules.md", "FAQ.md"] {
+            assert_eq!(ArtifactLayout::Flat.artifact_path(file, "x"), file);
+        }
+
+        let config: AppConfig =
+            toml::from_str("[defaults]\nartifact_layout = \"cursor\"\n").expect("parse");
+        assert_eq!(config.defaults.artifact_layout, cursor);
+        assert!(toml::from_str::<AppConfig>("[defaults]\nartifact_layout = \"emacs\"\n").is_err());
+    }
+
+    #[test]
+    fn crawl_mode_in_config_file() {
+        let config: AppConfig = toml::from_str("[defaults]\nmode = \"llms-txt\"\n").expect("parse");
+        assert_eq!(config.defaults.mode, CrawlMode::LlmsTxt);
+
+        let err = toml::from_str::<AppConfig>("[defaults]\nmode = \"sitemap\"\n").unwrap_err();
+        assert!(err.to_string().contains("unknown mode 'sitemap'"), "{err}");
+    }
+
+    #[test]
+    fn timeouts_parse_under_crawl_policies() {
+        let config: AppConfig = toml::from_str(
+            "[crawl_policies]\nread_timeout_secs = 120\ntimeout_secs = 900\n",
+        )
+        .expect("parse timeouts");
+        let timeouts = CrawlConfig::from(&config).timeouts;
+        assert_eq!(timeouts.connect_timeout_secs, 10);
+        assert_eq!(timeouts.read(), Duration::from_secs(120));
+        assert_eq!(timeouts.total(), Duration::from_secs(900));
+    }
+
+    #[test]
+    fn zero_timeouts_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("cb-zero-timeout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[crawl_policies]\nread_timeout_secs = 0\n").unwrap();
+
+        let err = load_config_from(&path).unwrap_err();
+        assert!(err.to_string().contains("read_timeout_secs must be at least 1"), "{err}");
+        assert!(HttpTimeouts::default().validate().is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn auth_parses_under_crawl_policies() {
+        let config: AppConfig = toml::from_str(
+            r#"
+[crawl_policies]
+user_agent = "DocsBot/1.0"
+proxy = "http://proxy.internal:3128"
+
+[crawl_policies.headers]
+Authorization = "Bearer secret"
+
+[crawl_policies.cookies]
+session = "abc"
+theme = "dark"
+"#,
+        )
+        .expect("parse auth");
+        let auth = CrawlConfig::from(&config).auth;
+        assert_eq!(auth.user_agent.as_deref(), Some("DocsBot/1.0"));
+        assert_eq!(auth.proxy.as_deref(), Some("http://proxy.internal:3128"));
+        assert_eq!(
+            auth.header_pairs(),
+            vec![
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("Cookie".to_string(), "session=abc; theme=dark".to_string()),
+            ]
+        );
+
+        let roundtrip: AppConfig = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(roundtrip.crawl_policies.auth, config.crawl_policies.auth);
+    }
+
+    #[test]
+    fn link_rewrites_parse_as_pairs() {
+        let config: AppConfig = toml::from_str(
+            r#"
+[crawl_policies]
+link_rewrites = [["/docs/old/", "/docs/"], ["/$", ""]]
+"#,
+        )
+        .expect("parse link rewrites");
+        assert_eq!(
+            CrawlConfig::from(&config).link_rewrites,
+            vec![
+                ("/docs/old/".to_string(), "/docs/".to_string()),
+                ("/$".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_tasks_parse_and_validate() {
+        let toml_str = r#"
+[[custom_tasks]]
+name = "faq"
+output = "FAQ.md"
+instructions = "Write an FAQ."
+context = ["toc", "pages"]
+
+[[custom_tasks]]
+name = "glossary"
+output = "glossary.md"
+instructions = "List key terms."
+"#;
+        let config: AppConfig = toml::from_str(toml_str).expect("parse");
+        assert_eq!(config.custom_tasks.len(), 2);
+        assert_eq!(config.custom_tasks[0].context, vec![TaskContext::Toc, TaskContext::Pages]);
+        assert_eq!(config.custom_tasks[1].context, vec![TaskContext::Summaries]);
+        assert!(config.custom_tasks.iter().all(|t| t.validate().is_ok()));
+
+        let mut task = config.custom_tasks[0].clone();
+        task.output = "../escape.md".into();
+        assert!(task.validate().is_err());
+        task.output = String::new();
+        assert!(task.validate().is_err());
     }
 
     #[test]
@@ -16,6 +16,19 @@ pub enum ContextBuilderError {
     #[error("network error: {0}")]
     Network(String),
 
+    /// The server requires credentials for the resource (HTTP 401).
+    #[error("unauthorized: {url} (HTTP 401); the site requires authentication")]
+    Unauthorized { url: String },
+
+    /// The server refused access to the resource (HTTP 403).
+    #[error("forbidden: {url} (HTTP 403); the site may require authentication")]
+    Forbidden { url: String },
+
+    /// A request was redirected more than the configured limit allows
+    /// (a long canonicalization chain or a redirect loop).
+    #[error("redirect limit exceeded: {url} (more than {limit} redirects)")]
+    RedirectLimit { url: String, limit: usize },
+
     /// HTML parsing or content extraction error.
     #[error("parse error: {message}")]
     Parse { message: String },
@@ -69,6 +82,59 @@ impl ContextBuilderError {
         }
     }
 
+    /// Create the network error returned when offline mode forbids `action`.
+    pub fn offline(action: impl std::fmt::Display) -> Self {
+        Self::Network(format!(
+            "offline mode: network access is disabled (cannot {action})"
+        ))
+    }
+
+    /// Create the error for a request to `url` that exceeded `limit` redirects.
+    pub fn redirect_limit(url: impl std::fmt::Display, limit: usize) -> Self {
+        Self::RedirectLimit {
+            url: url.to_string(),
+            limit,
+        }
+    }
+
+    /// Create the error for a request to `url` answered with a non-success
+    /// HTTP `status` (`code` plus reason, as in `404 Not Found`).
+    ///
+    /// 401 and 403 become [`Self::Unauthorized`] and [`Self::Forbidden`], so
+    /// missing credentials stand apart from other failures.
+    pub fn http_status(
+        url: impl std::fmt::Display,
+        code: u16,
+        status: impl std::fmt::Display,
+    ) -> Self {
+        match code {
+            401 => Self::Unauthorized { url: url.to_string() },
+            403 => Self::Forbidden { url: url.to_string() },
+            _ => Self::Network(format!("{url}: HTTP {status}")),
+        }
+    }
+
+    /// Whether the server refused the request for lack of credentials
+    /// (HTTP 401 or 403).
+    pub fn needs_auth(&self) -> bool {
+        matches!(self, Self::Unauthorized { .. } | Self::Forbidden { .. })
+    }
+
+    /// Whether retrying the request may succeed: a network failure (other
+    /// than offline mode) or an HTTP 429 or 5xx answer.
+    pub fn is_transient(&self) -> bool {
+        let Self::Network(message) = self else {
+            return false;
+        };
+        match message.rsplit_once(": HTTP ") {
+            Some((_, status)) => {
+                let code = status.split_whitespace().next().unwrap_or_default();
+                code == "429" || code.starts_with('5')
+            }
+            None => !message.starts_with("offline mode"),
+        }
+    }
+
     /// Wrap a `std::io::Error` with a path for context.
     pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
         Self::Io {
@@ -90,4 +156,36 @@ mod tests {
         let err = ContextBuilderError::validation("schema_version 99 not supported");
         assert!(err.to_string().contains("schema_version 99"));
     }
+
+    #[test]
+    fn http_status_singles_out_auth_failures() {
+        let url = "https://docs.example.com/private";
+        let err = ContextBuilderError::http_status(url, 401, "401 Unauthorized");
+        assert!(matches!(err, ContextBuilderError::Unauthorized { .. }), "{err:?}");
+        assert!(err.needs_auth());
+        assert!(err.to_string().contains("requires authentication"));
+
+        let err = ContextBuilderError::http_status(url, 403, "403 Forbidden");
+        assert!(matches!(err, ContextBuilderError::Forbidden { .. }), "{err:?}");
+        assert!(err.needs_auth());
+
+        let err = ContextBuilderError::http_status(url, 404, "404 Not Found");
+        assert_eq!(err.to_string(), format!("network error: {url}: HTTP 404 Not Found"));
+        assert!(!err.needs_auth());
+    }
+
+    #[test]
+    fn transient_errors_are_network_failures_429_and_5xx() {
+        let url = "https://docs.example.com/llms.txt";
+        let status = |code: u16, reason: &str| {
+            ContextBuilderError::http_status(url, code, format!("{code} {reason}"))
+        };
+        assert!(status(503, "Service Unavailable").is_transient());
+        assert!(status(429, "Too Many Requests").is_transient());
+        assert!(!status(404, "Not Found").is_transient());
+        assert!(!status(403, "Forbidden").is_transient());
+        assert!(ContextBuilderError::Network(format!("{url}: connection reset")).is_transient());
+        assert!(!ContextBuilderError::offline("fetch it").is_transient());
+        assert!(!ContextBuilderError::redirect_limit(url, 10).is_transient());
+    }
 }
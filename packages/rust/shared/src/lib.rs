@@ -3,18 +3,25 @@
 //! This crate is the foundation depended on by all other ContextBuilder crates.
 //! It provides:
 //! - [`ContextBuilderError`] — the unified error type
-//! - Domain types ([`KbManifest`], [`TocEntry`], [`PageMeta`], [`KbId`])
+//! - Domain types ([`KbManifest`], [`TocEntry`], [`PageMeta`], [`Chunk`], [`KbId`])
 //! - Configuration ([`AppConfig`], [`CrawlConfig`], config loading)
+//! - [`sitemap`] parsing and the [`is_ssrf_target`] guard used by every fetcher
 
 pub mod config;
 pub mod error;
+pub mod sitemap;
+pub mod ssrf;
 pub mod types;
 
 // Re-export public API at crate root for ergonomic imports.
 pub use config::{
-    AppConfig, CrawlConfig, CrawlPoliciesConfig, DefaultsConfig, KbRegistryEntry,
-    OpenRouterConfig, config_dir, config_file_path, init_config, load_config, load_config_from,
+    AppConfig, ArtifactLayout, CrawlConfig, CrawlMode, CrawlPoliciesConfig, CustomTaskConfig, DefaultsConfig,
+    HashBasis, HttpAuth, HttpTimeouts, InlineSvg, KbRegistryEntry, LinkBase, OpenRouterConfig, PolitenessProfile, SummaryFallback, TaskContext, config_dir, config_file_path, init_config, load_config, load_config_from,
     validate_api_key,
 };
 pub use error::{ContextBuilderError, Result};
-pub use types::{CURRENT_SCHEMA_VERSION, KbId, KbManifest, PageMeta, Toc, TocEntry};
+pub use ssrf::is_ssrf_target;
+pub use types::{
+    CURRENT_SCHEMA_VERSION, Chunk, Clock, CrawlScope, IdSource, KbId, KbManifest, PageMeta, Toc,
+    TocEntry, sanitize_filename, truncate_title,
+};
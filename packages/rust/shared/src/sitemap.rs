@@ -0,0 +1,187 @@
+//! Sitemap parsing shared by `sitemap.xml` discovery and crawl seeding.
+//!
+//! [`collect_sitemap_pages`] walks sitemaps and sitemap indexes with one set
+//! of limits and one SSRF guard; callers supply how a sitemap is fetched.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::ssrf::is_ssrf_target;
+
+/// A sitemap `<loc>` entry; group 1 is the URL.
+static LOC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").expect("valid regex"));
+
+/// Most sitemap files fetched, counting every nested index and its children.
+pub const MAX_SITEMAPS: usize = 50;
+
+/// Deepest chain of sitemap indexes followed below the starting sitemaps.
+pub const MAX_INDEX_DEPTH: usize = 3;
+
+/// The URLs listed in a sitemap or sitemap index, in order. Entries that are
+/// not absolute `http(s)` URLs are skipped.
+pub fn sitemap_urls(xml: &str) -> Vec<Url> {
+    LOC_RE
+        .captures_iter(xml)
+        .filter_map(|caps| {
+            let loc = caps[1].replace("&amp;", "&");
+            match Url::parse(&loc) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => Some(url),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!(loc, error = %e, "skipping invalid sitemap URL");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether `xml` is a sitemap index (its `<loc>`s are sitemaps, not pages).
+pub fn is_sitemap_index(xml: &str) -> bool {
+    xml.contains("<sitemapindex")
+}
+
+/// Page URLs listed by `sitemaps`, in document order and without duplicates.
+///
+/// Each entry is a sitemap URL and, if already fetched, its body; the rest
+/// are loaded with `fetch`, which returns `None` for a sitemap that failed
+/// to load (it is skipped). Indexes are followed up to [`MAX_INDEX_DEPTH`]
+/// levels and [`MAX_SITEMAPS`] files in all. Sitemaps still to be fetched
+/// that point at a private or local host are skipped unless
+/// `allow_private`.
+pub async fn collect_sitemap_pages<F, Fut>(
+    sitemaps: Vec<(Url, Option<String>)>,
+    allow_private: bool,
+    mut fetch: F,
+) -> Vec<Url>
+where
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let mut seen_sitemaps: HashSet<String> = HashSet::new();
+    let mut seen_pages = HashSet::new();
+    let mut pages = Vec::new();
+    let mut fetched = sitemaps.iter().filter(|(_, body)| body.is_some()).count();
+    // (sitemap URL, index depth, body once fetched), popped in document order
+    let mut pending: Vec<_> = sitemaps
+        .into_iter()
+        .filter(|(url, _)| seen_sitemaps.insert(url.to_string()))
+        .map(|(url, body)| (url, 0, body))
+        .rev()
+        .collect();
+
+    while let Some((sitemap_url, depth, body)) = pending.pop() {
+        let xml = match body {
+            Some(xml) => xml,
+            None => {
+                if !allow_private && is_ssrf_target(&sitemap_url) {
+                    warn!(%sitemap_url, "SSRF protection: blocked sitemap");
+                    continue;
+                }
+                if fetched == MAX_SITEMAPS {
+                    warn!(max = MAX_SITEMAPS, "too many sitemaps, ignoring the rest");
+                    break;
+                }
+                fetched += 1;
+                match fetch(sitemap_url.clone()).await {
+                    Some(xml) => xml,
+                    None => continue,
+                }
+            }
+        };
+
+        let urls = sitemap_urls(&xml);
+        if !is_sitemap_index(&xml) {
+            pages.extend(urls.into_iter().filter(|url| seen_pages.insert(url.to_string())));
+        } else if depth < MAX_INDEX_DEPTH {
+            debug!(%sitemap_url, sitemaps = urls.len(), "following sitemap index");
+            let children = urls.into_iter().filter(|url| seen_sitemaps.insert(url.to_string()));
+            let children: Vec<_> = children.map(|url| (url, depth + 1, None)).collect();
+            pending.extend(children.into_iter().rev());
+        } else {
+            warn!(%sitemap_url, max = MAX_INDEX_DEPTH, "sitemap indexes nested too deeply");
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sitemap_urls_reads_every_http_loc() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://docs.example.com/</loc></url>
+              <url>
+                <loc>
+                  https://docs.example.com/search?q=a&amp;page=2
+                </loc>
+                <lastmod>2025-01-01</lastmod>
+              </url>
+              <url><loc>mailto:me@example.com</loc></url>
+              <url><loc>file:///etc/passwd</loc></url>
+              <url><loc>not a url</loc></url>
+            </urlset>"#;
+        let urls: Vec<String> = sitemap_urls(xml).iter().map(Url::to_string).collect();
+        assert_eq!(
+            urls,
+            ["https://docs.example.com/", "https://docs.example.com/search?q=a&page=2"]
+        );
+        assert!(!is_sitemap_index(xml));
+        assert!(is_sitemap_index("<sitemapindex><sitemap><loc>x</loc></sitemap></sitemapindex>"));
+    }
+
+    #[tokio::test]
+    async fn child_sitemaps_on_private_hosts_are_blocked() {
+        let index = "<sitemapindex>\
+                     <sitemap><loc>http://127.0.0.1/internal.xml</loc></sitemap>\
+                     <sitemap><loc>https://docs.example.com/pages.xml</loc></sitemap>\
+                     </sitemapindex>";
+        let root = Url::parse("https://docs.example.com/sitemap.xml").unwrap();
+        let sitemaps = vec![(root, Some(index.to_string()))];
+
+        let urlset = "<urlset><url><loc>https://docs.example.com/a</loc></url></urlset>";
+        let mut requested = Vec::new();
+        let pages = collect_sitemap_pages(sitemaps.clone(), false, |url| {
+            requested.push(url.to_string());
+            async { Some(urlset.to_string()) }
+        })
+        .await;
+        assert_eq!(requested, ["https://docs.example.com/pages.xml"]);
+        assert_eq!(pages.len(), 1);
+
+        let mut requested = 0;
+        collect_sitemap_pages(sitemaps, true, |_| {
+            requested += 1;
+            async { None }
+        })
+        .await;
+        assert_eq!(requested, 2);
+    }
+
+    #[tokio::test]
+    async fn sitemap_indexes_nest_at_most_max_depth() {
+        let index = |n: usize| {
+            let entry = format!("<sitemap><loc>https://a.dev/{n}.xml</loc></sitemap>");
+            format!("<sitemapindex>{entry}</sitemapindex>")
+        };
+        let root = Url::parse("https://a.dev/0.xml").unwrap();
+        let mut requested = Vec::new();
+        let pages = collect_sitemap_pages(vec![(root, Some(index(1)))], false, |url| {
+            requested.push(url.to_string());
+            let n = requested.len() + 1;
+            async move { Some(index(n)) }
+        })
+        .await;
+        assert!(pages.is_empty());
+        assert_eq!(requested.len(), MAX_INDEX_DEPTH);
+    }
+}
@@ -0,0 +1,85 @@
+//! SSRF protection: URLs that must not be fetched on a site's say-so.
+
+use std::net::IpAddr;
+
+use url::Url;
+
+/// Check if a URL targets a potentially dangerous resource.
+pub fn is_ssrf_target(url: &Url) -> bool {
+    // Block non-HTTP schemes
+    match url.scheme() {
+        "http" | "https" => {}
+        _ => return true,
+    }
+
+    // Block private/loopback IPs
+    if let Some(host) = url.host_str() {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return is_private_ip(&ip);
+        }
+        // Block known local hostnames
+        if host == "localhost"
+            || host == "127.0.0.1"
+            || host == "[::1]"
+            || host.ends_with(".local")
+            || host.ends_with(".internal")
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Check if an IP is in a private/reserved range.
+fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_unspecified()
+                // 100.64.0.0/10 (Carrier-grade NAT)
+                || (v4.octets()[0] == 100 && (v4.octets()[1] & 0xC0) == 64)
+                // 192.0.0.0/24
+                || (v4.octets()[0] == 192 && v4.octets()[1] == 0 && v4.octets()[2] == 0)
+        }
+        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssrf_protection_blocks_file() {
+        let url = Url::parse("file:///etc/passwd").unwrap();
+        assert!(is_ssrf_target(&url));
+    }
+
+    #[test]
+    fn test_ssrf_protection_blocks_private_ip() {
+        let url = Url::parse("http://192.168.1.1/admin").unwrap();
+        assert!(is_ssrf_target(&url));
+
+        let url = Url::parse("http://10.0.0.1/").unwrap();
+        assert!(is_ssrf_target(&url));
+
+        let url = Url::parse("http://127.0.0.1:8080/").unwrap();
+        assert!(is_ssrf_target(&url));
+    }
+
+    #[test]
+    fn test_ssrf_protection_allows_public() {
+        let url = Url::parse("https://docs.example.com/page").unwrap();
+        assert!(!is_ssrf_target(&url));
+    }
+
+    #[test]
+    fn test_ssrf_blocks_localhost() {
+        let url = Url::parse("http://localhost:3000/api").unwrap();
+        assert!(is_ssrf_target(&url));
+    }
+}
@@ -2,8 +2,12 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::config::{CrawlConfig, CrawlMode};
+use crate::error::{ContextBuilderError, Result};
+
 /// Current schema version for the KB manifest format.
 pub const CURRENT_SCHEMA_VERSION: u32 = 1;
 
@@ -75,6 +79,65 @@ pub struct KbManifest {
     /// Enrichment metadata (model, tokens, timestamp).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enrichment: Option<serde_json::Value>,
+    /// How the crawl that built the KB was scoped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawl_scope: Option<CrawlScope>,
+}
+
+impl KbManifest {
+    /// Check that the KB at `kb_path` is in this tool's on-disk format, so
+    /// it can be updated or rebuilt safely. An older KB must be migrated
+    /// first; a newer one needs a newer tool.
+    pub fn ensure_current_schema(&self, kb_path: &std::path::Path) -> Result<()> {
+        use std::cmp::Ordering;
+
+        match self.schema_version.cmp(&CURRENT_SCHEMA_VERSION) {
+            Ordering::Equal => Ok(()),
+            Ordering::Less => Err(ContextBuilderError::validation(format!(
+                "KB at '{path}' uses schema_version {} but this version of ContextBuilder \
+                 needs {CURRENT_SCHEMA_VERSION}; run `contextbuilder migrate --kb {path}` to \
+                 upgrade it",
+                self.schema_version,
+                path = kb_path.display(),
+            ))),
+            Ordering::Greater => Err(ContextBuilderError::validation(format!(
+                "KB at '{}' uses schema_version {}, newer than this version of ContextBuilder \
+                 supports ({CURRENT_SCHEMA_VERSION}); upgrade ContextBuilder",
+                kb_path.display(),
+                self.schema_version,
+            ))),
+        }
+    }
+}
+
+/// The effective scope of a KB's crawl, recorded for auditing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlScope {
+    /// URL the crawl started from.
+    pub start_url: String,
+    /// Discovery/crawl mode.
+    pub mode: CrawlMode,
+    /// Maximum crawl depth from the start URL.
+    pub depth: u32,
+    /// URL include glob patterns.
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    /// URL exclude glob patterns.
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+}
+
+impl CrawlScope {
+    /// The scope of a `mode` crawl of `start_url` under `crawl`.
+    pub fn new(start_url: impl Into<String>, mode: CrawlMode, crawl: &CrawlConfig) -> Self {
+        Self {
+            start_url: start_url.into(),
+            mode,
+            depth: crawl.depth,
+            include_patterns: crawl.include_patterns.clone(),
+            exclude_patterns: crawl.exclude_patterns.clone(),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -134,12 +197,208 @@ pub struct PageMeta {
     /// Content length in bytes.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub content_len: Option<usize>,
+    /// Link hops from the crawl seed at which the page was reached; `None`
+    /// for pages sourced from llms.txt or fetched individually.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+    /// Author-written summary from the page's `<meta name="description">`
+    /// (or `og:description`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Chunk
+// ---------------------------------------------------------------------------
+
+/// A section of a page (split at H2 headings), stored for section-level
+/// enrichment and search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Unique chunk identifier (UUID v7).
+    pub id: String,
+    /// Owning knowledge base.
+    pub kb_id: String,
+    /// Path of the page this chunk belongs to.
+    pub parent_path: String,
+    /// Anchor slug of the section heading (`None` for the page preamble).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// Section heading text (`None` for the page preamble).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Position of the chunk within its page (0-based).
+    pub ordinal: u32,
+    /// Section Markdown content.
+    pub content: String,
+    /// LLM-generated section summary (populated by enrichment).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Clock
+// ---------------------------------------------------------------------------
+
+/// Source of the timestamps written into KB output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// Current wall-clock time.
+    #[default]
+    System,
+    /// A fixed instant, for reproducible output.
+    Fixed(DateTime<Utc>),
+}
+
+impl Clock {
+    /// Fixed clock for deterministic builds.
+    ///
+    /// Honors `SOURCE_DATE_EPOCH` (seconds since the Unix epoch) when set,
+    /// otherwise uses the Unix epoch itself.
+    pub fn deterministic() -> Self {
+        let secs = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .unwrap_or(0);
+        Self::Fixed(DateTime::from_timestamp(secs, 0).unwrap_or_default())
+    }
+
+    /// The current time according to this clock.
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Self::System => Utc::now(),
+            Self::Fixed(t) => *t,
+        }
+    }
+
+    /// Whether this clock is fixed (deterministic output).
+    pub fn is_fixed(&self) -> bool {
+        matches!(self, Self::Fixed(_))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// IdSource
+// ---------------------------------------------------------------------------
+
+/// Source of the ids given to KBs, pages, and chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdSource {
+    /// Fresh time-sortable UUIDs.
+    #[default]
+    Random,
+    /// UUIDs derived from a seed, for reproducible output.
+    Seeded,
+}
+
+impl IdSource {
+    /// The id source for a build: seeded when it is deterministic.
+    pub fn new(deterministic: bool) -> Self {
+        if deterministic {
+            Self::Seeded
+        } else {
+            Self::Random
+        }
+    }
+
+    /// An id for whatever `seed` names (say, a KB's source URL and name, or
+    /// a page's KB and path). Seeded ids come from the seed's SHA-256, so
+    /// every build gives the same seed the same id.
+    pub fn uuid(&self, seed: &str) -> Uuid {
+        match self {
+            Self::Random => Uuid::now_v7(),
+            Self::Seeded => {
+                let digest = Sha256::digest(seed.as_bytes());
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(&digest[..16]);
+                uuid::Builder::from_custom_bytes(bytes).into_uuid()
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Titles
+// ---------------------------------------------------------------------------
+
+/// Shorten `title` to at most `max_len` characters, cutting at a word
+/// boundary and ending with `…`.
+///
+/// Titles that already fit are returned unchanged. A single word longer than
+/// the limit is cut mid-word.
+pub fn truncate_title(title: &str, max_len: usize) -> String {
+    if title.chars().count() <= max_len {
+        return title.to_string();
+    }
+    let keep: String = title.chars().take(max_len.saturating_sub(1)).collect();
+    // Cut at the last word boundary, unless the next char starts a new word anyway
+    let at_boundary = title[keep.len()..].starts_with(char::is_whitespace);
+    let cut = match keep.rfind(char::is_whitespace) {
+        Some(i) if !at_boundary && i > 0 => &keep[..i],
+        _ => keep.as_str(),
+    };
+    let cut = cut.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '-' | '—'));
+    format!("{cut}…")
+}
+
+/// Characters that may not appear in a Windows file name.
+const ILLEGAL_FILENAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows reserves, with or without an extension.
+const RESERVED_FILENAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make one path segment safe to use as a file or directory name on every
+/// platform.
+///
+/// Characters illegal on Windows, control characters, and a trailing dot or
+/// space are percent-encoded (`a:b` becomes `a%3Ab`), so distinct segments
+/// stay distinct and the original can be recovered by percent-decoding.
+/// Reserved device names get a trailing `_` (`con` becomes `con_`,
+/// `aux.md` becomes `aux_.md`). Safe segments are returned unchanged.
+pub fn sanitize_filename(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    let last = segment.char_indices().last().map(|(i, _)| i);
+    for (i, c) in segment.char_indices() {
+        let trailing = Some(i) == last && matches!(c, '.' | ' ');
+        if ILLEGAL_FILENAME_CHARS.contains(&c) || c.is_ascii_control() || trailing {
+            out.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            out.push(c);
+        }
+    }
+
+    let stem_len = out.find('.').unwrap_or(out.len());
+    if RESERVED_FILENAMES.iter().any(|r| r.eq_ignore_ascii_case(&out[..stem_len])) {
+        out.insert(stem_len, '_');
+    }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn fixed_clock_is_stable() {
+        let t = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = Clock::Fixed(t);
+        assert_eq!(clock.now(), t);
+        assert_eq!(clock.now(), clock.now());
+        assert!(clock.is_fixed());
+        assert!(!Clock::System.is_fixed());
+    }
+
+    #[test]
+    fn seeded_ids_are_stable_per_seed() {
+        let ids = IdSource::new(true);
+        assert_eq!(ids.uuid("kb/a"), ids.uuid("kb/a"));
+        assert_ne!(ids.uuid("kb/a"), ids.uuid("kb/b"));
+        assert_ne!(IdSource::Random.uuid("kb/a"), IdSource::Random.uuid("kb/a"));
+    }
+
     #[test]
     fn kb_id_roundtrip() {
         let id = KbId::new();
@@ -162,12 +421,70 @@ mod tests {
             config: None,
             artifacts: None,
             enrichment: None,
+            crawl_scope: None,
         };
 
         let json = serde_json::to_string_pretty(&manifest).expect("serialize");
         let parsed: KbManifest = serde_json::from_str(&json).expect("deserialize");
         assert_eq!(parsed.schema_version, CURRENT_SCHEMA_VERSION);
         assert_eq!(parsed.name, "test-kb");
+        assert!(!json.contains("crawl_scope"));
+    }
+
+    #[test]
+    fn ensure_current_schema_rejects_other_versions() {
+        let mut manifest: KbManifest = serde_json::from_value(serde_json::json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "id": KbId::new(),
+            "name": "test-kb",
+            "source_url": "https://example.com/docs",
+            "tool_version": "0.1.0",
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+            "page_count": 0,
+        }))
+        .unwrap();
+        let kb_path = std::path::Path::new("var/kb/docs");
+        assert!(manifest.ensure_current_schema(kb_path).is_ok());
+
+        manifest.schema_version = CURRENT_SCHEMA_VERSION - 1;
+        let err = manifest.ensure_current_schema(kb_path).unwrap_err().to_string();
+        assert!(err.contains("run `contextbuilder migrate --kb var/kb/docs`"), "{err}");
+
+        manifest.schema_version = CURRENT_SCHEMA_VERSION + 1;
+        let err = manifest.ensure_current_schema(kb_path).unwrap_err().to_string();
+        assert!(err.contains("upgrade ContextBuilder"), "{err}");
+        assert!(!err.contains("migrate"), "{err}");
+    }
+
+    #[test]
+    fn manifest_round_trips_crawl_scope() {
+        let crawl = CrawlConfig {
+            depth: 2,
+            include_patterns: vec!["/docs/**".into()],
+            exclude_patterns: vec!["/docs/blog/**".into()],
+            ..CrawlConfig::default()
+        };
+        let scope = CrawlScope::new("https://example.com/docs", CrawlMode::Crawl, &crawl);
+        let manifest = KbManifest {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            id: KbId::new(),
+            name: "test-kb".into(),
+            source_url: "https://example.com/docs".into(),
+            tool_version: "0.1.0".into(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            page_count: 0,
+            config: None,
+            artifacts: None,
+            enrichment: None,
+            crawl_scope: Some(scope),
+        };
+
+        let json = serde_json::to_string(&manifest).expect("serialize");
+        assert!(json.contains(r#""mode":"crawl""#), "{json}");
+        let parsed: KbManifest = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(parsed.crawl_scope, manifest.crawl_scope);
     }
 
     #[test]
@@ -216,4 +533,42 @@ mod tests {
         assert_eq!(parsed.sections[0].children.len(), 2);
         assert_eq!(parsed.sections[0].title, "Getting Started");
     }
+
+    #[test]
+    fn truncate_title_cuts_at_word_boundary() {
+        let title = "How to configure the widget server for high availability deployments";
+        let short = truncate_title(title, 30);
+        assert_eq!(short, "How to configure the widget…");
+        assert!(short.chars().count() <= 30);
+
+        assert_eq!(truncate_title("Install, then configure", 12), "Install…");
+        assert_eq!(truncate_title("Supercalifragilistic", 8), "Superca…");
+    }
+
+    #[test]
+    fn truncate_title_leaves_short_titles_intact() {
+        assert_eq!(truncate_title("Getting Started", 80), "Getting Started");
+        assert_eq!(truncate_title("Exactly ten", 11), "Exactly ten");
+    }
+
+    #[test]
+    fn sanitize_filename_encodes_illegal_characters() {
+        assert_eq!(sanitize_filename("getting-started"), "getting-started");
+        assert_eq!(sanitize_filename("std::vec"), "std%3A%3Avec");
+        assert_eq!(sanitize_filename("a*b|c\"d<e>f?"), "a%2Ab%7Cc%22d%3Ce%3Ef%3F");
+        assert_eq!(sanitize_filename("tab\there"), "tab%09here");
+        assert_eq!(sanitize_filename("v1.2."), "v1.2%2E");
+        assert_eq!(sanitize_filename("trailing "), "trailing%20");
+        assert_eq!(sanitize_filename("ünïcode"), "ünïcode");
+    }
+
+    #[test]
+    fn sanitize_filename_guards_reserved_names() {
+        assert_eq!(sanitize_filename("CON"), "CON_");
+        assert_eq!(sanitize_filename("aux"), "aux_");
+        assert_eq!(sanitize_filename("nul.md"), "nul_.md");
+        assert_eq!(sanitize_filename("Com1"), "Com1_");
+        assert_eq!(sanitize_filename("console"), "console");
+        assert_eq!(sanitize_filename("com10"), "com10");
+    }
 }
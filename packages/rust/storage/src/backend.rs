@@ -0,0 +1,207 @@
+//! Backend-agnostic storage interface.
+//!
+//! [`StorageBackend`] covers the operations the crawler, pipeline, and
+//! enrichment stages perform. [`Storage`] implements it on top of libSQL;
+//! [`MemoryStorage`](crate::MemoryStorage) keeps everything in process memory
+//! for tests and throwaway runs.
+
+use async_trait::async_trait;
+use contextbuilder_shared::{Chunk, PageMeta, Result};
+
+use crate::Storage;
+
+/// Storage operations used by the build and update pipelines.
+///
+/// Semantics follow the libSQL implementation: pages are keyed by
+/// `kb_id + path`, listings are ordered, and upserts keep existing IDs.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Insert a new knowledge base record.
+    async fn insert_kb(
+        &self,
+        id: &str,
+        name: &str,
+        source_url: &str,
+        config_json: Option<&str>,
+    ) -> Result<()>;
+
+    /// Upsert a page (insert or update on conflict by `kb_id + path`).
+    async fn upsert_page(&self, page: &PageMeta) -> Result<()>;
+
+    /// Upsert a page along with its converted Markdown.
+    async fn upsert_page_with_content(&self, page: &PageMeta, content_md: &str) -> Result<()>;
+
+    /// Get a page by KB ID and path.
+    async fn get_page(&self, kb_id: &str, path: &str) -> Result<Option<PageMeta>>;
+
+    /// Get the stored Markdown of a page, if it was stored with any.
+    async fn get_page_content(&self, kb_id: &str, path: &str) -> Result<Option<String>>;
+
+    /// List all pages for a KB, ordered by path.
+    async fn list_pages_by_kb(&self, kb_id: &str) -> Result<Vec<PageMeta>>;
+
+    /// Delete a page (and its outgoing links) by ID.
+    async fn delete_page(&self, page_id: &str) -> Result<()>;
+
+    /// Insert a link record.
+    async fn insert_link(&self, from_page_id: &str, to_url: &str, kind: Option<&str>)
+    -> Result<()>;
+
+    /// Insert all `links` (`(to_url, kind)`) of a page at once.
+    async fn insert_links_batch(
+        &self,
+        from_page_id: &str,
+        links: &[(String, Option<String>)],
+    ) -> Result<()>;
+
+    /// Get the deduplicated link graph of a KB as `(from_page_path, to_url)`,
+    /// ordered by path then URL.
+    async fn list_links_by_kb(&self, kb_id: &str) -> Result<Vec<(String, String)>>;
+
+    /// Insert a new crawl job. Returns the generated job ID.
+    async fn insert_crawl_job(&self, kb_id: &str) -> Result<String>;
+
+    /// Update a crawl job with completion data.
+    async fn update_crawl_job(&self, job_id: &str, stats_json: &str) -> Result<()>;
+
+    /// Get a cached enrichment result.
+    async fn get_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+    ) -> Result<Option<String>>;
+
+    /// Store an enrichment result in the cache (upserts).
+    async fn set_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+        result_json: &str,
+    ) -> Result<()>;
+
+    /// Replace all chunks of a page with `chunks`.
+    async fn replace_chunks(&self, kb_id: &str, parent_path: &str, chunks: &[Chunk])
+    -> Result<()>;
+
+    /// List a page's chunks in document order.
+    async fn list_chunks_by_page(&self, kb_id: &str, parent_path: &str) -> Result<Vec<Chunk>>;
+
+    /// Store the enrichment summary for a chunk.
+    async fn set_chunk_summary(&self, chunk_id: &str, summary: &str) -> Result<()>;
+
+    /// Delete all chunks belonging to a page.
+    async fn delete_chunks_for_page(&self, kb_id: &str, parent_path: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl StorageBackend for Storage {
+    async fn insert_kb(
+        &self,
+        id: &str,
+        name: &str,
+        source_url: &str,
+        config_json: Option<&str>,
+    ) -> Result<()> {
+        Storage::insert_kb(self, id, name, source_url, config_json).await
+    }
+
+    async fn upsert_page(&self, page: &PageMeta) -> Result<()> {
+        Storage::upsert_page(self, page).await
+    }
+
+    async fn upsert_page_with_content(&self, page: &PageMeta, content_md: &str) -> Result<()> {
+        Storage::upsert_page_with_content(self, page, content_md).await
+    }
+
+    async fn get_page(&self, kb_id: &str, path: &str) -> Result<Option<PageMeta>> {
+        Storage::get_page(self, kb_id, path).await
+    }
+
+    async fn get_page_content(&self, kb_id: &str, path: &str) -> Result<Option<String>> {
+        Storage::get_page_content(self, kb_id, path).await
+    }
+
+    async fn list_pages_by_kb(&self, kb_id: &str) -> Result<Vec<PageMeta>> {
+        Storage::list_pages_by_kb(self, kb_id).await
+    }
+
+    async fn delete_page(&self, page_id: &str) -> Result<()> {
+        Storage::delete_page(self, page_id).await
+    }
+
+    async fn insert_link(
+        &self,
+        from_page_id: &str,
+        to_url: &str,
+        kind: Option<&str>,
+    ) -> Result<()> {
+        Storage::insert_link(self, from_page_id, to_url, kind).await
+    }
+
+    async fn insert_links_batch(
+        &self,
+        from_page_id: &str,
+        links: &[(String, Option<String>)],
+    ) -> Result<()> {
+        Storage::insert_links_batch(self, from_page_id, links).await
+    }
+
+    async fn list_links_by_kb(&self, kb_id: &str) -> Result<Vec<(String, String)>> {
+        Storage::list_links_by_kb(self, kb_id).await
+    }
+
+    async fn insert_crawl_job(&self, kb_id: &str) -> Result<String> {
+        Storage::insert_crawl_job(self, kb_id).await
+    }
+
+    async fn update_crawl_job(&self, job_id: &str, stats_json: &str) -> Result<()> {
+        Storage::update_crawl_job(self, job_id, stats_json).await
+    }
+
+    async fn get_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+    ) -> Result<Option<String>> {
+        Storage::get_enrichment_cache(self, kb_id, artifact_type, prompt_hash, model_id).await
+    }
+
+    async fn set_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+        result_json: &str,
+    ) -> Result<()> {
+        Storage::set_enrichment_cache(self, kb_id, artifact_type, prompt_hash, model_id, result_json)
+            .await
+    }
+
+    async fn replace_chunks(
+        &self,
+        kb_id: &str,
+        parent_path: &str,
+        chunks: &[Chunk],
+    ) -> Result<()> {
+        Storage::replace_chunks(self, kb_id, parent_path, chunks).await
+    }
+
+    async fn list_chunks_by_page(&self, kb_id: &str, parent_path: &str) -> Result<Vec<Chunk>> {
+        Storage::list_chunks_by_page(self, kb_id, parent_path).await
+    }
+
+    async fn set_chunk_summary(&self, chunk_id: &str, summary: &str) -> Result<()> {
+        Storage::set_chunk_summary(self, chunk_id, summary).await
+    }
+
+    async fn delete_chunks_for_page(&self, kb_id: &str, parent_path: &str) -> Result<()> {
+        Storage::delete_chunks_for_page(self, kb_id, parent_path).await
+    }
+}
@@ -3,22 +3,33 @@
 //! The [`Storage`] struct wraps a libSQL database for KB metadata, page indexes,
 //! link graphs, crawl jobs, enrichment cache, and full-text search.
 //!
+//! The pipeline talks to storage through the [`StorageBackend`] trait, which
+//! [`Storage`] and the in-memory [`MemoryStorage`] both implement.
+//!
 //! **Access rules:**
 //! - Rust CLI: read-write (sole writer) via [`Storage::open`]
 //! - TypeScript MCP server: read-only via [`Storage::open_readonly`]
+//!
+//! Shared KBs can live on a remote Turso/libSQL server instead
+//! ([`Storage::open_remote`], [`Storage::open_remote_readonly`]) or in a local
+//! embedded replica of one ([`Storage::open_replica`] + [`Storage::sync`]).
 
+mod backend;
+mod memory;
 mod migrations;
 
 use std::path::Path;
 
-use chrono::Utc;
-use contextbuilder_shared::{ContextBuilderError, PageMeta, Result};
+use chrono::{DateTime, Utc};
+use contextbuilder_shared::{Chunk, ContextBuilderError, PageMeta, Result};
 use libsql::{Connection, Database, params};
 use uuid::Uuid;
 
+pub use backend::StorageBackend;
+pub use memory::MemoryStorage;
+
 /// Primary storage handle wrapping a libSQL database.
 pub struct Storage {
-    #[allow(dead_code)]
     db: Database,
     conn: Connection,
     readonly: bool,
@@ -68,6 +79,69 @@ impl Storage {
         })
     }
 
+    /// Connect to a remote libSQL server (e.g. `libsql://<db>.turso.io`) in
+    /// read-write mode.
+    pub async fn open_remote(url: &str, auth_token: &str) -> Result<Self> {
+        let db = libsql::Builder::new_remote(url.to_string(), auth_token.to_string())
+            .build()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let storage = Self::connect(db, false)?;
+        storage.run_migrations().await?;
+        Ok(storage)
+    }
+
+    /// Connect to a remote libSQL server in read-only mode.
+    pub async fn open_remote_readonly(url: &str, auth_token: &str) -> Result<Self> {
+        let db = libsql::Builder::new_remote(url.to_string(), auth_token.to_string())
+            .build()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        Self::connect(db, true)
+    }
+
+    /// Open an embedded replica at `path` that mirrors the remote database at
+    /// `url`. Reads are served locally; writes go to the remote primary.
+    ///
+    /// The replica is synced once before migrations run; call [`Storage::sync`]
+    /// to pull later changes.
+    pub async fn open_replica(path: &Path, url: &str, auth_token: &str) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ContextBuilderError::io(parent, e))?;
+        }
+
+        let db = libsql::Builder::new_remote_replica(path, url.to_string(), auth_token.to_string())
+            .build()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let storage = Self::connect(db, false)?;
+        storage.sync().await?;
+        storage.run_migrations().await?;
+        Ok(storage)
+    }
+
+    /// Pull changes from the remote primary into an embedded replica.
+    ///
+    /// Fails for local and remote databases, which have nothing to sync.
+    pub async fn sync(&self) -> Result<()> {
+        self.db
+            .sync()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(format!("sync failed: {e}")))?;
+        Ok(())
+    }
+
+    /// Open a connection on `db`.
+    fn connect(db: Database, readonly: bool) -> Result<Self> {
+        let conn = db
+            .connect()
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        Ok(Self { db, conn, readonly })
+    }
+
     /// Run pending schema migrations.
     async fn run_migrations(&self) -> Result<()> {
         let current_version = self.get_schema_version().await;
@@ -223,19 +297,37 @@ impl Storage {
     // -----------------------------------------------------------------------
 
     /// Upsert a page (insert or update on conflict by `kb_id + path`).
+    ///
+    /// A page's stored Markdown is left as it is; see
+    /// [`upsert_page_with_content`](Self::upsert_page_with_content).
     pub async fn upsert_page(&self, page: &PageMeta) -> Result<()> {
+        self.upsert_page_row(page, None).await
+    }
+
+    /// Upsert a page along with its converted Markdown, which is indexed
+    /// for [`search`](Self::search) and served by
+    /// [`get_page_content`](Self::get_page_content).
+    pub async fn upsert_page_with_content(&self, page: &PageMeta, content_md: &str) -> Result<()> {
+        self.upsert_page_row(page, Some(content_md)).await
+    }
+
+    async fn upsert_page_row(&self, page: &PageMeta, content_md: Option<&str>) -> Result<()> {
         self.check_writable()?;
         self.conn
             .execute(
-                "INSERT INTO pages (id, kb_id, url, path, title, content_hash, fetched_at, status_code, content_len)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                "INSERT INTO pages (id, kb_id, url, path, title, content_hash, fetched_at, status_code,
+                                    content_len, depth, description, content_md)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
                  ON CONFLICT(kb_id, path) DO UPDATE SET
                    url = excluded.url,
                    title = excluded.title,
                    content_hash = excluded.content_hash,
                    fetched_at = excluded.fetched_at,
                    status_code = excluded.status_code,
-                   content_len = excluded.content_len",
+                   content_len = excluded.content_len,
+                   depth = excluded.depth,
+                   description = excluded.description,
+                   content_md = COALESCE(excluded.content_md, pages.content_md)",
                 params![
                     page.id.as_str(),
                     page.kb_id.as_str(),
@@ -246,6 +338,9 @@ impl Storage {
                     page.fetched_at.to_rfc3339(),
                     page.status_code.map(i64::from),
                     page.content_len.map(|l| l as i64),
+                    page.depth.map(i64::from),
+                    page.description.as_deref(),
+                    content_md,
                 ],
             )
             .await
@@ -258,7 +353,8 @@ impl Storage {
         let mut rows = self
             .conn
             .query(
-                "SELECT id, kb_id, url, path, title, content_hash, fetched_at, status_code, content_len
+                "SELECT id, kb_id, url, path, title, content_hash, fetched_at, status_code,
+                        content_len, depth, description
                  FROM pages WHERE kb_id = ?1 AND path = ?2",
                 params![kb_id, path],
             )
@@ -272,12 +368,31 @@ impl Storage {
         }
     }
 
+    /// Get the stored Markdown of a page, if it was stored with any.
+    pub async fn get_page_content(&self, kb_id: &str, path: &str) -> Result<Option<String>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT content_md FROM pages WHERE kb_id = ?1 AND path = ?2",
+                params![kb_id, path],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        match rows.next().await {
+            Ok(Some(row)) => Ok(row.get::<Option<String>>(0).ok().flatten()),
+            Ok(None) => Ok(None),
+            Err(e) => Err(ContextBuilderError::Storage(e.to_string())),
+        }
+    }
+
     /// List all pages for a KB.
     pub async fn list_pages_by_kb(&self, kb_id: &str) -> Result<Vec<PageMeta>> {
         let mut rows = self
             .conn
             .query(
-                "SELECT id, kb_id, url, path, title, content_hash, fetched_at, status_code, content_len
+                "SELECT id, kb_id, url, path, title, content_hash, fetched_at, status_code,
+                        content_len, depth, description
                  FROM pages WHERE kb_id = ?1 ORDER BY path",
                 params![kb_id],
             )
@@ -323,6 +438,34 @@ impl Storage {
         Ok(())
     }
 
+    /// Insert all `links` (`(to_url, kind)`) of a page in one transaction.
+    pub async fn insert_links_batch(
+        &self,
+        from_page_id: &str,
+        links: &[(String, Option<String>)],
+    ) -> Result<()> {
+        self.check_writable()?;
+        if links.is_empty() {
+            return Ok(());
+        }
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        for (to_url, kind) in links {
+            tx.execute(
+                "INSERT INTO links (from_page_id, to_url, kind) VALUES (?1, ?2, ?3)",
+                params![from_page_id, to_url.as_str(), kind.as_deref()],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))
+    }
+
     /// Get links originating from a page. Returns `Vec<(to_url, kind)>`.
     pub async fn get_links_for_page(
         &self,
@@ -348,6 +491,33 @@ impl Storage {
         Ok(results)
     }
 
+    /// Get the full link graph of a KB. Returns `Vec<(from_page_path, to_url)>`.
+    pub async fn list_links_by_kb(&self, kb_id: &str) -> Result<Vec<(String, String)>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT DISTINCT p.path, l.to_url
+                 FROM links l JOIN pages p ON p.id = l.from_page_id
+                 WHERE p.kb_id = ?1
+                 ORDER BY p.path, l.to_url",
+                params![kb_id],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let mut results = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let path: String = row
+                .get(0)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            let to_url: String = row
+                .get(1)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            results.push((path, to_url));
+        }
+        Ok(results)
+    }
+
     // -----------------------------------------------------------------------
     // Crawl job operations
     // -----------------------------------------------------------------------
@@ -381,6 +551,46 @@ impl Storage {
         Ok(())
     }
 
+    /// List a KB's crawl jobs, newest first.
+    pub async fn list_crawl_jobs(&self, kb_id: &str) -> Result<Vec<CrawlJobRecord>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, started_at, finished_at, stats_json FROM crawl_jobs
+                 WHERE kb_id = ?1
+                 ORDER BY started_at DESC, id DESC",
+                params![kb_id],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let mut results = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let id: String = row
+                .get(0)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            let started_at: String = row
+                .get(1)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            let finished_at = row
+                .get::<String>(2)
+                .ok()
+                .map(|s| parse_timestamp(&s))
+                .transpose()?;
+            let stats = row
+                .get::<String>(3)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok());
+            results.push(CrawlJobRecord {
+                id,
+                started_at: parse_timestamp(&started_at)?,
+                finished_at,
+                stats,
+            });
+        }
+        Ok(results)
+    }
+
     // -----------------------------------------------------------------------
     // Enrichment cache operations
     // -----------------------------------------------------------------------
@@ -454,6 +664,242 @@ impl Storage {
         Ok(())
     }
 
+    /// Delete all enrichment cache entries of a KB produced by `model_id`.
+    ///
+    /// Returns the number of entries removed.
+    pub async fn delete_enrichment_cache_by_model(
+        &self,
+        kb_id: &str,
+        model_id: &str,
+    ) -> Result<u64> {
+        self.prune_enrichment_cache(kb_id, Some(model_id), None).await
+    }
+
+    /// Delete enrichment cache entries of a KB matching all given filters.
+    ///
+    /// `model_id` restricts deletion to one model; `older_than` to entries
+    /// created more than that long ago. With no filters, every entry of the
+    /// KB is removed. Returns the number of entries removed.
+    pub async fn prune_enrichment_cache(
+        &self,
+        kb_id: &str,
+        model_id: Option<&str>,
+        older_than: Option<std::time::Duration>,
+    ) -> Result<u64> {
+        self.check_writable()?;
+        let cutoff = older_than
+            .map(|age| {
+                chrono::Duration::from_std(age)
+                    .map(|age| (Utc::now() - age).to_rfc3339())
+                    .map_err(|e| ContextBuilderError::validation(format!("invalid cache age: {e}")))
+            })
+            .transpose()?;
+        self.conn
+            .execute(
+                "DELETE FROM enrichment_cache
+                 WHERE kb_id = ?1
+                   AND (?2 IS NULL OR model_id = ?2)
+                   AND (?3 IS NULL OR julianday(created_at) < julianday(?3))",
+                params![kb_id, model_id, cutoff.as_deref()],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))
+    }
+
+    /// Delete the enrichment cache entries of a KB with the given
+    /// `(artifact_type, prompt_hash)` keys, for every model.
+    ///
+    /// Used to drop one page's cached results so only that page is
+    /// re-enriched. Returns the number of entries removed.
+    pub async fn delete_enrichment_cache_entries(
+        &self,
+        kb_id: &str,
+        keys: &[(String, String)],
+    ) -> Result<u64> {
+        self.check_writable()?;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        let mut removed = 0;
+        for (artifact_type, prompt_hash) in keys {
+            removed += tx
+                .execute(
+                    "DELETE FROM enrichment_cache
+                     WHERE kb_id = ?1 AND artifact_type = ?2 AND prompt_hash = ?3",
+                    params![kb_id, artifact_type.as_str(), prompt_hash.as_str()],
+                )
+                .await
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        Ok(removed)
+    }
+
+    /// Copy `source`'s enrichment cache entries for `model_id` into KB
+    /// `kb_id`, so content enriched for a sibling KB is not paid for twice.
+    ///
+    /// Entries are keyed by prompt hash, so only identical prompts will hit.
+    /// Existing entries are kept. Returns the number of entries copied.
+    pub async fn copy_enrichment_cache_from(
+        &self,
+        source: &Storage,
+        kb_id: &str,
+        model_id: &str,
+    ) -> Result<u64> {
+        self.check_writable()?;
+        let mut rows = source
+            .conn
+            .query(
+                "SELECT artifact_type, prompt_hash, result_json, created_at
+                 FROM enrichment_cache WHERE model_id = ?1",
+                params![model_id],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        let mut entries = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            let column = |i| {
+                row.get::<String>(i)
+                    .map_err(|e| ContextBuilderError::Storage(e.to_string()))
+            };
+            entries.push((column(0)?, column(1)?, column(2)?, column(3)?));
+        }
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        let mut copied = 0;
+        for (artifact_type, prompt_hash, result_json, created_at) in &entries {
+            let id = Uuid::now_v7().to_string();
+            copied += tx
+                .execute(
+                    "INSERT INTO enrichment_cache
+                       (id, kb_id, artifact_type, prompt_hash, model_id, result_json, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(kb_id, artifact_type, prompt_hash, model_id) DO NOTHING",
+                    params![
+                        id.as_str(),
+                        kb_id,
+                        artifact_type.as_str(),
+                        prompt_hash.as_str(),
+                        model_id,
+                        result_json.as_str(),
+                        created_at.as_str(),
+                    ],
+                )
+                .await
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        Ok(copied)
+    }
+
+    // -----------------------------------------------------------------------
+    // Chunk operations
+    // -----------------------------------------------------------------------
+
+    /// Replace all chunks of a page with `chunks`, in one transaction.
+    pub async fn replace_chunks(
+        &self,
+        kb_id: &str,
+        parent_path: &str,
+        chunks: &[Chunk],
+    ) -> Result<()> {
+        self.check_writable()?;
+        let tx = self
+            .conn
+            .transaction()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        tx.execute(
+            "DELETE FROM chunks WHERE kb_id = ?1 AND parent_path = ?2",
+            params![kb_id, parent_path],
+        )
+        .await
+        .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        for chunk in chunks {
+            tx.execute(
+                "INSERT INTO chunks (id, kb_id, parent_path, anchor, title, ordinal, content, summary)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    chunk.id.as_str(),
+                    kb_id,
+                    parent_path,
+                    chunk.anchor.as_deref(),
+                    chunk.title.as_deref(),
+                    chunk.ordinal,
+                    chunk.content.as_str(),
+                    chunk.summary.as_deref(),
+                ],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))
+    }
+
+    /// List a page's chunks in document order.
+    pub async fn list_chunks_by_page(&self, kb_id: &str, parent_path: &str) -> Result<Vec<Chunk>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, kb_id, parent_path, anchor, title, ordinal, content, summary
+                 FROM chunks WHERE kb_id = ?1 AND parent_path = ?2 ORDER BY ordinal",
+                params![kb_id, parent_path],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let mut results = Vec::new();
+        while let Ok(Some(row)) = rows.next().await {
+            results.push(row_to_chunk(&row)?);
+        }
+        Ok(results)
+    }
+
+    /// Store the enrichment summary for a chunk.
+    pub async fn set_chunk_summary(&self, chunk_id: &str, summary: &str) -> Result<()> {
+        self.check_writable()?;
+        self.conn
+            .execute(
+                "UPDATE chunks SET summary = ?1 WHERE id = ?2",
+                params![summary, chunk_id],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Delete all chunks belonging to a page.
+    pub async fn delete_chunks_for_page(&self, kb_id: &str, parent_path: &str) -> Result<()> {
+        self.check_writable()?;
+        self.conn
+            .execute(
+                "DELETE FROM chunks WHERE kb_id = ?1 AND parent_path = ?2",
+                params![kb_id, parent_path],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
     // -----------------------------------------------------------------------
     // FTS search
     // -----------------------------------------------------------------------
@@ -494,6 +940,80 @@ impl Storage {
         }
         Ok(results)
     }
+
+    /// One page of [`search`](Self::search) results, resuming after
+    /// `after` (the previous page's `next_cursor`).
+    ///
+    /// Results are ordered by rank with the row id as a tiebreak, and the
+    /// cursor records both, so consecutive pages never overlap or skip
+    /// results even when many pages share a rank.
+    pub async fn search_paged(
+        &self,
+        kb_id: &str,
+        query: &str,
+        limit: u32,
+        after: Option<SearchCursor>,
+    ) -> Result<SearchPage> {
+        let (after_rank, after_rowid) = match after {
+            Some(cursor) => (Some(cursor.rank), cursor.rowid),
+            None => (None, 0),
+        };
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT p.path, p.title, rank, p.rowid
+                 FROM pages_fts fts
+                 JOIN pages p ON p.rowid = fts.rowid
+                 WHERE pages_fts MATCH ?1 AND p.kb_id = ?2
+                   AND (?3 IS NULL OR rank > ?3 OR (rank = ?3 AND p.rowid > ?4))
+                 ORDER BY rank, p.rowid
+                 LIMIT ?5",
+                params![query, kb_id, after_rank, after_rowid, i64::from(limit) + 1],
+            )
+            .await
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+
+        let mut results = Vec::new();
+        let mut last = None;
+        let mut more = false;
+        while let Ok(Some(row)) = rows.next().await {
+            if results.len() == limit as usize {
+                more = true;
+                break;
+            }
+            let path: String = row
+                .get(0)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            let title: Option<String> = row.get(1).ok();
+            let score: f64 = row.get(2).unwrap_or(0.0);
+            let rowid: i64 = row
+                .get(3)
+                .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
+            last = Some(SearchCursor { rank: score, rowid });
+            results.push(SearchResult {
+                path,
+                title,
+                score,
+            });
+        }
+        Ok(SearchPage {
+            results,
+            next_cursor: if more { last } else { None },
+        })
+    }
+}
+
+/// A crawl job from a KB's crawl history.
+#[derive(Debug, Clone)]
+pub struct CrawlJobRecord {
+    /// Job ID.
+    pub id: String,
+    /// When the crawl started.
+    pub started_at: DateTime<Utc>,
+    /// When the crawl finished (`None` if it never completed).
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Crawl statistics (`status`, `pages_fetched`, `pages_skipped`, `errors`).
+    pub stats: Option<serde_json::Value>,
 }
 
 /// A search result from FTS5.
@@ -507,6 +1027,56 @@ pub struct SearchResult {
     pub score: f64,
 }
 
+/// One page of [`Storage::search_paged`] results.
+#[derive(Debug, Clone)]
+pub struct SearchPage {
+    /// Results, best first.
+    pub results: Vec<SearchResult>,
+    /// Where the next page starts (`None` on the last page).
+    pub next_cursor: Option<SearchCursor>,
+}
+
+/// Position after a search result: its rank and row id.
+///
+/// Round-trips through an opaque token ([`SearchCursor::encode`] /
+/// [`SearchCursor::decode`]) for clients that page across requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchCursor {
+    /// FTS5 rank of the last result returned.
+    pub rank: f64,
+    /// Row id of the last result returned.
+    pub rowid: i64,
+}
+
+impl SearchCursor {
+    /// Encode as a 32-digit hex token (the rank's bits, then the row id).
+    pub fn encode(&self) -> String {
+        format!("{:016x}{:016x}", self.rank.to_bits(), self.rowid as u64)
+    }
+
+    /// Decode a token produced by [`encode`](Self::encode).
+    pub fn decode(token: &str) -> Result<Self> {
+        let invalid =
+            || ContextBuilderError::validation(format!("invalid search cursor '{token}'"));
+        if token.len() != 32 || !token.is_ascii() {
+            return Err(invalid());
+        }
+        let rank = u64::from_str_radix(&token[..16], 16).map_err(|_| invalid())?;
+        let rowid = u64::from_str_radix(&token[16..], 16).map_err(|_| invalid())?;
+        Ok(Self {
+            rank: f64::from_bits(rank),
+            rowid: rowid as i64,
+        })
+    }
+}
+
+/// Parse an RFC 3339 timestamp column.
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| ContextBuilderError::Storage(format!("invalid date: {e}")))
+}
+
 /// Convert a database row to a [`PageMeta`].
 fn row_to_page_meta(row: &libsql::Row) -> Result<PageMeta> {
     Ok(PageMeta {
@@ -530,12 +1100,36 @@ fn row_to_page_meta(row: &libsql::Row) -> Result<PageMeta> {
             let s: String = row
                 .get(6)
                 .map_err(|e| ContextBuilderError::Storage(e.to_string()))?;
-            chrono::DateTime::parse_from_rfc3339(&s)
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .map_err(|e| ContextBuilderError::Storage(format!("invalid date: {e}")))?
+            parse_timestamp(&s)?
         },
         status_code: row.get::<i64>(7).ok().map(|v| v as u16),
         content_len: row.get::<i64>(8).ok().map(|v| v as usize),
+        depth: row.get::<i64>(9).ok().map(|v| v as u32),
+        description: row.get::<String>(10).ok(),
+    })
+}
+
+/// Convert a database row to a [`Chunk`].
+fn row_to_chunk(row: &libsql::Row) -> Result<Chunk> {
+    Ok(Chunk {
+        id: row
+            .get::<String>(0)
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?,
+        kb_id: row
+            .get::<String>(1)
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?,
+        parent_path: row
+            .get::<String>(2)
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?,
+        anchor: row.get::<String>(3).ok(),
+        title: row.get::<String>(4).ok(),
+        ordinal: row
+            .get::<u32>(5)
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?,
+        content: row
+            .get::<String>(6)
+            .map_err(|e| ContextBuilderError::Storage(e.to_string()))?,
+        summary: row.get::<String>(7).ok(),
     })
 }
 
@@ -551,11 +1145,80 @@ mod tests {
         Storage::open(&tmp).await.expect("open test db")
     }
 
+    /// Hrana pipeline response acknowledging a `[batch, get_autocommit, close]`
+    /// request, which is how remote `execute_batch` calls are sent.
+    fn hrana_batch_ok() -> serde_json::Value {
+        serde_json::json!({
+            "baton": null,
+            "base_url": null,
+            "results": [
+                {"type": "ok", "response": {"type": "batch", "result": {"step_results": [], "step_errors": []}}},
+                {"type": "ok", "response": {"type": "get_autocommit", "is_autocommit": true}},
+                {"type": "ok", "response": {"type": "close"}}
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn open_remote_runs_migrations_over_http() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/v3/pipeline"))
+            .and(wiremock::matchers::header("authorization", "Bearer secret-token"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(hrana_batch_ok()))
+            .mount(&server)
+            .await;
+
+        let storage = Storage::open_remote(&server.uri(), "secret-token")
+            .await
+            .expect("open remote db");
+        assert!(!storage.readonly);
+
+        let requests = server.received_requests().await.unwrap();
+        let bodies: Vec<String> = requests
+            .iter()
+            .map(|r| String::from_utf8_lossy(&r.body).into_owned())
+            .collect();
+        assert!(
+            bodies.iter().any(|b| b.contains("CREATE TABLE") && b.contains("schema_migrations")),
+            "migrations were not sent to the remote: {bodies:?}"
+        );
+        // Only embedded replicas can sync
+        assert!(storage.sync().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn open_remote_readonly_skips_migrations() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(hrana_batch_ok()))
+            .mount(&server)
+            .await;
+
+        let storage = Storage::open_remote_readonly(&server.uri(), "token")
+            .await
+            .expect("open remote db");
+        assert!(server.received_requests().await.unwrap().is_empty());
+        assert!(
+            storage
+                .insert_kb("kb", "Example", "https://example.com", None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn sync_requires_a_replica() {
+        let storage = test_storage().await;
+        let err = storage.sync().await.unwrap_err();
+        assert!(err.to_string().contains("sync failed"));
+    }
+
     #[tokio::test]
     async fn open_and_migrate() {
         let storage = test_storage().await;
         let version = storage.get_schema_version().await;
-        assert_eq!(version, 1);
+        assert_eq!(version, 5);
     }
 
     #[tokio::test]
@@ -564,7 +1227,7 @@ mod tests {
         let _s1 = Storage::open(&tmp).await.expect("first open");
         drop(_s1);
         let s2 = Storage::open(&tmp).await.expect("second open");
-        assert_eq!(s2.get_schema_version().await, 1);
+        assert_eq!(s2.get_schema_version().await, 5);
     }
 
     #[tokio::test]
@@ -609,6 +1272,8 @@ mod tests {
             fetched_at: Utc::now(),
             status_code: Some(200),
             content_len: Some(1024),
+            depth: Some(2),
+            description: Some("Getting started with the API.".into()),
         };
 
         storage.upsert_page(&page).await.expect("upsert page");
@@ -618,6 +1283,8 @@ mod tests {
         let found = found.unwrap();
         assert_eq!(found.title.as_deref(), Some("Introduction"));
         assert_eq!(found.content_hash, "abc123");
+        assert_eq!(found.depth, Some(2));
+        assert_eq!(found.description.as_deref(), Some("Getting started with the API."));
 
         // Upsert (update) with new hash
         let updated = PageMeta {
@@ -655,6 +1322,8 @@ mod tests {
             fetched_at: Utc::now(),
             status_code: None,
             content_len: None,
+            depth: None,
+            description: None,
         };
         storage.upsert_page(&page).await.unwrap();
 
@@ -671,6 +1340,88 @@ mod tests {
         assert_eq!(links[0].0, "https://example.com/b");
     }
 
+    #[tokio::test]
+    async fn list_links_by_kb_returns_distinct_links_of_that_kb() {
+        let storage = test_storage().await;
+        let page = |kb_id: &str, path: &str| PageMeta {
+            id: Uuid::now_v7().to_string(),
+            kb_id: kb_id.into(),
+            url: format!("https://example.com/{path}"),
+            path: path.into(),
+            title: None,
+            content_hash: "hash".into(),
+            fetched_at: Utc::now(),
+            status_code: None,
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        for kb_id in ["kb1", "kb2"] {
+            storage.insert_kb(kb_id, kb_id, "https://example.com", None).await.unwrap();
+        }
+        let mut page_ids = Vec::new();
+        for (kb_id, path) in [("kb1", "b"), ("kb1", "a"), ("kb2", "a")] {
+            let page = page(kb_id, path);
+            storage.upsert_page(&page).await.unwrap();
+            page_ids.push(page.id);
+        }
+
+        let links = [
+            (&page_ids[0], "https://example.com/a"),
+            (&page_ids[1], "https://example.com/b"),
+            (&page_ids[1], "https://example.com/b"),
+            (&page_ids[2], "https://example.com/c"),
+        ];
+        for (page_id, to_url) in links {
+            storage.insert_link(page_id, to_url, None).await.unwrap();
+        }
+
+        let graph = storage.list_links_by_kb("kb1").await.expect("list links");
+        let expected = [("a", "https://example.com/b"), ("b", "https://example.com/a")];
+        let expected: Vec<(String, String)> =
+            expected.iter().map(|(p, u)| (p.to_string(), u.to_string())).collect();
+        assert_eq!(graph, expected);
+    }
+
+    #[tokio::test]
+    async fn insert_links_batch_stores_every_link() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+        let page = PageMeta {
+            id: Uuid::now_v7().to_string(),
+            kb_id,
+            url: "https://example.com/a".into(),
+            path: "a".into(),
+            title: None,
+            content_hash: "hash".into(),
+            fetched_at: Utc::now(),
+            status_code: None,
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        storage.upsert_page(&page).await.unwrap();
+
+        let links: Vec<(String, Option<String>)> = (0..50)
+            .map(|i| (format!("https://example.com/p{i}"), (i % 2 == 0).then(|| "internal".into())))
+            .collect();
+        storage
+            .insert_links_batch(&page.id, &links)
+            .await
+            .expect("insert batch");
+        storage.insert_links_batch(&page.id, &[]).await.expect("empty batch");
+
+        let mut stored = storage.get_links_for_page(&page.id).await.expect("get links");
+        stored.sort();
+        let mut expected = links.clone();
+        expected.sort();
+        assert_eq!(stored, expected);
+    }
+
     #[tokio::test]
     async fn crawl_job_lifecycle() {
         let storage = test_storage().await;
@@ -692,6 +1443,36 @@ mod tests {
             .expect("update crawl job");
     }
 
+    #[tokio::test]
+    async fn crawl_jobs_are_listed_newest_first() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+
+        let first = storage.insert_crawl_job(&kb_id).await.unwrap();
+        storage
+            .update_crawl_job(&first, r#"{"status": "completed", "pages_fetched": 10}"#)
+            .await
+            .unwrap();
+        let second = storage.insert_crawl_job(&kb_id).await.unwrap();
+
+        let jobs = storage.list_crawl_jobs(&kb_id).await.expect("list crawl jobs");
+        assert_eq!(
+            jobs.iter().map(|j| j.id.as_str()).collect::<Vec<_>>(),
+            [second.as_str(), first.as_str()]
+        );
+        assert!(jobs[0].finished_at.is_none());
+        assert!(jobs[0].stats.is_none());
+        assert!(jobs[1].finished_at.unwrap() >= jobs[1].started_at);
+        assert_eq!(jobs[1].stats.as_ref().unwrap()["pages_fetched"], 10);
+
+        let other = storage.list_crawl_jobs("no-such-kb").await.unwrap();
+        assert!(other.is_empty());
+    }
+
     #[tokio::test]
     async fn enrichment_cache() {
         let storage = test_storage().await;
@@ -734,6 +1515,144 @@ mod tests {
         assert!(cached.is_none());
     }
 
+    /// Seed cache entries `(artifact_type, model_id, age_days)` for pruning tests.
+    async fn seed_cache(storage: &Storage, kb_id: &str, entries: &[(&str, &str, i64)]) {
+        for (artifact, model, age_days) in entries {
+            storage
+                .set_enrichment_cache(kb_id, artifact, "hash", model, "{}")
+                .await
+                .unwrap();
+            let created = (Utc::now() - chrono::Duration::days(*age_days)).to_rfc3339();
+            storage
+                .conn
+                .execute(
+                    "UPDATE enrichment_cache SET created_at = ?1
+                     WHERE kb_id = ?2 AND artifact_type = ?3 AND model_id = ?4",
+                    params![created.as_str(), kb_id, *artifact, *model],
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn cached(storage: &Storage, kb_id: &str, artifact: &str, model: &str) -> bool {
+        storage
+            .get_enrichment_cache(kb_id, artifact, "hash", model)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn enrichment_cache_copies_from_sibling_kb() {
+        let source = test_storage().await;
+        source.insert_kb("old", "old", "https://example.com/v1", None).await.unwrap();
+        for (hash, model) in [("h1", "m"), ("h2", "m"), ("h3", "other")] {
+            source
+                .set_enrichment_cache("old", "summarize_page", hash, model, hash)
+                .await
+                .unwrap();
+        }
+
+        let storage = test_storage().await;
+        storage.insert_kb("new", "new", "https://example.com/v2", None).await.unwrap();
+        storage
+            .set_enrichment_cache("new", "summarize_page", "h2", "m", "fresh")
+            .await
+            .unwrap();
+
+        let copied = storage.copy_enrichment_cache_from(&source, "new", "m").await.unwrap();
+        assert_eq!(copied, 1);
+        let get = |hash, model| storage.get_enrichment_cache("new", "summarize_page", hash, model);
+        assert_eq!(get("h1", "m").await.unwrap().as_deref(), Some("h1"));
+        assert_eq!(get("h2", "m").await.unwrap().as_deref(), Some("fresh"));
+        assert_eq!(get("h3", "other").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn enrichment_cache_deletes_only_given_entries() {
+        let storage = test_storage().await;
+        storage.insert_kb("kb", "kb", "https://example.com", None).await.unwrap();
+        for (task, hash, model) in [
+            ("summarize_page", "a", "m"),
+            ("summarize_page", "a", "other"),
+            ("generate_description", "a", "m"),
+            ("summarize_page", "b", "m"),
+        ] {
+            storage.set_enrichment_cache("kb", task, hash, model, "r").await.unwrap();
+        }
+
+        let keys = [("summarize_page".to_string(), "a".to_string())];
+        let removed = storage.delete_enrichment_cache_entries("kb", &keys).await.unwrap();
+        assert_eq!(removed, 2);
+        let get = |task, hash| storage.get_enrichment_cache("kb", task, hash, "m");
+        assert_eq!(get("summarize_page", "a").await.unwrap(), None);
+        assert!(get("generate_description", "a").await.unwrap().is_some());
+        assert!(get("summarize_page", "b").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn enrichment_cache_delete_by_model() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        let other_kb = Uuid::now_v7().to_string();
+        for id in [&kb_id, &other_kb] {
+            storage.insert_kb(id, "kb", "https://example.com", None).await.unwrap();
+        }
+        seed_cache(
+            &storage,
+            &kb_id,
+            &[("skill", "old-model", 0), ("rules", "old-model", 0), ("skill", "new-model", 0)],
+        )
+        .await;
+        seed_cache(&storage, &other_kb, &[("skill", "old-model", 0)]).await;
+
+        let removed = storage
+            .delete_enrichment_cache_by_model(&kb_id, "old-model")
+            .await
+            .expect("delete by model");
+
+        assert_eq!(removed, 2);
+        assert!(!cached(&storage, &kb_id, "skill", "old-model").await);
+        assert!(!cached(&storage, &kb_id, "rules", "old-model").await);
+        assert!(cached(&storage, &kb_id, "skill", "new-model").await);
+        assert!(cached(&storage, &other_kb, "skill", "old-model").await);
+    }
+
+    #[tokio::test]
+    async fn enrichment_cache_prune_by_age_and_model() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage.insert_kb(&kb_id, "kb", "https://example.com", None).await.unwrap();
+        seed_cache(
+            &storage,
+            &kb_id,
+            &[("skill", "a", 40), ("rules", "a", 1), ("style", "b", 40), ("do_dont", "b", 1)],
+        )
+        .await;
+
+        let thirty_days = std::time::Duration::from_secs(30 * 24 * 60 * 60);
+
+        // Age and model combined: only old entries of model "b"
+        let removed = storage
+            .prune_enrichment_cache(&kb_id, Some("b"), Some(thirty_days))
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(!cached(&storage, &kb_id, "style", "b").await);
+        assert!(cached(&storage, &kb_id, "skill", "a").await);
+
+        // Age only: remaining old entries of any model
+        let removed = storage
+            .prune_enrichment_cache(&kb_id, None, Some(thirty_days))
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(!cached(&storage, &kb_id, "skill", "a").await);
+        assert!(cached(&storage, &kb_id, "rules", "a").await);
+        assert!(cached(&storage, &kb_id, "do_dont", "b").await);
+    }
+
     #[tokio::test]
     async fn fts_search() {
         let storage = test_storage().await;
@@ -758,6 +1677,8 @@ mod tests {
                 fetched_at: Utc::now(),
                 status_code: Some(200),
                 content_len: None,
+                depth: None,
+                description: None,
             };
             storage.upsert_page(&page).await.unwrap();
         }
@@ -770,6 +1691,205 @@ mod tests {
         assert_eq!(results[0].path, "installation");
     }
 
+    #[tokio::test]
+    async fn page_content_is_stored_and_searchable() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+
+        let page = PageMeta {
+            id: Uuid::now_v7().to_string(),
+            kb_id: kb_id.clone(),
+            url: "https://example.com/setup".into(),
+            path: "setup".into(),
+            title: Some("Setup".into()),
+            content_hash: "hash".into(),
+            fetched_at: Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        };
+        storage.upsert_page(&page).await.unwrap();
+        assert_eq!(storage.get_page_content(&kb_id, "setup").await.unwrap(), None);
+        assert!(storage.search(&kb_id, "kubernetes", 10).await.unwrap().is_empty());
+
+        let md = "# Setup\n\nDeploy the operator to your Kubernetes cluster.\n";
+        storage.upsert_page_with_content(&page, md).await.unwrap();
+        assert_eq!(storage.get_page_content(&kb_id, "setup").await.unwrap().as_deref(), Some(md));
+        assert_eq!(storage.get_page_content(&kb_id, "missing").await.unwrap(), None);
+
+        // Matches the body, not only the title and path
+        let results = storage.search(&kb_id, "kubernetes", 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "setup");
+
+        // A metadata-only upsert keeps the stored content
+        storage.upsert_page(&page).await.unwrap();
+        assert_eq!(storage.get_page_content(&kb_id, "setup").await.unwrap().as_deref(), Some(md));
+        assert_eq!(storage.search(&kb_id, "kubernetes", 10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_paged_pages_through_tied_ranks() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+
+        // Identical titles, so every match shares one rank
+        for i in 0..7 {
+            let page = PageMeta {
+                id: Uuid::now_v7().to_string(),
+                kb_id: kb_id.clone(),
+                url: format!("https://example.com/guide-{i}"),
+                path: format!("guide-{i}"),
+                title: Some("Deployment Guide".into()),
+                content_hash: "hash".into(),
+                fetched_at: Utc::now(),
+                status_code: Some(200),
+                content_len: None,
+                depth: None,
+                description: None,
+            };
+            storage.upsert_page(&page).await.unwrap();
+        }
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = storage.search_paged(&kb_id, "deployment", 3, cursor).await.unwrap();
+            pages += 1;
+            assert!(page.results.len() <= 3);
+            seen.extend(page.results.into_iter().map(|r| r.path));
+            // Clients carry the cursor as an opaque token
+            match page.next_cursor {
+                Some(next) => cursor = Some(SearchCursor::decode(&next.encode()).unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+
+        let mut all: Vec<String> = storage
+            .search(&kb_id, "deployment", 100)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.path)
+            .collect();
+        let mut paged = seen.clone();
+        paged.sort();
+        paged.dedup();
+        all.sort();
+        assert_eq!(paged.len(), seen.len(), "pages overlap: {seen:?}");
+        assert_eq!(paged, all);
+
+        assert!(SearchCursor::decode("not-a-cursor").is_err());
+    }
+
+    #[tokio::test]
+    async fn chunk_replace_list_and_index() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+
+        let chunk = |ordinal: u32, anchor: &str, content: &str| Chunk {
+            id: Uuid::now_v7().to_string(),
+            kb_id: kb_id.clone(),
+            parent_path: "guide".into(),
+            anchor: Some(anchor.into()),
+            title: Some(anchor.into()),
+            ordinal,
+            content: content.into(),
+            summary: None,
+        };
+
+        storage
+            .replace_chunks(&kb_id, "guide", &[chunk(0, "old", "stale text")])
+            .await
+            .expect("first replace");
+        storage
+            .replace_chunks(
+                &kb_id,
+                "guide",
+                &[
+                    chunk(0, "install", "## Install\n\nRun the installer"),
+                    chunk(1, "configure", "## Configure\n\nEdit the toml file"),
+                ],
+            )
+            .await
+            .expect("second replace");
+
+        let chunks = storage.list_chunks_by_page(&kb_id, "guide").await.unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].anchor.as_deref(), Some("configure"));
+
+        storage
+            .set_chunk_summary(&chunks[0].id, "How to install")
+            .await
+            .unwrap();
+        let chunks = storage.list_chunks_by_page(&kb_id, "guide").await.unwrap();
+        assert_eq!(chunks[0].summary.as_deref(), Some("How to install"));
+
+        // The section index follows the replacement
+        async fn indexed(storage: &Storage, term: &str) -> Vec<String> {
+            let mut rows = storage
+                .conn
+                .query(
+                    "SELECT c.anchor FROM chunks_fts fts JOIN chunks c ON c.rowid = fts.rowid
+                     WHERE chunks_fts MATCH ?1",
+                    params![term],
+                )
+                .await
+                .unwrap();
+            let mut anchors = Vec::new();
+            while let Ok(Some(row)) = rows.next().await {
+                anchors.push(row.get::<String>(0).unwrap());
+            }
+            anchors
+        }
+        assert_eq!(indexed(&storage, "toml").await, ["configure"]);
+        assert!(indexed(&storage, "stale").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_chunk_replacement_keeps_the_old_chunks() {
+        let storage = test_storage().await;
+        let kb_id = Uuid::now_v7().to_string();
+        storage
+            .insert_kb(&kb_id, "test-kb", "https://example.com", None)
+            .await
+            .unwrap();
+        let chunk = |id: &str, ordinal: u32| Chunk {
+            id: id.into(),
+            kb_id: kb_id.clone(),
+            parent_path: "guide".into(),
+            anchor: None,
+            title: None,
+            ordinal,
+            content: format!("section {ordinal}"),
+            summary: None,
+        };
+        storage.replace_chunks(&kb_id, "guide", &[chunk("c0", 0)]).await.unwrap();
+
+        // A duplicate id fails the second insert, after the delete ran
+        let duplicate = [chunk("c1", 0), chunk("c1", 1)];
+        assert!(storage.replace_chunks(&kb_id, "guide", &duplicate).await.is_err());
+
+        let chunks = storage.list_chunks_by_page(&kb_id, "guide").await.unwrap();
+        let ids: Vec<&str> = chunks.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["c0"]);
+    }
+
     #[tokio::test]
     async fn readonly_rejects_writes() {
         let tmp = std::env::temp_dir().join(format!("cb_test_{}.db", Uuid::now_v7()));
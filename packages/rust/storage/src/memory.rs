@@ -0,0 +1,426 @@
+//! In-memory [`StorageBackend`] implementation.
+//!
+//! Mirrors the libSQL schema closely enough for the pipeline to run against
+//! it unchanged. Nothing is persisted; used by tests and dry runs.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use contextbuilder_shared::{Chunk, ContextBuilderError, PageMeta, Result};
+use uuid::Uuid;
+
+use crate::backend::StorageBackend;
+
+/// A KB record: `(name, source_url, config_json)`.
+type KbRecord = (String, String, Option<String>);
+
+/// Enrichment cache key: `(kb_id, artifact_type, prompt_hash, model_id)`.
+type CacheKey = (String, String, String, String);
+
+#[derive(Debug, Default)]
+struct Tables {
+    kbs: HashMap<String, KbRecord>,
+    /// Pages keyed by `(kb_id, path)` so listings come out ordered by path.
+    pages: BTreeMap<(String, String), PageMeta>,
+    /// Page Markdown, keyed like `pages`.
+    contents: HashMap<(String, String), String>,
+    /// Links as `(from_page_id, to_url, kind)`.
+    links: Vec<(String, String, Option<String>)>,
+    /// Crawl jobs: job ID → `(kb_id, stats_json)`.
+    crawl_jobs: HashMap<String, (String, Option<String>)>,
+    enrichment_cache: HashMap<CacheKey, String>,
+    chunks: Vec<Chunk>,
+}
+
+/// Process-local storage backend.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryStorage {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stats JSON recorded for a crawl job, if it has finished.
+    pub fn crawl_job_stats(&self, job_id: &str) -> Option<String> {
+        self.lock()
+            .ok()?
+            .crawl_jobs
+            .get(job_id)
+            .and_then(|(_, stats)| stats.clone())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Tables>> {
+        self.tables
+            .lock()
+            .map_err(|_| ContextBuilderError::Storage("memory storage lock poisoned".into()))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    async fn insert_kb(
+        &self,
+        id: &str,
+        name: &str,
+        source_url: &str,
+        config_json: Option<&str>,
+    ) -> Result<()> {
+        let mut t = self.lock()?;
+        if t.kbs.contains_key(id) {
+            return Err(ContextBuilderError::Storage(format!(
+                "UNIQUE constraint failed: kb.id ({id})"
+            )));
+        }
+        t.kbs.insert(
+            id.to_string(),
+            (
+                name.to_string(),
+                source_url.to_string(),
+                config_json.map(String::from),
+            ),
+        );
+        Ok(())
+    }
+
+    async fn upsert_page(&self, page: &PageMeta) -> Result<()> {
+        let mut t = self.lock()?;
+        let key = (page.kb_id.clone(), page.path.clone());
+        match t.pages.get_mut(&key) {
+            // Same as ON CONFLICT DO UPDATE: the original ID is kept.
+            Some(existing) => {
+                *existing = PageMeta {
+                    id: existing.id.clone(),
+                    ..page.clone()
+                };
+            }
+            None => {
+                t.pages.insert(key, page.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn upsert_page_with_content(&self, page: &PageMeta, content_md: &str) -> Result<()> {
+        self.upsert_page(page).await?;
+        let key = (page.kb_id.clone(), page.path.clone());
+        self.lock()?.contents.insert(key, content_md.to_string());
+        Ok(())
+    }
+
+    async fn get_page(&self, kb_id: &str, path: &str) -> Result<Option<PageMeta>> {
+        let t = self.lock()?;
+        Ok(t.pages.get(&(kb_id.to_string(), path.to_string())).cloned())
+    }
+
+    async fn get_page_content(&self, kb_id: &str, path: &str) -> Result<Option<String>> {
+        let t = self.lock()?;
+        Ok(t.contents.get(&(kb_id.to_string(), path.to_string())).cloned())
+    }
+
+    async fn list_pages_by_kb(&self, kb_id: &str) -> Result<Vec<PageMeta>> {
+        let t = self.lock()?;
+        Ok(t.pages
+            .values()
+            .filter(|p| p.kb_id == kb_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_page(&self, page_id: &str) -> Result<()> {
+        let mut t = self.lock()?;
+        let keys: Vec<_> = t
+            .pages
+            .iter()
+            .filter(|(_, p)| p.id == page_id)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            t.pages.remove(&key);
+            t.contents.remove(&key);
+        }
+        t.links.retain(|(from, _, _)| from != page_id);
+        Ok(())
+    }
+
+    async fn insert_link(
+        &self,
+        from_page_id: &str,
+        to_url: &str,
+        kind: Option<&str>,
+    ) -> Result<()> {
+        let mut t = self.lock()?;
+        t.links.push((
+            from_page_id.to_string(),
+            to_url.to_string(),
+            kind.map(String::from),
+        ));
+        Ok(())
+    }
+
+    async fn insert_links_batch(
+        &self,
+        from_page_id: &str,
+        links: &[(String, Option<String>)],
+    ) -> Result<()> {
+        let mut t = self.lock()?;
+        t.links.extend(
+            links
+                .iter()
+                .map(|(to, kind)| (from_page_id.to_string(), to.clone(), kind.clone())),
+        );
+        Ok(())
+    }
+
+    async fn list_links_by_kb(&self, kb_id: &str) -> Result<Vec<(String, String)>> {
+        let t = self.lock()?;
+        let paths: HashMap<&str, &str> = t
+            .pages
+            .values()
+            .filter(|p| p.kb_id == kb_id)
+            .map(|p| (p.id.as_str(), p.path.as_str()))
+            .collect();
+        let links: BTreeSet<(String, String)> = t
+            .links
+            .iter()
+            .filter_map(|(from, to, _)| {
+                paths
+                    .get(from.as_str())
+                    .map(|path| (path.to_string(), to.clone()))
+            })
+            .collect();
+        Ok(links.into_iter().collect())
+    }
+
+    async fn insert_crawl_job(&self, kb_id: &str) -> Result<String> {
+        let mut t = self.lock()?;
+        let id = Uuid::now_v7().to_string();
+        t.crawl_jobs.insert(id.clone(), (kb_id.to_string(), None));
+        Ok(id)
+    }
+
+    async fn update_crawl_job(&self, job_id: &str, stats_json: &str) -> Result<()> {
+        let mut t = self.lock()?;
+        if let Some((_, stats)) = t.crawl_jobs.get_mut(job_id) {
+            *stats = Some(stats_json.to_string());
+        }
+        Ok(())
+    }
+
+    async fn get_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+    ) -> Result<Option<String>> {
+        let t = self.lock()?;
+        let key = (
+            kb_id.to_string(),
+            artifact_type.to_string(),
+            prompt_hash.to_string(),
+            model_id.to_string(),
+        );
+        Ok(t.enrichment_cache.get(&key).cloned())
+    }
+
+    async fn set_enrichment_cache(
+        &self,
+        kb_id: &str,
+        artifact_type: &str,
+        prompt_hash: &str,
+        model_id: &str,
+        result_json: &str,
+    ) -> Result<()> {
+        let mut t = self.lock()?;
+        let key = (
+            kb_id.to_string(),
+            artifact_type.to_string(),
+            prompt_hash.to_string(),
+            model_id.to_string(),
+        );
+        t.enrichment_cache.insert(key, result_json.to_string());
+        Ok(())
+    }
+
+    async fn replace_chunks(
+        &self,
+        kb_id: &str,
+        parent_path: &str,
+        chunks: &[Chunk],
+    ) -> Result<()> {
+        let mut t = self.lock()?;
+        t.chunks
+            .retain(|c| !(c.kb_id == kb_id && c.parent_path == parent_path));
+        t.chunks.extend(chunks.iter().map(|c| Chunk {
+            kb_id: kb_id.to_string(),
+            parent_path: parent_path.to_string(),
+            ..c.clone()
+        }));
+        Ok(())
+    }
+
+    async fn list_chunks_by_page(&self, kb_id: &str, parent_path: &str) -> Result<Vec<Chunk>> {
+        let t = self.lock()?;
+        let mut chunks: Vec<Chunk> = t
+            .chunks
+            .iter()
+            .filter(|c| c.kb_id == kb_id && c.parent_path == parent_path)
+            .cloned()
+            .collect();
+        chunks.sort_by_key(|c| c.ordinal);
+        Ok(chunks)
+    }
+
+    async fn set_chunk_summary(&self, chunk_id: &str, summary: &str) -> Result<()> {
+        let mut t = self.lock()?;
+        if let Some(chunk) = t.chunks.iter_mut().find(|c| c.id == chunk_id) {
+            chunk.summary = Some(summary.to_string());
+        }
+        Ok(())
+    }
+
+    async fn delete_chunks_for_page(&self, kb_id: &str, parent_path: &str) -> Result<()> {
+        let mut t = self.lock()?;
+        t.chunks
+            .retain(|c| !(c.kb_id == kb_id && c.parent_path == parent_path));
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn page(id: &str, path: &str, hash: &str) -> PageMeta {
+        PageMeta {
+            id: id.into(),
+            kb_id: "kb".into(),
+            url: format!("https://example.com/{path}"),
+            path: path.into(),
+            title: None,
+            content_hash: hash.into(),
+            fetched_at: Utc::now(),
+            status_code: Some(200),
+            content_len: None,
+            depth: None,
+            description: None,
+        }
+    }
+
+    fn chunk(id: &str, ordinal: u32) -> Chunk {
+        Chunk {
+            id: id.into(),
+            kb_id: "kb".into(),
+            parent_path: "guide".into(),
+            anchor: None,
+            title: None,
+            ordinal,
+            content: format!("chunk {ordinal}"),
+            summary: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn upsert_keeps_id_and_orders_by_path() {
+        let store = MemoryStorage::new();
+        store.upsert_page(&page("p2", "zeta", "h1")).await.unwrap();
+        store.upsert_page(&page("p1", "alpha", "h1")).await.unwrap();
+        store.upsert_page(&page("other", "zeta", "h2")).await.unwrap();
+
+        let pages = store.list_pages_by_kb("kb").await.unwrap();
+        let paths: Vec<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths, vec!["alpha", "zeta"]);
+        assert_eq!(pages[1].id, "p2");
+        assert_eq!(pages[1].content_hash, "h2");
+        assert!(store.list_pages_by_kb("other-kb").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn page_content_survives_metadata_upserts() {
+        let store = MemoryStorage::new();
+        store.upsert_page_with_content(&page("p1", "a", "h1"), "# A\n").await.unwrap();
+        store.upsert_page(&page("p1", "a", "h2")).await.unwrap();
+        assert_eq!(store.get_page_content("kb", "a").await.unwrap().as_deref(), Some("# A\n"));
+
+        store.delete_page("p1").await.unwrap();
+        assert_eq!(store.get_page_content("kb", "a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn links_are_deduplicated_and_dropped_with_page() {
+        let store = MemoryStorage::new();
+        store.upsert_page(&page("p1", "a", "h")).await.unwrap();
+        store.upsert_page(&page("p2", "b", "h")).await.unwrap();
+        for to in ["https://x/2", "https://x/1", "https://x/1"] {
+            store.insert_link("p1", to, Some("internal")).await.unwrap();
+        }
+        store.insert_link("p2", "https://x/3", None).await.unwrap();
+
+        let links = store.list_links_by_kb("kb").await.unwrap();
+        assert_eq!(
+            links,
+            vec![
+                ("a".to_string(), "https://x/1".to_string()),
+                ("a".to_string(), "https://x/2".to_string()),
+                ("b".to_string(), "https://x/3".to_string()),
+            ]
+        );
+
+        store.delete_page("p1").await.unwrap();
+        assert_eq!(store.list_links_by_kb("kb").await.unwrap().len(), 1);
+        assert!(store.get_page("kb", "a").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn chunks_and_cache_round_trip() {
+        let store = MemoryStorage::new();
+        store
+            .replace_chunks("kb", "guide", &[chunk("c1", 1), chunk("c0", 0)])
+            .await
+            .unwrap();
+        store.set_chunk_summary("c1", "second").await.unwrap();
+
+        let chunks = store.list_chunks_by_page("kb", "guide").await.unwrap();
+        assert_eq!(chunks[0].id, "c0");
+        assert_eq!(chunks[1].summary.as_deref(), Some("second"));
+
+        store.replace_chunks("kb", "guide", &[chunk("c2", 0)]).await.unwrap();
+        assert_eq!(store.list_chunks_by_page("kb", "guide").await.unwrap().len(), 1);
+
+        store
+            .set_enrichment_cache("kb", "skill", "h", "m", "v1")
+            .await
+            .unwrap();
+        store
+            .set_enrichment_cache("kb", "skill", "h", "m", "v2")
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_enrichment_cache("kb", "skill", "h", "m").await.unwrap().as_deref(),
+            Some("v2")
+        );
+        assert!(store.get_enrichment_cache("kb", "skill", "h", "other").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn crawl_jobs_record_stats() {
+        let store = MemoryStorage::new();
+        store.insert_kb("kb", "Example", "https://example.com", None).await.unwrap();
+        assert!(store.insert_kb("kb", "Dup", "https://example.com", None).await.is_err());
+
+        let job = store.insert_crawl_job("kb").await.unwrap();
+        assert!(store.crawl_job_stats(&job).is_none());
+        store.update_crawl_job(&job, r#"{"pages":1}"#).await.unwrap();
+        assert_eq!(store.crawl_job_stats(&job).as_deref(), Some(r#"{"pages":1}"#));
+    }
+}
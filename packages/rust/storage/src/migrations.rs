@@ -12,10 +12,11 @@ pub(crate) struct Migration {
 
 /// All migrations, in ascending version order.
 pub(crate) fn all_migrations() -> Vec<Migration> {
-    vec![Migration {
-        version: 1,
-        description: "Initial schema: kb, pages, links, crawl_jobs, enrichment_cache, FTS5",
-        sql: r#"
+    vec![
+        Migration {
+            version: 1,
+            description: "Initial schema: kb, pages, links, crawl_jobs, enrichment_cache, FTS5",
+            sql: r#"
 -- Schema version tracking
 CREATE TABLE IF NOT EXISTS schema_migrations (
     version   INTEGER PRIMARY KEY,
@@ -112,5 +113,116 @@ END;
 
 INSERT INTO schema_migrations (version) VALUES (1);
 "#,
-    }]
+        },
+        Migration {
+            version: 2,
+            description: "Section chunks table with FTS5",
+            sql: r#"
+-- Page sections (split at H2) for section-level enrichment and search
+CREATE TABLE IF NOT EXISTS chunks (
+    id          TEXT PRIMARY KEY,
+    kb_id       TEXT NOT NULL REFERENCES kb(id) ON DELETE CASCADE,
+    parent_path TEXT NOT NULL,
+    anchor      TEXT,
+    title       TEXT,
+    ordinal     INTEGER NOT NULL,
+    content     TEXT NOT NULL,
+    summary     TEXT,
+    UNIQUE(kb_id, parent_path, ordinal)
+);
+
+CREATE INDEX IF NOT EXISTS idx_chunks_parent ON chunks(kb_id, parent_path);
+
+-- Full-text search on chunks
+CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+    title,
+    content,
+    content=chunks,
+    content_rowid=rowid
+);
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+    INSERT INTO chunks_fts(rowid, title, content)
+    VALUES (new.rowid, new.title, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+    INSERT INTO chunks_fts(chunks_fts, rowid, title, content)
+    VALUES ('delete', old.rowid, old.title, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE ON chunks BEGIN
+    INSERT INTO chunks_fts(chunks_fts, rowid, title, content)
+    VALUES ('delete', old.rowid, old.title, old.content);
+    INSERT INTO chunks_fts(rowid, title, content)
+    VALUES (new.rowid, new.title, new.content);
+END;
+
+INSERT INTO schema_migrations (version) VALUES (2);
+"#,
+        },
+        Migration {
+            version: 3,
+            description: "Crawl depth on pages",
+            sql: r#"
+-- BFS depth at which the crawler reached each page (NULL for non-crawled pages)
+ALTER TABLE pages ADD COLUMN depth INTEGER;
+
+INSERT INTO schema_migrations (version) VALUES (3);
+"#,
+        },
+        Migration {
+            version: 4,
+            description: "Meta description on pages",
+            sql: r#"
+-- The page's <meta name="description"> / og:description, when it has one
+ALTER TABLE pages ADD COLUMN description TEXT;
+
+INSERT INTO schema_migrations (version) VALUES (4);
+"#,
+        },
+        Migration {
+            version: 5,
+            description: "Page Markdown on pages, indexed for full-text search",
+            sql: r#"
+-- The converted Markdown of each page (NULL for pages stored without it)
+ALTER TABLE pages ADD COLUMN content_md TEXT;
+
+-- Rebuild the FTS index with the page body next to the title and path
+DROP TRIGGER IF EXISTS pages_fts_insert;
+DROP TRIGGER IF EXISTS pages_fts_delete;
+DROP TRIGGER IF EXISTS pages_fts_update;
+DROP TABLE IF EXISTS pages_fts;
+
+CREATE VIRTUAL TABLE pages_fts USING fts5(
+    title,
+    path,
+    content_md,
+    content=pages,
+    content_rowid=rowid
+);
+
+CREATE TRIGGER pages_fts_insert AFTER INSERT ON pages BEGIN
+    INSERT INTO pages_fts(rowid, title, path, content_md)
+    VALUES (new.rowid, new.title, new.path, new.content_md);
+END;
+
+CREATE TRIGGER pages_fts_delete AFTER DELETE ON pages BEGIN
+    INSERT INTO pages_fts(pages_fts, rowid, title, path, content_md)
+    VALUES ('delete', old.rowid, old.title, old.path, old.content_md);
+END;
+
+CREATE TRIGGER pages_fts_update AFTER UPDATE ON pages BEGIN
+    INSERT INTO pages_fts(pages_fts, rowid, title, path, content_md)
+    VALUES ('delete', old.rowid, old.title, old.path, old.content_md);
+    INSERT INTO pages_fts(rowid, title, path, content_md)
+    VALUES (new.rowid, new.title, new.path, new.content_md);
+END;
+
+INSERT INTO pages_fts(pages_fts) VALUES ('rebuild');
+
+INSERT INTO schema_migrations (version) VALUES (5);
+"#,
+        },
+    ]
 }
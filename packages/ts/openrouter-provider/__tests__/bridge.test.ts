@@ -7,6 +7,7 @@ import {
   ResponseMessageSchema,
   EnrichmentTaskSchema,
   EnrichmentResultSchema,
+  PROTOCOL_VERSION,
   TASK_TYPES,
 } from "../src/schemas";
 import { buildPrompt, SYSTEM_PROMPT } from "../src/prompts";
@@ -91,10 +92,15 @@ describe("ResponseMessageSchema", () => {
   });
 
   test("validates ready message", () => {
-    const msg = { type: "ready" };
+    const msg = { type: "ready", protocol_version: PROTOCOL_VERSION };
     const result = ResponseMessageSchema.safeParse(msg);
     expect(result.success).toBe(true);
   });
+
+  test("rejects ready message without protocol version", () => {
+    const result = ResponseMessageSchema.safeParse({ type: "ready" });
+    expect(result.success).toBe(false);
+  });
 });
 
 describe("EnrichmentTaskSchema", () => {
@@ -168,6 +174,17 @@ describe("buildPrompt", () => {
     expect(prompt).toContain("summary");
   });
 
+  test("summarize_section includes the section title and content", () => {
+    const prompt = buildPrompt({
+      task_type: "summarize_section",
+      content: "## Install\n\nRun npm install.",
+      title: "Getting Started — Install",
+    });
+    expect(prompt).toContain("section of a documentation page");
+    expect(prompt).toContain("Getting Started — Install");
+    expect(prompt).toContain("Run npm install");
+  });
+
   test("generate_description produces short description prompt", () => {
     const prompt = buildPrompt({
       task_type: "generate_description",
@@ -176,6 +193,17 @@ describe("buildPrompt", () => {
     });
     expect(prompt).toContain("single-line description");
     expect(prompt).toContain("120 characters");
+    expect(prompt).not.toContain("Author's description");
+  });
+
+  test("generate_description seeds the prompt with the meta description", () => {
+    const prompt = buildPrompt({
+      task_type: "generate_description",
+      content: "# API Reference\n\nComplete API docs.",
+      title: "API Reference",
+      description: "Every endpoint, with examples.",
+    });
+    expect(prompt).toContain("Author's description: Every endpoint, with examples.");
   });
 
   test("generate_skill_md includes KB context", () => {
@@ -203,6 +231,19 @@ describe("buildPrompt", () => {
     expect(prompt).toContain("Test KB");
   });
 
+  test("custom task uses instructions and only the provided context", () => {
+    const prompt = buildPrompt({
+      task_type: "custom",
+      task_name: "faq",
+      instructions: "Write an FAQ for new users.",
+      kb_name: "My Docs",
+      summaries_json: '{"intro":"Introduction page"}',
+    });
+    expect(prompt).toContain("Write an FAQ for new users.");
+    expect(prompt).toContain("Introduction page");
+    expect(prompt).not.toContain("Documentation TOC");
+  });
+
   test("all task types produce non-empty prompts", () => {
     for (const taskType of TASK_TYPES) {
       const prompt = buildPrompt({
@@ -8,10 +8,11 @@
  *
  * Protocol:
  *   stdin  → JSON-lines: { type: "enrich", id, task } | { type: "shutdown" }
- *   stdout ← JSON-lines: { type: "result", id, result } | { type: "error", id, error } | { type: "ready" }
+ *   stdout ← JSON-lines: { type: "result", id, result } | { type: "error", id, error }
+ *            | { type: "ready", protocol_version }
  *   stderr ← structured log lines (JSON)
  */
-import { RequestMessageSchema } from "./schemas";
+import { PROTOCOL_VERSION, RequestMessageSchema } from "./schemas";
 import type { ResponseMessage } from "./schemas";
 import { runEnrichment } from "./llm";
 
@@ -49,7 +50,7 @@ async function main(): Promise<void> {
   logStderr("info", "bridge_starting", { model: MODEL_ID });
 
   // Signal readiness
-  send({ type: "ready" });
+  send({ type: "ready", protocol_version: PROTOCOL_VERSION });
 
   // Read stdin line by line
   const reader = Bun.stdin.stream().getReader();
@@ -16,6 +16,7 @@ import {
   DoDontOutputSchema,
   LlmsTxtOutputSchema,
   LlmsFullTxtOutputSchema,
+  CustomOutputSchema,
 } from "./schemas";
 import type { EnrichmentResult, EnrichmentTask, TaskType } from "./schemas";
 import { SYSTEM_PROMPT, buildPrompt } from "./prompts";
@@ -29,6 +30,7 @@ const RETRY_BASE_MS = 1000;
 function getOutputSchema(taskType: TaskType): z.ZodObject<z.ZodRawShape> {
   switch (taskType) {
     case "summarize_page":
+    case "summarize_section":
       return PageSummaryOutputSchema;
     case "generate_description":
       return PageDescriptionOutputSchema;
@@ -44,6 +46,8 @@ function getOutputSchema(taskType: TaskType): z.ZodObject<z.ZodRawShape> {
       return LlmsTxtOutputSchema;
     case "generate_llms_full_txt":
       return LlmsFullTxtOutputSchema;
+    case "custom":
+      return CustomOutputSchema;
   }
 }
 
@@ -51,7 +55,7 @@ function getOutputSchema(taskType: TaskType): z.ZodObject<z.ZodRawShape> {
  * Extract the text result from a structured output object.
  */
 function extractText(taskType: TaskType, obj: Record<string, unknown>): string {
-  if (taskType === "summarize_page") {
+  if (taskType === "summarize_page" || taskType === "summarize_section") {
     return (obj as { summary: string }).summary;
   }
   if (taskType === "generate_description") {
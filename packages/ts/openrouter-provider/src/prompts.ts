@@ -13,6 +13,8 @@ export function buildPrompt(task: EnrichmentTask): string {
   switch (task.task_type) {
     case "summarize_page":
       return buildSummarizePage(task);
+    case "summarize_section":
+      return buildSummarizeSection(task);
     case "generate_description":
       return buildGenerateDescription(task);
     case "generate_skill_md":
@@ -27,6 +29,8 @@ export function buildPrompt(task: EnrichmentTask): string {
       return buildGenerateLlmsTxt(task);
     case "generate_llms_full_txt":
       return buildGenerateLlmsFullTxt(task);
+    case "custom":
+      return buildCustom(task);
   }
 }
 
@@ -49,12 +53,29 @@ ${task.content ?? ""}
 Provide a JSON object with a "summary" field.`;
 }
 
+function buildSummarizeSection(task: EnrichmentTask): string {
+  return `Summarize the following section of a documentation page in 1-2 concise sentences. Focus on what the section teaches or documents, not meta-commentary.
+
+Section: ${task.title ?? "Untitled"}
+
+---
+${task.content ?? ""}
+---
+
+Provide a JSON object with a "summary" field.`;
+}
+
 function buildGenerateDescription(task: EnrichmentTask): string {
+  const authorDescription = task.description
+    ? `Author's description: ${task.description}
+(Keep it if it is accurate and specific; otherwise improve on it.)
+`
+    : "";
   return `Write a single-line description (max 120 characters) for this documentation page, suitable for an llms.txt entry. Be specific and informative.
 
 Title: ${task.title ?? "Untitled"}
 Source: ${task.source_url ?? "unknown"}
-
+${authorDescription}
 ---
 ${task.content ?? ""}
 ---
@@ -184,3 +205,20 @@ ${task.pages_json ?? "[]"}
 
 Provide a JSON object with a "content" field containing the complete llms-full.txt file.`;
 }
+
+function buildCustom(task: EnrichmentTask): string {
+  const context = [
+    task.toc_json !== undefined ? `Documentation TOC:\n${task.toc_json}` : null,
+    task.summaries_json !== undefined ? `Page summaries:\n${task.summaries_json}` : null,
+    task.pages_json !== undefined ? `Full documentation content:\n${task.pages_json}` : null,
+  ].filter((section) => section !== null);
+
+  return `${task.instructions ?? `Generate the "${task.task_name ?? "custom"}" artifact.`}
+
+KB Name: ${task.kb_name ?? "Unknown"}
+Source: ${task.kb_source_url ?? "unknown"}
+
+${context.join("\n\n")}
+
+Provide a JSON object with a "content" field containing the full Markdown content.`;
+}
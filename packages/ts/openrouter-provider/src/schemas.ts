@@ -4,6 +4,14 @@
  */
 import { z } from "zod";
 
+/**
+ * Version of the bridge protocol, sent in the `ready` message. The Rust
+ * orchestrator refuses bridges outside its supported range
+ * (`SUPPORTED_PROTOCOL_VERSIONS` in `enrichment.rs`); bump both together on
+ * incompatible changes.
+ */
+export const PROTOCOL_VERSION = 2;
+
 // ---------------------------------------------------------------------------
 // Enrichment task types
 // ---------------------------------------------------------------------------
@@ -11,6 +19,7 @@ import { z } from "zod";
 /** Supported enrichment task types. */
 export const TASK_TYPES = [
   "summarize_page",
+  "summarize_section",
   "generate_description",
   "generate_skill_md",
   "generate_rules",
@@ -18,6 +27,7 @@ export const TASK_TYPES = [
   "generate_do_dont",
   "generate_llms_txt",
   "generate_llms_full_txt",
+  "custom",
 ] as const;
 
 export type TaskType = (typeof TASK_TYPES)[number];
@@ -46,6 +56,12 @@ export const EnrichmentTaskSchema = z.object({
   kb_name: z.string().optional(),
   /** KB source URL for context. */
   kb_source_url: z.string().optional(),
+  /** Name of a user-defined task (custom tasks only). */
+  task_name: z.string().optional(),
+  /** User-supplied prompt (custom tasks only). */
+  instructions: z.string().optional(),
+  /** The page's own meta description (generate_description only). */
+  description: z.string().optional(),
 });
 
 export type EnrichmentTask = z.infer<typeof EnrichmentTaskSchema>;
@@ -98,6 +114,7 @@ export const ResponseMessageSchema = z.discriminatedUnion("type", [
   }),
   z.object({
     type: z.literal("ready"),
+    protocol_version: z.number().int().positive(),
   }),
 ]);
 
@@ -152,6 +169,11 @@ export const LlmsTxtOutputSchema = z.object({
     .describe("Complete llms.txt file content following the llmstxt.org format."),
 });
 
+/** Schema for custom task output. */
+export const CustomOutputSchema = z.object({
+  content: z.string().describe("Complete Markdown content for the requested artifact."),
+});
+
 /** Schema for llms-full.txt output. */
 export const LlmsFullTxtOutputSchema = z.object({
   content: z
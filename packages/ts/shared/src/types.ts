@@ -28,6 +28,15 @@ export const KbManifestSchema = z
     config: z.record(z.unknown()).nullish(),
     artifacts: z.record(z.unknown()).nullish(),
     enrichment: z.record(z.unknown()).nullish(),
+    crawl_scope: z
+      .object({
+        start_url: z.string().url(),
+        mode: z.enum(["auto", "llms-txt", "crawl"]),
+        depth: z.number().int().min(0),
+        include_patterns: z.array(z.string()).default([]),
+        exclude_patterns: z.array(z.string()).default([]),
+      })
+      .nullish(),
   })
   .strict();
 